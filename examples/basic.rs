@@ -2,48 +2,17 @@ extern crate specs;
 extern crate specs_static;
 
 use specs::{
-    Component, DispatcherBuilder, ReadExpect, Read, Write, Join, System, SystemData, VecStorage, World,
+    Component, DispatcherBuilder, ReadExpect, System, SystemData, VecStorage, World, WorldExt,
 };
-use specs_static::{Id, Storage, WorldExt};
+use specs_static::prelude::*;
+use specs_static::query::Read;
+use specs_static::{define_id, tile_system};
 
-pub struct Tiles {
-    width: usize,
-    height: usize,
-}
-
-impl Tiles {
-    pub fn new(width: usize, height: usize) -> Self {
-        Tiles { width, height }
-    }
-
-    pub fn id(&self, x: usize, y: usize) -> TileId {
-        TileId(y as u32 * self.width as u32 + x as u32)
-    }
-
-    pub fn iter_all(&self) -> Box<Iterator<Item = TileId>> {
-        Box::new((0..self.width as u32 * self.height as u32 - 1).map(TileId))
-    }
-}
-
-pub type TileComps<'a, C> = Read<'a, Storage<C, <C as Component>::Storage, TileId>>;
-pub type TileCompsMut<'a, C> = Write<'a, Storage<C, <C as Component>::Storage, TileId>>;
-
-#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
-pub struct TileId(u32);
-
-impl Id for TileId {
-    fn from_u32(value: u32) -> Self {
-        TileId(value)
-    }
-
-    fn id(&self) -> u32 {
-        self.0
-    }
-}
+define_id!(pub struct TileId;);
 
 // ------
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 enum Material {
     Dirt,
     Grass,
@@ -57,10 +26,10 @@ impl Component for Material {
 struct Sys;
 
 impl<'a> System<'a> for Sys {
-    type SystemData = (ReadExpect<'a, Tiles>, TileComps<'a, Material>);
+    type SystemData = (ReadExpect<'a, Grid2d>, ReadTiles<'a, Material, TileId>);
 
-    fn run(&mut self, (tiles, materials): Self::SystemData) {
-        if let Some(mat) = materials.get(tiles.id(3, 4)) {
+    fn run(&mut self, (grid, materials): Self::SystemData) {
+        if let Some(mat) = grid.id(3, 4).and_then(|id| materials.get(id)) {
             println!("The material at (3, 4) is {:?}.", mat);
         }
 
@@ -77,30 +46,41 @@ impl<'a> System<'a> for Sys {
 }
 
 fn main() {
-    let mut d = DispatcherBuilder::new().with(Sys, "sys", &[]).build();
+    let grid = Grid2d::new(8, 8);
+
+    // Systems which only look at one tile at a time can be written as a closure.
+    let grass = tile_system::<Read<Material>, TileId, _>(move |id, mat| {
+        if let Material::Grass = *mat {
+            println!("Tile {:?} is covered with grass.", grid.coords(id).unwrap());
+        }
+    });
+
+    let mut d = DispatcherBuilder::new()
+        .with(Sys, "sys", &[])
+        .with(grass, "grass", &[])
+        .build();
     let mut w = World::new();
 
-    // Use method provided by `WorldExt`.
-    w.add_resource(Tiles::new(8, 8));
-    w.register_tile_comp::<Material, TileId>();
+    // Use method provided by `TileWorldExt`.
+    w.insert(grid);
+    w.register_tile_comp_with_capacity::<Material, TileId>(grid.width() * grid.height());
 
     // Initialize
 
     {
-        let tiles = w.read_resource::<Tiles>();
-        let mut materials: TileCompsMut<Material> = SystemData::fetch(&w.res);
+        let grid = w.read_resource::<Grid2d>();
+        let mut materials = WriteTiles::<Material, TileId>::fetch(&w);
+        let id = |x, y| grid.id(x, y).unwrap();
 
-        for tile_id in tiles.iter_all() {
-            materials.insert(tile_id, Material::Dirt);
-        }
+        materials.fill_range(TileId(0), TileId(grid.len()), Material::Dirt);
 
-        materials.insert(tiles.id(1, 5), Material::Grass);
-        materials.insert(tiles.id(2, 5), Material::Grass);
-        materials.insert(tiles.id(3, 4), Material::Water);
-        materials.insert(tiles.id(3, 7), Material::Water);
+        materials.insert(id(1, 5), Material::Grass);
+        materials.insert(id(2, 5), Material::Grass);
+        materials.insert(id(3, 4), Material::Water);
+        materials.insert(id(3, 7), Material::Water);
     }
 
     // ---
 
-    d.dispatch(&mut w.res);
+    d.dispatch(&w);
 }
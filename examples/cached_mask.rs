@@ -0,0 +1,98 @@
+//! Measures a join of three layers whose masks barely overlap, intersecting the masks while
+//! iterating against driving it with a `CachedMask`. Run with
+//! `cargo run --release --example cached_mask`.
+
+extern crate specs;
+#[macro_use]
+extern crate specs_static;
+
+use std::time::Instant;
+
+use specs::{Component, Join, VecStorage};
+use specs_static::{join_with_mask, CachedMask, Storage};
+
+define_id!(pub struct TileId;);
+
+// ------
+
+struct Material(u8);
+
+impl Component for Material {
+    type Storage = VecStorage<Self>;
+}
+
+struct Moisture(u8);
+
+impl Component for Moisture {
+    type Storage = VecStorage<Self>;
+}
+
+struct Vegetation(u32);
+
+impl Component for Vegetation {
+    type Storage = VecStorage<Self>;
+}
+
+const TILES: u32 = 1 << 22;
+
+/// Returns the milliseconds `f` takes, best of 10.
+fn best_ms<F: FnMut()>(mut f: F) -> f64 {
+    let mut best = f64::MAX;
+    for _ in 0..10 {
+        let start = Instant::now();
+        f();
+        best = best.min(start.elapsed().as_secs_f64() * 1e3);
+    }
+
+    best
+}
+
+fn main() {
+    let mut seed = 1u32;
+    let mut random = move |len: u32| {
+        seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        (seed >> 8) % len
+    };
+
+    let mut materials = Storage::<Material, VecStorage<_>, TileId>::default();
+    let mut moisture = Storage::<Moisture, VecStorage<_>, TileId>::default();
+    let mut vegetation = Storage::<Vegetation, VecStorage<_>, TileId>::default();
+    for id in 0..TILES {
+        if random(2) == 0 {
+            materials.insert(TileId(id), Material(1));
+        }
+        if random(2) == 0 {
+            moisture.insert(TileId(id), Moisture(2));
+        }
+        if random(8) == 0 {
+            vegetation.insert(TileId(id), Vegetation(0));
+        }
+    }
+    let overlap = (&materials, &moisture, &vegetation).join().count();
+    println!("{} of {} tiles have all three layers", overlap, TILES);
+
+    let lazy_ms = best_ms(|| {
+        let layers = (&materials, &moisture, &mut vegetation);
+        for (material, moisture, vegetation) in layers.join() {
+            vegetation.0 += u32::from(material.0 * moisture.0);
+        }
+    });
+
+    let mut cached = CachedMask::new();
+    let rebuild_ms = best_ms(|| cached.rebuild(&(&materials, &moisture, &vegetation)));
+    let cached_ms = best_ms(|| {
+        assert!(!cached.update(&(&materials, &moisture, &vegetation)));
+        let layers = (&materials, &moisture, &mut vegetation);
+        for (material, moisture, vegetation) in join_with_mask(&cached, layers) {
+            vegetation.0 += u32::from(material.0 * moisture.0);
+        }
+    });
+
+    println!(
+        "join {:.2} ms, with the cached mask {:.2} ms, {:.2}x (a rebuild takes {:.2} ms)",
+        lazy_ms,
+        cached_ms,
+        lazy_ms / cached_ms,
+        rebuild_ms
+    );
+}
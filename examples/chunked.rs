@@ -0,0 +1,95 @@
+//! Measures `ChunkedVecStorage` against `VecStorage` and `HashMapStorage` on a sparse map of
+//! 16M tiles with 10% of them occupied, once clustered into regions and once scattered.
+//! Run with `cargo run --release --example chunked`.
+
+extern crate specs;
+#[macro_use]
+extern crate specs_static;
+
+use std::mem;
+use std::time::Instant;
+
+use specs::storage::UnprotectedStorage;
+use specs::{Component, HashMapStorage, Join, VecStorage};
+use specs_static::{BitSetLike, ChunkedVecStorage, Storage, CHUNK_SIZE};
+
+define_id!(pub struct TileId;);
+
+// ------
+
+struct Ore(u64);
+
+impl Component for Ore {
+    type Storage = ChunkedVecStorage<Self>;
+}
+
+const TILES: u32 = 1 << 24;
+
+/// Returns the milliseconds `f` takes, best of 5.
+fn best_ms<F: FnMut() -> u64>(mut f: F) -> f64 {
+    let mut best = f64::MAX;
+    let mut expected = None;
+    for _ in 0..5 {
+        let start = Instant::now();
+        let sum = f();
+        let elapsed = start.elapsed();
+        assert_eq!(*expected.get_or_insert(sum), sum);
+        best = best.min(elapsed.as_secs_f64() * 1e3);
+    }
+
+    best
+}
+
+fn measure<D>(name: &str, ids: &[u32]) -> Storage<Ore, D, TileId>
+where
+    D: UnprotectedStorage<Ore> + Default,
+{
+    let mut ores = Storage::<Ore, D, TileId>::default();
+    let insert = best_ms(|| {
+        ores = Storage::default();
+        for &id in ids {
+            ores.insert(TileId(id), Ore(u64::from(id)));
+        }
+        0
+    });
+    let get = best_ms(|| ids.iter().map(|&id| ores.get(TileId(id)).unwrap().0).sum());
+    let join = best_ms(|| ores.join().map(|ore| ore.0).sum());
+    println!(
+        "  {:17} insert {:7.2} ms, get {:7.2} ms, join {:7.2} ms",
+        name, insert, get, join
+    );
+
+    ores
+}
+
+fn run(name: &str, ids: &[u32]) {
+    println!("{}, {} tiles occupied:", name, ids.len());
+    let vec = measure::<VecStorage<Ore>>("VecStorage", ids);
+    let chunked = measure::<ChunkedVecStorage<Ore>>("ChunkedVecStorage", ids);
+    measure::<HashMapStorage<Ore>>("HashMapStorage", ids);
+
+    // The slots the backends allocate for components, occupied or not.
+    let size = mem::size_of::<Ore>() as f64 / (1 << 20) as f64;
+    let last = vec.mask().iter().last().map_or(0, |id| id + 1);
+    let chunks = chunked.unprotected_storage().chunk_count() as f64;
+    println!(
+        "  slots: VecStorage {:.1} MiB, ChunkedVecStorage {:.1} MiB",
+        f64::from(last) * size,
+        chunks * f64::from(CHUNK_SIZE) * size
+    );
+}
+
+fn main() {
+    let mut seed = 1u32;
+    let mut random = move |len: u32| {
+        seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        (seed >> 8) % len
+    };
+
+    // Islands of 64k tiles, every tenth region of the map.
+    let clustered: Vec<u32> = (0..TILES).filter(|id| (id >> 16) % 10 == 3).collect();
+    run("clustered", &clustered);
+
+    let scattered: Vec<u32> = (0..TILES).filter(|_| random(10) == 0).collect();
+    run("scattered", &scattered);
+}
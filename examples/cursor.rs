@@ -0,0 +1,95 @@
+//! Compares looking at the 3x3 neighborhoods along random walks through `Grid2d::id` and
+//! `Storage::get` with doing the same through a `TileCursor`. Run with
+//! `cargo run --release --example cursor`.
+
+extern crate specs;
+#[macro_use]
+extern crate specs_static;
+
+use std::time::Instant;
+
+use specs::{Component, VecStorage};
+use specs_static::{Grid2d, Storage};
+
+define_id!(pub struct TileId;);
+
+// ------
+
+struct Food(u32);
+
+impl Component for Food {
+    type Storage = VecStorage<Self>;
+}
+
+const SIZE: u32 = 1024;
+const STEPS: usize = 1 << 20;
+
+/// Returns the milliseconds `f` takes, best of 10.
+fn best_ms<F: FnMut()>(mut f: F) -> f64 {
+    let mut best = f64::MAX;
+    for _ in 0..10 {
+        let start = Instant::now();
+        f();
+        best = best.min(start.elapsed().as_secs_f64() * 1e3);
+    }
+
+    best
+}
+
+fn main() {
+    let grid = Grid2d::new(SIZE, SIZE);
+    let mut food = Storage::<Food, VecStorage<_>, TileId>::default();
+    let mut seed = 1u32;
+    let mut random = || {
+        seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        seed >> 8
+    };
+    for id in 0..grid.len() {
+        if random() % 16 != 0 {
+            food.insert(TileId(id), Food(id % 7));
+        }
+    }
+    let moves: Vec<(i32, i32)> = (0..STEPS)
+        .map(|_| ((random() % 3) as i32 - 1, (random() % 3) as i32 - 1))
+        .collect();
+
+    let mut sums = (0, 0);
+    let naive = best_ms(|| {
+        let (mut x, mut y) = (SIZE / 2, SIZE / 2);
+        let mut sum = 0;
+        for &(dx, dy) in &moves {
+            let (nx, ny) = ((x as i32 + dx) as u32, (y as i32 + dy) as u32);
+            if grid.contains(nx, ny) {
+                x = nx;
+                y = ny;
+            }
+            for oy in -1..2 {
+                for ox in -1..2 {
+                    let id = grid.id::<TileId>((x as i32 + ox) as u32, (y as i32 + oy) as u32);
+                    if let Some(food) = id.and_then(|id| food.get(id)) {
+                        sum += food.0;
+                    }
+                }
+            }
+        }
+        sums.0 = sum;
+    });
+    let cursor = best_ms(|| {
+        let mut cursor = food.cursor(&grid);
+        cursor.move_to(SIZE / 2, SIZE / 2);
+        let mut sum = 0;
+        for &(dx, dy) in &moves {
+            cursor.step(dx, dy);
+            for oy in -1..2 {
+                for ox in -1..2 {
+                    if let Some(food) = cursor.peek(ox, oy) {
+                        sum += food.0;
+                    }
+                }
+            }
+        }
+        sums.1 = sum;
+    });
+    assert_eq!(sums.0, sums.1);
+    println!("3x3 neighborhoods: naive {:7.2} ms, cursor {:7.2} ms", naive, cursor);
+}
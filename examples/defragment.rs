@@ -0,0 +1,98 @@
+//! Measures the join throughput of a `DenseSliceStorage` layer before and after heavy churn,
+//! and after restoring its order with `sort_dense_by_id` or `defragment`. Run with
+//! `cargo run --release --example defragment`.
+
+extern crate specs;
+#[macro_use]
+extern crate specs_static;
+
+use std::time::Instant;
+
+use specs::{Component, Join, VecStorage};
+use specs_static::{DenseSliceStorage, Storage};
+
+define_id!(pub struct TileId;);
+
+// ------
+
+struct Temperature([f32; 8]);
+
+impl Component for Temperature {
+    type Storage = VecStorage<Self>;
+}
+
+type Temperatures = Storage<Temperature, DenseSliceStorage<Temperature>, TileId>;
+
+const TILES: u32 = 1 << 20;
+
+/// Returns the nanoseconds per component of summing the layer with a join, best of 10.
+fn join_ns(temperatures: &Temperatures) -> f64 {
+    let count = temperatures.join().count() as f64;
+    let mut best = f64::MAX;
+    for _ in 0..10 {
+        let start = Instant::now();
+        let sum: f32 = temperatures.join().map(|t| t.0[0]).sum();
+        let elapsed = start.elapsed();
+        assert!(sum >= 0.0);
+        best = best.min(elapsed.as_secs_f64() * 1e9 / count);
+    }
+
+    best
+}
+
+/// Removes and inserts pseudo-random tiles, which scatters the dense order, and fills the
+/// holes again.
+fn churn(temperatures: &mut Temperatures, mut seed: u32) {
+    for _ in 0..TILES {
+        seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        let id = TileId((seed >> 8) % TILES);
+        if temperatures.remove(id).is_none() {
+            temperatures.insert(id, Temperature([1.0; 8]));
+        }
+    }
+    for id in 0..TILES {
+        if temperatures.get(TileId(id)).is_none() {
+            temperatures.insert(TileId(id), Temperature([1.0; 8]));
+        }
+    }
+    println!(
+        "churned:      fragmentation {:.3}, {:.2} ns per component",
+        temperatures.fragmentation(),
+        join_ns(temperatures)
+    );
+}
+
+fn main() {
+    let mut temperatures = Temperatures::default();
+    for id in 0..TILES {
+        temperatures.insert(TileId(id), Temperature([1.0; 8]));
+    }
+    println!(
+        "fresh:        fragmentation {:.3}, {:.2} ns per component",
+        temperatures.fragmentation(),
+        join_ns(&temperatures)
+    );
+
+    churn(&mut temperatures, 1);
+    let start = Instant::now();
+    let moved = temperatures.sort_dense_by_id();
+    let elapsed = start.elapsed();
+    println!(
+        "sorted:       fragmentation {:.3}, {:.2} ns per component (took {:.1} ms, {} moved)",
+        temperatures.fragmentation(),
+        join_ns(&temperatures),
+        elapsed.as_secs_f64() * 1e3,
+        moved
+    );
+
+    churn(&mut temperatures, 2);
+    let start = Instant::now();
+    temperatures.defragment();
+    let elapsed = start.elapsed();
+    println!(
+        "defragmented: fragmentation {:.3}, {:.2} ns per component (took {:.1} ms)",
+        temperatures.fragmentation(),
+        join_ns(&temperatures),
+        elapsed.as_secs_f64() * 1e3
+    );
+}
@@ -0,0 +1,92 @@
+//! Measures `DenseStorage` against a `Storage` backed by `VecStorage` on a fully occupied map,
+//! for lookups by id, joins, and joins with a sparse layer. Run with
+//! `cargo run --release --example dense`.
+
+extern crate specs;
+#[macro_use]
+extern crate specs_static;
+
+use std::time::Instant;
+
+use specs::{Component, Join, VecStorage};
+use specs_static::{DenseStorage, Storage};
+
+define_id!(pub struct TileId;);
+
+// ------
+
+#[derive(Clone, Copy)]
+struct Elevation(u16);
+
+impl Component for Elevation {
+    type Storage = VecStorage<Self>;
+}
+
+struct Tree;
+
+impl Component for Tree {
+    type Storage = VecStorage<Self>;
+}
+
+const TILES: u32 = 1 << 22;
+
+/// Returns the milliseconds `f` takes, best of 10.
+fn best_ms<F: FnMut() -> u64>(mut f: F, expected: u64) -> f64 {
+    let mut best = f64::MAX;
+    for _ in 0..10 {
+        let start = Instant::now();
+        let sum = f();
+        let elapsed = start.elapsed();
+        assert_eq!(sum, expected);
+        best = best.min(elapsed.as_secs_f64() * 1e3);
+    }
+
+    best
+}
+
+fn report(name: &str, storage: f64, dense: f64) {
+    println!(
+        "{:14} Storage {:7.2} ms, DenseStorage {:7.2} ms, {:.2}x",
+        name,
+        storage,
+        dense,
+        storage / dense
+    );
+}
+
+fn main() {
+    let elevation = |id: u32| Elevation((id % 1000) as u16);
+    let dense = DenseStorage::<Elevation, TileId>::from_fn(TILES, |id| elevation(id.0));
+    let mut storage = Storage::<Elevation, VecStorage<_>, TileId>::default();
+    let mut trees = Storage::<Tree, VecStorage<_>, TileId>::default();
+    for id in 0..TILES {
+        storage.insert(TileId(id), elevation(id));
+        if id % 16 == 0 {
+            trees.insert(TileId(id), Tree);
+        }
+    }
+
+    let expected: u64 = (0..TILES).map(|id| u64::from(elevation(id).0)).sum();
+    let get = |id| -> u64 { u64::from(storage.get(TileId(id)).unwrap().0) };
+    let get_dense = |id| -> u64 { u64::from(dense.get(TileId(id)).0) };
+    report(
+        "get",
+        best_ms(|| (0..TILES).map(get).sum(), expected),
+        best_ms(|| (0..TILES).map(get_dense).sum(), expected),
+    );
+
+    let sum = |elevation: &Elevation| u64::from(elevation.0);
+    report(
+        "join",
+        best_ms(|| storage.join().map(sum).sum(), expected),
+        best_ms(|| dense.join().map(sum).sum(), expected),
+    );
+
+    let expected: u64 = (0..TILES).step_by(16).map(|id| u64::from(elevation(id).0)).sum();
+    let sum = |(elevation, _): (&Elevation, &Tree)| u64::from(elevation.0);
+    report(
+        "join sparse",
+        best_ms(|| (&storage, &trees).join().map(sum).sum(), expected),
+        best_ms(|| (&dense, &trees).join().map(sum).sum(), expected),
+    );
+}
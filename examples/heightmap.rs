@@ -0,0 +1,67 @@
+//! Converts a 16-bit grayscale heightmap into an `Elevation` layer and prints a few
+//! statistics about it. Run with `cargo run --example heightmap --features image`.
+//!
+//! The heightmap is generated here; a real one would come from a PNG or TIFF file, e.g.
+//! with `image::open(path)?.into_luma16()` and the matching `image` format feature.
+
+extern crate image;
+extern crate specs;
+#[macro_use]
+extern crate specs_static;
+
+use image::{ImageBuffer, Luma};
+use specs::{Component, Join, VecStorage};
+use specs_static::Storage;
+
+define_id!(pub struct TileId;);
+
+// ------
+
+/// The elevation of a tile above sea level, in meters.
+struct Elevation(f32);
+
+impl Component for Elevation {
+    type Storage = VecStorage<Self>;
+}
+
+/// The elevation of the highest luma value.
+const MAX_ELEVATION: f32 = 2500.0;
+/// The luma of sea level; lower pixels are water and don't get an elevation.
+const SEA_LEVEL: u16 = 12_000;
+
+fn main() {
+    // A single hill in the middle of the map.
+    let (width, height) = (256, 128);
+    let heightmap = ImageBuffer::from_fn(width, height, |x, y| {
+        let dx = (x as f32 - width as f32 / 2.0) / width as f32;
+        let dy = (y as f32 - height as f32 / 2.0) / height as f32;
+        let hill = (-(dx * dx + dy * dy) * 8.0).exp();
+
+        Luma([(hill * f32::from(u16::MAX)) as u16])
+    });
+
+    let (grid, elevation) =
+        Storage::<Elevation, VecStorage<_>, TileId>::from_image_luma16(&heightmap, |_, _, luma| {
+            match luma < SEA_LEVEL {
+                true => None,
+                false => {
+                    let above = f32::from(luma - SEA_LEVEL) / f32::from(u16::MAX - SEA_LEVEL);
+                    Some(Elevation(above * MAX_ELEVATION))
+                }
+            }
+        })
+        .expect("the heightmap fits into a grid");
+
+    let land = (&elevation).join().count();
+    let peak = (&elevation).join().map(|e| e.0).fold(0.0, f32::max);
+    let center = grid.id::<TileId>(width / 2, height / 2).unwrap();
+    println!(
+        "{}x{} tiles, {} of them land ({:.1}%), peak at {:.0} m, center at {:.0} m",
+        grid.width(),
+        grid.height(),
+        land,
+        100.0 * land as f32 / grid.len() as f32,
+        peak,
+        elevation.get(center).map_or(0.0, |e| e.0)
+    );
+}
@@ -0,0 +1,81 @@
+//! Measures `insert` overwriting occupied tiles, like painting terrain, against removing and
+//! inserting again, which it used to do. Run with `cargo run --release --example insert`.
+
+extern crate specs;
+#[macro_use]
+extern crate specs_static;
+
+use std::time::Instant;
+
+use specs::storage::UnprotectedStorage;
+use specs::{Component, DenseVecStorage, VecStorage};
+use specs_static::Storage;
+
+define_id!(pub struct TileId;);
+
+// ------
+
+#[derive(Clone, Copy)]
+struct Material(u8);
+
+impl Component for Material {
+    type Storage = VecStorage<Self>;
+}
+
+const TILES: u32 = 1 << 22;
+
+/// Returns the milliseconds `f` takes, best of 10.
+fn best_ms<F: FnMut()>(mut f: F) -> f64 {
+    let mut best = f64::MAX;
+    for _ in 0..10 {
+        let start = Instant::now();
+        f();
+        best = best.min(start.elapsed().as_secs_f64() * 1e3);
+    }
+
+    best
+}
+
+fn measure<D>(name: &str)
+where
+    D: UnprotectedStorage<Material> + Default,
+{
+    let mut materials = Storage::<Material, D, TileId>::default();
+    let vacant = best_ms(|| {
+        materials = Storage::default();
+        for id in 0..TILES {
+            materials.insert(TileId(id), Material(1));
+        }
+    });
+
+    let mut brush = 1;
+    let overwrite = best_ms(|| {
+        brush += 1;
+        for id in 0..TILES {
+            let old = materials.insert(TileId(id), Material(brush));
+            assert_eq!(old.map(|old| old.0), Some(brush - 1));
+        }
+    });
+    let remove_insert = best_ms(|| {
+        brush += 1;
+        for id in 0..TILES {
+            let old = materials.remove(TileId(id));
+            materials.insert(TileId(id), Material(brush));
+            assert_eq!(old.map(|old| old.0), Some(brush - 1));
+        }
+    });
+
+    println!(
+        "{:16} vacant {:7.2} ms, overwrite {:7.2} ms, remove and insert {:7.2} ms, {:.2}x",
+        name,
+        vacant,
+        overwrite,
+        remove_insert,
+        remove_insert / overwrite
+    );
+}
+
+fn main() {
+    measure::<VecStorage<Material>>("VecStorage");
+    measure::<DenseVecStorage<Material>>("DenseVecStorage");
+}
@@ -0,0 +1,58 @@
+//! Measures the latency of single inserts while a layer grows to 256 MiB, for `PagedStorage`
+//! and `VecStorage`, whose reallocations copy the whole layer. Run with
+//! `cargo run --release --example paged`.
+
+extern crate specs;
+#[macro_use]
+extern crate specs_static;
+
+use std::time::Instant;
+
+use specs::storage::UnprotectedStorage;
+use specs::{Component, VecStorage};
+use specs_static::{PagedStorage, Storage};
+
+define_id!(pub struct TileId;);
+
+// ------
+
+#[derive(Clone, Copy)]
+struct Cell([u64; 8]);
+
+impl Component for Cell {
+    type Storage = PagedStorage<Self>;
+}
+
+const TILES: u32 = 1 << 22;
+
+fn measure<D>(name: &str)
+where
+    D: UnprotectedStorage<Cell> + Default,
+{
+    let mut cells = Storage::<Cell, D, TileId>::default();
+    let mut latencies = Vec::with_capacity(TILES as usize);
+    let total = Instant::now();
+    for id in 0..TILES {
+        let start = Instant::now();
+        cells.insert(TileId(id), Cell([u64::from(id); 8]));
+        latencies.push(start.elapsed().as_secs_f64() * 1e6);
+    }
+    let total = total.elapsed().as_secs_f64() * 1e3;
+    assert_eq!(cells.get(TileId(TILES - 1)).map(|cell| cell.0[7]), Some(u64::from(TILES - 1)));
+
+    latencies.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let percentile = |p: f64| latencies[((latencies.len() - 1) as f64 * p) as usize];
+    println!(
+        "{:13} total {:7.1} ms, p99 {:6.2} us, p99.99 {:8.2} us, max {:9.2} us",
+        name,
+        total,
+        percentile(0.99),
+        percentile(0.9999),
+        latencies[latencies.len() - 1]
+    );
+}
+
+fn main() {
+    measure::<VecStorage<Cell>>("VecStorage");
+    measure::<PagedStorage<Cell>>("PagedStorage");
+}
@@ -0,0 +1,61 @@
+//! Measures `par_for_each_chunked` against `par_join` and `par_iter_mut` for a trivially cheap
+//! operation on every tile of a layer with 4M tiles. Run with
+//! `cargo run --release --example par_chunked`.
+
+extern crate rayon;
+extern crate specs;
+#[macro_use]
+extern crate specs_static;
+
+use std::time::Instant;
+
+use rayon::iter::ParallelIterator;
+use specs::{Component, ParJoin, VecStorage};
+use specs_static::Storage;
+
+define_id!(pub struct TileId;);
+
+// ------
+
+struct Heat(u32);
+
+impl Component for Heat {
+    type Storage = VecStorage<Self>;
+}
+
+const TILES: u32 = 1 << 22;
+
+/// Returns the milliseconds `f` takes, best of 10.
+fn best_ms<F: FnMut()>(mut f: F) -> f64 {
+    let mut best = f64::MAX;
+    for _ in 0..10 {
+        let start = Instant::now();
+        f();
+        best = best.min(start.elapsed().as_secs_f64() * 1e3);
+    }
+
+    best
+}
+
+fn main() {
+    let mut heats = Storage::<Heat, VecStorage<_>, TileId>::default();
+    for id in (0..TILES).filter(|id| id >> 16 & 3 != 0) {
+        heats.insert(TileId(id), Heat(id));
+    }
+    println!("{} threads", rayon::current_num_threads());
+
+    let par_join = best_ms(|| (&mut heats).par_join().for_each(|heat| heat.0 += 1));
+    println!("par_join:                    {:7.2} ms", par_join);
+    let par_iter_mut = best_ms(|| heats.par_iter_mut().for_each(|(_, heat)| heat.0 += 1));
+    println!("par_iter_mut:                {:7.2} ms", par_iter_mut);
+
+    for &chunk in &[64, 1024, 16_384, 262_144] {
+        let chunked = best_ms(|| heats.par_for_each_chunked(chunk, |_, heat| heat.0 += 1));
+        println!(
+            "par_for_each_chunked({:7}): {:7.2} ms, {:.2}x",
+            chunk,
+            chunked,
+            par_join / chunked
+        );
+    }
+}
@@ -0,0 +1,86 @@
+//! Measures how `par_fold` scales with the number of threads when computing statistics over a
+//! big layer. Run with `cargo run --release --example par_fold`.
+
+extern crate rayon;
+extern crate specs;
+#[macro_use]
+extern crate specs_static;
+
+use std::time::Instant;
+
+use rayon::ThreadPoolBuilder;
+use specs::{Component, Join, VecStorage};
+use specs_static::Storage;
+
+define_id!(pub struct TileId;);
+
+// ------
+
+struct Population(u32);
+
+impl Component for Population {
+    type Storage = VecStorage<Self>;
+}
+
+type Populations = Storage<Population, VecStorage<Population>, TileId>;
+
+const TILES: u32 = 1 << 23;
+
+/// The total population with the least and the most populated tile.
+type Stats = (u64, u32, u32);
+
+fn add((total, min, max): Stats, pop: &Population) -> Stats {
+    (total + u64::from(pop.0), min.min(pop.0), max.max(pop.0))
+}
+
+fn merge(a: Stats, b: Stats) -> Stats {
+    (a.0 + b.0, a.1.min(b.1), a.2.max(b.2))
+}
+
+fn empty() -> Stats {
+    (0, u32::MAX, 0)
+}
+
+/// Returns the milliseconds `f` takes, best of 10.
+fn best_ms<F: FnMut() -> Stats>(mut f: F, expected: Stats) -> f64 {
+    let mut best = f64::MAX;
+    for _ in 0..10 {
+        let start = Instant::now();
+        let stats = f();
+        let elapsed = start.elapsed();
+        assert_eq!(stats, expected);
+        best = best.min(elapsed.as_secs_f64() * 1e3);
+    }
+
+    best
+}
+
+fn main() {
+    let mut populations = Populations::default();
+    let mut seed = 1u32;
+    for id in 0..TILES {
+        seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        // Every eighth block of 64k tiles is ocean.
+        if id >> 16 & 7 != 0 {
+            populations.insert(TileId(id), Population(seed >> 20));
+        }
+    }
+
+    let expected = populations.join().fold(empty(), add);
+    let sequential = best_ms(|| populations.join().fold(empty(), add), expected);
+    println!("sequential: {:7.2} ms", sequential);
+
+    let mut threads = 1;
+    while threads <= rayon::current_num_threads() {
+        let pool = ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+        let fold = || populations.par_fold(empty, |stats, _, pop| add(stats, pop), merge);
+        let parallel = pool.install(|| best_ms(fold, expected));
+        println!(
+            "{:2} threads: {:7.2} ms, {:.2}x",
+            threads,
+            parallel,
+            sequential / parallel
+        );
+        threads *= 2;
+    }
+}
@@ -0,0 +1,96 @@
+//! Measures how `par_iter_mut`, `par_iter` and `par_ids` scale with the number of threads on
+//! a dense layer of 4M tiles. Run with `cargo run --release --example par_iter`.
+
+extern crate rayon;
+extern crate specs;
+#[macro_use]
+extern crate specs_static;
+
+use std::time::Instant;
+
+use rayon::iter::ParallelIterator;
+use rayon::ThreadPoolBuilder;
+use specs::{Component, Join, VecStorage};
+use specs_static::Storage;
+
+define_id!(pub struct TileId;);
+
+// ------
+
+struct Elevation(u32);
+
+impl Component for Elevation {
+    type Storage = VecStorage<Self>;
+}
+
+type Elevations = Storage<Elevation, VecStorage<Elevation>, TileId>;
+
+const TILES: u32 = 1 << 22;
+
+/// Returns the milliseconds `f` takes, best of 10.
+fn best_ms<F: FnMut()>(mut f: F) -> f64 {
+    let mut best = f64::MAX;
+    for _ in 0..10 {
+        let start = Instant::now();
+        f();
+        best = best.min(start.elapsed().as_secs_f64() * 1e3);
+    }
+
+    best
+}
+
+fn erode(id: TileId, elevation: &mut Elevation) {
+    elevation.0 = (elevation.0 ^ id.0).rotate_left(3).wrapping_mul(31);
+}
+
+fn sum(elevations: &Elevations) -> u64 {
+    elevations.par_iter().map(|(_, elevation)| u64::from(elevation.0)).sum()
+}
+
+fn main() {
+    let mut elevations = Elevations::default();
+    for id in 0..TILES {
+        elevations.insert(TileId(id), Elevation(id));
+    }
+
+    let mask = elevations.mask().clone();
+    let sequential_mut = best_ms(|| {
+        for (id, elevation) in (&mask, &mut elevations).join() {
+            erode(TileId(id), elevation);
+        }
+    });
+    let sequential_sum = best_ms(|| {
+        let total: u64 = elevations.join().map(|elevation| u64::from(elevation.0)).sum();
+        assert!(total > 0);
+    });
+    println!(
+        "sequential: mut {:7.2} ms, sum {:7.2} ms",
+        sequential_mut, sequential_sum
+    );
+
+    let mut threads = 1;
+    while threads <= rayon::current_num_threads() {
+        let pool = ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+        let (par_mut, par_sum, par_ids) = pool.install(|| {
+            let par_mut = best_ms(|| {
+                elevations
+                    .par_iter_mut()
+                    .for_each(|(id, elevation)| erode(id, elevation))
+            });
+            let par_sum = best_ms(|| assert!(sum(&elevations) > 0));
+            let par_ids = best_ms(|| assert_eq!(elevations.par_ids().count(), TILES as usize));
+
+            (par_mut, par_sum, par_ids)
+        });
+        println!(
+            "{:2} threads: mut {:7.2} ms, {:.2}x; sum {:7.2} ms, {:.2}x; ids {:7.2} ms",
+            threads,
+            par_mut,
+            sequential_mut / par_mut,
+            par_sum,
+            sequential_sum / par_sum,
+            par_ids
+        );
+        threads *= 2;
+    }
+}
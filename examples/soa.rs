@@ -0,0 +1,75 @@
+//! Compares passes over one field of a component stored as an array of structs, in a
+//! `Storage`, with the same passes over its column in a `SoaStorage`. Run with
+//! `cargo run --release --example soa`.
+
+#[macro_use]
+extern crate specs_static;
+extern crate specs;
+
+use std::time::Instant;
+
+use specs::Join;
+use specs_static::{SliceStorage, SoaStorage, Storage};
+
+define_id!(pub struct TileId;);
+
+// ------
+
+soa_tile_component! {
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct Climate {
+        pub temp: f32,
+        pub humidity: f32,
+        pub wind: u8,
+    }
+    columns = ClimateColumns;
+}
+
+const TILES: u32 = 1 << 22;
+
+/// Returns the milliseconds `f` takes, best of 10.
+fn best_ms<F: FnMut()>(mut f: F) -> f64 {
+    let mut best = f64::MAX;
+    for _ in 0..10 {
+        let start = Instant::now();
+        f();
+        best = best.min(start.elapsed().as_secs_f64() * 1e3);
+    }
+
+    best
+}
+
+fn main() {
+    let mut aos = Storage::<Climate, SliceStorage<Climate>, TileId>::default();
+    let mut soa = SoaStorage::<Climate, TileId>::new();
+    let mut seed = 1u32;
+    for id in 0..TILES {
+        seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        let climate = Climate {
+            temp: (seed >> 16) as f32 / 1000.0,
+            humidity: (seed & 0xffff) as f32 / 65_536.0,
+            wind: (seed >> 24) as u8,
+        };
+        aos.insert(TileId(id), climate);
+        soa.insert(TileId(id), climate);
+    }
+
+    let mut sums = (0.0, 0.0);
+    let aos_scan = best_ms(|| sums.0 = aos.join().map(|climate| climate.temp).sum::<f32>());
+    let soa_scan = best_ms(|| sums.1 = soa.columns().temp.iter().sum::<f32>());
+    assert_eq!(sums.0, sums.1);
+    println!("temperature sum:   AoS {:7.2} ms, SoA {:7.2} ms", aos_scan, soa_scan);
+
+    let aos_pass = best_ms(|| {
+        for climate in (&mut aos).join() {
+            climate.temp *= 0.99;
+        }
+    });
+    let soa_pass = best_ms(|| {
+        for temp in soa.columns_mut().temp.iter_mut() {
+            *temp *= 0.99;
+        }
+    });
+    assert!(aos.join().zip(soa.join()).all(|(a, s)| *a == s));
+    println!("temperature decay: AoS {:7.2} ms, SoA {:7.2} ms", aos_pass, soa_pass);
+}
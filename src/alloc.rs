@@ -0,0 +1,257 @@
+//! Routing the allocations of the crate's chunked backends through a user-provided allocator.
+
+use std::alloc::{self, Layout};
+use std::marker::PhantomData;
+use std::mem::MaybeUninit;
+use std::ops::{Deref, DerefMut};
+use std::ptr::{self, NonNull};
+use std::slice;
+
+/// An allocator handle for the chunks and tables of `ChunkedVecStorage` and `PagedStorage`,
+/// so their memory can be tracked or placed in an arena.
+///
+/// Every backend keeps a clone of the handle for every allocation it owns, so handles should
+/// be cheap to clone, like a zero-sized type or a reference to a `static`. specs requires
+/// backends to implement `Default`, so the handles have to as well: `Storage::default` and
+/// `TileWorldExt::register_tile_comp` use the default handle, `Storage::with_alloc` and
+/// `TileWorldExt::register_tile_comp_with` a given one. The occupancy bitset of a `Storage` is
+/// allocated by `hibitset`, which always uses the global allocator.
+///
+/// # Safety
+///
+/// The methods have the contract of the `GlobalAlloc` methods of the same names, and memory
+/// allocated by a handle has to be deallocatable by every clone of it.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use std::alloc::{GlobalAlloc, Layout, System};
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// use specs::Component;
+/// use specs_static::{ChunkedVecStorage, Storage, TileAlloc, CHUNK_SIZE};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// static TERRAIN_BYTES: AtomicUsize = AtomicUsize::new(0);
+///
+/// #[derive(Clone, Copy)]
+/// struct Counting(&'static AtomicUsize);
+///
+/// impl Default for Counting {
+///     fn default() -> Self {
+///         Counting(&TERRAIN_BYTES)
+///     }
+/// }
+///
+/// unsafe impl TileAlloc for Counting {
+///     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+///         self.0.fetch_add(layout.size(), Ordering::SeqCst);
+///         System.alloc(layout)
+///     }
+///
+///     unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+///         self.0.fetch_sub(layout.size(), Ordering::SeqCst);
+///         System.dealloc(ptr, layout)
+///     }
+/// }
+///
+/// struct Height(u64);
+///
+/// impl Component for Height {
+///     type Storage = ChunkedVecStorage<Self, Counting>;
+/// }
+///
+/// fn main() {
+///     let alloc = Counting(&TERRAIN_BYTES);
+///     let mut heights = Storage::<Height, ChunkedVecStorage<_, _>, TileId>::with_alloc(alloc);
+///     for id in 0..100_000 {
+///         heights.insert(TileId(id), Height(u64::from(id)));
+///     }
+///
+///     // 98 chunks of 1024 slots of 8 bytes, and the chunk table.
+///     let chunks = (100_000 + CHUNK_SIZE as usize - 1) / CHUNK_SIZE as usize;
+///     assert_eq!(heights.unprotected_storage().chunk_count(), chunks);
+///     assert!(TERRAIN_BYTES.load(Ordering::SeqCst) > chunks * CHUNK_SIZE as usize * 8);
+///
+///     heights.clear();
+///     assert_eq!(TERRAIN_BYTES.load(Ordering::SeqCst), 0);
+/// }
+/// ```
+pub unsafe trait TileAlloc: Clone + Default + Send + Sync + 'static {
+    /// Allocates memory as described by `layout`, which has a non-zero size. Returns null
+    /// if the allocation failed.
+    ///
+    /// # Safety
+    ///
+    /// See `GlobalAlloc::alloc`.
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8;
+
+    /// Deallocates the memory at `ptr`, which has been allocated with `layout` by this
+    /// handle or a clone of it.
+    ///
+    /// # Safety
+    ///
+    /// See `GlobalAlloc::dealloc`.
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout);
+}
+
+/// The allocator handle used by default, which forwards to the global allocator.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct StdAlloc;
+
+unsafe impl TileAlloc for StdAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        alloc::alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        alloc::dealloc(ptr, layout)
+    }
+}
+
+/// Allocates room for `len` values of `T`, returning a dangling pointer for zero bytes.
+fn allocate<T, A: TileAlloc>(alloc: &A, len: usize) -> NonNull<T> {
+    let layout = Layout::array::<T>(len).expect("allocation too large");
+    if layout.size() == 0 {
+        return NonNull::dangling();
+    }
+
+    let ptr = unsafe { alloc.alloc(layout) } as *mut T;
+    NonNull::new(ptr).unwrap_or_else(|| alloc::handle_alloc_error(layout))
+}
+
+/// Frees memory returned by `allocate::<T>(alloc, len)`.
+unsafe fn deallocate<T, A: TileAlloc>(alloc: &A, ptr: NonNull<T>, len: usize) {
+    let layout = Layout::array::<T>(len).expect("allocated with this layout");
+    if layout.size() != 0 {
+        alloc.dealloc(ptr.as_ptr() as *mut u8, layout);
+    }
+}
+
+/// A fixed amount of uninitialized slots, like `Box<[MaybeUninit<T>]>`. Dropping it frees
+/// the memory without dropping any values.
+pub(crate) struct Slots<T, A: TileAlloc> {
+    ptr: NonNull<MaybeUninit<T>>,
+    len: usize,
+    alloc: A,
+}
+
+unsafe impl<T: Send, A: TileAlloc> Send for Slots<T, A> {}
+unsafe impl<T: Sync, A: TileAlloc> Sync for Slots<T, A> {}
+
+impl<T, A: TileAlloc> Slots<T, A> {
+    pub(crate) fn new(len: usize, alloc: &A) -> Self {
+        Slots {
+            ptr: allocate(alloc, len),
+            len,
+            alloc: alloc.clone(),
+        }
+    }
+}
+
+impl<T, A: TileAlloc> Deref for Slots<T, A> {
+    type Target = [MaybeUninit<T>];
+
+    fn deref(&self) -> &[MaybeUninit<T>] {
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T, A: TileAlloc> DerefMut for Slots<T, A> {
+    fn deref_mut(&mut self) -> &mut [MaybeUninit<T>] {
+        unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T, A: TileAlloc> Drop for Slots<T, A> {
+    fn drop(&mut self) {
+        unsafe { deallocate(&self.alloc, self.ptr, self.len) }
+    }
+}
+
+/// A growable list of initialized values, like a `Vec` which only grows at the end.
+pub(crate) struct Table<T, A: TileAlloc> {
+    ptr: NonNull<T>,
+    cap: usize,
+    len: usize,
+    alloc: A,
+    phantom: PhantomData<T>,
+}
+
+unsafe impl<T: Send, A: TileAlloc> Send for Table<T, A> {}
+unsafe impl<T: Sync, A: TileAlloc> Sync for Table<T, A> {}
+
+impl<T, A: TileAlloc> Table<T, A> {
+    pub(crate) fn new(alloc: A) -> Self {
+        Table {
+            ptr: NonNull::dangling(),
+            cap: 0,
+            len: 0,
+            alloc,
+            phantom: PhantomData,
+        }
+    }
+
+    pub(crate) fn alloc(&self) -> &A {
+        &self.alloc
+    }
+
+    /// Appends values returned by `f` until there are `len` values.
+    pub(crate) fn grow_with<F: FnMut() -> T>(&mut self, len: usize, mut f: F) {
+        if len <= self.len {
+            return;
+        }
+        if len > self.cap {
+            let cap = len.max(self.cap * 2).max(4);
+            let ptr = allocate::<T, A>(&self.alloc, cap);
+            unsafe {
+                ptr::copy_nonoverlapping(self.ptr.as_ptr(), ptr.as_ptr(), self.len);
+                deallocate(&self.alloc, self.ptr, self.cap);
+            }
+            self.ptr = ptr;
+            self.cap = cap;
+        }
+
+        while self.len < len {
+            // Counted one by one, so a panicking `f` leaves the table consistent.
+            unsafe { ptr::write(self.ptr.as_ptr().add(self.len), f()) };
+            self.len += 1;
+        }
+    }
+
+    /// Drops all values and frees the memory.
+    pub(crate) fn clear(&mut self) {
+        let (ptr, cap, len) = (self.ptr, self.cap, self.len);
+        self.ptr = NonNull::dangling();
+        self.cap = 0;
+        self.len = 0;
+
+        unsafe {
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(ptr.as_ptr(), len));
+            deallocate(&self.alloc, ptr, cap);
+        }
+    }
+}
+
+impl<T, A: TileAlloc> Deref for Table<T, A> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T, A: TileAlloc> DerefMut for Table<T, A> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T, A: TileAlloc> Drop for Table<T, A> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
@@ -0,0 +1,420 @@
+//! A dense storage of atomic cells which can be written concurrently through shared references.
+
+use std::marker::PhantomData;
+use std::sync::atomic::{
+    AtomicI16, AtomicI32, AtomicI64, AtomicI8, AtomicU16, AtomicU32, AtomicU64, AtomicU8,
+    Ordering,
+};
+
+#[cfg(feature = "parallel")]
+use specs::ParJoin;
+use specs::Join;
+
+use mask::MAX_LEN;
+use {DenseStorage, Id, Index, RangeMask};
+
+/// Primitive integers which have an atomic counterpart.
+pub trait AtomicRepr: Copy + Send + Sync + 'static {
+    /// The atomic type holding a `Self`.
+    type Atomic: Send + Sync;
+
+    /// Creates a new atomic holding `self`.
+    fn new_atomic(self) -> Self::Atomic;
+
+    /// Loads the value of `atomic`.
+    fn load(atomic: &Self::Atomic) -> Self;
+
+    /// Stores `value` into `atomic`.
+    fn store(atomic: &Self::Atomic, value: Self);
+
+    /// Stores `value` into `atomic`, returning the previous value.
+    fn swap(atomic: &Self::Atomic, value: Self) -> Self;
+
+    /// Stores `new` if the current value is `current`, possibly failing spuriously.
+    fn compare_exchange_weak(atomic: &Self::Atomic, current: Self, new: Self)
+        -> Result<Self, Self>;
+
+    /// Consumes the atomic, returning the value it holds.
+    fn into_inner(atomic: Self::Atomic) -> Self;
+}
+
+macro_rules! impl_atomic_repr {
+    ($($ty:ty => $atomic:ty),*) => {
+        $(
+            impl AtomicRepr for $ty {
+                type Atomic = $atomic;
+
+                fn new_atomic(self) -> $atomic {
+                    <$atomic>::new(self)
+                }
+
+                fn load(atomic: &$atomic) -> Self {
+                    atomic.load(Ordering::Relaxed)
+                }
+
+                fn store(atomic: &$atomic, value: Self) {
+                    atomic.store(value, Ordering::Relaxed)
+                }
+
+                fn swap(atomic: &$atomic, value: Self) -> Self {
+                    atomic.swap(value, Ordering::Relaxed)
+                }
+
+                fn compare_exchange_weak(
+                    atomic: &$atomic,
+                    current: Self,
+                    new: Self,
+                ) -> Result<Self, Self> {
+                    atomic.compare_exchange_weak(current, new, Ordering::Relaxed, Ordering::Relaxed)
+                }
+
+                fn into_inner(atomic: $atomic) -> Self {
+                    atomic.into_inner()
+                }
+            }
+
+            impl AtomicComponent for $ty {
+                type Repr = $ty;
+
+                fn into_repr(self) -> $ty {
+                    self
+                }
+
+                fn from_repr(repr: $ty) -> Self {
+                    repr
+                }
+            }
+        )*
+    };
+}
+
+impl_atomic_repr!(
+    u8 => AtomicU8,
+    u16 => AtomicU16,
+    u32 => AtomicU32,
+    u64 => AtomicU64,
+    i8 => AtomicI8,
+    i16 => AtomicI16,
+    i32 => AtomicI32,
+    i64 => AtomicI64
+);
+
+/// Components which can be converted losslessly to and from a primitive integer,
+/// so they can be stored in an `AtomicStorage`.
+///
+/// This is implemented for the primitive integers themselves; newtypes can implement
+/// it by unwrapping and wrapping their value.
+pub trait AtomicComponent: Copy + Send + Sync + 'static {
+    /// The integer this component is stored as.
+    type Repr: AtomicRepr;
+
+    /// Converts the component into its integer representation.
+    fn into_repr(self) -> Self::Repr;
+
+    /// Converts an integer obtained from `into_repr` back into the component.
+    fn from_repr(repr: Self::Repr) -> Self;
+}
+
+/// A single atomically accessed component.
+///
+/// All operations use relaxed ordering: they are atomic, but don't synchronize other
+/// memory. That's enough when the writing threads are joined (for example at the end of a
+/// `rayon` scope or a system) before the results are read.
+pub struct AtomicCell<C: AtomicComponent> {
+    atomic: <C::Repr as AtomicRepr>::Atomic,
+    phantom: PhantomData<C>,
+}
+
+impl<C> AtomicCell<C>
+where
+    C: AtomicComponent,
+{
+    /// Creates a cell holding `value`.
+    pub fn new(value: C) -> Self {
+        AtomicCell {
+            atomic: value.into_repr().new_atomic(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns the current value.
+    pub fn load(&self) -> C {
+        C::from_repr(C::Repr::load(&self.atomic))
+    }
+
+    /// Replaces the current value.
+    pub fn store(&self, value: C) {
+        C::Repr::store(&self.atomic, value.into_repr())
+    }
+
+    /// Replaces the current value, returning the previous one.
+    pub fn swap(&self, value: C) -> C {
+        C::from_repr(C::Repr::swap(&self.atomic, value.into_repr()))
+    }
+
+    /// Atomically replaces the current value with `f(current)` and returns the previous value.
+    ///
+    /// `f` may be called multiple times if other threads write to this cell concurrently.
+    /// If it returns `None`, the value is left untouched and `Err(current)` is returned.
+    pub fn fetch_update<F>(&self, mut f: F) -> Result<C, C>
+    where
+        F: FnMut(C) -> Option<C>,
+    {
+        let mut current = C::Repr::load(&self.atomic);
+        while let Some(new) = f(C::from_repr(current)) {
+            match C::Repr::compare_exchange_weak(&self.atomic, current, new.into_repr()) {
+                Ok(previous) => return Ok(C::from_repr(previous)),
+                Err(actual) => current = actual,
+            }
+        }
+
+        Err(C::from_repr(current))
+    }
+
+    /// Stores the maximum of the current value and `value`, returning the previous value.
+    pub fn fetch_max(&self, value: C) -> C
+    where
+        C: Ord,
+    {
+        match self.fetch_update(|current| if value > current { Some(value) } else { None }) {
+            Ok(previous) | Err(previous) => previous,
+        }
+    }
+
+    /// Stores the minimum of the current value and `value`, returning the previous value.
+    pub fn fetch_min(&self, value: C) -> C
+    where
+        C: Ord,
+    {
+        match self.fetch_update(|current| if value < current { Some(value) } else { None }) {
+            Ok(previous) | Err(previous) => previous,
+        }
+    }
+
+    /// Consumes the cell, returning its value.
+    pub fn into_inner(self) -> C {
+        C::from_repr(C::Repr::into_inner(self.atomic))
+    }
+}
+
+/// A storage where every id in `0..len` has a component stored in an `AtomicCell`.
+///
+/// Like `DenseStorage`, occupancy is fixed, so there's no bitset that would need to be
+/// modified concurrently. Every cell can be written through a shared reference, which lets
+/// many threads scatter writes to arbitrary (even overlapping) ids at once, for example
+/// from a `rayon` scope or a `par_join` over another layer.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs_static;
+///
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// use specs_static::{AtomicComponent, AtomicStorage};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// #[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+/// struct Light(u8);
+///
+/// impl AtomicComponent for Light {
+///     type Repr = u8;
+///
+///     fn into_repr(self) -> u8 {
+///         self.0
+///     }
+///
+///     fn from_repr(repr: u8) -> Self {
+///         Light(repr)
+///     }
+/// }
+///
+/// const LEN: u32 = 4096;
+///
+/// // Every source lights up the tiles around it, fading with the distance.
+/// fn light_at(source: u32, id: u32) -> u8 {
+///     let distance = if source > id { source - id } else { id - source };
+///     255u32.saturating_sub(distance) as u8
+/// }
+///
+/// fn main() {
+///     let lights = Arc::new(AtomicStorage::<Light, TileId>::filled(LEN, Light(0)));
+///     let sources: Vec<u32> = (0..64).map(|i| i * 61 % LEN).collect();
+///
+///     let handles: Vec<_> = sources
+///         .chunks(8)
+///         .map(|sources| {
+///             let sources = sources.to_vec();
+///             let lights = lights.clone();
+///             thread::spawn(move || {
+///                 for &source in &sources {
+///                     let start = source.saturating_sub(255);
+///                     let end = (source + 256).min(LEN);
+///                     for id in start..end {
+///                         lights.fetch_max(TileId(id), Light(light_at(source, id)));
+///                     }
+///                 }
+///             })
+///         })
+///         .collect();
+///     for handle in handles {
+///         handle.join().unwrap();
+///     }
+///
+///     for id in 0..LEN {
+///         let expected = sources.iter().map(|&s| light_at(s, id)).max().unwrap();
+///         assert_eq!(lights.load(TileId(id)), Light(expected));
+///     }
+/// }
+/// ```
+#[derive(Derivative)]
+#[derivative(Default(bound = ""))]
+pub struct AtomicStorage<C: AtomicComponent, I> {
+    data: Vec<AtomicCell<C>>,
+    phantom: PhantomData<I>,
+}
+
+impl<C, I> AtomicStorage<C, I>
+where
+    C: AtomicComponent,
+    I: Id,
+{
+    /// Creates a storage with `len` components, computing each of them from its id.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` exceeds the amount of ids that can be joined over.
+    pub fn from_fn<F>(len: u32, mut f: F) -> Self
+    where
+        F: FnMut(I) -> C,
+    {
+        assert!(
+            u64::from(len) <= MAX_LEN,
+            "`AtomicStorage` length {} exceeds the maximum of {}",
+            len,
+            MAX_LEN
+        );
+
+        AtomicStorage {
+            data: (0..len).map(|id| AtomicCell::new(f(I::from_u32(id)))).collect(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Creates a storage with `len` components, all of them set to `value`.
+    pub fn filled(len: u32, value: C) -> Self {
+        AtomicStorage::from_fn(len, |_| value)
+    }
+
+    /// Returns the amount of ids (and thus components) in this storage.
+    pub fn len(&self) -> u32 {
+        self.data.len() as u32
+    }
+
+    /// Returns `true` if the storage doesn't hold any component.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Returns `true` if `id` is in bounds.
+    pub fn contains(&self, id: I) -> bool {
+        id.id() < self.len()
+    }
+
+    /// Returns the cell at `id`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` is out of bounds.
+    pub fn get(&self, id: I) -> &AtomicCell<C> {
+        &self.data[id.id() as usize]
+    }
+
+    /// Returns the component at `id`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` is out of bounds.
+    pub fn load(&self, id: I) -> C {
+        self.get(id).load()
+    }
+
+    /// Replaces the component at `id`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` is out of bounds.
+    pub fn store(&self, id: I, value: C) {
+        self.get(id).store(value)
+    }
+
+    /// Stores the maximum of the component at `id` and `value`, returning the previous
+    /// component.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` is out of bounds.
+    pub fn fetch_max(&self, id: I, value: C) -> C
+    where
+        C: Ord,
+    {
+        self.get(id).fetch_max(value)
+    }
+
+    /// Stores the minimum of the component at `id` and `value`, returning the previous
+    /// component.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` is out of bounds.
+    pub fn fetch_min(&self, id: I, value: C) -> C
+    where
+        C: Ord,
+    {
+        self.get(id).fetch_min(value)
+    }
+
+    /// Returns the mask of all ids in this storage.
+    pub fn mask(&self) -> RangeMask {
+        RangeMask::new(self.len())
+    }
+
+    /// Converts this storage into a `DenseStorage` of plain components.
+    pub fn into_dense(self) -> DenseStorage<C, I> {
+        DenseStorage::from_vec(self.data.into_iter().map(AtomicCell::into_inner).collect())
+    }
+}
+
+impl<C, I> From<DenseStorage<C, I>> for AtomicStorage<C, I>
+where
+    C: AtomicComponent,
+    I: Id,
+{
+    fn from(dense: DenseStorage<C, I>) -> Self {
+        AtomicStorage {
+            data: dense.into_vec().into_iter().map(AtomicCell::new).collect(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<'a, C, I> Join for &'a AtomicStorage<C, I>
+where
+    C: AtomicComponent,
+{
+    type Type = &'a AtomicCell<C>;
+    type Value = &'a [AtomicCell<C>];
+    type Mask = RangeMask;
+
+    unsafe fn open(self) -> (Self::Mask, Self::Value) {
+        (RangeMask::new(self.data.len() as u32), &self.data)
+    }
+
+    unsafe fn get(value: &mut Self::Value, id: Index) -> Self::Type {
+        value.get_unchecked(id as usize)
+    }
+}
+
+#[cfg(feature = "parallel")]
+unsafe impl<C, I> ParJoin for &AtomicStorage<C, I> where C: AtomicComponent {}
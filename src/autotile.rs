@@ -0,0 +1,233 @@
+//! Neighbor masks for choosing the sprites of tiles.
+
+use hibitset::BitSetLike;
+use specs::storage::UnprotectedStorage;
+use specs::{Component, VecStorage};
+
+use grid::{EIGHT, FOUR};
+use {Connectivity, Grid2d, Id, Storage};
+
+/// The neighbor mask of a tile, written by `Autotile::fill_masks`.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct AutotileMask(pub u8);
+
+impl Component for AutotileMask {
+    type Storage = VecStorage<Self>;
+}
+
+/// Computes the 4-bit neighbor mask of `id`; see `Autotile` for the bit order.
+///
+/// Neighbors outside the grid count as different.
+pub fn autotile_mask<C, D, I, F>(grid: &Grid2d, storage: &Storage<C, D, I>, id: I, same: F) -> u8
+where
+    D: UnprotectedStorage<C>,
+    I: Id,
+    F: Fn(&C, &C) -> bool,
+{
+    Autotile::new(Connectivity::Four).mask(grid, storage, id, same)
+}
+
+/// Computes the masks telling for every direction whether the neighbor of a tile is of the
+/// same kind, which is how autotiling picks the sprite of a tile.
+///
+/// Bit `n` of a mask is set if `same(tile, neighbor)` holds for the `n`th neighbor, in the
+/// same row-major order `Grid2d::neighbors` uses:
+///
+/// | `Connectivity` | bit 0 | 1 | 2 | 3 | 4 | 5 | 6 | 7 |
+/// |----------------|-------|---|---|---|---|---|---|---|
+/// | `Four`         | up    | left | right | down | | | | |
+/// | `Eight`        | up left | up | up right | left | right | down left | down | down right |
+///
+/// "Up" is the row with the lower `y`. Vacant neighbors always count as different, while
+/// neighbors outside the grid count as different unless `outside_matches` is set, which
+/// makes the edges of the map seamless. Vacant tiles and ids outside the grid have the mask
+/// `0`.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::{Component, VecStorage};
+/// use specs_static::{autotile_mask, Autotile, AutotileMask, Connectivity, Grid2d};
+/// use specs_static::Storage;
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// #[derive(PartialEq)]
+/// enum Tile {
+///     Wall,
+///     Floor,
+/// }
+///
+/// impl Component for Tile {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// fn main() {
+///     // The blank tile is vacant.
+///     let map = [
+///         "##.#",
+///         "###.",
+///         ".#. ",
+///     ];
+///     let grid = Grid2d::new(4, 3);
+///     let mut tiles = Storage::<Tile, VecStorage<_>, TileId>::default();
+///     for (y, row) in map.iter().enumerate() {
+///         for (x, tile) in row.chars().enumerate() {
+///             let id = grid.id(x as u32, y as u32).unwrap();
+///             match tile {
+///                 '#' => tiles.insert(id, Tile::Wall),
+///                 '.' => tiles.insert(id, Tile::Floor),
+///                 _ => None,
+///             };
+///         }
+///     }
+///     let same = |a: &Tile, b: &Tile| a == b;
+///     let id = |x, y| grid.id::<TileId>(x, y).unwrap();
+///
+///     // up = 1, left = 2, right = 4, down = 8
+///     assert_eq!(autotile_mask(&grid, &tiles, id(0, 0), same), 4 | 8);
+///     assert_eq!(autotile_mask(&grid, &tiles, id(1, 0), same), 2 | 8);
+///     assert_eq!(autotile_mask(&grid, &tiles, id(1, 1), same), 1 | 2 | 4 | 8);
+///     assert_eq!(autotile_mask(&grid, &tiles, id(3, 0), same), 0);
+///     assert_eq!(autotile_mask(&grid, &tiles, id(2, 1), same), 2);
+///     // The tile below is vacant.
+///     assert_eq!(autotile_mask(&grid, &tiles, id(3, 1), same), 0);
+///     assert_eq!(autotile_mask(&grid, &tiles, id(3, 2), same), 0);
+///
+///     let seamless = Autotile::new(Connectivity::Four).outside_matches(true);
+///     assert_eq!(seamless.mask(&grid, &tiles, id(0, 0), same), 1 | 2 | 4 | 8);
+///     assert_eq!(seamless.mask(&grid, &tiles, id(3, 0), same), 1 | 4);
+///     assert_eq!(seamless.mask(&grid, &tiles, id(3, 1), same), 4);
+///
+///     // up left = 1, up = 2, up right = 4, left = 8, right = 16,
+///     // down left = 32, down = 64, down right = 128
+///     let eight = Autotile::new(Connectivity::Eight);
+///     assert_eq!(eight.mask(&grid, &tiles, id(1, 1), same), 1 | 2 | 8 | 16 | 64);
+///     assert_eq!(eight.mask(&grid, &tiles, id(0, 1), same), 2 | 4 | 16 | 128);
+///     let eight = eight.outside_matches(true);
+///     assert_eq!(eight.mask(&grid, &tiles, id(0, 1), same), 1 | 2 | 4 | 8 | 16 | 32 | 128);
+///
+///     // All masks at once.
+///     let mut masks = Storage::<AutotileMask, VecStorage<_>, TileId>::default();
+///     eight.fill_masks(&grid, &tiles, tiles.mask(), same, &mut masks);
+///     for y in 0..3 {
+///         for x in 0..4 {
+///             let expected = eight.mask(&grid, &tiles, id(x, y), same);
+///             let mask = masks.get(id(x, y)).map(|mask| mask.0);
+///             assert_eq!(mask, Some(expected).filter(|_| (x, y) != (3, 2)));
+///         }
+///     }
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Autotile {
+    connectivity: Connectivity,
+    outside_matches: bool,
+}
+
+impl Autotile {
+    /// Creates a computation of 4-bit masks for `Connectivity::Four` and 8-bit masks for
+    /// `Connectivity::Eight`.
+    pub fn new(connectivity: Connectivity) -> Self {
+        Autotile {
+            connectivity,
+            outside_matches: false,
+        }
+    }
+
+    /// Sets whether neighbors outside the grid count as the same kind of tile.
+    pub fn outside_matches(mut self, matches: bool) -> Self {
+        self.outside_matches = matches;
+
+        self
+    }
+
+    /// Computes the mask of `id`.
+    pub fn mask<C, D, I, F>(&self, grid: &Grid2d, storage: &Storage<C, D, I>, id: I, same: F) -> u8
+    where
+        D: UnprotectedStorage<C>,
+        I: Id,
+        F: Fn(&C, &C) -> bool,
+    {
+        match (grid.coords(id), storage.get(id)) {
+            (Some((x, y)), Some(center)) => self.compute(grid, storage, x, y, center, &same),
+            _ => 0,
+        }
+    }
+
+    /// Writes the mask of every id in `mask` to `out` in one pass.
+    ///
+    /// Vacant tiles are removed from `out`, ids outside the grid are left alone.
+    pub fn fill_masks<C, D, I, M, F, D2>(
+        &self,
+        grid: &Grid2d,
+        storage: &Storage<C, D, I>,
+        mask: M,
+        same: F,
+        out: &mut Storage<AutotileMask, D2, I>,
+    ) where
+        D: UnprotectedStorage<C>,
+        I: Id,
+        M: BitSetLike,
+        F: Fn(&C, &C) -> bool,
+        D2: UnprotectedStorage<AutotileMask>,
+    {
+        for index in mask.iter() {
+            let id = I::from_u32(index);
+            let (x, y) = match grid.coords(id) {
+                Some(coords) => coords,
+                None => continue,
+            };
+
+            match storage.get(id) {
+                Some(center) => {
+                    let bits = self.compute(grid, storage, x, y, center, &same);
+                    out.insert(id, AutotileMask(bits));
+                }
+                None => {
+                    out.remove(id);
+                }
+            }
+        }
+    }
+
+    fn compute<C, D, I, F>(
+        &self,
+        grid: &Grid2d,
+        storage: &Storage<C, D, I>,
+        x: u32,
+        y: u32,
+        center: &C,
+        same: &F,
+    ) -> u8
+    where
+        D: UnprotectedStorage<C>,
+        I: Id,
+        F: Fn(&C, &C) -> bool,
+    {
+        let offsets = match self.connectivity {
+            Connectivity::Four => &FOUR[..],
+            Connectivity::Eight => &EIGHT[..],
+        };
+
+        let mut bits = 0;
+        for (bit, &(dx, dy)) in offsets.iter().enumerate() {
+            let (nx, ny) = (i64::from(x) + dx, i64::from(y) + dy);
+            let neighbor = match nx < 0 || ny < 0 {
+                true => None,
+                false => grid.id::<I>(nx as u32, ny as u32),
+            };
+            let matches = match neighbor {
+                Some(id) => storage.get(id).is_some_and(|other| same(center, other)),
+                None => self.outside_matches,
+            };
+            if matches {
+                bits |= 1 << bit;
+            }
+        }
+
+        bits
+    }
+}
@@ -0,0 +1,231 @@
+//! Inserting and removing many components at once.
+
+use hibitset::{BitSet, BitSetAnd, BitSetLike};
+use specs::storage::UnprotectedStorage;
+
+use bound;
+use mask::PendingRun;
+use region;
+use {Id, Index, Storage};
+
+impl<C, D, I> Storage<C, D, I>
+where
+    D: UnprotectedStorage<C>,
+    I: Id,
+{
+    /// Removes the components of all ids in `mask`.
+    ///
+    /// The ids to remove are found by intersecting `mask` with the occupancy bitset layer by
+    /// layer, so regions where either of them is empty are skipped wholesale, and the bitset
+    /// is updated word-wise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate hibitset;
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use hibitset::{BitSet, BitSetLike, BitSetNot};
+    /// use specs::{Component, Join, VecStorage};
+    /// use specs_static::Storage;
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// #[derive(Debug, PartialEq)]
+    /// struct Grass(u32);
+    ///
+    /// impl Component for Grass {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn random_mask(seed: &mut u32) -> BitSet {
+    ///     let mut mask = BitSet::new();
+    ///     for _ in 0..2000 {
+    ///         *seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+    ///         mask.add(*seed % 100_000);
+    ///     }
+    ///     mask
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut seed = 7;
+    ///     for _ in 0..20 {
+    ///         let a = random_mask(&mut seed);
+    ///         let b = random_mask(&mut seed);
+    ///         let c = random_mask(&mut seed);
+    ///
+    ///         let mut grass = Storage::<Grass, VecStorage<_>, TileId>::default();
+    ///         let mut naive = Storage::<Grass, VecStorage<_>, TileId>::default();
+    ///         for id in &a {
+    ///             grass.insert(TileId(id), Grass(id));
+    ///             naive.insert(TileId(id), Grass(id));
+    ///         }
+    ///
+    ///         // Remove grass wherever there's a building.
+    ///         grass.remove_mask(&b);
+    ///         // Plant new grass wherever there's soil, but no building.
+    ///         grass.insert_mask_with(&c & BitSetNot(&b), |id| Grass(id.0 + 1));
+    ///
+    ///         for id in &b {
+    ///             naive.remove(TileId(id));
+    ///         }
+    ///         for id in &c {
+    ///             if !b.contains(id) {
+    ///                 naive.insert(TileId(id), Grass(id + 1));
+    ///             }
+    ///         }
+    ///
+    ///         let actual: Vec<_> = (grass.mask(), &grass).join().collect();
+    ///         let expected: Vec<_> = (naive.mask(), &naive).join().collect();
+    ///         assert_eq!(actual, expected);
+    ///     }
+    /// }
+    /// ```
+    pub fn remove_mask<B>(&mut self, mask: B)
+    where
+        B: BitSetLike,
+    {
+        let mut removed = BitSet::new();
+        removed |= &BitSetAnd(mask, &self.bitset);
+        // Unmark the ids first, so the bitset never claims a component which has been
+        // dropped, even if dropping one of them panics.
+        self.bitset ^= &removed;
+        self.epoch.bump();
+        self.stamps.stamp_all(&removed);
+
+        for id in &removed {
+            let comp = unsafe { self.data.remove(id) };
+            self.observers.removed(id, &comp);
+        }
+    }
+
+    /// Inserts `f(id)` at every id in `mask`, replacing existing components.
+    ///
+    /// Only the ids in `mask` are visited, and the occupancy bitset is updated once per run of
+    /// consecutive new ids. If `f` panics, the components inserted so far are kept.
+    ///
+    /// Panics without inserting anything if an id of `mask` exceeds the bound of the storage.
+    pub fn insert_mask_with<B, F>(&mut self, mask: B, mut f: F)
+    where
+        B: BitSetLike,
+        F: FnMut(I) -> C,
+    {
+        bound::expect_in_bounds(self.check_mask(&mask));
+        #[cfg(feature = "strict-ids")]
+        for (element, id) in (&mask).iter().enumerate() {
+            self.debug_check_id(id, Some(element));
+        }
+
+        self.epoch.bump();
+        self.stamps.stamp_all(&mask);
+        {
+            let mut run = PendingRun::new(&mut self.bitset);
+
+            for id in (&mask).iter() {
+                let comp = f(I::from_u32(id));
+
+                unsafe {
+                    if run.bitset.contains(id) {
+                        *self.data.get_mut(id) = comp;
+                    } else {
+                        self.data.insert(id, comp);
+                        run.push(id);
+                    }
+                }
+            }
+        }
+        self.notify_inserted(&mask);
+    }
+
+    /// Removes and drops at most `max_items` components, continuing where the previous call
+    /// stopped, and returns `true` once the storage has been cleared completely.
+    ///
+    /// Clearing goes through the ids in ascending order, so large layers can be cleared over
+    /// several frames. In between, the storage stays fully usable: the ids cleared so far are
+    /// vacant, the others keep their components. Components inserted below the ids cleared
+    /// so far are kept, while those inserted above them are removed when clearing gets
+    /// there. After returning `true`, the next call starts clearing all over again; `clear`
+    /// aborts a budgeted clear in progress.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, Join, VecStorage};
+    /// use specs_static::Storage;
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// #[derive(Debug, PartialEq)]
+    /// struct Tree(u32);
+    ///
+    /// impl Component for Tree {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut trees = Storage::<Tree, VecStorage<_>, TileId>::default();
+    ///     for id in 0..1000 {
+    ///         trees.insert(TileId(id * 2), Tree(id));
+    ///     }
+    ///
+    ///     assert!(!trees.clear_budgeted(300));
+    ///     assert_eq!(trees.get(TileId(598)), None);
+    ///     assert_eq!(trees.get(TileId(600)), Some(&Tree(300)));
+    ///     assert_eq!((&trees).join().count(), 700);
+    ///
+    ///     // Frames go on: ids behind the cursor stay, ids ahead of it will be cleared.
+    ///     trees.insert(TileId(1), Tree(10_000));
+    ///     trees.insert(TileId(601), Tree(20_000));
+    ///     trees.get_mut(TileId(1000)).unwrap().0 += 1;
+    ///
+    ///     let mut frames = 1;
+    ///     loop {
+    ///         frames += 1;
+    ///         if trees.clear_budgeted(300) {
+    ///             break;
+    ///         }
+    ///         assert!(trees.get(TileId(1)).is_some());
+    ///     }
+    ///     // 1001 components at or after the cursor, 300 per frame.
+    ///     assert_eq!(frames, 4);
+    ///     assert_eq!((&trees).join().collect::<Vec<_>>(), vec![&Tree(10_000)]);
+    ///
+    ///     // The next pass clears everything again.
+    ///     assert!(trees.clear_budgeted(300));
+    ///     assert_eq!((&trees).join().count(), 0);
+    /// }
+    /// ```
+    pub fn clear_budgeted(&mut self, max_items: u32) -> bool {
+        let mut cursor = self.clear_cursor.unwrap_or(0) as usize;
+        let mut removed = Vec::new();
+        while removed.len() < max_items as usize {
+            match region::next_set(&self.bitset, 0, cursor) {
+                Some(id) => {
+                    removed.push(id as Index);
+                    cursor = id + 1;
+                }
+                None => break,
+            }
+        }
+        let done = region::next_set(&self.bitset, 0, cursor).is_none();
+
+        self.clear_cursor = match done {
+            true => None,
+            false => Some(cursor as Index),
+        };
+        if !removed.is_empty() {
+            self.epoch.bump();
+        }
+        for id in removed {
+            // Unmarked one by one, so a panicking `Drop` leaves the storage consistent.
+            self.bitset.remove(id);
+            self.stamps.stamp(id);
+            let comp = unsafe { self.data.remove(id) };
+            self.observers.removed(id, &comp);
+        }
+
+        done
+    }
+}
@@ -0,0 +1,268 @@
+//! The occupancy bitsets of the storages.
+//!
+//! With the `specs` feature these are hibitset's, which Specs joins over. Without it, a
+//! minimal bitset of the same layout takes their place, which only needs `alloc`.
+
+#[cfg(feature = "specs")]
+pub use hibitset::{BitSet, BitSetLike};
+
+#[cfg(not(feature = "specs"))]
+pub use self::minimal::{BitSet, BitSetLike};
+
+#[cfg(not(feature = "specs"))]
+mod minimal {
+    use liballoc::vec::Vec;
+
+    use mask::BITS;
+    use Index;
+
+    const MASK: Index = (1 << BITS) - 1;
+
+    /// A set of ids stored in four layers of words, like hibitset's, where every bit of a
+    /// layer says whether the corresponding word of the layer below has any bits set.
+    pub trait BitSetLike {
+        /// Returns the single word of the top layer.
+        fn layer3(&self) -> usize;
+
+        /// Returns the word `i` of the second layer.
+        fn layer2(&self, i: usize) -> usize;
+
+        /// Returns the word `i` of the third layer.
+        fn layer1(&self, i: usize) -> usize;
+
+        /// Returns the word `i` of the bottom layer, whose bits are the ids.
+        fn layer0(&self, i: usize) -> usize;
+
+        /// Returns `true` if `id` is in the set.
+        fn contains(&self, id: Index) -> bool;
+
+        /// Returns `true` if the set is empty.
+        fn is_empty(&self) -> bool {
+            self.layer3() == 0
+        }
+
+        /// Returns an iterator over the ids in the set, in ascending order.
+        fn iter(self) -> BitIter<Self>
+        where
+            Self: Sized,
+        {
+            let layer3 = self.layer3();
+
+            BitIter {
+                set: self,
+                masks: [0, 0, 0, layer3],
+                prefix: [0; 3],
+            }
+        }
+    }
+
+    impl<T> BitSetLike for &T
+    where
+        T: BitSetLike + ?Sized,
+    {
+        fn layer3(&self) -> usize {
+            (**self).layer3()
+        }
+
+        fn layer2(&self, i: usize) -> usize {
+            (**self).layer2(i)
+        }
+
+        fn layer1(&self, i: usize) -> usize {
+            (**self).layer1(i)
+        }
+
+        fn layer0(&self, i: usize) -> usize {
+            (**self).layer0(i)
+        }
+
+        fn contains(&self, id: Index) -> bool {
+            (**self).contains(id)
+        }
+    }
+
+    /// The iterator returned by `BitSetLike::iter`.
+    #[derive(Clone, Debug)]
+    pub struct BitIter<T> {
+        set: T,
+        masks: [usize; 4],
+        prefix: [u32; 3],
+    }
+
+    impl<T> BitIter<T>
+    where
+        T: BitSetLike,
+    {
+        fn word(&self, layer: usize, i: usize) -> usize {
+            match layer {
+                0 => self.set.layer0(i),
+                1 => self.set.layer1(i),
+                2 => self.set.layer2(i),
+                _ => self.set.layer3(),
+            }
+        }
+    }
+
+    impl<T> Iterator for BitIter<T>
+    where
+        T: BitSetLike,
+    {
+        type Item = Index;
+
+        fn next(&mut self) -> Option<Index> {
+            // Takes the lowest set bit of the lowest non-empty layer, and descends into the
+            // word it stands for until reaching an id of the bottom layer.
+            let mut layer = 0;
+            while layer < 4 {
+                if self.masks[layer] == 0 {
+                    layer += 1;
+                    continue;
+                }
+
+                let bit = self.masks[layer].trailing_zeros();
+                self.masks[layer] &= self.masks[layer] - 1;
+                let index = self.prefix.get(layer).cloned().unwrap_or(0) | bit;
+                if layer == 0 {
+                    return Some(index);
+                }
+
+                self.masks[layer - 1] = self.word(layer - 1, index as usize);
+                self.prefix[layer - 1] = index << BITS;
+                layer = 0;
+            }
+
+            None
+        }
+    }
+
+    /// A growable set of ids.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs_static;
+    ///
+    /// use specs_static::{BitSet, BitSetLike};
+    ///
+    /// fn main() {
+    ///     // Ids at the edges of the words of every layer.
+    ///     let ids = [0, 1, 63, 64, 4095, 4096, 262_143, 262_144, 16_777_215];
+    ///     let mut set = BitSet::new();
+    ///     for &id in &ids {
+    ///         assert!(!set.add(id));
+    ///     }
+    ///     assert!(set.add(64));
+    ///     assert_eq!((&set).iter().collect::<Vec<_>>(), ids);
+    ///     assert!(set.contains(262_143) && !set.contains(262_142));
+    ///
+    ///     assert!(set.remove(4096));
+    ///     assert!(!set.remove(4096));
+    ///     assert!(!set.remove(4097));
+    ///     assert_eq!((&set).iter().nth(5), Some(262_143));
+    ///
+    ///     for &id in &ids {
+    ///         set.remove(id);
+    ///     }
+    ///     assert!(set.is_empty());
+    ///     assert_eq!((&set).iter().next(), None);
+    /// }
+    /// ```
+    #[derive(Clone, Debug, Default)]
+    pub struct BitSet {
+        layer3: usize,
+        layer2: Vec<usize>,
+        layer1: Vec<usize>,
+        layer0: Vec<usize>,
+    }
+
+    impl BitSet {
+        /// Creates an empty set.
+        pub fn new() -> Self {
+            BitSet::default()
+        }
+
+        /// Adds `id` to the set, returning `true` if it already was in it.
+        pub fn add(&mut self, id: Index) -> bool {
+            let word = (id >> BITS) as usize;
+            if word >= self.layer0.len() {
+                self.layer0.resize(word + 1, 0);
+                self.layer1.resize((id >> (2 * BITS)) as usize + 1, 0);
+                self.layer2.resize((id >> (3 * BITS)) as usize + 1, 0);
+            }
+
+            let bit = 1 << (id & MASK);
+            if self.layer0[word] & bit != 0 {
+                return true;
+            }
+
+            self.layer0[word] |= bit;
+            self.layer1[(id >> (2 * BITS)) as usize] |= 1 << ((id >> BITS) & MASK);
+            self.layer2[(id >> (3 * BITS)) as usize] |= 1 << ((id >> (2 * BITS)) & MASK);
+            self.layer3 |= 1 << ((id >> (3 * BITS)) & MASK);
+
+            false
+        }
+
+        /// Removes `id` from the set, returning `true` if it was in it.
+        pub fn remove(&mut self, id: Index) -> bool {
+            if !self.contains(id) {
+                return false;
+            }
+
+            // Clears the bit of every layer whose word below has become empty.
+            let word = (id >> BITS) as usize;
+            self.layer0[word] &= !(1 << (id & MASK));
+            if self.layer0[word] != 0 {
+                return true;
+            }
+
+            let word = (id >> (2 * BITS)) as usize;
+            self.layer1[word] &= !(1 << ((id >> BITS) & MASK));
+            if self.layer1[word] != 0 {
+                return true;
+            }
+
+            let word = (id >> (3 * BITS)) as usize;
+            self.layer2[word] &= !(1 << ((id >> (2 * BITS)) & MASK));
+            if self.layer2[word] != 0 {
+                return true;
+            }
+
+            self.layer3 &= !(1 << ((id >> (3 * BITS)) & MASK));
+
+            true
+        }
+
+        /// Returns `true` if `id` is in the set.
+        pub fn contains(&self, id: Index) -> bool {
+            self.layer0(id as usize >> BITS) & (1 << (id & MASK)) != 0
+        }
+
+        /// Removes every id from the set.
+        pub fn clear(&mut self) {
+            *self = BitSet::new();
+        }
+    }
+
+    impl BitSetLike for BitSet {
+        fn layer3(&self) -> usize {
+            self.layer3
+        }
+
+        fn layer2(&self, i: usize) -> usize {
+            self.layer2.get(i).cloned().unwrap_or(0)
+        }
+
+        fn layer1(&self, i: usize) -> usize {
+            self.layer1.get(i).cloned().unwrap_or(0)
+        }
+
+        fn layer0(&self, i: usize) -> usize {
+            self.layer0.get(i).cloned().unwrap_or(0)
+        }
+
+        fn contains(&self, id: Index) -> bool {
+            BitSet::contains(self, id)
+        }
+    }
+}
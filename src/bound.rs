@@ -0,0 +1,309 @@
+//! Rejecting ids beyond a configured maximum before they reach the backend.
+
+use std::error::Error;
+use std::fmt;
+
+#[cfg(feature = "specs")]
+use hibitset::{BitSetAnd, BitSetLike, BitSetNot};
+
+#[cfg(feature = "specs")]
+use fill::CopyFill;
+use mask::MAX_LEN;
+#[cfg(feature = "specs")]
+use RangeMask;
+use {Id, Index, RawStorage, Storage};
+
+/// The highest id the bitsets of the storages can hold.
+pub(crate) const MAX_ID: Index = (MAX_LEN - 1) as Index;
+
+/// The error returned when an id exceeds the bound of a storage.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct IdOutOfBounds {
+    /// The rejected id.
+    pub id: u32,
+    /// The highest id the storage accepts.
+    pub bound: u32,
+}
+
+impl fmt::Display for IdOutOfBounds {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "id {} exceeds the bound {} of the storage", self.id, self.bound)
+    }
+}
+
+impl Error for IdOutOfBounds {}
+
+impl<C, D, I> Storage<C, D, I>
+where
+    D: RawStorage<C>,
+    I: Id,
+{
+    /// The highest id any storage accepts, given by the size of the occupancy bitset:
+    /// 2^24 - 1 on 64-bit targets and 2^20 - 1 on 32-bit ones.
+    ///
+    /// Ids above it are treated like ids exceeding a bound set with `with_bound`: the `try_`
+    /// insertions return an `IdOutOfBounds` error with `MAX_ID` as the bound, the plain ones
+    /// panic with that error, and `get`, `contains` and `remove` report them as vacant. This
+    /// holds for every backend, including ones like `HashMapStorage` for which large ids
+    /// would otherwise be no problem.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, HashMapStorage};
+    /// use specs_static::{IdOutOfBounds, Storage};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// #[derive(Clone, Debug, PartialEq)]
+    /// struct Beacon(u8);
+    ///
+    /// impl Component for Beacon {
+    ///     type Storage = HashMapStorage<Self>;
+    /// }
+    ///
+    /// type Beacons = Storage<Beacon, HashMapStorage<Beacon>, TileId>;
+    ///
+    /// fn main() {
+    ///     let max = Beacons::MAX_ID;
+    ///     let mut beacons = Beacons::default();
+    ///
+    ///     assert_eq!(beacons.try_insert(TileId(max), Beacon(1)), Ok(None));
+    ///     assert_eq!(beacons.get(TileId(max)), Some(&Beacon(1)));
+    ///     assert!(beacons.contains(TileId(max)));
+    ///
+    ///     for &id in &[max + 1, u32::MAX] {
+    ///         let error = IdOutOfBounds { id, bound: max };
+    ///         assert_eq!(beacons.try_insert(TileId(id), Beacon(2)), Err(error));
+    ///         assert_eq!(beacons.get(TileId(id)), None);
+    ///         assert!(!beacons.contains(TileId(id)));
+    ///         assert_eq!(beacons.remove(TileId(id)), None);
+    ///     }
+    ///     assert_eq!(beacons.try_fill_range(TileId(max), TileId(max + 1), Beacon(3)), Ok(()));
+    ///     assert_eq!(
+    ///         beacons.try_fill_range(TileId(max - 1), TileId(u32::MAX), Beacon(4)),
+    ///         Err(IdOutOfBounds { id: max + 1, bound: max })
+    ///     );
+    ///
+    ///     // A bound above the maximum doesn't lift it.
+    ///     let mut beacons = Beacons::with_bound(u32::MAX);
+    ///     assert_eq!(beacons.try_insert(TileId(max + 1), Beacon(5)).unwrap_err().bound, max);
+    ///     assert_eq!(beacons.remove(TileId(max)), None);
+    /// }
+    /// ```
+    ///
+    /// ```should_panic
+    /// # extern crate specs;
+    /// # extern crate specs_static;
+    /// # use specs::{Component, HashMapStorage};
+    /// # use specs_static::Storage;
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// # struct Beacon(u8);
+    /// # impl Component for Beacon {
+    /// #     type Storage = HashMapStorage<Self>;
+    /// # }
+    /// # fn main() {
+    /// let mut beacons = Storage::<Beacon, HashMapStorage<_>, TileId>::default();
+    /// // Panics: id 4294967295 exceeds the bound 16777215 of the storage.
+    /// beacons.insert(TileId(u32::MAX), Beacon(1));
+    /// # }
+    /// ```
+    pub const MAX_ID: u32 = MAX_ID;
+
+    /// Creates an empty storage which only accepts ids up to and including `max_id`.
+    ///
+    /// A single stray id can otherwise make a `VecStorage`-like backend allocate gigabytes.
+    /// With a bound, the `try_` variants of `insert` and the bulk operations return an
+    /// `IdOutOfBounds` error instead of touching the backend, and the plain variants panic.
+    /// `get` and `remove` simply return `None` for such ids.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, Join, VecStorage};
+    /// use specs_static::{IdOutOfBounds, RangeMask, Storage};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// #[derive(Clone, Debug, PartialEq)]
+    /// struct Crop(u8);
+    ///
+    /// impl Component for Crop {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut crops = Storage::<Crop, VecStorage<_>, TileId>::with_bound(999);
+    ///     assert_eq!(crops.bound(), Some(999));
+    ///
+    ///     assert_eq!(crops.try_insert(TileId(999), Crop(1)), Ok(None));
+    ///     assert_eq!(
+    ///         crops.try_insert(TileId(1000), Crop(2)),
+    ///         Err(IdOutOfBounds { id: 1000, bound: 999 })
+    ///     );
+    ///     assert_eq!(
+    ///         crops.try_insert(TileId(3_000_000_000), Crop(3)),
+    ///         Err(IdOutOfBounds { id: 3_000_000_000, bound: 999 })
+    ///     );
+    ///     assert_eq!(crops.get(TileId(3_000_000_000)), None);
+    ///     assert_eq!(crops.remove(TileId(3_000_000_000)), None);
+    ///
+    ///     // Bulk operations are atomic: if any id is out of bounds, nothing is inserted.
+    ///     assert_eq!(
+    ///         crops.try_fill_range(TileId(990), TileId(1010), Crop(4)),
+    ///         Err(IdOutOfBounds { id: 1000, bound: 999 })
+    ///     );
+    ///     assert_eq!(
+    ///         crops.try_insert_mask_with(RangeMask::from_range(500, 2000), |_| Crop(5)),
+    ///         Err(IdOutOfBounds { id: 1000, bound: 999 })
+    ///     );
+    ///     assert_eq!((&crops).join().collect::<Vec<_>>(), vec![&Crop(1)]);
+    ///
+    ///     assert_eq!(crops.try_fill_range(TileId(990), TileId(1000), Crop(4)), Ok(()));
+    ///     assert_eq!((&crops).join().count(), 10);
+    /// }
+    /// ```
+    pub fn with_bound(max_id: u32) -> Self
+    where
+        D: Default,
+    {
+        let mut storage = Self::default();
+        storage.bound = Some(max_id);
+
+        storage
+    }
+
+    /// Returns the highest id this storage accepts, if it has a bound.
+    ///
+    /// Ids above `MAX_ID` are rejected either way.
+    pub fn bound(&self) -> Option<u32> {
+        self.bound
+    }
+
+    /// Sets the highest id this storage accepts, or removes the bound.
+    ///
+    /// Components which are already inserted above the new bound are kept.
+    pub fn set_bound(&mut self, max_id: Option<u32>) {
+        self.bound = max_id;
+    }
+
+    /// Inserts `comp` at `id` like `insert`, but returns an error if `id` exceeds the bound.
+    pub fn try_insert(&mut self, id: I, comp: C) -> Result<Option<C>, IdOutOfBounds> {
+        self.check_bound(id.id())?;
+
+        Ok(self.insert(id, comp))
+    }
+
+    /// Inserts a clone of `comp` at every id in `start..end` like `fill_range`, but returns
+    /// an error without inserting anything if any of these ids exceeds the bound.
+    #[cfg(feature = "specs")]
+    pub fn try_fill_range(&mut self, start: I, end: I, comp: C) -> Result<(), IdOutOfBounds>
+    where
+        C: Clone,
+    {
+        self.check_range(start.id(), end.id())?;
+        self.fill_range(start, end, comp);
+
+        Ok(())
+    }
+
+    /// Inserts `comp` at every id in `start..end` like `fill_range_copy`, but returns an
+    /// error without inserting anything if any of these ids exceeds the bound.
+    #[cfg(feature = "specs")]
+    pub fn try_fill_range_copy(&mut self, start: I, end: I, comp: C) -> Result<(), IdOutOfBounds>
+    where
+        C: Copy,
+        D: CopyFill<C>,
+    {
+        self.check_range(start.id(), end.id())?;
+        self.fill_range_copy(start, end, comp);
+
+        Ok(())
+    }
+
+    /// Inserts `f(id)` at every id in `mask` like `insert_mask_with`, but returns an error
+    /// without inserting anything if any id of `mask` exceeds the bound.
+    ///
+    /// The error reports the lowest offending id.
+    #[cfg(feature = "specs")]
+    pub fn try_insert_mask_with<B, F>(&mut self, mask: B, f: F) -> Result<(), IdOutOfBounds>
+    where
+        B: BitSetLike,
+        F: FnMut(I) -> C,
+    {
+        self.check_mask(&mask)?;
+        self.insert_mask_with(mask, f);
+
+        Ok(())
+    }
+}
+
+// Unlike the checked accessors above, these don't need `C: Component`, which the `!Send`
+// components of a `LocalStorage` can't implement.
+impl<C, D, I> Storage<C, D, I>
+where
+    D: RawStorage<C>,
+{
+    /// Returns the bound of the storage, or `MAX_ID` if it's lower.
+    fn effective_bound(&self) -> Index {
+        self.bound.map_or(MAX_ID, |bound| bound.min(MAX_ID))
+    }
+
+    pub(crate) fn check_bound(&self, id: Index) -> Result<(), IdOutOfBounds> {
+        let bound = self.effective_bound();
+        match id > bound {
+            true => Err(IdOutOfBounds { id, bound }),
+            false => Ok(()),
+        }
+    }
+
+    #[cfg(feature = "specs")]
+    pub(crate) fn check_range(&self, start: Index, end: Index) -> Result<(), IdOutOfBounds> {
+        let bound = self.effective_bound();
+        match start < end && end - 1 > bound {
+            true => Err(IdOutOfBounds {
+                id: start.max(bound + 1),
+                bound,
+            }),
+            false => Ok(()),
+        }
+    }
+
+    #[cfg(feature = "specs")]
+    pub(crate) fn check_mask<B>(&self, mask: B) -> Result<(), IdOutOfBounds>
+    where
+        B: BitSetLike,
+    {
+        // Masks can't contain ids beyond `MAX_ID`, so such a bound rejects nothing.
+        let bound = match self.effective_bound() {
+            MAX_ID => return Ok(()),
+            bound => bound,
+        };
+        let allowed = RangeMask::from_range(0, bound + 1);
+
+        match BitSetAnd(mask, BitSetNot(allowed)).iter().next() {
+            Some(id) => Err(IdOutOfBounds { id, bound }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Unwraps the result of a bound check, panicking with the error otherwise.
+pub(crate) fn expect_in_bounds(result: Result<(), IdOutOfBounds>) {
+    if let Err(e) = result {
+        panic!("{}", e);
+    }
+}
+
+/// Panics if `id` exceeds `MAX_ID`, for the storages which aren't backends of `Storage` and
+/// have to check before touching their state.
+#[cfg(feature = "specs")]
+pub(crate) fn expect_max_id(id: Index) {
+    if id > MAX_ID {
+        expect_in_bounds(Err(IdOutOfBounds { id, bound: MAX_ID }));
+    }
+}
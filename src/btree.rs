@@ -0,0 +1,133 @@
+//! A backend keeping its components ordered by id.
+
+use std::collections::btree_map::{self, BTreeMap};
+use std::marker::PhantomData;
+use std::ops::Bound;
+
+use hibitset::BitSetLike;
+use specs::storage::UnprotectedStorage;
+
+use {Id, Index, ReserveIds, Storage};
+
+/// A storage backend keeping the components in a `BTreeMap`, ordered by their ids.
+///
+/// Like `HashMapStorage`, this only allocates memory for occupied ids, which suits sparse
+/// layers like points of interest. Choose it over `HashMapStorage` if you need the
+/// components in id order without going through the bitset, for example to find the next
+/// occupied id after a given one with `next_occupied_after` or to walk a range of ids with
+/// `iter_from`, or if an iteration order which doesn't depend on hashing matters for
+/// deterministic replays. Lookups are logarithmic instead of constant, though, so
+/// `HashMapStorage` is faster for random access.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::{Component, Join};
+/// use specs_static::{BTreeStorage, Storage};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// #[derive(Debug, PartialEq)]
+/// struct Waypoint(&'static str);
+///
+/// impl Component for Waypoint {
+///     type Storage = BTreeStorage<Self>;
+/// }
+///
+/// fn main() {
+///     let mut waypoints = Storage::<Waypoint, BTreeStorage<_>, TileId>::default();
+///     waypoints.insert(TileId(900), Waypoint("castle"));
+///     waypoints.insert(TileId(12), Waypoint("village"));
+///     waypoints.insert(TileId(4_000), Waypoint("harbour"));
+///     waypoints.insert(TileId(300), Waypoint("bridge"));
+///     waypoints.remove(TileId(300));
+///
+///     assert_eq!(waypoints.next_occupied_after(TileId(12)), Some(TileId(900)));
+///     assert_eq!(waypoints.next_occupied_after(TileId(13)), Some(TileId(900)));
+///     assert_eq!(waypoints.next_occupied_after(TileId(4_000)), None);
+///
+///     let from: Vec<_> = waypoints.iter_from(TileId(900)).map(|(id, w)| (id.0, w.0)).collect();
+///     assert_eq!(from, vec![(900, "castle"), (4_000, "harbour")]);
+///
+///     // Joins go through the bitset as usual.
+///     let all: Vec<_> = (&waypoints).join().map(|w| w.0).collect();
+///     assert_eq!(all, vec!["village", "castle", "harbour"]);
+/// }
+/// ```
+#[derive(Derivative)]
+#[derivative(Default(bound = ""))]
+pub struct BTreeStorage<C>(BTreeMap<Index, C>);
+
+// Not `DistinctStorage`: `get_mut` borrows the whole map mutably, which a mutable parallel
+// join would do for several ids at the same time.
+impl<C> UnprotectedStorage<C> for BTreeStorage<C> {
+    unsafe fn clean<B>(&mut self, _has: B)
+    where
+        B: BitSetLike,
+    {
+        self.0.clear();
+    }
+
+    unsafe fn get(&self, id: Index) -> &C {
+        &self.0[&id]
+    }
+
+    unsafe fn get_mut(&mut self, id: Index) -> &mut C {
+        self.0.get_mut(&id).expect("the bitset says the id is occupied")
+    }
+
+    unsafe fn insert(&mut self, id: Index, value: C) {
+        self.0.insert(id, value);
+    }
+
+    unsafe fn remove(&mut self, id: Index) -> C {
+        self.0.remove(&id).expect("the bitset says the id is occupied")
+    }
+}
+
+/// A `BTreeMap` can't reserve, so this does nothing.
+impl<C> ReserveIds for BTreeStorage<C> {
+    fn reserve_ids(&mut self, _: u32) {}
+}
+
+impl<C, I> Storage<C, BTreeStorage<C>, I>
+where
+    I: Id,
+{
+    /// Returns the lowest occupied id which is greater than `id`.
+    pub fn next_occupied_after(&self, id: I) -> Option<I> {
+        self.data
+            .0
+            .range((Bound::Excluded(id.id()), Bound::Unbounded))
+            .next()
+            .map(|(&id, _)| I::from_u32(id))
+    }
+
+    /// Yields the occupied ids starting at `id` together with their components, in
+    /// ascending order.
+    pub fn iter_from<'a>(&'a self, id: I) -> IterFrom<'a, C, I> {
+        IterFrom {
+            inner: self.data.0.range(id.id()..),
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// The iterator returned by `Storage::iter_from`.
+pub struct IterFrom<'a, C: 'a, I> {
+    inner: btree_map::Range<'a, Index, C>,
+    phantom: PhantomData<I>,
+}
+
+impl<'a, C, I> Iterator for IterFrom<'a, C, I>
+where
+    I: Id,
+{
+    type Item = (I, &'a C);
+
+    fn next(&mut self) -> Option<(I, &'a C)> {
+        self.inner.next().map(|(&id, comp)| (I::from_u32(id), comp))
+    }
+}
@@ -0,0 +1,237 @@
+//! Describing the initial content of a tile map declaratively.
+
+use std::marker::PhantomData;
+
+use specs::{Component, World};
+
+use {Grid2d, Id, ReserveIds, Storage, TileWorldExt};
+
+type Op = Box<dyn FnOnce(&mut World)>;
+
+/// Records insertions into tile storages and applies them to a `World` at once, which gives
+/// scenario maps and test fixtures a readable syntax.
+///
+/// The operations are applied in the order they have been recorded, so later operations
+/// override what earlier ones inserted at the same tiles. `fill` and `rect` use the bulk
+/// `fill_range` path of the storages. Components which haven't been registered yet are
+/// registered when the builder is applied.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::{Component, Join, VecStorage, World, WorldExt};
+/// use specs_static::{Grid2d, Storage, TileMapBuilder};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// #[derive(Clone, Debug, PartialEq)]
+/// enum Material {
+///     Dirt,
+///     Grass,
+///     Water,
+/// }
+///
+/// impl Component for Material {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// #[derive(Clone, Debug, PartialEq)]
+/// struct Tree;
+///
+/// impl Component for Tree {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// fn main() {
+///     let grid = Grid2d::new(8, 4);
+///     let mut world = World::new();
+///
+///     TileMapBuilder::<TileId>::new(grid)
+///         .fill(Material::Dirt)
+///         .rect(2, 1, 6, 3, Material::Grass)
+///         .set(3, 2, Material::Water)
+///         .set_many(vec![(4, 1, Tree), (5, 2, Tree)])
+///         // Overrides part of the grass again.
+///         .rect(5, 0, 8, 4, Material::Dirt)
+///         .apply(&mut world);
+///
+///     let materials = world.read_resource::<Storage<Material, VecStorage<_>, TileId>>();
+///     let trees = world.read_resource::<Storage<Tree, VecStorage<_>, TileId>>();
+///     let at = |x, y| materials.get(grid.id(x, y).unwrap()).cloned();
+///
+///     assert_eq!((&*materials).join().count(), 32);
+///     assert_eq!(at(0, 0), Some(Material::Dirt));
+///     assert_eq!(at(2, 1), Some(Material::Grass));
+///     assert_eq!(at(4, 2), Some(Material::Grass));
+///     assert_eq!(at(3, 2), Some(Material::Water));
+///     assert_eq!(at(5, 2), Some(Material::Dirt));
+///     assert_eq!(at(6, 1), Some(Material::Dirt));
+///     assert_eq!(at(2, 3), Some(Material::Dirt));
+///
+///     assert_eq!((&*trees).join().count(), 2);
+///     assert_eq!(trees.get(grid.id(5, 2).unwrap()), Some(&Tree));
+/// }
+/// ```
+pub struct TileMapBuilder<I> {
+    grid: Grid2d,
+    ops: Vec<Op>,
+    phantom: PhantomData<I>,
+}
+
+impl<I> TileMapBuilder<I>
+where
+    I: Id,
+{
+    /// Creates a builder for a map of the dimensions of `grid`.
+    pub fn new(grid: Grid2d) -> Self {
+        TileMapBuilder {
+            grid,
+            ops: Vec::new(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns the grid the coordinates are checked against.
+    pub fn grid(&self) -> Grid2d {
+        self.grid
+    }
+
+    /// Inserts a clone of `comp` at every tile of the grid.
+    pub fn fill<C>(self, comp: C) -> Self
+    where
+        C: Component + Clone + Send + Sync,
+        C::Storage: Default + ReserveIds + Send + Sync,
+    {
+        let len = self.grid.len();
+
+        self.op(move |storage: &mut Storage<C, C::Storage, I>| {
+            storage.fill_range(I::from_u32(0), I::from_u32(len), comp);
+        })
+    }
+
+    /// Inserts `comp` at `(x, y)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `(x, y)` is outside the grid.
+    ///
+    /// ```should_panic
+    /// # extern crate specs;
+    /// # extern crate specs_static;
+    /// # use specs::{Component, VecStorage};
+    /// # use specs_static::{Grid2d, TileMapBuilder};
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// struct Lava;
+    ///
+    /// impl Component for Lava {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// # fn main() {
+    /// TileMapBuilder::<TileId>::new(Grid2d::new(8, 8)).set(8, 0, Lava);
+    /// # }
+    /// ```
+    pub fn set<C>(self, x: u32, y: u32, comp: C) -> Self
+    where
+        C: Component + Send + Sync,
+        C::Storage: Default + ReserveIds + Send + Sync,
+    {
+        let id = self.id(x, y);
+
+        self.op(move |storage: &mut Storage<C, C::Storage, I>| {
+            storage.insert(id, comp);
+        })
+    }
+
+    /// Inserts a clone of `comp` at every tile with `x0 <= x < x1` and `y0 <= y < y1`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the rectangle doesn't fit into the grid.
+    pub fn rect<C>(self, x0: u32, y0: u32, x1: u32, y1: u32, comp: C) -> Self
+    where
+        C: Component + Clone + Send + Sync,
+        C::Storage: Default + ReserveIds + Send + Sync,
+    {
+        assert!(
+            x0 <= x1 && y0 <= y1 && x1 <= self.grid.width() && y1 <= self.grid.height(),
+            "the rectangle ({}, {})..({}, {}) doesn't fit into the {}x{} grid",
+            x0,
+            y0,
+            x1,
+            y1,
+            self.grid.width(),
+            self.grid.height()
+        );
+        let width = self.grid.width();
+
+        self.op(move |storage: &mut Storage<C, C::Storage, I>| {
+            for y in y0..y1 {
+                let row = y * width;
+                storage.fill_range(I::from_u32(row + x0), I::from_u32(row + x1), comp.clone());
+            }
+        })
+    }
+
+    /// Inserts every `(x, y, comp)` of `iter`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if one of the coordinates is outside the grid.
+    pub fn set_many<C, T>(self, iter: T) -> Self
+    where
+        C: Component + Send + Sync,
+        C::Storage: Default + ReserveIds + Send + Sync,
+        T: IntoIterator<Item = (u32, u32, C)>,
+    {
+        let comps: Vec<_> = iter
+            .into_iter()
+            .map(|(x, y, comp)| (self.id(x, y), comp))
+            .collect();
+
+        self.op(move |storage: &mut Storage<C, C::Storage, I>| {
+            for (id, comp) in comps {
+                storage.insert(id, comp);
+            }
+        })
+    }
+
+    /// Applies all operations to the storages of `world`, in the order they have been
+    /// recorded.
+    pub fn apply(self, world: &mut World) {
+        for op in self.ops {
+            op(world);
+        }
+    }
+
+    fn id(&self, x: u32, y: u32) -> I {
+        match self.grid.id(x, y) {
+            Some(id) => id,
+            None => panic!(
+                "({}, {}) is outside of the {}x{} grid",
+                x,
+                y,
+                self.grid.width(),
+                self.grid.height()
+            ),
+        }
+    }
+
+    fn op<C, F>(mut self, f: F) -> Self
+    where
+        C: Component + Send + Sync,
+        C::Storage: Default + ReserveIds + Send + Sync,
+        F: FnOnce(&mut Storage<C, C::Storage, I>) + 'static,
+    {
+        self.ops.push(Box::new(move |world: &mut World| {
+            if !world.has_value::<Storage<C, C::Storage, I>>() {
+                world.register_tile_comp::<C, I>();
+            }
+            f(&mut world.fetch_mut::<Storage<C, C::Storage, I>>());
+        }));
+
+        self
+    }
+}
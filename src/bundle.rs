@@ -0,0 +1,169 @@
+//! Inserting the components of a tile into several storages at once.
+
+use shred::World;
+use specs::storage::UnprotectedStorage;
+use specs::Component;
+
+use fetch;
+use {Id, Storage};
+
+/// Components which are inserted at an id together, each into its own tile storage.
+///
+/// This is implemented for tuples of up to eight components, and `tile_bundle!` implements
+/// it for structs by inserting their fields as a tuple. `T` is where the components go:
+///
+/// * a `&World`, in which case the storages `Storage<C, C::Storage, I>` are fetched one after
+///   the other, panicking like `WriteTiles` if one hasn't been registered;
+/// * a tuple of `&mut Storage`s in the order of the components, for systems which already
+///   hold the storages, for example through `WriteTiles`.
+///
+/// Every component replaces the one of its storage at the id, and the replaced ones are
+/// returned in the same order, each of them `None` if the storage had nothing there.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::{Component, RunNow, System, VecStorage, World, WorldExt};
+/// use specs_static::prelude::*;
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// #[derive(Debug, PartialEq)]
+/// enum Material {
+///     Grass,
+///     Tree,
+/// }
+///
+/// impl Component for Material {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Flammable(f32);
+///
+/// impl Component for Flammable {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Blocking;
+///
+/// impl Component for Blocking {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// fn tree() -> (Material, Flammable, Blocking) {
+///     (Material::Tree, Flammable(0.8), Blocking)
+/// }
+///
+/// struct Plant(TileId);
+///
+/// impl<'a> System<'a> for Plant {
+///     type SystemData = (
+///         WriteTiles<'a, Material, TileId>,
+///         WriteTiles<'a, Flammable, TileId>,
+///         WriteTiles<'a, Blocking, TileId>,
+///     );
+///
+///     fn run(&mut self, (mut materials, mut flammables, mut blocking): Self::SystemData) {
+///         let storages = (&mut *materials, &mut *flammables, &mut *blocking);
+///         assert_eq!(tree().insert_into(storages, self.0), (None, None, None));
+///     }
+/// }
+///
+/// fn main() {
+///     let mut world = World::new();
+///     world.register_tile_comp::<Material, TileId>();
+///     world.register_tile_comp::<Flammable, TileId>();
+///     world.register_tile_comp::<Blocking, TileId>();
+///
+///     // Grass which burns a little, but doesn't block.
+///     (Material::Grass, Flammable(0.2)).insert_into(&world, TileId(4));
+///
+///     assert_eq!(
+///         tree().insert_into(&world, TileId(4)),
+///         (Some(Material::Grass), Some(Flammable(0.2)), None)
+///     );
+///     assert_eq!(
+///         tree().insert_into(&world, TileId(4)),
+///         (Some(Material::Tree), Some(Flammable(0.8)), Some(Blocking))
+///     );
+///
+///     Plant(TileId(5)).run_now(&world);
+///     let materials = world.read_resource::<Storage<Material, VecStorage<_>, TileId>>();
+///     let flammables = world.read_resource::<Storage<Flammable, VecStorage<_>, TileId>>();
+///     let blocking = world.read_resource::<Storage<Blocking, VecStorage<_>, TileId>>();
+///     for &id in &[TileId(4), TileId(5)] {
+///         assert_eq!(
+///             (&*materials, &*flammables, &*blocking).get_all(id),
+///             Some((&Material::Tree, &Flammable(0.8), &Blocking))
+///         );
+///     }
+/// }
+/// ```
+pub trait TileBundle<T, I>: Sized {
+    /// The replaced components.
+    type Old;
+
+    /// Inserts every component into its storage of `target` at `id`, returning the
+    /// components they replace.
+    fn insert_into(self, target: T, id: I) -> Self::Old;
+}
+
+macro_rules! impl_tile_bundle {
+    ($($name:ident $backend:ident),*) => {
+        impl<'a, I, $($name),*> TileBundle<&'a World, I> for ($($name,)*)
+        where
+            I: Id,
+            $($name: Component + Send + Sync,)*
+        {
+            type Old = ($(Option<$name>,)*);
+
+            #[allow(non_snake_case)]
+            fn insert_into(self, world: &'a World, id: I) -> Self::Old {
+                let ($($name,)*) = self;
+
+                ($(
+                    {
+                        fetch::check_registered::<$name, I>(world);
+                        world
+                            .fetch_mut::<Storage<$name, $name::Storage, I>>()
+                            .insert(id, $name)
+                    },
+                )*)
+            }
+        }
+
+        impl<'a, I, $($name, $backend),*>
+            TileBundle<($(&'a mut Storage<$name, $backend, I>,)*), I> for ($($name,)*)
+        where
+            I: Id,
+            $($backend: UnprotectedStorage<$name>,)*
+        {
+            type Old = ($(Option<$name>,)*);
+
+            #[allow(non_snake_case)]
+            fn insert_into(
+                self,
+                storages: ($(&'a mut Storage<$name, $backend, I>,)*),
+                id: I,
+            ) -> Self::Old {
+                let ($($name,)*) = self;
+                let ($($backend,)*) = storages;
+
+                ($($backend.insert(id, $name),)*)
+            }
+        }
+    };
+}
+
+impl_tile_bundle!(A DA);
+impl_tile_bundle!(A DA, B DB);
+impl_tile_bundle!(A DA, B DB, C DC);
+impl_tile_bundle!(A DA, B DB, C DC, D DD);
+impl_tile_bundle!(A DA, B DB, C DC, D DD, E DE);
+impl_tile_bundle!(A DA, B DB, C DC, D DD, E DE, F DF);
+impl_tile_bundle!(A DA, B DB, C DC, D DD, E DE, F DF, G DG);
+impl_tile_bundle!(A DA, B DB, C DC, D DD, E DE, F DF, G DG, H DH);
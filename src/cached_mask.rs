@@ -0,0 +1,280 @@
+//! Caching the intersection of several storage masks across frames.
+
+use hibitset::BitSet;
+use specs::storage::UnprotectedStorage;
+use specs::Join;
+
+use epoch::Epoch;
+use Storage;
+
+/// Storages, or tuples of storages, whose masks can be cached by a `CachedMask`.
+///
+/// This is implemented for `&Storage` and `&mut Storage`, and for tuples of these of up to
+/// eight elements.
+pub trait CachedLayers {
+    /// Calls `f` with the mask of every storage and the epoch identifying its state.
+    #[doc(hidden)]
+    fn visit_masks(&self, f: &mut dyn FnMut(&BitSet, CacheEpoch));
+}
+
+/// The state of a storage mask a `CachedMask` was built from.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct CacheEpoch(Epoch);
+
+impl<C, D, I> CachedLayers for &Storage<C, D, I>
+where
+    D: UnprotectedStorage<C>,
+{
+    fn visit_masks(&self, f: &mut dyn FnMut(&BitSet, CacheEpoch)) {
+        f(&self.bitset, CacheEpoch(self.epoch));
+    }
+}
+
+impl<C, D, I> CachedLayers for &mut Storage<C, D, I>
+where
+    D: UnprotectedStorage<C>,
+{
+    fn visit_masks(&self, f: &mut dyn FnMut(&BitSet, CacheEpoch)) {
+        f(&self.bitset, CacheEpoch(self.epoch));
+    }
+}
+
+macro_rules! impl_cached_layers {
+    ($($name:ident)*) => {
+        impl<$($name),*> CachedLayers for ($($name,)*)
+        where
+            $($name: CachedLayers,)*
+        {
+            #[allow(non_snake_case)]
+            fn visit_masks(&self, f: &mut dyn FnMut(&BitSet, CacheEpoch)) {
+                let ($(ref $name,)*) = *self;
+
+                $($name.visit_masks(f);)*
+            }
+        }
+    };
+}
+
+impl_cached_layers!(A);
+impl_cached_layers!(A B);
+impl_cached_layers!(A B C);
+impl_cached_layers!(A B C D);
+impl_cached_layers!(A B C D E);
+impl_cached_layers!(A B C D E F);
+impl_cached_layers!(A B C D E F G);
+impl_cached_layers!(A B C D E F G H);
+
+/// The intersection of the masks of several storages, kept across frames for systems which
+/// join the same layers every frame.
+///
+/// `update` only recomputes the intersection if one of the storages changed structurally
+/// since the last rebuild (see `Storage::structural_epoch`), and `join_with_mask` drives a
+/// join over the storages with the cached intersection, instead of intersecting the masks
+/// again while iterating.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::{Component, Join, VecStorage};
+/// use specs_static::{join_with_mask, CachedMask, Storage};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// struct Material(u8);
+///
+/// impl Component for Material {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// struct Moisture(u8);
+///
+/// impl Component for Moisture {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// struct Vegetation(u32);
+///
+/// impl Component for Vegetation {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// fn main() {
+///     let mut materials = Storage::<Material, VecStorage<_>, TileId>::default();
+///     let mut moisture = Storage::<Moisture, VecStorage<_>, TileId>::default();
+///     let mut vegetation = Storage::<Vegetation, VecStorage<_>, TileId>::default();
+///     for id in 0..1000 {
+///         materials.insert(TileId(id), Material(1));
+///         if id % 2 == 0 {
+///             moisture.insert(TileId(id), Moisture(3));
+///         }
+///         if id % 3 == 0 {
+///             vegetation.insert(TileId(id), Vegetation(0));
+///         }
+///     }
+///
+///     let mut cached = CachedMask::new();
+///     assert!(cached.update(&(&materials, &moisture, &vegetation)));
+///
+///     for frame in 0..3 {
+///         // Changing components isn't structural, so the mask stays valid.
+///         assert!(!cached.update(&(&materials, &moisture, &vegetation)));
+///         let layers = (&materials, &moisture, &mut vegetation);
+///         for (material, moisture, vegetation) in join_with_mask(&cached, layers) {
+///             vegetation.0 += u32::from(material.0 * moisture.0);
+///         }
+///         assert_eq!(vegetation.get(TileId(6)).map(|v| v.0), Some(3 * (frame + 1)));
+///     }
+///     assert_eq!((&materials, &moisture, &vegetation).join().count(), 167);
+///
+///     // Removing a component is structural.
+///     let epoch = moisture.structural_epoch();
+///     moisture.remove(TileId(6));
+///     assert!(moisture.structural_epoch() > epoch);
+///     assert!(cached.is_stale(&(&materials, &moisture, &vegetation)));
+///     assert!(cached.update(&(&materials, &moisture, &vegetation)));
+///     let layers = (&materials, &moisture, &vegetation);
+///     assert_eq!(join_with_mask(&cached, layers).join().count(), 166);
+/// }
+/// ```
+///
+/// Joining with a stale mask panics, since it might name vacant ids:
+///
+/// ```should_panic
+/// # extern crate specs;
+/// # extern crate specs_static;
+/// # use specs::{Component, Join, VecStorage};
+/// # use specs_static::{join_with_mask, CachedMask, Storage};
+/// # specs_static::define_id!(pub struct TileId;);
+/// # struct Material(u8);
+/// # impl Component for Material {
+/// #     type Storage = VecStorage<Self>;
+/// # }
+/// # fn main() {
+/// let mut materials = Storage::<Material, VecStorage<_>, TileId>::default();
+/// materials.insert(TileId(0), Material(1));
+/// let mut cached = CachedMask::new();
+/// cached.update(&(&materials,));
+///
+/// materials.remove(TileId(0));
+/// join_with_mask(&cached, (&materials,)).join().count();
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CachedMask {
+    mask: BitSet,
+    epochs: Vec<CacheEpoch>,
+}
+
+impl CachedMask {
+    /// Creates a stale cache, which is built by the first `update`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Recomputes the intersection of the masks of `layers`.
+    pub fn rebuild<L: CachedLayers>(&mut self, layers: &L) {
+        let mut mask: Option<BitSet> = None;
+        self.epochs.clear();
+        layers.visit_masks(&mut |layer, epoch| {
+            self.epochs.push(epoch);
+            match mask {
+                Some(ref mut mask) => *mask &= layer,
+                None => mask = Some(layer.clone()),
+            }
+        });
+
+        self.mask = mask.unwrap_or_default();
+    }
+
+    /// Returns `true` if the cache wasn't built from the current masks of `layers`.
+    pub fn is_stale<L: CachedLayers>(&self, layers: &L) -> bool {
+        let mut index = 0;
+        let mut stale = false;
+        layers.visit_masks(&mut |_, epoch| {
+            stale |= self.epochs.get(index) != Some(&epoch);
+            index += 1;
+        });
+
+        stale || index != self.epochs.len()
+    }
+
+    /// Rebuilds the cache if it's stale, returning whether it was.
+    pub fn update<L: CachedLayers>(&mut self, layers: &L) -> bool {
+        let stale = self.is_stale(layers);
+        if stale {
+            self.rebuild(layers);
+        }
+
+        stale
+    }
+
+    /// Returns the cached intersection.
+    pub fn mask(&self) -> &BitSet {
+        &self.mask
+    }
+}
+
+/// Joins `layers` over the intersection cached in `cached`, which has to be up to date.
+///
+/// # Panics
+///
+/// Panics if `cached` is stale for `layers`, because the cached mask might contain vacant
+/// ids then.
+pub fn join_with_mask<'a, J>(cached: &'a CachedMask, layers: J) -> MaskedJoin<'a, J>
+where
+    J: CachedLayers + Join,
+{
+    assert!(
+        !cached.is_stale(&layers),
+        "the cached mask is stale, `CachedMask::update` has to be called first"
+    );
+
+    MaskedJoin {
+        mask: &cached.mask,
+        layers,
+    }
+}
+
+/// A join driven by the mask of a `CachedMask`, created with `join_with_mask`.
+pub struct MaskedJoin<'a, J> {
+    mask: &'a BitSet,
+    layers: J,
+}
+
+impl<'a, J> Join for MaskedJoin<'a, J>
+where
+    J: Join,
+{
+    type Type = J::Type;
+    type Value = J::Value;
+    type Mask = &'a BitSet;
+
+    // SAFETY: `join_with_mask` checked that the cached mask was built from the current masks
+    // of the layers. Epochs are unique per storage and change with every change of its mask,
+    // so the cached mask is exactly the intersection of the layer masks, which is what `J`
+    // requires of the ids passed to `get`.
+    unsafe fn open(self) -> (Self::Mask, Self::Value) {
+        let (_, value) = self.layers.open();
+
+        (self.mask, value)
+    }
+
+    unsafe fn get(value: &mut Self::Value, id: u32) -> Self::Type {
+        J::get(value, id)
+    }
+}
+
+impl<'a, J> IntoIterator for MaskedJoin<'a, J>
+where
+    J: Join,
+{
+    type Item = J::Type;
+    type IntoIter = ::specs::join::JoinIter<Self>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.join()
+    }
+}
+
@@ -0,0 +1,255 @@
+//! Tracking which chunks of a map contain changed tiles.
+
+use std::marker::PhantomData;
+
+use hibitset::{BitSet, BitSetLike, DrainBitIter, DrainableBitSet};
+use shred::{Read, World, System, SystemData, WriteExpect};
+use shrev::ReaderId;
+use specs::storage::ComponentEvent;
+use specs::{Component, Tracked};
+
+use {Grid2d, Id, Storage};
+
+/// Dirty flags for the square chunks of a grid, like the chunks a renderer builds one mesh
+/// for.
+///
+/// The chunk `(chunk_x, chunk_y)` covers the tiles with
+/// `chunk_x * chunk_size <= x < (chunk_x + 1) * chunk_size`, and the same for `y`; the last
+/// chunks of a row or column are smaller if the grid isn't a multiple of the chunk size.
+/// Marking a chunk which is already dirty does nothing, so any amount of changes in a chunk
+/// result in one flag.
+///
+/// The flags can be set manually with `mark`, or by a `ChunkDirtySystem` from the change
+/// events of a tracked storage.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::prelude::*;
+/// use specs_static::{ChunkDirty, ChunkDirtySystem, Grid2d, Storage, TileWorldExt};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// struct Wall;
+///
+/// impl Component for Wall {
+///     type Storage = FlaggedStorage<Self, VecStorage<Self>>;
+/// }
+///
+/// type Walls = Storage<Wall, FlaggedStorage<Wall, VecStorage<Wall>>, TileId>;
+///
+/// fn main() {
+///     let grid = Grid2d::new(100, 64);
+///     let id = |x, y| grid.id::<TileId>(x, y).unwrap();
+///
+///     let mut world = World::new();
+///     world.register_tile_comp::<Wall, TileId>();
+///     world.insert(ChunkDirty::<TileId>::new(grid, 32));
+///
+///     let mut dispatcher = DispatcherBuilder::new()
+///         .with(ChunkDirtySystem::<Wall, TileId>::new(), "wall_chunks", &[])
+///         .build();
+///     dispatcher.setup(&mut world);
+///
+///     let mut edit = |tiles: &[(u32, u32)]| {
+///         {
+///             let mut walls = world.write_resource::<Walls>();
+///             for &(x, y) in tiles {
+///                 walls.insert(id(x, y), Wall);
+///             }
+///         }
+///         dispatcher.dispatch(&world);
+///
+///         let mut dirty = world.write_resource::<ChunkDirty<TileId>>();
+///         dirty.drain_dirty_chunks().collect::<Vec<_>>()
+///     };
+///
+///     // Edits in one chunk dirty exactly that chunk, once.
+///     assert_eq!(edit(&[(40, 3), (41, 3), (63, 31)]), vec![(1, 0)]);
+///     assert_eq!(edit(&[]), vec![]);
+///     // Tiles on the border of a chunk only dirty the chunk they are in.
+///     assert_eq!(edit(&[(31, 31)]), vec![(0, 0)]);
+///     assert_eq!(edit(&[(32, 32)]), vec![(1, 1)]);
+///     // The last column of chunks is only four tiles wide.
+///     assert_eq!(edit(&[(99, 63), (0, 0)]), vec![(0, 0), (3, 1)]);
+/// }
+/// ```
+pub struct ChunkDirty<I> {
+    grid: Grid2d,
+    chunk_size: u32,
+    chunks_x: u32,
+    chunks_y: u32,
+    dirty: BitSet,
+    phantom: PhantomData<I>,
+}
+
+impl<I> ChunkDirty<I>
+where
+    I: Id,
+{
+    /// Creates flags for the chunks of `grid` with `chunk_size` tiles per side, all of
+    /// which are clean.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is `0`.
+    pub fn new(grid: Grid2d, chunk_size: u32) -> Self {
+        assert!(chunk_size > 0, "the chunk size has to be at least 1");
+        let chunks = |len: u32| len.div_ceil(chunk_size);
+
+        ChunkDirty {
+            grid,
+            chunk_size,
+            chunks_x: chunks(grid.width()),
+            chunks_y: chunks(grid.height()),
+            dirty: BitSet::new(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns the grid the chunks divide.
+    pub fn grid(&self) -> Grid2d {
+        self.grid
+    }
+
+    /// Returns the amount of tiles per side of a chunk.
+    pub fn chunk_size(&self) -> u32 {
+        self.chunk_size
+    }
+
+    /// Returns the chunk containing `id`, or `None` if `id` is outside the grid.
+    pub fn chunk_of(&self, id: I) -> Option<(u32, u32)> {
+        self.grid
+            .coords(id)
+            .map(|(x, y)| (x / self.chunk_size, y / self.chunk_size))
+    }
+
+    /// Marks the chunk containing `id` as dirty. Ids outside the grid are ignored.
+    pub fn mark(&mut self, id: I) {
+        if let Some((chunk_x, chunk_y)) = self.chunk_of(id) {
+            self.dirty.add(chunk_y * self.chunks_x + chunk_x);
+        }
+    }
+
+    /// Marks the chunk `(chunk_x, chunk_y)` as dirty, for example because something which
+    /// isn't stored in a tile storage affects its mesh.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no such chunk.
+    pub fn mark_chunk(&mut self, chunk_x: u32, chunk_y: u32) {
+        assert!(
+            chunk_x < self.chunks_x && chunk_y < self.chunks_y,
+            "there is no chunk ({}, {}) in a grid of {}x{} chunks",
+            chunk_x,
+            chunk_y,
+            self.chunks_x,
+            self.chunks_y
+        );
+        self.dirty.add(chunk_y * self.chunks_x + chunk_x);
+    }
+
+    /// Returns `true` if the chunk `(chunk_x, chunk_y)` is dirty.
+    pub fn is_dirty(&self, chunk_x: u32, chunk_y: u32) -> bool {
+        chunk_x < self.chunks_x
+            && chunk_y < self.chunks_y
+            && self.dirty.contains(chunk_y * self.chunks_x + chunk_x)
+    }
+
+    /// Returns `true` if no chunk is dirty.
+    pub fn is_clean(&self) -> bool {
+        self.dirty.is_empty()
+    }
+
+    /// Yields the dirty chunks row by row, clearing every flag as it is yielded.
+    ///
+    /// Chunks which haven't been yielded when the iterator is dropped stay dirty.
+    pub fn drain_dirty_chunks<'a>(&'a mut self) -> DirtyChunks<'a> {
+        DirtyChunks {
+            chunks_x: self.chunks_x,
+            iter: self.dirty.drain(),
+        }
+    }
+}
+
+/// The iterator returned by `ChunkDirty::drain_dirty_chunks`.
+pub struct DirtyChunks<'a> {
+    chunks_x: u32,
+    iter: DrainBitIter<'a, BitSet>,
+}
+
+impl<'a> Iterator for DirtyChunks<'a> {
+    type Item = (u32, u32);
+
+    fn next(&mut self) -> Option<(u32, u32)> {
+        self.iter
+            .next()
+            .map(|index| (index % self.chunks_x, index / self.chunks_x))
+    }
+}
+
+/// A system marking the chunks of the `ChunkDirty<I>` resource which contain tiles changed
+/// in the tracked storage `Storage<C, C::Storage, I>`.
+///
+/// Insertions, modifications and removals all mark the chunk. The `ChunkDirty<I>` resource
+/// has to be added before the system is set up, which registers its reader. One system per
+/// layer can feed the same resource.
+pub struct ChunkDirtySystem<C, I> {
+    reader: Option<ReaderId<ComponentEvent>>,
+    phantom: PhantomData<(C, I)>,
+}
+
+impl<C, I> ChunkDirtySystem<C, I> {
+    /// Creates a system which has to be set up before it runs.
+    pub fn new() -> Self {
+        ChunkDirtySystem {
+            reader: None,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<C, I> Default for ChunkDirtySystem<C, I> {
+    fn default() -> Self {
+        ChunkDirtySystem::new()
+    }
+}
+
+impl<'a, C, I> System<'a> for ChunkDirtySystem<C, I>
+where
+    C: Component + Send + Sync,
+    C::Storage: Default + Tracked,
+    I: Id,
+{
+    type SystemData = (
+        Read<'a, Storage<C, C::Storage, I>>,
+        WriteExpect<'a, ChunkDirty<I>>,
+    );
+
+    fn run(&mut self, (storage, mut dirty): Self::SystemData) {
+        let reader = self
+            .reader
+            .as_mut()
+            .expect("`ChunkDirtySystem` has to be set up before it runs");
+
+        for event in storage.channel().read(reader) {
+            let id = match *event {
+                ComponentEvent::Inserted(id)
+                | ComponentEvent::Modified(id)
+                | ComponentEvent::Removed(id) => id,
+            };
+            dirty.mark(I::from_u32(id));
+        }
+    }
+
+    fn setup(&mut self, res: &mut World) {
+        Self::SystemData::setup(res);
+        self.reader = Some(
+            res.fetch_mut::<Storage<C, C::Storage, I>>()
+                .channel_mut()
+                .register_reader(),
+        );
+    }
+}
@@ -0,0 +1,222 @@
+//! A backend allocating fixed-size chunks on demand.
+
+use std::ptr;
+
+use hibitset::{BitSet, BitSetLike};
+use specs::storage::{DistinctStorage, UnprotectedStorage};
+
+use alloc::{Slots, Table};
+use {Id, Index, ReserveIds, Storage, StdAlloc, TileAlloc};
+
+/// A single chunk of slots, together with the amount of occupied slots.
+struct Chunk<C, A: TileAlloc> {
+    slots: Slots<C, A>,
+    len: u32,
+}
+
+impl<C, A: TileAlloc> Chunk<C, A> {
+    fn new(alloc: &A) -> Self {
+        Chunk {
+            slots: Slots::new(CHUNK_SIZE as usize, alloc),
+            len: 0,
+        }
+    }
+}
+
+/// The amount of slots in one chunk of a `ChunkedVecStorage`.
+pub const CHUNK_SIZE: u32 = 1024;
+
+/// A storage backend which allocates fixed-size chunks of `CHUNK_SIZE` slots on demand
+/// and frees a chunk again as soon as it's empty.
+///
+/// In contrast to `VecStorage`, memory is only allocated for chunks which actually contain
+/// components, making this a good fit for sparse worlds, and components of one chunk are
+/// stored contiguously.
+///
+/// The chunks and the chunk table are allocated through the allocator handle `A`, see
+/// `TileAlloc` and `Storage::with_alloc`.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::Component;
+/// use specs_static::{ChunkedVecStorage, Storage};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// #[derive(Debug, PartialEq)]
+/// struct Ore(u32);
+///
+/// impl Component for Ore {
+///     type Storage = ChunkedVecStorage<Self>;
+/// }
+///
+/// fn main() {
+///     let mut ores = Storage::<Ore, ChunkedVecStorage<Ore>, TileId>::default();
+///     ores.insert(TileId(5), Ore(1));
+///     ores.insert(TileId(1_000_000), Ore(2));
+///
+///     assert_eq!(ores.get(TileId(1_000_000)), Some(&Ore(2)));
+///     assert_eq!(ores.unprotected_storage().chunk_count(), 2);
+///
+///     ores.remove(TileId(5));
+///     assert_eq!(ores.unprotected_storage().chunk_count(), 1);
+///     assert_eq!(ores.unprotected_storage().chunk_len(0), 0);
+/// }
+/// ```
+pub struct ChunkedVecStorage<C, A: TileAlloc = StdAlloc> {
+    chunks: Table<Option<Chunk<C, A>>, A>,
+}
+
+impl<C, A: TileAlloc> Default for ChunkedVecStorage<C, A> {
+    fn default() -> Self {
+        ChunkedVecStorage::with_alloc(A::default())
+    }
+}
+
+impl<C, A: TileAlloc> ChunkedVecStorage<C, A> {
+    /// Creates an empty backend which allocates through `alloc`.
+    pub fn with_alloc(alloc: A) -> Self {
+        ChunkedVecStorage {
+            chunks: Table::new(alloc),
+        }
+    }
+
+    /// Returns the number of chunks that are currently allocated.
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.iter().filter(|chunk| chunk.is_some()).count()
+    }
+
+    /// Returns the amount of occupied slots in the chunk with the given index.
+    ///
+    /// A chunk with index `n` contains the ids `n * CHUNK_SIZE..(n + 1) * CHUNK_SIZE`.
+    pub fn chunk_len(&self, chunk: u32) -> u32 {
+        match self.chunks.get(chunk as usize) {
+            Some(Some(chunk)) => chunk.len,
+            _ => 0,
+        }
+    }
+
+    /// Returns an iterator over the indices of all allocated chunks together with
+    /// their amount of occupied slots.
+    pub fn chunks<'a>(&'a self) -> impl Iterator<Item = (u32, u32)> + 'a {
+        self.chunks
+            .iter()
+            .enumerate()
+            .filter_map(|(i, chunk)| chunk.as_ref().map(|chunk| (i as u32, chunk.len)))
+    }
+
+    fn split(id: Index) -> (usize, usize) {
+        ((id / CHUNK_SIZE) as usize, (id % CHUNK_SIZE) as usize)
+    }
+
+    unsafe fn slot(&self, id: Index) -> *const C {
+        let (chunk, slot) = Self::split(id);
+
+        match *self.chunks.get_unchecked(chunk) {
+            Some(ref chunk) => chunk.slots.get_unchecked(slot).as_ptr(),
+            None => vacant_chunk(),
+        }
+    }
+
+    unsafe fn slot_mut(&mut self, id: Index) -> *mut C {
+        let (chunk, slot) = Self::split(id);
+
+        match *self.chunks.get_unchecked_mut(chunk) {
+            Some(ref mut chunk) => chunk.slots.get_unchecked_mut(slot).as_mut_ptr(),
+            None => vacant_chunk(),
+        }
+    }
+}
+
+/// Called when accessing a chunk which isn't allocated, which the bitset of `Storage` rules out.
+unsafe fn vacant_chunk() -> ! {
+    debug_assert!(false, "accessed an unallocated chunk");
+    ::std::hint::unreachable_unchecked()
+}
+
+impl<C, A: TileAlloc> UnprotectedStorage<C> for ChunkedVecStorage<C, A> {
+    unsafe fn clean<B>(&mut self, has: B)
+    where
+        B: BitSetLike,
+    {
+        for (i, chunk) in self.chunks.iter_mut().enumerate() {
+            if let Some(ref mut chunk) = *chunk {
+                let base = i as u32 * CHUNK_SIZE;
+
+                for (slot, value) in chunk.slots.iter_mut().enumerate() {
+                    if has.contains(base + slot as u32) {
+                        ptr::drop_in_place(value.as_mut_ptr());
+                    }
+                }
+            }
+        }
+        self.chunks.clear();
+    }
+
+    unsafe fn get(&self, id: Index) -> &C {
+        &*self.slot(id)
+    }
+
+    unsafe fn get_mut(&mut self, id: Index) -> &mut C {
+        &mut *self.slot_mut(id)
+    }
+
+    unsafe fn insert(&mut self, id: Index, value: C) {
+        let (chunk, slot) = Self::split(id);
+        self.chunks.grow_with(chunk + 1, || None);
+
+        if self.chunks[chunk].is_none() {
+            let new = Chunk::new(self.chunks.alloc());
+            self.chunks[chunk] = Some(new);
+        }
+        let chunk = self.chunks[chunk].as_mut().expect("allocated above");
+        chunk.len += 1;
+        ptr::write(chunk.slots.get_unchecked_mut(slot).as_mut_ptr(), value);
+    }
+
+    unsafe fn remove(&mut self, id: Index) -> C {
+        let index = (id / CHUNK_SIZE) as usize;
+        let value = ptr::read(self.slot(id));
+
+        let empty = match *self.chunks.get_unchecked_mut(index) {
+            Some(ref mut chunk) => {
+                chunk.len -= 1;
+                chunk.len == 0
+            }
+            None => vacant_chunk(),
+        };
+
+        if empty {
+            self.chunks[index] = None;
+        }
+
+        value
+    }
+}
+
+/// Only the chunk table is grown; the chunks themselves are still allocated on demand.
+impl<C, A: TileAlloc> ReserveIds for ChunkedVecStorage<C, A> {
+    fn reserve_ids(&mut self, len: u32) {
+        let chunks = len.div_ceil(CHUNK_SIZE) as usize;
+        self.chunks.grow_with(chunks, || None);
+    }
+}
+
+unsafe impl<C, A: TileAlloc> DistinctStorage for ChunkedVecStorage<C, A> {}
+
+impl<C, A, I> Storage<C, ChunkedVecStorage<C, A>, I>
+where
+    A: TileAlloc,
+    I: Id,
+{
+    /// Creates an empty storage whose backend allocates through `alloc`.
+    pub fn with_alloc(alloc: A) -> Self {
+        // An empty backend is consistent with an empty bitset.
+        let data = ChunkedVecStorage::with_alloc(alloc);
+
+        unsafe { Storage::from_parts_unchecked(data, BitSet::new()) }
+    }
+}
@@ -0,0 +1,189 @@
+//! Encoding components as bytes, so registered layers can be recorded and sent around.
+
+use std::convert::TryFrom;
+use std::mem;
+
+use specs::storage::UnprotectedStorage;
+
+use {AnyTileStorage, Id, Storage};
+
+/// Components which can be encoded as bytes and decoded again.
+///
+/// Layers whose components implement this can be added with `TileRegistry::register_codec`,
+/// which lets `MutationLog` record them and `replay` write them back through the registry.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs_static;
+///
+/// use specs_static::TileCodec;
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Depth(u16);
+///
+/// impl TileCodec for Depth {
+///     fn encode(&self, out: &mut Vec<u8>) {
+///         self.0.encode(out);
+///     }
+///
+///     fn decode(bytes: &[u8]) -> Option<Self> {
+///         u16::decode(bytes).map(Depth)
+///     }
+/// }
+///
+/// fn main() {
+///     let mut bytes = Vec::new();
+///     Depth(300).encode(&mut bytes);
+///     assert_eq!(bytes, vec![44, 1]);
+///     assert_eq!(Depth::decode(&bytes), Some(Depth(300)));
+///     assert_eq!(Depth::decode(&bytes[..1]), None);
+/// }
+/// ```
+pub trait TileCodec: Sized {
+    /// Appends the encoding of `self` to `out`.
+    fn encode(&self, out: &mut Vec<u8>);
+
+    /// Decodes a value from all of `bytes`, returning `None` if they aren't a valid encoding.
+    fn decode(bytes: &[u8]) -> Option<Self>;
+}
+
+macro_rules! impl_codec_le {
+    ($($ty:ty),*) => {
+        $(
+            /// Little endian.
+            impl TileCodec for $ty {
+                fn encode(&self, out: &mut Vec<u8>) {
+                    out.extend_from_slice(&self.to_le_bytes());
+                }
+
+                fn decode(bytes: &[u8]) -> Option<Self> {
+                    let bytes = <[u8; mem::size_of::<$ty>()]>::try_from(bytes).ok()?;
+
+                    Some(<$ty>::from_le_bytes(bytes))
+                }
+            }
+        )*
+    };
+}
+
+impl_codec_le!(u8, u16, u32, u64, i8, i16, i32, i64);
+
+/// The bits, little endian.
+impl TileCodec for f32 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.to_bits().encode(out);
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        u32::decode(bytes).map(f32::from_bits)
+    }
+}
+
+/// The bits, little endian.
+impl TileCodec for f64 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.to_bits().encode(out);
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        u64::decode(bytes).map(f64::from_bits)
+    }
+}
+
+/// One byte, which has to be 0 or 1.
+impl TileCodec for bool {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(*self as u8);
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            [0] => Some(false),
+            [1] => Some(true),
+            _ => None,
+        }
+    }
+}
+
+/// The UTF-8 bytes.
+impl TileCodec for String {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self.as_bytes());
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+}
+
+/// Decodes the bytes and replaces the component at the id, returning whether it differed
+/// from the decoded one, or `None` if the bytes can't be decoded.
+pub(crate) type DecodeReplace = fn(&mut dyn AnyTileStorage, u32, &[u8]) -> Option<bool>;
+
+/// How to decode the components of a registered layer.
+#[derive(Clone, Copy)]
+pub(crate) struct Codec {
+    /// Decodes the bytes and inserts the component at the id, returning `false` if they
+    /// can't be decoded.
+    pub(crate) decode_insert: fn(&mut dyn AnyTileStorage, u32, &[u8]) -> bool,
+    /// Only set for components which implement `PartialEq`.
+    pub(crate) decode_replace: Option<DecodeReplace>,
+}
+
+impl Codec {
+    pub(crate) fn new<C, D, I>() -> Self
+    where
+        C: TileCodec + Send + Sync + 'static,
+        D: UnprotectedStorage<C> + Send + Sync + 'static,
+        I: Id,
+    {
+        Codec {
+            decode_insert: |storage, id, bytes| {
+                let storage = storage
+                    .as_any_mut()
+                    .downcast_mut::<Storage<C, D, I>>()
+                    .expect("registered with this storage type");
+
+                match C::decode(bytes) {
+                    Some(comp) => {
+                        storage.insert(I::from_u32(id), comp);
+                        true
+                    }
+                    None => false,
+                }
+            },
+            decode_replace: None,
+        }
+    }
+
+    pub(crate) fn with_eq<C, D, I>() -> Self
+    where
+        C: PartialEq + TileCodec + Send + Sync + 'static,
+        D: UnprotectedStorage<C> + Send + Sync + 'static,
+        I: Id,
+    {
+        Codec {
+            decode_replace: Some(|storage, id, bytes| {
+                let storage = storage
+                    .as_any_mut()
+                    .downcast_mut::<Storage<C, D, I>>()
+                    .expect("registered with this storage type");
+                let comp = C::decode(bytes)?;
+
+                match storage.get_mut(I::from_u32(id)) {
+                    Some(old) if *old == comp => Some(false),
+                    Some(old) => {
+                        *old = comp;
+                        Some(true)
+                    }
+                    None => {
+                        storage.insert(I::from_u32(id), comp);
+                        Some(true)
+                    }
+                }
+            }),
+            ..Codec::new::<C, D, I>()
+        }
+    }
+}
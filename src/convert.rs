@@ -0,0 +1,315 @@
+//! Conversions between storages of different components, and to and from plain `HashMap`s.
+
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use std::mem;
+
+use hibitset::BitSet;
+use specs::storage::UnprotectedStorage;
+
+use mask::PendingRun;
+use {Id, Storage};
+
+impl<C, D, I> Storage<C, D, I>
+where
+    D: UnprotectedStorage<C>,
+    I: Id,
+{
+    /// Creates a storage with all components of `map`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use std::collections::HashMap;
+    ///
+    /// use specs::{Component, Join, VecStorage};
+    /// use specs_static::Storage;
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// #[derive(Clone, Debug, PartialEq)]
+    /// struct Label(String);
+    ///
+    /// impl Component for Label {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     // Every `HashMap` iterates in a different, random order.
+    ///     for round in 0..10 {
+    ///         let map: HashMap<_, _> = (0..500)
+    ///             .map(|i| (TileId(i * 13 + round), Label(format!("tile {}", i))))
+    ///             .collect();
+    ///
+    ///         let labels = Storage::<_, VecStorage<_>, TileId>::from_map(map.clone());
+    ///         assert_eq!((&labels).join().count(), 500);
+    ///         assert_eq!(labels.to_map(), map);
+    ///         assert_eq!(labels.into_map(), map);
+    ///     }
+    /// }
+    /// ```
+    pub fn from_map<S>(map: HashMap<I, C, S>) -> Self
+    where
+        D: Default,
+        S: BuildHasher,
+    {
+        let mut storage = Self::default();
+        for (id, comp) in map {
+            storage.insert(id, comp);
+        }
+
+        storage
+    }
+
+    /// Moves all components into a `HashMap`.
+    pub fn into_map(mut self) -> HashMap<I, C> {
+        // Taking the bitset first leaves `self` empty, so dropping it won't touch the
+        // components which have been moved out.
+        let bitset = mem::replace(&mut self.bitset, BitSet::new());
+
+        (&bitset)
+            .into_iter()
+            .map(|id| (I::from_u32(id), unsafe { self.data.remove(id) }))
+            .collect()
+    }
+
+    /// Copies all components into a `HashMap`.
+    pub fn to_map(&self) -> HashMap<I, C>
+    where
+        C: Clone,
+    {
+        (&self.bitset)
+            .into_iter()
+            .map(|id| (I::from_u32(id), unsafe { self.data.get(id) }.clone()))
+            .collect()
+    }
+
+    /// Moves every component through `f`, returning a storage of the results with exactly
+    /// the same ids occupied and the same bound and debug bound.
+    ///
+    /// This is meant for converting intermediate layers wholesale, like turning generated
+    /// noise into materials. The bitset of the new storage is built in runs of consecutive
+    /// ids, like with `insert_mask_with`. Use `filter_map` to leave some ids vacant, or
+    /// `map_ref` to keep the source storage.
+    ///
+    /// If `f` panics, the components which haven't been moved yet are dropped with the source
+    /// storage, and the ones already converted with the new one, so each component is dropped
+    /// exactly once.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, DenseVecStorage, Join, VecStorage};
+    /// use specs_static::Storage;
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// struct Noise(f32);
+    ///
+    /// impl Component for Noise {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// #[derive(Clone, Copy, Debug, PartialEq)]
+    /// enum Material {
+    ///     Water,
+    ///     Sand,
+    ///     Rock,
+    /// }
+    ///
+    /// impl Component for Material {
+    ///     type Storage = DenseVecStorage<Self>;
+    /// }
+    ///
+    /// fn material(noise: f32) -> Material {
+    ///     match noise {
+    ///         n if n < 0.3 => Material::Water,
+    ///         n if n < 0.6 => Material::Sand,
+    ///         _ => Material::Rock,
+    ///     }
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut noise = Storage::<Noise, VecStorage<_>, TileId>::with_bound(999);
+    ///     for id in (0..1000).filter(|id| id % 7 != 3) {
+    ///         noise.insert(TileId(id), Noise((id % 10) as f32 / 10.0));
+    ///     }
+    ///     let mask = noise.mask().clone();
+    ///
+    ///     // Keeping the noise around for another layer.
+    ///     let rocky = noise.map_ref::<_, VecStorage<_>, _>(|_, noise| noise.0 >= 0.6);
+    ///     assert_eq!(*rocky.mask(), mask);
+    ///     assert_eq!(rocky.get(TileId(8)), Some(&true));
+    ///     assert_eq!(rocky.bound(), Some(999));
+    ///
+    ///     let materials: Storage<_, DenseVecStorage<_>, _> =
+    ///         noise.map(|_, noise| material(noise.0));
+    ///     assert_eq!(*materials.mask(), mask);
+    ///     assert_eq!(materials.get(TileId(1)), Some(&Material::Water));
+    ///     assert_eq!(materials.get(TileId(3)), None);
+    ///     assert_eq!(materials.get(TileId(4)), Some(&Material::Sand));
+    ///     assert_eq!(materials.get(TileId(999)), Some(&Material::Rock));
+    ///     assert_eq!(materials.bound(), Some(999));
+    ///
+    ///     // Only the shores, as coast ids.
+    ///     let shore: Storage<_, VecStorage<_>, _> = materials.filter_map(|id: TileId, material| {
+    ///         match material {
+    ///             Material::Sand => Some(id.0),
+    ///             _ => None,
+    ///         }
+    ///     });
+    ///     assert!((&shore).join().all(|&id| id % 10 >= 3 && id % 10 < 6));
+    ///     assert_eq!(shore.get(TileId(5)), Some(&5));
+    ///     assert_eq!(shore.get(TileId(9)), None);
+    ///     assert_eq!(shore.bound(), Some(999));
+    /// }
+    /// ```
+    ///
+    /// Every component is dropped exactly once, even if `f` panics:
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use std::panic::{self, AssertUnwindSafe};
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    ///
+    /// use specs::{Component, VecStorage};
+    /// use specs_static::Storage;
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// static DROPS: AtomicUsize = AtomicUsize::new(0);
+    ///
+    /// struct Counted(u32);
+    ///
+    /// impl Drop for Counted {
+    ///     fn drop(&mut self) {
+    ///         DROPS.fetch_add(1, Ordering::SeqCst);
+    ///     }
+    /// }
+    ///
+    /// impl Component for Counted {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// struct Wrapped(Counted);
+    ///
+    /// impl Component for Wrapped {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// type Counteds = Storage<Counted, VecStorage<Counted>, TileId>;
+    /// type Wrappeds = Storage<Wrapped, VecStorage<Wrapped>, TileId>;
+    ///
+    /// fn counted() -> Counteds {
+    ///     let mut storage = Counteds::default();
+    ///     for id in (0..100).map(|id| id * 3) {
+    ///         storage.insert(TileId(id), Counted(id));
+    ///     }
+    ///
+    ///     storage
+    /// }
+    ///
+    /// fn drops() -> usize {
+    ///     DROPS.swap(0, Ordering::SeqCst)
+    /// }
+    ///
+    /// fn main() {
+    ///     // Moving the components doesn't drop them.
+    ///     let wrapped: Wrappeds = counted().map(|_, counted| Wrapped(counted));
+    ///     assert_eq!(drops(), 0);
+    ///     drop(wrapped);
+    ///     assert_eq!(drops(), 100);
+    ///
+    ///     // The components left out are dropped right away.
+    ///     let even: Wrappeds = counted().filter_map(|_, counted| match counted.0 % 2 {
+    ///         0 => Some(Wrapped(counted)),
+    ///         _ => None,
+    ///     });
+    ///     assert_eq!(drops(), 50);
+    ///     drop(even);
+    ///     assert_eq!(drops(), 50);
+    ///
+    ///     let storage = counted();
+    ///     let result = panic::catch_unwind(AssertUnwindSafe(move || {
+    ///         let _: Wrappeds = storage.map(|id: TileId, counted| match id.0 {
+    ///             150 => panic!("halfway through"),
+    ///             _ => Wrapped(counted),
+    ///         });
+    ///     }));
+    ///     assert!(result.is_err());
+    ///     assert_eq!(drops(), 100);
+    /// }
+    /// ```
+    pub fn map<C2, D2, F>(self, mut f: F) -> Storage<C2, D2, I>
+    where
+        D2: UnprotectedStorage<C2> + Default,
+        F: FnMut(I, C) -> C2,
+    {
+        self.filter_map(|id, comp| Some(f(id, comp)))
+    }
+
+    /// Moves every component through `f` like `map`, leaving the ids vacant for which `f`
+    /// returns `None`. The components `f` doesn't keep are dropped by it.
+    pub fn filter_map<C2, D2, F>(mut self, mut f: F) -> Storage<C2, D2, I>
+    where
+        D2: UnprotectedStorage<C2> + Default,
+        F: FnMut(I, C) -> Option<C2>,
+    {
+        let ids = self.bitset.clone();
+        let mut mapped = Storage::<C2, D2, I>::default();
+        mapped.epoch.bump();
+        copy_bounds(&self, &mut mapped);
+        {
+            let mut run = PendingRun::new(&mut mapped.bitset);
+
+            for id in &ids {
+                // Clearing the bit together with taking the component out means that, if
+                // `f` panics, dropping `self` drops the components which are still left.
+                let comp = unsafe { self.data.remove(id) };
+                self.bitset.remove(id);
+
+                if let Some(comp) = f(I::from_u32(id), comp) {
+                    unsafe {
+                        mapped.data.insert(id, comp);
+                    }
+                    run.push(id);
+                }
+            }
+        }
+        mapped.stamps.stamp_all(&mapped.bitset);
+
+        mapped
+    }
+
+    /// Converts every component with `f` like `map`, but without consuming the storage.
+    pub fn map_ref<C2, D2, F>(&self, mut f: F) -> Storage<C2, D2, I>
+    where
+        D2: UnprotectedStorage<C2> + Default,
+        F: FnMut(I, &C) -> C2,
+    {
+        let mut mapped = Storage::<C2, D2, I>::default();
+        mapped.insert_mask_with(&self.bitset, |id| f(id, unsafe { self.data.get(id.id()) }));
+        // Only after inserting, as components above a bound which was lowered later are kept.
+        copy_bounds(self, &mut mapped);
+
+        mapped
+    }
+}
+
+/// Gives `to` the bound and the debug bound of `from`.
+fn copy_bounds<C, D, C2, D2, I>(from: &Storage<C, D, I>, to: &mut Storage<C2, D2, I>)
+where
+    D: UnprotectedStorage<C>,
+    D2: UnprotectedStorage<C2>,
+{
+    to.bound = from.bound;
+    #[cfg(feature = "strict-ids")]
+    {
+        to.debug_bound = from.debug_bound;
+    }
+}
@@ -0,0 +1,203 @@
+//! Applying a kernel to every tile of a layer, like blurring a heightmap.
+
+use specs::storage::UnprotectedStorage;
+
+use {Grid2d, Id, Storage};
+
+/// What `convolve` does with the parts of the kernel which reach outside of the grid.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum EdgeMode {
+    /// Repeats the tiles at the border, as if they extended outwards.
+    Clamp,
+    /// Continues at the opposite side of the grid, like on a torus.
+    Wrap,
+    /// Leaves the tiles outside out, see `convolve` for how the rest is weighted.
+    Skip,
+}
+
+/// What `convolve` does with vacant tiles under the kernel.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum VacantTiles {
+    /// Leaves them out like `EdgeMode::Skip` does.
+    Skip,
+    /// Treats them as tiles with the value 0.
+    Zero,
+}
+
+impl EdgeMode {
+    /// Maps a coordinate which may be outside of `0..len` into it, or returns `None` if it
+    /// should be left out.
+    fn resolve(self, coord: i64, len: u32) -> Option<usize> {
+        let len = i64::from(len);
+        let coord = match self {
+            _ if coord >= 0 && coord < len => coord,
+            EdgeMode::Clamp => coord.max(0).min(len - 1),
+            EdgeMode::Wrap => coord.rem_euclid(len),
+            EdgeMode::Skip => return None,
+        };
+
+        Some(coord as usize)
+    }
+}
+
+/// Applies `kernel` to every occupied tile of `src` inside of `grid`, returning a storage
+/// with the results.
+///
+/// `kernel[ky][kx]` weights the tile `kx - K / 2` columns and `ky - K / 2` rows away from
+/// the tile, whose value is the sum of the weighted values `extract` returns for the tiles
+/// under the kernel. `apply` turns the component of the tile and the sum into its new
+/// component. Vacant tiles stay vacant, and occupied ids outside of `grid` are ignored.
+///
+/// `edge` decides which tiles the kernel reads beyond the border of the grid, and `vacant`
+/// how vacant tiles under the kernel count. If tiles are left out by `EdgeMode::Skip` or
+/// `VacantTiles::Skip`, the sum is scaled by the total weight of the kernel divided by the
+/// weight of the tiles which were used, so a blur doesn't darken the borders. Kernels whose
+/// weights sum up to 0, like edge detection ones, aren't scaled.
+///
+/// The values of the layer are read into a row-major buffer first, so the kernel walks
+/// contiguous rows whatever the backend of `src` is, and reading and writing never alias.
+///
+/// # Panics
+///
+/// Panics if `K` is even, since the kernel needs a center.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::{Component, VecStorage};
+/// use specs_static::{convolve, EdgeMode, Grid2d, Storage, VacantTiles};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// struct Heat(f32);
+///
+/// impl Component for Heat {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// type Layer = Storage<Heat, VecStorage<Heat>, TileId>;
+///
+/// const BLUR: [[f32; 3]; 3] = [
+///     [1.0 / 16.0, 2.0 / 16.0, 1.0 / 16.0],
+///     [2.0 / 16.0, 4.0 / 16.0, 2.0 / 16.0],
+///     [1.0 / 16.0, 2.0 / 16.0, 1.0 / 16.0],
+/// ];
+///
+/// fn blur(heat: &Layer, grid: &Grid2d, edge: EdgeMode, vacant: VacantTiles) -> Layer {
+///     convolve(heat, grid, &BLUR, |heat| heat.0, |_, sum| Heat(sum), edge, vacant)
+/// }
+///
+/// fn rows(heat: &Layer, grid: &Grid2d) -> Vec<Vec<Option<f32>>> {
+///     (0..5)
+///         .map(|y| {
+///             (0..5).map(|x| heat.get(grid.id(x, y).unwrap()).map(|heat| heat.0)).collect()
+///         })
+///         .collect()
+/// }
+///
+/// fn main() {
+///     // A 5x5 map, cold except for a hot corner.
+///     let grid = Grid2d::new(5, 5);
+///     let mut heat = Layer::default();
+///     for id in 0..25 {
+///         heat.insert(TileId(id), Heat(0.0));
+///     }
+///     heat.insert(grid.id(0, 0).unwrap(), Heat(16.0));
+///
+///     let zeros = vec![Some(0.0); 5];
+///     let clamped = rows(&blur(&heat, &grid, EdgeMode::Clamp, VacantTiles::Skip), &grid);
+///     assert_eq!(clamped[0], vec![Some(9.0), Some(3.0), Some(0.0), Some(0.0), Some(0.0)]);
+///     assert_eq!(clamped[1], vec![Some(3.0), Some(1.0), Some(0.0), Some(0.0), Some(0.0)]);
+///     assert!(clamped[2..].iter().all(|row| *row == zeros));
+///
+///     let wrapped = rows(&blur(&heat, &grid, EdgeMode::Wrap, VacantTiles::Skip), &grid);
+///     assert_eq!(wrapped[0], vec![Some(4.0), Some(2.0), Some(0.0), Some(0.0), Some(2.0)]);
+///     assert_eq!(wrapped[1], vec![Some(2.0), Some(1.0), Some(0.0), Some(0.0), Some(1.0)]);
+///     assert_eq!(wrapped[4], vec![Some(2.0), Some(1.0), Some(0.0), Some(0.0), Some(1.0)]);
+///     assert!(wrapped[2..4].iter().all(|row| *row == zeros));
+///
+///     // Skipping scales up what remains: at the corner, 9 of the 16 parts are inside.
+///     let skipped = rows(&blur(&heat, &grid, EdgeMode::Skip, VacantTiles::Skip), &grid);
+///     assert!((skipped[0][0].unwrap() - 4.0 * 16.0 / 9.0).abs() < 1e-5);
+///     assert!((skipped[0][1].unwrap() - 2.0 * 16.0 / 12.0).abs() < 1e-5);
+///     assert_eq!(skipped[2][2], Some(0.0));
+///
+///     // A uniform field with a hole in the middle.
+///     let mut heat = Layer::default();
+///     for id in 0..25 {
+///         heat.insert(TileId(id), Heat(8.0));
+///     }
+///     heat.remove(grid.id(2, 2).unwrap());
+///
+///     let zero = rows(&blur(&heat, &grid, EdgeMode::Clamp, VacantTiles::Zero), &grid);
+///     assert_eq!(zero[1][1..4].to_vec(), vec![Some(7.5), Some(7.0), Some(7.5)]);
+///     assert_eq!(zero[2][2], None);
+///     let skip = rows(&blur(&heat, &grid, EdgeMode::Clamp, VacantTiles::Skip), &grid);
+///     assert_eq!(skip[1][1..4].to_vec(), vec![Some(8.0), Some(8.0), Some(8.0)]);
+/// }
+/// ```
+pub fn convolve<C, D, I, E, A, const K: usize>(
+    src: &Storage<C, D, I>,
+    grid: &Grid2d,
+    kernel: &[[f32; K]; K],
+    extract: E,
+    apply: A,
+    edge: EdgeMode,
+    vacant: VacantTiles,
+) -> Storage<C, D, I>
+where
+    D: UnprotectedStorage<C> + Default,
+    I: Id,
+    E: Fn(&C) -> f32,
+    A: Fn(&C, f32) -> C,
+{
+    assert!(K % 2 == 1, "a kernel needs an odd size, but it's {}", K);
+
+    let (width, height) = (grid.width(), grid.height());
+    let values: Vec<Option<f32>> = (0..grid.len())
+        .map(|id| src.get(I::from_u32(id)).map(&extract))
+        .collect();
+    let total: f32 = kernel.iter().flat_map(|row| row.iter()).sum();
+    let radius = (K / 2) as i64;
+
+    let mut out = Storage::default();
+    for y in 0..height {
+        for x in 0..width {
+            let id = I::from_u32(y * width + x);
+            let comp = match src.get(id) {
+                Some(comp) => comp,
+                None => continue,
+            };
+
+            let (mut sum, mut used) = (0.0, 0.0);
+            for (ky, weights) in kernel.iter().enumerate() {
+                let ny = match edge.resolve(i64::from(y) + ky as i64 - radius, height) {
+                    Some(ny) => ny,
+                    None => continue,
+                };
+                let row = &values[ny * width as usize..(ny + 1) * width as usize];
+                for (kx, &weight) in weights.iter().enumerate() {
+                    let nx = match edge.resolve(i64::from(x) + kx as i64 - radius, width) {
+                        Some(nx) => nx,
+                        None => continue,
+                    };
+                    match (row[nx], vacant) {
+                        (Some(value), _) => sum += weight * value,
+                        (None, VacantTiles::Zero) => {}
+                        (None, VacantTiles::Skip) => continue,
+                    }
+                    used += weight;
+                }
+            }
+            if total != 0.0 && used != 0.0 && used != total {
+                sum *= total / used;
+            }
+
+            out.insert(id, apply(comp, sum));
+        }
+    }
+
+    out
+}
@@ -0,0 +1,287 @@
+//! A chunked backend whose chunks are shared between snapshots and copied on write.
+
+use std::marker::PhantomData;
+use std::mem;
+use std::sync::Arc;
+
+use hibitset::{BitSet, BitSetLike, BitSetOr};
+use specs::storage::UnprotectedStorage;
+
+use {Id, Index, ReserveIds, Storage, CHUNK_SIZE};
+
+/// A chunk of slots, together with the amount of occupied slots.
+#[derive(Clone)]
+pub(crate) struct Chunk<C> {
+    pub(crate) slots: Vec<Option<C>>,
+    len: u32,
+}
+
+/// A storage backend, chunked like `ChunkedVecStorage`, whose chunks are reference counted.
+///
+/// Taking a snapshot of a `Storage` using this backend only clones the chunk handles.
+/// Mutating a chunk that's shared with a snapshot copies just that chunk, so the memory cost
+/// of a snapshot is proportional to the chunks touched since it was taken.
+///
+/// Because mutable access may replace a chunk, this backend doesn't implement
+/// `DistinctStorage` and can't be used for parallel mutable joins.
+#[derive(Derivative)]
+#[derivative(Default(bound = ""))]
+pub struct CowStorage<C> {
+    pub(crate) chunks: Vec<Option<Arc<Chunk<C>>>>,
+}
+
+impl<C> CowStorage<C> {
+    /// Returns the number of chunks that are currently allocated.
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.iter().filter(|chunk| chunk.is_some()).count()
+    }
+
+    /// Returns the number of allocated chunks which are not shared with any snapshot.
+    pub fn unique_chunk_count(&self) -> usize {
+        self.chunks
+            .iter()
+            .filter(|chunk| match **chunk {
+                Some(ref chunk) => Arc::strong_count(chunk) == 1,
+                None => false,
+            })
+            .count()
+    }
+
+    pub(crate) fn split(id: Index) -> (usize, usize) {
+        ((id / CHUNK_SIZE) as usize, (id % CHUNK_SIZE) as usize)
+    }
+}
+
+impl<C> CowStorage<C>
+where
+    C: Clone,
+{
+    /// Returns the chunk containing `id`, making sure it's not shared with any snapshot.
+    fn chunk_mut(&mut self, id: Index) -> &mut Chunk<C> {
+        let chunk = (id / CHUNK_SIZE) as usize;
+        if self.chunks.len() <= chunk {
+            let delta = chunk + 1 - self.chunks.len();
+            self.chunks.extend((0..delta).map(|_| None));
+        }
+
+        let chunk = self.chunks[chunk].get_or_insert_with(|| {
+            Arc::new(Chunk {
+                slots: vec![None; CHUNK_SIZE as usize],
+                len: 0,
+            })
+        });
+
+        Arc::make_mut(chunk)
+    }
+}
+
+// Not `DistinctStorage`: `get_mut` can replace a chunk which is shared with a snapshot by
+// a copy, which would race with the accesses to other ids of that chunk.
+impl<C> UnprotectedStorage<C> for CowStorage<C>
+where
+    C: Clone,
+{
+    unsafe fn clean<B>(&mut self, _has: B)
+    where
+        B: BitSetLike,
+    {
+        self.chunks.clear();
+    }
+
+    unsafe fn get(&self, id: Index) -> &C {
+        let (chunk, slot) = Self::split(id);
+
+        self.chunks[chunk]
+            .as_ref()
+            .and_then(|chunk| chunk.slots[slot].as_ref())
+            .expect("accessed a vacant slot")
+    }
+
+    unsafe fn get_mut(&mut self, id: Index) -> &mut C {
+        let slot = Self::split(id).1;
+
+        self.chunk_mut(id).slots[slot]
+            .as_mut()
+            .expect("accessed a vacant slot")
+    }
+
+    unsafe fn insert(&mut self, id: Index, value: C) {
+        let slot = Self::split(id).1;
+        let chunk = self.chunk_mut(id);
+
+        if chunk.slots[slot].replace(value).is_none() {
+            chunk.len += 1;
+        }
+    }
+
+    unsafe fn remove(&mut self, id: Index) -> C {
+        let (index, slot) = Self::split(id);
+        let (value, empty) = {
+            let chunk = self.chunk_mut(id);
+            let value = chunk.slots[slot].take().expect("accessed a vacant slot");
+            chunk.len -= 1;
+
+            (value, chunk.len == 0)
+        };
+
+        if empty {
+            self.chunks[index] = None;
+        }
+
+        value
+    }
+}
+
+/// Only the chunk table is grown; the chunks themselves are still allocated on demand.
+impl<C> ReserveIds for CowStorage<C> {
+    fn reserve_ids(&mut self, len: u32) {
+        let chunks = len.div_ceil(CHUNK_SIZE) as usize;
+        self.chunks.reserve(chunks.saturating_sub(self.chunks.len()));
+    }
+}
+
+/// The state of a `Storage` backed by `CowStorage` at some point in time.
+///
+/// Snapshots share all chunks which haven't been modified since, and any number of them
+/// can be alive at the same time.
+pub struct StorageSnapshot<C, I> {
+    chunks: Vec<Option<Arc<Chunk<C>>>>,
+    bitset: BitSet,
+    phantom: PhantomData<I>,
+}
+
+impl<C, I> Clone for StorageSnapshot<C, I> {
+    fn clone(&self) -> Self {
+        StorageSnapshot {
+            chunks: self.chunks.clone(),
+            bitset: self.bitset.clone(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<C, I> Storage<C, CowStorage<C>, I>
+where
+    C: Clone,
+    I: Id,
+{
+    /// Takes a snapshot of the current state. This only clones the chunk handles and the
+    /// bitset; chunks are copied lazily once they're mutated.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, VecStorage};
+    /// use specs_static::{CowStorage, Storage};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// #[derive(Clone, Debug, PartialEq)]
+    /// struct Wall(u32);
+    ///
+    /// impl Component for Wall {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut walls = Storage::<Wall, CowStorage<_>, TileId>::default();
+    ///     walls.insert(TileId(1), Wall(10));
+    ///     walls.insert(TileId(5000), Wall(20));
+    ///
+    ///     let first = walls.snapshot();
+    ///     walls.get_mut(TileId(1)).unwrap().0 = 11;
+    ///     // Only the touched chunk has been copied.
+    ///     assert_eq!(walls.unprotected_storage().unique_chunk_count(), 1);
+    ///
+    ///     let second = walls.snapshot();
+    ///     walls.remove(TileId(5000));
+    ///     walls.insert(TileId(7), Wall(30));
+    ///
+    ///     walls.restore(&first);
+    ///     assert_eq!(walls.get(TileId(1)), Some(&Wall(10)));
+    ///     assert_eq!(walls.get(TileId(5000)), Some(&Wall(20)));
+    ///     assert_eq!(walls.get(TileId(7)), None);
+    ///
+    ///     walls.restore(&second);
+    ///     assert_eq!(walls.get(TileId(1)), Some(&Wall(11)));
+    ///     assert_eq!(walls.get(TileId(5000)), Some(&Wall(20)));
+    /// }
+    /// ```
+    pub fn snapshot(&self) -> StorageSnapshot<C, I> {
+        StorageSnapshot {
+            chunks: self.data.chunks.clone(),
+            bitset: self.bitset.clone(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Rolls back to the state of `snapshot`. The snapshot stays valid and can be restored
+    /// again later.
+    ///
+    /// Like `clear`, this stamps every id which was occupied before or is occupied afterwards,
+    /// and makes the next `clear_budgeted` start over.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, Join, VecStorage};
+    /// use specs_static::{CowStorage, Storage};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// #[derive(Clone, Debug, PartialEq)]
+    /// struct Wall(u32);
+    ///
+    /// impl Component for Wall {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut walls = Storage::<Wall, CowStorage<_>, TileId>::default();
+    ///     for id in 0..10 {
+    ///         walls.insert(TileId(id), Wall(id));
+    ///     }
+    ///     let snapshot = walls.snapshot();
+    ///     walls.remove(TileId(3));
+    ///     walls.insert(TileId(20), Wall(20));
+    ///
+    ///     walls.enable_epochs();
+    ///     let seen = walls.current_epoch();
+    ///     walls.advance_epoch();
+    ///     assert!(!walls.clear_budgeted(4));
+    ///
+    ///     walls.restore(&snapshot);
+    ///     assert!(walls.changed_since(TileId(3), seen));
+    ///     assert!(walls.changed_since(TileId(9), seen));
+    ///     assert!(walls.changed_since(TileId(20), seen));
+    ///
+    ///     // The budgeted clear starts over instead of resuming in the restored contents.
+    ///     assert!(!walls.clear_budgeted(4));
+    ///     assert_eq!(walls.get(TileId(3)), None);
+    ///     assert_eq!(walls.get(TileId(4)), Some(&Wall(4)));
+    ///     while !walls.clear_budgeted(4) {}
+    ///     assert_eq!((&walls).join().count(), 0);
+    /// }
+    /// ```
+    pub fn restore(&mut self, snapshot: &StorageSnapshot<C, I>) {
+        if self.observers.has_remove() {
+            for id in &self.bitset {
+                self.observers.removed(id, unsafe { self.data.get(id) });
+            }
+        }
+        self.data.chunks = snapshot.chunks.clone();
+        let old = mem::replace(&mut self.bitset, snapshot.bitset.clone());
+        self.epoch.bump();
+        self.stamps.stamp_all(BitSetOr(&old, &self.bitset));
+        self.clear_cursor = None;
+        if self.observers.has_insert() {
+            for id in &self.bitset {
+                self.observers.inserted(id, unsafe { self.data.get(id) });
+            }
+        }
+    }
+}
@@ -0,0 +1,446 @@
+//! Exporting layers to CSV and importing them again, for editing them in spreadsheets.
+
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::io::{self, Read, Write};
+use std::mem;
+use std::str::FromStr;
+
+use specs::storage::UnprotectedStorage;
+
+use {Grid2d, Id, Storage};
+
+/// Components which can be written to a CSV cell by `Storage::to_csv`.
+///
+/// Implemented for everything which implements `Display`, like numbers and strings. Quoting
+/// is done by `to_csv`, so implementations return the plain text of the cell.
+pub trait ToCsvCell {
+    /// Returns the text of the cell.
+    fn to_csv_cell(&self) -> String;
+}
+
+impl<T: Display> ToCsvCell for T {
+    fn to_csv_cell(&self) -> String {
+        self.to_string()
+    }
+}
+
+/// Components which can be read from a CSV cell by `Storage::from_csv`.
+///
+/// Implemented for everything which implements `FromStr` with a displayable error, like
+/// numbers and strings.
+pub trait FromCsvCell: Sized {
+    /// Parses the unquoted text of a cell, which is never empty, or describes why it can't.
+    fn from_csv_cell(cell: &str) -> Result<Self, String>;
+}
+
+impl<T> FromCsvCell for T
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    fn from_csv_cell(cell: &str) -> Result<Self, String> {
+        cell.parse().map_err(|e: T::Err| e.to_string())
+    }
+}
+
+/// The error returned by `Storage::from_csv`.
+///
+/// Rows and columns are counted from 0, like the coordinates of a grid, so spreadsheets
+/// show row `y` as row `y + 1`.
+#[derive(Debug)]
+pub enum CsvError {
+    /// Reading failed, or the input isn't valid UTF-8.
+    Io {
+        /// Why reading failed.
+        error: io::Error,
+    },
+    /// A quote in the row isn't closed, or appears in the middle of an unquoted cell.
+    Quote {
+        /// The row of the quote.
+        row: u32,
+    },
+    /// The input doesn't have as many rows as the grid.
+    RowCount {
+        /// The height of the grid.
+        expected: u32,
+        /// The amount of rows of the input.
+        found: usize,
+    },
+    /// A row doesn't have as many cells as the grid has columns.
+    ColumnCount {
+        /// The row with the wrong amount of cells.
+        row: u32,
+        /// The width of the grid.
+        expected: u32,
+        /// The amount of cells of the row.
+        found: usize,
+    },
+    /// A cell which isn't empty couldn't be parsed as a component.
+    Cell {
+        /// The column of the cell.
+        x: u32,
+        /// The row of the cell.
+        y: u32,
+        /// Why parsing failed.
+        message: String,
+    },
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CsvError::Io { ref error } => write!(f, "failed to read CSV: {}", error),
+            CsvError::Quote { row } => write!(f, "misplaced or unclosed quote in row {}", row),
+            CsvError::RowCount { expected, found } => {
+                write!(f, "expected {} rows, found {}", expected, found)
+            }
+            CsvError::ColumnCount {
+                row,
+                expected,
+                found,
+            } => write!(f, "expected {} cells in row {}, found {}", expected, row, found),
+            CsvError::Cell { x, y, ref message } => {
+                write!(f, "cell ({}, {}) isn't a valid component: {}", x, y, message)
+            }
+        }
+    }
+}
+
+impl Error for CsvError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            CsvError::Io { ref error } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl<C, D, I> Storage<C, D, I>
+where
+    D: UnprotectedStorage<C>,
+    I: Id,
+{
+    /// Writes the tiles of `grid` to `w` as CSV, one record per row with a cell per column,
+    /// leaving the cells of vacant tiles empty.
+    ///
+    /// Cells containing commas, quotes or line breaks are quoted, and records end with
+    /// `\r\n`, as in RFC 4180. Components outside of the grid are left out, and components
+    /// whose cells are empty read back as vacant tiles.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, VecStorage};
+    /// use specs_static::{FromCsvCell, Grid2d, Storage, ToCsvCell};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// #[derive(Debug, PartialEq)]
+    /// struct Richness(f32);
+    ///
+    /// impl Component for Richness {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// impl ToCsvCell for Richness {
+    ///     fn to_csv_cell(&self) -> String {
+    ///         self.0.to_csv_cell()
+    ///     }
+    /// }
+    ///
+    /// impl FromCsvCell for Richness {
+    ///     fn from_csv_cell(cell: &str) -> Result<Self, String> {
+    ///         f32::from_csv_cell(cell).map(Richness)
+    ///     }
+    /// }
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// struct Label(String);
+    ///
+    /// impl Component for Label {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// impl ToCsvCell for Label {
+    ///     fn to_csv_cell(&self) -> String {
+    ///         self.0.clone()
+    ///     }
+    /// }
+    ///
+    /// impl FromCsvCell for Label {
+    ///     fn from_csv_cell(cell: &str) -> Result<Self, String> {
+    ///         Ok(Label(cell.to_owned()))
+    ///     }
+    /// }
+    ///
+    /// type Richnesses = Storage<Richness, VecStorage<Richness>, TileId>;
+    /// type Labels = Storage<Label, VecStorage<Label>, TileId>;
+    ///
+    /// fn main() {
+    ///     let grid = Grid2d::new(3, 2);
+    ///     let mut richness = Richnesses::default();
+    ///     richness.insert(grid.id(0, 0).unwrap(), Richness(0.5));
+    ///     richness.insert(grid.id(2, 0).unwrap(), Richness(2.0));
+    ///     richness.insert(grid.id(1, 1).unwrap(), Richness(1.25));
+    ///
+    ///     let mut csv = Vec::new();
+    ///     richness.to_csv(&grid, &mut csv).unwrap();
+    ///     assert_eq!(String::from_utf8(csv.clone()).unwrap(), "0.5,,2\r\n,1.25,\r\n");
+    ///
+    ///     let back = Richnesses::from_csv(&grid, &mut &csv[..]).unwrap();
+    ///     for id in 0..grid.len() {
+    ///         assert_eq!(back.get(TileId(id)), richness.get(TileId(id)));
+    ///     }
+    ///
+    ///     // Text with separators, quotes and line breaks survives the round trip.
+    ///     let mut labels = Labels::default();
+    ///     labels.insert(TileId(0), Label("gold, lots".to_owned()));
+    ///     labels.insert(TileId(1), Label("the \"big\" one".to_owned()));
+    ///     labels.insert(TileId(5), Label("two\nlines".to_owned()));
+    ///     let mut csv = Vec::new();
+    ///     labels.to_csv(&grid, &mut csv).unwrap();
+    ///     assert_eq!(
+    ///         String::from_utf8(csv.clone()).unwrap(),
+    ///         "\"gold, lots\",\"the \"\"big\"\" one\",\r\n,,\"two\nlines\"\r\n"
+    ///     );
+    ///     let back = Labels::from_csv(&grid, &mut &csv[..]).unwrap();
+    ///     for id in 0..grid.len() {
+    ///         assert_eq!(back.get(TileId(id)), labels.get(TileId(id)));
+    ///     }
+    /// }
+    /// ```
+    pub fn to_csv<W>(&self, grid: &Grid2d, w: &mut W) -> io::Result<()>
+    where
+        C: ToCsvCell,
+        W: Write,
+    {
+        let mut line = String::new();
+        for y in 0..grid.height() {
+            line.clear();
+            for x in 0..grid.width() {
+                if x != 0 {
+                    line.push(',');
+                }
+
+                let id = y * grid.width() + x;
+                if self.bitset.contains(id) {
+                    push_cell(&mut line, &unsafe { self.data.get(id) }.to_csv_cell());
+                }
+            }
+            line.push_str("\r\n");
+            w.write_all(line.as_bytes())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<C, D, I> Storage<C, D, I>
+where
+    C: FromCsvCell,
+    D: UnprotectedStorage<C> + Default,
+    I: Id,
+{
+    /// Creates a storage from CSV with a record per row of `grid` and a cell per column, as
+    /// written by `to_csv`. Empty cells become vacant tiles.
+    ///
+    /// Records may end with `\r\n` or `\n`, and quoted cells may contain both. The input
+    /// has to match the dimensions of the grid exactly, so a sheet which lost or gained a
+    /// row or column is rejected instead of being loaded shifted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, VecStorage};
+    /// use specs_static::{CsvError, FromCsvCell, Grid2d, Storage};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// #[derive(Debug, PartialEq)]
+    /// struct SpawnWeight(u8);
+    ///
+    /// impl Component for SpawnWeight {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// impl FromCsvCell for SpawnWeight {
+    ///     fn from_csv_cell(cell: &str) -> Result<Self, String> {
+    ///         u8::from_csv_cell(cell).map(SpawnWeight)
+    ///     }
+    /// }
+    ///
+    /// type SpawnWeights = Storage<SpawnWeight, VecStorage<SpawnWeight>, TileId>;
+    ///
+    /// fn main() {
+    ///     let grid = Grid2d::new(3, 5);
+    ///     let load = |csv: &str| SpawnWeights::from_csv(&grid, &mut csv.as_bytes());
+    ///
+    ///     // As saved by a spreadsheet, with `\n` line breaks and a quoted number.
+    ///     let weights = load("1,,3\n,,\n\"7\",8,9\n,,\n,,2\n").unwrap();
+    ///     assert_eq!(weights.get(grid.id(0, 0).unwrap()), Some(&SpawnWeight(1)));
+    ///     assert_eq!(weights.get(grid.id(1, 0).unwrap()), None);
+    ///     assert_eq!(weights.get(grid.id(0, 2).unwrap()), Some(&SpawnWeight(7)));
+    ///     assert_eq!(weights.get(grid.id(2, 4).unwrap()), Some(&SpawnWeight(2)));
+    ///
+    ///     // A cell too many in row 3.
+    ///     match load("1,,3\n,,\n7,8,9\n,,,\n,,2\n") {
+    ///         Err(CsvError::ColumnCount { row: 3, expected: 3, found: 4 }) => {}
+    ///         other => panic!("unexpected result {:?}", other.err()),
+    ///     }
+    ///     match load("1,,3\n,,\n7,8,9\n,,\n") {
+    ///         Err(CsvError::RowCount { expected: 5, found: 4 }) => {}
+    ///         other => panic!("unexpected result {:?}", other.err()),
+    ///     }
+    ///     match load("1,,3\n,,\n7,800,9\n,,\n,,2\n") {
+    ///         Err(error @ CsvError::Cell { x: 1, y: 2, .. }) => {
+    ///             let message = error.to_string();
+    ///             assert!(message.starts_with("cell (1, 2) isn't a valid component: "));
+    ///             assert!(message.ends_with("number too large to fit in target type"));
+    ///         }
+    ///         other => panic!("unexpected result {:?}", other.err()),
+    ///     }
+    ///     match load("1,,3\n,,\n7,8,9\n,\"4,\n,,2\n") {
+    ///         Err(CsvError::Quote { row: 3 }) => {}
+    ///         other => panic!("unexpected result {:?}", other.err()),
+    ///     }
+    ///     match load("1,,3\n,,\n7,8,9\n,4\"\",\n,,2\n") {
+    ///         Err(CsvError::Quote { row: 3 }) => {}
+    ///         other => panic!("unexpected result {:?}", other.err()),
+    ///     }
+    /// }
+    /// ```
+    pub fn from_csv<R>(grid: &Grid2d, r: &mut R) -> Result<Self, CsvError>
+    where
+        R: Read,
+    {
+        let mut text = String::new();
+        r.read_to_string(&mut text)
+            .map_err(|error| CsvError::Io { error })?;
+        let records = parse_records(&text)?;
+        if records.len() != grid.height() as usize {
+            return Err(CsvError::RowCount {
+                expected: grid.height(),
+                found: records.len(),
+            });
+        }
+
+        let mut storage = Storage::default();
+        for (y, record) in records.iter().enumerate() {
+            let y = y as u32;
+            if record.len() != grid.width() as usize {
+                return Err(CsvError::ColumnCount {
+                    row: y,
+                    expected: grid.width(),
+                    found: record.len(),
+                });
+            }
+
+            for (x, cell) in record.iter().enumerate() {
+                let x = x as u32;
+                if cell.is_empty() {
+                    continue;
+                }
+
+                let comp =
+                    C::from_csv_cell(cell).map_err(|message| CsvError::Cell { x, y, message })?;
+                storage.insert(I::from_u32(y * grid.width() + x), comp);
+            }
+        }
+
+        Ok(storage)
+    }
+}
+
+/// Appends `cell` to `line`, quoted if it contains a separator, a quote or a line break.
+fn push_cell(line: &mut String, cell: &str) {
+    if !cell.contains([',', '"', '\n', '\r']) {
+        line.push_str(cell);
+        return;
+    }
+
+    line.push('"');
+    for c in cell.chars() {
+        if c == '"' {
+            line.push('"');
+        }
+        line.push(c);
+    }
+    line.push('"');
+}
+
+/// Splits `text` into records of unquoted cells. A line break at the very end doesn't start
+/// another record.
+fn parse_records(text: &str) -> Result<Vec<Vec<String>>, CsvError> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut cell = String::new();
+    // Whether the current record has any content, even if it's a single empty cell.
+    let mut in_record = false;
+    let mut in_quotes = false;
+    // Whether the current cell has been quoted and closed, so only a separator may follow.
+    let mut closed = false;
+
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let quote_error = || CsvError::Quote {
+            row: records.len() as u32,
+        };
+
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    cell.push('"');
+                }
+                '"' => {
+                    in_quotes = false;
+                    closed = true;
+                }
+                c => cell.push(c),
+            }
+            continue;
+        }
+
+        match c {
+            ',' => {
+                record.push(mem::take(&mut cell));
+                closed = false;
+                in_record = true;
+            }
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                record.push(mem::take(&mut cell));
+                records.push(mem::take(&mut record));
+                closed = false;
+                in_record = false;
+            }
+            '"' if cell.is_empty() && !closed => {
+                in_quotes = true;
+                in_record = true;
+            }
+            _ if closed => return Err(quote_error()),
+            '"' => return Err(quote_error()),
+            c => {
+                cell.push(c);
+                in_record = true;
+            }
+        }
+    }
+
+    if in_quotes {
+        return Err(CsvError::Quote {
+            row: records.len() as u32,
+        });
+    }
+    if in_record {
+        record.push(cell);
+        records.push(record);
+    }
+
+    Ok(records)
+}
@@ -0,0 +1,377 @@
+//! Cursors for repeatedly accessing the tiles around a position which moves in small steps.
+
+use specs::storage::UnprotectedStorage;
+
+use {Grid2d, Id, IdSpace, Index, Storage};
+
+/// The position of a cursor.
+#[derive(Clone, Copy, Debug)]
+struct Position {
+    width: u32,
+    height: u32,
+    x: u32,
+    y: u32,
+    id: Index,
+}
+
+impl Position {
+    fn new<S: ?Sized>(grid: &Grid2d<S>) -> Self {
+        assert!(!grid.is_empty(), "a cursor needs a grid with at least one tile");
+
+        Position {
+            width: grid.width(),
+            height: grid.height(),
+            x: 0,
+            y: 0,
+            id: 0,
+        }
+    }
+
+    fn move_to(&mut self, x: u32, y: u32) {
+        assert!(
+            x < self.width && y < self.height,
+            "({}, {}) is outside of the {}x{} grid",
+            x,
+            y,
+            self.width,
+            self.height
+        );
+
+        self.x = x;
+        self.y = y;
+        self.id = y * self.width + x;
+    }
+
+    #[inline]
+    fn step(&mut self, dx: i32, dy: i32) -> bool {
+        match self.offset(dx, dy) {
+            Some(id) => {
+                self.x = (self.x as i64 + dx as i64) as u32;
+                self.y = (self.y as i64 + dy as i64) as u32;
+                self.id = id;
+
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the id of the tile `(dx, dy)` away, or `None` if it's outside the grid.
+    #[inline]
+    fn offset(&self, dx: i32, dy: i32) -> Option<Index> {
+        let x = self.x as i64 + dx as i64;
+        let y = self.y as i64 + dy as i64;
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return None;
+        }
+
+        Some((self.id as i64 + dy as i64 * self.width as i64 + dx as i64) as Index)
+    }
+}
+
+impl<C, D, I> Storage<C, D, I>
+where
+    D: UnprotectedStorage<C>,
+    I: Id,
+{
+    /// Returns a cursor at the tile `(0, 0)` of `grid`, for looking at the tiles around a
+    /// position which moves in small steps, like the one of an agent.
+    ///
+    /// The cursor keeps its coordinates and its id, so relative lookups with `peek` are an
+    /// offset from the id plus the checks whether the tile is inside the grid, which don't
+    /// wrap around the edges of rows. Looking a tile up is as fast as `Storage::get` already,
+    /// since the bitset checks the lowest layer directly; the cursor saves the bookkeeping of
+    /// coordinates and ids.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the grid is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, VecStorage};
+    /// use specs_static::{Grid2d, Id, Storage};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// #[derive(Debug, PartialEq)]
+    /// struct Food(u32);
+    ///
+    /// impl Component for Food {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     // Rows of 100 tiles, so rows and words of the bitset end at different tiles.
+    ///     let grid = Grid2d::new(100, 3);
+    ///     let mut food = Storage::<Food, VecStorage<_>, TileId>::default();
+    ///     for &(x, y) in &[(62, 0), (63, 0), (64, 0), (99, 0), (0, 1), (63, 1), (99, 2)] {
+    ///         food.insert(grid.id(x, y).unwrap(), Food(x + 1000 * y));
+    ///     }
+    ///
+    ///     let mut cursor = food.cursor(&grid);
+    ///     assert_eq!(cursor.get(), None);
+    ///     cursor.move_to(63, 0);
+    ///     assert_eq!(cursor.get(), Some(&Food(63)));
+    ///     // Across the end of the word, and into the rows above and below.
+    ///     assert_eq!(cursor.peek(-1, 0), Some(&Food(62)));
+    ///     assert_eq!(cursor.peek(1, 0), Some(&Food(64)));
+    ///     assert_eq!(cursor.peek(0, 1), Some(&Food(1063)));
+    ///     assert_eq!(cursor.peek(0, -1), None);
+    ///     assert_eq!(cursor.peek(36, 2), Some(&Food(2099)));
+    ///
+    ///     // Across the end of the row, without wrapping around to the next one.
+    ///     cursor.move_to(99, 0);
+    ///     assert_eq!(cursor.get(), Some(&Food(99)));
+    ///     assert_eq!(cursor.peek(1, 0), None);
+    ///     assert_eq!(cursor.peek(-99, 1), Some(&Food(1000)));
+    ///     assert!(cursor.step(-99, 1));
+    ///     assert_eq!((cursor.position(), cursor.id()), ((0, 1), TileId(100)));
+    ///     assert_eq!(cursor.get(), Some(&Food(1000)));
+    ///     assert!(!cursor.step(-1, 0));
+    ///     assert_eq!(cursor.position(), (0, 1));
+    ///
+    ///     // Every tile agrees with `get`.
+    ///     for id in 0..grid.len() {
+    ///         let (x, y) = grid.coords(TileId(id)).unwrap();
+    ///         cursor.move_to(x, y);
+    ///         assert_eq!(cursor.get(), food.get(TileId(id)));
+    ///     }
+    /// }
+    /// ```
+    pub fn cursor<S>(&self, grid: &Grid2d<S>) -> TileCursor<'_, C, D, I>
+    where
+        S: ?Sized,
+        I: IdSpace<S>,
+    {
+        TileCursor {
+            position: Position::new(grid),
+            storage: self,
+        }
+    }
+
+    /// Returns a cursor like `cursor`, which can also change the components.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, VecStorage};
+    /// use specs_static::{Grid2d, Storage};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// #[derive(Debug, PartialEq)]
+    /// struct Scent(u32);
+    ///
+    /// impl Component for Scent {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let grid = Grid2d::new(70, 2);
+    ///     let mut scents = Storage::<Scent, VecStorage<_>, TileId>::default();
+    ///
+    ///     // An ant walking along the first row, leaving scent and following it back.
+    ///     let mut cursor = scents.cursor_mut(&grid);
+    ///     cursor.insert(Scent(10));
+    ///     while cursor.step(1, 0) {
+    ///         cursor.insert(Scent(10));
+    ///     }
+    ///     assert_eq!(cursor.position(), (69, 0));
+    ///     assert_eq!(cursor.peek(-69, 0), Some(&Scent(10)));
+    ///     while cursor.step(-1, 0) {
+    ///         if let Some(scent) = cursor.peek_mut(0, 0) {
+    ///             scent.0 -= 1;
+    ///         }
+    ///     }
+    ///     *cursor.get_mut().unwrap() = Scent(0);
+    ///     cursor.move_to(65, 1);
+    ///     assert_eq!(cursor.remove(), None);
+    ///     assert_eq!(cursor.peek(0, -1), Some(&Scent(9)));
+    ///     cursor.move_to(65, 0);
+    ///     assert_eq!(cursor.remove(), Some(Scent(9)));
+    ///     assert_eq!(cursor.get(), None);
+    ///     assert_eq!(cursor.peek(-1, 0), Some(&Scent(9)));
+    ///
+    ///     assert_eq!(scents.get(TileId(0)), Some(&Scent(0)));
+    ///     assert_eq!(scents.get(TileId(69)), Some(&Scent(10)));
+    ///     assert_eq!(scents.get(TileId(65)), None);
+    ///     assert_eq!((0..140).filter(|&id| scents.get(TileId(id)).is_some()).count(), 69);
+    /// }
+    /// ```
+    ///
+    /// The cursor hands out one mutable reference at a time:
+    ///
+    /// ```compile_fail
+    /// # extern crate specs;
+    /// # extern crate specs_static;
+    /// # use specs::{Component, VecStorage};
+    /// # use specs_static::{Grid2d, Storage};
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// # struct Scent(u32);
+    /// # impl Component for Scent {
+    /// #     type Storage = VecStorage<Self>;
+    /// # }
+    /// # fn main() {
+    /// let grid = Grid2d::new(8, 8);
+    /// let mut scents = Storage::<Scent, VecStorage<_>, TileId>::default();
+    /// let mut cursor = scents.cursor_mut(&grid);
+    /// let left = cursor.peek_mut(-1, 0);
+    /// let right = cursor.peek_mut(1, 0);
+    /// std::mem::swap(left.unwrap(), right.unwrap());
+    /// # }
+    /// ```
+    pub fn cursor_mut<S>(&mut self, grid: &Grid2d<S>) -> TileCursorMut<'_, C, D, I>
+    where
+        S: ?Sized,
+        I: IdSpace<S>,
+    {
+        TileCursorMut {
+            position: Position::new(grid),
+            storage: self,
+        }
+    }
+
+}
+
+/// A position on a grid with cached lookups of the components around it, see
+/// `Storage::cursor`.
+pub struct TileCursor<'a, C: 'a, D: UnprotectedStorage<C> + 'a, I: 'a> {
+    storage: &'a Storage<C, D, I>,
+    position: Position,
+}
+
+impl<'a, C, D, I> TileCursor<'a, C, D, I>
+where
+    D: UnprotectedStorage<C>,
+    I: Id,
+{
+    /// Returns the coordinates of the cursor.
+    pub fn position(&self) -> (u32, u32) {
+        (self.position.x, self.position.y)
+    }
+
+    /// Returns the id of the tile of the cursor.
+    pub fn id(&self) -> I {
+        I::from_u32(self.position.id)
+    }
+
+    /// Moves the cursor to `(x, y)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `(x, y)` is outside of the grid.
+    pub fn move_to(&mut self, x: u32, y: u32) {
+        self.position.move_to(x, y);
+    }
+
+    /// Moves the cursor by `(dx, dy)`, returning `false` without moving it if the target is
+    /// outside of the grid.
+    pub fn step(&mut self, dx: i32, dy: i32) -> bool {
+        self.position.step(dx, dy)
+    }
+
+    /// Returns the component at the cursor.
+    pub fn get(&self) -> Option<&'a C> {
+        self.storage.get(I::from_u32(self.position.id))
+    }
+
+    /// Returns the component `(dx, dy)` away from the cursor, or `None` if there is none or
+    /// the tile is outside of the grid.
+    pub fn peek(&self, dx: i32, dy: i32) -> Option<&'a C> {
+        let id = self.position.offset(dx, dy)?;
+
+        self.storage.get(I::from_u32(id))
+    }
+}
+
+impl<'a, C, D, I> Clone for TileCursor<'a, C, D, I>
+where
+    D: UnprotectedStorage<C>,
+{
+    fn clone(&self) -> Self {
+        TileCursor {
+            storage: self.storage,
+            position: self.position,
+        }
+    }
+}
+
+/// A position on a grid with cached lookups of the components around it, which can change
+/// them, see `Storage::cursor_mut`.
+pub struct TileCursorMut<'a, C: 'a, D: UnprotectedStorage<C> + 'a, I: 'a> {
+    storage: &'a mut Storage<C, D, I>,
+    position: Position,
+}
+
+impl<'a, C, D, I> TileCursorMut<'a, C, D, I>
+where
+    D: UnprotectedStorage<C>,
+    I: Id,
+{
+    /// Returns the coordinates of the cursor.
+    pub fn position(&self) -> (u32, u32) {
+        (self.position.x, self.position.y)
+    }
+
+    /// Returns the id of the tile of the cursor.
+    pub fn id(&self) -> I {
+        I::from_u32(self.position.id)
+    }
+
+    /// Moves the cursor to `(x, y)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `(x, y)` is outside of the grid.
+    pub fn move_to(&mut self, x: u32, y: u32) {
+        self.position.move_to(x, y);
+    }
+
+    /// Moves the cursor by `(dx, dy)`, returning `false` without moving it if the target is
+    /// outside of the grid.
+    pub fn step(&mut self, dx: i32, dy: i32) -> bool {
+        self.position.step(dx, dy)
+    }
+
+    /// Returns the component at the cursor.
+    pub fn get(&self) -> Option<&C> {
+        self.storage.get(I::from_u32(self.position.id))
+    }
+
+    /// Returns the component at the cursor mutably.
+    pub fn get_mut(&mut self) -> Option<&mut C> {
+        self.storage.get_mut(I::from_u32(self.position.id))
+    }
+
+    /// Returns the component `(dx, dy)` away from the cursor, or `None` if there is none or
+    /// the tile is outside of the grid.
+    pub fn peek(&self, dx: i32, dy: i32) -> Option<&C> {
+        let id = self.position.offset(dx, dy)?;
+
+        self.storage.get(I::from_u32(id))
+    }
+
+    /// Returns the component `(dx, dy)` away from the cursor mutably, see `peek`.
+    pub fn peek_mut(&mut self, dx: i32, dy: i32) -> Option<&mut C> {
+        let id = self.position.offset(dx, dy)?;
+
+        self.storage.get_mut(I::from_u32(id))
+    }
+
+    /// Inserts `comp` at the cursor like `Storage::insert`, returning the component it
+    /// replaces.
+    pub fn insert(&mut self, comp: C) -> Option<C> {
+        self.storage.insert(I::from_u32(self.position.id), comp)
+    }
+
+    /// Removes the component at the cursor like `Storage::remove`, returning it.
+    pub fn remove(&mut self) -> Option<C> {
+        self.storage.remove(I::from_u32(self.position.id))
+    }
+}
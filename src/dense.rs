@@ -0,0 +1,209 @@
+//! A storage for layers where every id below a bound always has a component.
+
+use std::marker::PhantomData;
+use std::mem;
+use std::ops::{Index as IndexOp, IndexMut};
+
+#[cfg(feature = "parallel")]
+use specs::ParJoin;
+use specs::Join;
+
+use join::JoinMut;
+use mask::MAX_LEN;
+use {Id, Index, RangeMask};
+
+/// A storage where every id in `0..len` has a component.
+///
+/// Because occupancy is guaranteed, `get` doesn't need to check a bitset and joins iterate
+/// a plain index range. Components can be replaced, but never removed.
+///
+/// `DenseStorage` implements `Default` (as an empty storage), so it can be registered with
+/// `TileWorldExt::register_dense_tile_comp` or fetched lazily like `Storage`.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::{Component, Join, VecStorage};
+/// use specs_static::{DenseStorage, Storage};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// #[derive(Clone, Copy, Debug, PartialEq)]
+/// struct Elevation(u16);
+///
+/// struct Tree;
+///
+/// impl Component for Tree {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// fn main() {
+///     let mut elevations = DenseStorage::<Elevation, TileId>::from_fn(64 * 64, |id| {
+///         Elevation(id.0 as u16 % 7)
+///     });
+///     elevations.set(TileId(10), Elevation(100));
+///     assert_eq!(*elevations.get(TileId(10)), Elevation(100));
+///
+///     let mut trees = Storage::<Tree, VecStorage<Tree>, TileId>::default();
+///     trees.insert(TileId(10), Tree);
+///     trees.insert(TileId(4000), Tree);
+///     trees.insert(TileId(5000), Tree);
+///
+///     let heights: Vec<u16> = (&elevations, &trees).join().map(|(e, _)| e.0).collect();
+///     assert_eq!(heights, vec![100, 4000 % 7]);
+/// }
+/// ```
+#[derive(Derivative)]
+#[derivative(Default(bound = ""))]
+pub struct DenseStorage<C, I> {
+    data: Vec<C>,
+    phantom: PhantomData<I>,
+}
+
+impl<C, I> DenseStorage<C, I>
+where
+    I: Id,
+{
+    /// Creates a storage from a `Vec`, where the component at index `n` belongs to the id `n`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `data` has more elements than ids can be joined over.
+    pub fn from_vec(data: Vec<C>) -> Self {
+        assert!(
+            data.len() as u64 <= MAX_LEN,
+            "`DenseStorage` length {} exceeds the maximum of {}",
+            data.len(),
+            MAX_LEN
+        );
+
+        DenseStorage {
+            data,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Creates a storage with `len` components, computing each of them from its id.
+    pub fn from_fn<F>(len: u32, mut f: F) -> Self
+    where
+        F: FnMut(I) -> C,
+    {
+        DenseStorage::from_vec((0..len).map(|id| f(I::from_u32(id))).collect())
+    }
+
+    /// Creates a storage with `len` components, all of them clones of `value`.
+    pub fn filled(len: u32, value: C) -> Self
+    where
+        C: Clone,
+    {
+        DenseStorage::from_vec(vec![value; len as usize])
+    }
+
+    /// Returns the amount of ids (and thus components) in this storage.
+    pub fn len(&self) -> u32 {
+        self.data.len() as u32
+    }
+
+    /// Returns `true` if the storage doesn't hold any component.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Returns `true` if `id` is in bounds.
+    pub fn contains(&self, id: I) -> bool {
+        id.id() < self.len()
+    }
+
+    /// Returns the component at `id`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` is out of bounds.
+    pub fn get(&self, id: I) -> &C {
+        &self.data[id.id() as usize]
+    }
+
+    /// Returns the component at `id` mutably.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` is out of bounds.
+    pub fn get_mut(&mut self, id: I) -> &mut C {
+        &mut self.data[id.id() as usize]
+    }
+
+    /// Replaces the component at `id`, returning the old one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` is out of bounds.
+    pub fn set(&mut self, id: I, comp: C) -> C {
+        mem::replace(self.get_mut(id), comp)
+    }
+
+    /// Returns the mask of all ids in this storage.
+    pub fn mask(&self) -> RangeMask {
+        RangeMask::new(self.len())
+    }
+
+    /// Returns the components as a `Vec`, where the component at index `n` belongs to the id `n`.
+    pub fn into_vec(self) -> Vec<C> {
+        self.data
+    }
+}
+
+impl<C, I> IndexOp<I> for DenseStorage<C, I>
+where
+    I: Id,
+{
+    type Output = C;
+
+    fn index(&self, id: I) -> &C {
+        self.get(id)
+    }
+}
+
+impl<C, I> IndexMut<I> for DenseStorage<C, I>
+where
+    I: Id,
+{
+    fn index_mut(&mut self, id: I) -> &mut C {
+        self.get_mut(id)
+    }
+}
+
+impl<'a, C, I> Join for &'a DenseStorage<C, I> {
+    type Type = &'a C;
+    type Value = &'a [C];
+    type Mask = RangeMask;
+
+    unsafe fn open(self) -> (Self::Mask, Self::Value) {
+        (RangeMask::new(self.data.len() as u32), &self.data)
+    }
+
+    unsafe fn get(value: &mut Self::Value, id: Index) -> Self::Type {
+        value.get_unchecked(id as usize)
+    }
+}
+
+impl<'a, C, I> Join for &'a mut DenseStorage<C, I> {
+    type Type = &'a mut C;
+    type Value = JoinMut<'a, C>;
+    type Mask = RangeMask;
+
+    unsafe fn open(self) -> (Self::Mask, Self::Value) {
+        (RangeMask::new(self.data.len() as u32), JoinMut::from_slice(&mut self.data))
+    }
+
+    unsafe fn get(value: &mut Self::Value, id: Index) -> Self::Type {
+        value.get_at(id as usize)
+    }
+}
+
+#[cfg(feature = "parallel")]
+unsafe impl<C, I> ParJoin for &DenseStorage<C, I> where C: Sync {}
+
+#[cfg(feature = "parallel")]
+unsafe impl<C, I> ParJoin for &mut DenseStorage<C, I> where C: Send + Sync {}
@@ -0,0 +1,403 @@
+//! A summary of all tile storages of a world, for debug overlays and bug reports.
+
+use std::error::Error;
+use std::fmt;
+use std::mem;
+
+use hibitset::{BitSet, BitSetLike};
+#[cfg(feature = "serde")]
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use shred::{Resource, ResourceId, World};
+use specs::storage::UnprotectedStorage;
+
+use mask::BITS;
+use {Grid2d, Id, Storage, TileRegistry};
+
+/// The state of every layer registered in the `TileRegistry<I>` of a world, returned by
+/// `TileWorldExt::tile_diagnostics`.
+///
+/// Taking it doesn't change anything, and storages which are borrowed mutably at the time,
+/// like by a running system, are skipped with a note instead of blocking or panicking.
+///
+/// It implements `Display` as an aligned table for logs and overlays, and `Serialize` with
+/// the `serde` feature, so it can be attached to crash reports.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::{Component, FlaggedStorage, NullStorage, VecStorage, World, WorldExt};
+/// use specs_static::{Grid2d, LayerSkipped, Storage, TileRegistry, TileWorldExt};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// struct Height(u32);
+///
+/// impl Component for Height {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// #[derive(Default)]
+/// struct Wall;
+///
+/// impl Component for Wall {
+///     type Storage = FlaggedStorage<Self, NullStorage<Self>>;
+/// }
+///
+/// struct Loot(u64);
+///
+/// impl Component for Loot {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// type Heights = Storage<Height, VecStorage<Height>, TileId>;
+/// type Walls = Storage<Wall, FlaggedStorage<Wall, NullStorage<Wall>>, TileId>;
+/// type Loots = Storage<Loot, VecStorage<Loot>, TileId>;
+///
+/// fn main() {
+///     let mut world = World::new();
+///     world.insert(Grid2d::new(100, 50));
+///     world.register_tile_comp_named::<Height, TileId>("height").unwrap();
+///     world.register_tile_comp::<Wall, TileId>();
+///     world.register_tile_comp::<Loot, TileId>();
+///     for id in 0..5000 {
+///         world.write_resource::<Heights>().insert(TileId(id), Height(0));
+///     }
+///     for &id in &[0, 99, 4900] {
+///         world.write_resource::<Walls>().insert(TileId(id), Wall);
+///     }
+///     world.fetch_mut::<TileRegistry<TileId>>().register_tracked::<Wall>();
+///
+///     // A system is busy with the loot.
+///     let mut loot = world.write_resource::<Loots>();
+///     loot.insert(TileId(7), Loot(100));
+///
+///     let report = world.tile_diagnostics::<TileId>();
+///     assert_eq!(report.grid, Some((100, 50)));
+///     assert_eq!(report.layers.len(), 3);
+///
+///     let height = &report.layers[0];
+///     assert_eq!(height.name.as_ref().map(String::as_str), Some("height"));
+///     assert!(!height.tracked);
+///     let stats = height.stats.as_ref().unwrap();
+///     assert_eq!(stats.occupied, 5000);
+///     assert_eq!(stats.highest_id, Some(4999));
+///     // Four bytes per height, plus the words of the bitset.
+///     assert_eq!(stats.estimated_bytes, 5000 * 4 + (79 + 2 + 1) * 8);
+///
+///     let wall = &report.layers[1];
+///     assert_eq!(wall.name, None);
+///     assert!(wall.type_name.ends_with("Wall"));
+///     assert!(wall.tracked);
+///     let stats = wall.stats.as_ref().unwrap();
+///     assert_eq!((stats.occupied, stats.highest_id), (3, Some(4900)));
+///     assert_eq!(stats.estimated_bytes, (77 + 2 + 1) * 8);
+///
+///     assert_eq!(report.layers[2].stats, Err(LayerSkipped::Borrowed));
+///     assert_eq!(report.occupied, 5003);
+///     assert_eq!(report.estimated_bytes, 20_656 + 640);
+///
+///     let table = report.to_string();
+///     println!("{}", table);
+///     assert!(table.lines().any(|line| line.starts_with("height") && line.contains("5000")));
+///     assert!(table.contains("skipped: borrowed mutably elsewhere"));
+///     assert!(table.contains("grid: 100x50"));
+///
+///     drop(loot);
+///     let stats = world.tile_diagnostics::<TileId>().layers[2].stats.clone().unwrap();
+///     assert_eq!((stats.occupied, stats.highest_id, stats.estimated_bytes), (1, Some(7), 32));
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TileDiagnostics {
+    /// The width and height of the `Grid2d` resource, if there is one which isn't borrowed
+    /// mutably.
+    pub grid: Option<(u32, u32)>,
+    /// The registered layers, in registration order.
+    pub layers: Vec<LayerDiagnostics>,
+    /// The amount of components in all layers which haven't been skipped.
+    pub occupied: usize,
+    /// The estimated bytes of all layers which haven't been skipped.
+    pub estimated_bytes: usize,
+}
+
+/// The state of one layer in a `TileDiagnostics`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LayerDiagnostics {
+    /// The name of the component type.
+    pub type_name: &'static str,
+    /// The name the layer is registered under, if any.
+    pub name: Option<String>,
+    /// Whether the layer has been marked with `TileRegistry::register_tracked`.
+    pub tracked: bool,
+    /// The numbers of the storage, or why they couldn't be taken.
+    pub stats: Result<LayerStats, LayerSkipped>,
+}
+
+impl LayerDiagnostics {
+    /// Returns the name of the layer, or its type name if it has none.
+    pub fn label(&self) -> &str {
+        self.name.as_ref().map_or(self.type_name, String::as_str)
+    }
+}
+
+/// The numbers of one storage in a `TileDiagnostics`.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct LayerStats {
+    /// The amount of components.
+    pub occupied: usize,
+    /// The highest occupied id, if there is any.
+    pub highest_id: Option<u32>,
+    /// The size of the components plus the words of the occupancy bitset up to the highest
+    /// id. Space the backend allocates on top of that, like the vacant slots of a
+    /// `VecStorage`, isn't included, so this is a lower bound.
+    pub estimated_bytes: usize,
+}
+
+impl LayerStats {
+    fn of(mask: &BitSet, component_size: usize) -> Self {
+        let occupied = mask.iter().count();
+        let highest_id = highest_id(mask);
+        let bitset_words = highest_id.map_or(0, |id| {
+            let id = id as usize;
+
+            (id >> BITS) + (id >> (2 * BITS)) + (id >> (3 * BITS)) + 3
+        });
+
+        LayerStats {
+            occupied,
+            highest_id,
+            estimated_bytes: occupied * component_size + bitset_words * mem::size_of::<usize>(),
+        }
+    }
+}
+
+/// Why a layer has been skipped in a `TileDiagnostics`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum LayerSkipped {
+    /// The storage is borrowed mutably, for example by a running system.
+    Borrowed,
+    /// The storage resource has been removed from the world.
+    Removed,
+}
+
+impl fmt::Display for LayerSkipped {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LayerSkipped::Borrowed => write!(f, "borrowed mutably elsewhere"),
+            LayerSkipped::Removed => write!(f, "storage resource removed"),
+        }
+    }
+}
+
+impl Error for LayerSkipped {}
+
+impl fmt::Display for TileDiagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let width = self
+            .layers
+            .iter()
+            .map(|layer| layer.label().len())
+            .chain(Some("layer".len()))
+            .max()
+            .unwrap_or(0);
+
+        writeln!(
+            f,
+            "{:<w$} {:>10} {:>12} {:>10} {:>7}",
+            "layer",
+            "occupied",
+            "est. bytes",
+            "highest id",
+            "tracked",
+            w = width
+        )?;
+        for layer in &self.layers {
+            let tracked = if layer.tracked { "yes" } else { "no" };
+            match layer.stats {
+                Ok(ref stats) => writeln!(
+                    f,
+                    "{:<w$} {:>10} {:>12} {:>10} {:>7}",
+                    layer.label(),
+                    stats.occupied,
+                    stats.estimated_bytes,
+                    stats.highest_id.map_or("-".to_owned(), |id| id.to_string()),
+                    tracked,
+                    w = width
+                )?,
+                Err(skipped) => writeln!(
+                    f,
+                    "{:<w$} skipped: {}",
+                    layer.label(),
+                    skipped,
+                    w = width
+                )?,
+            }
+        }
+        writeln!(
+            f,
+            "{:<w$} {:>10} {:>12}",
+            "total",
+            self.occupied,
+            self.estimated_bytes,
+            w = width
+        )?;
+
+        match self.grid {
+            Some((width, height)) => write!(f, "grid: {}x{}", width, height),
+            None => write!(f, "grid: none"),
+        }
+    }
+}
+
+/// # Examples
+///
+/// ```
+/// extern crate serde_json;
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::{Component, VecStorage, World, WorldExt};
+/// use specs_static::{Storage, TileWorldExt};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// struct Fog(u8);
+///
+/// impl Component for Fog {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// fn main() {
+///     let mut world = World::new();
+///     world.register_tile_comp_named::<Fog, TileId>("fog").unwrap();
+///     world.write_resource::<Storage<Fog, VecStorage<_>, TileId>>().insert(TileId(2), Fog(9));
+///
+///     let json = serde_json::to_value(world.tile_diagnostics::<TileId>()).unwrap();
+///     assert_eq!(json["grid"], serde_json::Value::Null);
+///     assert_eq!(json["occupied"], 1);
+///     assert_eq!(json["layers"][0]["name"], "fog");
+///     assert_eq!(json["layers"][0]["highest_id"], 2);
+///
+///     let fog = world.write_resource::<Storage<Fog, VecStorage<_>, TileId>>();
+///     let json = serde_json::to_value(world.tile_diagnostics::<TileId>()).unwrap();
+///     assert_eq!(json["layers"][0]["occupied"], serde_json::Value::Null);
+///     assert_eq!(json["layers"][0]["skipped"], "borrowed mutably elsewhere");
+///     drop(fog);
+/// }
+/// ```
+#[cfg(feature = "serde")]
+impl Serialize for TileDiagnostics {
+    fn serialize<Z>(&self, serializer: Z) -> Result<Z::Ok, Z::Error>
+    where
+        Z: Serializer,
+    {
+        let mut diagnostics = serializer.serialize_struct("TileDiagnostics", 4)?;
+        diagnostics.serialize_field("grid", &self.grid)?;
+        diagnostics.serialize_field("layers", &self.layers)?;
+        diagnostics.serialize_field("occupied", &self.occupied)?;
+        diagnostics.serialize_field("estimated_bytes", &self.estimated_bytes)?;
+
+        diagnostics.end()
+    }
+}
+
+/// Serialized flat, with the numbers `null` and `skipped` naming the reason if the layer has
+/// been skipped.
+#[cfg(feature = "serde")]
+impl Serialize for LayerDiagnostics {
+    fn serialize<Z>(&self, serializer: Z) -> Result<Z::Ok, Z::Error>
+    where
+        Z: Serializer,
+    {
+        let stats = self.stats.as_ref().ok();
+        let skipped = self.stats.as_ref().err().map(ToString::to_string);
+
+        let mut layer = serializer.serialize_struct("LayerDiagnostics", 7)?;
+        layer.serialize_field("type_name", self.type_name)?;
+        layer.serialize_field("name", &self.name)?;
+        layer.serialize_field("tracked", &self.tracked)?;
+        layer.serialize_field("occupied", &stats.map(|stats| stats.occupied))?;
+        layer.serialize_field("highest_id", &stats.and_then(|stats| stats.highest_id))?;
+        layer.serialize_field("estimated_bytes", &stats.map(|stats| stats.estimated_bytes))?;
+        layer.serialize_field("skipped", &skipped)?;
+
+        layer.end()
+    }
+}
+
+/// Takes the numbers of the storage resource `Storage<C, D, I>`, unless it's borrowed mutably.
+pub(crate) type ProbeLayer = fn(&World) -> Result<LayerStats, LayerSkipped>;
+
+pub(crate) fn probe<C, D, I>(world: &World) -> Result<LayerStats, LayerSkipped>
+where
+    C: Send + Sync + 'static,
+    D: UnprotectedStorage<C> + Send + Sync + 'static,
+    I: Id,
+{
+    let storage = try_borrow::<Storage<C, D, I>, _, _>(world, |storage| {
+        LayerStats::of(&storage.bitset, mem::size_of::<C>())
+    });
+
+    storage.unwrap_or(Err(LayerSkipped::Removed))
+}
+
+/// Calls `f` with the resource `T`, returning `None` if there is none and `Err` if it's
+/// borrowed mutably, instead of panicking like `World::try_fetch`.
+fn try_borrow<T, F, R>(world: &World, f: F) -> Option<Result<R, LayerSkipped>>
+where
+    T: Resource,
+    F: FnOnce(&T) -> R,
+{
+    let cell = world.try_fetch_internal(ResourceId::new::<T>())?;
+
+    Some(match cell.try_borrow() {
+        Ok(resource) => Ok(f(resource
+            .downcast_ref::<T>()
+            .expect("fetched by its resource id"))),
+        Err(_) => Err(LayerSkipped::Borrowed),
+    })
+}
+
+pub(crate) fn collect<I>(world: &World) -> TileDiagnostics
+where
+    I: Id,
+{
+    let grid = try_borrow::<Grid2d, _, _>(world, |grid| (grid.width(), grid.height()))
+        .and_then(Result::ok);
+    let layers: Vec<_> = world
+        .try_fetch::<TileRegistry<I>>()
+        .map(|registry| registry.named_entries())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(entry, name)| LayerDiagnostics {
+            type_name: entry.type_name,
+            name,
+            tracked: entry.tracked,
+            stats: (entry.probe)(world),
+        })
+        .collect();
+
+    let stats = || layers.iter().filter_map(|layer| layer.stats.as_ref().ok());
+    TileDiagnostics {
+        grid,
+        occupied: stats().map(|stats| stats.occupied).sum(),
+        estimated_bytes: stats().map(|stats| stats.estimated_bytes).sum(),
+        layers,
+    }
+}
+
+/// Returns the highest id in `mask`, walking down from the top layer of the bitset.
+fn highest_id(mask: &BitSet) -> Option<u32> {
+    let top = |word: usize| (1 << BITS) - 1 - word.leading_zeros() as usize;
+
+    let layer3 = mask.layer3();
+    if layer3 == 0 {
+        return None;
+    }
+    let i2 = top(layer3);
+    let i1 = (i2 << BITS) + top(mask.layer2(i2));
+    let i0 = (i1 << BITS) + top(mask.layer1(i1));
+
+    Some(((i0 << BITS) + top(mask.layer0(i0))) as u32)
+}
@@ -0,0 +1,134 @@
+//! Two storages of the same layer, one holding the previous state and one the next one.
+
+use std::mem;
+
+use specs::storage::UnprotectedStorage;
+
+use {Id, Storage};
+
+/// A pair of storages for simulations which compute the next state of a layer from its
+/// previous state, like cellular automata.
+///
+/// Systems read from `previous()` and write to `current()` (or use `split()` to do both at
+/// once); at the end of a frame, `swap()` turns the written state into the previous one.
+///
+/// # Semantics of structural changes
+///
+/// Insertions and removals only ever apply to the write buffer. `swap()` does not mirror
+/// them, so after a swap the write buffer holds the state from two frames ago, which is
+/// fine if every frame writes all of its components anew. Use `swap_cloned()` if the write
+/// buffer should instead start out as a copy of the state that was just written.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::{Component, VecStorage};
+/// use specs_static::DoubleBuffered;
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// #[derive(Clone, Copy, Debug, PartialEq)]
+/// struct Alive(bool);
+///
+/// impl Component for Alive {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// const SIZE: i32 = 5;
+///
+/// fn id(x: i32, y: i32) -> TileId {
+///     TileId((y * SIZE + x) as u32)
+/// }
+///
+/// fn main() {
+///     let mut cells = DoubleBuffered::<Alive, VecStorage<_>, TileId>::default();
+///     for y in 0..SIZE {
+///         for x in 0..SIZE {
+///             let alive = y == 2 && x >= 1 && x <= 3;
+///             cells.current_mut().insert(id(x, y), Alive(alive));
+///         }
+///     }
+///     cells.swap();
+///
+///     {
+///         let (previous, current) = cells.split();
+///         for y in 0..SIZE {
+///             for x in 0..SIZE {
+///                 let mut neighbors = 0;
+///                 for (dx, dy) in (-1..2).flat_map(|dx| (-1..2).map(move |dy| (dx, dy))) {
+///                     let (nx, ny) = (x + dx, y + dy);
+///                     let inside = nx >= 0 && ny >= 0 && nx < SIZE && ny < SIZE;
+///                     if (dx, dy) != (0, 0) && inside && previous.get(id(nx, ny)).unwrap().0 {
+///                         neighbors += 1;
+///                     }
+///                 }
+///
+///                 let was_alive = previous.get(id(x, y)).unwrap().0;
+///                 let alive = neighbors == 3 || (was_alive && neighbors == 2);
+///                 current.insert(id(x, y), Alive(alive));
+///             }
+///         }
+///     }
+///     cells.swap();
+///
+///     // The horizontal blinker turned vertical, and the old state is still readable
+///     // until the next frame writes over it.
+///     assert_eq!(cells.previous().get(id(2, 1)), Some(&Alive(true)));
+///     assert_eq!(cells.previous().get(id(1, 2)), Some(&Alive(false)));
+///     assert_eq!(cells.current().get(id(1, 2)), Some(&Alive(true)));
+/// }
+/// ```
+#[derive(Derivative)]
+#[derivative(Default(bound = "D: Default"))]
+pub struct DoubleBuffered<C, D: UnprotectedStorage<C>, I> {
+    current: Storage<C, D, I>,
+    previous: Storage<C, D, I>,
+}
+
+impl<C, D, I> DoubleBuffered<C, D, I>
+where
+    D: UnprotectedStorage<C>,
+    I: Id,
+{
+    /// Returns the write buffer.
+    pub fn current(&self) -> &Storage<C, D, I> {
+        &self.current
+    }
+
+    /// Returns the write buffer mutably.
+    pub fn current_mut(&mut self) -> &mut Storage<C, D, I> {
+        &mut self.current
+    }
+
+    /// Returns the read buffer, which holds the state written before the last swap.
+    pub fn previous(&self) -> &Storage<C, D, I> {
+        &self.previous
+    }
+
+    /// Returns the read buffer and the write buffer at the same time.
+    pub fn split(&mut self) -> (&Storage<C, D, I>, &mut Storage<C, D, I>) {
+        (&self.previous, &mut self.current)
+    }
+
+    /// Flips the buffers, so the state that was just written becomes the previous one.
+    pub fn swap(&mut self) {
+        mem::swap(&mut self.current, &mut self.previous);
+    }
+
+    /// Flips the buffers like `swap`, then makes the write buffer a copy of the new read
+    /// buffer, so structural changes carry over to the next frame.
+    pub fn swap_cloned(&mut self)
+    where
+        C: Clone,
+    {
+        self.swap();
+        self.current.clear();
+
+        for id in &self.previous.bitset {
+            let comp = unsafe { self.previous.data.get(id) }.clone();
+            self.current.insert(I::from_u32(id), comp);
+        }
+    }
+}
@@ -0,0 +1,231 @@
+//! Queries combining named layers which are only known at runtime.
+
+use std::error::Error;
+use std::fmt;
+use std::marker::PhantomData;
+
+use hibitset::{BitSet, BitSetAnd, BitSetNot};
+use shred::World;
+
+use {Id, TileWorldExt};
+
+/// A filter over the layers registered with `TileWorldExt::register_tile_comp_named`, built
+/// from their names at runtime, like the filters a user puts together in an editor.
+///
+/// `execute` returns the ids which have a component in every layer passed to `with` and in
+/// none of the layers passed to `without`. Only the masks of the layers are combined, the
+/// components aren't looked at. The result can be joined with the storages like any
+/// `BitSet`, or turned into coordinates with `Grid2d::coords`.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::{Component, NullStorage, World, WorldExt};
+/// use specs_static::{DynQuery, DynQueryError, Grid2d, Storage, TileWorldExt};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// #[derive(Default)]
+/// struct Water;
+///
+/// impl Component for Water {
+///     type Storage = NullStorage<Self>;
+/// }
+///
+/// #[derive(Default)]
+/// struct Bridge;
+///
+/// impl Component for Bridge {
+///     type Storage = NullStorage<Self>;
+/// }
+///
+/// #[derive(Default)]
+/// struct Blocked;
+///
+/// impl Component for Blocked {
+///     type Storage = NullStorage<Self>;
+/// }
+///
+/// type Waters = Storage<Water, NullStorage<Water>, TileId>;
+/// type Bridges = Storage<Bridge, NullStorage<Bridge>, TileId>;
+/// type Blocks = Storage<Blocked, NullStorage<Blocked>, TileId>;
+///
+/// fn ids(query: &DynQuery<TileId>, world: &World) -> Vec<u32> {
+///     use specs::Join;
+///
+///     query.execute(world).unwrap().join().collect()
+/// }
+///
+/// fn main() {
+///     let mut world = World::new();
+///     world.register_tile_comp_named::<Water, TileId>("water").unwrap();
+///     world.register_tile_comp_named::<Bridge, TileId>("bridge").unwrap();
+///     world.register_tile_comp_named::<Blocked, TileId>("blocked").unwrap();
+///     for id in 0..6 {
+///         world.write_resource::<Waters>().insert(TileId(id), Water);
+///     }
+///     for id in 3..9 {
+///         world.write_resource::<Bridges>().insert(TileId(id), Bridge);
+///     }
+///     for &id in &[1, 4, 7] {
+///         world.write_resource::<Blocks>().insert(TileId(id), Blocked);
+///     }
+///
+///     let water = DynQuery::new().with("water");
+///     assert_eq!(ids(&water, &world), vec![0, 1, 2, 3, 4, 5]);
+///     assert_eq!(ids(&water.clone().with("bridge"), &world), vec![3, 4, 5]);
+///     assert_eq!(ids(&water.clone().without("bridge"), &world), vec![0, 1, 2]);
+///     assert_eq!(ids(&water.clone().without("blocked"), &world), vec![0, 2, 3, 5]);
+///     assert_eq!(ids(&water.clone().with("bridge").without("blocked"), &world), vec![3, 5]);
+///     assert_eq!(
+///         ids(&DynQuery::new().with("bridge").without("water").without("blocked"), &world),
+///         vec![6, 8]
+///     );
+///
+///     // The ids as coordinates of the map.
+///     let grid = Grid2d::new(3, 3);
+///     let open_bridges = DynQuery::<TileId>::new().with("bridge").without("blocked");
+///     let coords: Vec<_> = ids(&open_bridges, &world)
+///         .into_iter()
+///         .filter_map(|id| grid.coords(TileId(id)))
+///         .collect();
+///     assert_eq!(coords, vec![(0, 1), (2, 1), (0, 2), (2, 2)]);
+///
+///     match DynQuery::<TileId>::new().with("water").without("lava").execute(&world) {
+///         Err(DynQueryError::UnknownLayer { ref name }) => assert_eq!(name, "lava"),
+///         _ => panic!("there is no lava layer"),
+///     }
+///     match DynQuery::<TileId>::new().without("blocked").execute(&world) {
+///         Err(DynQueryError::NothingRequired) => {}
+///         _ => panic!("a query needs a layer to start from"),
+///     }
+/// }
+/// ```
+pub struct DynQuery<I> {
+    with: Vec<String>,
+    without: Vec<String>,
+    phantom: PhantomData<I>,
+}
+
+impl<I> DynQuery<I>
+where
+    I: Id,
+{
+    /// Creates a query without any layers, which has to be given one with `with` before
+    /// it can be executed.
+    pub fn new() -> Self {
+        DynQuery {
+            with: Vec::new(),
+            without: Vec::new(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Only keeps the ids with a component in the layer registered under `name`.
+    pub fn with(mut self, name: &str) -> Self {
+        self.with.push(name.to_owned());
+
+        self
+    }
+
+    /// Only keeps the ids without a component in the layer registered under `name`.
+    pub fn without(mut self, name: &str) -> Self {
+        self.without.push(name.to_owned());
+
+        self
+    }
+
+    /// Returns the ids matching the query in the layers of the
+    /// `specs_static::TileRegistry<I>` resource of `world`.
+    ///
+    /// Returns an error if one of the names isn't registered, or if no layer has been
+    /// passed to `with`, since there would be no ids to start from.
+    ///
+    /// # Panics
+    ///
+    /// Panics if one of the storages is currently borrowed mutably.
+    pub fn execute(&self, world: &World) -> Result<BitSet, DynQueryError> {
+        let fetch = |name: &String| {
+            world
+                .tile_storage_named::<I>(name)
+                .ok_or_else(|| DynQueryError::UnknownLayer { name: name.clone() })
+        };
+        let with = self.with.iter().map(fetch).collect::<Result<Vec<_>, _>>()?;
+        let without = self.without.iter().map(fetch).collect::<Result<Vec<_>, _>>()?;
+
+        let (first, rest) = with.split_first().ok_or(DynQueryError::NothingRequired)?;
+        let mut required = first.mask().clone();
+        for storage in rest {
+            required &= storage.mask();
+        }
+        if without.is_empty() {
+            return Ok(required);
+        }
+
+        let mut excluded = BitSet::new();
+        for storage in &without {
+            excluded |= storage.mask();
+        }
+        let mut ids = BitSet::new();
+        ids |= &BitSetAnd(&required, BitSetNot(&excluded));
+
+        Ok(ids)
+    }
+}
+
+impl<I> Clone for DynQuery<I> {
+    fn clone(&self) -> Self {
+        DynQuery {
+            with: self.with.clone(),
+            without: self.without.clone(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<I> fmt::Debug for DynQuery<I> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DynQuery")
+            .field("with", &self.with)
+            .field("without", &self.without)
+            .finish()
+    }
+}
+
+impl<I> Default for DynQuery<I>
+where
+    I: Id,
+{
+    fn default() -> Self {
+        DynQuery::new()
+    }
+}
+
+/// The error returned by `DynQuery::execute`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DynQueryError {
+    /// No layer is registered under the name, or its storage has been removed.
+    UnknownLayer {
+        /// The name.
+        name: String,
+    },
+    /// The query doesn't require any layer.
+    NothingRequired,
+}
+
+impl fmt::Display for DynQueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DynQueryError::UnknownLayer { ref name } => {
+                write!(f, "no tile layer is registered under the name {:?}", name)
+            }
+            DynQueryError::NothingRequired => {
+                write!(f, "the query has no layer to start from; add one with `with`")
+            }
+        }
+    }
+}
+
+impl Error for DynQueryError {}
@@ -0,0 +1,171 @@
+//! Links between tile ids and the entities tiles have been promoted to.
+
+use std::collections::HashMap;
+
+use specs::world::EntitiesRes;
+use specs::Entity;
+
+use Id;
+
+/// A bidirectional mapping between ids and entities, for tiles which need to be full
+/// entities (with scripts, health, and so on) in addition to their tile components.
+///
+/// Every id is linked to at most one entity and every entity to at most one id. Linking
+/// an id or an entity again replaces its previous link.
+///
+/// Entities can die without the map noticing, so call `purge_dead` regularly (for example
+/// after `World::maintain`) to remove the links of deleted entities.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::{Builder, World, WorldExt};
+/// use specs_static::TileEntityMap;
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// fn main() {
+///     let mut world = World::new();
+///     world.insert(TileEntityMap::<TileId>::new());
+///
+///     let door = world.create_entity().build();
+///     let chest = world.create_entity().build();
+///     {
+///         let mut links = world.write_resource::<TileEntityMap<TileId>>();
+///         assert_eq!(links.link(TileId(4), door), None);
+///         assert_eq!(links.link(TileId(9), chest), None);
+///         assert_eq!(links.entity_at(TileId(4)), Some(door));
+///         assert_eq!(links.tile_of(chest), Some(TileId(9)));
+///     }
+///
+///     world.delete_entity(door).unwrap();
+///     world.maintain();
+///
+///     let mut links = world.write_resource::<TileEntityMap<TileId>>();
+///     assert_eq!(links.purge_dead(&world.entities()), 1);
+///     assert_eq!(links.entity_at(TileId(4)), None);
+///     assert_eq!(links.tile_of(door), None);
+///     assert_eq!(links.entity_at(TileId(9)), Some(chest));
+/// }
+/// ```
+#[derive(Derivative)]
+#[derivative(
+    Clone(bound = "I: Id"),
+    Debug(bound = "I: ::std::fmt::Debug"),
+    Default(bound = "")
+)]
+pub struct TileEntityMap<I> {
+    entities: HashMap<I, Entity>,
+    tiles: HashMap<Entity, I>,
+}
+
+impl<I> TileEntityMap<I>
+where
+    I: Id,
+{
+    /// Creates an empty map.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Links `id` to `entity`, returning the entity `id` was linked to before.
+    ///
+    /// If `entity` was linked to another id, that link is removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate specs;
+    /// # extern crate specs_static;
+    /// # use specs::{Builder, World, WorldExt};
+    /// # use specs_static::TileEntityMap;
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// # fn main() {
+    /// # let mut world = World::new();
+    /// let (a, b) = (world.create_entity().build(), world.create_entity().build());
+    /// let mut links = TileEntityMap::new();
+    ///
+    /// links.link(TileId(1), a);
+    /// assert_eq!(links.link(TileId(1), b), Some(a));
+    /// assert_eq!(links.tile_of(a), None);
+    ///
+    /// links.link(TileId(2), b);
+    /// assert_eq!(links.entity_at(TileId(1)), None);
+    /// assert_eq!(links.len(), 1);
+    /// # }
+    /// ```
+    pub fn link(&mut self, id: I, entity: Entity) -> Option<Entity> {
+        if let Some(old_id) = self.tiles.insert(entity, id) {
+            if old_id != id {
+                self.entities.remove(&old_id);
+            }
+        }
+
+        let displaced = self.entities.insert(id, entity).filter(|&old| old != entity);
+        if let Some(old) = displaced {
+            self.tiles.remove(&old);
+        }
+
+        displaced
+    }
+
+    /// Removes the link of `id`, returning the entity it was linked to.
+    pub fn unlink_tile(&mut self, id: I) -> Option<Entity> {
+        let entity = self.entities.remove(&id)?;
+        self.tiles.remove(&entity);
+
+        Some(entity)
+    }
+
+    /// Removes the link of `entity`, returning the id it was linked to.
+    pub fn unlink_entity(&mut self, entity: Entity) -> Option<I> {
+        let id = self.tiles.remove(&entity)?;
+        self.entities.remove(&id);
+
+        Some(id)
+    }
+
+    /// Returns the entity `id` is linked to.
+    pub fn entity_at(&self, id: I) -> Option<Entity> {
+        self.entities.get(&id).cloned()
+    }
+
+    /// Returns the id `entity` is linked to.
+    pub fn tile_of(&self, entity: Entity) -> Option<I> {
+        self.tiles.get(&entity).cloned()
+    }
+
+    /// Returns the amount of links.
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    /// Returns `true` if there are no links.
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    /// Returns an iterator over all links.
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = (I, Entity)> + 'a {
+        self.entities.iter().map(|(&id, &entity)| (id, entity))
+    }
+
+    /// Removes the links of all entities which are no longer alive, returning how many
+    /// links were removed.
+    pub fn purge_dead(&mut self, entities: &EntitiesRes) -> usize {
+        let dead: Vec<Entity> = self
+            .tiles
+            .keys()
+            .cloned()
+            .filter(|&entity| !entities.is_alive(entity))
+            .collect();
+
+        for &entity in &dead {
+            self.unlink_entity(entity);
+        }
+
+        dead.len()
+    }
+}
@@ -0,0 +1,42 @@
+//! Counting the structural changes of storages, which tells caches whether a mask changed.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use {RawStorage, Storage};
+
+/// Identifies the mask state of a storage: the storage gets a unique id on its first
+/// structural change, and counts its structural changes from then on.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub(crate) struct Epoch {
+    /// Zero while the storage has never changed, so its mask is empty.
+    uid: u64,
+    count: u64,
+}
+
+impl Epoch {
+    /// Records a structural change, which may have changed the mask.
+    #[inline]
+    pub(crate) fn bump(&mut self) {
+        static NEXT_UID: AtomicU64 = AtomicU64::new(1);
+
+        if self.uid == 0 {
+            self.uid = NEXT_UID.fetch_add(1, Ordering::Relaxed);
+        }
+        self.count += 1;
+    }
+}
+
+impl<C, D, I> Storage<C, D, I>
+where
+    D: RawStorage<C>,
+{
+    /// Returns the amount of structural changes, which is increased by every change which
+    /// may have changed the mask, like inserting into a vacant id or removing a component.
+    /// Replacing or modifying components doesn't count.
+    ///
+    /// Together with the identity of the storage this tells whether the mask changed, which
+    /// is what `CachedMask` uses.
+    pub fn structural_epoch(&self) -> u64 {
+        self.epoch.count
+    }
+}
@@ -0,0 +1,232 @@
+//! Insertions which report why they failed.
+
+use std::error::Error;
+use std::fmt;
+use std::mem;
+
+use {Id, RawStorage, Storage};
+
+/// The error returned by the checked insertions, handing back the rejected component.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TileStorageError<C> {
+    /// The id exceeds the bound of the storage.
+    OutOfBounds {
+        /// The rejected id.
+        id: u32,
+        /// The highest id the storage accepts.
+        bound: u32,
+        /// The component which hasn't been inserted.
+        component: C,
+    },
+    /// The id already has a component, which has been kept.
+    Occupied {
+        /// The rejected id.
+        id: u32,
+        /// The component which hasn't been inserted.
+        component: C,
+    },
+}
+
+impl<C> TileStorageError<C> {
+    /// Returns the rejected id.
+    pub fn id(&self) -> u32 {
+        match *self {
+            TileStorageError::OutOfBounds { id, .. } | TileStorageError::Occupied { id, .. } => id,
+        }
+    }
+
+    /// Returns the component which hasn't been inserted.
+    pub fn into_component(self) -> C {
+        match self {
+            TileStorageError::OutOfBounds { component, .. }
+            | TileStorageError::Occupied { component, .. } => component,
+        }
+    }
+}
+
+impl<C> fmt::Display for TileStorageError<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TileStorageError::OutOfBounds { id, bound, .. } => {
+                write!(f, "id {} exceeds the bound {} of the storage", id, bound)
+            }
+            TileStorageError::Occupied { id, .. } => write!(f, "id {} is already occupied", id),
+        }
+    }
+}
+
+impl<C: fmt::Debug> Error for TileStorageError<C> {}
+
+impl<C, D, I> Storage<C, D, I>
+where
+    D: RawStorage<C>,
+    I: Id,
+{
+    /// Inserts `comp` at `id` like `insert`, but returns an error carrying `comp` if `id`
+    /// exceeds the bound of the storage.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, VecStorage};
+    /// use specs_static::{Storage, TileStorageError};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// #[derive(Debug, PartialEq)]
+    /// struct Building(&'static str);
+    ///
+    /// impl Component for Building {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut buildings = Storage::<Building, VecStorage<_>, TileId>::with_bound(63);
+    ///
+    ///     assert_eq!(buildings.try_insert_checked(TileId(63), Building("mill")), Ok(None));
+    ///     assert_eq!(
+    ///         buildings.try_insert_checked(TileId(63), Building("farm")),
+    ///         Ok(Some(Building("mill")))
+    ///     );
+    ///
+    ///     match buildings.try_insert_checked(TileId(64), Building("tower")) {
+    ///         Err(TileStorageError::OutOfBounds { id, bound, component }) => {
+    ///             assert_eq!((id, bound), (64, 63));
+    ///             assert_eq!(component, Building("tower"));
+    ///         }
+    ///         other => panic!("unexpected result {:?}", other),
+    ///     }
+    /// }
+    /// ```
+    pub fn try_insert_checked(&mut self, id: I, comp: C) -> Result<Option<C>, TileStorageError<C>> {
+        if let Err(e) = self.check_bound(id.id()) {
+            return Err(TileStorageError::OutOfBounds {
+                id: e.id,
+                bound: e.bound,
+                component: comp,
+            });
+        }
+
+        Ok(self.insert(id, comp))
+    }
+
+    /// Inserts `comp` at `id` only if `id` doesn't have a component yet.
+    ///
+    /// Returns an error carrying `comp` if `id` is occupied, in which case the existing
+    /// component is kept, or if `id` exceeds the bound of the storage.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use std::error::Error;
+    ///
+    /// use specs::{Component, VecStorage};
+    /// use specs_static::{Storage, TileStorageError};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// #[derive(Debug, PartialEq)]
+    /// struct Building(&'static str);
+    ///
+    /// impl Component for Building {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn place(
+    ///     buildings: &mut Storage<Building, VecStorage<Building>, TileId>,
+    ///     id: TileId,
+    ///     building: Building,
+    /// ) -> Result<(), Box<dyn Error>> {
+    ///     buildings.try_insert_vacant(id, building)?;
+    ///
+    ///     Ok(())
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut buildings = Storage::<Building, VecStorage<_>, TileId>::with_bound(63);
+    ///
+    ///     assert!(place(&mut buildings, TileId(7), Building("mill")).is_ok());
+    ///     assert_eq!(
+    ///         buildings.try_insert_vacant(TileId(7), Building("farm")),
+    ///         Err(TileStorageError::Occupied { id: 7, component: Building("farm") })
+    ///     );
+    ///     assert_eq!(buildings.get(TileId(7)), Some(&Building("mill")));
+    ///
+    ///     let error = place(&mut buildings, TileId(7), Building("farm")).unwrap_err();
+    ///     assert_eq!(error.to_string(), "id 7 is already occupied");
+    ///     let error = place(&mut buildings, TileId(64), Building("farm")).unwrap_err();
+    ///     assert_eq!(error.to_string(), "id 64 exceeds the bound 63 of the storage");
+    /// }
+    /// ```
+    pub fn try_insert_vacant(&mut self, id: I, comp: C) -> Result<(), TileStorageError<C>> {
+        if self.bitset.contains(id.id()) {
+            return Err(TileStorageError::Occupied {
+                id: id.id(),
+                component: comp,
+            });
+        }
+
+        self.try_insert_checked(id, comp).map(|_| ())
+    }
+
+    /// Replaces the component of `id` with `comp` and returns the old one, or hands `comp`
+    /// back as the error if `id` is vacant, without touching the storage.
+    ///
+    /// # Inserting and updating
+    ///
+    /// * `insert` stores `comp` whether or not `id` has a component, returning the one it
+    ///   replaced.
+    /// * `try_insert_vacant` only stores `comp` if `id` has no component yet.
+    /// * `update` only stores `comp` if `id` already has a component, so code which updates
+    ///   tiles can't create components on tiles which shouldn't have them.
+    ///
+    /// Like `insert`, a successful update replaces the component in place, so tracked
+    /// backends report a modification.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, VecStorage};
+    /// use specs_static::Storage;
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// #[derive(Debug, PartialEq)]
+    /// struct Ore(u32);
+    ///
+    /// impl Component for Ore {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut ores = Storage::<Ore, VecStorage<_>, TileId>::default();
+    ///     ores.insert(TileId(4), Ore(100));
+    ///
+    ///     assert_eq!(ores.update(TileId(4), Ore(90)), Ok(Ore(100)));
+    ///     assert_eq!(ores.get(TileId(4)), Some(&Ore(90)));
+    ///
+    ///     assert_eq!(ores.update(TileId(5), Ore(90)), Err(Ore(90)));
+    ///     assert_eq!(ores.get(TileId(5)), None);
+    ///     assert_eq!(ores.mask().into_iter().collect::<Vec<_>>(), vec![4]);
+    /// }
+    /// ```
+    pub fn update(&mut self, id: I, comp: C) -> Result<C, C> {
+        match self.get_mut(id) {
+            Some(old) => {
+                let old = mem::replace(old, comp);
+                if self.observers.has_insert() {
+                    self.observers.inserted(id.id(), unsafe { self.data.get(id.id()) });
+                }
+
+                Ok(old)
+            }
+            None => Err(comp),
+        }
+    }
+}
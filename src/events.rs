@@ -0,0 +1,173 @@
+//! Forwarding the change events of tracked tile storages as user-defined events.
+
+use std::marker::PhantomData;
+
+use shred::{Read, World, System, SystemData, Write};
+use shrev::{EventChannel, ReaderId};
+use specs::storage::ComponentEvent;
+use specs::{Component, Tracked};
+
+use {Id, Storage};
+
+/// A change event of a tracked tile storage, with the id converted to `I`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum TileComponentEvent<I> {
+    /// A component has been inserted at a vacant id.
+    Inserted(I),
+    /// A component has been accessed mutably or replaced by inserting at an occupied id.
+    Modified(I),
+    /// A component has been removed.
+    Removed(I),
+}
+
+impl<I> TileComponentEvent<I>
+where
+    I: Id,
+{
+    /// Converts a `ComponentEvent` of a tile storage.
+    pub fn from_event(event: ComponentEvent) -> Self {
+        match event {
+            ComponentEvent::Inserted(id) => TileComponentEvent::Inserted(I::from_u32(id)),
+            ComponentEvent::Modified(id) => TileComponentEvent::Modified(I::from_u32(id)),
+            ComponentEvent::Removed(id) => TileComponentEvent::Removed(I::from_u32(id)),
+        }
+    }
+
+    /// Returns the id of the changed tile.
+    pub fn id(&self) -> I {
+        match *self {
+            TileComponentEvent::Inserted(id)
+            | TileComponentEvent::Modified(id)
+            | TileComponentEvent::Removed(id) => id,
+        }
+    }
+}
+
+/// A system publishing the mapped change events of the tracked storage
+/// `Storage<C, C::Storage, I>` into an `EventChannel<E>`.
+///
+/// Its reader is registered in `System::setup`, so the bridge has to be added to a dispatcher
+/// which is set up (or be set up manually) before it runs. Events for which the mapping
+/// returns `None` are dropped.
+///
+/// # Examples
+///
+/// ```
+/// extern crate shrev;
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use shrev::EventChannel;
+/// use specs::prelude::*;
+/// use specs_static::{Grid2d, Id, Storage, TileComponentEvent, TileEventBridge, TileWorldExt};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// struct Wall;
+///
+/// impl Component for Wall {
+///     type Storage = FlaggedStorage<Self, VecStorage<Self>>;
+/// }
+///
+/// #[derive(Debug, PartialEq)]
+/// enum MapUiEvent {
+///     TileChanged { x: u32, y: u32 },
+///     TileCleared { x: u32, y: u32 },
+/// }
+///
+/// type Walls = Storage<Wall, FlaggedStorage<Wall, VecStorage<Wall>>, TileId>;
+///
+/// fn main() {
+///     let grid = Grid2d::new(16, 16);
+///
+///     let mut world = World::new();
+///     world.register_tile_comp::<Wall, TileId>();
+///
+///     let bridge = TileEventBridge::<Wall, TileId, _, _>::new(move |event| {
+///         let (x, y) = grid.coords(event.id()).unwrap();
+///         match event {
+///             TileComponentEvent::Removed(_) => Some(MapUiEvent::TileCleared { x, y }),
+///             _ => Some(MapUiEvent::TileChanged { x, y }),
+///         }
+///     });
+///     let mut dispatcher = DispatcherBuilder::new().with(bridge, "wall_events", &[]).build();
+///     dispatcher.setup(&mut world);
+///
+///     let mut reader = world.write_resource::<EventChannel<MapUiEvent>>().register_reader();
+///     {
+///         let mut walls = world.write_resource::<Walls>();
+///         walls.insert(TileId(17), Wall);
+///         walls.insert(TileId(17), Wall);
+///         walls.remove(TileId(17));
+///     }
+///     dispatcher.dispatch(&world);
+///
+///     let channel = world.read_resource::<EventChannel<MapUiEvent>>();
+///     let events: Vec<_> = channel.read(&mut reader).collect();
+///     assert_eq!(
+///         events,
+///         vec![
+///             &MapUiEvent::TileChanged { x: 1, y: 1 },
+///             &MapUiEvent::TileChanged { x: 1, y: 1 },
+///             &MapUiEvent::TileCleared { x: 1, y: 1 },
+///         ]
+///     );
+/// }
+/// ```
+pub struct TileEventBridge<C, I, E, F> {
+    map: F,
+    reader: Option<ReaderId<ComponentEvent>>,
+    phantom: PhantomData<(C, I, E)>,
+}
+
+impl<C, I, E, F> TileEventBridge<C, I, E, F>
+where
+    I: Id,
+    F: Fn(TileComponentEvent<I>) -> Option<E>,
+{
+    /// Creates a bridge mapping events with `map`.
+    pub fn new(map: F) -> Self {
+        TileEventBridge {
+            map,
+            reader: None,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<'a, C, I, E, F> System<'a> for TileEventBridge<C, I, E, F>
+where
+    C: Component + Send + Sync,
+    C::Storage: Default + Tracked,
+    I: Id,
+    E: Send + Sync + 'static,
+    F: Fn(TileComponentEvent<I>) -> Option<E>,
+{
+    type SystemData = (
+        Read<'a, Storage<C, C::Storage, I>>,
+        Write<'a, EventChannel<E>>,
+    );
+
+    fn run(&mut self, (storage, mut channel): Self::SystemData) {
+        let reader = self
+            .reader
+            .as_mut()
+            .expect("`TileEventBridge` has to be set up before it runs");
+        let map = &self.map;
+        let mut events: Vec<E> = storage
+            .channel()
+            .read(reader)
+            .filter_map(|&event| map(TileComponentEvent::from_event(event)))
+            .collect();
+
+        channel.drain_vec_write(&mut events);
+    }
+
+    fn setup(&mut self, res: &mut World) {
+        Self::SystemData::setup(res);
+        self.reader = Some(
+            res.fetch_mut::<Storage<C, C::Storage, I>>()
+                .channel_mut()
+                .register_reader(),
+        );
+    }
+}
@@ -0,0 +1,242 @@
+//! Exporting occupied ids and their components into contiguous buffers.
+
+use specs::storage::UnprotectedStorage;
+
+use {Grid2d, Id, Storage};
+
+impl<C, D, I> Storage<C, D, I>
+where
+    D: UnprotectedStorage<C>,
+    I: Id,
+{
+    /// Appends all occupied ids to `ids_out` and clones of their components to `comps_out`,
+    /// in ascending id order, so both end up in matching order.
+    ///
+    /// Nothing is cleared, so the buffers can be reused across frames without allocating
+    /// again once they're big enough; call `clear` on them first if you don't want to append.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, VecStorage};
+    /// use specs_static::Storage;
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// #[derive(Clone, Debug, PartialEq)]
+    /// struct Tree(u8);
+    ///
+    /// impl Component for Tree {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut trees = Storage::<Tree, VecStorage<_>, TileId>::default();
+    ///     // Insert in scrambled order; the export is sorted by id regardless.
+    ///     let mut id = 17u32;
+    ///     for _ in 0..1000 {
+    ///         id = (id * 7919 + 13) % 100_000;
+    ///         trees.insert(TileId(id), Tree((id % 256) as u8));
+    ///     }
+    ///
+    ///     let (mut ids, mut comps) = (Vec::new(), Vec::new());
+    ///     for _ in 0..2 {
+    ///         ids.clear();
+    ///         comps.clear();
+    ///         trees.export_packed(&mut ids, &mut comps);
+    ///
+    ///         assert_eq!(ids.len(), comps.len());
+    ///         assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+    ///         for (&id, tree) in ids.iter().zip(&comps) {
+    ///             assert_eq!(trees.get(TileId(id)), Some(tree));
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn export_packed(&self, ids_out: &mut Vec<u32>, comps_out: &mut Vec<C>)
+    where
+        C: Clone,
+    {
+        for id in &self.bitset {
+            ids_out.push(id);
+            comps_out.push(unsafe { self.data.get(id) }.clone());
+        }
+    }
+
+    /// Appends `f(id, &component)` for every occupied id to `out`, in ascending id order.
+    ///
+    /// This converts components straight into a vertex or instance type, without an
+    /// intermediate copy. Like `export_packed`, nothing is cleared.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, VecStorage};
+    /// use specs_static::Storage;
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// struct Rock {
+    ///     size: f32,
+    /// }
+    ///
+    /// impl Component for Rock {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// #[repr(C)]
+    /// struct Instance {
+    ///     position: [f32; 2],
+    ///     scale: f32,
+    /// }
+    ///
+    /// fn main() {
+    ///     const WIDTH: u32 = 256;
+    ///
+    ///     let mut rocks = Storage::<Rock, VecStorage<_>, TileId>::default();
+    ///     rocks.insert(TileId(WIDTH + 3), Rock { size: 2.0 });
+    ///     rocks.insert(TileId(1), Rock { size: 0.5 });
+    ///
+    ///     let mut instances = Vec::new();
+    ///     rocks.export_packed_with(&mut instances, |id, rock| Instance {
+    ///         position: [(id.0 % WIDTH) as f32, (id.0 / WIDTH) as f32],
+    ///         scale: rock.size,
+    ///     });
+    ///
+    ///     assert_eq!(
+    ///         instances,
+    ///         vec![
+    ///             Instance { position: [1.0, 0.0], scale: 0.5 },
+    ///             Instance { position: [3.0, 1.0], scale: 2.0 },
+    ///         ]
+    ///     );
+    /// }
+    /// ```
+    pub fn export_packed_with<T, F>(&self, out: &mut Vec<T>, mut f: F)
+    where
+        F: FnMut(I, &C) -> T,
+    {
+        for id in &self.bitset {
+            out.push(f(I::from_u32(id), unsafe { self.data.get(id) }));
+        }
+    }
+
+    /// Appends the occupancy of the tiles of `grid` to `out` as a bitmap of one bit per tile,
+    /// in row-major order, for minimaps and tests.
+    ///
+    /// Every row starts at a new byte and takes `(grid.width() + 7) / 8` bytes; bits past the
+    /// width of a row are padding and always zero. Within a byte, the tile with the lowest `x`
+    /// is the least significant bit, so tile `(x, y)` is bit `x % 8` of byte
+    /// `y * row_bytes + x / 8`. Occupied ids outside of the grid are ignored.
+    ///
+    /// Nothing is cleared, like with `export_packed`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, VecStorage};
+    /// use specs_static::{Grid2d, Storage};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// struct Wall;
+    ///
+    /// impl Component for Wall {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     // A checkerboard on a grid whose width isn't a multiple of 8.
+    ///     let grid = Grid2d::new(10, 3);
+    ///     let mut walls = Storage::<Wall, VecStorage<_>, TileId>::default();
+    ///     for y in 0..3 {
+    ///         for x in (y % 2..10).step_by(2) {
+    ///             walls.insert(grid.id(x, y).unwrap(), Wall);
+    ///         }
+    ///     }
+    ///     // Outside of the grid.
+    ///     walls.insert(TileId(30), Wall);
+    ///
+    ///     let mut bytes = Vec::new();
+    ///     walls.occupancy_bytes(&grid, &mut bytes);
+    ///     assert_eq!(
+    ///         bytes,
+    ///         vec![0b0101_0101, 0b01, 0b1010_1010, 0b10, 0b0101_0101, 0b01]
+    ///     );
+    ///
+    ///     // A single row.
+    ///     let row = Grid2d::new(12, 1);
+    ///     let mut walls = Storage::<Wall, VecStorage<_>, TileId>::default();
+    ///     for &x in &[0, 7, 8, 11] {
+    ///         walls.insert(TileId(x), Wall);
+    ///     }
+    ///     bytes.clear();
+    ///     walls.occupancy_bytes(&row, &mut bytes);
+    ///     assert_eq!(bytes, vec![0b1000_0001, 0b0000_1001]);
+    /// }
+    /// ```
+    pub fn occupancy_bytes(&self, grid: &Grid2d, out: &mut Vec<u8>) {
+        let width = grid.width() as usize;
+        let row_bytes = width.div_ceil(8);
+        let start = out.len();
+        out.resize(start + row_bytes * grid.height() as usize, 0);
+
+        for id in &self.bitset {
+            if id >= grid.len() {
+                break;
+            }
+
+            let (x, y) = (id as usize % width, id as usize / width);
+            out[start + y * row_bytes + x / 8] |= 1 << (x % 8);
+        }
+    }
+
+    /// Renders the occupancy of the tiles of `grid` as rows of `#` (occupied) and `.`
+    /// (vacant), each ending with a newline, for test failure messages and debugging.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, VecStorage};
+    /// use specs_static::{Grid2d, Storage};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// struct Wall;
+    ///
+    /// impl Component for Wall {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let grid = Grid2d::new(5, 2);
+    ///     let mut walls = Storage::<Wall, VecStorage<_>, TileId>::default();
+    ///     for &id in &[0, 2, 4, 6, 8] {
+    ///         walls.insert(TileId(id), Wall);
+    ///     }
+    ///
+    ///     assert_eq!(walls.occupancy_ascii(&grid), "#.#.#\n.#.#.\n");
+    /// }
+    /// ```
+    pub fn occupancy_ascii(&self, grid: &Grid2d) -> String {
+        let width = grid.width() as usize;
+        let mut ascii = String::with_capacity((width + 1) * grid.height() as usize);
+        for id in 0..grid.len() {
+            ascii.push(if self.bitset.contains(id) { '#' } else { '.' });
+            if (id as usize + 1).is_multiple_of(width) {
+                ascii.push('\n');
+            }
+        }
+
+        ascii
+    }
+}
@@ -0,0 +1,218 @@
+//! System data which insists on storages being registered instead of defaulting them.
+
+use std::any;
+use std::ops::{Deref, DerefMut};
+
+use shred::{Fetch, FetchMut, ResourceId, World, SystemData};
+use specs::Component;
+
+use {Id, Storage, TileRegistry};
+
+/// Fetches the tile storage `Storage<C, C::Storage, I>` immutably, like
+/// `Read<Storage<C, C::Storage, I>>`.
+///
+/// In contrast to `Read`, this doesn't fall back to an empty default storage: fetching it
+/// panics with a message naming `C` and `I` if the storage hasn't been registered with
+/// `TileWorldExt::register_tile_comp`. That makes a mismatching id type a loud failure instead of
+/// a system which silently never sees any tiles.
+///
+/// # Examples
+///
+/// ```should_panic
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::prelude::*;
+/// use specs_static::prelude::*;
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// # specs_static::define_id!(pub struct OtherId;);
+/// struct Ore;
+///
+/// impl Component for Ore {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// struct Mine;
+///
+/// impl<'a> System<'a> for Mine {
+///     // Oops, this should have been `TileId`.
+///     type SystemData = ReadTiles<'a, Ore, OtherId>;
+///
+///     fn run(&mut self, _: Self::SystemData) {}
+/// }
+///
+/// fn main() {
+///     let mut world = World::new();
+///     world.register_tile_comp::<Ore, TileId>();
+///
+///     // Panics: tile component `Ore` hasn't been registered for the id type `OtherId`.
+///     Mine.run_now(&world);
+/// }
+/// ```
+pub struct ReadTiles<'a, C: Component + 'a, I: 'a> {
+    inner: Fetch<'a, Storage<C, C::Storage, I>>,
+}
+
+impl<'a, C, I> Deref for ReadTiles<'a, C, I>
+where
+    C: Component + Send + Sync,
+    I: Id,
+{
+    type Target = Storage<C, C::Storage, I>;
+
+    fn deref(&self) -> &Storage<C, C::Storage, I> {
+        &self.inner
+    }
+}
+
+impl<'a, C, I> SystemData<'a> for ReadTiles<'a, C, I>
+where
+    C: Component + Send + Sync,
+    I: Id,
+{
+    fn setup(_: &mut World) {}
+
+    fn fetch(res: &'a World) -> Self {
+        check_registered::<C, I>(res);
+
+        ReadTiles { inner: res.fetch() }
+    }
+
+    fn reads() -> Vec<ResourceId> {
+        vec![
+            ResourceId::new::<Storage<C, C::Storage, I>>(),
+            ResourceId::new::<TileRegistry<I>>(),
+        ]
+    }
+
+    fn writes() -> Vec<ResourceId> {
+        vec![]
+    }
+}
+
+/// Fetches the tile storage `Storage<C, C::Storage, I>` mutably, like
+/// `Write<Storage<C, C::Storage, I>>`.
+///
+/// Like `ReadTiles`, this panics if the storage hasn't been registered with
+/// `TileWorldExt::register_tile_comp` instead of falling back to an empty default storage.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::prelude::*;
+/// use specs_static::prelude::*;
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// #[derive(Debug, PartialEq)]
+/// struct Ore(u32);
+///
+/// impl Component for Ore {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// struct Deposit;
+///
+/// impl<'a> System<'a> for Deposit {
+///     type SystemData = WriteTiles<'a, Ore, TileId>;
+///
+///     fn run(&mut self, mut ores: Self::SystemData) {
+///         ores.insert(TileId(3), Ore(10));
+///     }
+/// }
+///
+/// struct Mine;
+///
+/// impl<'a> System<'a> for Mine {
+///     type SystemData = ReadTiles<'a, Ore, TileId>;
+///
+///     fn run(&mut self, ores: Self::SystemData) {
+///         assert_eq!(ores.get(TileId(3)), Some(&Ore(10)));
+///     }
+/// }
+///
+/// fn main() {
+///     let mut world = World::new();
+///     world.register_tile_comp::<Ore, TileId>();
+///     world.assert_tile_comp_registered::<Ore, TileId>();
+///
+///     let mut dispatcher = DispatcherBuilder::new()
+///         .with(Deposit, "deposit", &[])
+///         .with(Mine, "mine", &["deposit"])
+///         .build();
+///     dispatcher.setup(&mut world);
+///     dispatcher.dispatch(&world);
+/// }
+/// ```
+pub struct WriteTiles<'a, C: Component + 'a, I: 'a> {
+    inner: FetchMut<'a, Storage<C, C::Storage, I>>,
+}
+
+impl<'a, C, I> Deref for WriteTiles<'a, C, I>
+where
+    C: Component + Send + Sync,
+    I: Id,
+{
+    type Target = Storage<C, C::Storage, I>;
+
+    fn deref(&self) -> &Storage<C, C::Storage, I> {
+        &self.inner
+    }
+}
+
+impl<'a, C, I> DerefMut for WriteTiles<'a, C, I>
+where
+    C: Component + Send + Sync,
+    I: Id,
+{
+    fn deref_mut(&mut self) -> &mut Storage<C, C::Storage, I> {
+        &mut self.inner
+    }
+}
+
+impl<'a, C, I> SystemData<'a> for WriteTiles<'a, C, I>
+where
+    C: Component + Send + Sync,
+    I: Id,
+{
+    fn setup(_: &mut World) {}
+
+    fn fetch(res: &'a World) -> Self {
+        check_registered::<C, I>(res);
+
+        WriteTiles {
+            inner: res.fetch_mut(),
+        }
+    }
+
+    fn reads() -> Vec<ResourceId> {
+        vec![ResourceId::new::<TileRegistry<I>>()]
+    }
+
+    fn writes() -> Vec<ResourceId> {
+        vec![ResourceId::new::<Storage<C, C::Storage, I>>()]
+    }
+}
+
+/// Panics if `C` hasn't been registered for the id type `I` with
+/// `TileWorldExt::register_tile_comp`.
+pub(crate) fn check_registered<C, I>(res: &World)
+where
+    C: Component + Send + Sync,
+    I: Id,
+{
+    let registered = res
+        .try_fetch::<TileRegistry<I>>()
+        .is_some_and(|registry| registry.contains::<C>());
+
+    assert!(
+        registered && res.has_value::<Storage<C, C::Storage, I>>(),
+        "tile component `{}` hasn't been registered for the id type `{}`; \
+         call `register_tile_comp::<{0}, {1}>()` first",
+        any::type_name::<C>(),
+        any::type_name::<I>()
+    );
+}
@@ -0,0 +1,109 @@
+//! Filling ranges of ids at once.
+
+use specs::storage::UnprotectedStorage;
+
+use bound;
+use {Id, Index, RangeMask, Storage};
+
+/// Backends which can write copies of a value to a range of slots in bulk.
+///
+/// This is what allows `Storage::fill_range_copy` to skip the per-element insertion.
+pub trait CopyFill<C>: UnprotectedStorage<C>
+where
+    C: Copy,
+{
+    /// Writes `value` to every slot in `start..end`.
+    ///
+    /// Some of these slots may already be occupied; they are simply overwritten,
+    /// which is fine because `Copy` types don't need to be dropped.
+    ///
+    /// # Safety
+    ///
+    /// `start` has to be smaller than `end`, and the caller is responsible for marking
+    /// all of these slots as occupied afterwards.
+    unsafe fn fill_copy(&mut self, start: Index, end: Index, value: C);
+}
+
+impl<C, D, I> Storage<C, D, I>
+where
+    D: UnprotectedStorage<C>,
+    I: Id,
+{
+    /// Inserts a clone of `comp` at every id in `start..end`, replacing existing components.
+    ///
+    /// This works with every backend. For `Copy` components stored in a backend implementing
+    /// `CopyFill`, `fill_range_copy` is much faster.
+    ///
+    /// Panics without inserting anything if an id exceeds the bound of the storage.
+    pub fn fill_range(&mut self, start: I, end: I, comp: C)
+    where
+        C: Clone,
+    {
+        bound::expect_in_bounds(self.check_range(start.id(), end.id()));
+        self.debug_check_range(start.id(), end.id());
+
+        for id in start.id()..end.id() {
+            self.insert(I::from_u32(id), comp.clone());
+        }
+    }
+
+    /// Inserts `comp` at every id in `start..end`, replacing existing components.
+    ///
+    /// The backend writes the components in bulk and the bitset words are set at once,
+    /// so no per-element insertion is done. This is only available for `Copy` components,
+    /// because overwritten components are not dropped.
+    ///
+    /// Panics without inserting anything if an id exceeds the bound of the storage.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, Join, VecStorage};
+    /// use specs_static::{SliceStorage, Storage};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// #[derive(Clone, Copy, Debug, PartialEq)]
+    /// struct Elevation(u16);
+    ///
+    /// impl Component for Elevation {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut elevations = Storage::<Elevation, SliceStorage<_>, TileId>::default();
+    ///     elevations.insert(TileId(0), Elevation(1));
+    ///     elevations.insert(TileId(500), Elevation(2));
+    ///
+    ///     elevations.fill_range_copy(TileId(100), TileId(1000), Elevation(7));
+    ///
+    ///     assert_eq!(elevations.get(TileId(0)), Some(&Elevation(1)));
+    ///     assert_eq!(elevations.get(TileId(99)), None);
+    ///     assert_eq!(elevations.get(TileId(500)), Some(&Elevation(7)));
+    ///     assert_eq!(elevations.get(TileId(1000)), None);
+    ///     assert_eq!((&elevations).join().count(), 901);
+    /// }
+    /// ```
+    pub fn fill_range_copy(&mut self, start: I, end: I, comp: C)
+    where
+        C: Copy,
+        D: CopyFill<C>,
+    {
+        bound::expect_in_bounds(self.check_range(start.id(), end.id()));
+        self.debug_check_range(start.id(), end.id());
+
+        let mask = RangeMask::from_range(start.id(), end.id());
+        if mask.is_empty() {
+            return;
+        }
+
+        unsafe {
+            self.data.fill_copy(mask.start(), mask.end(), comp);
+        }
+        self.bitset |= &mask;
+        self.epoch.bump();
+        self.notify_inserted(mask);
+    }
+}
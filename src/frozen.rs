@@ -0,0 +1,186 @@
+//! Immutable, reference counted snapshots of storages that can be sent to other threads.
+
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use hibitset::BitSet;
+use specs::storage::UnprotectedStorage;
+use specs::Join;
+
+use cow::Chunk;
+use {CowStorage, Id, Index, Storage};
+
+struct Frozen<C> {
+    chunks: Vec<Option<Arc<Chunk<C>>>>,
+    bitset: BitSet,
+}
+
+/// A read-only snapshot of a `Storage`, created with `Storage::freeze` or
+/// `Storage::freeze_cloned`.
+///
+/// The snapshot doesn't borrow the storage or the `World`, so it can be handed to another
+/// thread (a renderer, for example) and outlive the dispatch that produced it. Cloning it
+/// only bumps a reference count.
+///
+/// # Staleness
+///
+/// A frozen storage shows the state of the storage at the time it was frozen. Later
+/// changes to the live storage are not reflected; freeze again to get a fresh view.
+pub struct FrozenStorage<C, I> {
+    inner: Arc<Frozen<C>>,
+    phantom: PhantomData<I>,
+}
+
+impl<C, I> Clone for FrozenStorage<C, I> {
+    fn clone(&self) -> Self {
+        FrozenStorage {
+            inner: self.inner.clone(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<C, I> FrozenStorage<C, I>
+where
+    I: Id,
+{
+    fn new(chunks: Vec<Option<Arc<Chunk<C>>>>, bitset: BitSet) -> Self {
+        FrozenStorage {
+            inner: Arc::new(Frozen { chunks, bitset }),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns the component at `id`, if there was one when the storage was frozen.
+    pub fn get(&self, id: I) -> Option<&C> {
+        if self.inner.bitset.contains(id.id()) {
+            Some(unsafe { component(&self.inner.chunks, id.id()) })
+        } else {
+            None
+        }
+    }
+
+    /// Returns `true` if there was a component at `id` when the storage was frozen.
+    pub fn contains(&self, id: I) -> bool {
+        self.inner.bitset.contains(id.id())
+    }
+
+    /// Returns the mask of all occupied ids.
+    pub fn mask(&self) -> &BitSet {
+        &self.inner.bitset
+    }
+
+    /// Returns an iterator over all ids and their components, in ascending order.
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = (I, &'a C)> + 'a {
+        let chunks = &self.inner.chunks;
+
+        (&self.inner.bitset)
+            .into_iter()
+            .map(move |id| (I::from_u32(id), unsafe { component(chunks, id) }))
+    }
+}
+
+/// Returns the component at `id`, which has to be occupied.
+unsafe fn component<C>(chunks: &[Option<Arc<Chunk<C>>>], id: Index) -> &C {
+    let (chunk, slot) = CowStorage::<C>::split(id);
+
+    match *chunks.get_unchecked(chunk) {
+        Some(ref chunk) => match *chunk.slots.get_unchecked(slot) {
+            Some(ref comp) => comp,
+            None => unreachable!("accessed a vacant slot"),
+        },
+        None => unreachable!("accessed an unallocated chunk"),
+    }
+}
+
+impl<'a, C, I> Join for &'a FrozenStorage<C, I> {
+    type Type = &'a C;
+    type Value = &'a FrozenStorage<C, I>;
+    type Mask = &'a BitSet;
+
+    unsafe fn open(self) -> (Self::Mask, Self::Value) {
+        (&self.inner.bitset, self)
+    }
+
+    unsafe fn get(value: &mut Self::Value, id: Index) -> Self::Type {
+        component(&value.inner.chunks, id)
+    }
+}
+
+impl<C, I> Storage<C, CowStorage<C>, I>
+where
+    C: Clone,
+    I: Id,
+{
+    /// Freezes the current state into a `FrozenStorage`.
+    ///
+    /// Just like `snapshot`, this only clones the chunk handles; chunks are copied once the
+    /// live storage mutates them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use std::thread;
+    ///
+    /// use specs::{Component, Join, VecStorage};
+    /// use specs_static::{CowStorage, Storage};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// #[derive(Clone, Debug, PartialEq)]
+    /// struct Sprite(u32);
+    ///
+    /// impl Component for Sprite {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut sprites = Storage::<Sprite, CowStorage<_>, TileId>::default();
+    ///     for i in 0..100 {
+    ///         sprites.insert(TileId(i), Sprite(i));
+    ///     }
+    ///
+    ///     let frozen = sprites.freeze();
+    ///     sprites.get_mut(TileId(5)).unwrap().0 = 500;
+    ///     sprites.remove(TileId(6));
+    ///     sprites.insert(TileId(200), Sprite(200));
+    ///
+    ///     let renderer = thread::spawn(move || {
+    ///         assert_eq!(frozen.get(TileId(5)), Some(&Sprite(5)));
+    ///         assert_eq!(frozen.get(TileId(6)), Some(&Sprite(6)));
+    ///         assert_eq!(frozen.get(TileId(200)), None);
+    ///         assert_eq!(frozen.iter().count(), 100);
+    ///
+    ///         (&frozen).join().map(|sprite| sprite.0).sum::<u32>()
+    ///     });
+    ///     assert_eq!(renderer.join().unwrap(), 4950);
+    /// }
+    /// ```
+    pub fn freeze(&self) -> FrozenStorage<C, I> {
+        FrozenStorage::new(self.data.chunks.clone(), self.bitset.clone())
+    }
+}
+
+impl<C, D, I> Storage<C, D, I>
+where
+    C: Clone,
+    D: UnprotectedStorage<C>,
+    I: Id,
+{
+    /// Freezes the current state into a `FrozenStorage` by cloning every component.
+    ///
+    /// This works with every backend, but takes time proportional to the amount of
+    /// components; storages using `CowStorage` can use the cheap `freeze` instead.
+    pub fn freeze_cloned(&self) -> FrozenStorage<C, I> {
+        let mut data = CowStorage::default();
+        for id in &self.bitset {
+            unsafe {
+                data.insert(id, self.data.get(id).clone());
+            }
+        }
+
+        FrozenStorage::new(data.chunks, self.bitset.clone())
+    }
+}
@@ -0,0 +1,168 @@
+//! Constructing storages by generating a component for every id.
+
+use specs::storage::UnprotectedStorage;
+
+use mask::PendingRun;
+use {Grid2d, Id, Index, RangeMask, Storage};
+
+impl<C, D, I> Storage<C, D, I>
+where
+    D: UnprotectedStorage<C> + Default,
+    I: Id,
+{
+    /// Creates a storage with the components `f(x, y)` for every tile of `grid`, skipping tiles
+    /// for which `f` returns `None`.
+    ///
+    /// `f` is called row by row, in ascending id order. Since the storage starts out empty,
+    /// components are written straight to the backend, and the bitset is updated once per run
+    /// of consecutive components instead of once per component. If `f` panics, the components
+    /// generated so far are still properly owned by the storage.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, Join, VecStorage};
+    /// use specs_static::{Grid2d, Storage};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// #[derive(Debug, PartialEq)]
+    /// struct Height(u32);
+    ///
+    /// impl Component for Height {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// struct Lake;
+    ///
+    /// impl Component for Lake {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let grid = Grid2d::new(300, 200);
+    ///
+    ///     let heights = Storage::<_, VecStorage<_>, TileId>::from_fn(&grid, |x, y| {
+    ///         Some(Height(x * y))
+    ///     });
+    ///     assert_eq!((&heights).join().count(), 300 * 200);
+    ///     assert_eq!(heights.get(grid.id(17, 3).unwrap()), Some(&Height(51)));
+    ///
+    ///     // A lake in a circle around (100, 100).
+    ///     let lakes = Storage::<_, VecStorage<_>, TileId>::from_fn(&grid, |x, y| {
+    ///         let (dx, dy) = (x as i32 - 100, y as i32 - 100);
+    ///         match dx * dx + dy * dy < 50 * 50 {
+    ///             true => Some(Lake),
+    ///             false => None,
+    ///         }
+    ///     });
+    ///     for y in 0..grid.height() {
+    ///         for x in 0..grid.width() {
+    ///             let (dx, dy) = (x as i32 - 100, y as i32 - 100);
+    ///             let id = grid.id(x, y).unwrap();
+    ///             assert_eq!(lakes.get(id).is_some(), dx * dx + dy * dy < 50 * 50);
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn from_fn<F>(grid: &Grid2d, mut f: F) -> Self
+    where
+        F: FnMut(u32, u32) -> Option<C>,
+    {
+        let width = grid.width();
+        let (mut x, mut y) = (0, 0);
+
+        Self::generate(0, grid.len(), |_| {
+            let comp = f(x, y);
+            x += 1;
+            if x == width {
+                x = 0;
+                y += 1;
+            }
+
+            comp
+        })
+    }
+
+    /// Creates a storage with the components `f(id)` for every id in `start..end`, skipping ids
+    /// for which `f` returns `None`.
+    ///
+    /// Like `from_fn`, this calls `f` in ascending id order and writes the components
+    /// straight to the backend.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `end` exceeds the capacity of a hierarchical bitset.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, Join, VecStorage};
+    /// use specs_static::Storage;
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// #[derive(Debug, PartialEq)]
+    /// struct Ore(u32);
+    ///
+    /// impl Component for Ore {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let ores = Storage::<_, VecStorage<_>, TileId>::from_fn_range(
+    ///         TileId(100),
+    ///         TileId(10_000),
+    ///         |id| match id.0 % 7 < 3 {
+    ///             true => Some(Ore(id.0)),
+    ///             false => None,
+    ///         },
+    ///     );
+    ///
+    ///     let expected: Vec<_> = (100..10_000).filter(|id| id % 7 < 3).collect();
+    ///     let actual: Vec<_> = (&ores).join().map(|ore| ore.0).collect();
+    ///     assert_eq!(actual, expected);
+    ///     assert_eq!(ores.get(TileId(99)), None);
+    /// }
+    /// ```
+    pub fn from_fn_range<F>(start: I, end: I, mut f: F) -> Self
+    where
+        F: FnMut(I) -> Option<C>,
+    {
+        Self::generate(start.id(), end.id(), |id| f(I::from_u32(id)))
+    }
+
+    fn generate<F>(start: Index, end: Index, mut f: F) -> Self
+    where
+        F: FnMut(Index) -> Option<C>,
+    {
+        let range = RangeMask::from_range(start, end);
+        let mut storage = Self::default();
+        {
+            let Storage {
+                ref mut data,
+                ref mut bitset,
+                ref mut epoch,
+                ..
+            } = storage;
+            epoch.bump();
+            let mut run = PendingRun::new(bitset);
+
+            for id in range.start()..range.end() {
+                if let Some(comp) = f(id) {
+                    unsafe {
+                        data.insert(id, comp);
+                    }
+                    run.push(id);
+                }
+            }
+        }
+
+        storage
+    }
+}
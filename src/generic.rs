@@ -0,0 +1,236 @@
+//! Accessing entity storages and tile storages through the same traits.
+
+use std::ops::{Deref, DerefMut};
+
+use hibitset::BitSetLike;
+use specs::storage::{MaskedStorage, UnprotectedStorage};
+use specs::{Component, Entity, Join};
+
+use {Id, Index, Storage};
+
+/// Read access to the components of type `C` of a storage keyed by `Key`, so algorithms can
+/// be written once for entity storages (`ReadStorage` and `WriteStorage`, keyed by `Entity`)
+/// and tile storages (keyed by their id type).
+///
+/// `GenericWriteAccess` adds mutable access and `GenericInsertAccess` adds inserting and
+/// removing.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::{Builder, Component, VecStorage, World, WorldExt};
+/// use specs_static::{GenericInsertAccess, GenericReadAccess, GenericWriteAccess, Storage};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// #[derive(Debug, PartialEq)]
+/// struct Heat(f32);
+///
+/// impl Component for Heat {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// /// Lets every value decay by 1% per tick, removing values which dropped below 1.
+/// fn decay<S: GenericInsertAccess<Heat>>(heat: &mut S) {
+///     let mut cold = Vec::new();
+///     heat.for_each_mut(|key, heat| {
+///         heat.0 *= 0.99;
+///         if heat.0 < 1.0 {
+///             cold.push(key);
+///         }
+///     });
+///     for key in cold {
+///         heat.remove(key);
+///     }
+/// }
+///
+/// fn total<S: GenericReadAccess<Heat>>(heat: &S) -> f32 {
+///     let mut total = 0.0;
+///     heat.for_each(|_, heat| total += heat.0);
+///     total
+/// }
+///
+/// fn main() {
+///     let mut world = World::new();
+///     world.register::<Heat>();
+///     let hot = world.create_entity().with(Heat(100.0)).build();
+///     let warm = world.create_entity().with(Heat(1.0)).build();
+///
+///     let mut tiles = Storage::<Heat, VecStorage<_>, TileId>::default();
+///     tiles.insert(TileId(4), Heat(100.0));
+///     tiles.insert(TileId(9), Heat(1.0));
+///
+///     {
+///         let mut entities = world.write_storage::<Heat>();
+///         decay(&mut entities);
+///         assert_eq!(GenericReadAccess::get(&entities, hot), Some(&Heat(99.0)));
+///         assert_eq!(GenericReadAccess::get(&entities, warm), None);
+///         assert_eq!(total(&entities), 99.0);
+///     }
+///     assert_eq!(total(&world.read_storage::<Heat>()), 99.0);
+///
+///     decay(&mut tiles);
+///     assert_eq!(tiles.get(TileId(4)), Some(&Heat(99.0)));
+///     assert_eq!(tiles.get(TileId(9)), None);
+///     assert_eq!(total(&tiles), 99.0);
+/// }
+/// ```
+pub trait GenericReadAccess<C> {
+    /// The key of the components, `Entity` or the id type.
+    type Key: Copy;
+
+    /// Returns the component of `key`.
+    fn get(&self, key: Self::Key) -> Option<&C>;
+
+    /// Calls `f` with every key and its component, in ascending index order.
+    fn for_each<F>(&self, f: F)
+    where
+        F: FnMut(Self::Key, &C);
+}
+
+/// Mutable access to the components of a storage, see `GenericReadAccess`.
+pub trait GenericWriteAccess<C>: GenericReadAccess<C> {
+    /// Returns the component of `key` mutably.
+    fn get_mut(&mut self, key: Self::Key) -> Option<&mut C>;
+
+    /// Calls `f` with every key and its component, in ascending index order.
+    fn for_each_mut<F>(&mut self, f: F)
+    where
+        F: FnMut(Self::Key, &mut C);
+}
+
+/// Inserting and removing the components of a storage, see `GenericReadAccess`.
+pub trait GenericInsertAccess<C>: GenericWriteAccess<C> {
+    /// Inserts `comp` at `key`, returning the component it replaced.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` is a dead entity, or an id which exceeds the bound of the storage.
+    fn insert(&mut self, key: Self::Key, comp: C) -> Option<C>;
+
+    /// Removes the component of `key`.
+    fn remove(&mut self, key: Self::Key) -> Option<C>;
+}
+
+impl<C, D, I> GenericReadAccess<C> for Storage<C, D, I>
+where
+    C: Component,
+    D: UnprotectedStorage<C>,
+    I: Id,
+{
+    type Key = I;
+
+    fn get(&self, key: I) -> Option<&C> {
+        Storage::get(self, key)
+    }
+
+    fn for_each<F>(&self, mut f: F)
+    where
+        F: FnMut(I, &C),
+    {
+        for id in &self.bitset {
+            f(I::from_u32(id), unsafe { self.data.get(id) });
+        }
+    }
+}
+
+impl<C, D, I> GenericWriteAccess<C> for Storage<C, D, I>
+where
+    C: Component,
+    D: UnprotectedStorage<C>,
+    I: Id,
+{
+    fn get_mut(&mut self, key: I) -> Option<&mut C> {
+        Storage::get_mut(self, key)
+    }
+
+    fn for_each_mut<F>(&mut self, mut f: F)
+    where
+        F: FnMut(I, &mut C),
+    {
+        for id in &self.bitset {
+            f(I::from_u32(id), unsafe { self.data.get_mut(id) });
+        }
+    }
+}
+
+impl<C, D, I> GenericInsertAccess<C> for Storage<C, D, I>
+where
+    C: Component,
+    D: UnprotectedStorage<C>,
+    I: Id,
+{
+    fn insert(&mut self, key: I, comp: C) -> Option<C> {
+        Storage::insert(self, key, comp)
+    }
+
+    fn remove(&mut self, key: I) -> Option<C> {
+        Storage::remove(self, key)
+    }
+}
+
+impl<'e, C, D> GenericReadAccess<C> for ::specs::Storage<'e, C, D>
+where
+    C: Component,
+    D: Deref<Target = MaskedStorage<C>>,
+{
+    type Key = Entity;
+
+    fn get(&self, key: Entity) -> Option<&C> {
+        ::specs::Storage::get(self, key)
+    }
+
+    fn for_each<F>(&self, mut f: F)
+    where
+        F: FnMut(Entity, &C),
+    {
+        for (entity, comp) in (self.fetched_entities(), self).join() {
+            f(entity, comp);
+        }
+    }
+}
+
+impl<'e, C, D> GenericWriteAccess<C> for ::specs::Storage<'e, C, D>
+where
+    C: Component,
+    D: DerefMut<Target = MaskedStorage<C>>,
+{
+    fn get_mut(&mut self, key: Entity) -> Option<&mut C> {
+        ::specs::Storage::get_mut(self, key)
+    }
+
+    fn for_each_mut<F>(&mut self, mut f: F)
+    where
+        F: FnMut(Entity, &mut C),
+    {
+        // The entities are borrowed from the storage, so they can't be joined with it
+        // mutably. Components of entities deleted since the last `maintain` are skipped,
+        // like joins do.
+        let ids: Vec<Index> = self.mask().iter().collect();
+        for id in ids {
+            let entity = self.fetched_entities().entity(id);
+            if let Some(comp) = ::specs::Storage::get_mut(self, entity) {
+                f(entity, comp);
+            }
+        }
+    }
+}
+
+impl<'e, C, D> GenericInsertAccess<C> for ::specs::Storage<'e, C, D>
+where
+    C: Component,
+    D: DerefMut<Target = MaskedStorage<C>>,
+{
+    fn insert(&mut self, key: Entity, comp: C) -> Option<C> {
+        match ::specs::Storage::insert(self, key, comp) {
+            Ok(replaced) => replaced,
+            Err(e) => panic!("can't insert a component: {}", e),
+        }
+    }
+
+    fn remove(&mut self, key: Entity) -> Option<C> {
+        ::specs::Storage::remove(self, key)
+    }
+}
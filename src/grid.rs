@@ -0,0 +1,379 @@
+//! Mapping between two-dimensional tile coordinates and ids.
+
+use std::error::Error;
+use std::fmt;
+use std::marker::PhantomData;
+use std::slice;
+
+#[cfg(not(feature = "specs"))]
+use liballoc::boxed::Box;
+
+use mask::MAX_LEN;
+use {Id, IdSpace, Untagged};
+
+/// The error returned when a grid would have more tiles than a storage can hold ids for.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct GridTooLarge {
+    /// The requested amount of columns.
+    pub width: u32,
+    /// The requested amount of rows.
+    pub height: u32,
+}
+
+impl fmt::Display for GridTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "a {}x{} grid exceeds the maximum of {} tiles",
+            self.width, self.height, MAX_LEN
+        )
+    }
+}
+
+impl Error for GridTooLarge {}
+
+/// The dimensions of a rectangular tile map, whose ids are assigned row by row.
+///
+/// The tile at `(x, y)` has the id `y * width + x`, so the ids of a map are exactly
+/// `0..width * height`. Usually there's one `Grid2d` per id type, added as a resource.
+///
+/// A plain `Grid2d` works with any id type. A grid tagged with `tagged` only produces and
+/// accepts the `TaggedId`s of its tag, so the grids of different id spaces can't be mixed
+/// up; `untagged` turns it back into a plain grid for the tools which take one.
+///
+/// # Examples
+///
+/// ```
+/// use specs_static::Grid2d;
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// let grid = Grid2d::new(64, 32);
+///
+/// assert_eq!(grid.len(), 2048);
+/// assert_eq!(grid.id(3, 2), Some(TileId(131)));
+/// assert_eq!(grid.id::<TileId>(64, 0), None);
+/// assert_eq!(grid.coords(TileId(131)), Some((3, 2)));
+/// assert_eq!(grid.coords(TileId(2048)), None);
+/// ```
+#[derive(Derivative)]
+#[derivative(
+    Clone(bound = ""),
+    Copy(bound = ""),
+    Debug(bound = ""),
+    Eq(bound = ""),
+    Hash(bound = ""),
+    PartialEq(bound = "")
+)]
+pub struct Grid2d<S: ?Sized = Untagged> {
+    width: u32,
+    height: u32,
+    #[derivative(Debug = "ignore")]
+    phantom: PhantomData<fn() -> Box<S>>,
+}
+
+impl Grid2d {
+    /// Creates a grid of `width * height` tiles.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the amount of tiles exceeds the capacity of a hierarchical bitset. Use
+    /// `try_new` for dimensions which aren't known to be small enough.
+    ///
+    /// ```should_panic
+    /// use specs_static::Grid2d;
+    ///
+    /// Grid2d::new(70_000, 70_000);
+    /// ```
+    pub fn new(width: u32, height: u32) -> Self {
+        match Grid2d::try_new(width, height) {
+            Ok(grid) => grid,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Creates a grid of `width * height` tiles, or returns an error if the amount of tiles
+    /// exceeds the capacity of a hierarchical bitset (2<sup>24</sup> on 64-bit targets).
+    ///
+    /// The product is computed without overflowing, and since every id of the grid is below
+    /// it, converting between coordinates and ids can't overflow either.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use specs_static::{Grid2d, GridTooLarge};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    ///
+    /// let grid = Grid2d::try_new(4096, 4096).unwrap();
+    /// assert_eq!(grid.id(4095, 4095), Some(TileId((1 << 24) - 1)));
+    /// assert!(Grid2d::try_new(1 << 24, 1).is_ok());
+    /// assert_eq!(
+    ///     Grid2d::try_new(4097, 4096),
+    ///     Err(GridTooLarge { width: 4097, height: 4096 })
+    /// );
+    /// // 65536 * 65536 wraps around to 0 in 32-bit arithmetic.
+    /// assert!(Grid2d::try_new(65_536, 65_536).is_err());
+    /// assert!(Grid2d::try_new(65_537, 65_537).is_err());
+    /// assert!(Grid2d::try_new(70_000, 70_000).is_err());
+    /// assert!(Grid2d::try_new(u32::max_value(), u32::max_value()).is_err());
+    /// ```
+    pub fn try_new(width: u32, height: u32) -> Result<Self, GridTooLarge> {
+        match u64::from(width) * u64::from(height) <= MAX_LEN {
+            true => Ok(Grid2d {
+                width,
+                height,
+                phantom: PhantomData,
+            }),
+            false => Err(GridTooLarge { width, height }),
+        }
+    }
+
+    /// Returns a grid with the same dimensions which only works with `TaggedId<T>`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use specs_static::{Grid2d, TaggedId};
+    ///
+    /// struct Surface;
+    /// type SurfaceId = TaggedId<Surface>;
+    ///
+    /// let grid = Grid2d::new(16, 16).tagged::<Surface>();
+    /// let id: SurfaceId = grid.id(3, 1).unwrap();
+    /// assert_eq!(grid.coords(id), Some((3, 1)));
+    /// ```
+    pub fn tagged<T>(self) -> Grid2d<T>
+    where
+        T: 'static + Send + Sync,
+    {
+        Grid2d {
+            width: self.width,
+            height: self.height,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<S: ?Sized> Grid2d<S> {
+    /// Returns a plain grid with the same dimensions, which works with any id type.
+    pub fn untagged(&self) -> Grid2d {
+        Grid2d {
+            width: self.width,
+            height: self.height,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns the amount of columns.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Returns the amount of rows.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the amount of tiles, which is one past the highest id.
+    pub fn len(&self) -> u32 {
+        self.width * self.height
+    }
+
+    /// Returns `true` if the grid doesn't have any tiles.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns `true` if `(x, y)` is inside the grid.
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        x < self.width && y < self.height
+    }
+
+    /// Returns the id of the tile at `(x, y)`, or `None` if it's outside the grid.
+    pub fn id<I: IdSpace<S>>(&self, x: u32, y: u32) -> Option<I> {
+        match self.contains(x, y) {
+            true => Some(I::from_u32(y * self.width + x)),
+            false => None,
+        }
+    }
+
+    /// Returns the coordinates of the tile `id`, or `None` if it's outside the grid.
+    pub fn coords<I: IdSpace<S>>(&self, id: I) -> Option<(u32, u32)> {
+        self.coords_of(id.id())
+    }
+
+    /// Returns an iterator over the ids of the tiles adjacent to `id` which are inside the
+    /// grid, in row-major order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use specs_static::{Connectivity, Grid2d};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// let grid = Grid2d::new(4, 3);
+    ///
+    /// let four: Vec<_> = grid.neighbors(TileId(5), Connectivity::Four).collect();
+    /// assert_eq!(four, vec![TileId(1), TileId(4), TileId(6), TileId(9)]);
+    /// let corner: Vec<_> = grid.neighbors(TileId(0), Connectivity::Eight).collect();
+    /// assert_eq!(corner, vec![TileId(1), TileId(4), TileId(5)]);
+    /// assert_eq!(grid.neighbors(TileId(12), Connectivity::Eight).count(), 0);
+    /// ```
+    pub fn neighbors<I: IdSpace<S>>(&self, id: I, connectivity: Connectivity) -> Neighbors<I> {
+        let offsets = match connectivity {
+            Connectivity::Four => &FOUR[..],
+            Connectivity::Eight => &EIGHT[..],
+        };
+
+        Neighbors {
+            grid: self.untagged(),
+            center: self.coords(id),
+            offsets: offsets.iter(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns a value formatting `id` as the coordinates it has in this grid, like
+    /// `(x: 3, y: 2)`, for log lines and panic messages.
+    ///
+    /// Ids which are outside the grid are printed as the raw id instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use specs_static::Grid2d;
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// let grid = Grid2d::new(500, 20);
+    ///
+    /// assert_eq!(grid.display(TileId(8919)).to_string(), "(x: 419, y: 17)");
+    /// assert_eq!(
+    ///     format!("{:?}", grid.display(TileId(8919))),
+    ///     "TileDisplay { id: 8919, x: 419, y: 17 }"
+    /// );
+    /// assert_eq!(
+    ///     grid.display(TileId(10_000)).to_string(),
+    ///     "id 10000 (outside of the 500x20 grid)"
+    /// );
+    /// ```
+    pub fn display<I: IdSpace<S>>(&self, id: I) -> TileDisplay {
+        TileDisplay {
+            grid: self.untagged(),
+            id: id.id(),
+        }
+    }
+}
+
+/// Formats an id as its coordinates in a `Grid2d`, created with `Grid2d::display`.
+#[derive(Clone, Copy)]
+pub struct TileDisplay {
+    grid: Grid2d,
+    id: u32,
+}
+
+impl fmt::Display for TileDisplay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.grid.coords_of(self.id) {
+            Some((x, y)) => write!(f, "(x: {}, y: {})", x, y),
+            None => write!(
+                f,
+                "id {} (outside of the {}x{} grid)",
+                self.id, self.grid.width, self.grid.height
+            ),
+        }
+    }
+}
+
+impl fmt::Debug for TileDisplay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut s = f.debug_struct("TileDisplay");
+        s.field("id", &self.id);
+        if let Some((x, y)) = self.grid.coords_of(self.id) {
+            s.field("x", &x).field("y", &y);
+        }
+
+        s.finish()
+    }
+}
+
+impl<S: ?Sized> Grid2d<S> {
+    pub(crate) fn coords_of(&self, id: u32) -> Option<(u32, u32)> {
+        match id < self.len() {
+            true => Some((id % self.width, id / self.width)),
+            false => None,
+        }
+    }
+}
+
+pub(crate) const FOUR: [(i64, i64); 4] = [(0, -1), (-1, 0), (1, 0), (0, 1)];
+pub(crate) const EIGHT: [(i64, i64); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+/// Which tiles count as adjacent to each other.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Connectivity {
+    /// Tiles sharing an edge are adjacent.
+    Four,
+    /// Tiles sharing an edge or a corner are adjacent.
+    Eight,
+}
+
+impl Connectivity {
+    /// Returns the least amount of steps between `a` and `b`, which is the Manhattan distance
+    /// for `Four` and the Chebyshev distance for `Eight`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use specs_static::Connectivity;
+    ///
+    /// assert_eq!(Connectivity::Four.distance((1, 1), (4, 3)), 5);
+    /// assert_eq!(Connectivity::Eight.distance((1, 1), (4, 3)), 3);
+    /// ```
+    pub fn distance(self, a: (u32, u32), b: (u32, u32)) -> u32 {
+        let dx = a.0.max(b.0) - a.0.min(b.0);
+        let dy = a.1.max(b.1) - a.1.min(b.1);
+
+        match self {
+            Connectivity::Four => dx + dy,
+            Connectivity::Eight => dx.max(dy),
+        }
+    }
+}
+
+/// The iterator returned by `Grid2d::neighbors`.
+#[derive(Clone, Debug)]
+pub struct Neighbors<I> {
+    grid: Grid2d,
+    center: Option<(u32, u32)>,
+    offsets: slice::Iter<'static, (i64, i64)>,
+    phantom: PhantomData<I>,
+}
+
+impl<I: Id> Iterator for Neighbors<I> {
+    type Item = I;
+
+    fn next(&mut self) -> Option<I> {
+        let (x, y) = self.center?;
+
+        for &(dx, dy) in &mut self.offsets {
+            let (nx, ny) = (i64::from(x) + dx, i64::from(y) + dy);
+            if nx < 0 || ny < 0 {
+                continue;
+            }
+            if let Some(id) = self.grid.id(nx as u32, ny as u32) {
+                return Some(id);
+            }
+        }
+
+        None
+    }
+}
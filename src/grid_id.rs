@@ -0,0 +1,260 @@
+//! Ids of grids whose dimensions are known at compile time.
+
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::ops::Range;
+
+use mask::MAX_LEN;
+use {Grid2d, Id};
+
+/// The id of a tile of a `W * H` grid whose dimensions are known at compile time, like a
+/// chess board, assigned row by row like the ids of `Grid2d`.
+///
+/// Since the dimensions are constants, converting between coordinates and ids compiles to
+/// a few instructions, bounds checks against constant coordinates are folded away, and
+/// no `Grid2d` resource is needed. The constructors and accessors are `const fn`s, so ids
+/// can be computed in constants. Grids with more tiles than a storage can hold ids for
+/// fail to compile once their ids are created.
+///
+/// `GridId` is an `Id`, so it's used with `Storage` like any other id type. Ids created
+/// with `Id::from_u32` aren't checked, like those of `Grid2d`.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::{Component, VecStorage};
+/// use specs_static::{GridId, Storage};
+///
+/// type Square = GridId<8, 8>;
+///
+/// const E1: Option<Square> = Square::new(4, 0);
+/// const E1_NEIGHBORS: [Option<Square>; 4] = match E1 {
+///     Some(e1) => e1.neighbors4(),
+///     None => [None; 4],
+/// };
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Piece(char);
+///
+/// impl Component for Piece {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// fn main() {
+///     let e1 = E1.unwrap();
+///     assert_eq!((e1.x(), e1.y()), (4, 0));
+///     assert_eq!(
+///         E1_NEIGHBORS,
+///         [None, Square::new(3, 0), Square::new(5, 0), Square::new(4, 1)]
+///     );
+///
+///     // The last row and column are inside, one past them isn't.
+///     let h8 = Square::new(7, 7).unwrap();
+///     assert_eq!((h8.x(), h8.y()), (7, 7));
+///     assert_eq!(h8.neighbors4(), [Square::new(7, 6), Square::new(6, 7), None, None]);
+///     assert_eq!(Square::new(8, 7), None);
+///     assert_eq!(Square::new(7, 8), None);
+///     assert_eq!(Square::LEN, 64);
+///
+///     let mut board = Storage::<Piece, VecStorage<_>, Square>::default();
+///     board.insert(e1, Piece('K'));
+///     for square in Square::iter_all().filter(|square| square.y() == 1) {
+///         board.insert(square, Piece('P'));
+///     }
+///     assert_eq!(board.get(e1), Some(&Piece('K')));
+///     assert_eq!(Square::iter_all().filter(|&square| board.get(square).is_some()).count(), 9);
+///     assert_eq!(Square::grid().id(4, 0), Some(e1));
+/// }
+/// ```
+#[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct GridId<const W: u32, const H: u32>(u32);
+
+impl<const W: u32, const H: u32> GridId<W, H> {
+    /// The amount of tiles, which is one past the highest id. Evaluating it fails to
+    /// compile if the grid has more tiles than a storage can hold ids for.
+    ///
+    /// ```compile_fail
+    /// use specs_static::GridId;
+    ///
+    /// let _ = GridId::<70_000, 70_000>::new(0, 0);
+    /// ```
+    pub const LEN: u32 = {
+        let len = W as u64 * H as u64;
+        // Indexing out of bounds is a compile error in constants.
+        [len][(len > MAX_LEN) as usize] as u32
+    };
+
+    /// Returns the id of the tile at `(x, y)`, or `None` if it's outside the grid.
+    #[inline]
+    pub const fn new(x: u32, y: u32) -> Option<Self> {
+        match x < W && y < H && Self::LEN > 0 {
+            true => Some(GridId(y * W + x)),
+            false => None,
+        }
+    }
+
+    /// Returns the column of the tile.
+    #[inline]
+    pub const fn x(self) -> u32 {
+        self.0 % W
+    }
+
+    /// Returns the row of the tile.
+    #[inline]
+    pub const fn y(self) -> u32 {
+        self.0 / W
+    }
+
+    /// Returns the tiles sharing an edge with this one, in row-major order like
+    /// `Grid2d::neighbors`: above, left, right and below. Neighbors outside the grid are
+    /// `None`.
+    #[inline]
+    pub const fn neighbors4(self) -> [Option<Self>; 4] {
+        let (x, y) = (self.x(), self.y());
+
+        [
+            match y > 0 {
+                true => Self::new(x, y - 1),
+                false => None,
+            },
+            match x > 0 {
+                true => Self::new(x - 1, y),
+                false => None,
+            },
+            Self::new(x + 1, y),
+            Self::new(x, y + 1),
+        ]
+    }
+
+    /// Returns an iterator over all ids of the grid, in ascending order.
+    pub fn iter_all() -> GridIds<W, H> {
+        GridIds(0..Self::LEN)
+    }
+
+    /// Returns the `Grid2d` with the same dimensions, for the tools which take one.
+    pub fn grid() -> Grid2d {
+        Grid2d::new(W, H)
+    }
+}
+
+impl<const W: u32, const H: u32> Id for GridId<W, H> {
+    #[inline]
+    fn from_u32(value: u32) -> Self {
+        GridId(value)
+    }
+
+    #[inline]
+    fn id(&self) -> u32 {
+        self.0
+    }
+}
+
+/// The error returned when converting coordinates outside the grid into a `GridId`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct OutsideGrid {
+    /// The column of the coordinates.
+    pub x: u32,
+    /// The row of the coordinates.
+    pub y: u32,
+    /// The amount of columns of the grid.
+    pub width: u32,
+    /// The amount of rows of the grid.
+    pub height: u32,
+}
+
+impl fmt::Display for OutsideGrid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "({}, {}) is outside the {}x{} grid",
+            self.x, self.y, self.width, self.height
+        )
+    }
+}
+
+impl Error for OutsideGrid {}
+
+/// Converts the coordinates `(x, y)` into the id of their tile, so storages with grid ids can
+/// be accessed by coordinates with `Storage::get_at` and its siblings, like
+/// `board.get_at((4, 0))`. Fails for coordinates outside the grid, like `GridId::new`.
+///
+/// # Examples
+///
+/// ```
+/// use std::convert::TryFrom;
+///
+/// use specs_static::{GridId, OutsideGrid};
+///
+/// type Cell = GridId<16, 9>;
+///
+/// assert_eq!(Cell::try_from((3, 4)), Ok(Cell::new(3, 4).unwrap()));
+/// assert_eq!(
+///     Cell::try_from((16, 0)),
+///     Err(OutsideGrid { x: 16, y: 0, width: 16, height: 9 })
+/// );
+/// ```
+impl<const W: u32, const H: u32> TryFrom<(u32, u32)> for GridId<W, H> {
+    type Error = OutsideGrid;
+
+    #[inline]
+    fn try_from((x, y): (u32, u32)) -> Result<Self, OutsideGrid> {
+        GridId::new(x, y).ok_or(OutsideGrid {
+            x,
+            y,
+            width: W,
+            height: H,
+        })
+    }
+}
+
+/// Prints the id together with its coordinates, like `GridId(67 @ 3, 4)`. Ids outside the
+/// grid, which only `Id::from_u32` creates, are printed as the bare value.
+///
+/// # Examples
+///
+/// ```
+/// use specs_static::{GridId, Id};
+///
+/// type Cell = GridId<16, 9>;
+///
+/// assert_eq!(format!("{:?}", Cell::new(3, 4).unwrap()), "GridId(67 @ 3, 4)");
+/// assert_eq!(format!("{:?}", Cell::new(15, 8).unwrap()), "GridId(143 @ 15, 8)");
+/// assert_eq!(format!("{:?}", Cell::from_u32(144)), "GridId(144)");
+/// assert_eq!(format!("{:?}", GridId::<0, 9>::from_u32(0)), "GridId(0)");
+/// ```
+impl<const W: u32, const H: u32> fmt::Debug for GridId<W, H> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match u64::from(self.0) < u64::from(W) * u64::from(H) {
+            true => write!(f, "GridId({} @ {}, {})", self.0, self.x(), self.y()),
+            false => write!(f, "GridId({})", self.0),
+        }
+    }
+}
+
+/// The iterator returned by `GridId::iter_all`.
+#[derive(Clone, Debug)]
+pub struct GridIds<const W: u32, const H: u32>(Range<u32>);
+
+impl<const W: u32, const H: u32> Iterator for GridIds<W, H> {
+    type Item = GridId<W, H>;
+
+    fn next(&mut self) -> Option<GridId<W, H>> {
+        self.0.next().map(GridId)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<const W: u32, const H: u32> DoubleEndedIterator for GridIds<W, H> {
+    fn next_back(&mut self) -> Option<GridId<W, H>> {
+        self.0.next_back().map(GridId)
+    }
+}
+
+impl<const W: u32, const H: u32> ExactSizeIterator for GridIds<W, H> {}
@@ -0,0 +1,128 @@
+//! Hashing the contents of storages, for comparing the states of peers.
+
+use std::hash::{Hash, Hasher};
+
+use specs::storage::UnprotectedStorage;
+
+use {Id, Storage};
+
+/// A 64-bit FNV-1a hasher which hashes integers as little-endian bytes, so the same logical
+/// content hashes the same on every platform and with every Rust version.
+///
+/// `usize` and `isize` are hashed as 64-bit integers. The hashes of floats and pointers
+/// still depend on their bits, which may differ between platforms for the same computation;
+/// keeping float components deterministic is up to the user.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct StableHasher(u64);
+
+impl StableHasher {
+    /// Creates a hasher with the FNV offset basis as its state.
+    pub fn new() -> Self {
+        StableHasher(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        StableHasher::new()
+    }
+}
+
+impl Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+}
+
+impl<C, D, I> Storage<C, D, I>
+where
+    C: Hash,
+    D: UnprotectedStorage<C>,
+    I: Id,
+{
+    /// Feeds every occupied id and its component into `state`, in ascending id order,
+    /// followed by the amount of components.
+    ///
+    /// The hash only depends on the contents, not on the backend or the order the
+    /// components have been inserted in. Together with `StableHasher`, it's stable across
+    /// platforms, as long as the `Hash` impl of `C` is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use std::hash::Hasher;
+    ///
+    /// use specs::storage::HashMapStorage;
+    /// use specs::{Component, VecStorage};
+    /// use specs_static::{StableHasher, Storage};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// #[derive(Hash)]
+    /// struct Owner(u8);
+    ///
+    /// impl Component for Owner {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn hash<S: Hasher + Default>(f: impl FnOnce(&mut S)) -> u64 {
+    ///     let mut state = S::default();
+    ///     f(&mut state);
+    ///     state.finish()
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut forward = Storage::<Owner, VecStorage<_>, TileId>::default();
+    ///     let mut backward = Storage::<Owner, HashMapStorage<_>, TileId>::default();
+    ///     for id in 0..100 {
+    ///         forward.insert(TileId(id), Owner(id as u8 % 4));
+    ///         backward.insert(TileId(99 - id), Owner((99 - id) as u8 % 4));
+    ///     }
+    ///
+    ///     let expected = hash::<StableHasher>(|state| forward.content_hash(state));
+    ///     assert_eq!(hash::<StableHasher>(|state| backward.content_hash(state)), expected);
+    ///
+    ///     backward.insert(TileId(50), Owner(3));
+    ///     assert_ne!(hash::<StableHasher>(|state| backward.content_hash(state)), expected);
+    /// }
+    /// ```
+    pub fn content_hash<H: Hasher>(&self, state: &mut H) {
+        let mut len = 0u64;
+        for id in &self.bitset {
+            state.write_u32(id);
+            unsafe { self.data.get(id) }.hash(state);
+            len += 1;
+        }
+
+        state.write_u64(len);
+    }
+}
@@ -0,0 +1,135 @@
+//! Creating layers from grayscale images, like heightmaps.
+
+use image::{GrayImage, ImageBuffer, Luma};
+use specs::storage::UnprotectedStorage;
+
+use {Grid2d, GridTooLarge, Id, Storage};
+
+impl<C, D, I> Storage<C, D, I>
+where
+    D: UnprotectedStorage<C> + Default,
+    I: Id,
+{
+    /// Creates a grid with the dimensions of `img` and a storage with the components
+    /// `f(x, y, luma)` for its pixels, skipping pixels for which `f` returns `None`.
+    ///
+    /// Pixels are visited row by row and written through the same path as `from_fn`, so
+    /// the pixel at `(x, y)` ends up at `grid.id(x, y)`. Decoding the image file is left to
+    /// the `image` crate, with the format features of your choice, for example
+    /// `image::open(path)?.into_luma8()`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GridTooLarge` if the image has more pixels than a grid can have tiles.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate image;
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use image::{GrayImage, Luma};
+    /// use specs::{Component, Join, VecStorage};
+    /// use specs_static::Storage;
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// #[derive(Debug, PartialEq)]
+    /// struct Height(u8);
+    ///
+    /// impl Component for Height {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// type Heights = Storage<Height, VecStorage<Height>, TileId>;
+    ///
+    /// fn main() {
+    ///     // Distinct corners, and a hole in the middle of the top row.
+    ///     let img = GrayImage::from_fn(5, 3, |x, y| match (x, y) {
+    ///         (0, 0) => Luma([10]),
+    ///         (4, 0) => Luma([20]),
+    ///         (0, 2) => Luma([30]),
+    ///         (4, 2) => Luma([40]),
+    ///         (2, 0) => Luma([0]),
+    ///         _ => Luma([1]),
+    ///     });
+    ///
+    ///     let (grid, heights) = Heights::from_image_luma(&img, |_, _, luma| match luma {
+    ///         0 => None,
+    ///         luma => Some(Height(luma)),
+    ///     })
+    ///     .unwrap();
+    ///     assert_eq!((grid.width(), grid.height()), (5, 3));
+    ///     assert_eq!(heights.get(TileId(0)), Some(&Height(10)));
+    ///     assert_eq!(heights.get(TileId(4)), Some(&Height(20)));
+    ///     assert_eq!(heights.get(TileId(10)), Some(&Height(30)));
+    ///     assert_eq!(heights.get(TileId(14)), Some(&Height(40)));
+    ///     assert_eq!(heights.get(TileId(2)), None);
+    ///     assert_eq!((&heights).join().count(), 14);
+    ///
+    ///     // The coordinates are passed along, for example to crop the image.
+    ///     let (_, left) = Heights::from_image_luma(&img, |x, _, luma| match x < 2 {
+    ///         true => Some(Height(luma)),
+    ///         false => None,
+    ///     })
+    ///     .unwrap();
+    ///     assert_eq!((&left).join().count(), 6);
+    /// }
+    /// ```
+    pub fn from_image_luma<F>(img: &GrayImage, mut f: F) -> Result<(Grid2d, Self), GridTooLarge>
+    where
+        F: FnMut(u32, u32, u8) -> Option<C>,
+    {
+        let grid = Grid2d::try_new(img.width(), img.height())?;
+        let storage = Self::from_fn(&grid, |x, y| f(x, y, img.get_pixel(x, y)[0]));
+
+        Ok((grid, storage))
+    }
+
+    /// Like `from_image_luma`, but for 16-bit grayscale images, which heightmaps of real
+    /// elevation data usually are, for example from `image::open(path)?.into_luma16()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate image;
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use image::{ImageBuffer, Luma};
+    /// use specs::{Component, VecStorage};
+    /// use specs_static::Storage;
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// #[derive(Debug, PartialEq)]
+    /// struct Depth(u16);
+    ///
+    /// impl Component for Depth {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let img = ImageBuffer::from_fn(2, 2, |x, y| Luma([1000 * (y * 2 + x) as u16 + 999]));
+    ///     let (grid, depths) = Storage::<Depth, VecStorage<_>, TileId>::from_image_luma16(
+    ///         &img,
+    ///         |_, _, luma| Some(Depth(luma)),
+    ///     )
+    ///     .unwrap();
+    ///
+    ///     assert_eq!(depths.get(grid.id(0, 0).unwrap()), Some(&Depth(999)));
+    ///     assert_eq!(depths.get(grid.id(1, 1).unwrap()), Some(&Depth(3999)));
+    /// }
+    /// ```
+    pub fn from_image_luma16<F>(
+        img: &ImageBuffer<Luma<u16>, Vec<u16>>,
+        mut f: F,
+    ) -> Result<(Grid2d, Self), GridTooLarge>
+    where
+        F: FnMut(u32, u32, u16) -> Option<C>,
+    {
+        let grid = Grid2d::try_new(img.width(), img.height())?;
+        let storage = Self::from_fn(&grid, |x, y| f(x, y, img.get_pixel(x, y)[0]));
+
+        Ok((grid, storage))
+    }
+}
@@ -0,0 +1,53 @@
+//! Handing out mutable references from joins without extending reborrows.
+
+use std::marker::PhantomData;
+
+/// A pointer to data which is mutably borrowed for `'a`, used as the `Join::Value` of
+/// mutable joins.
+///
+/// `Join::get` only receives a short-lived `&mut Self::Value`, so the references it returns
+/// can't be borrowed from that. Instead, every call creates a fresh reference from this
+/// pointer, which stems from the original `&'a mut` borrow. Joins visit every index at most
+/// once, so the references handed out never alias.
+pub struct JoinMut<'a, T: 'a> {
+    ptr: *mut T,
+    phantom: PhantomData<&'a mut T>,
+}
+
+unsafe impl<'a, T: Send> Send for JoinMut<'a, T> {}
+unsafe impl<'a, T: Sync> Sync for JoinMut<'a, T> {}
+
+impl<'a, T> JoinMut<'a, T> {
+    /// Creates a pointer to `value`.
+    pub(crate) fn new(value: &'a mut T) -> Self {
+        JoinMut {
+            ptr: value,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Creates a pointer to the first element of `slice`.
+    pub(crate) fn from_slice(slice: &'a mut [T]) -> Self {
+        JoinMut {
+            ptr: slice.as_mut_ptr(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns the value this points to.
+    ///
+    /// The caller has to make sure that the parts of the value which are accessed through
+    /// the returned reference aren't accessed through any other reference at the same time.
+    pub(crate) unsafe fn get(&self) -> &'a mut T {
+        &mut *self.ptr
+    }
+
+    /// Returns element `index` of the slice this points to.
+    ///
+    /// The caller has to make sure that this has been created with `from_slice`, that
+    /// `index` is in bounds, and that the element isn't accessed through any other
+    /// reference at the same time.
+    pub(crate) unsafe fn get_at(&self, index: usize) -> &'a mut T {
+        &mut *self.ptr.add(index)
+    }
+}
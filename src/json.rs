@@ -0,0 +1,440 @@
+//! Human-readable JSON grids of layers, for debugging and hand-authored test fixtures.
+
+use std::error::Error;
+use std::fmt;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{self, Value};
+use specs::storage::UnprotectedStorage;
+
+use {AnyTileStorage, Grid2d, Id, IdOutOfBounds, Storage};
+
+/// The error returned by `Storage::from_json_grid`.
+#[derive(Debug)]
+pub enum JsonGridError {
+    /// The value, or one of its rows, isn't an array. `row` is `None` for the value itself.
+    NotAnArray {
+        /// The row which isn't an array.
+        row: Option<u32>,
+    },
+    /// The value doesn't have as many rows as the grid.
+    Height {
+        /// The height of the grid.
+        expected: u32,
+        /// The amount of rows of the value.
+        found: usize,
+    },
+    /// A row doesn't have as many cells as the grid has columns.
+    Width {
+        /// The row with the wrong amount of cells.
+        row: u32,
+        /// The width of the grid.
+        expected: u32,
+        /// The amount of cells of the row.
+        found: usize,
+    },
+    /// A cell which isn't `null` couldn't be deserialized as a component.
+    Cell {
+        /// The column of the cell.
+        x: u32,
+        /// The row of the cell.
+        y: u32,
+        /// Why deserializing failed.
+        error: serde_json::Error,
+    },
+}
+
+impl fmt::Display for JsonGridError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            JsonGridError::NotAnArray { row: None } => write!(f, "the grid isn't an array of rows"),
+            JsonGridError::NotAnArray { row: Some(row) } => {
+                write!(f, "row {} isn't an array of cells", row)
+            }
+            JsonGridError::Height { expected, found } => {
+                write!(f, "expected {} rows, found {}", expected, found)
+            }
+            JsonGridError::Width {
+                row,
+                expected,
+                found,
+            } => write!(f, "expected {} cells in row {}, found {}", expected, row, found),
+            JsonGridError::Cell { x, y, ref error } => {
+                write!(f, "cell ({}, {}) isn't a valid component: {}", x, y, error)
+            }
+        }
+    }
+}
+
+impl Error for JsonGridError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            JsonGridError::Cell { ref error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// The error returned by `TileWorldExt::tile_json` and `TileWorldExt::set_tile_json`.
+#[derive(Debug)]
+pub enum TileJsonError {
+    /// No layer is registered under the name, or its storage resource has been removed.
+    UnknownLayer {
+        /// The name of the layer.
+        name: String,
+    },
+    /// The layer hasn't been added with `TileRegistry::register_json`.
+    NotJson {
+        /// The name of the layer.
+        name: String,
+    },
+    /// The id exceeds the bound of the storage of the layer.
+    OutOfBounds {
+        /// The name of the layer.
+        name: String,
+        /// The rejected id and the bound.
+        error: IdOutOfBounds,
+    },
+    /// The value couldn't be deserialized as a component of the layer.
+    Invalid {
+        /// The name of the layer.
+        name: String,
+        /// Why deserializing failed.
+        error: serde_json::Error,
+    },
+}
+
+impl fmt::Display for TileJsonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TileJsonError::UnknownLayer { ref name } => {
+                write!(f, "there is no layer named {:?}", name)
+            }
+            TileJsonError::NotJson { ref name } => {
+                write!(f, "the layer {:?} hasn't been registered for JSON access", name)
+            }
+            TileJsonError::OutOfBounds {
+                ref name,
+                ref error,
+            } => write!(f, "layer {:?}: {}", name, error),
+            TileJsonError::Invalid {
+                ref name,
+                ref error,
+            } => write!(f, "invalid component for the layer {:?}: {}", name, error),
+        }
+    }
+}
+
+impl Error for TileJsonError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            TileJsonError::OutOfBounds { ref error, .. } => Some(error),
+            TileJsonError::Invalid { ref error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// How to convert the components of a registered layer from and to JSON.
+#[derive(Clone, Copy)]
+pub(crate) struct JsonTile {
+    /// Serializes the component at the id, if there is one.
+    pub(crate) get: fn(&dyn AnyTileStorage, u32) -> Option<Value>,
+    /// Deserializes the value and inserts it at the id, or removes the component at the id
+    /// if the value is `null`. The name of the layer is used for errors.
+    pub(crate) set: fn(&mut dyn AnyTileStorage, &str, u32, &Value) -> Result<(), TileJsonError>,
+}
+
+impl JsonTile {
+    pub(crate) fn new<C, D, I>() -> Self
+    where
+        C: DeserializeOwned + Serialize + Send + Sync + 'static,
+        D: UnprotectedStorage<C> + Send + Sync + 'static,
+        I: Id,
+    {
+        JsonTile {
+            get: |storage, id| {
+                let storage = storage
+                    .as_any()
+                    .downcast_ref::<Storage<C, D, I>>()
+                    .expect("registered with this storage type");
+
+                storage.get(I::from_u32(id)).map(|comp| {
+                    serde_json::to_value(comp).expect("component can't be represented as JSON")
+                })
+            },
+            set: |storage, name, id, value| {
+                let storage = storage
+                    .as_any_mut()
+                    .downcast_mut::<Storage<C, D, I>>()
+                    .expect("registered with this storage type");
+                if value.is_null() {
+                    storage.remove(I::from_u32(id));
+                    return Ok(());
+                }
+
+                let comp = C::deserialize(value).map_err(|error| TileJsonError::Invalid {
+                    name: name.to_owned(),
+                    error,
+                })?;
+                storage
+                    .try_insert(I::from_u32(id), comp)
+                    .map(|_| ())
+                    .map_err(|error| TileJsonError::OutOfBounds {
+                        name: name.to_owned(),
+                        error,
+                    })
+            },
+        }
+    }
+}
+
+impl<C, D, I> Storage<C, D, I>
+where
+    D: UnprotectedStorage<C>,
+    I: Id,
+{
+    /// Returns the tiles of `grid` as a JSON array of rows, each an array of the serialized
+    /// components of the row, with `null` for vacant tiles.
+    ///
+    /// This is meant for looking at layers and diffing them, so it favors readability over
+    /// size and its layout may change; use `TileCodec` or the save formats for persistent
+    /// data. Components outside of the grid are left out. Pretty-printing the value with
+    /// `serde_json::to_string_pretty` puts every cell on a line of its own, so for small
+    /// grids of numbers, printing row by row may be easier to read.
+    ///
+    /// Since vacant tiles are `null`, components which serialize to `null` themselves, like
+    /// unit structs, can't be told apart from vacant tiles by `from_json_grid`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a component can't be represented as JSON, for example a map with keys
+    /// which aren't strings.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// #[macro_use]
+    /// extern crate serde_derive;
+    /// # extern crate serde_crate as serde;
+    /// extern crate serde_json;
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use serde_json::Value;
+    /// use specs::{Component, VecStorage};
+    /// use specs_static::{Grid2d, Storage};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// #[derive(Deserialize, Serialize)]
+    /// # #[serde(crate = "serde")]
+    /// struct Height(u8);
+    ///
+    /// impl Component for Height {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// type Heights = Storage<Height, VecStorage<Height>, TileId>;
+    ///
+    /// fn main() {
+    ///     let grid = Grid2d::new(3, 2);
+    ///     let mut heights = Heights::default();
+    ///     heights.insert(grid.id(0, 0).unwrap(), Height(4));
+    ///     heights.insert(grid.id(2, 0).unwrap(), Height(7));
+    ///     heights.insert(grid.id(1, 1).unwrap(), Height(0));
+    ///
+    ///     let json = heights.to_json_grid(&grid);
+    ///     assert_eq!(json.to_string(), "[[4,null,7],[null,0,null]]");
+    ///
+    ///     let back = Heights::from_json_grid(&grid, &json).unwrap();
+    ///     assert_eq!(back.to_json_grid(&grid), json);
+    ///
+    ///     // The grid decides which tiles are exported.
+    ///     let row = heights.to_json_grid(&Grid2d::new(2, 1));
+    ///     assert_eq!(row, Value::Array(vec![serde_json::json!([4, null])]));
+    /// }
+    /// ```
+    pub fn to_json_grid(&self, grid: &Grid2d) -> Value
+    where
+        C: Serialize,
+    {
+        let rows = (0..grid.height())
+            .map(|y| {
+                let cells = (0..grid.width())
+                    .map(|x| {
+                        let id = y * grid.width() + x;
+                        match self.bitset.contains(id) {
+                            true => serde_json::to_value(unsafe { self.data.get(id) })
+                                .expect("component can't be represented as JSON"),
+                            false => Value::Null,
+                        }
+                    })
+                    .collect();
+
+                Value::Array(cells)
+            })
+            .collect();
+
+        Value::Array(rows)
+    }
+}
+
+impl<C, D, I> Storage<C, D, I>
+where
+    C: DeserializeOwned,
+    D: UnprotectedStorage<C> + Default,
+    I: Id,
+{
+    /// Creates a storage from a JSON array of rows of `grid`, as returned by `to_json_grid`.
+    /// `null` cells become vacant tiles.
+    ///
+    /// The value has to match the dimensions of the grid exactly, so a fixture which is out
+    /// of date with its test fails loudly instead of loading a partial layer.
+    ///
+    /// # Examples
+    ///
+    /// Loading a hand-authored fixture:
+    ///
+    /// ```
+    /// #[macro_use]
+    /// extern crate serde_derive;
+    /// # extern crate serde_crate as serde;
+    /// extern crate serde_json;
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, VecStorage};
+    /// use specs_static::{Grid2d, Storage};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// # #[serde(crate = "serde")]
+    /// enum Soil {
+    ///     Clay,
+    ///     Loam { fertility: u8 },
+    ///     Sand,
+    /// }
+    ///
+    /// impl Component for Soil {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// type Soils = Storage<Soil, VecStorage<Soil>, TileId>;
+    ///
+    /// fn main() {
+    ///     let fixture = include_str!(concat!(
+    ///         env!("CARGO_MANIFEST_DIR"),
+    ///         "/tests/fixtures/soil.json"
+    ///     ));
+    ///     let json: serde_json::Value = serde_json::from_str(fixture).unwrap();
+    ///
+    ///     let grid = Grid2d::new(4, 3);
+    ///     let soils = Soils::from_json_grid(&grid, &json).unwrap();
+    ///     assert_eq!(soils.get(grid.id(0, 0).unwrap()), Some(&Soil::Clay));
+    ///     assert_eq!(soils.get(grid.id(1, 0).unwrap()), None);
+    ///     assert_eq!(soils.get(grid.id(2, 1).unwrap()), Some(&Soil::Loam { fertility: 3 }));
+    ///     assert_eq!(soils.get(grid.id(3, 2).unwrap()), Some(&Soil::Sand));
+    ///
+    ///     // Round-trips through the JSON grid and its text.
+    ///     assert_eq!(soils.to_json_grid(&grid), json);
+    ///     let text = serde_json::to_string_pretty(&soils.to_json_grid(&grid)).unwrap();
+    ///     let reloaded = Soils::from_json_grid(&grid, &serde_json::from_str(&text).unwrap());
+    ///     assert_eq!(reloaded.unwrap().to_json_grid(&grid), json);
+    /// }
+    /// ```
+    ///
+    /// Errors name the offending row or cell:
+    ///
+    /// ```
+    /// #[macro_use]
+    /// extern crate serde_derive;
+    /// # extern crate serde_crate as serde;
+    /// #[macro_use]
+    /// extern crate serde_json;
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, VecStorage};
+    /// use specs_static::{Grid2d, JsonGridError, Storage};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// #[derive(Deserialize, Serialize)]
+    /// # #[serde(crate = "serde")]
+    /// struct Height(u8);
+    ///
+    /// impl Component for Height {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// type Heights = Storage<Height, VecStorage<Height>, TileId>;
+    ///
+    /// fn main() {
+    ///     let grid = Grid2d::new(2, 2);
+    ///     let load = |value| Heights::from_json_grid(&grid, &value).err().unwrap();
+    ///
+    ///     match load(json!({ "rows": [] })) {
+    ///         JsonGridError::NotAnArray { row: None } => {}
+    ///         other => panic!("unexpected error {}", other),
+    ///     }
+    ///     match load(json!([[1, 2]])) {
+    ///         JsonGridError::Height { expected: 2, found: 1 } => {}
+    ///         other => panic!("unexpected error {}", other),
+    ///     }
+    ///     match load(json!([[1, 2], 3])) {
+    ///         JsonGridError::NotAnArray { row: Some(1) } => {}
+    ///         other => panic!("unexpected error {}", other),
+    ///     }
+    ///     match load(json!([[1, 2], [3]])) {
+    ///         JsonGridError::Width { row: 1, expected: 2, found: 1 } => {}
+    ///         other => panic!("unexpected error {}", other),
+    ///     }
+    ///
+    ///     let error = load(json!([[1, null], [300, 4]]));
+    ///     match error {
+    ///         JsonGridError::Cell { x: 0, y: 1, .. } => {}
+    ///         ref other => panic!("unexpected error {}", other),
+    ///     }
+    ///     assert!(error.to_string().starts_with("cell (0, 1) isn't a valid component"));
+    /// }
+    /// ```
+    pub fn from_json_grid(grid: &Grid2d, value: &Value) -> Result<Self, JsonGridError> {
+        let rows = value
+            .as_array()
+            .ok_or(JsonGridError::NotAnArray { row: None })?;
+        if rows.len() != grid.height() as usize {
+            return Err(JsonGridError::Height {
+                expected: grid.height(),
+                found: rows.len(),
+            });
+        }
+
+        let mut storage = Storage::default();
+        for (y, row) in rows.iter().enumerate() {
+            let y = y as u32;
+            let cells = row
+                .as_array()
+                .ok_or(JsonGridError::NotAnArray { row: Some(y) })?;
+            if cells.len() != grid.width() as usize {
+                return Err(JsonGridError::Width {
+                    row: y,
+                    expected: grid.width(),
+                    found: cells.len(),
+                });
+            }
+
+            for (x, cell) in cells.iter().enumerate() {
+                let x = x as u32;
+                if cell.is_null() {
+                    continue;
+                }
+
+                let comp =
+                    C::deserialize(cell).map_err(|error| JsonGridError::Cell { x, y, error })?;
+                storage.insert(I::from_u32(y * grid.width() + x), comp);
+            }
+        }
+
+        Ok(storage)
+    }
+}
@@ -0,0 +1,147 @@
+//! Labeling connected regions of tiles.
+
+use specs::storage::UnprotectedStorage;
+use specs::{Component, VecStorage};
+
+use {Connectivity, Grid2d, Id, Index, Storage};
+
+/// The region a tile belongs to, assigned by `label_regions`.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct RegionLabel(pub u32);
+
+impl Component for RegionLabel {
+    type Storage = VecStorage<Self>;
+}
+
+/// Labels the regions of tiles matching `pred` which are connected with 4-connectivity.
+///
+/// See `label_regions_with` for the details.
+pub fn label_regions<C, D, I, F>(
+    grid: &Grid2d,
+    storage: &Storage<C, D, I>,
+    pred: F,
+) -> (Storage<RegionLabel, VecStorage<RegionLabel>, I>, u32)
+where
+    D: UnprotectedStorage<C>,
+    I: Id,
+    F: Fn(&C) -> bool,
+{
+    label_regions_with(grid, storage, Connectivity::Four, pred)
+}
+
+/// Assigns every connected region of tiles whose component in `storage` matches `pred` a
+/// distinct label, and returns the labels together with the amount of regions.
+///
+/// The labels are `0..count`, assigned in the order of the lowest id of every region, so
+/// the same input always results in the same labels. Tiles without a component don't match,
+/// and neither do ids outside the grid.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::{Component, Join, VecStorage};
+/// use specs_static::{label_regions, label_regions_with, Connectivity, Grid2d};
+/// use specs_static::{RegionLabel, Storage};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// #[derive(Clone, PartialEq)]
+/// enum Terrain {
+///     Land,
+///     Sea,
+/// }
+///
+/// impl Component for Terrain {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// fn main() {
+///     let map = [
+///         "##..#",
+///         "#...#",
+///         "..#..",
+///         ".#...",
+///     ];
+///     let grid = Grid2d::new(5, 4);
+///     let mut terrain = Storage::<Terrain, VecStorage<_>, TileId>::default();
+///     for (y, row) in map.iter().enumerate() {
+///         for (x, tile) in row.chars().enumerate() {
+///             let land = if tile == '#' { Terrain::Land } else { Terrain::Sea };
+///             terrain.insert(grid.id(x as u32, y as u32).unwrap(), land);
+///         }
+///     }
+///     let label = |labels: &Storage<RegionLabel, _, TileId>, x, y| {
+///         labels.get(grid.id(x, y).unwrap()).map(|label| label.0)
+///     };
+///
+///     let (labels, count) = label_regions(&grid, &terrain, |t| *t == Terrain::Land);
+///     assert_eq!(count, 4);
+///     assert_eq!(label(&labels, 0, 0), Some(0));
+///     assert_eq!(label(&labels, 0, 1), Some(0));
+///     assert_eq!(label(&labels, 4, 0), Some(1));
+///     assert_eq!(label(&labels, 2, 2), Some(2));
+///     assert_eq!(label(&labels, 1, 3), Some(3));
+///     assert_eq!(label(&labels, 2, 0), None);
+///
+///     // Diagonally adjacent islands are connected with 8-connectivity.
+///     let land = |t: &Terrain| *t == Terrain::Land;
+///     let (labels, count) = label_regions_with(&grid, &terrain, Connectivity::Eight, land);
+///     assert_eq!(count, 3);
+///     assert_eq!(label(&labels, 2, 2), label(&labels, 1, 3));
+///
+///     // The labels only depend on the input.
+///     let (again, _) = label_regions_with(&grid, &terrain, Connectivity::Eight, land);
+///     assert!((&labels, &again).join().all(|(a, b)| a == b));
+///
+///     // The sea is one region.
+///     let (_, count) = label_regions(&grid, &terrain, |t| *t == Terrain::Sea);
+///     assert_eq!(count, 1);
+///
+///     let (labels, count) = label_regions(&grid, &terrain, |_| true);
+///     assert_eq!((count, (&labels).join().count()), (1, 20));
+///     let (labels, count) = label_regions(&grid, &terrain, |_| false);
+///     assert_eq!((count, (&labels).join().count()), (0, 0));
+/// }
+/// ```
+pub fn label_regions_with<C, D, I, F>(
+    grid: &Grid2d,
+    storage: &Storage<C, D, I>,
+    connectivity: Connectivity,
+    pred: F,
+) -> (Storage<RegionLabel, VecStorage<RegionLabel>, I>, u32)
+where
+    D: UnprotectedStorage<C>,
+    I: Id,
+    F: Fn(&C) -> bool,
+{
+    let mut labels = Storage::default();
+    let mut count = 0;
+    let mut stack: Vec<Index> = Vec::new();
+
+    for seed in storage.mask() {
+        if seed >= grid.len() || labels.get(I::from_u32(seed)).is_some() {
+            continue;
+        }
+        if !storage.get(I::from_u32(seed)).is_some_and(&pred) {
+            continue;
+        }
+
+        let label = RegionLabel(count);
+        count += 1;
+        labels.insert(I::from_u32(seed), label);
+        stack.push(seed);
+
+        while let Some(id) = stack.pop() {
+            for next in grid.neighbors(I::from_u32(id), connectivity) {
+                if labels.get(next).is_none() && storage.get(next).is_some_and(&pred) {
+                    labels.insert(next, label);
+                    stack.push(next.id());
+                }
+            }
+        }
+    }
+
+    (labels, count)
+}
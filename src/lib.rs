@@ -8,22 +8,414 @@
 //! freely.
 //!
 
+#![cfg_attr(not(feature = "specs"), no_std)]
+
 #[macro_use]
 extern crate derivative;
+#[cfg(feature = "specs")]
 extern crate hibitset;
+#[cfg(feature = "image")]
+extern crate image;
+#[cfg(feature = "mmap")]
+extern crate memmap2;
+#[cfg(feature = "rand")]
+extern crate rand;
+#[cfg(feature = "parallel")]
+extern crate rayon;
+#[cfg(feature = "serde")]
+extern crate serde_crate as serde;
+#[cfg(any(feature = "serde", feature = "tiled"))]
+extern crate serde_json;
+#[cfg(feature = "specs")]
 extern crate shred;
+#[cfg(feature = "specs")]
 extern crate specs;
+#[cfg(feature = "specs")]
 extern crate shrev;
 
+// Without Specs the crate is `no_std`, and `std` paths resolve to `core`.
+#[cfg(not(feature = "specs"))]
+extern crate alloc as liballoc;
+#[cfg(not(feature = "specs"))]
+extern crate core as std;
+
+use std::convert::TryFrom;
 use std::hash::Hash;
+#[cfg(feature = "specs")]
+use std::hash::Hasher;
 use std::marker::PhantomData;
+use std::mem;
 
-use hibitset::BitSet;
-use specs::storage::{UnprotectedStorage, ComponentEvent};
+#[cfg(feature = "specs")]
+use specs::storage::{ComponentEvent, UnprotectedStorage};
+#[cfg(feature = "specs")]
+use specs::storage::DistinctStorage;
+#[cfg(feature = "parallel")]
+use specs::ParJoin;
+#[cfg(feature = "specs")]
 use specs::{Component, Join, World, Tracked};
+#[cfg(feature = "specs")]
 use shrev::EventChannel;
+#[cfg(feature = "serde")]
+use serde_json::Value;
+
+use epoch::Epoch;
+#[cfg(feature = "specs")]
+use join::JoinMut;
+#[cfg(feature = "specs")]
+use local::LocalTiles;
+use observe::Observers;
+use profile::Counters;
+use stamps::Stamps;
+
+#[cfg(feature = "specs")]
+pub use alloc::{StdAlloc, TileAlloc};
+#[cfg(feature = "specs")]
+pub use atomic::{AtomicCell, AtomicComponent, AtomicRepr, AtomicStorage};
+#[cfg(feature = "specs")]
+pub use autotile::{autotile_mask, Autotile, AutotileMask};
+pub use bitset::{BitSet, BitSetLike};
+pub use bound::IdOutOfBounds;
+#[cfg(feature = "specs")]
+pub use btree::{BTreeStorage, IterFrom};
+#[cfg(feature = "specs")]
+pub use builder::TileMapBuilder;
+#[cfg(feature = "specs")]
+pub use bundle::TileBundle;
+#[cfg(feature = "specs")]
+pub use cached_mask::{join_with_mask, CacheEpoch, CachedLayers, CachedMask, MaskedJoin};
+#[cfg(feature = "specs")]
+pub use chunk_dirty::{ChunkDirty, ChunkDirtySystem, DirtyChunks};
+#[cfg(feature = "specs")]
+pub use chunked::{ChunkedVecStorage, CHUNK_SIZE};
+#[cfg(feature = "specs")]
+pub use codec::TileCodec;
+#[cfg(feature = "specs")]
+pub use convolve::{convolve, EdgeMode, VacantTiles};
+#[cfg(feature = "specs")]
+pub use cow::{CowStorage, StorageSnapshot};
+#[cfg(feature = "specs")]
+pub use csv::{CsvError, FromCsvCell, ToCsvCell};
+#[cfg(feature = "specs")]
+pub use cursor::{TileCursor, TileCursorMut};
+#[cfg(feature = "specs")]
+pub use dense::DenseStorage;
+#[cfg(feature = "specs")]
+pub use dense_vec::{DenseAccess, DenseSliceStorage};
+#[cfg(feature = "specs")]
+pub use diagnostics::{LayerDiagnostics, LayerSkipped, LayerStats, TileDiagnostics};
+#[cfg(feature = "specs")]
+pub use double_buffered::DoubleBuffered;
+#[cfg(feature = "specs")]
+pub use dyn_query::{DynQuery, DynQueryError};
+#[cfg(feature = "specs")]
+pub use entity_map::TileEntityMap;
+pub use error::TileStorageError;
+#[cfg(feature = "specs")]
+pub use events::{TileComponentEvent, TileEventBridge};
+#[cfg(feature = "specs")]
+pub use fetch::{ReadTiles, WriteTiles};
+#[cfg(feature = "specs")]
+pub use fill::CopyFill;
+#[cfg(feature = "specs")]
+pub use frozen::FrozenStorage;
+#[cfg(feature = "specs")]
+pub use generic::{GenericInsertAccess, GenericReadAccess, GenericWriteAccess};
+pub use grid::{Connectivity, Grid2d, GridTooLarge, Neighbors, TileDisplay};
+pub use grid_id::{GridId, GridIds, OutsideGrid};
+#[cfg(feature = "specs")]
+pub use hash::StableHasher;
+#[cfg(feature = "serde")]
+pub use json::{JsonGridError, TileJsonError};
+#[cfg(feature = "specs")]
+pub use labeling::{label_regions, label_regions_with, RegionLabel};
+#[cfg(feature = "specs")]
+pub use local::{LocalStorage, ReadLocalTiles, WriteLocalTiles};
+#[cfg(feature = "specs")]
+pub use lod::{average_by, downsample, majority};
+#[cfg(feature = "specs")]
+pub use mapped::{mapped_join, MappedView};
+#[cfg(feature = "specs")]
+pub use maps::{CompositeId, MapRegistry, MapRegistryError};
+pub use mask::RangeMask;
+#[cfg(feature = "specs")]
+pub use mirror::TileToEntitySync;
+#[cfg(feature = "mmap")]
+pub use mmap::{MmapStorage, MmapStorageBuilder, Pod};
+#[cfg(feature = "specs")]
+pub use mutation_log::{
+    replay, Mutation, MutationLog, MutationOp, MutationRecorder, ReplayError,
+};
+pub use observe::{Observer, ObserverHandle};
+#[cfg(feature = "specs")]
+pub use packed::{PackedEnumStorage, PackedRepr};
+#[cfg(feature = "specs")]
+pub use paged::{PagedStorage, PAGE_SIZE};
+#[cfg(feature = "specs")]
+pub use palette::PaletteStorage;
+#[cfg(feature = "specs")]
+pub use parts::{CheckedParts, PartsError};
+#[cfg(feature = "pathfinding")]
+pub use pathfinding::{astar, flow_field, flow_field_into, AStar, FlowCell, BUCKET_LIMIT};
+#[cfg(feature = "specs")]
+pub use presize::{GridSized, ReserveIds};
+#[cfg(feature = "profile")]
+pub use profile::{OpCounters, ProfileReport};
+#[cfg(feature = "specs")]
+pub use proof::{Occupied, Proofs, ProofsMut};
+#[cfg(feature = "specs")]
+pub use query::{tile_system, TileSystem};
+pub use raw::{RawStorage, SlabStorage};
+#[cfg(feature = "specs")]
+pub use region::REGION_SIZE;
+#[cfg(feature = "specs")]
+pub use region_lock::{OutsideRegion, RegionGuard, RegionLocked};
+#[cfg(feature = "specs")]
+pub use registry::{
+    AnyTileStorage, CopyReport, LayerNameError, RemovedSummary, TileRegistry, TileStorageMut,
+    TileStorageRef,
+};
+#[cfg(feature = "specs")]
+pub use rle::{RleStorage, RunMask};
+#[cfg(feature = "specs")]
+pub use shared::{SharedTileStorage, TileShareSystem};
+#[cfg(feature = "specs")]
+pub use slice::{SliceAccess, SliceStorage};
+#[cfg(feature = "serde")]
+pub use snapshot::{SnapshotError, SnapshotLoad, TileSnapshot};
+#[cfg(feature = "specs")]
+pub use soa::{SoaColumn, SoaComponent, SoaStorage};
+#[cfg(feature = "specs")]
+pub use stamp::{stamp, Prefab, StampMode};
+#[cfg(feature = "specs")]
+pub use split::{ChunksMut, StorageChunkMut};
+#[cfg(feature = "specs")]
+pub use streaming::{
+    ChunkAccess, ChunkCoord, ChunkHandle, ChunkSlotsExhausted, ChunkStreamer, UnloadedChunk,
+};
+#[cfg(feature = "specs")]
+pub use sync::{
+    apply_delta, apply_delta_report, ApplyDeltaError, ApplyReport, LayerConflicts, LayerDelta,
+    TileDelta, TileSyncAccessor, TileSyncData, TileSyncSystem,
+};
+#[cfg(feature = "specs")]
+pub use tag::{AtomicTagStorage, TagStorage};
+pub use tagged::{IdSpace, TaggedId, Untagged};
+#[cfg(feature = "specs")]
+pub use tile_get::TileGet;
+#[cfg(feature = "specs")]
+pub use tile_ref::{tile_lookup, TileLookup, TileRef};
+#[cfg(feature = "tiled")]
+pub use tiled::{TiledError, TiledMap, TiledTile};
+#[cfg(feature = "specs")]
+pub use tombstone::TombstoneStorage;
+#[cfg(feature = "specs")]
+pub use transform::{mirror_region, rotate_region, Axis, RegionNotSquare, Rotation};
+#[cfg(feature = "specs")]
+pub use undo::{EditTransaction, UndoStack};
+#[cfg(feature = "specs")]
+pub use updates::TileUpdates;
+#[cfg(feature = "specs")]
+pub use versioned::{VersionedId, VersionedStorage};
+#[cfg(feature = "specs")]
+pub use view::{StorageView, StorageViewMut, ViewIds};
+#[cfg(feature = "specs")]
+pub use watch::RegionWatcher;
+#[cfg(feature = "specs")]
+pub use with_ids::WithIds;
+#[cfg(feature = "specs")]
+pub use zip::{zip, zip3, zip3_mut, zip_mut};
+
+#[cfg(feature = "specs")]
+mod alloc;
+#[cfg(feature = "specs")]
+mod atomic;
+#[cfg(feature = "specs")]
+mod autotile;
+#[cfg(feature = "specs")]
+mod batch;
+mod bitset;
+mod bound;
+#[cfg(feature = "specs")]
+mod btree;
+#[cfg(feature = "specs")]
+mod builder;
+#[cfg(feature = "specs")]
+mod bundle;
+#[cfg(feature = "specs")]
+mod cached_mask;
+#[cfg(feature = "specs")]
+mod chunk_dirty;
+#[cfg(feature = "specs")]
+mod chunked;
+#[cfg(feature = "specs")]
+mod codec;
+#[cfg(feature = "specs")]
+mod convert;
+#[cfg(feature = "specs")]
+mod convolve;
+#[cfg(feature = "specs")]
+mod cow;
+#[cfg(feature = "specs")]
+mod csv;
+#[cfg(feature = "specs")]
+mod cursor;
+#[cfg(feature = "specs")]
+mod dense;
+#[cfg(feature = "specs")]
+mod dense_vec;
+#[cfg(feature = "specs")]
+mod diagnostics;
+#[cfg(feature = "specs")]
+mod double_buffered;
+#[cfg(feature = "specs")]
+mod dyn_query;
+#[cfg(feature = "specs")]
+mod entity_map;
+mod epoch;
+mod error;
+#[cfg(feature = "specs")]
+mod events;
+#[cfg(feature = "specs")]
+mod export;
+#[cfg(feature = "specs")]
+mod fetch;
+#[cfg(feature = "specs")]
+mod fill;
+#[cfg(feature = "specs")]
+mod frozen;
+#[cfg(feature = "specs")]
+mod generate;
+#[cfg(feature = "specs")]
+mod generic;
+mod grid;
+mod grid_id;
+#[cfg(feature = "specs")]
+mod hash;
+#[cfg(feature = "image")]
+mod heightmap;
+#[cfg(feature = "serde")]
+mod json;
+#[cfg(feature = "specs")]
+mod join;
+#[cfg(feature = "specs")]
+mod labeling;
+#[cfg(feature = "specs")]
+mod local;
+#[cfg(feature = "specs")]
+mod lod;
+mod macros;
+#[cfg(feature = "specs")]
+mod mapped;
+#[cfg(feature = "specs")]
+mod maps;
+mod mask;
+#[cfg(feature = "specs")]
+mod mirror;
+#[cfg(feature = "mmap")]
+pub mod mmap;
+#[cfg(feature = "specs")]
+mod mutation_log;
+#[cfg(feature = "specs")]
+mod neighborhood;
+mod observe;
+#[cfg(feature = "specs")]
+mod ordering;
+#[cfg(feature = "specs")]
+mod packed;
+#[cfg(feature = "specs")]
+mod paged;
+#[cfg(feature = "specs")]
+mod palette;
+#[cfg(feature = "parallel")]
+mod parallel;
+#[cfg(feature = "specs")]
+mod parts;
+#[cfg(feature = "pathfinding")]
+mod pathfinding;
+#[cfg(feature = "specs")]
+pub mod prelude;
+#[cfg(feature = "specs")]
+mod presize;
+mod profile;
+#[cfg(feature = "specs")]
+mod proof;
+#[cfg(feature = "specs")]
+pub mod query;
+mod raw;
+#[cfg(feature = "specs")]
+mod region;
+#[cfg(feature = "specs")]
+mod region_lock;
+#[cfg(feature = "specs")]
+mod registry;
+#[cfg(feature = "specs")]
+mod rle;
+#[cfg(feature = "rand")]
+mod sample;
+#[cfg(feature = "specs")]
+mod select;
+#[cfg(feature = "specs")]
+mod shared;
+#[cfg(feature = "specs")]
+mod slice;
+#[cfg(feature = "serde")]
+mod snapshot;
+#[cfg(feature = "specs")]
+mod soa;
+#[cfg(feature = "specs")]
+mod split;
+#[cfg(feature = "specs")]
+mod stamp;
+mod stamps;
+#[cfg(feature = "specs")]
+mod streaming;
+mod strict;
+#[cfg(feature = "specs")]
+mod sync;
+#[cfg(feature = "specs")]
+mod tag;
+mod tagged;
+#[cfg(feature = "specs")]
+pub mod testing;
+#[cfg(feature = "specs")]
+mod tile_get;
+#[cfg(feature = "specs")]
+mod tile_ref;
+#[cfg(feature = "tiled")]
+mod tiled;
+#[cfg(feature = "specs")]
+mod tombstone;
+#[cfg(feature = "specs")]
+mod transform;
+#[cfg(feature = "specs")]
+mod undo;
+#[cfg(feature = "specs")]
+mod updates;
+#[cfg(feature = "specs")]
+mod versioned;
+#[cfg(feature = "specs")]
+mod view;
+#[cfg(feature = "specs")]
+mod watch;
+#[cfg(feature = "specs")]
+mod with_ids;
+#[cfg(feature = "specs")]
+mod zip;
+
 type Index = u32;
 
+#[doc(hidden)]
+pub mod __private {
+    pub use std::convert::TryFrom;
+    pub use std::fmt;
+
+    #[cfg(feature = "serde")]
+    pub use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    #[cfg(feature = "specs")]
+    pub use specs::{Component, World};
+}
+
 /// The ids component storages are indexed with. This is mostly just a newtype wrapper with a `u32`
 /// in it.
 ///
@@ -58,38 +450,298 @@ pub trait Id: Copy + Eq + Hash + Ord + Send + Sync + Sized + 'static {
 ///
 /// # Registering
 ///
-/// These component storages also have to be registered. This can be done using the `WorldExt`
+/// These component storages also have to be registered. This can be done using the `TileWorldExt`
 /// trait and its `register_tile_comp` method.
+///
+/// # Parallel joins
+///
+/// With the `parallel` feature (enabled by default), `&Storage` and `&mut Storage` implement
+/// `ParJoin`, so they can be used with `par_join` just like normal component storages.
+/// Mutable parallel joins require the backend to implement `DistinctStorage`.
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::prelude::*;
+/// use specs_static::{Storage, TileWorldExt};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// struct Heat(u32);
+///
+/// impl Component for Heat {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// type Heats<'a> = Write<'a, Storage<Heat, VecStorage<Heat>, TileId>>;
+///
+/// struct Diffuse;
+///
+/// impl<'a> System<'a> for Diffuse {
+///     type SystemData = Heats<'a>;
+///
+///     fn run(&mut self, mut heats: Self::SystemData) {
+///         (&mut *heats).par_join().for_each(|heat| heat.0 = heat.0 * 3 + 1);
+///     }
+/// }
+///
+/// fn main() {
+///     let mut world = World::new();
+///     world.register_tile_comp::<Heat, TileId>();
+///
+///     let mut expected = Vec::new();
+///     {
+///         let mut heats = world.write_resource::<Storage<Heat, VecStorage<Heat>, TileId>>();
+///         for i in 0..256 * 256 {
+///             if i % 3 != 0 {
+///                 heats.insert(TileId(i), Heat(i));
+///                 expected.push(i * 3 + 1);
+///             }
+///         }
+///     }
+///
+///     let mut dispatcher = DispatcherBuilder::new().with(Diffuse, "diffuse", &[]).build();
+///     dispatcher.dispatch(&world);
+///
+///     let heats = world.read_resource::<Storage<Heat, VecStorage<Heat>, TileId>>();
+///     let actual: Vec<u32> = (&*heats).join().map(|heat| heat.0).collect();
+///     assert_eq!(actual, expected);
+/// }
+/// ```
+///
+/// # Optional components
+///
+/// Like any `Join`, `&Storage` and `&mut Storage` have `Join::maybe`, whose mask doesn't
+/// constrain the join and which yields `Option<&C>` or `Option<&mut C>`. So a dense layer can
+/// be walked once while reading a sparse layer where it's present, also together with
+/// normal component storages, whose entity indices are matched with the tile ids.
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::prelude::*;
+/// use specs_static::Storage;
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// struct Soil(u32);
+///
+/// impl Component for Soil {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// struct Crop(u32);
+///
+/// impl Component for Crop {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// struct Marker;
+///
+/// impl Component for Marker {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// fn main() {
+///     let mut soil = Storage::<Soil, VecStorage<_>, TileId>::default();
+///     let mut crops = Storage::<Crop, VecStorage<_>, TileId>::default();
+///     for id in 0..100 {
+///         soil.insert(TileId(id), Soil(id));
+///         if id % 10 == 0 {
+///             crops.insert(TileId(id), Crop(0));
+///         }
+///     }
+///
+///     let (mut some, mut none) = (0, 0);
+///     for (_, crop) in (&soil, (&crops).maybe()).join() {
+///         match crop {
+///             Some(_) => some += 1,
+///             None => none += 1,
+///         }
+///     }
+///     assert_eq!((some, none), (10, 90));
+///
+///     for (soil, crop) in (&soil, (&mut crops).maybe()).join() {
+///         if let Some(crop) = crop {
+///             crop.0 = soil.0;
+///         }
+///     }
+///     assert_eq!(crops.get(TileId(30)).map(|crop| crop.0), Some(30));
+///
+///     // Together with a normal component storage.
+///     let mut world = World::new();
+///     world.register::<Marker>();
+///     for i in 0..20 {
+///         let mut builder = world.create_entity();
+///         if i % 4 == 0 {
+///             builder = builder.with(Marker);
+///         }
+///         builder.build();
+///     }
+///     let markers = world.read_storage::<Marker>();
+///     let marked: Vec<_> = (&markers, &soil, (&crops).maybe())
+///         .join()
+///         .map(|(_, soil, crop)| (soil.0, crop.is_some()))
+///         .collect();
+///     assert_eq!(marked, vec![(0, true), (4, false), (8, false), (12, false), (16, false)]);
+/// }
+/// ```
+///
+/// # Coordinates
+///
+/// `get`, `get_mut`, `insert`, `remove` and `contains` only take an `I`. Ids like `GridId` can
+/// also be created from their coordinates, which goes through the `_at` variants like
+/// `get_at((3, 4))` instead. Making the plain accessors generic over `Into<I>` would break
+/// inferring calls like `get(Id::from_u32(n))`, and `Into` can't fail, so coordinates outside
+/// the grid would have to panic or wrap into the next row. The `_at` variants use `TryFrom`
+/// and return `None` or the conversion error for them.
+///
+/// # Panic safety
+///
+/// Components are only marked as occupied after they have been written to the backend, and
+/// unmarked before they are dropped. If a backend, a `Clone` impl or a closure passed to a
+/// bulk operation panics, the storage stays consistent: every component it claims to have
+/// can be accessed and will be dropped exactly once.
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use std::panic::{self, AssertUnwindSafe};
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// use specs::{Component, Join, VecStorage};
+/// use specs_static::{RangeMask, Storage};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// static LIVE: AtomicUsize = AtomicUsize::new(0);
+/// static CLONES_LEFT: AtomicUsize = AtomicUsize::new(0);
+///
+/// struct Fragile(u32);
+///
+/// impl Fragile {
+///     fn new(value: u32) -> Self {
+///         LIVE.fetch_add(1, Ordering::SeqCst);
+///         Fragile(value)
+///     }
+/// }
+///
+/// impl Clone for Fragile {
+///     fn clone(&self) -> Self {
+///         if CLONES_LEFT.fetch_sub(1, Ordering::SeqCst) == 0 {
+///             panic!("out of clones");
+///         }
+///         Fragile::new(self.0)
+///     }
+/// }
+///
+/// impl Drop for Fragile {
+///     fn drop(&mut self) {
+///         LIVE.fetch_sub(1, Ordering::SeqCst);
+///     }
+/// }
+///
+/// impl Component for Fragile {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// fn main() {
+///     {
+///         let mut storage = Storage::<Fragile, VecStorage<_>, TileId>::default();
+///
+///         CLONES_LEFT.store(50, Ordering::SeqCst);
+///         let result = panic::catch_unwind(AssertUnwindSafe(|| {
+///             storage.fill_range(TileId(0), TileId(100), Fragile::new(1));
+///         }));
+///         assert!(result.is_err());
+///         assert_eq!((&storage).join().count(), 50);
+///
+///         let result = panic::catch_unwind(AssertUnwindSafe(|| {
+///             storage.insert_mask_with(RangeMask::from_range(100, 200), |id| match id.0 {
+///                 150 => panic!("cannot generate"),
+///                 id => Fragile::new(id),
+///             });
+///         }));
+///         assert!(result.is_err());
+///         assert_eq!((&storage).join().count(), 100);
+///         assert_eq!(storage.get(TileId(149)).map(|f| f.0), Some(149));
+///         assert!(storage.get(TileId(150)).is_none());
+///         assert_eq!(LIVE.load(Ordering::SeqCst), 100);
+///     }
+///     assert_eq!(LIVE.load(Ordering::SeqCst), 0);
+/// }
+/// ```
 #[derive(Derivative)]
 #[derivative(Default(bound = "D: Default"))]
-pub struct Storage<C, D: UnprotectedStorage<C>, I> {
+pub struct Storage<C, D: RawStorage<C>, I> {
     data: D,
     bitset: BitSet,
+    epoch: Epoch,
+    stamps: Stamps,
+    clear_cursor: Option<Index>,
+    bound: Option<Index>,
+    #[cfg(feature = "strict-ids")]
+    debug_bound: Option<Index>,
+    counters: Counters,
+    observers: Observers<C, I>,
     phantom: PhantomData<(C, I)>,
 }
 
 impl<C, D, I> Storage<C, D, I>
 where
-    C: Component,
-    D: UnprotectedStorage<C>,
+    D: RawStorage<C>,
     I: Id,
 {
+    /// Returns the backend storing the components.
+    pub fn unprotected_storage(&self) -> &D {
+        &self.data
+    }
+
+    /// Returns the backend storing the components mutably.
+    ///
+    /// # Safety
+    ///
+    /// The caller must not insert or remove components through the backend, since
+    /// that would make it disagree with the occupancy bitset.
+    pub unsafe fn unprotected_storage_mut(&mut self) -> &mut D {
+        &mut self.data
+    }
+
+    /// Returns the mask of all occupied ids.
+    pub fn mask(&self) -> &BitSet {
+        &self.bitset
+    }
+
     /// Tries to retrieve a component by its `Id`.
     /// This will only check whether a component is inserted or not, without doing
     /// any liveness checks for the id.
     pub fn get(&self, id: I) -> Option<&C> {
+        self.debug_check_id(id.id(), None);
+        self.counters.get();
+
         match self.bitset.contains(id.id()) {
             true => unsafe { Some(self.data.get(id.id())) },
             false => None,
         }
     }
 
+    /// Returns `true` if there is a component at `id`.
+    pub fn contains(&self, id: I) -> bool {
+        self.bitset.contains(id.id())
+    }
+
     /// Tries to retrieve a component mutably by its `Id`.
     /// This will only check whether a component is inserted or not, without doing
     /// any liveness checks for the id.
     pub fn get_mut(&mut self, id: I) -> Option<&mut C> {
+        self.debug_check_id(id.id(), None);
+        self.counters.get_mut();
+
         match self.bitset.contains(id.id()) {
-            true => unsafe { Some(self.data.get_mut(id.id())) },
+            true => {
+                self.stamps.stamp(id.id());
+                unsafe { Some(self.data.get_mut(id.id())) }
+            }
             false => None,
         }
     }
@@ -97,40 +749,207 @@ where
     /// Inserts `comp` at `id`. If there already was a value, it will be returned.
     ///
     /// In contrast to entities, **there are no invalid ids.**
+    ///
+    /// Overwriting an existing component swaps it in place, so tracked backends will
+    /// report a modification rather than a removal followed by an insertion.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the storage has been created with `with_bound` and `id` exceeds the bound,
+    /// or if `id` exceeds `Storage::MAX_ID`. Use `try_insert` to handle that case.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate specs;
+    /// # extern crate specs_static;
+    /// # use specs::{Component, VecStorage};
+    /// # use specs_static::Storage;
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// #[derive(Debug, PartialEq)]
+    /// struct Material(&'static str);
+    ///
+    /// impl Component for Material {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// # fn main() {
+    /// let mut materials = Storage::<Material, VecStorage<_>, TileId>::default();
+    ///
+    /// assert_eq!(materials.insert(TileId(3), Material("dirt")), None);
+    /// assert_eq!(materials.insert(TileId(3), Material("grass")), Some(Material("dirt")));
+    /// assert_eq!(materials.get(TileId(3)), Some(&Material("grass")));
+    /// # }
+    /// ```
     pub fn insert(&mut self, id: I, comp: C) -> Option<C> {
-        let old = match self.bitset.add(id.id()) {
-            true => unsafe { Some(self.data.remove(id.id())) },
-            false => None,
-        };
+        let id = id.id();
+        bound::expect_in_bounds(self.check_bound(id));
+        self.debug_check_id(id, None);
+        self.counters.insert();
+        self.stamps.stamp(id);
+
+        if self.bitset.contains(id) {
+            let old = mem::replace(unsafe { self.data.get_mut(id) }, comp);
+            if self.observers.has_insert() {
+                self.observers.inserted(id, unsafe { self.data.get(id) });
+            }
+
+            return Some(old);
+        }
 
         unsafe {
-            self.data.insert(id.id(), comp);
+            self.data.insert(id, comp);
+        }
+        self.bitset.add(id);
+        self.epoch.bump();
+        if self.observers.has_insert() {
+            self.observers.inserted(id, unsafe { self.data.get(id) });
         }
 
-        old
+        None
     }
 
     /// Removes the component at `id`.
     pub fn remove(&mut self, id: I) -> Option<C> {
+        self.debug_check_id(id.id(), None);
+        self.counters.remove();
+
         match self.bitset.remove(id.id()) {
-            true => unsafe { Some(self.data.remove(id.id())) },
+            true => {
+                self.epoch.bump();
+                self.stamps.stamp(id.id());
+                let comp = unsafe { self.data.remove(id.id()) };
+                self.observers.removed(id.id(), &comp);
+
+                Some(comp)
+            }
             false => None,
         }
     }
+
+    /// Like `get`, but takes anything `I` can be converted from with `TryFrom`, like the
+    /// coordinates of a `GridId`. Returns `None` if the conversion fails, like for
+    /// coordinates outside the grid.
+    ///
+    /// `get` itself only takes an `I`, so arguments like `Id::from_u32(n)` are inferred to
+    /// be one; passing an `I` here works as well.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, VecStorage};
+    /// use specs_static::{GridId, Id, Storage};
+    ///
+    /// type Cell = GridId<16, 9>;
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// enum Material {
+    ///     Stone,
+    ///     Water,
+    /// }
+    ///
+    /// impl Component for Material {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut materials = Storage::<Material, VecStorage<_>, Cell>::default();
+    ///     assert_eq!(materials.insert_at((3, 4), Material::Stone), Ok(None));
+    ///     assert_eq!(materials.get_at((3, 4)), Some(&Material::Stone));
+    ///     assert!(materials.contains_at((3, 4)));
+    ///     assert_eq!(materials.get(Cell::new(3, 4).unwrap()), Some(&Material::Stone));
+    ///     assert_eq!(materials.get(Id::from_u32(67)), Some(&Material::Stone));
+    ///
+    ///     *materials.get_mut_at((3, 4)).unwrap() = Material::Water;
+    ///     assert_eq!(materials.remove_at((3, 4)), Some(Material::Water));
+    ///     assert_eq!(materials.get_at((15, 8)), None);
+    ///
+    ///     // Coordinates outside the grid are rejected instead of wrapping into the next row.
+    ///     assert_eq!(materials.get_at((16, 0)), None);
+    ///     assert!(!materials.contains_at((0, 9)));
+    ///     assert!(materials.insert_at((16, 0), Material::Stone).is_err());
+    ///     assert_eq!(materials.get(Cell::from_u32(16)), None);
+    /// }
+    /// ```
+    pub fn get_at<A>(&self, at: A) -> Option<&C>
+    where
+        I: TryFrom<A>,
+    {
+        self.get(I::try_from(at).ok()?)
+    }
+
+    /// Like `contains`, but takes anything `get_at` takes. Returns `false` if the conversion
+    /// fails.
+    pub fn contains_at<A>(&self, at: A) -> bool
+    where
+        I: TryFrom<A>,
+    {
+        I::try_from(at).is_ok_and(|id| self.contains(id))
+    }
+
+    /// Like `get_mut`, but takes anything `get_at` takes. Returns `None` if the conversion
+    /// fails.
+    pub fn get_mut_at<A>(&mut self, at: A) -> Option<&mut C>
+    where
+        I: TryFrom<A>,
+    {
+        self.get_mut(I::try_from(at).ok()?)
+    }
+
+    /// Like `insert`, but takes anything `get_at` takes. Returns the error of the conversion
+    /// without inserting anything if it fails.
+    pub fn insert_at<A>(&mut self, at: A, comp: C) -> Result<Option<C>, I::Error>
+    where
+        I: TryFrom<A>,
+    {
+        Ok(self.insert(I::try_from(at)?, comp))
+    }
+
+    /// Like `remove`, but takes anything `get_at` takes. Returns `None` if the conversion
+    /// fails.
+    pub fn remove_at<A>(&mut self, at: A) -> Option<C>
+    where
+        I: TryFrom<A>,
+    {
+        self.remove(I::try_from(at).ok()?)
+    }
+
+    /// Removes all components.
+    pub fn clear(&mut self) {
+        // Taking the bitset first means a panicking `Drop` of a component can't cause the
+        // components which have already been dropped to be dropped again.
+        let bitset = mem::replace(&mut self.bitset, BitSet::new());
+        self.epoch.bump();
+        self.stamps.stamp_all(&bitset);
+        self.clear_cursor = None;
+        self.notify_removed(&bitset);
+        unsafe {
+            self.data.clean(&bitset);
+        }
+    }
 }
 
+#[cfg(feature = "specs")]
 impl<C, D, I> Tracked for Storage<C, D, I>
-    where D: Tracked + UnprotectedStorage<C>,
-          C: Component
+    where D: Tracked + UnprotectedStorage<C>
 {
     fn channel(&self) -> &EventChannel<ComponentEvent> { self.data.channel() }
 
     fn channel_mut(&mut self) -> &mut EventChannel<ComponentEvent> { self.data.channel_mut() }
+
+    #[cfg(feature = "storage-event-control")]
+    fn set_event_emission(&mut self, emit: bool) { self.data.set_event_emission(emit) }
+
+    #[cfg(feature = "storage-event-control")]
+    fn event_emission(&self) -> bool { self.data.event_emission() }
 }
 
 impl<C, D, I> Drop for Storage<C, D, I>
 where
-    D: UnprotectedStorage<C>,
+    D: RawStorage<C>,
 {
     fn drop(&mut self) {
         unsafe {
@@ -139,63 +958,1749 @@ where
     }
 }
 
+#[cfg(feature = "specs")]
 impl<'a, C, D, I> Join for &'a Storage<C, D, I>
 where
     D: UnprotectedStorage<C>,
 {
     type Type = &'a C;
+    #[cfg(not(feature = "profile"))]
     type Value = &'a D;
+    #[cfg(feature = "profile")]
+    type Value = (&'a D, &'a Counters);
     type Mask = &'a BitSet;
 
+    #[cfg(not(feature = "profile"))]
     unsafe fn open(self) -> (Self::Mask, Self::Value) {
         (&self.bitset, &self.data)
     }
 
+    #[cfg(feature = "profile")]
+    unsafe fn open(self) -> (Self::Mask, Self::Value) {
+        (&self.bitset, (&self.data, &self.counters))
+    }
+
+    #[cfg(not(feature = "profile"))]
     unsafe fn get(value: &mut Self::Value, id: Index) -> Self::Type {
         (*value).get(id)
     }
+
+    #[cfg(feature = "profile")]
+    unsafe fn get(value: &mut Self::Value, id: Index) -> Self::Type {
+        value.1.join();
+        value.0.get(id)
+    }
 }
 
+/// Every id is visited at most once, so the components handed out can all be held at the same
+/// time.
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::{Component, Join, VecStorage};
+/// use specs_static::{DenseStorage, SliceStorage, Storage};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// #[derive(Debug, PartialEq)]
+/// struct Water(u32);
+///
+/// impl Component for Water {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Sand(u32);
+///
+/// impl Component for Sand {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// fn main() {
+///     let mut water = Storage::<Water, SliceStorage<_>, TileId>::default();
+///     let mut sand = Storage::<Sand, SliceStorage<_>, TileId>::default();
+///     let mut depth = DenseStorage::<u32, TileId>::filled(40, 0);
+///     for i in 0..40 {
+///         water.insert(TileId(i), Water(i));
+///         if i % 2 == 0 {
+///             sand.insert(TileId(i), Sand(i));
+///         }
+///     }
+///
+///     let joined: Vec<_> = (&mut water, &mut sand, &mut depth).join().collect();
+///     for (water, sand, depth) in joined {
+///         water.0 += 1;
+///         sand.0 += 2;
+///         *depth = water.0 + sand.0;
+///     }
+///
+///     assert_eq!(water.get(TileId(4)), Some(&Water(5)));
+///     assert_eq!(water.get(TileId(5)), Some(&Water(5)));
+///     assert_eq!(sand.get(TileId(4)), Some(&Sand(6)));
+///     assert_eq!(depth.get(TileId(4)), &11);
+///     assert_eq!(depth.get(TileId(5)), &0);
+/// }
+/// ```
+#[cfg(feature = "specs")]
 impl<'a, C, D, I> Join for &'a mut Storage<C, D, I>
 where
     D: UnprotectedStorage<C>,
 {
     type Type = &'a mut C;
-    type Value = &'a mut D;
+    #[cfg(not(feature = "profile"))]
+    type Value = JoinMut<'a, D>;
+    #[cfg(feature = "profile")]
+    type Value = (JoinMut<'a, D>, &'a Counters);
     type Mask = &'a BitSet;
 
+    #[cfg(not(feature = "profile"))]
+    unsafe fn open(self) -> (Self::Mask, Self::Value) {
+        (&self.bitset, JoinMut::new(&mut self.data))
+    }
+
+    #[cfg(feature = "profile")]
     unsafe fn open(self) -> (Self::Mask, Self::Value) {
-        (&self.bitset, &mut self.data)
+        (&self.bitset, (JoinMut::new(&mut self.data), &self.counters))
     }
 
+    #[cfg(not(feature = "profile"))]
     unsafe fn get(value: &mut Self::Value, id: Index) -> Self::Type {
-        // This is horribly unsafe. Unfortunately, Rust doesn't provide a way
-        // to abstract mutable/immutable state at the moment, so we have to hack
-        // our way through it.
-        let value: *mut Self::Value = value as *mut Self::Value;
-        (*value).get_mut(id)
+        value.get().get_mut(id)
     }
+
+    #[cfg(feature = "profile")]
+    unsafe fn get(value: &mut Self::Value, id: Index) -> Self::Type {
+        value.1.join();
+        value.0.get().get_mut(id)
+    }
+}
+
+/// A `Storage` hands out the components of distinct ids as non-aliasing references exactly
+/// if its backend does, so the mutable parallel joins and iterators (`par_join` on
+/// `&mut Storage`, `par_iter_mut` and `par_for_each_chunked`) accept it as a backend as well.
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::storage::{DistinctStorage, HashMapStorage};
+/// use specs::{Component, VecStorage};
+/// use specs_static::Storage;
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// struct Sign(&'static str);
+///
+/// impl Component for Sign {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// fn assert_distinct<D: DistinctStorage>() {}
+///
+/// fn main() {
+///     // specs marks `HashMapStorage` as distinct.
+///     assert_distinct::<Storage<Sign, HashMapStorage<Sign>, TileId>>();
+///     assert_distinct::<Storage<Sign, VecStorage<Sign>, TileId>>();
+/// }
+/// ```
+///
+/// Backends which aren't distinct, like `CowStorage`, whose `get_mut` can copy a chunk which
+/// is shared with a snapshot, are rejected:
+///
+/// ```compile_fail
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::storage::DistinctStorage;
+/// use specs::{Component, VecStorage};
+/// use specs_static::{CowStorage, Storage};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// #[derive(Clone)]
+/// struct Sign(&'static str);
+///
+/// impl Component for Sign {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// fn assert_distinct<D: DistinctStorage>() {}
+///
+/// fn main() {
+///     assert_distinct::<Storage<Sign, CowStorage<Sign>, TileId>>();
+/// }
+/// ```
+// SAFETY: `Storage` only ever accesses the backend at the id it has been asked for, and
+// keeps no state of its own which is touched by `get_mut`. Mutable joins hand out
+// components through `JoinMut`, calling `D::get_mut` only with the distinct ids of the
+// bitset, which is the access `DistinctStorage` allows from several threads at once. So two
+// ids alias in the storage only if they alias in the backend, which `D: DistinctStorage`
+// rules out.
+#[cfg(feature = "specs")]
+unsafe impl<C, D, I> DistinctStorage for Storage<C, D, I>
+where
+    D: UnprotectedStorage<C> + DistinctStorage,
+{
+}
+
+#[cfg(feature = "parallel")]
+unsafe impl<C, D, I> ParJoin for &Storage<C, D, I>
+where
+    D: UnprotectedStorage<C> + Sync,
+{
+}
+
+#[cfg(feature = "parallel")]
+unsafe impl<C, D, I> ParJoin for &mut Storage<C, D, I>
+where
+    D: UnprotectedStorage<C> + Sync + DistinctStorage,
+{
 }
 
 /// An extension trait for registering statically managed component storages.
-pub trait WorldExt {
+#[cfg(feature = "specs")]
+pub trait TileWorldExt {
     /// Registers a `specs_static::Storage` for the components of type `C`.
     /// This will be done automatically if your storage has a `Default` and you're fetching it with
     /// `Read` / `Write`.
+    ///
+    /// If there is a `Grid2d` resource, the storage gets room for all of its tiles up front,
+    /// like one fetched with the `GridSized` setup handler.
+    ///
+    /// The storage is also added to the `specs_static::TileRegistry<I>` resource, so it shows up
+    /// in `tile_storages`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, World, WorldExt};
+    /// use specs_static::{Grid2d, SliceStorage, Storage, TileWorldExt};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// struct Snow(u8);
+    ///
+    /// impl Component for Snow {
+    ///     type Storage = SliceStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut world = World::new();
+    ///     world.insert(Grid2d::new(100, 80));
+    ///     world.register_tile_comp::<Snow, TileId>();
+    ///
+    ///     let snow = world.read_resource::<Storage<Snow, SliceStorage<Snow>, TileId>>();
+    ///     assert!(snow.unprotected_storage().capacity() >= 100 * 80);
+    /// }
+    /// ```
     fn register_tile_comp<C, I>(&mut self)
     where
         C: Component + Send + Sync,
-        C::Storage: Default,
+        C::Storage: Default + ReserveIds + Send + Sync,
         I: Id;
-}
 
-impl WorldExt for World {
-    fn register_tile_comp<C, I>(&mut self)
+    /// Registers a storage with the backend `D` for the components of type `C`, like
+    /// `register_tile_comp` does with `C::Storage`.
+    ///
+    /// This works for any `C`, not only specs components, so types which only ever live on
+    /// tiles, like the intermediate layers of a world generator, don't need a `Component` impl
+    /// and an entity storage they never use. Everything else about the storage works the same
+    /// for them, except the `World` conveniences which look the backend up as `C::Storage`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Join, VecStorage, World, WorldExt};
+    /// use specs_static::{Storage, TileWorldExt};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// // Not a `Component`.
+    /// struct Moisture(f32);
+    ///
+    /// type MoistureLayer = Storage<Moisture, VecStorage<Moisture>, TileId>;
+    ///
+    /// fn main() {
+    ///     let mut world = World::new();
+    ///     world.register_tile_comp_as::<Moisture, VecStorage<_>, TileId>();
+    ///
+    ///     {
+    ///         let mut moisture = world.write_resource::<MoistureLayer>();
+    ///         for id in 0..10 {
+    ///             moisture.insert(TileId(id), Moisture(id as f32 / 10.0));
+    ///         }
+    ///         moisture.remove(TileId(0));
+    ///     }
+    ///
+    ///     let moisture = world.read_resource::<MoistureLayer>();
+    ///     let total: f32 = moisture.join().map(|moisture| moisture.0).sum();
+    ///     assert!((total - 4.5).abs() < 1e-5);
+    ///     assert_eq!(world.tile_storages::<TileId>()[0].len(), 9);
+    /// }
+    /// ```
+    fn register_tile_comp_as<C, D, I>(&mut self)
+    where
+        C: Send + Sync + 'static,
+        D: UnprotectedStorage<C> + Default + ReserveIds + Send + Sync + 'static,
+        I: Id;
+
+    /// Registers a storage for the components of type `C` like `register_tile_comp`, with
+    /// room for the ids below `len` instead of the tiles of the `Grid2d` resource.
+    ///
+    /// This serves maps which are filled before there is a grid, or which only use a part of
+    /// it. The bitset and, through `ReserveIds`, the backend are sized up front, so filling
+    /// the ids below `len` doesn't reallocate. `len` is only a hint: ids beyond it can still
+    /// be inserted, they just grow the storage like usual.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, World, WorldExt};
+    /// use specs_static::{SliceStorage, Storage, TileWorldExt};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// struct Rain(u8);
+    ///
+    /// impl Component for Rain {
+    ///     type Storage = SliceStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut world = World::new();
+    ///     world.register_tile_comp_with_capacity::<Rain, TileId>(256 * 128);
+    ///
+    ///     let mut rain = world.write_resource::<Storage<Rain, SliceStorage<Rain>, TileId>>();
+    ///     let capacity = rain.unprotected_storage().capacity();
+    ///     assert!(capacity >= 256 * 128);
+    ///     for id in 0..256 * 128 {
+    ///         rain.insert(TileId(id), Rain(1));
+    ///     }
+    ///     assert_eq!(rain.unprotected_storage().capacity(), capacity);
+    ///
+    ///     // Beyond the hint, the storage grows.
+    ///     rain.insert(TileId(100_000), Rain(2));
+    ///     assert_eq!(rain.get(TileId(100_000)).map(|rain| rain.0), Some(2));
+    ///     assert!(rain.unprotected_storage().capacity() > 100_000);
+    /// }
+    /// ```
+    fn register_tile_comp_with_capacity<C, I>(&mut self, len: u32)
     where
         C: Component + Send + Sync,
-        C::Storage: Default,
-        I: Id,
-    {
-        self.add_resource(Storage::<C, C::Storage, I>::default());
-    }
+        C::Storage: Default + ReserveIds + Send + Sync,
+        I: Id;
+
+    /// Registers a storage for the components of type `C` like `register_tile_comp`, with a
+    /// clone of `value` at every tile of the `Grid2d` resource.
+    ///
+    /// This is the usual setup of a dense layer, like the terrain of a map, in one call. The
+    /// storage is sized for the grid first, so filling it doesn't reallocate.
+    ///
+    /// Stable Rust can't pick a faster path for `Copy` components behind this signature, so
+    /// the tiles are filled with `Storage::fill_range`. For large `Copy` layers with a
+    /// `CopyFill` backend, registering the storage and calling `fill_range_copy` is faster.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no `Grid2d` resource, instead of registering an empty layer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, Join, World, WorldExt};
+    /// use specs_static::{Grid2d, SliceStorage, Storage, TileWorldExt};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// #[derive(Clone, Debug, PartialEq)]
+    /// enum Terrain {
+    ///     Grass,
+    ///     Water,
+    /// }
+    ///
+    /// impl Component for Terrain {
+    ///     type Storage = SliceStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut world = World::new();
+    ///     let grid = Grid2d::new(64, 48);
+    ///     world.insert(grid);
+    ///     world.register_tile_comp_filled::<Terrain, TileId>(Terrain::Grass);
+    ///
+    ///     let mut terrain = world.write_resource::<Storage<Terrain, SliceStorage<_>, TileId>>();
+    ///     assert_eq!((&*terrain).join().count(), 64 * 48);
+    ///     for &(x, y) in &[(0, 0), (63, 0), (0, 47), (63, 47)] {
+    ///         let id = grid.id::<TileId>(x, y).unwrap();
+    ///         assert_eq!(terrain.get(id), Some(&Terrain::Grass));
+    ///     }
+    ///     assert_eq!(terrain.get(TileId(64 * 48)), None);
+    ///
+    ///     terrain.insert(TileId(5), Terrain::Water);
+    ///     assert_eq!(terrain.get(TileId(5)), Some(&Terrain::Water));
+    /// }
+    /// ```
+    ///
+    /// ```should_panic
+    /// # extern crate specs;
+    /// # extern crate specs_static;
+    /// # use specs::{Component, VecStorage, World, WorldExt};
+    /// # use specs_static::TileWorldExt;
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// # #[derive(Clone)]
+    /// # struct Fog(u8);
+    /// # impl Component for Fog {
+    /// #     type Storage = VecStorage<Self>;
+    /// # }
+    /// # fn main() {
+    /// // Without a grid, there are no tiles to fill.
+    /// let mut world = World::new();
+    /// world.register_tile_comp_filled::<Fog, TileId>(Fog(3));
+    /// # }
+    /// ```
+    fn register_tile_comp_filled<C, I>(&mut self, value: C)
+    where
+        C: Component + Clone + Send + Sync,
+        C::Storage: Default + ReserveIds + Send + Sync,
+        I: Id;
+
+    /// Registers `storage` like `register_tile_comp`, for storages which can't be created
+    /// with `Default`, like ones allocating through a given `TileAlloc` handle.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, World, WorldExt};
+    /// use specs_static::{AnyTileStorage, PagedStorage, StdAlloc, Storage, TileWorldExt};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// struct Light(u8);
+    ///
+    /// impl Component for Light {
+    ///     type Storage = PagedStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut world = World::new();
+    ///     let lights = Storage::<Light, PagedStorage<_>, TileId>::with_alloc(StdAlloc);
+    ///     world.register_tile_comp_with(lights);
+    ///     world
+    ///         .write_resource::<Storage<Light, PagedStorage<_>, TileId>>()
+    ///         .insert(TileId(3), Light(9));
+    ///
+    ///     assert_eq!(world.tile_storages::<TileId>()[0].len(), 1);
+    /// }
+    /// ```
+    fn register_tile_comp_with<C, D, I>(&mut self, storage: Storage<C, D, I>)
+    where
+        C: Send + Sync + 'static,
+        D: UnprotectedStorage<C> + Send + Sync + 'static,
+        I: Id;
+
+    /// Registers a storage for the components of type `C` like `register_tile_comp`, under
+    /// `name`, so it can be looked up with `tile_storage_named` by code which doesn't know
+    /// the component type, like scripts.
+    ///
+    /// See `TileRegistry::register_named` for the rules for names. Nothing is registered if
+    /// an error is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, VecStorage, World, WorldExt};
+    /// use specs_static::{LayerNameError, Storage, TileRegistry, TileWorldExt};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// struct Material(u8);
+    ///
+    /// impl Component for Material {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// struct Moisture(f32);
+    ///
+    /// impl Component for Moisture {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut world = World::new();
+    ///     world.register_tile_comp_named::<Material, TileId>("material").unwrap();
+    ///     world.register_tile_comp_named::<Moisture, TileId>("moisture").unwrap();
+    ///     world
+    ///         .write_resource::<Storage<Material, VecStorage<_>, TileId>>()
+    ///         .insert(TileId(4), Material(2));
+    ///
+    ///     // Clearing a tile by the layer name.
+    ///     let mut material = world.tile_storage_named_mut::<TileId>("material").unwrap();
+    ///     assert!(material.remove(4));
+    ///     drop(material);
+    ///     assert!(world.tile_storage_named::<TileId>("moisture").unwrap().is_empty());
+    ///     assert!(world.tile_storage_named::<TileId>("height").is_none());
+    ///
+    ///     match world.register_tile_comp_named::<Moisture, TileId>("material") {
+    ///         Err(LayerNameError::Taken { ref name, type_name }) => {
+    ///             assert_eq!(name, "material");
+    ///             assert!(type_name.ends_with("Material"));
+    ///         }
+    ///         _ => panic!("the name is taken"),
+    ///     }
+    ///     match world.register_tile_comp_named::<Moisture, TileId>("wetness") {
+    ///         Err(LayerNameError::AlreadyNamed { ref name, .. }) => assert_eq!(name, "moisture"),
+    ///         _ => panic!("the layer has a name"),
+    ///     }
+    ///
+    ///     let registry = world.read_resource::<TileRegistry<TileId>>();
+    ///     assert_eq!(registry.name_of::<Moisture>(), Some("moisture"));
+    ///     assert!(registry.type_name_of("material").unwrap().ends_with("Material"));
+    /// }
+    /// ```
+    fn register_tile_comp_named<C, I>(&mut self, name: &str) -> Result<(), LayerNameError>
+    where
+        C: Component + Send + Sync,
+        C::Storage: Default + ReserveIds + Send + Sync,
+        I: Id;
+
+    /// Registers a `specs_static::DoubleBuffered` pair of storages for the components of
+    /// type `C`.
+    fn register_double_buffered_tile_comp<C, I>(&mut self)
+    where
+        C: Component + Send + Sync,
+        C::Storage: Default,
+        I: Id;
+
+    /// Registers an empty `specs_static::LocalStorage` for components which aren't `Send` or
+    /// `Sync`, fetched with `ReadLocalTiles` and `WriteLocalTiles` on the current thread only.
+    ///
+    /// The storage isn't added to the `TileRegistry`, so `tile_storages` and the other
+    /// registry-wide operations skip it.
+    fn register_local_tile_comp<C, D, I>(&mut self)
+    where
+        C: 'static,
+        D: UnprotectedStorage<C> + Default + 'static,
+        I: Id;
+
+    /// Registers a `specs_static::DenseStorage`, which has to be constructed up front
+    /// because it needs to know how many ids it covers.
+    fn register_dense_tile_comp<C, I>(&mut self, storage: DenseStorage<C, I>)
+    where
+        C: Send + Sync + 'static,
+        I: Id;
+
+    /// Registers an empty `specs_static::TagStorage` for the marker type `T`.
+    fn register_tag<T, I>(&mut self)
+    where
+        T: Send + Sync + 'static,
+        I: Id;
+
+    /// Applies all updates queued in the `specs_static::TileUpdates<I>` resource, in the
+    /// order they were queued.
+    fn maintain_tiles<I>(&mut self)
+    where
+        I: Id;
+
+    /// Panics with a message naming `C` and `I` unless `register_tile_comp::<C, I>` has been
+    /// called, which is handy in setup tests.
+    ///
+    /// `ReadTiles` and `WriteTiles` do the same check every time they are fetched.
+    fn assert_tile_comp_registered<C, I>(&self)
+    where
+        C: Component + Send + Sync,
+        I: Id;
+
+    /// Returns a `specs_static::SharedTileStorage` handle through which other threads read the
+    /// layer of `C` without borrowing the world, adding it as a resource first if needed,
+    /// with the current state of the storage as the first generation.
+    ///
+    /// Publish later changes with a `specs_static::TileShareSystem`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `C` hasn't been registered with `register_tile_comp`.
+    fn share_tile_comp<C, I>(&mut self) -> SharedTileStorage<C, I>
+    where
+        C: Component + Clone + Send + Sync,
+        I: Id;
+
+    /// Fetches all storages registered in the `specs_static::TileRegistry<I>` resource, in
+    /// registration order.
+    ///
+    /// Storages whose resources have been removed are skipped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if one of the storages is currently borrowed mutably.
+    fn tile_storages<I>(&self) -> Vec<TileStorageRef<'_>>
+    where
+        I: Id;
+
+    /// Fetches all storages registered in the `specs_static::TileRegistry<I>` resource mutably,
+    /// in registration order.
+    ///
+    /// Storages whose resources have been removed are skipped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if one of the storages is currently borrowed.
+    fn tile_storages_mut<I>(&self) -> Vec<TileStorageMut<'_>>
+    where
+        I: Id;
+
+    /// Fetches the storage registered under `name` in the `specs_static::TileRegistry<I>`
+    /// resource, or returns `None` if there is none or its resource has been removed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the storage is currently borrowed mutably.
+    fn tile_storage_named<I>(&self, name: &str) -> Option<TileStorageRef<'_>>
+    where
+        I: Id;
+
+    /// Fetches the storage registered under `name` in the `specs_static::TileRegistry<I>`
+    /// resource mutably, or returns `None` if there is none or its resource has been removed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the storage is currently borrowed.
+    fn tile_storage_named_mut<I>(&self, name: &str) -> Option<TileStorageMut<'_>>
+    where
+        I: Id;
+
+    /// Returns the component at `id` of the layer registered under `name` as JSON, or
+    /// `None` if the tile is vacant.
+    ///
+    /// The layer has to be added with `TileRegistry::register_json`, see `set_tile_json`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the storage is currently borrowed mutably, or if the component can't be
+    /// represented as JSON.
+    #[cfg(feature = "serde")]
+    fn tile_json<I>(&self, name: &str, id: u32) -> Result<Option<Value>, TileJsonError>
+    where
+        I: Id;
+
+    /// Deserializes `value` as a component of the layer registered under `name` and inserts
+    /// it at `id`, or removes the component at `id` if `value` is `null`.
+    ///
+    /// Together with `tile_json`, this gives scripting layers read and write access to the
+    /// tiles of a layer without knowing its component type. The layer has to be added with
+    /// `TileRegistry::register_json` for this, as well as be named.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the storage is currently borrowed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// #[macro_use]
+    /// extern crate serde_derive;
+    /// # extern crate serde_crate as serde;
+    /// #[macro_use]
+    /// extern crate serde_json;
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, VecStorage, World, WorldExt};
+    /// use specs_static::{Storage, TileJsonError, TileRegistry, TileWorldExt};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// # #[serde(crate = "serde")]
+    /// enum Material {
+    ///     Dirt,
+    ///     Stone { hardness: u8 },
+    /// }
+    ///
+    /// impl Component for Material {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// struct Light(u8);
+    ///
+    /// impl Component for Light {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// type Materials = Storage<Material, VecStorage<Material>, TileId>;
+    ///
+    /// fn main() {
+    ///     let mut world = World::new();
+    ///     world.register_tile_comp_named::<Material, TileId>("material").unwrap();
+    ///     world.register_tile_comp_named::<Light, TileId>("light").unwrap();
+    ///     world.write_resource::<TileRegistry<TileId>>().register_json::<Material>();
+    ///
+    ///     // What a script setting the material of a tile does.
+    ///     let stone = json!({ "Stone": { "hardness": 7 } });
+    ///     world.set_tile_json::<TileId>("material", 12, &stone).unwrap();
+    ///     world.set_tile_json::<TileId>("material", 13, &json!("Dirt")).unwrap();
+    ///
+    ///     // Read back through the typed API, and the other way around.
+    ///     {
+    ///         let mut materials = world.write_resource::<Materials>();
+    ///         assert_eq!(materials.get(TileId(12)), Some(&Material::Stone { hardness: 7 }));
+    ///         assert_eq!(materials.get(TileId(13)), Some(&Material::Dirt));
+    ///         materials.insert(TileId(14), Material::Stone { hardness: 2 });
+    ///     }
+    ///     let read = world.tile_json::<TileId>("material", 14).unwrap();
+    ///     assert_eq!(read, Some(json!({ "Stone": { "hardness": 2 } })));
+    ///     assert_eq!(world.tile_json::<TileId>("material", 15).unwrap(), None);
+    ///
+    ///     // `null` clears the tile.
+    ///     world.set_tile_json::<TileId>("material", 12, &json!(null)).unwrap();
+    ///     assert_eq!(world.read_resource::<Materials>().get(TileId(12)), None);
+    ///
+    ///     match world.set_tile_json::<TileId>("material", 12, &json!("Lava")) {
+    ///         Err(TileJsonError::Invalid { ref name, .. }) => assert_eq!(name, "material"),
+    ///         _ => panic!("there is no lava"),
+    ///     }
+    ///     match world.set_tile_json::<TileId>("light", 12, &json!(3)) {
+    ///         Err(TileJsonError::NotJson { .. }) => {}
+    ///         _ => panic!("lights aren't registered for JSON access"),
+    ///     }
+    ///     match world.tile_json::<TileId>("water", 12) {
+    ///         Err(TileJsonError::UnknownLayer { .. }) => {}
+    ///         _ => panic!("there is no water layer"),
+    ///     }
+    /// }
+    /// ```
+    #[cfg(feature = "serde")]
+    fn set_tile_json<I>(&self, name: &str, id: u32, value: &Value) -> Result<(), TileJsonError>
+    where
+        I: Id;
+
+    /// Takes a snapshot of every named layer added with `TileRegistry::register_json`,
+    /// together with the `Grid2d` resource, see `TileSnapshot`.
+    ///
+    /// Returns `SnapshotError::NoGrid` if there is no grid, since loading the layers into a
+    /// world with another grid would scramble their coordinates.
+    ///
+    /// # Panics
+    ///
+    /// Panics if one of the storages is currently borrowed mutably, or if a component can't
+    /// be represented as JSON.
+    #[cfg(feature = "serde")]
+    fn tile_snapshot<I>(&self) -> Result<TileSnapshot, SnapshotError>
+    where
+        I: Id;
+
+    /// Replaces the contents of the layers in `snapshot` with its components, see
+    /// `TileSnapshot`.
+    ///
+    /// If the world has no `Grid2d` resource, the grid of the snapshot is added. If it has
+    /// one with other dimensions, `load` decides whether that's an error or the tiles are
+    /// moved to the ids of their coordinates. The layers of the world which aren't in the
+    /// snapshot are left alone.
+    ///
+    /// Missing layers and ids which don't fit are reported before anything is changed. A
+    /// component which can't be deserialized or exceeds the bound of its storage is only
+    /// noticed while loading, so the layers before it have been replaced already.
+    ///
+    /// # Panics
+    ///
+    /// Panics if one of the storages is currently borrowed.
+    #[cfg(feature = "serde")]
+    fn load_tile_snapshot<I>(
+        &mut self,
+        snapshot: &TileSnapshot,
+        load: SnapshotLoad,
+    ) -> Result<(), SnapshotError>
+    where
+        I: Id;
+
+    /// Calls `f` with the component type name and the storage of every layer registered in the
+    /// `specs_static::TileRegistry<I>` resource, in registration order.
+    ///
+    /// In contrast to `tile_storages`, only one storage is fetched at a time, so `f` can fetch
+    /// other resources (apart from the storage it's given) from the world.
+    ///
+    /// # Panics
+    ///
+    /// Panics if one of the storages is currently borrowed mutably.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, VecStorage, World, WorldExt};
+    /// use specs_static::prelude::*;
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// struct Height(f32);
+    ///
+    /// impl Component for Height {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// struct Moisture(f32);
+    ///
+    /// impl Component for Moisture {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// struct Owner(u8);
+    ///
+    /// impl Component for Owner {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut world = World::new();
+    ///     world.register_tile_comp::<Height, TileId>();
+    ///     world.register_tile_comp::<Moisture, TileId>();
+    ///     world.register_tile_comp::<Owner, TileId>();
+    ///     {
+    ///         let mut heights = world.write_resource::<Storage<Height, VecStorage<_>, TileId>>();
+    ///         heights.insert(TileId(7), Height(1.5));
+    ///         heights.insert(TileId(8), Height(2.0));
+    ///     }
+    ///     world
+    ///         .write_resource::<Storage<Owner, VecStorage<_>, TileId>>()
+    ///         .insert(TileId(7), Owner(1));
+    ///
+    ///     // Clear everything at tile 7.
+    ///     world.for_each_tile_storage_mut::<TileId, _>(|_, storage| {
+    ///         storage.remove(7);
+    ///     });
+    ///
+    ///     let mut layers = Vec::new();
+    ///     world.for_each_tile_storage::<TileId, _>(|name, storage| {
+    ///         assert!(!storage.contains(7));
+    ///         layers.push((name.rsplit("::").next().unwrap(), storage.len()));
+    ///     });
+    ///     assert_eq!(layers, vec![("Height", 1), ("Moisture", 0), ("Owner", 0)]);
+    /// }
+    /// ```
+    fn for_each_tile_storage<I, F>(&self, f: F)
+    where
+        I: Id,
+        F: FnMut(&'static str, &dyn AnyTileStorage);
+
+    /// Calls `f` with the component type name and the mutable storage of every layer
+    /// registered in the `specs_static::TileRegistry<I>` resource, in registration order.
+    ///
+    /// Like `for_each_tile_storage`, this only fetches one storage at a time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if one of the storages is currently borrowed.
+    fn for_each_tile_storage_mut<I, F>(&self, f: F)
+    where
+        I: Id,
+        F: FnMut(&'static str, &mut dyn AnyTileStorage);
+
+    /// Clones the components at `from` into `to` in every layer registered in the
+    /// `specs_static::TileRegistry<I>` resource, overwriting the components at `to`.
+    ///
+    /// Only layers added with `TileRegistry::register_clone` can be copied; the others are
+    /// listed in `CopyReport::skipped`. Layers without a component at `from` keep their
+    /// component at `to`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `to` is out of bounds of a layer with a component at `from`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, VecStorage, World, WorldExt};
+    /// use specs_static::{Storage, TileRegistry, TileWorldExt};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// #[derive(Clone, Debug, PartialEq)]
+    /// struct Terrain(&'static str);
+    ///
+    /// impl Component for Terrain {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// #[derive(Clone, Debug, PartialEq)]
+    /// struct Decal(u8);
+    ///
+    /// impl Component for Decal {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// // Unique, so it can't be cloned.
+    /// #[derive(Debug, PartialEq)]
+    /// struct Spawner(u32);
+    ///
+    /// impl Component for Spawner {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut world = World::new();
+    ///     world.register_tile_comp::<Terrain, TileId>();
+    ///     world.register_tile_comp::<Decal, TileId>();
+    ///     world.register_tile_comp::<Spawner, TileId>();
+    ///     {
+    ///         let mut registry = world.write_resource::<TileRegistry<TileId>>();
+    ///         registry.register_clone::<Terrain>();
+    ///         registry.register_clone::<Decal>();
+    ///         assert!(!registry.is_cloneable::<Spawner>());
+    ///     }
+    ///
+    ///     world
+    ///         .write_resource::<Storage<Terrain, VecStorage<_>, TileId>>()
+    ///         .insert(TileId(1), Terrain("sand"));
+    ///     world
+    ///         .write_resource::<Storage<Decal, VecStorage<_>, TileId>>()
+    ///         .insert(TileId(2), Decal(4));
+    ///     world
+    ///         .write_resource::<Storage<Spawner, VecStorage<_>, TileId>>()
+    ///         .insert(TileId(1), Spawner(7));
+    ///
+    ///     let report = world.copy_tile(TileId(1), TileId(2));
+    ///     assert_eq!(report.copied.len(), 1);
+    ///     assert!(report.copied[0].ends_with("Terrain"));
+    ///     assert_eq!(report.skipped.len(), 1);
+    ///     assert!(report.skipped[0].ends_with("Spawner"));
+    ///
+    ///     let terrain = world.read_resource::<Storage<Terrain, VecStorage<_>, TileId>>();
+    ///     assert_eq!(terrain.get(TileId(1)), Some(&Terrain("sand")));
+    ///     assert_eq!(terrain.get(TileId(2)), Some(&Terrain("sand")));
+    ///     // There is no decal at the source tile, so the target keeps its own.
+    ///     let decals = world.read_resource::<Storage<Decal, VecStorage<_>, TileId>>();
+    ///     assert_eq!(decals.get(TileId(2)), Some(&Decal(4)));
+    ///     let spawners = world.read_resource::<Storage<Spawner, VecStorage<_>, TileId>>();
+    ///     assert_eq!(spawners.get(TileId(2)), None);
+    /// }
+    /// ```
+    fn copy_tile<I>(&mut self, from: I, to: I) -> CopyReport
+    where
+        I: Id;
+
+    /// Removes all components from every layer registered in the
+    /// `specs_static::TileRegistry<I>` resource, like when switching to another map.
+    ///
+    /// Returns the component type name of every layer with the amount of components which
+    /// have been dropped, in registration order. Storages which haven't been registered for
+    /// `I` are left alone.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate hibitset;
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use hibitset::BitSet;
+    /// use specs::{Component, VecStorage, World, WorldExt};
+    /// use specs_static::{AnyTileStorage, Storage, TileWorldExt};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// struct Floor;
+    ///
+    /// impl Component for Floor {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// struct Item(u32);
+    ///
+    /// impl Component for Item {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// struct Marker;
+    ///
+    /// impl Component for Marker {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// type Floors = Storage<Floor, VecStorage<Floor>, TileId>;
+    /// type Items = Storage<Item, VecStorage<Item>, TileId>;
+    /// type Markers = Storage<Marker, VecStorage<Marker>, TileId>;
+    ///
+    /// fn main() {
+    ///     let mut world = World::new();
+    ///     world.register_tile_comp::<Floor, TileId>();
+    ///     world.register_tile_comp::<Item, TileId>();
+    ///     // Not in the registry.
+    ///     world.insert(Markers::default());
+    ///
+    ///     for id in 0..100 {
+    ///         world.write_resource::<Floors>().insert(TileId(id), Floor);
+    ///         world.write_resource::<Markers>().insert(TileId(id), Marker);
+    ///     }
+    ///     world.write_resource::<Items>().insert(TileId(10), Item(3));
+    ///     world.write_resource::<Items>().insert(TileId(80), Item(5));
+    ///
+    ///     // Unloading the chunk of the first 64 tiles.
+    ///     let mut chunk = BitSet::new();
+    ///     for id in 0..64 {
+    ///         chunk.add(id);
+    ///     }
+    ///     let dropped = world.clear_tile_space_mask::<TileId>(&chunk);
+    ///     let dropped: Vec<usize> = dropped.into_iter().map(|(_, count)| count).collect();
+    ///     assert_eq!(dropped, vec![64, 1]);
+    ///     assert_eq!(world.read_resource::<Items>().get(TileId(80)).map(|i| i.0), Some(5));
+    ///
+    ///     let dropped = world.clear_tile_space::<TileId>();
+    ///     assert!(dropped[0].0.ends_with("Floor"));
+    ///     assert!(dropped[1].0.ends_with("Item"));
+    ///     assert_eq!((dropped[0].1, dropped[1].1), (36, 1));
+    ///     assert!(world.read_resource::<Floors>().is_empty());
+    ///     assert!(world.read_resource::<Items>().is_empty());
+    ///     assert_eq!(world.read_resource::<Markers>().len(), 100);
+    /// }
+    /// ```
+    fn clear_tile_space<I>(&mut self) -> Vec<(&'static str, usize)>
+    where
+        I: Id;
+
+    /// Removes the components at the ids in `mask` from every layer registered in the
+    /// `specs_static::TileRegistry<I>` resource, like when unloading a chunk.
+    ///
+    /// Returns the dropped amounts per layer like `clear_tile_space`.
+    fn clear_tile_space_mask<I>(&mut self, mask: &BitSet) -> Vec<(&'static str, usize)>
+    where
+        I: Id;
+
+    /// Moves the components at `from` to `to` in every layer registered in the
+    /// `specs_static::TileRegistry<I>` resource, dropping the components they replace, and
+    /// returns the component type names of the layers which had a component to move.
+    ///
+    /// Afterwards, `from` is vacant in every layer. Layers without a component at `from`
+    /// keep their component at `to`. Moving a component within a layer is all-or-nothing,
+    /// but the layers are moved one after another: if one of them panics, for example
+    /// because `to` exceeds its bound, the layers before it have been moved already.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `to` is out of bounds of a layer with a component at `from`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, VecStorage, World, WorldExt};
+    /// use specs_static::{Storage, TileWorldExt};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// #[derive(Debug, PartialEq)]
+    /// struct Crate(u32);
+    ///
+    /// impl Component for Crate {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// struct Health(u8);
+    ///
+    /// impl Component for Health {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// struct Light(u8);
+    ///
+    /// impl Component for Light {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// type Crates = Storage<Crate, VecStorage<Crate>, TileId>;
+    /// type Healths = Storage<Health, VecStorage<Health>, TileId>;
+    /// type Lights = Storage<Light, VecStorage<Light>, TileId>;
+    ///
+    /// fn main() {
+    ///     let mut world = World::new();
+    ///     world.register_tile_comp::<Crate, TileId>();
+    ///     world.register_tile_comp::<Health, TileId>();
+    ///     world.register_tile_comp::<Light, TileId>();
+    ///
+    ///     world.write_resource::<Crates>().insert(TileId(4), Crate(1));
+    ///     world.write_resource::<Healths>().insert(TileId(4), Health(90));
+    ///     // Replaced by the moved component.
+    ///     world.write_resource::<Healths>().insert(TileId(5), Health(10));
+    ///     // Kept, since there is no light at the old tile.
+    ///     world.write_resource::<Lights>().insert(TileId(5), Light(3));
+    ///
+    ///     let moved = world.move_tile(TileId(4), TileId(5));
+    ///     assert_eq!(moved.len(), 2);
+    ///     assert!(moved[0].ends_with("Crate") && moved[1].ends_with("Health"));
+    ///
+    ///     let crates = world.read_resource::<Crates>();
+    ///     let healths = world.read_resource::<Healths>();
+    ///     let lights = world.read_resource::<Lights>();
+    ///     assert_eq!((crates.get(TileId(4)), crates.get(TileId(5))), (None, Some(&Crate(1))));
+    ///     assert_eq!((healths.get(TileId(4)), healths.get(TileId(5))), (None, Some(&Health(90))));
+    ///     assert_eq!((lights.get(TileId(4)), lights.get(TileId(5))), (None, Some(&Light(3))));
+    /// }
+    /// ```
+    fn move_tile<I>(&mut self, from: I, to: I) -> Vec<&'static str>
+    where
+        I: Id;
+
+    /// Removes the components at `id` from every layer registered in the
+    /// `specs_static::TileRegistry<I>` resource, like when a tile is blown up.
+    ///
+    /// The layers are found through the registry, so layers added later are cleared as well
+    /// without naming them here. Storages which haven't been registered for `I` are left
+    /// alone, and no storage is created.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, VecStorage, World, WorldExt};
+    /// use specs_static::{AnyTileStorage, Storage, TileWorldExt};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// struct Ground;
+    ///
+    /// impl Component for Ground {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// struct Wall;
+    ///
+    /// impl Component for Wall {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// struct Loot(u32);
+    ///
+    /// impl Component for Loot {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// type Grounds = Storage<Ground, VecStorage<Ground>, TileId>;
+    /// type Walls = Storage<Wall, VecStorage<Wall>, TileId>;
+    /// type Loots = Storage<Loot, VecStorage<Loot>, TileId>;
+    ///
+    /// fn main() {
+    ///     let mut world = World::new();
+    ///     world.register_tile_comp::<Ground, TileId>();
+    ///     world.register_tile_comp::<Wall, TileId>();
+    ///     world.register_tile_comp::<Loot, TileId>();
+    ///     for id in 0..4 {
+    ///         world.write_resource::<Grounds>().insert(TileId(id), Ground);
+    ///     }
+    ///     world.write_resource::<Loots>().insert(TileId(2), Loot(10));
+    ///     world.write_resource::<Walls>().insert(TileId(3), Wall);
+    ///
+    ///     // A crater at the loot, but not at the wall.
+    ///     let summary = world.remove_tile(TileId(2));
+    ///     assert_eq!(summary.removed.len(), 2);
+    ///     assert!(summary.removed[0].ends_with("Ground"));
+    ///     assert!(summary.removed[1].ends_with("Loot"));
+    ///     assert_eq!(summary.vacant.len(), 1);
+    ///     assert!(summary.vacant[0].ends_with("Wall"));
+    ///
+    ///     assert_eq!(world.read_resource::<Grounds>().len(), 3);
+    ///     assert!(world.read_resource::<Loots>().is_empty());
+    ///     assert_eq!(world.read_resource::<Walls>().len(), 1);
+    ///
+    ///     // Nothing is left to remove.
+    ///     let summary = world.remove_tile(TileId(2));
+    ///     assert!(summary.removed.is_empty());
+    ///     assert_eq!(summary.vacant.len(), 3);
+    ///
+    ///     // Without a registry, there are no layers.
+    ///     let mut empty = World::new();
+    ///     assert_eq!(empty.remove_tile(TileId(0)), Default::default());
+    ///     assert!(!empty.has_value::<Grounds>());
+    /// }
+    /// ```
+    fn remove_tile<I>(&mut self, id: I) -> RemovedSummary
+    where
+        I: Id;
+
+    /// Hashes the contents of every layer added with `TileRegistry::register_hash` for `I`
+    /// with a `StableHasher`, in registration order, for detecting desyncs between peers.
+    ///
+    /// Every layer is hashed with `Storage::content_hash`, so the result only depends on
+    /// the components and the order the layers have been registered in. Layers without the
+    /// hash capability are left out. Whether float components hash the same on every peer
+    /// depends on their computations being deterministic, which is up to the user.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, VecStorage, World, WorldExt};
+    /// use specs_static::{Storage, TileRegistry, TileWorldExt};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// #[derive(Hash)]
+    /// struct Building(u16);
+    ///
+    /// impl Component for Building {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// #[derive(Hash)]
+    /// struct Road;
+    ///
+    /// impl Component for Road {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// type Buildings = Storage<Building, VecStorage<Building>, TileId>;
+    /// type Roads = Storage<Road, VecStorage<Road>, TileId>;
+    ///
+    /// fn peer(ids: &[u32]) -> World {
+    ///     let mut world = World::new();
+    ///     world.register_tile_comp::<Building, TileId>();
+    ///     world.register_tile_comp::<Road, TileId>();
+    ///     {
+    ///         let mut registry = world.write_resource::<TileRegistry<TileId>>();
+    ///         registry.register_hash::<Building>();
+    ///         registry.register_hash::<Road>();
+    ///     }
+    ///     for &id in ids {
+    ///         world.write_resource::<Buildings>().insert(TileId(id), Building(id as u16));
+    ///         world.write_resource::<Roads>().insert(TileId(id + 1), Road);
+    ///     }
+    ///     world
+    /// }
+    ///
+    /// fn main() {
+    ///     let a = peer(&[1, 5, 9]);
+    ///     let b = peer(&[9, 1, 5]);
+    ///     assert_eq!(a.tile_space_hash::<TileId>(), b.tile_space_hash::<TileId>());
+    ///
+    ///     b.write_resource::<Buildings>().insert(TileId(5), Building(6));
+    ///     assert_ne!(a.tile_space_hash::<TileId>(), b.tile_space_hash::<TileId>());
+    /// }
+    /// ```
+    fn tile_space_hash<I>(&self) -> u64
+    where
+        I: Id;
+
+    /// Summarizes every layer registered in the `specs_static::TileRegistry<I>` resource and
+    /// the `Grid2d` resource, see `specs_static::TileDiagnostics`.
+    ///
+    /// Storages which are borrowed mutably are skipped instead of panicking, so this can be
+    /// called at any time, like from a panic hook.
+    fn tile_diagnostics<I>(&self) -> TileDiagnostics
+    where
+        I: Id;
+    /// Gathers the operation counters of all layers registered in the
+    /// `specs_static::TileRegistry<I>` resource, in registration order, with the `profile`
+    /// feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, Join, VecStorage, World, WorldExt};
+    /// use specs_static::{Storage, TileWorldExt};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// struct Water(u8);
+    ///
+    /// impl Component for Water {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// struct Rock;
+    ///
+    /// impl Component for Rock {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut world = World::new();
+    ///     world.register_tile_comp::<Water, TileId>();
+    ///     world.register_tile_comp::<Rock, TileId>();
+    ///     {
+    ///         let mut water = world.write_resource::<Storage<Water, VecStorage<Water>, TileId>>();
+    ///         let mut rocks = world.write_resource::<Storage<Rock, VecStorage<Rock>, TileId>>();
+    ///         for id in 0..4 {
+    ///             water.insert(TileId(id), Water(1));
+    ///         }
+    ///         rocks.insert(TileId(2), Rock);
+    ///         for (water, _) in (&mut *water, &*rocks).join() {
+    ///             water.0 = 0;
+    ///         }
+    ///     }
+    ///
+    ///     let report = world.tile_profile_report::<TileId>();
+    ///     let water = report.layers[0].1;
+    ///     let rocks = report.layers[1].1;
+    ///     assert!(report.layers[0].0.ends_with("Water"));
+    ///     assert_eq!((water.inserts, water.joined), (4, 1));
+    ///     assert_eq!((rocks.inserts, rocks.joined), (1, 1));
+    ///     assert_eq!(report.total().inserts, 5);
+    ///     println!("{}", report);
+    ///
+    ///     // Next frame.
+    ///     world.take_tile_profile_report::<TileId>();
+    ///     assert_eq!(world.tile_profile_report::<TileId>().total().inserts, 0);
+    /// }
+    /// ```
+    #[cfg(feature = "profile")]
+    fn tile_profile_report<I>(&self) -> ProfileReport
+    where
+        I: Id;
+
+    /// Gathers the operation counters like `tile_profile_report` and resets them, for example
+    /// once per frame.
+    #[cfg(feature = "profile")]
+    fn take_tile_profile_report<I>(&self) -> ProfileReport
+    where
+        I: Id;
+}
+
+#[cfg(feature = "specs")]
+impl TileWorldExt for World {
+    fn register_tile_comp<C, I>(&mut self)
+    where
+        C: Component + Send + Sync,
+        C::Storage: Default + ReserveIds + Send + Sync,
+        I: Id,
+    {
+        self.register_tile_comp_as::<C, C::Storage, I>();
+    }
+
+    fn register_tile_comp_as<C, D, I>(&mut self)
+    where
+        C: Send + Sync + 'static,
+        D: UnprotectedStorage<C> + Default + ReserveIds + Send + Sync + 'static,
+        I: Id,
+    {
+        let storage = presize::presized::<C, D, I>(self);
+        self.insert(storage);
+        self.entry::<TileRegistry<I>>()
+            .or_insert_with(Default::default)
+            .register::<C, D>();
+    }
+
+    fn register_tile_comp_with_capacity<C, I>(&mut self, len: u32)
+    where
+        C: Component + Send + Sync,
+        C::Storage: Default + ReserveIds + Send + Sync,
+        I: Id,
+    {
+        let mut storage = Storage::<C, C::Storage, I>::default();
+        storage.reserve_ids(len);
+        self.register_tile_comp_with(storage);
+    }
+
+    fn register_tile_comp_filled<C, I>(&mut self, value: C)
+    where
+        C: Component + Clone + Send + Sync,
+        C::Storage: Default + ReserveIds + Send + Sync,
+        I: Id,
+    {
+        let len = match self.try_fetch::<Grid2d>() {
+            Some(grid) => grid.len(),
+            None => panic!(
+                "can't fill the layer of `{}`: the world has no `Grid2d` resource",
+                std::any::type_name::<C>()
+            ),
+        };
+        let mut storage = presize::presized::<C, C::Storage, I>(self);
+        storage.fill_range(I::from_u32(0), I::from_u32(len), value);
+        self.register_tile_comp_with(storage);
+    }
+
+    fn register_tile_comp_with<C, D, I>(&mut self, storage: Storage<C, D, I>)
+    where
+        C: Send + Sync + 'static,
+        D: UnprotectedStorage<C> + Send + Sync + 'static,
+        I: Id,
+    {
+        self.insert(storage);
+        self.entry::<TileRegistry<I>>()
+            .or_insert_with(Default::default)
+            .register::<C, D>();
+    }
+
+    fn register_tile_comp_named<C, I>(&mut self, name: &str) -> Result<(), LayerNameError>
+    where
+        C: Component + Send + Sync,
+        C::Storage: Default + ReserveIds + Send + Sync,
+        I: Id,
+    {
+        self.entry::<TileRegistry<I>>()
+            .or_insert_with(Default::default)
+            .register_named::<C, C::Storage>(name)?;
+        let storage = presize::presized::<C, C::Storage, I>(self);
+        self.insert(storage);
+
+        Ok(())
+    }
+
+    fn register_double_buffered_tile_comp<C, I>(&mut self)
+    where
+        C: Component + Send + Sync,
+        C::Storage: Default,
+        I: Id,
+    {
+        self.insert(DoubleBuffered::<C, C::Storage, I>::default());
+    }
+
+    fn register_local_tile_comp<C, D, I>(&mut self)
+    where
+        C: 'static,
+        D: UnprotectedStorage<C> + Default + 'static,
+        I: Id,
+    {
+        self.insert(LocalTiles::new(LocalStorage::<C, D, I>::new()));
+    }
+
+    fn register_dense_tile_comp<C, I>(&mut self, storage: DenseStorage<C, I>)
+    where
+        C: Send + Sync + 'static,
+        I: Id,
+    {
+        self.insert(storage);
+    }
+
+    fn register_tag<T, I>(&mut self)
+    where
+        T: Send + Sync + 'static,
+        I: Id,
+    {
+        self.insert(TagStorage::<T, I>::new());
+    }
+
+    fn maintain_tiles<I>(&mut self)
+    where
+        I: Id,
+    {
+        let updates = match self.try_fetch::<TileUpdates<I>>() {
+            Some(updates) => updates.take(),
+            None => return,
+        };
+
+        for update in updates {
+            update(self);
+        }
+    }
+
+    fn assert_tile_comp_registered<C, I>(&self)
+    where
+        C: Component + Send + Sync,
+        I: Id,
+    {
+        fetch::check_registered::<C, I>(self);
+    }
+
+    fn share_tile_comp<C, I>(&mut self) -> SharedTileStorage<C, I>
+    where
+        C: Component + Clone + Send + Sync,
+        I: Id,
+    {
+        fetch::check_registered::<C, I>(self);
+        if !self.has_value::<SharedTileStorage<C, I>>() {
+            let shared = SharedTileStorage::share(&*self.fetch::<Storage<C, C::Storage, I>>());
+            self.insert(shared);
+        }
+
+        SharedTileStorage::clone(&self.fetch())
+    }
+
+    fn tile_storages<I>(&self) -> Vec<TileStorageRef<'_>>
+    where
+        I: Id,
+    {
+        registry_entries::<I>(self)
+            .into_iter()
+            .filter_map(|entry| (entry.fetch)(self))
+            .collect()
+    }
+
+    fn tile_storages_mut<I>(&self) -> Vec<TileStorageMut<'_>>
+    where
+        I: Id,
+    {
+        registry_entries::<I>(self)
+            .into_iter()
+            .filter_map(|entry| (entry.fetch_mut)(self))
+            .collect()
+    }
+
+    fn tile_storage_named<I>(&self, name: &str) -> Option<TileStorageRef<'_>>
+    where
+        I: Id,
+    {
+        registry_entry_named::<I>(self, name).and_then(|entry| (entry.fetch)(self))
+    }
+
+    fn tile_storage_named_mut<I>(&self, name: &str) -> Option<TileStorageMut<'_>>
+    where
+        I: Id,
+    {
+        registry_entry_named::<I>(self, name).and_then(|entry| (entry.fetch_mut)(self))
+    }
+
+    #[cfg(feature = "serde")]
+    fn tile_json<I>(&self, name: &str, id: u32) -> Result<Option<Value>, TileJsonError>
+    where
+        I: Id,
+    {
+        let (entry, json) = registry_json_entry::<I>(self, name)?;
+        let storage = (entry.fetch)(self).ok_or_else(|| TileJsonError::UnknownLayer {
+            name: name.to_owned(),
+        })?;
+
+        Ok((json.get)(&*storage, id))
+    }
+
+    #[cfg(feature = "serde")]
+    fn set_tile_json<I>(&self, name: &str, id: u32, value: &Value) -> Result<(), TileJsonError>
+    where
+        I: Id,
+    {
+        let (entry, json) = registry_json_entry::<I>(self, name)?;
+        let mut storage = (entry.fetch_mut)(self).ok_or_else(|| {
+            TileJsonError::UnknownLayer {
+                name: name.to_owned(),
+            }
+        })?;
+
+        (json.set)(&mut *storage, name, id, value)
+    }
+
+    #[cfg(feature = "serde")]
+    fn tile_snapshot<I>(&self) -> Result<TileSnapshot, SnapshotError>
+    where
+        I: Id,
+    {
+        snapshot::save::<I>(self)
+    }
+
+    #[cfg(feature = "serde")]
+    fn load_tile_snapshot<I>(
+        &mut self,
+        snapshot: &TileSnapshot,
+        load: SnapshotLoad,
+    ) -> Result<(), SnapshotError>
+    where
+        I: Id,
+    {
+        snapshot::load::<I>(self, snapshot, load)
+    }
+
+    fn for_each_tile_storage<I, F>(&self, mut f: F)
+    where
+        I: Id,
+        F: FnMut(&'static str, &dyn AnyTileStorage),
+    {
+        for entry in registry_entries::<I>(self) {
+            if let Some(storage) = (entry.fetch)(self) {
+                f(entry.type_name, &*storage);
+            }
+        }
+    }
+
+    fn for_each_tile_storage_mut<I, F>(&self, mut f: F)
+    where
+        I: Id,
+        F: FnMut(&'static str, &mut dyn AnyTileStorage),
+    {
+        for entry in registry_entries::<I>(self) {
+            if let Some(mut storage) = (entry.fetch_mut)(self) {
+                f(entry.type_name, &mut *storage);
+            }
+        }
+    }
+
+    fn copy_tile<I>(&mut self, from: I, to: I) -> CopyReport
+    where
+        I: Id,
+    {
+        let mut report = CopyReport::default();
+        for entry in registry_entries::<I>(self) {
+            let clone_tile = match entry.clone_tile {
+                Some(clone_tile) => clone_tile,
+                None => {
+                    report.skipped.push(entry.type_name);
+                    continue;
+                }
+            };
+            if let Some(mut storage) = (entry.fetch_mut)(self) {
+                if clone_tile(&mut *storage, from.id(), to.id()) {
+                    report.copied.push(entry.type_name);
+                }
+            }
+        }
+
+        report
+    }
+
+    fn clear_tile_space<I>(&mut self) -> Vec<(&'static str, usize)>
+    where
+        I: Id,
+    {
+        let mut dropped = Vec::new();
+        self.for_each_tile_storage_mut::<I, _>(|name, storage| {
+            dropped.push((name, storage.len()));
+            storage.clear();
+        });
+
+        dropped
+    }
+
+    fn clear_tile_space_mask<I>(&mut self, mask: &BitSet) -> Vec<(&'static str, usize)>
+    where
+        I: Id,
+    {
+        let mut dropped = Vec::new();
+        self.for_each_tile_storage_mut::<I, _>(|name, storage| {
+            dropped.push((name, storage.remove_mask(mask)));
+        });
+
+        dropped
+    }
+
+    fn move_tile<I>(&mut self, from: I, to: I) -> Vec<&'static str>
+    where
+        I: Id,
+    {
+        let mut moved = Vec::new();
+        for entry in registry_entries::<I>(self) {
+            if let Some(mut storage) = (entry.fetch_mut)(self) {
+                if (entry.move_tile)(&mut *storage, from.id(), to.id()) {
+                    moved.push(entry.type_name);
+                }
+            }
+        }
+
+        moved
+    }
+
+    fn remove_tile<I>(&mut self, id: I) -> RemovedSummary
+    where
+        I: Id,
+    {
+        let mut summary = RemovedSummary::default();
+        self.for_each_tile_storage_mut::<I, _>(|name, storage| match storage.remove(id.id()) {
+            true => summary.removed.push(name),
+            false => summary.vacant.push(name),
+        });
+
+        summary
+    }
+
+    fn tile_space_hash<I>(&self) -> u64
+    where
+        I: Id,
+    {
+        let mut state = StableHasher::new();
+        for entry in registry_entries::<I>(self) {
+            let hash_tiles = match entry.hash_tiles {
+                Some(hash_tiles) => hash_tiles,
+                None => continue,
+            };
+            if let Some(storage) = (entry.fetch)(self) {
+                hash_tiles(&*storage, &mut state);
+            }
+        }
+
+        state.finish()
+    }
+
+    fn tile_diagnostics<I>(&self) -> TileDiagnostics
+    where
+        I: Id,
+    {
+        diagnostics::collect::<I>(self)
+    }
+
+    #[cfg(feature = "profile")]
+    fn tile_profile_report<I>(&self) -> ProfileReport
+    where
+        I: Id,
+    {
+        tile_profile::<I>(self, |storage| storage.op_counters())
+    }
+
+    #[cfg(feature = "profile")]
+    fn take_tile_profile_report<I>(&self) -> ProfileReport
+    where
+        I: Id,
+    {
+        tile_profile::<I>(self, |storage| storage.take_op_counters())
+    }
+}
+
+#[cfg(all(feature = "profile", feature = "specs"))]
+fn tile_profile<I>(
+    world: &World,
+    counters: fn(&dyn AnyTileStorage) -> OpCounters,
+) -> ProfileReport
+where
+    I: Id,
+{
+    let mut report = ProfileReport::default();
+    for entry in registry_entries::<I>(world) {
+        if let Some(storage) = (entry.fetch)(world) {
+            report.layers.push((entry.type_name, counters(&*storage)));
+        }
+    }
+
+    report
+}
+
+#[cfg(feature = "specs")]
+fn registry_entries<I>(world: &World) -> Vec<registry::Entry>
+where
+    I: Id,
+{
+    world
+        .try_fetch::<TileRegistry<I>>()
+        .map(|registry| registry.entries())
+        .unwrap_or_default()
+}
+
+#[cfg(feature = "specs")]
+fn registry_entry_named<I>(world: &World, name: &str) -> Option<registry::Entry>
+where
+    I: Id,
+{
+    world
+        .try_fetch::<TileRegistry<I>>()
+        .and_then(|registry| registry.entry_named(name))
+}
+
+#[cfg(feature = "serde")]
+fn registry_json_entry<I>(
+    world: &World,
+    name: &str,
+) -> Result<(registry::Entry, json::JsonTile), TileJsonError>
+where
+    I: Id,
+{
+    let entry = registry_entry_named::<I>(world, name).ok_or_else(|| {
+        TileJsonError::UnknownLayer {
+            name: name.to_owned(),
+        }
+    })?;
+    let json = entry.json.ok_or_else(|| TileJsonError::NotJson {
+        name: name.to_owned(),
+    })?;
+
+    Ok((entry, json))
 }
@@ -0,0 +1,387 @@
+//! Tile storages for components which can't be sent to other threads, like ones holding an
+//! `Rc`.
+
+use std::any;
+use std::marker::PhantomData;
+use std::mem::ManuallyDrop;
+use std::ops::{Deref, DerefMut};
+use std::thread::{self, ThreadId};
+
+use hibitset::BitSet;
+use shred::{Fetch, FetchMut, ResourceId, World, SystemData};
+use specs::storage::UnprotectedStorage;
+use specs::Join;
+
+use {Id, Index, Storage};
+
+/// A tile storage for components which are neither `Send` nor `Sync`, for example handles
+/// holding an `Rc`.
+///
+/// It has the `get`, `insert`, `remove` and `join` surface of `Storage`, but isn't `Send` or
+/// `Sync` itself. Register it with `TileWorldExt::register_local_tile_comp` and fetch it with
+/// `ReadLocalTiles` or `WriteLocalTiles`, either from systems added with
+/// `DispatcherBuilder::with_thread_local` or directly through `World::system_data`, on the
+/// thread which registered it.
+///
+/// As `Component` requires `Send + Sync` storages, the components don't implement it; the
+/// backend is named explicitly instead, e.g. `VecStorage<C>`.
+#[derive(Derivative)]
+#[derivative(Default(bound = "D: Default"))]
+pub struct LocalStorage<C, D: UnprotectedStorage<C>, I> {
+    storage: Storage<C, D, I>,
+    phantom: PhantomData<*const ()>,
+}
+
+impl<C, D, I> LocalStorage<C, D, I>
+where
+    D: UnprotectedStorage<C>,
+    I: Id,
+{
+    /// Creates an empty storage.
+    pub fn new() -> Self
+    where
+        D: Default,
+    {
+        Default::default()
+    }
+
+    /// Returns the mask of all ids which have a component.
+    pub fn mask(&self) -> &BitSet {
+        self.storage.mask()
+    }
+
+    /// Returns the component at `id`, if any.
+    pub fn get(&self, id: I) -> Option<&C> {
+        self.storage.get(id)
+    }
+
+    /// Returns the component at `id` mutably, if any.
+    pub fn get_mut(&mut self, id: I) -> Option<&mut C> {
+        self.storage.get_mut(id)
+    }
+
+    /// Inserts `comp` at `id`, returning the component which was there before.
+    pub fn insert(&mut self, id: I, comp: C) -> Option<C> {
+        self.storage.insert(id, comp)
+    }
+
+    /// Removes the component at `id` and returns it, if any.
+    pub fn remove(&mut self, id: I) -> Option<C> {
+        self.storage.remove(id)
+    }
+
+    /// Removes all components.
+    pub fn clear(&mut self) {
+        self.storage.clear();
+    }
+}
+
+impl<'a, C, D, I> Join for &'a LocalStorage<C, D, I>
+where
+    D: UnprotectedStorage<C>,
+{
+    type Type = &'a C;
+    type Value = <&'a Storage<C, D, I> as Join>::Value;
+    type Mask = &'a BitSet;
+
+    unsafe fn open(self) -> (Self::Mask, Self::Value) {
+        (&self.storage).open()
+    }
+
+    unsafe fn get(value: &mut Self::Value, id: Index) -> Self::Type {
+        <&'a Storage<C, D, I> as Join>::get(value, id)
+    }
+}
+
+impl<'a, C, D, I> Join for &'a mut LocalStorage<C, D, I>
+where
+    D: UnprotectedStorage<C>,
+{
+    type Type = &'a mut C;
+    type Value = <&'a mut Storage<C, D, I> as Join>::Value;
+    type Mask = &'a BitSet;
+
+    unsafe fn open(self) -> (Self::Mask, Self::Value) {
+        (&mut self.storage).open()
+    }
+
+    unsafe fn get(value: &mut Self::Value, id: Index) -> Self::Type {
+        <&'a mut Storage<C, D, I> as Join>::get(value, id)
+    }
+}
+
+/// The resource a `LocalStorage` is kept in, which remembers the thread that registered it.
+///
+/// It is only ever accessed on that thread, which `access` and `access_mut` assert. If it's
+/// dropped on another thread, the components are leaked instead of dropped there.
+pub(crate) struct LocalTiles<C, D: UnprotectedStorage<C>, I> {
+    owner: ThreadId,
+    storage: ManuallyDrop<LocalStorage<C, D, I>>,
+}
+
+// The storage is only handed out on the `owner` thread and never dropped on another one.
+unsafe impl<C, D: UnprotectedStorage<C>, I> Send for LocalTiles<C, D, I> {}
+unsafe impl<C, D: UnprotectedStorage<C>, I> Sync for LocalTiles<C, D, I> {}
+
+impl<C, D, I> LocalTiles<C, D, I>
+where
+    D: UnprotectedStorage<C>,
+{
+    pub(crate) fn new(storage: LocalStorage<C, D, I>) -> Self {
+        LocalTiles {
+            owner: thread::current().id(),
+            storage: ManuallyDrop::new(storage),
+        }
+    }
+
+    fn access(&self) -> &LocalStorage<C, D, I> {
+        self.check_thread();
+
+        &self.storage
+    }
+
+    fn access_mut(&mut self) -> &mut LocalStorage<C, D, I> {
+        self.check_thread();
+
+        &mut self.storage
+    }
+
+    fn check_thread(&self) {
+        assert!(
+            thread::current().id() == self.owner,
+            "thread-local tile component `{}` has been fetched on another thread than the one \
+             which registered it; add its systems with `with_thread_local`",
+            any::type_name::<C>()
+        );
+    }
+}
+
+impl<C, D, I> Drop for LocalTiles<C, D, I>
+where
+    D: UnprotectedStorage<C>,
+{
+    fn drop(&mut self) {
+        if thread::current().id() == self.owner {
+            unsafe {
+                ManuallyDrop::drop(&mut self.storage);
+            }
+        }
+    }
+}
+
+/// Fetches the `LocalStorage<C, D, I>` registered with `TileWorldExt::register_local_tile_comp`
+/// immutably.
+///
+/// Fetching it panics if the storage hasn't been registered, or on another thread than the
+/// one which registered it, so systems using it have to be added with `with_thread_local`.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use std::cell::Cell;
+/// use std::rc::Rc;
+///
+/// use specs::prelude::*;
+/// use specs_static::prelude::*;
+/// use specs_static::{ReadLocalTiles, WriteLocalTiles};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// /// A counter shared with code outside of the world, e.g. a renderer.
+/// struct Visits(Rc<Cell<u32>>);
+///
+/// struct Visit;
+///
+/// impl<'a> System<'a> for Visit {
+///     type SystemData = ReadLocalTiles<'a, Visits, VecStorage<Visits>, TileId>;
+///
+///     fn run(&mut self, visits: Self::SystemData) {
+///         for visits in (&*visits).join() {
+///             visits.0.set(visits.0.get() + 1);
+///         }
+///     }
+/// }
+///
+/// fn main() {
+///     let mut world = World::new();
+///     world.register_local_tile_comp::<Visits, VecStorage<Visits>, TileId>();
+///
+///     let counter = Rc::new(Cell::new(0));
+///     {
+///         let mut visits =
+///             world.system_data::<WriteLocalTiles<Visits, VecStorage<Visits>, TileId>>();
+///         visits.insert(TileId(2), Visits(counter.clone()));
+///         visits.insert(TileId(7), Visits(counter.clone()));
+///     }
+///
+///     let mut dispatcher = DispatcherBuilder::new()
+///         .with_thread_local(Visit)
+///         .build();
+///     dispatcher.setup(&mut world);
+///     dispatcher.dispatch(&world);
+///     dispatcher.dispatch(&world);
+///
+///     assert_eq!(counter.get(), 4);
+///     assert_eq!(Rc::strong_count(&counter), 3);
+///
+///     drop(world);
+///     assert_eq!(Rc::strong_count(&counter), 1);
+/// }
+/// ```
+///
+/// Fetching it on another thread panics:
+///
+/// ```should_panic
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use std::rc::Rc;
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// use specs::prelude::*;
+/// use specs_static::prelude::*;
+/// use specs_static::ReadLocalTiles;
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// struct Texture(Rc<String>);
+///
+/// fn main() {
+///     let mut world = World::new();
+///     world.register_local_tile_comp::<Texture, VecStorage<Texture>, TileId>();
+///
+///     let world = Arc::new(world);
+///     let remote = world.clone();
+///     let result = thread::spawn(move || {
+///         remote.system_data::<ReadLocalTiles<Texture, VecStorage<Texture>, TileId>>();
+///     })
+///     .join();
+///
+///     // Rethrow the other thread's panic.
+///     result.unwrap();
+/// }
+/// ```
+pub struct ReadLocalTiles<'a, C: 'a, D: UnprotectedStorage<C> + 'a, I: 'a> {
+    inner: Fetch<'a, LocalTiles<C, D, I>>,
+    phantom: PhantomData<*const ()>,
+}
+
+impl<'a, C, D, I> Deref for ReadLocalTiles<'a, C, D, I>
+where
+    C: 'static,
+    D: UnprotectedStorage<C> + 'static,
+    I: Id,
+{
+    type Target = LocalStorage<C, D, I>;
+
+    fn deref(&self) -> &LocalStorage<C, D, I> {
+        self.inner.access()
+    }
+}
+
+impl<'a, C, D, I> SystemData<'a> for ReadLocalTiles<'a, C, D, I>
+where
+    C: 'static,
+    D: UnprotectedStorage<C> + 'static,
+    I: Id,
+{
+    fn setup(_: &mut World) {}
+
+    fn fetch(res: &'a World) -> Self {
+        let inner = fetch_local::<C, D, I>(res);
+        inner.check_thread();
+
+        ReadLocalTiles {
+            inner,
+            phantom: PhantomData,
+        }
+    }
+
+    fn reads() -> Vec<ResourceId> {
+        vec![ResourceId::new::<LocalTiles<C, D, I>>()]
+    }
+
+    fn writes() -> Vec<ResourceId> {
+        vec![]
+    }
+}
+
+/// Fetches the `LocalStorage<C, D, I>` registered with `TileWorldExt::register_local_tile_comp`
+/// mutably.
+///
+/// Like `ReadLocalTiles`, fetching it panics if the storage hasn't been registered or on
+/// another thread than the one which registered it.
+pub struct WriteLocalTiles<'a, C: 'a, D: UnprotectedStorage<C> + 'a, I: 'a> {
+    inner: FetchMut<'a, LocalTiles<C, D, I>>,
+    phantom: PhantomData<*const ()>,
+}
+
+impl<'a, C, D, I> Deref for WriteLocalTiles<'a, C, D, I>
+where
+    C: 'static,
+    D: UnprotectedStorage<C> + 'static,
+    I: Id,
+{
+    type Target = LocalStorage<C, D, I>;
+
+    fn deref(&self) -> &LocalStorage<C, D, I> {
+        self.inner.access()
+    }
+}
+
+impl<'a, C, D, I> DerefMut for WriteLocalTiles<'a, C, D, I>
+where
+    C: 'static,
+    D: UnprotectedStorage<C> + 'static,
+    I: Id,
+{
+    fn deref_mut(&mut self) -> &mut LocalStorage<C, D, I> {
+        self.inner.access_mut()
+    }
+}
+
+impl<'a, C, D, I> SystemData<'a> for WriteLocalTiles<'a, C, D, I>
+where
+    C: 'static,
+    D: UnprotectedStorage<C> + 'static,
+    I: Id,
+{
+    fn setup(_: &mut World) {}
+
+    fn fetch(res: &'a World) -> Self {
+        fetch_local::<C, D, I>(res).check_thread();
+
+        WriteLocalTiles {
+            inner: res.fetch_mut(),
+            phantom: PhantomData,
+        }
+    }
+
+    fn reads() -> Vec<ResourceId> {
+        vec![]
+    }
+
+    fn writes() -> Vec<ResourceId> {
+        vec![ResourceId::new::<LocalTiles<C, D, I>>()]
+    }
+}
+
+/// Fetches the `LocalTiles` resource, panicking with a message naming `C` and `I` if it
+/// hasn't been registered.
+fn fetch_local<C, D, I>(res: &World) -> Fetch<'_, LocalTiles<C, D, I>>
+where
+    C: 'static,
+    D: UnprotectedStorage<C> + 'static,
+    I: Id,
+{
+    res.try_fetch().unwrap_or_else(|| {
+        panic!(
+            "thread-local tile component `{}` hasn't been registered for the id type `{}`; \
+             call `register_local_tile_comp` first",
+            any::type_name::<C>(),
+            any::type_name::<I>()
+        )
+    })
+}
@@ -0,0 +1,176 @@
+//! Aggregating blocks of a grid into the tiles of a coarser grid, for zoomed out views.
+
+use specs::storage::UnprotectedStorage;
+use specs::VecStorage;
+
+use {Grid2d, Id, Storage};
+
+/// Aggregates every `factor` x `factor` block of `src_grid` into one tile of a coarser grid,
+/// returning a storage keyed by the ids of that grid.
+///
+/// The coarse grid is `(src_grid.width() + factor - 1) / factor` tiles wide and
+/// `(src_grid.height() + factor - 1) / factor` tiles high. If the dimensions aren't
+/// divisible by `factor`, the blocks of the last column and row are smaller and only cover
+/// the tiles inside of `src_grid`.
+///
+/// `agg` is called once per block with the occupied components of the block, in row-major
+/// order (by `y`, then by `x`), and returns the aggregate, or `None` to leave the coarse
+/// tile vacant. It's called for empty blocks as well. Occupied ids outside of `src_grid`
+/// are ignored.
+///
+/// `average_by` and `majority` are ready-made aggregations.
+///
+/// # Panics
+///
+/// Panics if `factor` is 0.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::{Component, VecStorage};
+/// use specs_static::{average_by, downsample, majority, Grid2d, Storage};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// # specs_static::define_id!(pub struct CoarseId;);
+/// #[derive(Debug, PartialEq)]
+/// struct Elevation(f32);
+///
+/// impl Component for Elevation {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// #[derive(Clone, Debug, PartialEq)]
+/// enum Material {
+///     Grass,
+///     Rock,
+/// }
+///
+/// impl Component for Material {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// fn main() {
+///     // A 6x5 grid is aggregated into 2x2 blocks of 4x4, 2x4, 4x1 and 2x1 tiles.
+///     let grid = Grid2d::new(6, 5);
+///     let coarse = Grid2d::new(2, 2);
+///
+///     let mut elevation = Storage::<Elevation, VecStorage<_>, TileId>::default();
+///     let mut materials = Storage::<Material, VecStorage<_>, TileId>::default();
+///     for y in 0..5 {
+///         for x in 0..6 {
+///             let id = grid.id(x, y).unwrap();
+///             elevation.insert(id, Elevation((x + y) as f32));
+///             if x < 4 && y < 4 {
+///                 let material = if x == y { Material::Rock } else { Material::Grass };
+///                 materials.insert(id, material);
+///             }
+///         }
+///     }
+///     // A tie, which goes to the material seen first in row-major order.
+///     materials.insert(grid.id(4, 0).unwrap(), Material::Rock);
+///     materials.insert(grid.id(5, 0).unwrap(), Material::Grass);
+///
+///     let average: Storage<Elevation, _, CoarseId> =
+///         downsample(&elevation, &grid, 4, average_by(|e: &Elevation| e.0 as f64, |avg| {
+///             Elevation(avg as f32)
+///         }));
+///     let elevations: Vec<_> = (0..4).map(|id| average.get(CoarseId(id)).unwrap().0).collect();
+///     assert_eq!(elevations, vec![3.0, 6.0, 5.5, 8.5]);
+///
+///     let dominant: Storage<Material, _, CoarseId> = downsample(&materials, &grid, 4, majority);
+///     let at = |x, y| dominant.get(coarse.id(x, y).unwrap());
+///     assert_eq!(at(0, 0), Some(&Material::Grass));
+///     assert_eq!(at(1, 0), Some(&Material::Rock));
+///     // Blocks without components stay vacant.
+///     assert_eq!(at(0, 1), None);
+///     assert_eq!(at(1, 1), None);
+/// }
+/// ```
+pub fn downsample<C, D, I, C2, I2, F>(
+    src: &Storage<C, D, I>,
+    src_grid: &Grid2d,
+    factor: u32,
+    mut agg: F,
+) -> Storage<C2, VecStorage<C2>, I2>
+where
+    D: UnprotectedStorage<C>,
+    I: Id,
+    I2: Id,
+    F: FnMut(&mut dyn Iterator<Item = &C>) -> Option<C2>,
+{
+    assert!(factor != 0, "can't downsample by a factor of 0");
+
+    let (width, height) = (src_grid.width(), src_grid.height());
+    let coarse_width = width.div_ceil(factor);
+    let coarse_height = height.div_ceil(factor);
+
+    let mut out = Storage::default();
+    for cy in 0..coarse_height {
+        let ys = cy * factor..((cy + 1) * factor).min(height);
+        for cx in 0..coarse_width {
+            let xs = cx * factor..((cx + 1) * factor).min(width);
+            let mut block = ys
+                .clone()
+                .flat_map(|y| xs.clone().map(move |x| y * width + x))
+                .filter_map(|id| src.get(I::from_u32(id)));
+
+            if let Some(comp) = agg(&mut block) {
+                out.insert(I2::from_u32(cy * coarse_width + cx), comp);
+            }
+        }
+    }
+
+    out
+}
+
+/// Returns an aggregation for `downsample` which averages `value` over the occupied
+/// components of a block and converts the average with `make`. Empty blocks stay vacant.
+pub fn average_by<C, C2, V, M>(
+    value: V,
+    make: M,
+) -> impl FnMut(&mut dyn Iterator<Item = &C>) -> Option<C2>
+where
+    V: Fn(&C) -> f64,
+    M: Fn(f64) -> C2,
+{
+    move |block| {
+        let (sum, count) = block.fold((0.0, 0u32), |(sum, count), comp| {
+            (sum + value(comp), count + 1)
+        });
+
+        match count {
+            0 => None,
+            count => Some(make(sum / f64::from(count))),
+        }
+    }
+}
+
+/// An aggregation for `downsample` which picks the most common component of a block.
+///
+/// Ties go to the component which comes first in the row-major order of the block, so the
+/// result is deterministic. Components are only compared with `PartialEq`, which takes
+/// quadratic time in the amount of distinct components per block.
+pub fn majority<C>(block: &mut dyn Iterator<Item = &C>) -> Option<C>
+where
+    C: Clone + PartialEq,
+{
+    let mut counts: Vec<(&C, u32)> = Vec::new();
+    for comp in block {
+        match counts.iter_mut().find(|&&mut (seen, _)| seen == comp) {
+            Some(&mut (_, ref mut count)) => *count += 1,
+            None => counts.push((comp, 1)),
+        }
+    }
+
+    let mut best: Option<(&C, u32)> = None;
+    for (comp, count) in counts {
+        if best.is_none_or(|(_, most)| count > most) {
+            best = Some((comp, count));
+        }
+    }
+
+    best.map(|(comp, _)| comp.clone())
+}
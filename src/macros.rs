@@ -0,0 +1,541 @@
+//! Declaring tile components together with their storage and id type.
+
+/// Declares a tile component, its `Component` impl and the id type it's registered for.
+///
+/// The struct or enum is emitted as written, followed by a `Component` impl choosing the
+/// given storage and an inherent `register(world: &mut World)` function calling
+/// `TileWorldExt::register_tile_comp` with the given id type. Setup code which only uses
+/// `register` can't register the component for the wrong id type.
+///
+/// The storage is either the name of a backend like `VecStorage`, which is instantiated for
+/// the component, or a full type like `FlaggedStorage<Self, DenseVecStorage<Self>>`. Unit,
+/// tuple and named structs as well as enums are supported, generic ones aren't.
+///
+/// # Examples
+///
+/// ```
+/// #[macro_use]
+/// extern crate specs_static;
+/// extern crate specs;
+///
+/// use specs::{DenseVecStorage, FlaggedStorage, NullStorage, VecStorage, World, WorldExt};
+/// use specs_static::Storage;
+///
+/// # define_id!(pub struct TileId;);
+/// tile_component! {
+///     /// How wet a tile is.
+///     #[derive(Debug, PartialEq)]
+///     pub struct Moisture(pub f32);
+///     storage = VecStorage;
+///     id = TileId;
+/// }
+///
+/// tile_component! {
+///     pub struct Crop {
+///         pub age: u32,
+///     }
+///     storage = FlaggedStorage<Self, DenseVecStorage<Self>>;
+///     id = TileId;
+/// }
+///
+/// tile_component! {
+///     #[derive(Default)]
+///     struct Burning;
+///     storage = NullStorage;
+///     id = TileId;
+/// }
+///
+/// tile_component! {
+///     enum Biome {
+///         Desert,
+///         Forest { trees: u32 },
+///     }
+///     storage = VecStorage;
+///     id = TileId;
+/// }
+///
+/// fn main() {
+///     let mut world = World::new();
+///     Moisture::register(&mut world);
+///     Crop::register(&mut world);
+///     Burning::register(&mut world);
+///     Biome::register(&mut world);
+///
+///     let mut moisture = world.write_resource::<Storage<Moisture, VecStorage<_>, TileId>>();
+///     moisture.insert(TileId(3), Moisture(0.5));
+///     assert_eq!(moisture.get(TileId(3)), Some(&Moisture(0.5)));
+///
+///     type Crops = Storage<Crop, FlaggedStorage<Crop, DenseVecStorage<Crop>>, TileId>;
+///     assert!(world.has_value::<Crops>());
+///     assert!(world.has_value::<Storage<Burning, NullStorage<_>, TileId>>());
+///     assert!(world.has_value::<Storage<Biome, VecStorage<_>, TileId>>());
+/// }
+/// ```
+///
+/// Generic components are rejected:
+///
+/// ```compile_fail
+/// #[macro_use]
+/// extern crate specs_static;
+/// extern crate specs;
+///
+/// use specs::VecStorage;
+///
+/// # define_id!(pub struct TileId;);
+/// tile_component! {
+///     pub struct Wrapper<T>(T);
+///     storage = VecStorage;
+///     id = TileId;
+/// }
+///
+/// fn main() {}
+/// ```
+///
+/// So is leaving out the id type:
+///
+/// ```compile_fail
+/// #[macro_use]
+/// extern crate specs_static;
+/// extern crate specs;
+///
+/// use specs::VecStorage;
+///
+/// tile_component! {
+///     pub struct Moisture(pub f32);
+///     storage = VecStorage;
+/// }
+///
+/// fn main() {}
+/// ```
+#[cfg(feature = "specs")]
+#[macro_export]
+macro_rules! tile_component {
+    (@impl $name:ident; storage = $storage:ident; id = $id:ty;) => {
+        $crate::tile_component!(@impl $name; storage = $storage<Self>; id = $id;);
+    };
+    (@impl $name:ident; storage = $storage:ty; id = $id:ty;) => {
+        impl $crate::__private::Component for $name {
+            type Storage = $storage;
+        }
+
+        impl $name {
+            /// Registers the storage of this tile component for its id type.
+            #[allow(dead_code)]
+            pub fn register(world: &mut $crate::__private::World) {
+                $crate::TileWorldExt::register_tile_comp::<$name, $id>(world);
+            }
+        }
+    };
+    (@impl $name:ident; $($rest:tt)*) => {
+        compile_error!(concat!(
+            "expected `storage = <storage>; id = <id type>;` after the declaration of `",
+            stringify!($name),
+            "`"
+        ));
+    };
+    ($(#[$meta:meta])* $vis:vis struct $name:ident < $($rest:tt)*) => {
+        compile_error!("`tile_component!` doesn't support generic components");
+    };
+    ($(#[$meta:meta])* $vis:vis enum $name:ident < $($rest:tt)*) => {
+        compile_error!("`tile_component!` doesn't support generic components");
+    };
+    ($(#[$meta:meta])* $vis:vis struct $name:ident; $($rest:tt)*) => {
+        $(#[$meta])*
+        $vis struct $name;
+
+        $crate::tile_component!(@impl $name; $($rest)*);
+    };
+    ($(#[$meta:meta])* $vis:vis struct $name:ident ($($fields:tt)*); $($rest:tt)*) => {
+        $(#[$meta])*
+        $vis struct $name($($fields)*);
+
+        $crate::tile_component!(@impl $name; $($rest)*);
+    };
+    ($(#[$meta:meta])* $vis:vis struct $name:ident {$($fields:tt)*} $($rest:tt)*) => {
+        $(#[$meta])*
+        $vis struct $name {$($fields)*}
+
+        $crate::tile_component!(@impl $name; $($rest)*);
+    };
+    ($(#[$meta:meta])* $vis:vis enum $name:ident {$($variants:tt)*} $($rest:tt)*) => {
+        $(#[$meta])*
+        $vis enum $name {$($variants)*}
+
+        $crate::tile_component!(@impl $name; $($rest)*);
+    };
+    ($($rest:tt)*) => {
+        compile_error!(
+            "expected a struct or enum declaration followed by \
+             `storage = <storage>; id = <id type>;`"
+        );
+    };
+}
+
+/// Declares a newtype id over an unsigned integer, with the `Id` impl, the derives `Id`
+/// requires, `Debug` and a `Display` impl printing the number.
+///
+/// The inner type defaults to `u32`. A narrower one like `u16` halves the size of ids kept
+/// around in components or buffers; `Id::from_u32` panics for values which don't fit into
+/// it. Attributes, including doc comments and further derives, and the
+/// visibility are passed through.
+///
+/// With the `serde` feature, the id also implements `Serialize` and `Deserialize` as its
+/// bare number, without the crate declaring it depending on serde itself.
+///
+/// # Examples
+///
+/// ```
+/// #[macro_use]
+/// extern crate specs_static;
+/// extern crate specs;
+///
+/// use std::mem;
+///
+/// use specs::{Component, VecStorage};
+/// use specs_static::{Id, Storage};
+///
+/// define_id! {
+///     /// A room of the dungeon.
+///     pub struct RoomId;
+/// }
+///
+/// define_id! {
+///     #[derive(Default)]
+///     pub(crate) struct DoorId(u16);
+/// }
+///
+/// struct Lit;
+///
+/// impl Component for Lit {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// fn main() {
+///     let mut lit = Storage::<Lit, VecStorage<_>, RoomId>::default();
+///     lit.insert(RoomId(4), Lit);
+///     assert!(lit.get(RoomId::from_u32(4)).is_some());
+///     assert_eq!(RoomId(4).id(), 4);
+///     assert_eq!(format!("{} {:?}", RoomId(4), RoomId(4)), "4 RoomId(4)");
+///     assert!(RoomId(3) < RoomId(4));
+///
+///     assert_eq!(mem::size_of::<DoorId>(), 2);
+///     assert_eq!(DoorId::from_u32(65_535), DoorId(u16::MAX));
+///     assert_eq!(DoorId(7).id(), 7u32);
+///     assert_eq!(DoorId::default(), DoorId(0));
+/// }
+/// ```
+///
+/// Ids which don't fit into the inner type are rejected:
+///
+/// ```should_panic
+/// #[macro_use]
+/// extern crate specs_static;
+///
+/// use specs_static::Id;
+///
+/// define_id!(struct DoorId(u16););
+///
+/// fn main() {
+///     DoorId::from_u32(65_536);
+/// }
+/// ```
+///
+/// Serializing with the `serde` feature:
+///
+#[cfg_attr(feature = "serde", doc = "```")]
+#[cfg_attr(not(feature = "serde"), doc = "```ignore")]
+/// #[macro_use]
+/// extern crate specs_static;
+/// extern crate serde_json;
+///
+/// define_id!(pub struct RoomId;);
+/// define_id!(pub struct DoorId(u16););
+///
+/// fn main() {
+///     let json = serde_json::to_string(&(RoomId(4), DoorId(65_535))).unwrap();
+///     assert_eq!(json, "[4,65535]");
+///     let ids: (RoomId, DoorId) = serde_json::from_str(&json).unwrap();
+///     assert_eq!(ids, (RoomId(4), DoorId(65_535)));
+///
+///     // The inner type checks the range.
+///     assert!(serde_json::from_str::<DoorId>("65536").is_err());
+/// }
+/// ```
+#[macro_export]
+macro_rules! define_id {
+    ($(#[$meta:meta])* $vis:vis struct $name:ident;) => {
+        $crate::define_id!($(#[$meta])* $vis struct $name(u32););
+    };
+    ($(#[$meta:meta])* $vis:vis struct $name:ident ($inner:ty);) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+        $vis struct $name(pub $inner);
+
+        impl $crate::Id for $name {
+            fn from_u32(value: u32) -> Self {
+                match $crate::__private::TryFrom::try_from(value) {
+                    Ok(value) => $name(value),
+                    Err(_) => panic!(
+                        "the id {} doesn't fit into `{}`",
+                        value,
+                        stringify!($name)
+                    ),
+                }
+            }
+
+            fn id(&self) -> u32 {
+                u32::from(self.0)
+            }
+        }
+
+        impl $crate::__private::fmt::Display for $name {
+            fn fmt(
+                &self,
+                f: &mut $crate::__private::fmt::Formatter,
+            ) -> $crate::__private::fmt::Result {
+                $crate::__private::fmt::Display::fmt(&self.0, f)
+            }
+        }
+
+        $crate::__define_id_serde!($name, $inner);
+    };
+    ($($rest:tt)*) => {
+        compile_error!("expected a unit struct like `pub struct RoomId;` or `struct Id(u16);`");
+    };
+}
+
+/// Declares a struct of a tile component for a `SoaStorage`, together with its columns.
+///
+/// The struct is emitted as written, followed by the struct named after `columns =`, with
+/// a `SoaColumn` for every field under the same name and with the same visibility, and the
+/// `SoaComponent` impl connecting them. Only structs with named fields are supported. The
+/// types of the fields have to implement `Clone`, for assembling components from their
+/// fields, and `Default`, for the slots of vacant ids.
+///
+/// See `SoaStorage` for an example.
+///
+/// # Examples
+///
+/// Tuple structs are rejected:
+///
+/// ```compile_fail
+/// #[macro_use]
+/// extern crate specs_static;
+///
+/// soa_tile_component! {
+///     pub struct Wind(pub f32, pub f32);
+///     columns = WindColumns;
+/// }
+///
+/// fn main() {}
+/// ```
+#[cfg(feature = "specs")]
+#[macro_export]
+macro_rules! soa_tile_component {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $($(#[$field_meta:meta])* $field_vis:vis $field:ident : $ty:ty),* $(,)?
+        }
+        columns = $columns:ident;
+    ) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $($(#[$field_meta])* $field_vis $field: $ty),*
+        }
+
+        /// The columns of a component declared with `soa_tile_component!`.
+        #[derive(Default)]
+        $vis struct $columns {
+            $(
+                /// The column of a field, see `SoaColumn`.
+                $field_vis $field: $crate::SoaColumn<$ty>
+            ),*
+        }
+
+        impl $crate::SoaComponent for $name {
+            type Columns = $columns;
+
+            fn grow(columns: &mut $columns, len: usize) {
+                $(columns.$field.__grow(len);)*
+            }
+
+            fn read(columns: &$columns, index: usize) -> Self {
+                $name {
+                    $($field: ::std::clone::Clone::clone(&columns.$field[index])),*
+                }
+            }
+
+            fn replace(columns: &mut $columns, index: usize, comp: Self) -> Self {
+                $name {
+                    $($field: ::std::mem::replace(&mut columns.$field[index], comp.$field)),*
+                }
+            }
+
+            fn take(columns: &mut $columns, index: usize) -> Self {
+                $name {
+                    $($field: ::std::mem::take(&mut columns.$field[index])),*
+                }
+            }
+        }
+    };
+    ($($rest:tt)*) => {
+        compile_error!(
+            "expected a struct with named fields followed by `columns = <columns struct>;`"
+        );
+    };
+}
+
+/// Declares a struct of components which are inserted at a tile together, implementing
+/// `TileBundle` for it.
+///
+/// The struct is emitted as written, and inserting it inserts its fields as a tuple in the
+/// order they are declared in, so it goes wherever that tuple goes and returns the replaced
+/// components as a tuple of `Option`s. Only structs with named fields are supported.
+///
+/// # Examples
+///
+/// ```
+/// #[macro_use]
+/// extern crate specs_static;
+/// extern crate specs;
+///
+/// use specs::{NullStorage, VecStorage, World, WorldExt};
+/// use specs_static::{Id, Storage, TileBundle, TileGet, TileWorldExt};
+///
+/// # define_id!(pub struct TileId;);
+/// tile_component! {
+///     #[derive(Debug, PartialEq)]
+///     pub enum Material { Rock, Tree }
+///     storage = VecStorage;
+///     id = TileId;
+/// }
+///
+/// tile_component! {
+///     #[derive(Debug, PartialEq)]
+///     pub struct Flammable(pub f32);
+///     storage = VecStorage;
+///     id = TileId;
+/// }
+///
+/// tile_component! {
+///     #[derive(Debug, Default, PartialEq)]
+///     pub struct Blocking;
+///     storage = NullStorage;
+///     id = TileId;
+/// }
+///
+/// tile_bundle! {
+///     /// Everything a tree is made of.
+///     pub struct Tree {
+///         pub material: Material,
+///         pub flammable: Flammable,
+///         pub blocking: Blocking,
+///     }
+/// }
+///
+/// impl Tree {
+///     fn new() -> Self {
+///         Tree { material: Material::Tree, flammable: Flammable(0.8), blocking: Blocking }
+///     }
+/// }
+///
+/// fn main() {
+///     let mut world = World::new();
+///     Material::register(&mut world);
+///     Flammable::register(&mut world);
+///     Blocking::register(&mut world);
+///
+///     // A boulder, which blocks but doesn't burn.
+///     (Material::Rock, Blocking).insert_into(&world, TileId(2));
+///     assert_eq!(
+///         Tree::new().insert_into(&world, TileId(2)),
+///         (Some(Material::Rock), None, Some(Blocking))
+///     );
+///
+///     let mut materials = world.write_resource::<Storage<Material, VecStorage<_>, TileId>>();
+///     let mut flammables = world.write_resource::<Storage<Flammable, VecStorage<_>, TileId>>();
+///     let mut blocking = world.write_resource::<Storage<Blocking, NullStorage<_>, TileId>>();
+///     let storages = (&mut *materials, &mut *flammables, &mut *blocking);
+///     assert_eq!(Tree::new().insert_into(storages, TileId(3)), (None, None, None));
+///     assert_eq!(
+///         (&*materials, &*flammables, &*blocking).get_all(TileId(3)),
+///         Some((&Material::Tree, &Flammable(0.8), &Blocking))
+///     );
+/// }
+/// ```
+///
+/// Tuple structs are rejected:
+///
+/// ```compile_fail
+/// #[macro_use]
+/// extern crate specs_static;
+///
+/// tile_bundle! {
+///     pub struct Tree(pub u8, pub f32);
+/// }
+///
+/// fn main() {}
+/// ```
+#[cfg(feature = "specs")]
+#[macro_export]
+macro_rules! tile_bundle {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $($(#[$field_meta:meta])* $field_vis:vis $field:ident : $ty:ty),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $($(#[$field_meta])* $field_vis $field: $ty),*
+        }
+
+        impl<__Target, __Id> $crate::TileBundle<__Target, __Id> for $name
+        where
+            ($($ty,)*): $crate::TileBundle<__Target, __Id>,
+        {
+            type Old = <($($ty,)*) as $crate::TileBundle<__Target, __Id>>::Old;
+
+            fn insert_into(self, target: __Target, id: __Id) -> Self::Old {
+                $crate::TileBundle::insert_into(($(self.$field,)*), target, id)
+            }
+        }
+    };
+    ($($rest:tt)*) => {
+        compile_error!("expected a struct with named fields");
+    };
+}
+
+#[cfg(feature = "serde")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __define_id_serde {
+    ($name:ident, $inner:ty) => {
+        impl $crate::__private::Serialize for $name {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: $crate::__private::Serializer,
+            {
+                $crate::__private::Serialize::serialize(&self.0, serializer)
+            }
+        }
+
+        impl<'de> $crate::__private::Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: $crate::__private::Deserializer<'de>,
+            {
+                <$inner as $crate::__private::Deserialize>::deserialize(deserializer).map($name)
+            }
+        }
+    };
+}
+
+// Without the `serde` feature, `define_id!` emits no serde impls.
+#[cfg(not(feature = "serde"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __define_id_serde {
+    ($name:ident, $inner:ty) => {};
+}
@@ -0,0 +1,184 @@
+//! Reading a coarse storage through the ids of a finer one.
+
+use std::marker::PhantomData;
+
+use hibitset::BitSetAll;
+use specs::storage::UnprotectedStorage;
+use specs::Join;
+
+use {Grid2d, Id, IdSpace, Storage};
+
+/// Borrows `storage`, which is keyed by the coarse ids `Co`, as a storage keyed by the fine
+/// ids `Fi`, with `mapping` returning the coarse id of a fine one.
+///
+/// The view can be joined together with fine storages: it doesn't restrict the join, like
+/// `Join::maybe`, and yields the component of the coarse tile if there is one. It has to be
+/// joined with at least one storage which does restrict the join. `Grid2d::downsample_map`
+/// returns the mapping for blocks of a grid.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::{Component, Join, VecStorage};
+/// use specs_static::{mapped_join, Grid2d, Storage, TaggedId};
+///
+/// struct Blocks;
+/// struct Tiles;
+/// type BlockId = TaggedId<Blocks>;
+/// type TileId = TaggedId<Tiles>;
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Climate(&'static str);
+///
+/// impl Component for Climate {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// struct Crop(u32);
+///
+/// impl Component for Crop {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// fn main() {
+///     // 20x12 tiles in blocks of 8x8, so the last blocks are partial.
+///     let tiles = Grid2d::new(20, 12).tagged::<Tiles>();
+///     let blocks = tiles.downsampled(8).tagged::<Blocks>();
+///     assert_eq!((blocks.width(), blocks.height()), (3, 2));
+///
+///     let mut climates = Storage::<Climate, VecStorage<_>, BlockId>::default();
+///     climates.insert(blocks.id(0, 0).unwrap(), Climate("temperate"));
+///     climates.insert(blocks.id(2, 1).unwrap(), Climate("arid"));
+///
+///     let climate = mapped_join(&climates, tiles.downsample_map::<TileId, BlockId>(8));
+///     assert_eq!(climate.get(tiles.id(7, 7).unwrap()), Some(&Climate("temperate")));
+///     assert_eq!(climate.get(tiles.id(8, 7).unwrap()), None);
+///     assert_eq!(climate.get(tiles.id(16, 8).unwrap()), Some(&Climate("arid")));
+///     assert_eq!(climate.get(tiles.id(19, 11).unwrap()), Some(&Climate("arid")));
+///     assert_eq!(climate.get(tiles.id(15, 11).unwrap()), None);
+///
+///     let mut crops = Storage::<Crop, VecStorage<_>, TileId>::default();
+///     for &(x, y) in &[(0, 0), (7, 0), (8, 0), (19, 11)] {
+///         crops.insert(tiles.id(x, y).unwrap(), Crop(0));
+///     }
+///     for (crop, climate) in (&mut crops, &climate).join() {
+///         crop.0 = match climate {
+///             Some(&Climate("temperate")) => 3,
+///             Some(_) => 1,
+///             None => 2,
+///         };
+///     }
+///     let grown: Vec<u32> = (&crops).join().map(|crop| crop.0).collect();
+///     assert_eq!(grown, vec![3, 3, 2, 1]);
+/// }
+/// ```
+pub fn mapped_join<'a, C, D, Co, Fi, M>(
+    storage: &'a Storage<C, D, Co>,
+    mapping: M,
+) -> MappedView<'a, C, D, Co, Fi, M>
+where
+    D: UnprotectedStorage<C>,
+    Co: Id,
+    Fi: Id,
+    M: Fn(Fi) -> Co,
+{
+    MappedView {
+        storage,
+        mapping,
+        phantom: PhantomData,
+    }
+}
+
+/// A storage keyed by coarse ids, read through fine ids. Created with `mapped_join`.
+pub struct MappedView<'a, C: 'a, D: UnprotectedStorage<C> + 'a, Co: 'a, Fi, M> {
+    storage: &'a Storage<C, D, Co>,
+    mapping: M,
+    phantom: PhantomData<Fi>,
+}
+
+impl<'a, C, D, Co, Fi, M> MappedView<'a, C, D, Co, Fi, M>
+where
+    D: UnprotectedStorage<C>,
+    Co: Id,
+    Fi: Id,
+    M: Fn(Fi) -> Co,
+{
+    /// Returns the component of the coarse tile `fine` belongs to.
+    pub fn get(&self, fine: Fi) -> Option<&'a C> {
+        self.storage.get((self.mapping)(fine))
+    }
+
+    /// Returns the coarse id `fine` belongs to.
+    pub fn map(&self, fine: Fi) -> Co {
+        (self.mapping)(fine)
+    }
+
+    /// Returns the coarse storage.
+    pub fn storage(&self) -> &'a Storage<C, D, Co> {
+        self.storage
+    }
+}
+
+impl<'a, 'b, C, D, Co, Fi, M> Join for &'b MappedView<'a, C, D, Co, Fi, M>
+where
+    D: UnprotectedStorage<C>,
+    Co: Id,
+    Fi: Id,
+    M: Fn(Fi) -> Co,
+{
+    type Type = Option<&'a C>;
+    type Value = &'b MappedView<'a, C, D, Co, Fi, M>;
+    type Mask = BitSetAll;
+
+    unsafe fn open(self) -> (Self::Mask, Self::Value) {
+        (BitSetAll, self)
+    }
+
+    unsafe fn get(view: &mut Self::Value, id: u32) -> Option<&'a C> {
+        view.get(Fi::from_u32(id))
+    }
+}
+
+impl<S: ?Sized> Grid2d<S> {
+    /// Returns the grid of the `factor * factor` blocks of this grid. Blocks at the right and
+    /// bottom edges are partial if the dimensions aren't multiples of `factor`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `factor` is zero.
+    pub fn downsampled(&self, factor: u32) -> Grid2d {
+        assert!(factor > 0, "can't downsample a grid by a factor of zero");
+
+        let blocks = |len: u32| match len {
+            0 => 0,
+            len => (len - 1) / factor + 1,
+        };
+
+        Grid2d::new(blocks(self.width()), blocks(self.height()))
+    }
+
+    /// Returns a mapping from the ids of this grid to the ids of its `downsampled(factor)`
+    /// grid, for `mapped_join`. Ids past the end of this grid are mapped as if it had more
+    /// rows.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `factor` is zero.
+    pub fn downsample_map<Fi, Co>(&self, factor: u32) -> impl Fn(Fi) -> Co + Copy + Send + Sync
+    where
+        Fi: IdSpace<S>,
+        Co: Id,
+    {
+        let coarse_width = self.downsampled(factor).width();
+        let width = self.width();
+
+        move |fine: Fi| {
+            let (x, y) = (fine.id() % width, fine.id() / width);
+
+            Co::from_u32(y / factor * coarse_width + x / factor)
+        }
+    }
+}
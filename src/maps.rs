@@ -0,0 +1,330 @@
+//! Several maps sharing one id space.
+
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+
+use mask::BITS;
+use {Grid2d, Id, RangeMask};
+
+/// The amount of bits a storage can address, which are split between the map and the tile
+/// index of a `CompositeId`.
+const ID_BITS: u32 = BITS as u32 * 4;
+
+/// An id combining a map handle `M` in its high bits with the tile id `T` inside of that map
+/// in its low bits, so the storages keyed with it hold the tiles of all maps.
+///
+/// How many bits the tile id gets is decided by the `MapRegistry` which creates the ids.
+pub struct CompositeId<M, T> {
+    value: u32,
+    phantom: PhantomData<fn() -> (M, T)>,
+}
+
+impl<M, T> Id for CompositeId<M, T>
+where
+    M: 'static,
+    T: 'static,
+{
+    fn from_u32(value: u32) -> Self {
+        CompositeId {
+            value,
+            phantom: PhantomData,
+        }
+    }
+
+    fn id(&self) -> u32 {
+        self.value
+    }
+}
+
+impl<M, T> Clone for CompositeId<M, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<M, T> Copy for CompositeId<M, T> {}
+
+impl<M, T> fmt::Debug for CompositeId<M, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("CompositeId").field(&self.value).finish()
+    }
+}
+
+impl<M, T> PartialEq for CompositeId<M, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl<M, T> Eq for CompositeId<M, T> {}
+
+impl<M, T> PartialOrd for CompositeId<M, T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<M, T> Ord for CompositeId<M, T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.value.cmp(&other.value)
+    }
+}
+
+impl<M, T> Hash for CompositeId<M, T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.value.hash(state);
+    }
+}
+
+/// The error returned when a `MapRegistry` can't add a map.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum MapRegistryError {
+    /// The map has more tiles than the tile bits of an id can hold.
+    MapTooLarge {
+        /// The width of the map.
+        width: u32,
+        /// The height of the map.
+        height: u32,
+        /// The most tiles a map can have.
+        max_tiles: u32,
+    },
+    /// All map slots are taken.
+    OutOfSlots {
+        /// The amount of slots.
+        max_maps: u32,
+    },
+}
+
+impl fmt::Display for MapRegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MapRegistryError::MapTooLarge {
+                width,
+                height,
+                max_tiles,
+            } => write!(
+                f,
+                "a {}x{} map exceeds the maximum of {} tiles per map",
+                width, height, max_tiles
+            ),
+            MapRegistryError::OutOfSlots { max_maps } => {
+                write!(f, "all {} map slots are taken", max_maps)
+            }
+        }
+    }
+}
+
+impl Error for MapRegistryError {}
+
+/// The resource assigning the maps of a world their slots in the `CompositeId<M, T>` space,
+/// and recording their dimensions.
+///
+/// Every map gets a contiguous block of `2^tile_bits` ids, so `mask` can restrict a join to
+/// one map, while a plain join goes over all of them. Slots of removed maps are reused.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::{Component, Join, VecStorage};
+/// use specs_static::{CompositeId, Grid2d, MapRegistry, MapRegistryError, Storage};
+///
+/// # specs_static::define_id!(pub struct MapId;);
+/// # specs_static::define_id!(pub struct TileId;);
+/// type WorldTile = CompositeId<MapId, TileId>;
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Light(u8);
+///
+/// impl Component for Light {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// fn main() {
+///     // 16 tile bits leave 8 bits for map slots.
+///     let mut maps = MapRegistry::<MapId, TileId>::new(16);
+///     assert_eq!(maps.max_maps(), 256);
+///     let overworld = maps.add_map(Grid2d::new(256, 256)).unwrap();
+///     let cellar = maps.add_map(Grid2d::new(8, 8)).unwrap();
+///     assert_eq!(
+///         maps.add_map(Grid2d::new(512, 512)),
+///         Err(MapRegistryError::MapTooLarge { width: 512, height: 512, max_tiles: 65536 })
+///     );
+///
+///     let mut lights = Storage::<Light, VecStorage<_>, WorldTile>::default();
+///     let torch = maps.id(cellar, 3, 4).unwrap();
+///     lights.insert(torch, Light(9));
+///     lights.insert(maps.id(overworld, 100, 0).unwrap(), Light(2));
+///     lights.insert(maps.id(overworld, 7, 7).unwrap(), Light(3));
+///
+///     assert_eq!(maps.map_of(torch), cellar);
+///     assert_eq!(maps.tile_of(torch), TileId(35));
+///     assert_eq!(maps.coords(torch), Some((cellar, 3, 4)));
+///     assert_eq!(maps.id(cellar, 8, 0), None);
+///
+///     // All maps at once, or just one.
+///     assert_eq!((&lights).join().count(), 3);
+///     let overworld_lights: Vec<_> = (&lights, maps.mask(overworld).unwrap())
+///         .join()
+///         .map(|(light, _)| light.0)
+///         .collect();
+///     assert_eq!(overworld_lights, vec![2, 3]);
+///
+///     // The slot of a removed map is handed out again.
+///     maps.remove_map(overworld);
+///     assert_eq!(maps.grid(overworld), None);
+///     assert_eq!(maps.maps().collect::<Vec<_>>(), vec![(cellar, Grid2d::new(8, 8))]);
+///     assert_eq!(maps.add_map(Grid2d::new(4, 4)), Ok(overworld));
+///
+///     // Giving all bits to the tiles leaves one slot.
+///     let mut single = MapRegistry::<MapId, TileId>::new(24);
+///     assert_eq!(single.add_map(Grid2d::new(4096, 4096)), Ok(MapId(0)));
+///     assert_eq!(
+///         single.add_map(Grid2d::new(1, 1)),
+///         Err(MapRegistryError::OutOfSlots { max_maps: 1 })
+///     );
+/// }
+/// ```
+pub struct MapRegistry<M, T> {
+    tile_bits: u32,
+    grids: Vec<Option<Grid2d>>,
+    phantom: PhantomData<fn() -> (M, T)>,
+}
+
+impl<M, T> MapRegistry<M, T>
+where
+    M: Id,
+    T: Id,
+{
+    /// Creates a registry giving the tile ids the `tile_bits` low bits of a `CompositeId`,
+    /// and the map slots the remaining bits a storage can address.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tile_bits` exceeds the bits a storage can address, which are 24 on 64-bit
+    /// targets and 20 on 32-bit ones.
+    pub fn new(tile_bits: u32) -> Self {
+        assert!(
+            tile_bits <= ID_BITS,
+            "{} tile bits exceed the {} bits of an id",
+            tile_bits,
+            ID_BITS
+        );
+
+        MapRegistry {
+            tile_bits,
+            grids: Vec::new(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns the amount of bits of the tile id.
+    pub fn tile_bits(&self) -> u32 {
+        self.tile_bits
+    }
+
+    /// Returns the most tiles a map can have.
+    pub fn max_tiles(&self) -> u32 {
+        1 << self.tile_bits
+    }
+
+    /// Returns the most maps the registry can hold at once.
+    pub fn max_maps(&self) -> u32 {
+        1 << (ID_BITS - self.tile_bits)
+    }
+
+    /// Adds a map with the dimensions of `grid` in the lowest free slot.
+    pub fn add_map(&mut self, grid: Grid2d) -> Result<M, MapRegistryError> {
+        if grid.len() > self.max_tiles() {
+            return Err(MapRegistryError::MapTooLarge {
+                width: grid.width(),
+                height: grid.height(),
+                max_tiles: self.max_tiles(),
+            });
+        }
+
+        let slot = match self.grids.iter().position(Option::is_none) {
+            Some(slot) => slot,
+            None if (self.grids.len() as u32) < self.max_maps() => {
+                self.grids.push(None);
+                self.grids.len() - 1
+            }
+            None => {
+                return Err(MapRegistryError::OutOfSlots {
+                    max_maps: self.max_maps(),
+                })
+            }
+        };
+        self.grids[slot] = Some(grid);
+
+        Ok(M::from_u32(slot as u32))
+    }
+
+    /// Frees the slot of `map`, returning its grid.
+    ///
+    /// The components of the map aren't removed from the storages; that's up to the caller,
+    /// for example by removing everything in `mask(map)` first.
+    pub fn remove_map(&mut self, map: M) -> Option<Grid2d> {
+        self.grids
+            .get_mut(map.id() as usize)
+            .and_then(|grid| grid.take())
+    }
+
+    /// Returns the grid of `map`, or `None` if there is no such map.
+    pub fn grid(&self, map: M) -> Option<Grid2d> {
+        self.grids.get(map.id() as usize).and_then(|&grid| grid)
+    }
+
+    /// Yields all maps with their grids, in slot order.
+    pub fn maps<'a>(&'a self) -> impl Iterator<Item = (M, Grid2d)> + 'a {
+        self.grids
+            .iter()
+            .enumerate()
+            .filter_map(|(slot, grid)| grid.map(|grid| (M::from_u32(slot as u32), grid)))
+    }
+
+    /// Returns the id of the tile at `(x, y)` of `map`, or `None` if there is no such map or
+    /// the coordinates are outside of it.
+    pub fn id(&self, map: M, x: u32, y: u32) -> Option<CompositeId<M, T>> {
+        let tile: T = self.grid(map)?.id(x, y)?;
+
+        Some(self.compose(map, tile))
+    }
+
+    /// Combines `map` and the id of a tile inside of it, without checking either.
+    pub fn compose(&self, map: M, tile: T) -> CompositeId<M, T> {
+        CompositeId::from_u32(map.id() << self.tile_bits | tile.id())
+    }
+
+    /// Returns the map `id` belongs to.
+    pub fn map_of(&self, id: CompositeId<M, T>) -> M {
+        M::from_u32(id.value >> self.tile_bits)
+    }
+
+    /// Returns the id of the tile inside of its map.
+    pub fn tile_of(&self, id: CompositeId<M, T>) -> T {
+        T::from_u32(id.value & (self.max_tiles() - 1))
+    }
+
+    /// Returns the map of `id` and the coordinates inside of it, or `None` if there is no
+    /// such map or tile.
+    pub fn coords(&self, id: CompositeId<M, T>) -> Option<(M, u32, u32)> {
+        let map = self.map_of(id);
+        let (x, y) = self.grid(map)?.coords(self.tile_of(id))?;
+
+        Some((map, x, y))
+    }
+
+    /// Returns the mask of the ids of `map`, which restricts a join to that map.
+    pub fn mask(&self, map: M) -> Option<RangeMask> {
+        let grid = self.grid(map)?;
+        let start = map.id() << self.tile_bits;
+
+        Some(RangeMask::from_range(start, start + grid.len()))
+    }
+}
@@ -0,0 +1,188 @@
+//! Masks which are computed instead of stored.
+
+#[cfg(feature = "specs")]
+use hibitset::BitSet;
+#[cfg(feature = "parallel")]
+use specs::ParJoin;
+#[cfg(feature = "specs")]
+use specs::Join;
+
+use {BitSetLike, Index};
+
+/// Base two log of the number of bits in a `usize`.
+#[cfg(target_pointer_width = "64")]
+pub(crate) const BITS: usize = 6;
+#[cfg(target_pointer_width = "32")]
+pub(crate) const BITS: usize = 5;
+
+/// The highest amount of ids a `BitSetLike` can describe.
+pub(crate) const MAX_LEN: u64 = 1 << (BITS * 4);
+
+/// A mask containing every index in `start..end` without storing any bits.
+///
+/// Every layer word is computed on demand, so joining against it is as cheap as walking a
+/// plain index range, and or-ing it into a `BitSet` sets whole words at once.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct RangeMask {
+    start: u32,
+    end: u32,
+}
+
+impl RangeMask {
+    /// Creates a mask of all indices in `0..len`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` exceeds the capacity of a hierarchical bitset.
+    pub fn new(len: u32) -> Self {
+        RangeMask::from_range(0, len)
+    }
+
+    /// Creates a mask of all indices in `start..end`. If `end <= start`, the mask is empty.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `end` exceeds the capacity of a hierarchical bitset.
+    pub fn from_range(start: u32, end: u32) -> Self {
+        assert!(
+            u64::from(end) <= MAX_LEN,
+            "`RangeMask` end {} exceeds the maximum of {}",
+            end,
+            MAX_LEN
+        );
+
+        RangeMask {
+            start,
+            end: end.max(start),
+        }
+    }
+
+    /// Returns the first index of this mask.
+    pub fn start(&self) -> u32 {
+        self.start
+    }
+
+    /// Returns the index one past the last index of this mask.
+    pub fn end(&self) -> u32 {
+        self.end
+    }
+
+    /// Returns the amount of indices in this mask.
+    pub fn len(&self) -> u32 {
+        self.end - self.start
+    }
+
+    /// Returns `true` if this mask doesn't contain any index.
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Computes word `i` of the layer with the given level.
+    pub(crate) fn word(&self, level: usize, i: usize) -> usize {
+        if self.is_empty() {
+            return 0;
+        }
+
+        let shift = BITS * level;
+        let first = (self.start >> shift) as usize;
+        let last = ((self.end - 1) >> shift) as usize;
+        let base = i << BITS;
+        let top = base + (1 << BITS) - 1;
+
+        if first > top || last < base {
+            return 0;
+        }
+
+        let from = first.max(base) - base;
+        let to = last.min(top) - base;
+
+        (!0 >> ((1 << BITS) - 1 - to)) & (!0 << from)
+    }
+}
+
+impl BitSetLike for RangeMask {
+    fn layer3(&self) -> usize {
+        self.word(3, 0)
+    }
+
+    fn layer2(&self, i: usize) -> usize {
+        self.word(2, i)
+    }
+
+    fn layer1(&self, i: usize) -> usize {
+        self.word(1, i)
+    }
+
+    fn layer0(&self, i: usize) -> usize {
+        self.word(0, i)
+    }
+
+    fn contains(&self, i: Index) -> bool {
+        self.start <= i && i < self.end
+    }
+}
+
+/// Joining over a `RangeMask` yields the indices in it, and restricts a join to them.
+#[cfg(feature = "specs")]
+impl Join for RangeMask {
+    type Type = Index;
+    type Value = ();
+    type Mask = RangeMask;
+
+    unsafe fn open(self) -> (RangeMask, ()) {
+        (self, ())
+    }
+
+    unsafe fn get(_: &mut (), id: Index) -> Index {
+        id
+    }
+}
+
+#[cfg(feature = "parallel")]
+unsafe impl ParJoin for RangeMask {}
+
+/// A run of ids which have been written to a backend, but not yet marked in its bitset.
+///
+/// The run is or-ed into the bitset whenever it's interrupted and when the guard is dropped,
+/// so bulk insertions set whole words at once and still leave the bitset consistent with the
+/// backend if they panic halfway through.
+#[cfg(feature = "specs")]
+pub(crate) struct PendingRun<'a> {
+    pub(crate) bitset: &'a mut BitSet,
+    start: Index,
+    end: Index,
+}
+
+#[cfg(feature = "specs")]
+impl<'a> PendingRun<'a> {
+    pub(crate) fn new(bitset: &'a mut BitSet) -> Self {
+        PendingRun {
+            bitset,
+            start: 0,
+            end: 0,
+        }
+    }
+
+    /// Adds `id`, which has just been written to the backend, to the run.
+    pub(crate) fn push(&mut self, id: Index) {
+        if id != self.end {
+            self.flush();
+            self.start = id;
+        }
+        self.end = id + 1;
+    }
+
+    fn flush(&mut self) {
+        if self.start < self.end {
+            *self.bitset |= &RangeMask::from_range(self.start, self.end);
+            self.start = self.end;
+        }
+    }
+}
+
+#[cfg(feature = "specs")]
+impl<'a> Drop for PendingRun<'a> {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
@@ -0,0 +1,201 @@
+//! Mirroring tile components onto the entities tiles are linked to.
+
+use std::marker::PhantomData;
+
+use hibitset::{BitSet, BitSetLike};
+use shred::{Read, World, System, SystemData, Write};
+use shrev::ReaderId;
+use specs::storage::ComponentEvent;
+use specs::{Component, Entities, Tracked, WriteStorage};
+
+use {Id, Storage, TileEntityMap};
+
+/// A system copying the components of the tracked storage `Storage<C, C::Storage, I>` onto
+/// the entities the tiles are linked to by the `TileEntityMap<I>` resource, for systems which
+/// only understand entities.
+///
+/// Only the ids named by change events since the last run are looked at. For each of them,
+/// the current component of the tile is cloned into the `WriteStorage<C>` of the linked
+/// entity, or removed from it if the tile has none; the entity itself is never deleted.
+/// Tiles without a living linked entity are skipped, unless `create_missing` is set, in
+/// which case a proxy entity is created and linked.
+///
+/// Its reader is registered in `System::setup`, so components inserted before that are
+/// not mirrored.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::prelude::*;
+/// use specs_static::{Storage, TileEntityMap, TileToEntitySync, TileWorldExt};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// #[derive(Clone, Debug, PartialEq)]
+/// struct Collider(u8);
+///
+/// impl Component for Collider {
+///     type Storage = FlaggedStorage<Self, VecStorage<Self>>;
+/// }
+///
+/// type Colliders = Storage<Collider, FlaggedStorage<Collider, VecStorage<Collider>>, TileId>;
+///
+/// /// Checks that the linked entities hold exactly the components of their tiles.
+/// fn assert_converged(world: &World) {
+///     let tiles = world.read_resource::<Colliders>();
+///     let links = world.read_resource::<TileEntityMap<TileId>>();
+///     let entities = world.read_storage::<Collider>();
+///     for (id, entity) in links.iter() {
+///         assert_eq!(entities.get(entity), tiles.get(id));
+///     }
+///     for id in 0..64 {
+///         if tiles.get(TileId(id)).is_some() {
+///             assert!(links.entity_at(TileId(id)).is_some());
+///         }
+///     }
+/// }
+///
+/// fn main() {
+///     let mut world = World::new();
+///     world.register::<Collider>();
+///     world.register_tile_comp::<Collider, TileId>();
+///
+///     // Some proxies are created up front.
+///     let gate = world.create_entity().build();
+///     let mut links = TileEntityMap::<TileId>::new();
+///     links.link(TileId(7), gate);
+///     world.insert(links);
+///
+///     let sync = TileToEntitySync::<Collider, TileId>::new().create_missing(true);
+///     let mut dispatcher = DispatcherBuilder::new().with(sync, "colliders", &[]).build();
+///     dispatcher.setup(&mut world);
+///
+///     {
+///         let mut tiles = world.write_resource::<Colliders>();
+///         for id in 0..8 {
+///             tiles.insert(TileId(id), Collider(id as u8));
+///         }
+///     }
+///     dispatcher.dispatch(&world);
+///     world.maintain();
+///     assert_converged(&world);
+///     assert_eq!(world.read_storage::<Collider>().get(gate), Some(&Collider(7)));
+///     assert_eq!(world.read_resource::<TileEntityMap<TileId>>().len(), 8);
+///
+///     {
+///         let mut tiles = world.write_resource::<Colliders>();
+///         tiles.get_mut(TileId(7)).unwrap().0 = 70;
+///         tiles.remove(TileId(3));
+///         tiles.remove(TileId(5));
+///         tiles.insert(TileId(5), Collider(50));
+///         tiles.insert(TileId(40), Collider(40));
+///     }
+///     dispatcher.dispatch(&world);
+///     world.maintain();
+///     assert_converged(&world);
+///     assert_eq!(world.read_storage::<Collider>().get(gate), Some(&Collider(70)));
+///     assert_eq!(world.read_storage::<Collider>().join().count(), 8);
+/// }
+/// ```
+pub struct TileToEntitySync<C, I> {
+    create_missing: bool,
+    reader: Option<ReaderId<ComponentEvent>>,
+    changed: BitSet,
+    phantom: PhantomData<(C, I)>,
+}
+
+impl<C, I> TileToEntitySync<C, I>
+where
+    I: Id,
+{
+    /// Creates a system which only mirrors onto entities which are already linked.
+    pub fn new() -> Self {
+        TileToEntitySync {
+            create_missing: false,
+            reader: None,
+            changed: BitSet::new(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Sets whether tiles which gain a component but have no living linked entity get a new
+    /// proxy entity.
+    pub fn create_missing(mut self, create: bool) -> Self {
+        self.create_missing = create;
+
+        self
+    }
+}
+
+impl<C, I> Default for TileToEntitySync<C, I>
+where
+    I: Id,
+{
+    fn default() -> Self {
+        TileToEntitySync::new()
+    }
+}
+
+impl<'a, C, I> System<'a> for TileToEntitySync<C, I>
+where
+    C: Component + Clone + Send + Sync,
+    C::Storage: Default + Tracked,
+    I: Id,
+{
+    type SystemData = (
+        Read<'a, Storage<C, C::Storage, I>>,
+        Write<'a, TileEntityMap<I>>,
+        Entities<'a>,
+        WriteStorage<'a, C>,
+    );
+
+    fn run(&mut self, (tiles, mut links, entities, mut comps): Self::SystemData) {
+        let reader = self
+            .reader
+            .as_mut()
+            .expect("`TileToEntitySync` has to be set up before it runs");
+
+        // Only the latest state of an id matters, however many events it had.
+        self.changed.clear();
+        for event in tiles.channel().read(reader) {
+            match *event {
+                ComponentEvent::Inserted(id)
+                | ComponentEvent::Modified(id)
+                | ComponentEvent::Removed(id) => self.changed.add(id),
+            };
+        }
+
+        for raw in (&self.changed).iter() {
+            let id = I::from_u32(raw);
+            let linked = links
+                .entity_at(id)
+                .filter(|&entity| entities.is_alive(entity));
+
+            match (tiles.get(id), linked) {
+                (Some(comp), Some(entity)) => {
+                    comps.insert(entity, comp.clone()).expect("the entity is alive");
+                }
+                (Some(comp), None) if self.create_missing => {
+                    let entity = entities.create();
+                    links.link(id, entity);
+                    comps.insert(entity, comp.clone()).expect("the entity is alive");
+                }
+                (None, Some(entity)) => {
+                    comps.remove(entity);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn setup(&mut self, res: &mut World) {
+        Self::SystemData::setup(res);
+        self.reader = Some(
+            res.fetch_mut::<Storage<C, C::Storage, I>>()
+                .channel_mut()
+                .register_reader(),
+        );
+    }
+}
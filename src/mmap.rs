@@ -0,0 +1,333 @@
+//! Read-only storages living in memory-mapped files.
+//!
+//! # File layout
+//!
+//! | Offset | Size               | Content                                               |
+//! |--------|--------------------|-------------------------------------------------------|
+//! | 0      | 4                  | Magic bytes `SSMM`                                    |
+//! | 4      | 4                  | Format version, currently `1`                         |
+//! | 8      | 4                  | Byte order of the payload: `1` little, `2` big endian |
+//! | 12     | 4                  | `size_of::<C>()`                                      |
+//! | 16     | 4                  | `align_of::<C>()`                                     |
+//! | 20     | 4                  | Number of slots `n`                                   |
+//! | 24     | `ceil(n / 8)`      | Occupancy bitmap, bit `i % 8` of byte `i / 8` is id `i` |
+//! | -      | -                  | Zero padding up to the next multiple of 64            |
+//! | -      | `n * size_of::<C>()` | The components, in native layout; vacant slots are zeroed |
+//!
+//! All header fields are little-endian `u32`s. The payload is written in the byte order of the
+//! writing machine, and opening a file written with a different byte order (or a different
+//! component size or alignment) fails. Components may be aligned to at most 64 bytes.
+
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::marker::PhantomData;
+use std::mem;
+use std::path::Path;
+use std::slice;
+
+use hibitset::BitSet;
+use memmap2::Mmap;
+use specs::storage::UnprotectedStorage;
+use specs::Join;
+
+use {Id, Index, Storage};
+
+const MAGIC: &[u8; 4] = b"SSMM";
+const VERSION: u32 = 1;
+const HEADER_LEN: usize = 24;
+const PAYLOAD_ALIGN: usize = 64;
+
+#[cfg(target_endian = "little")]
+const BYTE_ORDER: u32 = 1;
+#[cfg(target_endian = "big")]
+const BYTE_ORDER: u32 = 2;
+
+/// Plain old data which can be stored and mapped as raw bytes.
+///
+/// # Safety
+///
+/// Implementors must be `Copy`, contain no padding or pointers, and every bit pattern
+/// (including all zeros) must be a valid value. Usually this means a `#[repr(C)]` struct
+/// of numbers.
+pub unsafe trait Pod: Copy + Send + Sync + 'static {}
+
+macro_rules! impl_pod {
+    ($($ty:ty),*) => {
+        $(unsafe impl Pod for $ty {})*
+    };
+}
+
+impl_pod!(u8, u16, u32, u64, i8, i16, i32, i64, f32, f64);
+
+/// Collects components and writes them in the format `MmapStorage` maps.
+pub struct MmapStorageBuilder<C, I> {
+    slots: Vec<C>,
+    bitset: BitSet,
+    phantom: PhantomData<I>,
+}
+
+impl<C, I> Default for MmapStorageBuilder<C, I> {
+    fn default() -> Self {
+        MmapStorageBuilder {
+            slots: Vec::new(),
+            bitset: BitSet::new(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<C, I> MmapStorageBuilder<C, I>
+where
+    C: Pod,
+    I: Id,
+{
+    /// Creates an empty builder.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Creates a builder containing all components of `storage`.
+    pub fn from_storage<D>(storage: &Storage<C, D, I>) -> Self
+    where
+        D: UnprotectedStorage<C>,
+    {
+        let mut builder = MmapStorageBuilder::new();
+        for id in &storage.bitset {
+            builder.insert(I::from_u32(id), *unsafe { storage.data.get(id) });
+        }
+
+        builder
+    }
+
+    /// Inserts `comp` at `id`, replacing any previous component.
+    pub fn insert(&mut self, id: I, comp: C) {
+        let index = id.id() as usize;
+        if self.slots.len() <= index {
+            self.slots.resize(index + 1, zeroed());
+        }
+
+        self.slots[index] = comp;
+        self.bitset.add(id.id());
+    }
+
+    /// Writes the packed binary format to `writer`.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        if mem::align_of::<C>() > PAYLOAD_ALIGN {
+            return Err(invalid("components may be aligned to at most 64 bytes"));
+        }
+
+        let len = self.slots.len();
+
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        header.extend_from_slice(MAGIC);
+        for &field in &[
+            VERSION,
+            BYTE_ORDER,
+            mem::size_of::<C>() as u32,
+            mem::align_of::<C>() as u32,
+            len as u32,
+        ] {
+            header.extend_from_slice(&field.to_le_bytes());
+        }
+        writer.write_all(&header)?;
+
+        let mut occupancy = vec![0u8; len.div_ceil(8)];
+        for id in &self.bitset {
+            occupancy[id as usize / 8] |= 1 << (id % 8);
+        }
+        writer.write_all(&occupancy)?;
+
+        let padding = payload_offset(len) - HEADER_LEN - occupancy.len();
+        writer.write_all(&[0; PAYLOAD_ALIGN][..padding])?;
+
+        let payload = unsafe {
+            slice::from_raw_parts(self.slots[..].as_ptr() as *const u8, len * mem::size_of::<C>())
+        };
+        writer.write_all(payload)
+    }
+
+    /// Writes the packed binary format to the file at `path` and maps it back.
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> io::Result<MmapStorage<C, I>> {
+        {
+            let mut file = io::BufWriter::new(File::create(path.as_ref())?);
+            self.write_to(&mut file)?;
+            file.flush()?;
+        }
+
+        MmapStorage::open(path)
+    }
+}
+
+/// A read-only storage whose components live in a memory-mapped file, so the operating
+/// system pages them in on demand.
+///
+/// The file is created with a `MmapStorageBuilder`; see the module documentation for its
+/// layout. Only the occupancy bitset is kept on the heap.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::{Component, Join, VecStorage};
+/// use specs_static::{MmapStorage, MmapStorageBuilder, Storage};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// struct Wetness(u32);
+///
+/// impl Component for Wetness {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// fn main() {
+///     let path = std::env::temp_dir().join("specs_static_mmap_doctest.bin");
+///
+///     let mut builder = MmapStorageBuilder::<u16, TileId>::new();
+///     for i in 0..1000 {
+///         builder.insert(TileId(i * 2), i as u16);
+///     }
+///     let terrain: MmapStorage<u16, TileId> = builder.write(&path).unwrap();
+///     assert_eq!(terrain.get(TileId(10)), Some(&5));
+///     assert_eq!(terrain.get(TileId(11)), None);
+///
+///     let mut wetness = Storage::<Wetness, VecStorage<_>, TileId>::default();
+///     for i in 0..100 {
+///         wetness.insert(TileId(i), Wetness(0));
+///     }
+///
+///     for (height, wet) in (&terrain, &mut wetness).join() {
+///         wet.0 += u32::from(*height);
+///     }
+///     assert_eq!(wetness.get(TileId(98)).unwrap().0, 49);
+///     assert_eq!(wetness.get(TileId(99)).unwrap().0, 0);
+///
+///     drop(terrain);
+///     std::fs::remove_file(&path).unwrap();
+/// }
+/// ```
+pub struct MmapStorage<C, I> {
+    map: Mmap,
+    offset: usize,
+    len: usize,
+    bitset: BitSet,
+    phantom: PhantomData<(C, I)>,
+}
+
+impl<C, I> MmapStorage<C, I>
+where
+    C: Pod,
+    I: Id,
+{
+    /// Maps the file at `path`, validating its header.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let map = unsafe { Mmap::map(&file)? };
+
+        let mut header = &map[..HEADER_LEN.min(map.len())];
+        let mut magic = [0; 4];
+        header.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a specs-static mapped storage file"));
+        }
+
+        let mut fields = [0u32; 5];
+        for field in &mut fields {
+            let mut bytes = [0; 4];
+            header.read_exact(&mut bytes)?;
+            *field = u32::from_le_bytes(bytes);
+        }
+
+        let [version, byte_order, size, align, len] = fields;
+        if version != VERSION {
+            return Err(invalid("unsupported format version"));
+        }
+        if byte_order != BYTE_ORDER {
+            return Err(invalid("file was written with a different byte order"));
+        }
+        if size as usize != mem::size_of::<C>() || align as usize != mem::align_of::<C>() {
+            return Err(invalid("component size or alignment doesn't match"));
+        }
+
+        let len = len as usize;
+        let offset = payload_offset(len);
+        if map.len() < offset + len * mem::size_of::<C>() {
+            return Err(invalid("file is truncated"));
+        }
+
+        let mut bitset = BitSet::with_capacity(len as Index);
+        let occupancy = &map[HEADER_LEN..HEADER_LEN + len.div_ceil(8)];
+        for (byte_index, &byte) in occupancy.iter().enumerate() {
+            for bit in 0..8 {
+                if byte & (1 << bit) != 0 {
+                    bitset.add((byte_index * 8 + bit) as Index);
+                }
+            }
+        }
+
+        Ok(MmapStorage {
+            map,
+            offset,
+            len,
+            bitset,
+            phantom: PhantomData,
+        })
+    }
+
+    /// Returns the component at `id`, if there is one.
+    pub fn get(&self, id: I) -> Option<&C> {
+        if self.bitset.contains(id.id()) {
+            Some(&self.slots()[id.id() as usize])
+        } else {
+            None
+        }
+    }
+
+    /// Returns the mask of occupied ids.
+    pub fn mask(&self) -> &BitSet {
+        &self.bitset
+    }
+
+    /// Returns all slots, including the zeroed vacant ones.
+    fn slots(&self) -> &[C] {
+        if self.len == 0 {
+            return &[];
+        }
+
+        // The map is page aligned and the payload offset is a multiple of 64,
+        // which `open` made sure is enough for `C`.
+        unsafe { slice::from_raw_parts(self.map.as_ptr().add(self.offset) as *const C, self.len) }
+    }
+}
+
+impl<'a, C, I> Join for &'a MmapStorage<C, I>
+where
+    C: Pod,
+    I: Id,
+{
+    type Type = &'a C;
+    type Value = &'a [C];
+    type Mask = &'a BitSet;
+
+    unsafe fn open(self) -> (Self::Mask, Self::Value) {
+        (&self.bitset, self.slots())
+    }
+
+    unsafe fn get(value: &mut Self::Value, id: Index) -> Self::Type {
+        value.get_unchecked(id as usize)
+    }
+}
+
+fn payload_offset(len: usize) -> usize {
+    let unpadded = HEADER_LEN + len.div_ceil(8);
+
+    unpadded.div_ceil(PAYLOAD_ALIGN) * PAYLOAD_ALIGN
+}
+
+fn zeroed<C: Pod>() -> C {
+    unsafe { mem::zeroed() }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
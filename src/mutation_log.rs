@@ -0,0 +1,536 @@
+//! Recording the mutations of tile storages, so a session can be replayed.
+
+use std::any;
+use std::error::Error;
+use std::fmt;
+use std::marker::PhantomData;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use specs::storage::UnprotectedStorage;
+use specs::World;
+
+use {Id, Storage, TileCodec, TileRegistry};
+
+/// What a `Mutation` did to its tile.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Deserialize, Serialize),
+    serde(crate = "serde")
+)]
+pub enum MutationOp {
+    /// A component was inserted; these are its encoded bytes.
+    Insert(Vec<u8>),
+    /// The component was changed in place, and these are the encoded bytes of its new value.
+    Modify(Vec<u8>),
+    /// The component was removed.
+    Remove,
+}
+
+/// One recorded mutation of a tile.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Deserialize, Serialize),
+    serde(crate = "serde")
+)]
+pub struct Mutation {
+    /// The tick the mutation happened in.
+    pub tick: u64,
+    /// The position of the mutation in the log, which orders the mutations within a tick.
+    pub seq: u64,
+    /// The name the layer has been registered under with `TileRegistry::register_named`, if
+    /// the mutation was recorded through `MutationLog::record_named`, otherwise the type name
+    /// of the component, as listed by `TileRegistry::names`.
+    ///
+    /// Type names may change between compiler versions, so logs which are replayed by other
+    /// builds than the one recording them should use `record_named`.
+    pub layer: String,
+    /// The id of the tile.
+    pub id: u32,
+    /// What happened to the tile.
+    pub op: MutationOp,
+}
+
+/// The error returned when `replay` can't apply a mutation.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum ReplayError {
+    /// There is no layer with the name of the mutation in the `TileRegistry`, or its storage
+    /// resource is missing.
+    UnknownLayer {
+        /// The name of the layer.
+        layer: String,
+    },
+    /// The layer hasn't been added with `TileRegistry::register_codec`.
+    NoCodec {
+        /// The name of the layer.
+        layer: String,
+    },
+    /// The bytes of the mutation aren't a valid encoding of a component.
+    Decode {
+        /// The name of the layer.
+        layer: String,
+        /// The id of the tile.
+        id: u32,
+    },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ReplayError::UnknownLayer { ref layer } => {
+                write!(f, "the layer `{}` isn't registered", layer)
+            }
+            ReplayError::NoCodec { ref layer } => {
+                write!(f, "the layer `{}` has no codec registered", layer)
+            }
+            ReplayError::Decode { ref layer, id } => {
+                write!(f, "the component of layer `{}` at {} can't be decoded", layer, id)
+            }
+        }
+    }
+}
+
+impl Error for ReplayError {}
+
+/// A resource recording the mutations of tile storages keyed by `I`, tagged with the tick
+/// they happened in.
+///
+/// Mutations are recorded through the `MutationRecorder` returned by `record`, whose
+/// `log_modify` records changes made through mutable references. Every entry gets the next
+/// sequence number of the log, so entries keep the order they were recorded in even if
+/// several systems write in the same tick; systems which record into the log need write
+/// access to it, which the dispatcher serializes. The entries only hold plain data and the
+/// components encoded with `TileCodec`, so they can be stored by other means and turned
+/// back into a log with `from_entries`.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::{Component, VecStorage, World, WorldExt};
+/// use specs_static::{replay, MutationLog, Storage, TileCodec, TileRegistry, TileWorldExt};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// #[derive(Debug, PartialEq)]
+/// struct Height(i32);
+///
+/// impl Component for Height {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// impl TileCodec for Height {
+///     fn encode(&self, out: &mut Vec<u8>) {
+///         self.0.encode(out);
+///     }
+///
+///     fn decode(bytes: &[u8]) -> Option<Self> {
+///         i32::decode(bytes).map(Height)
+///     }
+/// }
+///
+/// type Heights = Storage<Height, VecStorage<Height>, TileId>;
+///
+/// fn world() -> World {
+///     let mut world = World::new();
+///     world.register_tile_comp::<Height, TileId>();
+///     world.write_resource::<TileRegistry<TileId>>().register_codec::<Height>();
+///     world
+/// }
+///
+/// fn heights(world: &World) -> Vec<(u32, i32)> {
+///     let heights = world.read_resource::<Heights>();
+///     (0..10).filter_map(|id| Some((id, heights.get(TileId(id))?.0))).collect()
+/// }
+///
+/// fn main() {
+///     // A scripted editing session.
+///     let session = world();
+///     let mut log = MutationLog::<TileId>::new();
+///     {
+///         let mut heights = session.write_resource::<Heights>();
+///         let mut edit = log.record(&mut heights);
+///         for id in 0..5 {
+///             edit.insert(TileId(id), Height(id as i32));
+///         }
+///         edit.remove(TileId(2));
+///
+///         log.set_tick(1);
+///         let mut edit = log.record(&mut heights);
+///         edit.insert(TileId(2), Height(20));
+///         edit.get_mut(TileId(4)).unwrap().0 += 40;
+///         edit.log_modify(TileId(4));
+///
+///         log.set_tick(2);
+///         log.record(&mut heights).remove(TileId(0));
+///     }
+///     assert_eq!(log.len(), 9);
+///
+///     // Replaying everything reproduces the session...
+///     let mut fresh = world();
+///     assert_eq!(replay(&log, &mut fresh, 2), Ok(9));
+///     assert_eq!(heights(&fresh), heights(&session));
+///     assert_eq!(heights(&fresh), vec![(1, 1), (2, 20), (3, 3), (4, 44)]);
+///
+///     // ...and stopping early shows the state at the end of a tick.
+///     let mut fresh = world();
+///     assert_eq!(replay(&log, &mut fresh, 0), Ok(6));
+///     assert_eq!(heights(&fresh), vec![(0, 0), (1, 1), (3, 3), (4, 4)]);
+///
+///     // The entries are plain data, so they can be saved and loaded.
+///     let loaded = MutationLog::<TileId>::from_entries(log.entries().to_vec());
+///     let mut fresh = world();
+///     assert_eq!(replay(&loaded, &mut fresh, 2), Ok(9));
+///     assert_eq!(heights(&fresh), heights(&session));
+/// }
+/// ```
+///
+/// With the `serde` feature, the log implements `Serialize` and `Deserialize`, and so do
+/// its entries. A deserialized log continues the tick and sequence numbers of the saved one.
+///
+#[cfg_attr(feature = "serde", doc = "```")]
+#[cfg_attr(not(feature = "serde"), doc = "```ignore")]
+/// extern crate serde_json;
+/// extern crate specs_static;
+///
+/// use specs_static::{Mutation, MutationLog, MutationOp};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// fn main() {
+///     let mut log = MutationLog::<TileId>::from_entries(vec![
+///         Mutation {
+///             tick: 3,
+///             seq: 0,
+///             layer: "game::Height".to_owned(),
+///             id: 17,
+///             op: MutationOp::Insert(vec![1, 0, 0, 0]),
+///         },
+///         Mutation {
+///             tick: 4,
+///             seq: 1,
+///             layer: "game::Height".to_owned(),
+///             id: 17,
+///             op: MutationOp::Remove,
+///         },
+///     ]);
+///     log.set_tick(5);
+///
+///     let saved = serde_json::to_string(&log).unwrap();
+///     let loaded: MutationLog<TileId> = serde_json::from_str(&saved).unwrap();
+///     assert_eq!(loaded.entries(), log.entries());
+///     assert_eq!(loaded.tick(), 5);
+///
+///     let entry = serde_json::to_value(&loaded.entries()[1]).unwrap();
+///     assert_eq!(entry["op"], "Remove");
+/// }
+/// ```
+#[derive(Derivative)]
+#[derivative(Default(bound = ""))]
+#[cfg_attr(
+    feature = "serde",
+    derive(Deserialize, Serialize),
+    serde(crate = "serde")
+)]
+pub struct MutationLog<I> {
+    entries: Vec<Mutation>,
+    tick: u64,
+    next_seq: u64,
+    phantom: PhantomData<I>,
+}
+
+impl<I> MutationLog<I>
+where
+    I: Id,
+{
+    /// Creates an empty log, recording into tick 0.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Creates a log from entries recorded earlier, like ones loaded from a file. New
+    /// entries are recorded into the highest tick of them, and after all of them.
+    pub fn from_entries(entries: Vec<Mutation>) -> Self {
+        let tick = entries.iter().map(|mutation| mutation.tick).max().unwrap_or(0);
+        let next_seq = entries.iter().map(|mutation| mutation.seq + 1).max().unwrap_or(0);
+
+        MutationLog {
+            entries,
+            tick,
+            next_seq,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns the tick new entries are recorded into.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Sets the tick new entries are recorded into.
+    pub fn set_tick(&mut self, tick: u64) {
+        self.tick = tick;
+    }
+
+    /// Returns the recorded entries, in the order they were recorded.
+    pub fn entries(&self) -> &[Mutation] {
+        &self.entries
+    }
+
+    /// Returns the amount of recorded entries.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if nothing has been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Removes all entries. Sequence numbers keep counting, so entries recorded later still
+    /// sort after the removed ones.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Returns a recorder which applies mutations to `storage` and records them under the
+    /// type name of `C`. See `record_named` for logs which have to outlive the build.
+    pub fn record<'a, C, D>(
+        &'a mut self,
+        storage: &'a mut Storage<C, D, I>,
+    ) -> MutationRecorder<'a, C, D, I>
+    where
+        C: TileCodec,
+        D: UnprotectedStorage<C>,
+    {
+        self.record_named(storage, any::type_name::<C>())
+    }
+
+    /// Like `record`, but records the mutations under `layer`, the name `storage` has been
+    /// registered under with `TileRegistry::register_named`. Unlike type names, this stays
+    /// the same across builds, so saved logs can be replayed by later versions of the game.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, VecStorage, World, WorldExt};
+    /// use specs_static::{replay, MutationLog, Storage, TileCodec, TileRegistry, TileWorldExt};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// #[derive(Debug, PartialEq)]
+    /// struct Height(i32);
+    ///
+    /// impl Component for Height {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// impl TileCodec for Height {
+    ///     fn encode(&self, out: &mut Vec<u8>) {
+    ///         self.0.encode(out);
+    ///     }
+    ///
+    ///     fn decode(bytes: &[u8]) -> Option<Self> {
+    ///         i32::decode(bytes).map(Height)
+    ///     }
+    /// }
+    ///
+    /// type Heights = Storage<Height, VecStorage<Height>, TileId>;
+    ///
+    /// fn world() -> World {
+    ///     let mut world = World::new();
+    ///     world.register_tile_comp_named::<Height, TileId>("height").unwrap();
+    ///     world.write_resource::<TileRegistry<TileId>>().register_codec::<Height>();
+    ///     world
+    /// }
+    ///
+    /// fn main() {
+    ///     let session = world();
+    ///     let mut log = MutationLog::<TileId>::new();
+    ///     {
+    ///         let mut heights = session.write_resource::<Heights>();
+    ///         let mut edit = log.record_named(&mut heights, "height");
+    ///         edit.insert(TileId(3), Height(30));
+    ///         edit.insert(TileId(4), Height(40));
+    ///         edit.remove(TileId(3));
+    ///     }
+    ///     assert!(log.entries().iter().all(|mutation| mutation.layer == "height"));
+    ///
+    ///     let mut fresh = world();
+    ///     assert_eq!(replay(&log, &mut fresh, 0), Ok(3));
+    ///     let heights = fresh.read_resource::<Heights>();
+    ///     assert_eq!(heights.get(TileId(3)), None);
+    ///     assert_eq!(heights.get(TileId(4)), Some(&Height(40)));
+    /// }
+    /// ```
+    pub fn record_named<'a, C, D>(
+        &'a mut self,
+        storage: &'a mut Storage<C, D, I>,
+        layer: &'a str,
+    ) -> MutationRecorder<'a, C, D, I>
+    where
+        C: TileCodec,
+        D: UnprotectedStorage<C>,
+    {
+        MutationRecorder {
+            log: self,
+            storage,
+            layer,
+        }
+    }
+
+    fn push(&mut self, layer: &str, id: u32, op: MutationOp) {
+        self.entries.push(Mutation {
+            tick: self.tick,
+            seq: self.next_seq,
+            layer: layer.to_owned(),
+            id,
+            op,
+        });
+        self.next_seq += 1;
+    }
+}
+
+/// Applies mutations to a storage and records them in a `MutationLog`.
+pub struct MutationRecorder<'a, C: 'a, D: UnprotectedStorage<C> + 'a, I: 'a> {
+    log: &'a mut MutationLog<I>,
+    storage: &'a mut Storage<C, D, I>,
+    layer: &'a str,
+}
+
+impl<'a, C, D, I> MutationRecorder<'a, C, D, I>
+where
+    C: TileCodec,
+    D: UnprotectedStorage<C>,
+    I: Id,
+{
+    /// Returns the component at `id`.
+    pub fn get(&self, id: I) -> Option<&C> {
+        self.storage.get(id)
+    }
+
+    /// Returns the component at `id` mutably, without recording anything. Call `log_modify`
+    /// after changing it.
+    pub fn get_mut(&mut self, id: I) -> Option<&mut C> {
+        self.storage.get_mut(id)
+    }
+
+    /// Inserts `comp` at `id` and records it. Nothing is recorded if the insertion panics,
+    /// like for ids above the bound of the storage.
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use std::panic::{self, AssertUnwindSafe};
+    ///
+    /// use specs::VecStorage;
+    /// use specs_static::{MutationLog, Storage};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// fn main() {
+    ///     let mut heights = Storage::<i32, VecStorage<_>, TileId>::with_bound(9);
+    ///     let mut log = MutationLog::<TileId>::new();
+    ///     let result = panic::catch_unwind(AssertUnwindSafe(|| {
+    ///         log.record(&mut heights).insert(TileId(10), 5);
+    ///     }));
+    ///     assert!(result.is_err());
+    ///     assert!(log.is_empty());
+    /// }
+    /// ```
+    pub fn insert(&mut self, id: I, comp: C) -> Option<C> {
+        let mut bytes = Vec::new();
+        comp.encode(&mut bytes);
+        let old = self.storage.insert(id, comp);
+        self.log.push(self.layer, id.id(), MutationOp::Insert(bytes));
+
+        old
+    }
+
+    /// Removes the component at `id`, recording it if there was one.
+    pub fn remove(&mut self, id: I) -> Option<C> {
+        let old = self.storage.remove(id)?;
+        self.log.push(self.layer, id.id(), MutationOp::Remove);
+
+        Some(old)
+    }
+
+    /// Records the current value of the component at `id` as a modification. Returns `false`
+    /// without recording anything if `id` is vacant.
+    pub fn log_modify(&mut self, id: I) -> bool {
+        let mut bytes = Vec::new();
+        match self.storage.get(id) {
+            Some(comp) => comp.encode(&mut bytes),
+            None => return false,
+        }
+        self.log.push(self.layer, id.id(), MutationOp::Modify(bytes));
+
+        true
+    }
+}
+
+/// Applies the entries of `log` up to and including the tick `up_to_tick` to the storages of
+/// `world`, which are looked up through its `TileRegistry<I>`. Returns how many entries were
+/// applied.
+///
+/// The entries are applied ordered by tick and sequence number. Their layers are looked up by
+/// the names of `TileRegistry::register_named` first, then by the type names of the
+/// components. Every layer in the log has to be added with `TileRegistry::register_codec`; the first entry which can't be applied
+/// stops the replay with an error, leaving the entries before it applied. See `MutationLog`
+/// for an example.
+pub fn replay<I>(
+    log: &MutationLog<I>,
+    world: &mut World,
+    up_to_tick: u64,
+) -> Result<usize, ReplayError>
+where
+    I: Id,
+{
+    let entries = world
+        .try_fetch::<TileRegistry<I>>()
+        .map(|registry| registry.named_entries())
+        .unwrap_or_default();
+    let mut mutations: Vec<&Mutation> = log
+        .entries
+        .iter()
+        .filter(|mutation| mutation.tick <= up_to_tick)
+        .collect();
+    mutations.sort_by_key(|mutation| (mutation.tick, mutation.seq));
+
+    for mutation in &mutations {
+        let unknown = || ReplayError::UnknownLayer {
+            layer: mutation.layer.clone(),
+        };
+        let named = entries
+            .iter()
+            .find(|(_, name)| name.as_ref() == Some(&mutation.layer));
+        let entry = named
+            .or_else(|| entries.iter().find(|(entry, _)| entry.type_name == mutation.layer))
+            .map(|&(entry, _)| entry)
+            .ok_or_else(unknown)?;
+        let mut storage = (entry.fetch_mut)(world).ok_or_else(unknown)?;
+        let codec = entry.codec.ok_or_else(|| ReplayError::NoCodec {
+            layer: mutation.layer.clone(),
+        })?;
+
+        let bytes = match mutation.op {
+            MutationOp::Insert(ref bytes) | MutationOp::Modify(ref bytes) => bytes,
+            MutationOp::Remove => {
+                storage.remove(mutation.id);
+                continue;
+            }
+        };
+        if !(codec.decode_insert)(&mut *storage, mutation.id, bytes) {
+            return Err(ReplayError::Decode {
+                layer: mutation.layer.clone(),
+                id: mutation.id,
+            });
+        }
+    }
+
+    Ok(mutations.len())
+}
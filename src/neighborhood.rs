@@ -0,0 +1,263 @@
+//! Fetching the components around a tile in one call.
+
+use std::mem::MaybeUninit;
+
+use specs::storage::UnprotectedStorage;
+
+use {Grid2d, Id, SliceAccess, Storage};
+
+/// The ids of the eight tiles around `id`, in the order of `Storage::neighborhood`, with
+/// `None` for the ones outside of `grid`.
+fn neighbor_ids(grid: &Grid2d, id: u32) -> [Option<u32>; 8] {
+    let width = grid.width();
+    if id >= grid.len() {
+        return [None; 8];
+    }
+
+    let (x, y) = (id % width, id / width);
+    let (left, right) = (x > 0, x + 1 < width);
+    let (up, down) = (y > 0, y + 1 < grid.height());
+    let at = |inside: bool, id: u32| if inside { Some(id) } else { None };
+
+    let above = id.wrapping_sub(width);
+    let below = id + width;
+    [
+        at(up && left, above.wrapping_sub(1)),
+        at(up, above),
+        at(up && right, above.wrapping_add(1)),
+        at(left, id.wrapping_sub(1)),
+        at(right, id + 1),
+        at(down && left, below.wrapping_sub(1)),
+        at(down, below),
+        at(down && right, below + 1),
+    ]
+}
+
+impl<C, D, I> Storage<C, D, I>
+where
+    D: UnprotectedStorage<C>,
+    I: Id,
+{
+    /// Returns the components of the eight tiles around `id` in `grid`, in row-major order:
+    /// the three tiles of the row above from left to right, then the left and the right
+    /// tile, then the three tiles of the row below.
+    ///
+    /// Tiles outside of the grid and vacant ones are `None`, and so are all of them if `id`
+    /// is outside of the grid. The coordinates are only computed once, and the tiles of a
+    /// row are accessed one after the other.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, VecStorage};
+    /// use specs_static::{Grid2d, Storage};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// #[derive(Debug, PartialEq)]
+    /// struct Depth(u32);
+    ///
+    /// impl Component for Depth {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let grid = Grid2d::new(4, 3);
+    ///     let mut depth = Storage::<Depth, VecStorage<_>, TileId>::default();
+    ///     for id in 0..12 {
+    ///         depth.insert(TileId(id), Depth(id));
+    ///     }
+    ///     depth.remove(TileId(6));
+    ///
+    ///     let values = |id| -> Vec<_> {
+    ///         depth.neighborhood(&grid, id).iter().map(|d| d.map(|d| d.0)).collect()
+    ///     };
+    ///
+    ///     // An inner tile, next to a vacant one.
+    ///     let expected = vec![
+    ///         Some(0), Some(1), Some(2),
+    ///         Some(4),          None,
+    ///         Some(8), Some(9), Some(10),
+    ///     ];
+    ///     assert_eq!(values(TileId(5)), expected);
+    ///     // The corners.
+    ///     let expected = vec![None, None, None, None, Some(1), None, Some(4), Some(5)];
+    ///     assert_eq!(values(TileId(0)), expected);
+    ///     let expected = vec![None, Some(7), None, Some(10), None, None, None, None];
+    ///     assert_eq!(values(TileId(11)), expected);
+    ///     // Outside of the grid.
+    ///     assert_eq!(values(TileId(12)), vec![None; 8]);
+    ///
+    ///     // The same as looking up every neighbor by its coordinates.
+    ///     let offsets = [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)];
+    ///     for id in 0..12 {
+    ///         let (x, y) = grid.coords(TileId(id)).unwrap();
+    ///         let naive: Vec<_> = offsets
+    ///             .iter()
+    ///             .map(|&(dx, dy)| {
+    ///                 let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+    ///                 if nx < 0 || ny < 0 {
+    ///                     return None;
+    ///                 }
+    ///                 let id: TileId = grid.id(nx as u32, ny as u32)?;
+    ///                 depth.get(id).map(|d| d.0)
+    ///             })
+    ///             .collect();
+    ///         assert_eq!(values(TileId(id)), naive);
+    ///     }
+    /// }
+    /// ```
+    pub fn neighborhood(&self, grid: &Grid2d, id: I) -> [Option<&C>; 8] {
+        let ids = neighbor_ids(grid, id.id());
+        let mut comps = [None; 8];
+        for (comp, id) in comps.iter_mut().zip(&ids) {
+            *comp = id.and_then(|id| self.get(I::from_u32(id)));
+        }
+
+        comps
+    }
+
+    /// Returns the components of the four tiles sharing an edge with `id` in `grid`, in
+    /// row-major order: above, left, right and below. Like `neighborhood`, tiles outside of
+    /// the grid and vacant ones are `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, VecStorage};
+    /// use specs_static::{Connectivity, Grid2d, Storage};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// struct Depth(u32);
+    ///
+    /// impl Component for Depth {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let grid = Grid2d::new(4, 3);
+    ///     let mut depth = Storage::<Depth, VecStorage<_>, TileId>::default();
+    ///     for id in 0..12 {
+    ///         depth.insert(TileId(id), Depth(id));
+    ///     }
+    ///
+    ///     let values = |id| -> Vec<_> {
+    ///         depth.neighborhood4(&grid, id).iter().filter_map(|d| d.map(|d| d.0)).collect()
+    ///     };
+    ///     assert_eq!(values(TileId(5)), vec![1, 4, 6, 9]);
+    ///     assert_eq!(values(TileId(3)), vec![2, 7]);
+    ///
+    ///     // The same tiles `Grid2d::neighbors` walks.
+    ///     for id in 0..12 {
+    ///         let walked: Vec<_> = grid.neighbors(TileId(id), Connectivity::Four).map(|n| n.0)
+    ///             .collect();
+    ///         assert_eq!(values(TileId(id)), walked);
+    ///     }
+    /// }
+    /// ```
+    pub fn neighborhood4(&self, grid: &Grid2d, id: I) -> [Option<&C>; 4] {
+        let ids = neighbor_ids(grid, id.id());
+        let mut comps = [None; 4];
+        for (comp, &id) in comps.iter_mut().zip(&[ids[1], ids[3], ids[4], ids[6]]) {
+            *comp = id.and_then(|id| self.get(I::from_u32(id)));
+        }
+
+        comps
+    }
+}
+
+impl<C, D, I> Storage<C, D, I>
+where
+    D: SliceAccess<C>,
+    I: Id,
+{
+    /// Returns the component at `id` mutably together with the components of the eight
+    /// tiles around it, in the order of `neighborhood`, or `None` if `id` is vacant or
+    /// outside of `grid`.
+    ///
+    /// This serves updates which read the neighbors of a tile to change it, like diffusion.
+    /// The center is split off from the slots of the backend before the neighbors are taken
+    /// from the slots on either side of it, so the mutable reference never aliases them. That
+    /// is why this needs a `SliceAccess` backend, where every id has its own slot.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::Component;
+    /// use specs_static::{Grid2d, SliceStorage, Storage};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// struct Gas(f32);
+    ///
+    /// impl Component for Gas {
+    ///     type Storage = SliceStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let grid = Grid2d::new(3, 3);
+    ///     let mut gas = Storage::<Gas, SliceStorage<_>, TileId>::default();
+    ///     for id in 0..9 {
+    ///         gas.insert(TileId(id), Gas(if id == 4 { 0.0 } else { 1.0 }));
+    ///     }
+    ///     gas.remove(TileId(0));
+    ///
+    ///     {
+    ///         let (center, around) = gas.neighborhood_with_center_mut(&grid, TileId(4)).unwrap();
+    ///         let present: Vec<f32> = around.iter().filter_map(|g| g.map(|g| g.0)).collect();
+    ///         center.0 = present.iter().sum::<f32>() / present.len() as f32;
+    ///     }
+    ///     assert_eq!(gas.get(TileId(4)).unwrap().0, 1.0);
+    ///
+    ///     // A corner only has three neighbors, and vacant tiles have no neighborhood.
+    ///     let (_, around) = gas.neighborhood_with_center_mut(&grid, TileId(8)).unwrap();
+    ///     assert_eq!(around.iter().filter(|g| g.is_some()).count(), 3);
+    ///     assert!(gas.neighborhood_with_center_mut(&grid, TileId(0)).is_none());
+    ///     assert!(gas.neighborhood_with_center_mut(&grid, TileId(9)).is_none());
+    /// }
+    /// ```
+    pub fn neighborhood_with_center_mut(
+        &mut self,
+        grid: &Grid2d,
+        id: I,
+    ) -> Option<(&mut C, [Option<&C>; 8])> {
+        let center = id.id();
+        if center >= grid.len() || !self.bitset.contains(center) {
+            return None;
+        }
+        let ids = neighbor_ids(grid, center);
+        self.counters.get_mut();
+        self.stamps.stamp(center);
+
+        let bitset = &self.bitset;
+        let counters = &self.counters;
+        // The occupied slots stay initialized, since only the center is handed out mutably.
+        let slots = unsafe { self.data.slots_mut() };
+        let (before, rest) = slots.split_at_mut(center as usize);
+        let (comp, after) = rest.split_first_mut()?;
+        let (before, after) = (&*before, &*after);
+
+        let mut around = [None; 8];
+        for (slot, id) in around.iter_mut().zip(&ids) {
+            *slot = id.filter(|&id| bitset.contains(id)).map(|id| {
+                counters.get();
+                let slot: &MaybeUninit<C> = if id < center {
+                    &before[id as usize]
+                } else {
+                    &after[(id - center - 1) as usize]
+                };
+
+                unsafe { &*slot.as_ptr() }
+            });
+        }
+
+        Some((unsafe { &mut *comp.as_mut_ptr() }, around))
+    }
+}
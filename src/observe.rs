@@ -0,0 +1,241 @@
+//! Callbacks which storages call synchronously when components are inserted or removed.
+
+#[cfg(not(feature = "specs"))]
+use liballoc::boxed::Box;
+#[cfg(not(feature = "specs"))]
+use liballoc::vec::Vec;
+
+use {BitSetLike, Id, Index, RawStorage, Storage};
+
+/// A callback registered with `Storage::on_insert` or `Storage::on_remove`.
+pub type Observer<C, I> = Box<dyn FnMut(I, &C) + Send>;
+
+/// The observers of a storage.
+pub(crate) struct Observers<C, I> {
+    insert: Vec<(u64, Observer<C, I>)>,
+    remove: Vec<(u64, Observer<C, I>)>,
+    next: u64,
+}
+
+// The observers are only ever called or changed through `&mut Storage`.
+unsafe impl<C, I> Sync for Observers<C, I> {}
+
+impl<C, I> Default for Observers<C, I> {
+    fn default() -> Self {
+        Observers {
+            insert: Vec::new(),
+            remove: Vec::new(),
+            next: 0,
+        }
+    }
+}
+
+impl<C, I> Observers<C, I>
+where
+    I: Id,
+{
+    #[cfg(feature = "specs")]
+    #[inline]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.insert.is_empty() && self.remove.is_empty()
+    }
+
+    #[inline]
+    pub(crate) fn inserted(&mut self, id: Index, comp: &C) {
+        for &mut (_, ref mut observer) in &mut self.insert {
+            observer(I::from_u32(id), comp);
+        }
+    }
+
+    #[inline]
+    pub(crate) fn removed(&mut self, id: Index, comp: &C) {
+        for &mut (_, ref mut observer) in &mut self.remove {
+            observer(I::from_u32(id), comp);
+        }
+    }
+
+    pub(crate) fn has_insert(&self) -> bool {
+        !self.insert.is_empty()
+    }
+
+    pub(crate) fn has_remove(&self) -> bool {
+        !self.remove.is_empty()
+    }
+
+    fn push(&mut self, remove: bool, observer: Observer<C, I>) -> ObserverHandle {
+        let handle = ObserverHandle {
+            id: self.next,
+            remove,
+        };
+        self.next += 1;
+        match remove {
+            false => self.insert.push((handle.id, observer)),
+            true => self.remove.push((handle.id, observer)),
+        }
+
+        handle
+    }
+}
+
+/// Identifies an observer registered with `Storage::on_insert` or `Storage::on_remove`, for
+/// unregistering it with `Storage::remove_observer`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct ObserverHandle {
+    id: u64,
+    remove: bool,
+}
+
+impl<C, D, I> Storage<C, D, I>
+where
+    D: RawStorage<C>,
+    I: Id,
+{
+    /// Registers `observer` to be called with the id and the new component whenever a
+    /// component is stored, returning a handle for unregistering it.
+    ///
+    /// Observers are called synchronously, after the component is in place, from `insert`
+    /// (also when it replaces a component), `update` and everything built on them. The bulk
+    /// operations `insert_mask_with`, `fill_range_copy` and `CowStorage`'s `restore` call
+    /// them once per id after the whole operation. Several observers are called in the
+    /// order they were registered. A storage without observers only pays for a check
+    /// whether the list is empty.
+    ///
+    /// Observers can't call back into the storage: it's mutably borrowed while they run,
+    /// so the borrow checker rejects using it directly, and fetching it from the `World`
+    /// again panics because it's already borrowed. Changes made with `get_mut` or mutable
+    /// joins aren't observed, and neither is taking the storage apart or converting it.
+    /// `chunks_mut` panics if the storage has observers.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// use specs::{Component, VecStorage};
+    /// use specs_static::{RangeMask, Storage};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// #[derive(Debug, PartialEq)]
+    /// struct Building(&'static str);
+    ///
+    /// impl Component for Building {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     // The tiles whose path costs have to be recomputed.
+    ///     let stale = Arc::new(Mutex::new(Vec::new()));
+    ///     let log = Arc::new(Mutex::new(Vec::new()));
+    ///
+    ///     let mut buildings = Storage::<Building, VecStorage<_>, TileId>::default();
+    ///     let invalidate = {
+    ///         let stale = stale.clone();
+    ///         move |id: TileId, _: &Building| stale.lock().unwrap().push(id.0)
+    ///     };
+    ///     buildings.on_insert(Box::new(invalidate.clone()));
+    ///     buildings.on_remove(Box::new(invalidate));
+    ///     let logger = {
+    ///         let log = log.clone();
+    ///         buildings.on_insert(Box::new(move |id, building: &Building| {
+    ///             log.lock().unwrap().push(format!("{} at {}", building.0, id.0));
+    ///         }))
+    ///     };
+    ///
+    ///     buildings.insert(TileId(3), Building("mill"));
+    ///     buildings.insert(TileId(3), Building("farm"));
+    ///     buildings.remove(TileId(3));
+    ///     buildings.remove(TileId(3));
+    ///     assert_eq!(*stale.lock().unwrap(), vec![3, 3, 3]);
+    ///
+    ///     // Bulk operations call the observers for every id afterwards.
+    ///     buildings.insert_mask_with(RangeMask::from_range(10, 13), |_| Building("wall"));
+    ///     assert_eq!(stale.lock().unwrap()[3..], [10, 11, 12]);
+    ///     buildings.clear();
+    ///     assert_eq!(stale.lock().unwrap()[6..], [10, 11, 12]);
+    ///
+    ///     assert!(buildings.remove_observer(logger));
+    ///     assert!(!buildings.remove_observer(logger));
+    ///     buildings.insert(TileId(0), Building("well"));
+    ///     assert_eq!(stale.lock().unwrap().len(), 10);
+    ///     assert_eq!(
+    ///         *log.lock().unwrap(),
+    ///         vec!["mill at 3", "farm at 3", "wall at 10", "wall at 11", "wall at 12"]
+    ///     );
+    /// }
+    /// ```
+    ///
+    /// An observer can't reach the storage it observes:
+    ///
+    /// ```compile_fail
+    /// # extern crate specs;
+    /// # extern crate specs_static;
+    /// # use specs::{Component, VecStorage};
+    /// # use specs_static::Storage;
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// # struct Building(&'static str);
+    /// # impl Component for Building {
+    /// #     type Storage = VecStorage<Self>;
+    /// # }
+    /// # fn main() {
+    /// let mut buildings = Storage::<Building, VecStorage<_>, TileId>::default();
+    /// buildings.on_insert(Box::new(|id, _| {
+    ///     buildings.remove(id);
+    /// }));
+    /// # }
+    /// ```
+    pub fn on_insert(&mut self, observer: Observer<C, I>) -> ObserverHandle {
+        self.observers.push(false, observer)
+    }
+
+    /// Registers `observer` to be called with the id and the component whenever a component
+    /// is removed, returning a handle for unregistering it.
+    ///
+    /// Observers are called synchronously, right before the component is dropped or handed
+    /// back, from `remove`, `clear`, `remove_mask`, `clear_budgeted`, `CowStorage`'s
+    /// `restore` and everything built on them. The bulk operations call them once per id.
+    /// Otherwise, everything `on_insert` says applies.
+    pub fn on_remove(&mut self, observer: Observer<C, I>) -> ObserverHandle {
+        self.observers.push(true, observer)
+    }
+
+    /// Unregisters the observer of `handle`, returning `false` if it has already been
+    /// unregistered.
+    pub fn remove_observer(&mut self, handle: ObserverHandle) -> bool {
+        let observers = match handle.remove {
+            false => &mut self.observers.insert,
+            true => &mut self.observers.remove,
+        };
+        let len = observers.len();
+        observers.retain(|&(id, _)| id != handle.id);
+
+        observers.len() != len
+    }
+
+    /// Calls the insert observers for every id of `ids`, which have to be occupied.
+    #[cfg(feature = "specs")]
+    pub(crate) fn notify_inserted<B>(&mut self, ids: B)
+    where
+        B: BitSetLike,
+    {
+        if self.observers.has_insert() {
+            for id in ids.iter() {
+                self.observers.inserted(id, unsafe { self.data.get(id) });
+            }
+        }
+    }
+
+    /// Calls the remove observers for every id of `ids`, which have to be occupied.
+    pub(crate) fn notify_removed<B>(&mut self, ids: B)
+    where
+        B: BitSetLike,
+    {
+        if self.observers.has_remove() {
+            for id in ids.iter() {
+                self.observers.removed(id, unsafe { self.data.get(id) });
+            }
+        }
+    }
+}
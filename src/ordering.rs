@@ -0,0 +1,248 @@
+//! Ordering and grouping occupied ids by their components.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use hibitset::{BitSet, BitSetAnd, BitSetLike};
+use specs::storage::UnprotectedStorage;
+
+use {Id, Storage};
+
+impl<C, D, I> Storage<C, D, I>
+where
+    D: UnprotectedStorage<C>,
+    I: Id,
+{
+    /// Returns all occupied ids, sorted by the key `key` computes from every id and its
+    /// component.
+    ///
+    /// `key` is called exactly once per occupied id and components aren't cloned. Ids with
+    /// equal keys stay in ascending id order, so the result only depends on the contents.
+    /// Wrap the key in `std::cmp::Reverse` for a descending order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use std::cmp::Reverse;
+    ///
+    /// use specs::{Component, VecStorage};
+    /// use specs_static::Storage;
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// struct Water {
+    ///     pressure: u32,
+    /// }
+    ///
+    /// impl Component for Water {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut water = Storage::<Water, VecStorage<_>, TileId>::default();
+    ///     for &(id, pressure) in &[(9, 3), (2, 5), (7, 3), (4, 1), (5, 5), (1, 3)] {
+    ///         water.insert(TileId(id), Water { pressure });
+    ///     }
+    ///
+    ///     let ids = water.ids_sorted_by_key(|_, water| Reverse(water.pressure));
+    ///     let ids: Vec<u32> = ids.into_iter().map(|id| id.0).collect();
+    ///     assert_eq!(ids, vec![2, 5, 1, 7, 9, 4]);
+    ///
+    ///     // Equal keys keep every id in ascending order.
+    ///     let ids = water.ids_sorted_by_key(|_, _| ());
+    ///     let ids: Vec<u32> = ids.into_iter().map(|id| id.0).collect();
+    ///     assert_eq!(ids, vec![1, 2, 4, 5, 7, 9]);
+    /// }
+    /// ```
+    pub fn ids_sorted_by_key<K, F>(&self, mut key: F) -> Vec<I>
+    where
+        K: Ord,
+        F: FnMut(I, &C) -> K,
+    {
+        let mut keyed: Vec<(K, I)> = (&self.bitset)
+            .into_iter()
+            .map(|raw| {
+                let id = I::from_u32(raw);
+                (key(id, unsafe { self.data.get(raw) }), id)
+            })
+            .collect();
+        // Stable, and the ids have been collected in ascending order.
+        keyed.sort_by(|a, b| a.0.cmp(&b.0));
+
+        keyed.into_iter().map(|(_, id)| id).collect()
+    }
+
+    /// Groups the occupied ids by the key `key` computes from their components, in one pass
+    /// over the occupied ids.
+    ///
+    /// Every group is a mask, so it can be joined with this or other storages, or be passed
+    /// to mask-based methods like `remove_mask`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, Join, VecStorage};
+    /// use specs_static::Storage;
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+    /// enum Material {
+    ///     Grass,
+    ///     Stone,
+    ///     Water,
+    /// }
+    ///
+    /// impl Component for Material {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// struct Height(u32);
+    ///
+    /// impl Component for Height {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut materials = Storage::<Material, VecStorage<_>, TileId>::default();
+    ///     let mut heights = Storage::<Height, VecStorage<_>, TileId>::default();
+    ///     for id in 0..12 {
+    ///         let material = if id % 3 == 1 { Material::Stone } else { Material::Grass };
+    ///         materials.insert(TileId(id), material);
+    ///         heights.insert(TileId(id), Height(id * 10));
+    ///     }
+    ///     materials.insert(TileId(100), Material::Water);
+    ///
+    ///     let groups = materials.group_by(|&material| material);
+    ///     assert_eq!(groups.len(), 3);
+    ///     let ids = |material| (&groups[&material]).join().collect::<Vec<_>>();
+    ///     assert_eq!(ids(Material::Stone), vec![1, 4, 7, 10]);
+    ///     assert_eq!(ids(Material::Grass), vec![0, 2, 3, 5, 6, 8, 9, 11]);
+    ///     assert_eq!(ids(Material::Water), vec![100]);
+    ///
+    ///     let stone_heights: u32 = (&heights, &groups[&Material::Stone])
+    ///         .join()
+    ///         .map(|(height, _)| height.0)
+    ///         .sum();
+    ///     assert_eq!(stone_heights, 10 + 40 + 70 + 100);
+    /// }
+    /// ```
+    pub fn group_by<K, F>(&self, mut key: F) -> HashMap<K, BitSet>
+    where
+        K: Eq + Hash,
+        F: FnMut(&C) -> K,
+    {
+        let mut groups: HashMap<K, BitSet> = HashMap::new();
+        for id in &self.bitset {
+            groups
+                .entry(key(unsafe { self.data.get(id) }))
+                .or_default()
+                .add(id);
+        }
+
+        groups
+    }
+
+    /// Counts the occupied ids per key `key` computes from their components, in one pass.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate hibitset;
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use std::collections::HashMap;
+    ///
+    /// use hibitset::BitSet;
+    /// use specs::{Component, VecStorage};
+    /// use specs_static::Storage;
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+    /// enum Material {
+    ///     Dirt,
+    ///     Sand,
+    ///     Stone,
+    ///     Water,
+    /// }
+    ///
+    /// impl Component for Material {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// const MATERIALS: [Material; 4] =
+    ///     [Material::Dirt, Material::Sand, Material::Stone, Material::Water];
+    ///
+    /// fn main() {
+    ///     let mut materials = Storage::<Material, VecStorage<_>, TileId>::default();
+    ///     let mut reference = Vec::new();
+    ///     let mut seed = 12345u32;
+    ///     for _ in 0..5000 {
+    ///         seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+    ///         let id = (seed >> 8) % 100_000;
+    ///         let material = MATERIALS[(seed >> 4) as usize % 4];
+    ///         materials.insert(TileId(id), material);
+    ///         reference.retain(|&(other, _)| other != id);
+    ///         reference.push((id, material));
+    ///     }
+    ///
+    ///     let naive = |keep: &dyn Fn(u32) -> bool| {
+    ///         let mut counts = HashMap::new();
+    ///         for &(_, material) in reference.iter().filter(|&&(id, _)| keep(id)) {
+    ///             *counts.entry(material).or_insert(0) += 1;
+    ///         }
+    ///         counts
+    ///     };
+    ///     assert_eq!(materials.value_counts(|&material| material), naive(&|_| true));
+    ///
+    ///     // The statistics of the chunk of ids `20_000..30_000`.
+    ///     let mut chunk = BitSet::new();
+    ///     for id in 20_000..30_000 {
+    ///         chunk.add(id);
+    ///     }
+    ///     assert_eq!(
+    ///         materials.value_counts_in(&chunk, |&material| material),
+    ///         naive(&|id| id >= 20_000 && id < 30_000)
+    ///     );
+    /// }
+    /// ```
+    pub fn value_counts<K, F>(&self, key: F) -> HashMap<K, u32>
+    where
+        K: Eq + Hash,
+        F: FnMut(&C) -> K,
+    {
+        self.count_values(&self.bitset, key)
+    }
+
+    /// Counts the occupied ids of `region` per key, like `value_counts`.
+    ///
+    /// Only the intersection of `region` with the occupied ids is visited, and the upper
+    /// bitset layers skip empty parts of it, so this is cheap for small regions of large
+    /// layers.
+    pub fn value_counts_in<K, F>(&self, region: &BitSet, key: F) -> HashMap<K, u32>
+    where
+        K: Eq + Hash,
+        F: FnMut(&C) -> K,
+    {
+        self.count_values(BitSetAnd(&self.bitset, region), key)
+    }
+
+    fn count_values<B, K, F>(&self, ids: B, mut key: F) -> HashMap<K, u32>
+    where
+        B: BitSetLike,
+        K: Eq + Hash,
+        F: FnMut(&C) -> K,
+    {
+        let mut counts = HashMap::new();
+        for id in ids.iter() {
+            *counts.entry(key(unsafe { self.data.get(id) })).or_insert(0) += 1;
+        }
+
+        counts
+    }
+}
@@ -0,0 +1,225 @@
+//! A storage packing small enums into a few bits each.
+
+use std::marker::PhantomData;
+
+use hibitset::BitSet;
+use specs::Join;
+
+use bound;
+use {Id, Index};
+
+/// Components that can be represented by a small amount of bits, like field-less enums.
+pub trait PackedRepr: Copy {
+    /// The amount of bits a value needs, between 1 and 8.
+    const BITS: u32;
+
+    /// Converts the value into its representation, which must be smaller than `1 << BITS`.
+    fn to_bits(self) -> u8;
+
+    /// Converts a representation returned by `to_bits` back into the value.
+    fn from_bits(bits: u8) -> Self;
+}
+
+/// A storage which stores its components in `C::BITS` bits each.
+///
+/// Because there is no memory a reference could point into, components are returned by
+/// value, so this can't be used as a backend of `Storage`. Instead, it offers the same
+/// methods itself, and joining over it yields the decoded components.
+///
+/// Values don't straddle word boundaries, so each `u64` holds `64 / C::BITS` of them.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::{Component, Join, VecStorage};
+/// use specs_static::{PackedEnumStorage, PackedRepr, Storage};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// #[derive(Clone, Copy, Debug, PartialEq)]
+/// enum Material {
+///     Dirt,
+///     Grass,
+///     Sand,
+///     Stone,
+/// }
+///
+/// impl PackedRepr for Material {
+///     const BITS: u32 = 2;
+///
+///     fn to_bits(self) -> u8 {
+///         self as u8
+///     }
+///
+///     fn from_bits(bits: u8) -> Self {
+///         [Material::Dirt, Material::Grass, Material::Sand, Material::Stone][bits as usize]
+///     }
+/// }
+///
+/// struct Tree;
+///
+/// impl Component for Tree {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// fn main() {
+///     let mut materials = PackedEnumStorage::<Material, TileId>::new();
+///     for id in 0..1000 {
+///         let material = if id % 3 == 0 { Material::Grass } else { Material::Sand };
+///         materials.insert(TileId(id), material);
+///     }
+///     assert_eq!(materials.insert(TileId(33), Material::Stone), Some(Material::Grass));
+///     assert_eq!(materials.remove(TileId(34)), Some(Material::Sand));
+///     assert_eq!(materials.get(TileId(34)), None);
+///
+///     // 32 materials fit into one word.
+///     assert_eq!(materials.words().len(), 32);
+///
+///     let mut trees = Storage::<Tree, VecStorage<_>, TileId>::default();
+///     trees.insert(TileId(33), Tree);
+///     trees.insert(TileId(34), Tree);
+///     trees.insert(TileId(99), Tree);
+///
+///     let under_trees: Vec<_> = (&materials, &trees).join().map(|(m, _)| m).collect();
+///     assert_eq!(under_trees, vec![Material::Stone, Material::Grass]);
+/// }
+/// ```
+#[derive(Derivative)]
+#[derivative(Default(bound = ""))]
+pub struct PackedEnumStorage<C, I> {
+    words: Vec<u64>,
+    bitset: BitSet,
+    phantom: PhantomData<(C, I)>,
+}
+
+impl<C, I> PackedEnumStorage<C, I>
+where
+    C: PackedRepr,
+    I: Id,
+{
+    /// Creates an empty storage.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Returns the component at `id`, if there is one.
+    pub fn get(&self, id: I) -> Option<C> {
+        if self.bitset.contains(id.id()) {
+            Some(unsafe { decode(&self.words, id.id()) })
+        } else {
+            None
+        }
+    }
+
+    /// Returns `true` if there's a component at `id`.
+    pub fn contains(&self, id: I) -> bool {
+        self.bitset.contains(id.id())
+    }
+
+    /// Inserts `comp` at `id`. If there already was a value, it will be returned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` exceeds `Storage::MAX_ID`.
+    ///
+    /// ```should_panic
+    /// # extern crate specs_static;
+    /// # use specs_static::{PackedEnumStorage, PackedRepr};
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// # #[derive(Clone, Copy)]
+    /// # struct Wet(bool);
+    /// # impl PackedRepr for Wet {
+    /// #     const BITS: u32 = 1;
+    /// #     fn to_bits(self) -> u8 { self.0 as u8 }
+    /// #     fn from_bits(bits: u8) -> Self { Wet(bits != 0) }
+    /// # }
+    /// # fn main() {
+    /// let mut wet = PackedEnumStorage::<Wet, TileId>::new();
+    /// // Panics: id 16777216 exceeds the bound 16777215 of the storage.
+    /// wet.insert(TileId(1 << 24), Wet(true));
+    /// # }
+    /// ```
+    pub fn insert(&mut self, id: I, comp: C) -> Option<C> {
+        bound::expect_max_id(id.id());
+        let old = self.get(id);
+
+        let (word, shift) = locate::<C>(id.id());
+        if self.words.len() <= word {
+            self.words.resize(word + 1, 0);
+        }
+
+        let bits = u64::from(comp.to_bits());
+        debug_assert!(bits < 1 << C::BITS, "`PackedRepr::to_bits` returned too many bits");
+        let slot = &mut self.words[word];
+        *slot = (*slot & !(value_mask::<C>() << shift)) | ((bits & value_mask::<C>()) << shift);
+        self.bitset.add(id.id());
+
+        old
+    }
+
+    /// Removes the component at `id`.
+    pub fn remove(&mut self, id: I) -> Option<C> {
+        let old = self.get(id);
+        self.bitset.remove(id.id());
+
+        old
+    }
+
+    /// Removes all components.
+    pub fn clear(&mut self) {
+        self.words.clear();
+        self.bitset.clear();
+    }
+
+    /// Returns the mask of all occupied ids.
+    pub fn mask(&self) -> &BitSet {
+        &self.bitset
+    }
+
+    /// Returns the packed words. The component of id `n` is stored in word
+    /// `n / (64 / C::BITS)`, starting at bit `n % (64 / C::BITS) * C::BITS`.
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+}
+
+fn value_mask<C: PackedRepr>() -> u64 {
+    (1 << C::BITS) - 1
+}
+
+fn locate<C: PackedRepr>(id: Index) -> (usize, u32) {
+    // This check is constant, so it's optimized out.
+    assert!(
+        C::BITS >= 1 && C::BITS <= 8,
+        "`PackedRepr::BITS` must be between 1 and 8, but is {}",
+        C::BITS
+    );
+    let per_word = 64 / C::BITS;
+
+    ((id / per_word) as usize, id % per_word * C::BITS)
+}
+
+unsafe fn decode<C: PackedRepr>(words: &[u64], id: Index) -> C {
+    let (word, shift) = locate::<C>(id);
+
+    C::from_bits(((words.get_unchecked(word) >> shift) & value_mask::<C>()) as u8)
+}
+
+impl<'a, C, I> Join for &'a PackedEnumStorage<C, I>
+where
+    C: PackedRepr,
+{
+    type Type = C;
+    type Value = &'a [u64];
+    type Mask = &'a BitSet;
+
+    unsafe fn open(self) -> (Self::Mask, Self::Value) {
+        (&self.bitset, &self.words)
+    }
+
+    unsafe fn get(value: &mut Self::Value, id: Index) -> C {
+        decode(value, id)
+    }
+}
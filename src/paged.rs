@@ -0,0 +1,208 @@
+//! A backend made of fixed-size pages which are never moved once allocated.
+
+use std::ptr;
+
+use hibitset::{BitSet, BitSetLike};
+use specs::storage::{DistinctStorage, UnprotectedStorage};
+
+use alloc::{Slots, Table};
+use {Id, Index, ReserveIds, Storage, StdAlloc, TileAlloc};
+
+/// The amount of slots in one page of a `PagedStorage`.
+pub const PAGE_SIZE: u32 = 4096;
+
+/// A single page of slots.
+type Page<C, A> = Slots<C, A>;
+
+/// A storage backend which allocates pages of `PAGE_SIZE` slots and never moves components
+/// after they have been inserted.
+///
+/// Growing a `VecStorage` reallocates and copies every component, which becomes a noticeable
+/// stall once a layer is hundreds of megabytes large. Inserting into a `PagedStorage` at most
+/// allocates the one page the id falls into (and grows the page table, which only holds
+/// pointers), so the worst-case insertion latency stays bounded.
+///
+/// Unlike `ChunkedVecStorage`, pages are kept around when they become empty, so repeatedly
+/// filling and clearing the same region doesn't allocate; `Storage::shrink_pages` frees them.
+/// Use `reserve` to allocate the page table up front.
+///
+/// The pages and the page table are allocated through the allocator handle `A`, see
+/// `TileAlloc` and `Storage::with_alloc`.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::{Component, Join};
+/// use specs_static::{PagedStorage, Storage, PAGE_SIZE};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// #[derive(Debug, PartialEq)]
+/// struct Light(u32);
+///
+/// impl Component for Light {
+///     type Storage = PagedStorage<Self>;
+/// }
+///
+/// fn main() {
+///     let mut lights = Storage::<Light, PagedStorage<_>, TileId>::default();
+///     unsafe { lights.unprotected_storage_mut() }.reserve(64 * PAGE_SIZE);
+///
+///     for i in 0..64 * PAGE_SIZE / 7 {
+///         lights.insert(TileId(i * 7), Light(i));
+///     }
+///     assert_eq!(lights.unprotected_storage().page_count(), 64);
+///     assert_eq!(lights.get(TileId(7 * 10_000)), Some(&Light(10_000)));
+///
+///     let first = lights.get(TileId(0)).unwrap() as *const Light;
+///     lights.insert(TileId(200 * PAGE_SIZE), Light(1));
+///     // Existing components didn't move.
+///     assert_eq!(first, lights.get(TileId(0)).unwrap() as *const Light);
+///
+///     let removed = (0..PAGE_SIZE).filter(|id| id % 7 == 0).collect::<Vec<_>>();
+///     for &id in &removed {
+///         lights.remove(TileId(id));
+///     }
+///     // The empty page is kept for reuse.
+///     assert_eq!(lights.unprotected_storage().page_count(), 65);
+///     assert_eq!((&lights).join().count() as u32, 64 * PAGE_SIZE / 7 - removed.len() as u32 + 1);
+///
+///     lights.shrink_pages();
+///     assert_eq!(lights.unprotected_storage().page_count(), 64);
+/// }
+/// ```
+pub struct PagedStorage<C, A: TileAlloc = StdAlloc> {
+    pages: Table<Option<Page<C, A>>, A>,
+}
+
+impl<C, A: TileAlloc> Default for PagedStorage<C, A> {
+    fn default() -> Self {
+        PagedStorage::with_alloc(A::default())
+    }
+}
+
+impl<C, A: TileAlloc> PagedStorage<C, A> {
+    /// Creates an empty backend which allocates through `alloc`.
+    pub fn with_alloc(alloc: A) -> Self {
+        PagedStorage {
+            pages: Table::new(alloc),
+        }
+    }
+
+    /// Returns the number of pages that are currently allocated.
+    pub fn page_count(&self) -> usize {
+        self.pages.iter().filter(|page| page.is_some()).count()
+    }
+
+    /// Grows the page table so that pages for all ids below `len` can be allocated
+    /// without reallocating it. No pages are allocated by this.
+    pub fn reserve(&mut self, len: u32) {
+        let pages = len.div_ceil(PAGE_SIZE) as usize;
+        self.pages.grow_with(pages, || None);
+    }
+
+    fn split(id: Index) -> (usize, usize) {
+        ((id / PAGE_SIZE) as usize, (id % PAGE_SIZE) as usize)
+    }
+
+    unsafe fn slot(&self, id: Index) -> *const C {
+        let (page, slot) = Self::split(id);
+
+        match *self.pages.get_unchecked(page) {
+            Some(ref page) => page.get_unchecked(slot).as_ptr(),
+            None => vacant_page(),
+        }
+    }
+
+    unsafe fn slot_mut(&mut self, id: Index) -> *mut C {
+        let (page, slot) = Self::split(id);
+
+        match *self.pages.get_unchecked_mut(page) {
+            Some(ref mut page) => page.get_unchecked_mut(slot).as_mut_ptr(),
+            None => vacant_page(),
+        }
+    }
+}
+
+/// Called when accessing a page which isn't allocated, which the bitset of `Storage` rules out.
+unsafe fn vacant_page() -> ! {
+    debug_assert!(false, "accessed an unallocated page");
+    ::std::hint::unreachable_unchecked()
+}
+
+impl<C, A: TileAlloc> UnprotectedStorage<C> for PagedStorage<C, A> {
+    unsafe fn clean<B>(&mut self, has: B)
+    where
+        B: BitSetLike,
+    {
+        for id in has.iter() {
+            ptr::drop_in_place(self.slot_mut(id));
+        }
+        self.pages.clear();
+    }
+
+    unsafe fn get(&self, id: Index) -> &C {
+        &*self.slot(id)
+    }
+
+    unsafe fn get_mut(&mut self, id: Index) -> &mut C {
+        &mut *self.slot_mut(id)
+    }
+
+    unsafe fn insert(&mut self, id: Index, value: C) {
+        self.reserve(id + 1);
+
+        let (page, slot) = Self::split(id);
+        if self.pages[page].is_none() {
+            let new = Slots::new(PAGE_SIZE as usize, self.pages.alloc());
+            self.pages[page] = Some(new);
+        }
+        let page = self.pages[page].as_mut().expect("allocated above");
+        ptr::write(page.get_unchecked_mut(slot).as_mut_ptr(), value);
+    }
+
+    unsafe fn remove(&mut self, id: Index) -> C {
+        ptr::read(self.slot(id))
+    }
+}
+
+impl<C, A: TileAlloc> ReserveIds for PagedStorage<C, A> {
+    fn reserve_ids(&mut self, len: u32) {
+        self.reserve(len);
+    }
+}
+
+unsafe impl<C, A: TileAlloc> DistinctStorage for PagedStorage<C, A> {}
+
+impl<C, A, I> Storage<C, PagedStorage<C, A>, I>
+where
+    A: TileAlloc,
+    I: Id,
+{
+    /// Creates an empty storage whose backend allocates through `alloc`.
+    pub fn with_alloc(alloc: A) -> Self {
+        // An empty backend is consistent with an empty bitset.
+        let data = PagedStorage::with_alloc(alloc);
+
+        unsafe { Storage::from_parts_unchecked(data, BitSet::new()) }
+    }
+}
+
+impl<C, A, I> Storage<C, PagedStorage<C, A>, I>
+where
+    A: TileAlloc,
+    I: Id,
+{
+    /// Frees all pages which don't contain any components.
+    pub fn shrink_pages(&mut self) {
+        let bitset = &self.bitset;
+        for (i, page) in self.data.pages.iter_mut().enumerate() {
+            let base = i as u32 * PAGE_SIZE;
+            if page.is_some() && !(base..base + PAGE_SIZE).any(|id| bitset.contains(id)) {
+                *page = None;
+            }
+        }
+    }
+}
@@ -0,0 +1,350 @@
+//! A storage keeping each distinct value once, with a small index per tile.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::mem;
+
+use hibitset::BitSet;
+use specs::Join;
+
+use bound;
+use {Id, Index};
+
+/// The palette indices of all tiles, as narrow as the palette allows.
+pub enum Indices {
+    U8(Vec<u8>),
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+impl Indices {
+    fn width(&self) -> usize {
+        match *self {
+            Indices::U8(_) => 1,
+            Indices::U16(_) => 2,
+            Indices::U32(_) => 4,
+        }
+    }
+
+    fn len(&self) -> usize {
+        match *self {
+            Indices::U8(ref v) => v.len(),
+            Indices::U16(ref v) => v.len(),
+            Indices::U32(ref v) => v.len(),
+        }
+    }
+
+    /// Returns the largest palette index which fits.
+    fn max(&self) -> u32 {
+        match *self {
+            Indices::U8(_) => u32::from(u8::MAX),
+            Indices::U16(_) => u32::from(u16::MAX),
+            Indices::U32(_) => u32::MAX,
+        }
+    }
+
+    unsafe fn get_unchecked(&self, id: Index) -> u32 {
+        match *self {
+            Indices::U8(ref v) => u32::from(*v.get_unchecked(id as usize)),
+            Indices::U16(ref v) => u32::from(*v.get_unchecked(id as usize)),
+            Indices::U32(ref v) => *v.get_unchecked(id as usize),
+        }
+    }
+
+    /// Sets the index of `id`, which has to fit into the current width.
+    fn set(&mut self, id: Index, index: u32) {
+        let len = id as usize + 1;
+        match *self {
+            Indices::U8(ref mut v) => {
+                if v.len() < len {
+                    v.resize(len, 0);
+                }
+                v[id as usize] = index as u8;
+            }
+            Indices::U16(ref mut v) => {
+                if v.len() < len {
+                    v.resize(len, 0);
+                }
+                v[id as usize] = index as u16;
+            }
+            Indices::U32(ref mut v) => {
+                if v.len() < len {
+                    v.resize(len, 0);
+                }
+                v[id as usize] = index;
+            }
+        }
+    }
+
+    /// Switches to the next wider representation, keeping all indices.
+    fn widen(&mut self) {
+        let wider = match *self {
+            Indices::U8(ref v) => Indices::U16(v.iter().map(|&i| u16::from(i)).collect()),
+            Indices::U16(ref v) => Indices::U32(v.iter().map(|&i| u32::from(i)).collect()),
+            Indices::U32(_) => unreachable!("palette indices can't get wider than `u32`"),
+        };
+        *self = wider;
+    }
+}
+
+/// A storage for mostly uniform layers, like biomes, keeping every distinct value once in
+/// a palette and only a palette index per tile, similar to the chunk palettes of voxel
+/// games.
+///
+/// The indices start out as `u8` and are widened to `u16` (and `u32`) once the palette
+/// outgrows them. Palette entries are reference counted, so values which are no longer
+/// used by any tile are dropped and their slots reused.
+///
+/// Because a component is shared by all tiles with the same value, handing out mutable
+/// references would change them all, and `Storage` overwrites components through mutable
+/// references. So like `PackedEnumStorage`, this can't be used as a backend of `Storage`,
+/// but offers the same methods itself; `get` and joins return references into the palette.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use std::mem;
+///
+/// use specs::{Component, Join, VecStorage};
+/// use specs_static::{PaletteStorage, Storage};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// #[derive(Clone, Debug, Eq, Hash, PartialEq)]
+/// struct Biome {
+///     name: String,
+///     temperature: i32,
+/// }
+///
+/// fn biome(name: &str, temperature: i32) -> Biome {
+///     Biome { name: name.to_owned(), temperature }
+/// }
+///
+/// struct Village;
+///
+/// impl Component for Village {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// fn main() {
+///     let mut biomes = PaletteStorage::<Biome, TileId>::new();
+///     for id in 0..64 * 64 {
+///         let name = if id % 64 < 50 { "plains" } else { "desert" };
+///         biomes.insert(TileId(id), biome(name, 20));
+///     }
+///     assert_eq!(biomes.insert(TileId(0), biome("lake", 15)), Some(biome("plains", 20)));
+///     assert_eq!(biomes.palette_len(), 3);
+///     assert_eq!(biomes.index_width(), 1);
+///     assert!(biomes.memory_usage() < 64 * 64 * mem::size_of::<Biome>() / 10);
+///
+///     // The lake is gone, and so is its palette entry.
+///     assert_eq!(biomes.remove(TileId(0)), Some(biome("lake", 15)));
+///     assert_eq!(biomes.get(TileId(0)), None);
+///     assert_eq!(biomes.palette_len(), 2);
+///
+///     let mut villages = Storage::<Village, VecStorage<_>, TileId>::default();
+///     villages.insert(TileId(0), Village);
+///     villages.insert(TileId(60), Village);
+///     let names: Vec<_> = (&biomes, &villages).join().map(|(b, _)| b.name.as_str()).collect();
+///     assert_eq!(names, vec!["desert"]);
+///
+///     // The 257th distinct value doesn't fit into `u8` indices.
+///     let mut temperatures = PaletteStorage::<i32, TileId>::new();
+///     for id in 0..256 {
+///         temperatures.insert(TileId(id), id as i32);
+///     }
+///     assert_eq!((temperatures.palette_len(), temperatures.index_width()), (256, 1));
+///     temperatures.insert(TileId(256), 256);
+///     assert_eq!((temperatures.palette_len(), temperatures.index_width()), (257, 2));
+///     for id in 0..257 {
+///         assert_eq!(temperatures.get(TileId(id)), Some(&(id as i32)));
+///     }
+/// }
+/// ```
+pub struct PaletteStorage<C, I> {
+    indices: Indices,
+    /// The values, with `None` for slots which are free.
+    palette: Vec<Option<C>>,
+    /// How many tiles use each palette slot.
+    counts: Vec<u32>,
+    lookup: HashMap<C, u32>,
+    free: Vec<u32>,
+    bitset: BitSet,
+    phantom: PhantomData<I>,
+}
+
+impl<C, I> Default for PaletteStorage<C, I>
+where
+    C: Eq + Hash,
+{
+    fn default() -> Self {
+        PaletteStorage {
+            indices: Indices::U8(Vec::new()),
+            palette: Vec::new(),
+            counts: Vec::new(),
+            lookup: HashMap::new(),
+            free: Vec::new(),
+            bitset: BitSet::new(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<C, I> PaletteStorage<C, I>
+where
+    C: Clone + Eq + Hash,
+    I: Id,
+{
+    /// Creates an empty storage.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Returns the component at `id`, if there is one.
+    pub fn get(&self, id: I) -> Option<&C> {
+        if self.bitset.contains(id.id()) {
+            Some(unsafe { self.value(id.id()) })
+        } else {
+            None
+        }
+    }
+
+    /// Returns `true` if there's a component at `id`.
+    pub fn contains(&self, id: I) -> bool {
+        self.bitset.contains(id.id())
+    }
+
+    /// Inserts `comp` at `id`. If there already was a value, it will be returned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` exceeds `Storage::MAX_ID`.
+    ///
+    /// ```should_panic
+    /// # extern crate specs_static;
+    /// # use specs_static::PaletteStorage;
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// # fn main() {
+    /// let mut biomes = PaletteStorage::<u8, TileId>::new();
+    /// // Panics: id 16777223 exceeds the bound 16777215 of the storage.
+    /// biomes.insert(TileId((1 << 24) + 7), 3);
+    /// # }
+    /// ```
+    pub fn insert(&mut self, id: I, comp: C) -> Option<C> {
+        bound::expect_max_id(id.id());
+        let old = self.remove(id);
+
+        let index = match self.lookup.get(&comp) {
+            Some(&index) => index,
+            None => self.add_to_palette(comp),
+        };
+        self.counts[index as usize] += 1;
+        self.indices.set(id.id(), index);
+        self.bitset.add(id.id());
+
+        old
+    }
+
+    /// Removes the component at `id`.
+    pub fn remove(&mut self, id: I) -> Option<C> {
+        if !self.bitset.remove(id.id()) {
+            return None;
+        }
+
+        let index = unsafe { self.indices.get_unchecked(id.id()) };
+        let count = &mut self.counts[index as usize];
+        *count -= 1;
+        if *count > 0 {
+            return self.palette[index as usize].clone();
+        }
+
+        let comp = self.palette[index as usize]
+            .take()
+            .expect("used palette slots have a value");
+        self.lookup.remove(&comp);
+        self.free.push(index);
+
+        Some(comp)
+    }
+
+    /// Removes all components.
+    pub fn clear(&mut self) {
+        *self = Default::default();
+    }
+
+    /// Returns the mask of all occupied ids.
+    pub fn mask(&self) -> &BitSet {
+        &self.bitset
+    }
+
+    /// Returns the amount of distinct values in the palette.
+    pub fn palette_len(&self) -> usize {
+        self.lookup.len()
+    }
+
+    /// Returns the width of the per-tile palette indices in bytes.
+    pub fn index_width(&self) -> usize {
+        self.indices.width()
+    }
+
+    /// Returns an estimate of the heap memory used for the indices and the palette, in
+    /// bytes. Memory owned by the components themselves isn't included.
+    pub fn memory_usage(&self) -> usize {
+        let entry = mem::size_of::<Option<C>>() + mem::size_of::<u32>();
+        let lookup = self.lookup.capacity() * (mem::size_of::<C>() + mem::size_of::<u32>());
+
+        self.indices.len() * self.indices.width()
+            + self.palette.capacity() * entry
+            + self.free.capacity() * mem::size_of::<u32>()
+            + lookup
+    }
+
+    unsafe fn value(&self, id: Index) -> &C {
+        palette_value(&self.indices, &self.palette, id)
+    }
+
+    /// Puts `comp` into a free palette slot, widening the indices if needed.
+    fn add_to_palette(&mut self, comp: C) -> u32 {
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                self.palette.push(None);
+                self.counts.push(0);
+                self.palette.len() as u32 - 1
+            }
+        };
+        if index > self.indices.max() {
+            self.indices.widen();
+        }
+
+        self.lookup.insert(comp.clone(), index);
+        self.palette[index as usize] = Some(comp);
+
+        index
+    }
+}
+
+unsafe fn palette_value<'a, C>(indices: &Indices, palette: &'a [Option<C>], id: Index) -> &'a C {
+    let index = indices.get_unchecked(id);
+
+    palette[index as usize]
+        .as_ref()
+        .expect("used palette slots have a value")
+}
+
+impl<'a, C, I> Join for &'a PaletteStorage<C, I> {
+    type Type = &'a C;
+    type Value = (&'a Indices, &'a [Option<C>]);
+    type Mask = &'a BitSet;
+
+    unsafe fn open(self) -> (Self::Mask, Self::Value) {
+        (&self.bitset, (&self.indices, &self.palette))
+    }
+
+    unsafe fn get(value: &mut Self::Value, id: Index) -> &'a C {
+        palette_value(value.0, value.1, id)
+    }
+}
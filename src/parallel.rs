@@ -0,0 +1,310 @@
+//! Rayon-based parallel iteration over a `Storage`, usable without a dispatcher.
+
+use hibitset::{BitSetAnd, BitSetLike};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use specs::storage::{DistinctStorage, UnprotectedStorage};
+
+use join::JoinMut;
+use mask::MAX_LEN;
+use region::next_set;
+use {Id, RangeMask, Storage};
+
+impl<C, D, I> Storage<C, D, I>
+where
+    C: Sync,
+    D: UnprotectedStorage<C> + Sync,
+    I: Id,
+{
+    /// Returns a parallel iterator over all occupied ids.
+    ///
+    /// The work is distributed by splitting the bitset, so every id is yielded exactly once.
+    pub fn par_ids<'a>(&'a self) -> impl ParallelIterator<Item = I> + 'a {
+        (&self.bitset).par_iter().map(I::from_u32)
+    }
+
+    /// Returns a parallel iterator over all occupied ids and their components.
+    ///
+    /// Reading only shares the components between the threads, so they don't have to be
+    /// `Send`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate rayon;
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use std::marker::PhantomData;
+    /// use std::sync::MutexGuard;
+    ///
+    /// use rayon::iter::ParallelIterator;
+    /// use specs::VecStorage;
+    /// use specs_static::Storage;
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// // Sync, but bound to the thread which created it.
+    /// struct Light(u32, PhantomData<MutexGuard<'static, ()>>);
+    ///
+    /// fn main() {
+    ///     let mut lights = Storage::<Light, VecStorage<_>, TileId>::default();
+    ///     for id in 0..100 {
+    ///         lights.insert(TileId(id), Light(id % 4, PhantomData));
+    ///     }
+    ///
+    ///     let lit = lights.par_iter().filter(|&(_, light)| light.0 > 0).count();
+    ///     assert_eq!(lit, 75);
+    ///     assert_eq!(lights.par_ids().count(), 100);
+    /// }
+    /// ```
+    pub fn par_iter<'a>(&'a self) -> impl ParallelIterator<Item = (I, &'a C)> + 'a {
+        let data = &self.data;
+
+        (&self.bitset)
+            .par_iter()
+            .map(move |id| (I::from_u32(id), unsafe { data.get(id) }))
+    }
+
+    /// Folds all occupied ids and their components in parallel, the way rayon's `fold` and
+    /// `reduce` do.
+    ///
+    /// Every task starts with a value created by `init` and folds the components of its part
+    /// of the bitset into it with `fold`; `reduce` then combines the values of the tasks. The
+    /// bitset is split along its layers, so empty regions are skipped using the upper layers
+    /// without visiting their ids. How the work is split depends on the thread pool, so the
+    /// result only matches the sequential fold if `reduce` is associative and `init` creates
+    /// its identity.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, Join, VecStorage};
+    /// use specs_static::Storage;
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// struct Elevation(i32);
+    ///
+    /// impl Component for Elevation {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// struct Stats {
+    ///     tiles: u64,
+    ///     min: Option<(i32, TileId)>,
+    ///     max: i32,
+    /// }
+    ///
+    /// fn add(stats: Stats, id: TileId, elevation: &Elevation) -> Stats {
+    ///     Stats {
+    ///         tiles: stats.tiles + 1,
+    ///         min: match stats.min {
+    ///             Some(min) if min <= (elevation.0, id) => Some(min),
+    ///             _ => Some((elevation.0, id)),
+    ///         },
+    ///         max: stats.max.max(elevation.0),
+    ///     }
+    /// }
+    ///
+    /// fn merge(a: Stats, b: Stats) -> Stats {
+    ///     Stats {
+    ///         tiles: a.tiles + b.tiles,
+    ///         min: match (a.min, b.min) {
+    ///             (Some(a), Some(b)) => Some(a.min(b)),
+    ///             (a, b) => a.or(b),
+    ///         },
+    ///         max: a.max.max(b.max),
+    ///     }
+    /// }
+    ///
+    /// fn empty() -> Stats {
+    ///     Stats { tiles: 0, min: None, max: i32::MIN }
+    /// }
+    ///
+    /// fn main() {
+    ///     // Random elevations on random tiles, with big empty areas in between.
+    ///     let mut elevations = Storage::<Elevation, VecStorage<_>, TileId>::default();
+    ///     let mut seed = 7u32;
+    ///     for _ in 0..200_000 {
+    ///         seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+    ///         let id = (seed >> 8) % 1_000_000;
+    ///         if id % 100_000 < 60_000 {
+    ///             elevations.insert(TileId(id), Elevation((seed % 20_000) as i32 - 10_000));
+    ///         }
+    ///     }
+    ///
+    ///     let parallel = elevations.par_fold(empty, add, merge);
+    ///     let sequential = elevations
+    ///         .with_ids()
+    ///         .join()
+    ///         .fold(empty(), |stats, (id, elevation)| add(stats, id, elevation));
+    ///     assert_eq!(parallel, sequential);
+    ///     assert_eq!(parallel.tiles, elevations.join().count() as u64);
+    ///
+    ///     let total = elevations.par_sum_by(|elevation| f64::from(elevation.0));
+    ///     let expected: i64 = elevations.join().map(|elevation| i64::from(elevation.0)).sum();
+    ///     assert_eq!(total, expected as f64);
+    /// }
+    /// ```
+    pub fn par_fold<T, N, F, R>(&self, init: N, fold: F, reduce: R) -> T
+    where
+        T: Send,
+        N: Fn() -> T + Sync,
+        F: Fn(T, I, &C) -> T + Sync,
+        R: Fn(T, T) -> T + Sync,
+    {
+        let data = &self.data;
+        let (init, fold, reduce) = (&init, &fold, &reduce);
+
+        (&self.bitset)
+            .par_iter()
+            .fold(init, move |acc, id| fold(acc, I::from_u32(id), unsafe { data.get(id) }))
+            .reduce(init, reduce)
+    }
+
+    /// Returns the sum of `f` over all components, computed in parallel.
+    ///
+    /// Floating point addition isn't associative, so the result can differ from the
+    /// sequential sum in the last bits, and from run to run, unless all partial sums are
+    /// exact, like for integer values below 2^53. See `par_fold` for an example.
+    pub fn par_sum_by<F>(&self, f: F) -> f64
+    where
+        F: Fn(&C) -> f64 + Sync,
+    {
+        self.par_iter().map(|(_, comp)| f(comp)).sum()
+    }
+}
+
+impl<C, D, I> Storage<C, D, I>
+where
+    C: Send + Sync,
+    D: UnprotectedStorage<C> + DistinctStorage + Send + Sync,
+    I: Id,
+{
+    /// Returns a parallel iterator over all occupied ids and mutable references to their
+    /// components.
+    ///
+    /// The bitset is split into disjoint parts, one per task, so every component is handed
+    /// out to exactly one thread. This requires the backend to implement `DistinctStorage`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate rayon;
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use rayon::iter::ParallelIterator;
+    /// use specs::{Component, VecStorage};
+    /// use specs_static::Storage;
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// struct Elevation(u32);
+    ///
+    /// impl Component for Elevation {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut elevations = Storage::<Elevation, VecStorage<_>, TileId>::default();
+    ///     for i in 0..100_000 {
+    ///         elevations.insert(TileId(i), Elevation(0));
+    ///     }
+    ///
+    ///     elevations
+    ///         .par_iter_mut()
+    ///         .for_each(|(id, elevation)| elevation.0 += id.0);
+    ///
+    ///     assert_eq!(elevations.par_ids().count(), 100_000);
+    ///     assert!(elevations.par_iter().all(|(id, elevation)| elevation.0 == id.0));
+    /// }
+    /// ```
+    pub fn par_iter_mut<'a>(&'a mut self) -> impl ParallelIterator<Item = (I, &'a mut C)> + 'a {
+        // Every thread only accesses the ids it got from the split bitset.
+        let data = JoinMut::new(&mut self.data);
+
+        (&self.bitset)
+            .par_iter()
+            .map(move |id| (I::from_u32(id), unsafe { data.get().get_mut(id) }))
+    }
+
+    /// Calls `f` with every occupied id and its component, in parallel batches of the
+    /// aligned id ranges `k * chunk..(k + 1) * chunk`.
+    ///
+    /// This has less scheduling overhead than `par_iter_mut` when `f` is cheap. Chunks
+    /// without components are skipped using the upper layers of the bitset, and every
+    /// non-empty chunk becomes one task, walking its ids in ascending order. The chunks are
+    /// disjoint, so every component is handed to exactly one thread, which requires the
+    /// backend to implement `DistinctStorage`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate rayon;
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    ///
+    /// use rayon::iter::ParallelIterator;
+    /// use specs::{Component, VecStorage};
+    /// use specs_static::Storage;
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// struct Heat(u32);
+    ///
+    /// impl Component for Heat {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut heats = Storage::<Heat, VecStorage<_>, TileId>::default();
+    ///     for i in (0..300_000).filter(|i| i % 7 != 0 && !(100_000..250_000).contains(i)) {
+    ///         heats.insert(TileId(i), Heat(i));
+    ///     }
+    ///
+    ///     let visits = AtomicUsize::new(0);
+    ///     heats.par_for_each_chunked(1024, |id, heat| {
+    ///         heat.0 = heat.0 * 2 - id.0;
+    ///         visits.fetch_add(1, Ordering::Relaxed);
+    ///     });
+    ///
+    ///     // Every component was visited exactly once.
+    ///     assert_eq!(visits.into_inner(), heats.par_ids().count());
+    ///     assert!(heats.par_iter().all(|(id, heat)| heat.0 == id.0));
+    /// }
+    /// ```
+    pub fn par_for_each_chunked<F>(&mut self, chunk: u32, f: F)
+    where
+        F: Fn(I, &mut C) + Send + Sync,
+    {
+        assert!(chunk > 0, "the chunk size has to be positive");
+
+        let bitset = &self.bitset;
+        let mut chunks = Vec::new();
+        let mut pos = 0;
+        while let Some(id) = next_set(bitset, 0, pos) {
+            let start = id as u64 / u64::from(chunk) * u64::from(chunk);
+            let end = (start + u64::from(chunk)).min(MAX_LEN);
+            chunks.push(RangeMask::from_range(start as u32, end as u32));
+            pos = end as usize;
+            if end == MAX_LEN {
+                break;
+            }
+        }
+
+        // Every task only accesses the ids of its own chunk.
+        let data = JoinMut::new(&mut self.data);
+        chunks.into_par_iter().for_each(|range| {
+            for id in BitSetAnd(&range, bitset).iter() {
+                f(I::from_u32(id), unsafe { data.get().get_mut(id) });
+            }
+        });
+    }
+}
@@ -0,0 +1,198 @@
+//! Taking storages apart into their backend and bitset, and putting them back together.
+
+use std::error::Error;
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem::ManuallyDrop;
+use std::ptr;
+
+use hibitset::BitSet;
+use specs::storage::{NullStorage, UnprotectedStorage};
+
+use epoch::Epoch;
+use stamps::Stamps;
+use {Id, Storage};
+
+/// Backends which can be owned on their own and which can tell whether a bitset describes
+/// their components, so `Storage::from_parts` and `Storage::into_parts` are safe for them.
+///
+/// # Safety
+///
+/// `check_parts` may only succeed if the backend holds a component for every id of the
+/// bitset, and nothing else, so it can be used as the backend of a storage with that bitset.
+/// Dropping the backend on its own has to drop its components, or at least not touch any
+/// memory which doesn't hold a component.
+pub unsafe trait CheckedParts<C>: UnprotectedStorage<C> {
+    /// Checks that the occupied ids of this backend are exactly the ids of `bitset`.
+    fn check_parts(&self, bitset: &BitSet) -> Result<(), PartsError>;
+}
+
+/// Every id is valid for a storage of zero-sized components.
+unsafe impl<C> CheckedParts<C> for NullStorage<C>
+where
+    C: Default,
+{
+    fn check_parts(&self, _bitset: &BitSet) -> Result<(), PartsError> {
+        Ok(())
+    }
+}
+
+/// The error returned by `Storage::from_parts` if the backend and the bitset disagree.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PartsError {
+    /// The bitset marks an id as occupied which the backend has no component for.
+    Vacant {
+        /// The id without a component.
+        id: u32,
+    },
+    /// The backend has a component at an id which the bitset doesn't mark as occupied.
+    Unmarked {
+        /// The id of the component.
+        id: u32,
+    },
+}
+
+impl fmt::Display for PartsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PartsError::Vacant { id } => {
+                write!(f, "id {} is marked as occupied, but has no component", id)
+            }
+            PartsError::Unmarked { id } => {
+                write!(f, "id {} has a component, but isn't marked as occupied", id)
+            }
+        }
+    }
+}
+
+impl Error for PartsError {}
+
+impl<C, D, I> Storage<C, D, I>
+where
+    D: UnprotectedStorage<C>,
+    I: Id,
+{
+    /// Creates an unbounded storage from a backend and the bitset of its occupied ids,
+    /// after checking that they agree.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    ///
+    /// use specs::{Component, VecStorage};
+    /// use specs_static::{DenseSliceStorage, PartsError, Storage};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// static LIVE: AtomicUsize = AtomicUsize::new(0);
+    ///
+    /// struct Tracked(u32);
+    ///
+    /// impl Tracked {
+    ///     fn new(value: u32) -> Self {
+    ///         LIVE.fetch_add(1, Ordering::SeqCst);
+    ///         Tracked(value)
+    ///     }
+    /// }
+    ///
+    /// impl Drop for Tracked {
+    ///     fn drop(&mut self) {
+    ///         LIVE.fetch_sub(1, Ordering::SeqCst);
+    ///     }
+    /// }
+    ///
+    /// impl Component for Tracked {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// type Trackeds = Storage<Tracked, DenseSliceStorage<Tracked>, TileId>;
+    ///
+    /// fn main() {
+    ///     let mut storage = Trackeds::default();
+    ///     for id in 0..10 {
+    ///         storage.insert(TileId(id * 3), Tracked::new(id));
+    ///     }
+    ///     storage.remove(TileId(3));
+    ///
+    ///     // Taking the storage apart neither drops nor leaks components.
+    ///     let (data, mut bitset) = storage.into_parts();
+    ///     assert_eq!(LIVE.load(Ordering::SeqCst), 9);
+    ///     let storage = Trackeds::from_parts(data, bitset.clone()).unwrap();
+    ///     assert_eq!(storage.get(TileId(27)).map(|t| t.0), Some(9));
+    ///     assert_eq!(LIVE.load(Ordering::SeqCst), 9);
+    ///
+    ///     let (data, _) = storage.into_parts();
+    ///     bitset.add(4);
+    ///     bitset.remove(0);
+    ///     let error = Trackeds::from_parts(data, bitset).err();
+    ///     assert_eq!(error, Some(PartsError::Vacant { id: 4 }));
+    ///     // The rejected backend has been dropped with its components.
+    ///     assert_eq!(LIVE.load(Ordering::SeqCst), 0);
+    /// }
+    /// ```
+    pub fn from_parts(data: D, bitset: BitSet) -> Result<Self, PartsError>
+    where
+        D: CheckedParts<C>,
+    {
+        data.check_parts(&bitset)?;
+
+        Ok(unsafe { Self::from_parts_unchecked(data, bitset) })
+    }
+
+    /// Creates an unbounded storage from a backend and the bitset of its occupied ids,
+    /// without checking them.
+    ///
+    /// # Safety
+    ///
+    /// `data` has to hold a component for every id of `bitset`, and must not own a
+    /// component at any other id, which would otherwise not be dropped or be overwritten
+    /// without being dropped.
+    pub unsafe fn from_parts_unchecked(data: D, bitset: BitSet) -> Self {
+        let mut epoch = Epoch::default();
+        epoch.bump();
+
+        Storage {
+            data,
+            bitset,
+            epoch,
+            stamps: Stamps::default(),
+            clear_cursor: None,
+            bound: None,
+            #[cfg(feature = "strict-ids")]
+            debug_bound: None,
+            counters: Default::default(),
+            observers: Default::default(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Takes the storage apart into its backend and the bitset of its occupied ids, without
+    /// dropping any component. `from_parts` puts them back together.
+    pub fn into_parts(self) -> (D, BitSet)
+    where
+        D: CheckedParts<C>,
+    {
+        unsafe { self.into_parts_unchecked() }
+    }
+
+    /// Takes the storage apart like `into_parts`, for backends which don't know on their
+    /// own which of their slots are occupied.
+    ///
+    /// # Safety
+    ///
+    /// The backend has to be put back into a storage with `from_parts_unchecked`, or be
+    /// cleaned with the bitset (`UnprotectedStorage::clean`) before it's dropped. Many
+    /// backends, like `VecStorage`, would otherwise read vacant slots when dropped.
+    pub unsafe fn into_parts_unchecked(self) -> (D, BitSet) {
+        // The fields are moved out exactly once, and `Drop` doesn't run on the husk, so the
+        // ones which own memory and aren't handed out are dropped here.
+        let mut storage = ManuallyDrop::new(self);
+        ptr::drop_in_place(&mut storage.stamps);
+        ptr::drop_in_place(&mut storage.observers);
+
+        (ptr::read(&storage.data), ptr::read(&storage.bitset))
+    }
+}
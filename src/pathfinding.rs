@@ -0,0 +1,479 @@
+//! Finding shortest paths over a layer of movement costs.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use specs::storage::UnprotectedStorage;
+use specs::{Component, VecStorage};
+
+use {Connectivity, Grid2d, Id, Index, Storage};
+
+/// Finds the cheapest path from `start` to `goal` with 4-connectivity, using a fresh `AStar`.
+///
+/// See `AStar::find_path` for the details; use an `AStar` directly for 8-connectivity or to
+/// reuse the search state across calls.
+pub fn astar<C, D, I, F>(
+    grid: &Grid2d,
+    costs: &Storage<C, D, I>,
+    start: I,
+    goal: I,
+    cost_fn: F,
+) -> Option<(Vec<I>, u32)>
+where
+    D: UnprotectedStorage<C>,
+    I: Id,
+    F: Fn(&C) -> Option<u32>,
+{
+    AStar::new(Connectivity::Four).find_path(grid, costs, start, goal, cost_fn)
+}
+
+/// The state of an A* search, which can be reused to avoid allocating for every search.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::{Component, VecStorage};
+/// use specs_static::{astar, AStar, Connectivity, Grid2d, Storage};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// #[derive(Clone, Copy, PartialEq)]
+/// enum Terrain {
+///     Road,
+///     Mud,
+///     Wall,
+/// }
+///
+/// impl Component for Terrain {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// fn cost(terrain: &Terrain) -> Option<u32> {
+///     match *terrain {
+///         Terrain::Road => Some(1),
+///         Terrain::Mud => Some(9),
+///         Terrain::Wall => None,
+///     }
+/// }
+///
+/// fn main() {
+///     // ......
+///     // .####.
+///     // ..~~..
+///     let grid = Grid2d::new(6, 3);
+///     let mut terrain = Storage::<Terrain, VecStorage<_>, TileId>::default();
+///     terrain.fill_range(TileId(0), TileId(grid.len()), Terrain::Road);
+///     for x in 1..5 {
+///         terrain.insert(grid.id(x, 1).unwrap(), Terrain::Wall);
+///     }
+///     for x in 2..4 {
+///         terrain.insert(grid.id(x, 2).unwrap(), Terrain::Mud);
+///     }
+///     let id = |x, y| grid.id::<TileId>(x, y).unwrap();
+///
+///     // A straight corridor.
+///     let (path, total) = astar(&grid, &terrain, id(0, 0), id(5, 0), cost).unwrap();
+///     assert_eq!(path, (0..6).map(|x| id(x, 0)).collect::<Vec<_>>());
+///     assert_eq!(total, 5);
+///
+///     // Around the wall, over the road instead of through the mud.
+///     let (path, total) = astar(&grid, &terrain, id(1, 2), id(4, 2), cost).unwrap();
+///     assert_eq!(total, 11);
+///     assert_eq!(path.first(), Some(&id(1, 2)));
+///     assert_eq!(path.last(), Some(&id(4, 2)));
+///     assert!(path.contains(&id(0, 1)) && path.contains(&id(5, 1)));
+///
+///     // Diagonal steps cost the same as straight ones.
+///     let mut search = AStar::new(Connectivity::Eight);
+///     let (path, total) = search.find_path(&grid, &terrain, id(0, 2), id(5, 2), cost).unwrap();
+///     assert_eq!(total, 7);
+///     assert_eq!(path.len(), 8);
+///
+///     // Start and goal are the same.
+///     assert_eq!(astar(&grid, &terrain, id(3, 0), id(3, 0), cost), Some((vec![id(3, 0)], 0)));
+///
+///     // The goal is walled in, or has no terrain at all.
+///     terrain.insert(id(0, 1), Terrain::Wall);
+///     terrain.insert(id(5, 1), Terrain::Wall);
+///     assert_eq!(search.find_path(&grid, &terrain, id(0, 0), id(3, 2), cost), None);
+///     terrain.remove(id(5, 0));
+///     assert_eq!(search.find_path(&grid, &terrain, id(0, 0), id(5, 0), cost), None);
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct AStar {
+    connectivity: Connectivity,
+    open: BinaryHeap<Reverse<(u32, u32, Index)>>,
+    cost: Vec<u32>,
+    parent: Vec<Index>,
+    seen: Vec<u32>,
+    generation: u32,
+}
+
+impl AStar {
+    /// Creates the state for searches over tiles adjacent with `connectivity`.
+    pub fn new(connectivity: Connectivity) -> Self {
+        AStar {
+            connectivity,
+            open: BinaryHeap::new(),
+            cost: Vec::new(),
+            parent: Vec::new(),
+            seen: Vec::new(),
+            generation: 0,
+        }
+    }
+
+    /// Returns the connectivity the searches use.
+    pub fn connectivity(&self) -> Connectivity {
+        self.connectivity
+    }
+
+    /// Finds the cheapest path from `start` to `goal`, returning its tiles from `start` to
+    /// `goal` and its total cost.
+    ///
+    /// Entering a tile costs `cost_fn` of its component in `costs`. Tiles for which it
+    /// returns `None` are impassable, and so are tiles without a component. The cost of
+    /// `start` itself isn't counted. Diagonal steps, if enabled, cost the same as straight
+    /// ones and may cut corners.
+    ///
+    /// Returns `None` if the goal can't be reached or if `start` or `goal` are outside the
+    /// grid. The heuristic assumes that every step costs at least 1; with cheaper tiles, the
+    /// path found may not be the cheapest one.
+    pub fn find_path<C, D, I, F>(
+        &mut self,
+        grid: &Grid2d,
+        costs: &Storage<C, D, I>,
+        start: I,
+        goal: I,
+        cost_fn: F,
+    ) -> Option<(Vec<I>, u32)>
+    where
+        D: UnprotectedStorage<C>,
+        I: Id,
+        F: Fn(&C) -> Option<u32>,
+    {
+        grid.coords(start)?;
+        let goal_coords = grid.coords(goal)?;
+        if start == goal {
+            return Some((vec![start], 0));
+        }
+        costs.get(goal).and_then(&cost_fn)?;
+
+        self.reset(grid.len() as usize);
+        let connectivity = self.connectivity;
+        let heuristic = |id: I| {
+            let coords = grid.coords(id).expect("neighbors are inside the grid");
+
+            connectivity.distance(coords, goal_coords)
+        };
+        let start_h = heuristic(start);
+        let (start, goal) = (start.id(), goal.id());
+        self.visit(start, 0, start);
+        self.open.push(Reverse((start_h, 0, start)));
+
+        while let Some(Reverse((_, cost, id))) = self.open.pop() {
+            if cost > self.cost[id as usize] {
+                continue;
+            }
+            if id == goal {
+                return Some((self.path(start, goal), cost));
+            }
+
+            for next in grid.neighbors(I::from_u32(id), connectivity) {
+                let step = match costs.get(next).and_then(&cost_fn) {
+                    Some(step) => step,
+                    None => continue,
+                };
+                let next_cost = cost.saturating_add(step);
+                let index = next.id() as usize;
+                if self.seen[index] != self.generation || next_cost < self.cost[index] {
+                    self.visit(next.id(), next_cost, id);
+                    let estimate = next_cost.saturating_add(heuristic(next));
+                    self.open.push(Reverse((estimate, next_cost, next.id())));
+                }
+            }
+        }
+
+        None
+    }
+
+    fn reset(&mut self, len: usize) {
+        self.open.clear();
+        if self.cost.len() < len {
+            self.cost.resize(len, 0);
+            self.parent.resize(len, 0);
+            self.seen.resize(len, 0);
+        }
+
+        self.generation = self.generation.wrapping_add(1);
+        if self.generation == 0 {
+            for seen in &mut self.seen {
+                *seen = 0;
+            }
+            self.generation = 1;
+        }
+    }
+
+    fn visit(&mut self, id: Index, cost: u32, parent: Index) {
+        let index = id as usize;
+        self.seen[index] = self.generation;
+        self.cost[index] = cost;
+        self.parent[index] = parent;
+    }
+
+    fn path<I: Id>(&self, start: Index, goal: Index) -> Vec<I> {
+        let mut path = vec![I::from_u32(goal)];
+        let mut id = goal;
+        while id != start {
+            id = self.parent[id as usize];
+            path.push(I::from_u32(id));
+        }
+        path.reverse();
+
+        path
+    }
+}
+
+/// A tile of a flow field, created with `flow_field`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct FlowCell {
+    /// The total cost of the cheapest path from this tile to one of the goals.
+    pub cost: u32,
+    /// The offset `(dx, dy)` to the neighbor the cheapest path continues with, or `None` for
+    /// the goals themselves.
+    pub direction: Option<(i8, i8)>,
+}
+
+impl Component for FlowCell {
+    type Storage = VecStorage<Self>;
+}
+
+/// Computes a flow field towards `goals` with 4-connectivity.
+///
+/// See `flow_field_into` for the details.
+pub fn flow_field<C, D, I, F>(
+    grid: &Grid2d,
+    costs: &Storage<C, D, I>,
+    goals: &[I],
+    cost_fn: F,
+) -> Storage<FlowCell, VecStorage<FlowCell>, I>
+where
+    D: UnprotectedStorage<C>,
+    I: Id,
+    F: Fn(&C) -> Option<u32>,
+{
+    let mut field = Storage::default();
+    flow_field_into(grid, costs, goals, Connectivity::Four, cost_fn, &mut field);
+
+    field
+}
+
+/// Computes, for every tile from which one of `goals` can be reached, the cost of the
+/// cheapest path to the nearest goal and the direction this path starts with, and writes
+/// them to `field`, which is cleared first.
+///
+/// The costs are the same as for `AStar::find_path`: entering a tile costs `cost_fn` of its
+/// component, and tiles without a component or for which `cost_fn` returns `None` are
+/// impassable and don't get a cell. Following the directions from any tile leads to a goal
+/// along a cheapest path, so agents only have to look at the cell they're standing on.
+///
+/// This is a multi-source Dijkstra search. If no tile costs more than `BUCKET_LIMIT`, which
+/// is the common case, the tiles are queued in a bucket queue with one bucket per possible
+/// cost ahead of the current one, which makes the search linear in the amount of tiles.
+/// Otherwise, a binary heap is used.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::{Component, VecStorage};
+/// use specs_static::{astar, flow_field, Grid2d, Storage};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// struct Cost(Option<u32>);
+///
+/// impl Component for Cost {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// fn main() {
+///     let grid = Grid2d::new(32, 32);
+///     let mut costs = Storage::<Cost, VecStorage<_>, TileId>::default();
+///     for y in 0..32 {
+///         for x in 0..32 {
+///             let cost = match (x, y) {
+///                 (16, 2..=29) | (4..=27, 24) => None,
+///                 _ => Some(1 + (x * 7 + y * 13) % 5),
+///             };
+///             costs.insert(grid.id(x, y).unwrap(), Cost(cost));
+///         }
+///     }
+///     let goals = [grid.id(2, 2).unwrap(), grid.id(30, 30).unwrap()];
+///
+///     let field = flow_field(&grid, &costs, &goals, |cost| cost.0);
+///     assert_eq!(field.get(goals[0]).map(|cell| cell.cost), Some(0));
+///     assert_eq!(field.get(grid.id(16, 10).unwrap()), None);
+///
+///     for y in 0..32 {
+///         for x in 0..32 {
+///             let mut id = match grid.id(x, y).and_then(|id| field.get(id).map(|_| id)) {
+///                 Some(id) => id,
+///                 None => continue,
+///             };
+///
+///             // The cost matches the cheapest path to the nearest goal.
+///             let cheapest = goals
+///                 .iter()
+///                 .filter_map(|&goal| astar(&grid, &costs, id, goal, |cost| cost.0))
+///                 .map(|(_, cost)| cost)
+///                 .min();
+///             assert_eq!(Some(field.get(id).unwrap().cost), cheapest);
+///
+///             // Following the directions strictly decreases the cost until a goal is reached.
+///             while let Some((dx, dy)) = field.get(id).unwrap().direction {
+///                 let (x, y) = grid.coords(id).unwrap();
+///                 let (x, y) = ((x as i32 + dx as i32) as u32, (y as i32 + dy as i32) as u32);
+///                 let next = grid.id(x, y).unwrap();
+///                 assert!(field.get(next).unwrap().cost < field.get(id).unwrap().cost);
+///                 id = next;
+///             }
+///             assert!(goals.contains(&id));
+///         }
+///     }
+/// }
+/// ```
+pub fn flow_field_into<C, D, I, F>(
+    grid: &Grid2d,
+    costs: &Storage<C, D, I>,
+    goals: &[I],
+    connectivity: Connectivity,
+    cost_fn: F,
+    field: &mut Storage<FlowCell, VecStorage<FlowCell>, I>,
+) where
+    D: UnprotectedStorage<C>,
+    I: Id,
+    F: Fn(&C) -> Option<u32>,
+{
+    field.clear();
+
+    // Costs of entering every tile, with `IMPASSABLE` for impassable ones, and the best
+    // cells found so far. Looking these up is much cheaper than going through the storages.
+    const IMPASSABLE: u32 = u32::MAX;
+    let steps: Vec<u32> = (0..grid.len())
+        .map(|id| match costs.get(I::from_u32(id)).and_then(&cost_fn) {
+            Some(step) => step.min(IMPASSABLE - 1),
+            None => IMPASSABLE,
+        })
+        .collect();
+    let mut cells: Vec<Option<FlowCell>> = vec![None; steps.len()];
+    let max_step = steps.iter().cloned().filter(|&step| step != IMPASSABLE).max();
+    let mut open = FlowQueue::new(max_step.unwrap_or(0), grid.len());
+
+    for &goal in goals {
+        let index = goal.id() as usize;
+        if grid.coords(goal).is_some() && steps[index] != IMPASSABLE {
+            cells[index] = Some(FlowCell { cost: 0, direction: None });
+            open.push(0, goal.id());
+        }
+    }
+
+    while let Some((cost, id)) = open.pop() {
+        if cells[id as usize].is_none_or(|cell| cost > cell.cost) {
+            continue;
+        }
+
+        // Moving from a neighbor to this tile costs as much as entering this tile.
+        let cost = cost.saturating_add(steps[id as usize]);
+        let (x, y) = grid.coords(I::from_u32(id)).expect("ids in the queue are inside the grid");
+
+        for prev in grid.neighbors(I::from_u32(id), connectivity) {
+            let index = prev.id() as usize;
+            let reached = cells[index].is_some_and(|cell| cell.cost <= cost);
+            if reached || steps[index] == IMPASSABLE {
+                continue;
+            }
+
+            let (px, py) = grid.coords(prev).expect("neighbors are inside the grid");
+            let direction = Some(((x as i64 - px as i64) as i8, (y as i64 - py as i64) as i8));
+            cells[index] = Some(FlowCell { cost, direction });
+            open.push(cost, prev.id());
+        }
+    }
+
+    for (id, cell) in cells.into_iter().enumerate() {
+        if let Some(cell) = cell {
+            field.insert(I::from_u32(id as u32), cell);
+        }
+    }
+}
+
+/// The highest cost of entering a tile for which `flow_field_into` uses a bucket queue.
+pub const BUCKET_LIMIT: u32 = 1024;
+
+/// The queue of `flow_field_into`, ordered by cost.
+enum FlowQueue {
+    Buckets {
+        buckets: Vec<Vec<Index>>,
+        current: u32,
+        len: usize,
+    },
+    Heap(BinaryHeap<Reverse<(u32, Index)>>),
+}
+
+impl FlowQueue {
+    fn new(max_step: u32, tiles: u32) -> Self {
+        // Buckets are picked by the cost modulo their amount, so the costs must not saturate.
+        let fits = u64::from(max_step) * u64::from(tiles) < u64::from(u32::MAX);
+
+        match max_step <= BUCKET_LIMIT && fits {
+            true => FlowQueue::Buckets {
+                buckets: vec![Vec::new(); max_step as usize + 1],
+                current: 0,
+                len: 0,
+            },
+            false => FlowQueue::Heap(BinaryHeap::new()),
+        }
+    }
+
+    fn push(&mut self, cost: u32, id: Index) {
+        match *self {
+            FlowQueue::Buckets {
+                ref mut buckets,
+                ref mut len,
+                ..
+            } => {
+                let index = cost as usize % buckets.len();
+                buckets[index].push(id);
+                *len += 1;
+            }
+            FlowQueue::Heap(ref mut heap) => heap.push(Reverse((cost, id))),
+        }
+    }
+
+    fn pop(&mut self) -> Option<(u32, Index)> {
+        match *self {
+            FlowQueue::Buckets {
+                ref mut buckets,
+                ref mut current,
+                ref mut len,
+            } => {
+                while *len > 0 {
+                    let index = *current as usize % buckets.len();
+                    if let Some(id) = buckets[index].pop() {
+                        *len -= 1;
+
+                        return Some((*current, id));
+                    }
+                    *current += 1;
+                }
+
+                None
+            }
+            FlowQueue::Heap(ref mut heap) => heap.pop().map(|Reverse(entry)| entry),
+        }
+    }
+}
@@ -0,0 +1,35 @@
+//! The types and traits needed for most uses of this crate, plus `Join` from Specs.
+//!
+//! This exports a `Storage` just like `specs::prelude` does, so import the items you need
+//! from Specs by name when using this prelude.
+//!
+//! ```
+//! extern crate specs;
+//! extern crate specs_static;
+//!
+//! use specs::{Component, VecStorage, World, WorldExt};
+//! use specs_static::prelude::*;
+//!
+//! # specs_static::define_id!(pub struct TileId;);
+//! struct Lava;
+//!
+//! impl Component for Lava {
+//!     type Storage = VecStorage<Self>;
+//! }
+//!
+//! fn main() {
+//!     let mut world = World::new();
+//!     world.register_tile_comp::<Lava, TileId>();
+//!
+//!     let grid = Grid2d::new(8, 8);
+//!     let mut lava = world.write_resource::<Storage<Lava, VecStorage<Lava>, TileId>>();
+//!     lava.insert(grid.id(2, 3).unwrap(), Lava);
+//!     assert_eq!((&*lava).join().count(), 1);
+//! }
+//! ```
+
+#[cfg(feature = "parallel")]
+pub use specs::ParJoin;
+pub use specs::Join;
+
+pub use {Grid2d, Id, ReadTiles, Storage, TileBundle, TileGet, TileWorldExt, WriteTiles};
@@ -0,0 +1,176 @@
+//! Creating tile storages with room for every tile of the map.
+
+use hibitset::BitSet;
+use shred::{SetupHandler, World};
+use specs::storage::{
+    BTreeStorage as SpecsBTreeStorage, DefaultVecStorage, DenseVecStorage, FlaggedStorage,
+    HashMapStorage, NullStorage, UnprotectedStorage,
+};
+use specs::VecStorage;
+
+use {Grid2d, Id, Storage};
+
+/// Backends which can make room for a number of ids up front, so inserting them doesn't
+/// reallocate.
+///
+/// `TileWorldExt::register_tile_comp` and `GridSized` use this to size new storages for the
+/// `Grid2d` resource. Specs' own backends don't expose their allocations, so they implement it
+/// by doing nothing, and only the storage's bitset is sized for them; `SliceStorage` and
+/// `DenseSliceStorage` are the equivalents of `VecStorage` and `DenseVecStorage` which do make
+/// room. A backend of your own which can't reserve implements it the same way.
+pub trait ReserveIds {
+    /// Makes room for the ids below `len`.
+    fn reserve_ids(&mut self, len: u32);
+}
+
+macro_rules! reserve_nothing {
+    ($($backend:ident),*) => {
+        $(
+            impl<C> ReserveIds for $backend<C> {
+                fn reserve_ids(&mut self, _: u32) {}
+            }
+        )*
+    };
+}
+
+reserve_nothing!(
+    DefaultVecStorage,
+    DenseVecStorage,
+    HashMapStorage,
+    NullStorage,
+    SpecsBTreeStorage,
+    VecStorage
+);
+
+impl<C, T> ReserveIds for FlaggedStorage<C, T> {
+    fn reserve_ids(&mut self, _: u32) {}
+}
+
+impl<C, D, I> Storage<C, D, I>
+where
+    D: UnprotectedStorage<C> + ReserveIds,
+    I: Id,
+{
+    /// Makes room for the ids below `len` in the backend and the bitset, so inserting them
+    /// doesn't reallocate.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::Component;
+    /// use specs_static::{SliceStorage, Storage};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// struct Fog(u8);
+    ///
+    /// impl Component for Fog {
+    ///     type Storage = SliceStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut fog = Storage::<Fog, SliceStorage<_>, TileId>::default();
+    ///     fog.insert(TileId(3), Fog(10));
+    ///     fog.reserve_ids(1000);
+    ///     assert!(fog.unprotected_storage().capacity() >= 1000);
+    ///
+    ///     // Components inserted before stay where they are.
+    ///     assert_eq!(fog.get(TileId(3)).map(|fog| fog.0), Some(10));
+    /// }
+    /// ```
+    pub fn reserve_ids(&mut self, len: u32) {
+        self.data.reserve_ids(len);
+
+        if len > 0 {
+            let mut bitset = BitSet::with_capacity(len);
+            bitset |= &self.bitset;
+            self.bitset = bitset;
+        }
+    }
+}
+
+/// A shred `SetupHandler` which creates a missing storage resource with room for every tile
+/// of the `Grid2d` resource, or empty if there is no grid.
+///
+/// `Read` and `Write` create missing resources with `Default`, which knows nothing about the
+/// map, so the first inserts reallocate over and over. Fetching through
+/// `Read<Storage<C, D, I>, GridSized>` instead creates the storage the same way
+/// `TileWorldExt::register_tile_comp` does.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::prelude::*;
+/// use specs_static::prelude::*;
+/// use specs_static::{GridSized, SliceStorage};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// struct Grass(u8);
+///
+/// impl Component for Grass {
+///     type Storage = SliceStorage<Self>;
+/// }
+///
+/// type Lawn = specs_static::Storage<Grass, SliceStorage<Grass>, TileId>;
+///
+/// struct Sow;
+///
+/// impl<'a> System<'a> for Sow {
+///     type SystemData = (ReadExpect<'a, Grid2d>, Write<'a, Lawn, GridSized>);
+///
+///     fn run(&mut self, (grid, mut lawn): Self::SystemData) {
+///         // Filling the whole map never grows the backend.
+///         let capacity = lawn.unprotected_storage().capacity();
+///         assert!(capacity >= grid.len() as usize);
+///         for id in 0..grid.len() {
+///             lawn.insert(TileId(id), Grass(1));
+///         }
+///         assert_eq!(lawn.unprotected_storage().capacity(), capacity);
+///     }
+/// }
+///
+/// fn main() {
+///     let mut world = World::new();
+///     world.insert(Grid2d::new(64, 48));
+///
+///     let mut dispatcher = DispatcherBuilder::new().with(Sow, "sow", &[]).build();
+///     dispatcher.setup(&mut world);
+///     dispatcher.dispatch(&world);
+///
+///     assert_eq!((&*world.fetch::<Lawn>()).join().count(), 64 * 48);
+/// }
+/// ```
+pub struct GridSized;
+
+impl<C, D, I> SetupHandler<Storage<C, D, I>> for GridSized
+where
+    C: Send + Sync + 'static,
+    D: UnprotectedStorage<C> + Default + ReserveIds + Send + Sync + 'static,
+    I: Id,
+{
+    fn setup(world: &mut World) {
+        if !world.has_value::<Storage<C, D, I>>() {
+            let storage = presized::<C, D, I>(world);
+            world.insert(storage);
+        }
+    }
+}
+
+/// Creates an empty storage with room for every tile of the `Grid2d` resource, if any.
+pub(crate) fn presized<C, D, I>(world: &World) -> Storage<C, D, I>
+where
+    D: UnprotectedStorage<C> + Default + ReserveIds,
+    I: Id,
+{
+    let mut storage = Storage::default();
+    if let Some(grid) = world.try_fetch::<Grid2d>() {
+        storage.reserve_ids(grid.len());
+    }
+
+    storage
+}
@@ -0,0 +1,257 @@
+//! Counting the operations on every storage, with the `profile` feature.
+//!
+//! Without the feature, the counters are zero-sized and counting compiles to nothing.
+
+#[cfg(feature = "profile")]
+use std::fmt;
+#[cfg(feature = "profile")]
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[cfg(all(feature = "profile", not(feature = "specs")))]
+use liballoc::vec::Vec;
+
+#[cfg(feature = "profile")]
+use {Id, RawStorage, Storage};
+
+/// The counters of a storage, bumped with relaxed atomics so read paths don't need `&mut`.
+///
+/// Public but not exported, since joins over storages carry it in their `Join::Value`.
+#[cfg(feature = "profile")]
+#[derive(Debug, Default)]
+pub struct Counters {
+    gets: AtomicU64,
+    get_muts: AtomicU64,
+    inserts: AtomicU64,
+    removes: AtomicU64,
+    joined: AtomicU64,
+}
+
+#[cfg(not(feature = "profile"))]
+#[derive(Debug, Default)]
+pub(crate) struct Counters;
+
+#[cfg(feature = "profile")]
+impl Counters {
+    pub(crate) fn get(&self) {
+        self.gets.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn get_mut(&self) {
+        self.get_muts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn insert(&self) {
+        self.inserts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn remove(&self) {
+        self.removes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[cfg(feature = "specs")]
+    pub(crate) fn join(&self) {
+        self.joined.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> OpCounters {
+        OpCounters {
+            gets: self.gets.load(Ordering::Relaxed),
+            get_muts: self.get_muts.load(Ordering::Relaxed),
+            inserts: self.inserts.load(Ordering::Relaxed),
+            removes: self.removes.load(Ordering::Relaxed),
+            joined: self.joined.load(Ordering::Relaxed),
+        }
+    }
+
+    fn take(&self) -> OpCounters {
+        OpCounters {
+            gets: self.gets.swap(0, Ordering::Relaxed),
+            get_muts: self.get_muts.swap(0, Ordering::Relaxed),
+            inserts: self.inserts.swap(0, Ordering::Relaxed),
+            removes: self.removes.swap(0, Ordering::Relaxed),
+            joined: self.joined.swap(0, Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(not(feature = "profile"))]
+impl Counters {
+    #[inline(always)]
+    pub(crate) fn get(&self) {}
+
+    #[inline(always)]
+    pub(crate) fn get_mut(&self) {}
+
+    #[inline(always)]
+    pub(crate) fn insert(&self) {}
+
+    #[inline(always)]
+    pub(crate) fn remove(&self) {}
+}
+
+/// How often the operations of a storage have been called, see `Storage::op_counters`.
+#[cfg(feature = "profile")]
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct OpCounters {
+    /// Calls of `get`.
+    pub gets: u64,
+    /// Calls of `get_mut`.
+    pub get_muts: u64,
+    /// Calls of `insert`, including ones replacing a component.
+    pub inserts: u64,
+    /// Calls of `remove`, including ones for vacant ids.
+    pub removes: u64,
+    /// Components handed out by joins over `&Storage` and `&mut Storage`.
+    pub joined: u64,
+}
+
+#[cfg(feature = "profile")]
+impl<C, D, I> Storage<C, D, I>
+where
+    D: RawStorage<C>,
+    I: Id,
+{
+    /// Returns how often the operations of this storage have been called since it was
+    /// created or the counters were last reset.
+    ///
+    /// `get`, `get_mut`, `insert` and `remove` are counted, as well as every component a join
+    /// hands out. The proven accesses of `proofs` and `proofs_mut` count as `get` and
+    /// `get_mut`. Other accessors, like the slice access of backends, aren't.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, Join, VecStorage};
+    /// use specs_static::{OpCounters, Storage};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// struct Heat(f32);
+    ///
+    /// impl Component for Heat {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut heat = Storage::<Heat, VecStorage<_>, TileId>::default();
+    ///     for id in 0..10 {
+    ///         heat.insert(TileId(id), Heat(20.0));
+    ///     }
+    ///     heat.insert(TileId(3), Heat(80.0));
+    ///     heat.remove(TileId(9));
+    ///     heat.remove(TileId(9));
+    ///     heat.get(TileId(3));
+    ///     heat.get(TileId(42));
+    ///     heat.get_mut(TileId(4)).unwrap().0 += 1.0;
+    ///     heat.proofs_mut(|mut heat| {
+    ///         let token = heat.check(TileId(5)).unwrap();
+    ///         let doubled = heat.get_proven(token).0 * 2.0;
+    ///         heat.get_proven_mut(token).0 = doubled;
+    ///     });
+    ///     for heat in (&mut heat).join() {
+    ///         heat.0 -= 0.5;
+    ///     }
+    ///     let warm = (&heat).join().filter(|heat| heat.0 > 50.0).count();
+    ///     assert_eq!(warm, 1);
+    ///
+    ///     let expected = OpCounters {
+    ///         gets: 3,
+    ///         get_muts: 2,
+    ///         inserts: 11,
+    ///         removes: 2,
+    ///         joined: 18,
+    ///     };
+    ///     assert_eq!(heat.op_counters(), expected);
+    ///
+    ///     // Starting the next frame.
+    ///     assert_eq!(heat.take_op_counters(), expected);
+    ///     assert_eq!(heat.op_counters(), OpCounters::default());
+    /// }
+    /// ```
+    pub fn op_counters(&self) -> OpCounters {
+        self.counters.snapshot()
+    }
+
+    /// Returns the counters like `op_counters` and resets them to zero, for example once
+    /// per frame.
+    pub fn take_op_counters(&self) -> OpCounters {
+        self.counters.take()
+    }
+}
+
+/// The operation counters of all layers of an id space, see
+/// `TileWorldExt::tile_profile_report`.
+///
+/// Its `Display` implementation prints a table with one row per layer.
+#[cfg(feature = "profile")]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ProfileReport {
+    /// The component type names of the layers with their counters, in registration order.
+    pub layers: Vec<(&'static str, OpCounters)>,
+}
+
+#[cfg(feature = "profile")]
+impl ProfileReport {
+    /// Returns the counters of the layer of component type `name`, like `layers` lists it.
+    pub fn layer(&self, name: &str) -> Option<OpCounters> {
+        self.layers
+            .iter()
+            .find(|&&(layer, _)| layer == name)
+            .map(|&(_, counters)| counters)
+    }
+
+    /// Returns the sums of the counters of all layers.
+    pub fn total(&self) -> OpCounters {
+        self.layers
+            .iter()
+            .fold(OpCounters::default(), |total, &(_, counters)| OpCounters {
+                gets: total.gets + counters.gets,
+                get_muts: total.get_muts + counters.get_muts,
+                inserts: total.inserts + counters.inserts,
+                removes: total.removes + counters.removes,
+                joined: total.joined + counters.joined,
+            })
+    }
+}
+
+#[cfg(feature = "profile")]
+impl fmt::Display for ProfileReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let width = self
+            .layers
+            .iter()
+            .map(|&(name, _)| name.len())
+            .chain(Some("layer".len()))
+            .max()
+            .unwrap_or(0);
+
+        writeln!(
+            f,
+            "{:<w$} {:>10} {:>10} {:>10} {:>10} {:>10}",
+            "layer",
+            "gets",
+            "get_muts",
+            "inserts",
+            "removes",
+            "joined",
+            w = width
+        )?;
+        for &(name, ref c) in &self.layers {
+            writeln!(
+                f,
+                "{:<w$} {:>10} {:>10} {:>10} {:>10} {:>10}",
+                name,
+                c.gets,
+                c.get_muts,
+                c.inserts,
+                c.removes,
+                c.joined,
+                w = width
+            )?;
+        }
+
+        Ok(())
+    }
+}
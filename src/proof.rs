@@ -0,0 +1,246 @@
+//! Tokens proving that an id is occupied, so accessing it doesn't need to check again.
+
+use std::marker::PhantomData;
+
+use specs::storage::UnprotectedStorage;
+
+use {Id, Storage};
+
+/// An invariant lifetime which ties tokens to the one storage they were created for.
+type Brand<'id> = PhantomData<fn(&'id ()) -> &'id ()>;
+
+/// A proof that `id` was occupied when this token was created.
+///
+/// Tokens can only be created by `Proofs::check` or `ProofsMut::check` and only be used
+/// with the same scope, which doesn't allow removing components. Thus the id is guaranteed
+/// to still be occupied whenever the token is used.
+#[derive(Clone, Copy, Debug)]
+pub struct Occupied<'id, I> {
+    id: I,
+    brand: Brand<'id>,
+}
+
+impl<'id, I> Occupied<'id, I>
+where
+    I: Id,
+{
+    /// Returns the id this token was created for.
+    pub fn id(&self) -> I {
+        self.id
+    }
+}
+
+/// Read access to a storage which can hand out `Occupied` tokens.
+///
+/// Obtained with `Storage::proofs`.
+pub struct Proofs<'a, 'id, C: 'a, D: 'a + UnprotectedStorage<C>, I: 'a> {
+    storage: &'a Storage<C, D, I>,
+    brand: Brand<'id>,
+}
+
+impl<'a, 'id, C, D, I> Proofs<'a, 'id, C, D, I>
+where
+    D: UnprotectedStorage<C>,
+    I: Id,
+{
+    /// Returns a token for `id` if it's occupied.
+    pub fn check(&self, id: I) -> Option<Occupied<'id, I>> {
+        check(self.storage, id)
+    }
+
+    /// Returns the component of an id which is known to be occupied.
+    pub fn get_proven(&self, token: Occupied<'id, I>) -> &'a C {
+        self.storage.counters.get();
+        unsafe { self.storage.data.get(token.id.id()) }
+    }
+
+    /// Returns the underlying storage.
+    pub fn storage(&self) -> &'a Storage<C, D, I> {
+        self.storage
+    }
+}
+
+/// Mutable access to a storage which can hand out `Occupied` tokens.
+///
+/// Components can be modified and inserted, but not removed. Obtained with
+/// `Storage::proofs_mut`.
+pub struct ProofsMut<'a, 'id, C: 'a, D: 'a + UnprotectedStorage<C>, I: 'a> {
+    storage: &'a mut Storage<C, D, I>,
+    brand: Brand<'id>,
+}
+
+impl<'a, 'id, C, D, I> ProofsMut<'a, 'id, C, D, I>
+where
+    D: UnprotectedStorage<C>,
+    I: Id,
+{
+    /// Returns a token for `id` if it's occupied.
+    pub fn check(&self, id: I) -> Option<Occupied<'id, I>> {
+        check(self.storage, id)
+    }
+
+    /// Returns the component of an id which is known to be occupied.
+    pub fn get_proven(&self, token: Occupied<'id, I>) -> &C {
+        self.storage.counters.get();
+        unsafe { self.storage.data.get(token.id.id()) }
+    }
+
+    /// Returns the component of an id which is known to be occupied mutably, stamping its epoch
+    /// like `Storage::get_mut`.
+    pub fn get_proven_mut(&mut self, token: Occupied<'id, I>) -> &mut C {
+        self.storage.counters.get_mut();
+        self.storage.stamps.stamp(token.id.id());
+        unsafe { self.storage.data.get_mut(token.id.id()) }
+    }
+
+    /// Inserts a component like `Storage::insert` and returns a token for its id.
+    pub fn insert(&mut self, id: I, comp: C) -> (Option<C>, Occupied<'id, I>) {
+        let old = self.storage.insert(id, comp);
+
+        (old, Occupied { id, brand: PhantomData })
+    }
+
+    /// Returns the underlying storage.
+    pub fn storage(&self) -> &Storage<C, D, I> {
+        self.storage
+    }
+}
+
+fn check<'id, C, D, I>(storage: &Storage<C, D, I>, id: I) -> Option<Occupied<'id, I>>
+where
+    D: UnprotectedStorage<C>,
+    I: Id,
+{
+    if storage.bitset.contains(id.id()) {
+        Some(Occupied { id, brand: PhantomData })
+    } else {
+        None
+    }
+}
+
+impl<C, D, I> Storage<C, D, I>
+where
+    D: UnprotectedStorage<C>,
+    I: Id,
+{
+    /// Calls `f` with a scope in which occupancy can be checked once and then relied on,
+    /// so repeated accesses through `get_proven` skip the bitset lookup and the branch.
+    ///
+    /// Every call uses a fresh brand lifetime `'id`, so tokens can neither escape the closure
+    /// nor be used with another storage, and the storage can't be modified while the scope
+    /// is alive.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, VecStorage};
+    /// use specs_static::Storage;
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// struct Height(u32);
+    ///
+    /// impl Component for Height {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut heights = Storage::<Height, VecStorage<_>, TileId>::default();
+    ///     for i in 0..16 {
+    ///         heights.insert(TileId(i * 2), Height(i));
+    ///     }
+    ///
+    ///     let sum = heights.proofs(|proofs| {
+    ///         let tokens: Vec<_> = (0..32).filter_map(|i| proofs.check(TileId(i))).collect();
+    ///
+    ///         let mut sum = 0;
+    ///         for _ in 0..10 {
+    ///             for &token in &tokens {
+    ///                 sum += proofs.get_proven(token).0;
+    ///             }
+    ///         }
+    ///         sum
+    ///     });
+    ///     assert_eq!(sum, 1200);
+    /// }
+    /// ```
+    ///
+    /// Tokens can't be used with another storage:
+    ///
+    /// ```compile_fail
+    /// # extern crate specs;
+    /// # extern crate specs_static;
+    /// # use specs::{Component, VecStorage};
+    /// # use specs_static::Storage;
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// # struct Height(u32);
+    /// # impl Component for Height {
+    /// #     type Storage = VecStorage<Self>;
+    /// # }
+    /// # fn main() {
+    /// let mut a = Storage::<Height, VecStorage<_>, TileId>::default();
+    /// let b = Storage::<Height, VecStorage<_>, TileId>::default();
+    /// a.insert(TileId(0), Height(0));
+    ///
+    /// a.proofs(|a| {
+    ///     let token = a.check(TileId(0)).unwrap();
+    ///     b.proofs(|b| b.get_proven(token).0);
+    /// });
+    /// # }
+    /// ```
+    pub fn proofs<'a, F, R>(&'a self, f: F) -> R
+    where
+        F: for<'id> FnOnce(Proofs<'a, 'id, C, D, I>) -> R,
+    {
+        f(Proofs {
+            storage: self,
+            brand: PhantomData,
+        })
+    }
+
+    /// Like `proofs`, but allows modifying and inserting components.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, VecStorage};
+    /// use specs_static::Storage;
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// struct Heat(u32);
+    ///
+    /// impl Component for Heat {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut heat = Storage::<Heat, VecStorage<_>, TileId>::default();
+    ///     heat.insert(TileId(3), Heat(0));
+    ///
+    ///     heat.proofs_mut(|mut heat| {
+    ///         let hot = heat.check(TileId(3)).unwrap();
+    ///         let (_, cold) = heat.insert(TileId(4), Heat(0));
+    ///         for _ in 0..100 {
+    ///             heat.get_proven_mut(hot).0 += 2;
+    ///             heat.get_proven_mut(cold).0 += 1;
+    ///         }
+    ///     });
+    ///     assert_eq!(heat.get(TileId(3)).unwrap().0, 200);
+    ///     assert_eq!(heat.get(TileId(4)).unwrap().0, 100);
+    /// }
+    /// ```
+    pub fn proofs_mut<'a, F, R>(&'a mut self, f: F) -> R
+    where
+        F: for<'id> FnOnce(ProofsMut<'a, 'id, C, D, I>) -> R,
+    {
+        f(ProofsMut {
+            storage: self,
+            brand: PhantomData,
+        })
+    }
+}
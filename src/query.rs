@@ -0,0 +1,205 @@
+//! Systems which run a closure for every tile with a given set of components.
+//!
+//! The components are named with the `Read` and `Write` markers of this module, which are
+//! distinct from the `SystemData` of the same names in Specs. See `tile_system` for an
+//! example.
+
+use std::marker::PhantomData;
+
+use shred::SystemData;
+use specs::{Component, Join, System};
+
+use {Id, ReadTiles, Storage, WithIds, WriteTiles};
+
+/// Asks a `tile_system` for shared access to the tile components `C`.
+pub struct Read<C>(PhantomData<C>);
+
+/// Asks a `tile_system` for mutable access to the tile components `C`.
+pub struct Write<C>(PhantomData<C>);
+
+/// The components a `tile_system` hands to its closure for one id, borrowed for `'b`.
+pub trait TileQueryItem<'b, I> {
+    /// The components, like `(&'b Material, &'b mut Heat)`.
+    type Item;
+    /// The join yielding `Item`s.
+    type Join: Join<Type = Self::Item>;
+}
+
+/// A `Read` or `Write` marker, or a tuple of them, describing the storages a `tile_system`
+/// fetches and joins.
+pub trait TileQuery<'a, I>: for<'b> TileQueryItem<'b, I> {
+    /// The storages to fetch.
+    type SystemData: SystemData<'a>;
+
+    /// Borrows the join over the fetched storages.
+    fn view<'b>(data: &'b mut Self::SystemData) -> <Self as TileQueryItem<'b, I>>::Join;
+}
+
+impl<'b, C, I> TileQueryItem<'b, I> for Read<C>
+where
+    C: Component,
+    I: 'static,
+{
+    type Item = &'b C;
+    type Join = &'b Storage<C, C::Storage, I>;
+}
+
+impl<'a, C, I> TileQuery<'a, I> for Read<C>
+where
+    C: Component + Send + Sync,
+    I: Id,
+{
+    type SystemData = ReadTiles<'a, C, I>;
+
+    fn view<'b>(data: &'b mut ReadTiles<'a, C, I>) -> &'b Storage<C, C::Storage, I> {
+        data
+    }
+}
+
+impl<'b, C, I> TileQueryItem<'b, I> for Write<C>
+where
+    C: Component,
+    I: 'static,
+{
+    type Item = &'b mut C;
+    type Join = &'b mut Storage<C, C::Storage, I>;
+}
+
+impl<'a, C, I> TileQuery<'a, I> for Write<C>
+where
+    C: Component + Send + Sync,
+    I: Id,
+{
+    type SystemData = WriteTiles<'a, C, I>;
+
+    fn view<'b>(data: &'b mut WriteTiles<'a, C, I>) -> &'b mut Storage<C, C::Storage, I> {
+        data
+    }
+}
+
+macro_rules! impl_tile_query {
+    ($($name:ident)*) => {
+        impl<'b, I, $($name),*> TileQueryItem<'b, I> for ($($name,)*)
+        where
+            $($name: TileQueryItem<'b, I>,)*
+        {
+            type Item = ($($name::Item,)*);
+            type Join = ($($name::Join,)*);
+        }
+
+        impl<'a, I, $($name),*> TileQuery<'a, I> for ($($name,)*)
+        where
+            $($name: TileQuery<'a, I>,)*
+        {
+            type SystemData = ($($name::SystemData,)*);
+
+            #[allow(non_snake_case)]
+            fn view<'b>(data: &'b mut Self::SystemData) -> <Self as TileQueryItem<'b, I>>::Join {
+                let ($(ref mut $name,)*) = *data;
+
+                ($($name::view($name),)*)
+            }
+        }
+    };
+}
+
+impl_tile_query!(A);
+impl_tile_query!(A B);
+impl_tile_query!(A B C);
+impl_tile_query!(A B C D);
+impl_tile_query!(A B C D E);
+impl_tile_query!(A B C D E F);
+impl_tile_query!(A B C D E F G);
+impl_tile_query!(A B C D E F G H);
+
+/// The system returned by `tile_system`.
+pub struct TileSystem<Q, I, F> {
+    f: F,
+    phantom: PhantomData<fn() -> (Q, I)>,
+}
+
+impl<'a, Q, I, F> System<'a> for TileSystem<Q, I, F>
+where
+    Q: TileQuery<'a, I>,
+    I: Id,
+    F: for<'b> FnMut(I, <Q as TileQueryItem<'b, I>>::Item),
+{
+    type SystemData = Q::SystemData;
+
+    fn run(&mut self, mut data: Self::SystemData) {
+        for (id, item) in WithIds::new(Q::view(&mut data)).join() {
+            (self.f)(id, item);
+        }
+    }
+}
+
+/// Creates a system calling `f` with the id and the components of every tile which has all
+/// components `Q` asks for.
+///
+/// `Q` is a `Read` or `Write` marker, or a tuple of up to eight of them. The storages are
+/// fetched like with `ReadTiles` and `WriteTiles`, so they have to be registered with
+/// `TileWorldExt::register_tile_comp`.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::{Component, DispatcherBuilder, VecStorage, World, WorldExt};
+/// use specs_static::query::{Read, Write};
+/// use specs_static::{tile_system, Storage, TileWorldExt};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// struct Material {
+///     conductivity: u32,
+/// }
+///
+/// impl Component for Material {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Heat(u32);
+///
+/// impl Component for Heat {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// fn main() {
+///     let mut world = World::new();
+///     world.register_tile_comp::<Material, TileId>();
+///     world.register_tile_comp::<Heat, TileId>();
+///     {
+///         let mut materials = world.write_resource::<Storage<Material, VecStorage<_>, TileId>>();
+///         let mut heats = world.write_resource::<Storage<Heat, VecStorage<_>, TileId>>();
+///         for i in 0..10 {
+///             materials.insert(TileId(i), Material { conductivity: 2 });
+///             if i % 2 == 0 {
+///                 heats.insert(TileId(i), Heat(0));
+///             }
+///         }
+///     }
+///
+///     let heat = tile_system::<(Read<Material>, Write<Heat>), TileId, _>(|id, (mat, heat)| {
+///         heat.0 += mat.conductivity * id.0;
+///     });
+///     let mut dispatcher = DispatcherBuilder::new().with(heat, "heat", &[]).build();
+///     dispatcher.dispatch(&world);
+///
+///     let heats = world.read_resource::<Storage<Heat, VecStorage<_>, TileId>>();
+///     assert_eq!(heats.get(TileId(4)), Some(&Heat(8)));
+///     assert_eq!(heats.get(TileId(5)), None);
+/// }
+/// ```
+pub fn tile_system<Q, I, F>(f: F) -> TileSystem<Q, I, F>
+where
+    Q: for<'a> TileQuery<'a, I>,
+    I: Id,
+    F: for<'b> FnMut(I, <Q as TileQueryItem<'b, I>>::Item),
+{
+    TileSystem {
+        f,
+        phantom: PhantomData,
+    }
+}
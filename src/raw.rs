@@ -0,0 +1,133 @@
+//! The trait for the backends `Storage` keeps its components in, and a backend which works
+//! without Specs.
+
+use std::mem::MaybeUninit;
+use std::ptr;
+
+#[cfg(not(feature = "specs"))]
+use liballoc::vec::Vec;
+#[cfg(feature = "specs")]
+use specs::storage::DistinctStorage;
+
+use bitset::BitSetLike;
+use Index;
+
+/// Specs' `UnprotectedStorage`, under the name backends use without the `specs` feature.
+#[cfg(feature = "specs")]
+pub use specs::storage::UnprotectedStorage as RawStorage;
+
+/// The backend of a `Storage`, which stores components at ids it doesn't track itself.
+///
+/// With the `specs` feature this is Specs' `UnprotectedStorage`, so every Specs backend can
+/// be used. Without it, it's a trait of this crate with the same methods, so backends
+/// written against it compile in both configurations, as long as they're `Default`.
+#[cfg(not(feature = "specs"))]
+pub trait RawStorage<T> {
+    /// Drops the components at the ids in `has`.
+    ///
+    /// # Safety
+    ///
+    /// May only be called with the mask of the ids which have components.
+    unsafe fn clean<B>(&mut self, has: B)
+    where
+        B: BitSetLike;
+
+    /// Returns the component at `id`.
+    ///
+    /// # Safety
+    ///
+    /// There must be a component at `id`.
+    unsafe fn get(&self, id: Index) -> &T;
+
+    /// Returns the component at `id` mutably.
+    ///
+    /// # Safety
+    ///
+    /// There must be a component at `id`.
+    unsafe fn get_mut(&mut self, id: Index) -> &mut T;
+
+    /// Inserts `value` at `id`.
+    ///
+    /// # Safety
+    ///
+    /// There must not be a component at `id`, since it would be leaked.
+    unsafe fn insert(&mut self, id: Index, value: T);
+
+    /// Removes the component at `id` and returns it.
+    ///
+    /// # Safety
+    ///
+    /// There must be a component at `id`.
+    unsafe fn remove(&mut self, id: Index) -> T;
+}
+
+/// A backend keeping the components in a `Vec` indexed by id, like Specs' `VecStorage`,
+/// which is available without the `specs` feature.
+///
+/// # Examples
+///
+/// ```
+/// #[macro_use]
+/// extern crate specs_static;
+///
+/// use specs_static::{BitSetLike, SlabStorage, Storage};
+///
+/// define_id!(pub struct TileId;);
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Depth(u8);
+///
+/// fn main() {
+///     let mut depths = Storage::<Depth, SlabStorage<Depth>, TileId>::default();
+///     for id in (0..1000).step_by(7) {
+///         depths.insert(TileId(id), Depth((id % 256) as u8));
+///     }
+///
+///     assert_eq!(depths.get(TileId(700)), Some(&Depth(188)));
+///     assert_eq!(depths.remove(TileId(7)), Some(Depth(7)));
+///     assert!(!depths.contains(TileId(7)));
+///     assert_eq!(depths.mask().iter().take(3).collect::<Vec<_>>(), [0, 14, 21]);
+/// }
+/// ```
+pub struct SlabStorage<T>(Vec<MaybeUninit<T>>);
+
+impl<T> Default for SlabStorage<T> {
+    fn default() -> Self {
+        SlabStorage(Vec::new())
+    }
+}
+
+impl<T> RawStorage<T> for SlabStorage<T> {
+    unsafe fn clean<B>(&mut self, has: B)
+    where
+        B: BitSetLike,
+    {
+        for id in has.iter() {
+            ptr::drop_in_place(self.0[id as usize].as_mut_ptr());
+        }
+        self.0.clear();
+    }
+
+    unsafe fn get(&self, id: Index) -> &T {
+        &*self.0.get_unchecked(id as usize).as_ptr()
+    }
+
+    unsafe fn get_mut(&mut self, id: Index) -> &mut T {
+        &mut *self.0.get_unchecked_mut(id as usize).as_mut_ptr()
+    }
+
+    unsafe fn insert(&mut self, id: Index, value: T) {
+        let id = id as usize;
+        if self.0.len() <= id {
+            self.0.resize_with(id + 1, MaybeUninit::uninit);
+        }
+        self.0[id] = MaybeUninit::new(value);
+    }
+
+    unsafe fn remove(&mut self, id: Index) -> T {
+        ptr::read(self.get(id))
+    }
+}
+
+#[cfg(feature = "specs")]
+unsafe impl<T> DistinctStorage for SlabStorage<T> {}
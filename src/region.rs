@@ -0,0 +1,276 @@
+//! Queries answered by the upper layers of the occupancy bitset.
+
+use std::marker::PhantomData;
+
+use hibitset::{BitSet, BitSetLike};
+use specs::storage::UnprotectedStorage;
+
+use mask::{BITS, MAX_LEN};
+use {Id, RangeMask, Storage};
+
+/// The amount of ids covered by one bit of the third bitset layer (`layer2`).
+///
+/// `Storage::occupied_ranges` works at this granularity. It is 4096 on 64-bit targets and
+/// 1024 on 32-bit targets.
+pub const REGION_SIZE: u32 = 1 << (BITS * 2);
+
+impl<C, D, I> Storage<C, D, I>
+where
+    D: UnprotectedStorage<C>,
+    I: Id,
+{
+    /// Returns `true` if no id in `start..end` is occupied.
+    ///
+    /// Only the bitset words at the borders of the range are inspected on the lower layers;
+    /// everything in between is answered by the upper layers, so this takes constant time
+    /// regardless of the length of the range.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, VecStorage};
+    /// use specs_static::{Storage, REGION_SIZE};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// struct Unit;
+    ///
+    /// impl Component for Unit {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut units = Storage::<Unit, VecStorage<_>, TileId>::default();
+    ///     assert!(units.is_range_empty(TileId(0), TileId(1_000_000)));
+    ///
+    ///     units.insert(TileId(REGION_SIZE * 3 + 5), Unit);
+    ///
+    ///     // Aligned to region boundaries.
+    ///     assert!(units.is_range_empty(TileId(0), TileId(REGION_SIZE * 3)));
+    ///     assert!(!units.is_range_empty(TileId(REGION_SIZE * 3), TileId(REGION_SIZE * 4)));
+    ///     assert!(units.is_range_empty(TileId(REGION_SIZE * 4), TileId(REGION_SIZE * 100)));
+    ///
+    ///     // Misaligned.
+    ///     assert!(units.is_range_empty(TileId(17), TileId(REGION_SIZE * 3 + 5)));
+    ///     assert!(!units.is_range_empty(TileId(17), TileId(REGION_SIZE * 3 + 6)));
+    ///     assert!(!units.is_range_empty(TileId(REGION_SIZE * 3 + 5), TileId(u32::max_value())));
+    ///     assert!(units.is_range_empty(TileId(REGION_SIZE * 3 + 6), TileId(u32::max_value())));
+    ///     assert!(units.is_range_empty(TileId(6), TileId(6)));
+    /// }
+    /// ```
+    pub fn is_range_empty(&self, start: I, end: I) -> bool {
+        let end = u64::from(end.id()).min(MAX_LEN) as u32;
+        let range = RangeMask::from_range(start.id(), end);
+
+        range.is_empty() || !any_in_range(&self.bitset, &range, 3, 0)
+    }
+
+    /// Returns the coalesced ranges of regions which contain at least one component.
+    ///
+    /// Every yielded `(start, end)` range is aligned to `REGION_SIZE`, contains at least one
+    /// occupied id per region and may contain vacant ids as well. Together, the ranges
+    /// contain every occupied id. Empty regions are skipped using the upper bitset layers,
+    /// without looking at their bits.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, VecStorage};
+    /// use specs_static::{Storage, REGION_SIZE};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// struct Unit;
+    ///
+    /// impl Component for Unit {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut units = Storage::<Unit, VecStorage<_>, TileId>::default();
+    ///     units.insert(TileId(3), Unit);
+    ///     units.insert(TileId(REGION_SIZE + 1), Unit);
+    ///     units.insert(TileId(REGION_SIZE * 70 - 1), Unit);
+    ///     units.insert(TileId(REGION_SIZE * 70), Unit);
+    ///
+    ///     let ranges: Vec<_> = units.occupied_ranges().map(|(s, e)| (s.0, e.0)).collect();
+    ///     assert_eq!(
+    ///         ranges,
+    ///         vec![(0, REGION_SIZE * 2), (REGION_SIZE * 69, REGION_SIZE * 71)]
+    ///     );
+    /// }
+    /// ```
+    pub fn occupied_ranges<'a>(&'a self) -> impl Iterator<Item = (I, I)> + 'a {
+        let mut ranges: Vec<(I, I)> = Vec::new();
+        let mut regions = Vec::new();
+        occupied_regions(&self.bitset, 3, 0, &mut regions);
+
+        for region in regions {
+            let start = region << (BITS * 2);
+            let end = start + REGION_SIZE;
+
+            match ranges.last_mut() {
+                Some(&mut (_, ref mut last)) if last.id() == start => *last = I::from_u32(end),
+                _ => ranges.push((I::from_u32(start), I::from_u32(end))),
+            }
+        }
+
+        ranges.into_iter()
+    }
+
+    /// Returns the start id and length of every maximal run of consecutive occupied ids,
+    /// in ascending order.
+    ///
+    /// Runs are found word by word, using the upper layers to skip empty words and counting
+    /// trailing zeros and ones within a word, so long runs don't cost one step per id.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, VecStorage};
+    /// use specs_static::{Storage, REGION_SIZE};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// struct Wall;
+    ///
+    /// impl Component for Wall {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut walls = Storage::<Wall, VecStorage<_>, TileId>::default();
+    ///     for id in (0..3).chain(60..70).chain(REGION_SIZE - 1..REGION_SIZE + 130) {
+    ///         walls.insert(TileId(id), Wall);
+    ///     }
+    ///     walls.insert(TileId(1_000_000), Wall);
+    ///
+    ///     let runs: Vec<_> = walls.runs().map(|(start, len)| (start.0, len)).collect();
+    ///     assert_eq!(
+    ///         runs,
+    ///         vec![(0, 3), (60, 10), (REGION_SIZE - 1, 131), (1_000_000, 1)]
+    ///     );
+    ///
+    ///     let mut dense = Storage::<Wall, VecStorage<_>, TileId>::default();
+    ///     for id in 0..100_000 {
+    ///         dense.insert(TileId(id), Wall);
+    ///     }
+    ///     let runs: Vec<_> = dense.runs().map(|(start, len)| (start.0, len)).collect();
+    ///     assert_eq!(runs, vec![(0, 100_000)]);
+    /// }
+    /// ```
+    pub fn runs<'a>(&'a self) -> impl Iterator<Item = (I, u32)> + 'a {
+        Runs {
+            bitset: &self.bitset,
+            pos: 0,
+            phantom: PhantomData,
+        }
+    }
+}
+
+struct Runs<'a, I> {
+    bitset: &'a BitSet,
+    pos: usize,
+    phantom: PhantomData<I>,
+}
+
+impl<'a, I> Iterator for Runs<'a, I>
+where
+    I: Id,
+{
+    type Item = (I, u32);
+
+    fn next(&mut self) -> Option<(I, u32)> {
+        let start = next_set(self.bitset, 0, self.pos)?;
+
+        let mut end = start;
+        loop {
+            let offset = end & WORD_MASK;
+            // Zeros are shifted in at the top, so this counts at most the rest of the word.
+            let ones = (!(self.bitset.layer0(end >> BITS) >> offset)).trailing_zeros() as usize;
+            end += ones;
+
+            if end & WORD_MASK != 0 || ones == 0 {
+                break;
+            }
+        }
+        self.pos = end;
+
+        Some((I::from_u32(start as u32), (end - start) as u32))
+    }
+}
+
+const WORD_MASK: usize = (1 << BITS) - 1;
+
+/// Returns the position of the first set bit of layer `level` at or after `pos`.
+pub(crate) fn next_set<B: BitSetLike>(set: &B, level: usize, mut pos: usize) -> Option<usize> {
+    loop {
+        let idx = pos >> BITS;
+        if level == 3 && idx > 0 {
+            return None;
+        }
+
+        let word = set.get_from_layer(level, idx) & (!0 << (pos & WORD_MASK));
+        if word != 0 {
+            return Some((idx << BITS) | word.trailing_zeros() as usize);
+        }
+        if level == 3 {
+            return None;
+        }
+
+        // The next non-empty word of this layer is below the next set bit of the parent.
+        pos = next_set(set, level + 1, idx + 1)? << BITS;
+    }
+}
+
+/// Returns `true` if any bit of the given layer word which overlaps `range` is set,
+/// descending only into the words at the borders of the range.
+fn any_in_range<B: BitSetLike>(set: &B, range: &RangeMask, level: usize, idx: usize) -> bool {
+    let mut word = set.get_from_layer(level, idx) & range.word(level, idx);
+    if level == 0 {
+        return word != 0;
+    }
+
+    while word != 0 {
+        let bit = word.trailing_zeros() as usize;
+        word &= word - 1;
+
+        let child = (idx << BITS) | bit;
+        let shift = BITS * level;
+        let covered = RangeMask::from_range(
+            (child << shift) as u32,
+            (((child + 1) << shift) as u64).min(MAX_LEN) as u32,
+        );
+        // A set bit on an upper layer means at least one id below it is occupied.
+        if range.start() <= covered.start() && covered.end() <= range.end() {
+            return true;
+        }
+        if any_in_range(set, range, level - 1, child) {
+            return true;
+        }
+    }
+
+    false
+}
+
+/// Collects the indices of all set bits of layer 2 below the given word.
+fn occupied_regions<B: BitSetLike>(set: &B, level: usize, idx: usize, regions: &mut Vec<u32>) {
+    let mut word = set.get_from_layer(level, idx);
+    while word != 0 {
+        let bit = word.trailing_zeros() as usize;
+        word &= word - 1;
+
+        let child = (idx << BITS) | bit;
+        if level == 2 {
+            regions.push(child as u32);
+        } else {
+            occupied_regions(set, level - 1, child, regions);
+        }
+    }
+}
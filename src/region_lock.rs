@@ -0,0 +1,317 @@
+//! Splitting a storage into ranges of ids which can be locked independently.
+
+use std::error::Error;
+use std::fmt;
+use std::sync::{Mutex, MutexGuard};
+
+use hibitset::BitSetLike;
+use specs::storage::UnprotectedStorage;
+
+use {Id, Storage};
+
+/// The error returned when a `RegionGuard` is used with an id outside of its region.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct OutsideRegion {
+    /// The rejected id.
+    pub id: u32,
+    /// The locked region.
+    pub region: u32,
+}
+
+impl fmt::Display for OutsideRegion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "id {} is outside of the locked region {}", self.id, self.region)
+    }
+}
+
+impl Error for OutsideRegion {}
+
+/// A storage split into regions of `region_size` consecutive ids, each of which is guarded
+/// by its own lock, so several threads can mutate disjoint regions at the same time.
+///
+/// Region `r` contains the ids `r * region_size..(r + 1) * region_size`. Every region has
+/// a backend and a bitset of its own, addressed by the offset of an id within its region,
+/// so adding and removing components never touches state shared with other regions.
+/// `into_inner` merges the regions back into the original storage.
+///
+/// Components are moved out of the storage by `new` and back by `into_inner`, so tracked
+/// backends report all of them as removed and inserted again.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// use specs::{Component, VecStorage};
+/// use specs_static::{OutsideRegion, RegionLocked, Storage};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// #[derive(Debug, PartialEq)]
+/// struct Ore(u32);
+///
+/// impl Component for Ore {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// type Ores = Storage<Ore, VecStorage<Ore>, TileId>;
+///
+/// const REGION_SIZE: u32 = 256;
+/// const REGIONS: u32 = 8;
+///
+/// /// The edit thread `thread` makes in step `step`. Threads contend for the same regions,
+/// /// but every id is edited by one thread only, so the result doesn't depend on timing.
+/// fn edit(thread: u32, step: u32) -> (u32, TileId, u32) {
+///     let region = (thread + step) % REGIONS;
+///     let id = region * REGION_SIZE + thread * 64 + step * 7 % 64;
+///
+///     (region, TileId(id), thread + 1)
+/// }
+///
+/// fn main() {
+///     let mut ores = Ores::default();
+///     ores.insert(TileId(3), Ore(100));
+///     ores.insert(TileId(REGION_SIZE * 5 + 1), Ore(200));
+///
+///     let mut reference = Ores::default();
+///     reference.insert(TileId(3), Ore(100));
+///     reference.insert(TileId(REGION_SIZE * 5 + 1), Ore(200));
+///     for thread in 0..4 {
+///         for step in 0..2000 {
+///             let (_, id, amount) = edit(thread, step);
+///             match reference.get_mut(id) {
+///                 Some(ore) if ore.0 > 50 => ore.0 -= 50,
+///                 Some(ore) => ore.0 += amount,
+///                 None => {
+///                     reference.insert(id, Ore(amount));
+///                 }
+///             }
+///             if reference.get(id) == Some(&Ore(60)) {
+///                 reference.remove(id);
+///             }
+///         }
+///     }
+///
+///     let locked = Arc::new(RegionLocked::new(ores, REGION_SIZE, REGIONS));
+///     let threads: Vec<_> = (0..4)
+///         .map(|thread| {
+///             let locked = locked.clone();
+///             thread::spawn(move || {
+///                 for step in 0..2000 {
+///                     let (region, id, amount) = edit(thread, step);
+///                     let mut guard = locked.lock_region(region);
+///                     match guard.get_mut(id).unwrap() {
+///                         Some(ore) if ore.0 > 50 => ore.0 -= 50,
+///                         Some(ore) => ore.0 += amount,
+///                         None => {
+///                             guard.insert(id, Ore(amount)).unwrap();
+///                         }
+///                     }
+///                     if guard.get(id).unwrap() == Some(&Ore(60)) {
+///                         guard.remove(id).unwrap();
+///                     }
+///
+///                     // The neighbor region can't be touched through this guard.
+///                     let outside = TileId(((region + 1) % REGIONS) * REGION_SIZE);
+///                     assert_eq!(
+///                         guard.insert(outside, Ore(0)),
+///                         Err(OutsideRegion { id: outside.0, region })
+///                     );
+///                 }
+///             })
+///         })
+///         .collect();
+///     for thread in threads {
+///         thread.join().unwrap();
+///     }
+///
+///     let ores = Arc::try_unwrap(locked).ok().unwrap().into_inner();
+///     for id in 0..REGION_SIZE * REGIONS {
+///         assert_eq!(ores.get(TileId(id)), reference.get(TileId(id)), "id {}", id);
+///     }
+///     let ids = |ores: &Ores| ores.mask().into_iter().collect::<Vec<_>>();
+///     assert_eq!(ids(&ores), ids(&reference));
+/// }
+/// ```
+pub struct RegionLocked<C, D: UnprotectedStorage<C>, I> {
+    storage: Storage<C, D, I>,
+    regions: Vec<Mutex<Storage<C, D, I>>>,
+    region_size: u32,
+}
+
+impl<C, D, I> RegionLocked<C, D, I>
+where
+    D: UnprotectedStorage<C> + Default,
+    I: Id,
+{
+    /// Splits `storage` into `regions` regions of `region_size` ids each, moving all of its
+    /// components into the regions.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `region_size` is 0, if the regions cover more than `u32::MAX + 1` ids, or
+    /// if `storage` has a component outside of the regions.
+    pub fn new(mut storage: Storage<C, D, I>, region_size: u32, regions: u32) -> Self {
+        assert!(region_size != 0, "regions have to contain at least one id");
+        let covered = u64::from(region_size) * u64::from(regions);
+        assert!(
+            covered <= 1 << 32,
+            "{} regions of {} ids exceed the id space",
+            regions,
+            region_size
+        );
+
+        let mut split: Vec<Storage<C, D, I>> = (0..regions).map(|_| Storage::default()).collect();
+        let ids: Vec<u32> = (&storage.bitset).iter().collect();
+        for id in ids {
+            assert!(
+                u64::from(id) < covered,
+                "id {} is outside of the {} regions of {} ids",
+                id,
+                regions,
+                region_size
+            );
+
+            let comp = storage.remove(I::from_u32(id)).expect("the id is occupied");
+            split[(id / region_size) as usize].insert(I::from_u32(id % region_size), comp);
+        }
+
+        RegionLocked {
+            storage,
+            regions: split.into_iter().map(Mutex::new).collect(),
+            region_size,
+        }
+    }
+
+    /// Returns the amount of ids of every region.
+    pub fn region_size(&self) -> u32 {
+        self.region_size
+    }
+
+    /// Returns the amount of regions.
+    pub fn region_count(&self) -> u32 {
+        self.regions.len() as u32
+    }
+
+    /// Returns the region `id` belongs to.
+    pub fn region_of(&self, id: I) -> u32 {
+        id.id() / self.region_size
+    }
+
+    /// Locks `region`, blocking until no other thread holds its lock.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no such region, or if a thread panicked while holding the lock.
+    pub fn lock_region<'a>(&'a self, region: u32) -> RegionGuard<'a, C, D, I> {
+        let storage = self
+            .regions
+            .get(region as usize)
+            .unwrap_or_else(|| panic!("there is no region {}", region))
+            .lock()
+            .expect("tile region poisoned");
+
+        RegionGuard {
+            storage,
+            region,
+            start: region * self.region_size,
+            region_size: self.region_size,
+        }
+    }
+
+    /// Moves the components of all regions back into the storage and returns it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a thread panicked while holding the lock of a region.
+    pub fn into_inner(self) -> Storage<C, D, I> {
+        let RegionLocked {
+            mut storage,
+            regions,
+            region_size,
+        } = self;
+
+        for (region, split) in regions.into_iter().enumerate() {
+            let mut split = split.into_inner().expect("tile region poisoned");
+            let start = region as u32 * region_size;
+            let ids: Vec<u32> = (&split.bitset).iter().collect();
+            for offset in ids {
+                let comp = split.remove(I::from_u32(offset)).expect("the id is occupied");
+                storage.insert(I::from_u32(start + offset), comp);
+            }
+        }
+
+        storage
+    }
+}
+
+/// Exclusive access to one region of a `RegionLocked`, created by `lock_region`.
+///
+/// All methods take ids of the whole storage and return `OutsideRegion` for ids of other
+/// regions.
+pub struct RegionGuard<'a, C: 'a, D: UnprotectedStorage<C> + 'a, I: 'a> {
+    storage: MutexGuard<'a, Storage<C, D, I>>,
+    region: u32,
+    start: u32,
+    region_size: u32,
+}
+
+impl<'a, C, D, I> RegionGuard<'a, C, D, I>
+where
+    D: UnprotectedStorage<C>,
+    I: Id,
+{
+    /// Returns the locked region.
+    pub fn region(&self) -> u32 {
+        self.region
+    }
+
+    /// Returns the range `(start, end)` of the ids of the locked region, with `end`
+    /// clamped to `u32::MAX`.
+    pub fn range(&self) -> (I, I) {
+        let end = u64::from(self.start) + u64::from(self.region_size);
+
+        (I::from_u32(self.start), I::from_u32(end.min(u64::from(u32::MAX)) as u32))
+    }
+
+    fn offset(&self, id: I) -> Result<I, OutsideRegion> {
+        match id.id().checked_sub(self.start) {
+            Some(offset) if offset < self.region_size => Ok(I::from_u32(offset)),
+            _ => Err(OutsideRegion {
+                id: id.id(),
+                region: self.region,
+            }),
+        }
+    }
+
+    /// Returns the component of `id`.
+    pub fn get(&self, id: I) -> Result<Option<&C>, OutsideRegion> {
+        let offset = self.offset(id)?;
+
+        Ok(self.storage.get(offset))
+    }
+
+    /// Returns the component of `id` mutably.
+    pub fn get_mut(&mut self, id: I) -> Result<Option<&mut C>, OutsideRegion> {
+        let offset = self.offset(id)?;
+
+        Ok(self.storage.get_mut(offset))
+    }
+
+    /// Inserts `comp` at `id`, returning the component it replaced.
+    pub fn insert(&mut self, id: I, comp: C) -> Result<Option<C>, OutsideRegion> {
+        let offset = self.offset(id)?;
+
+        Ok(self.storage.insert(offset, comp))
+    }
+
+    /// Removes the component of `id`.
+    pub fn remove(&mut self, id: I) -> Result<Option<C>, OutsideRegion> {
+        let offset = self.offset(id)?;
+
+        Ok(self.storage.remove(offset))
+    }
+}
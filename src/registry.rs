@@ -0,0 +1,716 @@
+//! Type-erased access to all tile storages registered for an id type.
+
+use std::any::{self, Any, TypeId};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+
+use hibitset::{BitSet, BitSetAnd, BitSetLike};
+use shred::{Fetch, FetchMut, World};
+use specs::storage::UnprotectedStorage;
+use specs::{Component, Tracked};
+
+#[cfg(feature = "serde")]
+use serde::de::DeserializeOwned;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+use bound;
+use codec::Codec;
+use diagnostics::{self, ProbeLayer};
+#[cfg(feature = "serde")]
+use json::JsonTile;
+#[cfg(feature = "profile")]
+use OpCounters;
+use {Id, RangeMask, Storage, TileCodec};
+
+/// The object-safe part of `Storage`, so tools like editors can inspect and modify layers
+/// without knowing their component types at compile time.
+///
+/// Ids are plain `u32`s here, since the id type is erased as well.
+pub trait AnyTileStorage: Any + Send + Sync {
+    /// Returns the name of the component type.
+    fn type_name(&self) -> &'static str;
+
+    /// Returns the `TypeId` of the component type.
+    fn component_type(&self) -> TypeId;
+
+    /// Returns `true` if there's a component at `id`.
+    fn contains(&self, id: u32) -> bool;
+
+    /// Removes and drops the component at `id`, returning `true` if there was one.
+    fn remove(&mut self, id: u32) -> bool;
+
+    /// Removes all components.
+    fn clear(&mut self);
+
+    /// Removes and drops the components at the ids in `mask`, returning how many there were.
+    fn remove_mask(&mut self, mask: &BitSet) -> usize {
+        let ids: Vec<u32> = BitSetAnd(mask, self.mask()).iter().collect();
+        for &id in &ids {
+            self.remove(id);
+        }
+
+        ids.len()
+    }
+
+    /// Returns the amount of components.
+    ///
+    /// This has to count them, so it takes time proportional to the amount of components.
+    fn len(&self) -> usize;
+
+    /// Returns `true` if there are no components.
+    fn is_empty(&self) -> bool {
+        self.mask().is_empty()
+    }
+
+    /// Returns the mask of all occupied ids.
+    fn mask(&self) -> &BitSet;
+
+    /// Returns `self` as `Any`, so it can be downcast to the concrete storage type.
+    fn as_any(&self) -> &dyn Any;
+
+    /// Returns `self` as mutable `Any`, so it can be downcast to the concrete storage type.
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    /// Returns the operation counters, see `Storage::op_counters`.
+    #[cfg(feature = "profile")]
+    fn op_counters(&self) -> OpCounters;
+
+    /// Returns the operation counters and resets them, see `Storage::take_op_counters`.
+    #[cfg(feature = "profile")]
+    fn take_op_counters(&self) -> OpCounters;
+}
+
+impl<C, D, I> AnyTileStorage for Storage<C, D, I>
+where
+    C: Send + Sync + 'static,
+    D: UnprotectedStorage<C> + Send + Sync + 'static,
+    I: Id,
+{
+    fn type_name(&self) -> &'static str {
+        any::type_name::<C>()
+    }
+
+    fn component_type(&self) -> TypeId {
+        TypeId::of::<C>()
+    }
+
+    fn contains(&self, id: u32) -> bool {
+        self.bitset.contains(id)
+    }
+
+    fn remove(&mut self, id: u32) -> bool {
+        Storage::remove(self, I::from_u32(id)).is_some()
+    }
+
+    fn clear(&mut self) {
+        Storage::clear(self)
+    }
+
+    fn remove_mask(&mut self, mask: &BitSet) -> usize {
+        let removed = BitSetAnd(mask, &self.bitset).iter().count();
+        Storage::remove_mask(self, mask);
+
+        removed
+    }
+
+    fn len(&self) -> usize {
+        (&self.bitset).into_iter().count()
+    }
+
+    fn mask(&self) -> &BitSet {
+        &self.bitset
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    #[cfg(feature = "profile")]
+    fn op_counters(&self) -> OpCounters {
+        Storage::op_counters(self)
+    }
+
+    #[cfg(feature = "profile")]
+    fn take_op_counters(&self) -> OpCounters {
+        Storage::take_op_counters(self)
+    }
+}
+
+/// A borrowed, type-erased tile storage resource.
+pub struct TileStorageRef<'a> {
+    inner: Box<dyn Deref<Target = dyn AnyTileStorage> + 'a>,
+}
+
+impl<'a> Deref for TileStorageRef<'a> {
+    type Target = dyn AnyTileStorage;
+
+    fn deref(&self) -> &(dyn AnyTileStorage + 'static) {
+        &**self.inner
+    }
+}
+
+/// A mutably borrowed, type-erased tile storage resource.
+pub struct TileStorageMut<'a> {
+    inner: Box<dyn DerefMut<Target = dyn AnyTileStorage> + 'a>,
+}
+
+impl<'a> Deref for TileStorageMut<'a> {
+    type Target = dyn AnyTileStorage;
+
+    fn deref(&self) -> &(dyn AnyTileStorage + 'static) {
+        &**self.inner
+    }
+}
+
+impl<'a> DerefMut for TileStorageMut<'a> {
+    fn deref_mut(&mut self) -> &mut (dyn AnyTileStorage + 'static) {
+        &mut **self.inner
+    }
+}
+
+struct Erased<T>(T);
+
+impl<'a, T> Deref for Erased<Fetch<'a, T>>
+where
+    T: AnyTileStorage,
+{
+    type Target = dyn AnyTileStorage;
+
+    fn deref(&self) -> &(dyn AnyTileStorage + 'static) {
+        &*self.0
+    }
+}
+
+impl<'a, T> Deref for Erased<FetchMut<'a, T>>
+where
+    T: AnyTileStorage,
+{
+    type Target = dyn AnyTileStorage;
+
+    fn deref(&self) -> &(dyn AnyTileStorage + 'static) {
+        &*self.0
+    }
+}
+
+impl<'a, T> DerefMut for Erased<FetchMut<'a, T>>
+where
+    T: AnyTileStorage,
+{
+    fn deref_mut(&mut self) -> &mut (dyn AnyTileStorage + 'static) {
+        &mut *self.0
+    }
+}
+
+/// Clones the component at the first id into the second one, returning `false` if there is
+/// none to clone.
+pub(crate) type CloneTile = fn(&mut dyn AnyTileStorage, u32, u32) -> bool;
+
+/// Moves the component at the first id to the second one, returning `false` if there is
+/// none to move.
+pub(crate) type MoveTile = fn(&mut dyn AnyTileStorage, u32, u32) -> bool;
+
+/// Feeds the contents of a storage into a hasher, see `Storage::content_hash`.
+pub(crate) type HashTiles = fn(&dyn AnyTileStorage, &mut dyn Hasher);
+
+/// A registered layer: its component type and how to fetch its storage resource.
+#[derive(Clone, Copy)]
+pub(crate) struct Entry {
+    pub(crate) component_type: TypeId,
+    pub(crate) type_name: &'static str,
+    /// The backend `D` of the registered `Storage<C, D, I>`.
+    pub(crate) backend_type: TypeId,
+    pub(crate) backend_name: &'static str,
+    pub(crate) fetch: fn(&World) -> Option<TileStorageRef<'_>>,
+    pub(crate) fetch_mut: fn(&World) -> Option<TileStorageMut<'_>>,
+    /// Removes the components in `start..end`, returning them as a boxed
+    /// `Vec<(u32, C)>` of their offsets from `start` and the components.
+    pub(crate) take_range: fn(&mut dyn AnyTileStorage, u32, u32) -> Box<dyn Any + Send>,
+    pub(crate) move_tile: MoveTile,
+    /// How to encode the components, if the layer has been added with `register_codec`.
+    pub(crate) codec: Option<Codec>,
+    /// How to clone components, if the layer has been added with `register_clone`.
+    pub(crate) clone_tile: Option<CloneTile>,
+    /// How to hash the layer, if it has been added with `register_hash`.
+    pub(crate) hash_tiles: Option<HashTiles>,
+    /// Takes the numbers of the storage for `TileWorldExt::tile_diagnostics`.
+    pub(crate) probe: ProbeLayer,
+    /// Whether the layer has been added with `register_tracked`.
+    pub(crate) tracked: bool,
+    /// How to convert components from and to JSON, if the layer has been added with
+    /// `register_json`.
+    #[cfg(feature = "serde")]
+    pub(crate) json: Option<JsonTile>,
+}
+
+/// The error returned when a layer can't be registered under a name.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum LayerNameError {
+    /// Another layer is registered under the name already.
+    Taken {
+        /// The requested name.
+        name: String,
+        /// The component type of the layer registered under the name.
+        type_name: &'static str,
+    },
+    /// The layer is registered under another name already.
+    AlreadyNamed {
+        /// The component type of the layer.
+        type_name: &'static str,
+        /// The name the layer is registered under.
+        name: String,
+    },
+}
+
+impl fmt::Display for LayerNameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LayerNameError::Taken {
+                ref name,
+                type_name,
+            } => write!(f, "the layer name {:?} is taken by {}", name, type_name),
+            LayerNameError::AlreadyNamed {
+                type_name,
+                ref name,
+            } => write!(f, "the layer {} is named {:?} already", type_name, name),
+        }
+    }
+}
+
+impl Error for LayerNameError {}
+
+/// The resource listing all tile storages registered for the id type `I`.
+///
+/// `TileWorldExt::register_tile_comp` adds every storage it registers to this list, and
+/// `TileWorldExt::tile_storages` uses it to fetch them.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::{Component, VecStorage, World, WorldExt};
+/// use specs_static::{AnyTileStorage, Storage, TileWorldExt};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// struct Grass;
+///
+/// impl Component for Grass {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// struct Water(u8);
+///
+/// impl Component for Water {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// fn main() {
+///     let mut world = World::new();
+///     world.register_tile_comp::<Grass, TileId>();
+///     world.register_tile_comp::<Water, TileId>();
+///     world
+///         .write_resource::<Storage<Water, VecStorage<_>, TileId>>()
+///         .insert(TileId(3), Water(2));
+///
+///     // An editor deleting everything at the tile under the cursor.
+///     for mut storage in world.tile_storages_mut::<TileId>() {
+///         storage.remove(3);
+///     }
+///
+///     let storages = world.tile_storages::<TileId>();
+///     let names: Vec<_> = storages.iter().map(|storage| storage.type_name()).collect();
+///     assert!(names[0].ends_with("Grass"));
+///     assert!(names[1].ends_with("Water"));
+///     assert!(storages.iter().all(|storage| storage.is_empty()));
+///
+///     let water = storages[1].as_any().downcast_ref::<Storage<Water, VecStorage<_>, TileId>>();
+///     assert!(water.is_some());
+/// }
+/// ```
+#[derive(Derivative)]
+#[derivative(Default(bound = ""))]
+pub struct TileRegistry<I> {
+    entries: Vec<Entry>,
+    /// The indices of the entries of named layers.
+    names: HashMap<String, usize>,
+    phantom: PhantomData<I>,
+}
+
+impl<I> TileRegistry<I>
+where
+    I: Id,
+{
+    /// Adds the storage resource `Storage<C, D, I>` to the registry, unless it's already
+    /// registered for the component type `C`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `C` is registered with another backend than `D`, since the type-erased
+    /// accesses would keep going to the storage of the first one.
+    ///
+    /// ```should_panic
+    /// # extern crate specs;
+    /// # extern crate specs_static;
+    /// # use specs::{Component, DenseVecStorage, VecStorage};
+    /// # use specs_static::TileRegistry;
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// # struct Water(u8);
+    /// # impl Component for Water {
+    /// #     type Storage = VecStorage<Self>;
+    /// # }
+    /// # fn main() {
+    /// let mut registry = TileRegistry::<TileId>::default();
+    /// registry.register::<Water, VecStorage<_>>();
+    /// registry.register::<Water, VecStorage<_>>();
+    /// // Panics: `Water` is registered with another backend already.
+    /// registry.register::<Water, DenseVecStorage<_>>();
+    /// # }
+    /// ```
+    pub fn register<C, D>(&mut self)
+    where
+        C: Send + Sync + 'static,
+        D: UnprotectedStorage<C> + Send + Sync + 'static,
+    {
+        let existing = self
+            .entries
+            .iter()
+            .find(|entry| entry.component_type == TypeId::of::<C>());
+        if let Some(entry) = existing {
+            assert!(
+                entry.backend_type == TypeId::of::<D>(),
+                "`{}` is registered with the backend `{}` already, not `{}`",
+                entry.type_name,
+                entry.backend_name,
+                any::type_name::<D>()
+            );
+            return;
+        }
+
+        self.entries.push(Entry {
+            component_type: TypeId::of::<C>(),
+            type_name: any::type_name::<C>(),
+            backend_type: TypeId::of::<D>(),
+            backend_name: any::type_name::<D>(),
+            fetch: |res| {
+                res.try_fetch::<Storage<C, D, I>>().map(|fetch| TileStorageRef {
+                    inner: Box::new(Erased(fetch)),
+                })
+            },
+            fetch_mut: |res| {
+                res.try_fetch_mut::<Storage<C, D, I>>().map(|fetch| TileStorageMut {
+                    inner: Box::new(Erased(fetch)),
+                })
+            },
+            take_range: |storage, start, end| {
+                let storage = storage
+                    .as_any_mut()
+                    .downcast_mut::<Storage<C, D, I>>()
+                    .expect("registered with this storage type");
+                let ids: Vec<_> = BitSetAnd(storage.mask(), RangeMask::from_range(start, end))
+                    .iter()
+                    .collect();
+                let taken: Vec<(u32, C)> = ids
+                    .into_iter()
+                    .filter_map(|id| Some((id - start, storage.remove(I::from_u32(id))?)))
+                    .collect();
+
+                Box::new(taken)
+            },
+            move_tile: |storage, from, to| {
+                let storage = storage
+                    .as_any_mut()
+                    .downcast_mut::<Storage<C, D, I>>()
+                    .expect("registered with this storage type");
+                if !storage.bitset.contains(from) {
+                    return false;
+                }
+                // Checked before removing, so a bad target doesn't lose the component.
+                bound::expect_in_bounds(storage.check_bound(to));
+                if let Some(comp) = storage.remove(I::from_u32(from)) {
+                    storage.insert(I::from_u32(to), comp);
+                }
+
+                true
+            },
+            codec: None,
+            clone_tile: None,
+            hash_tiles: None,
+            probe: diagnostics::probe::<C, D, I>,
+            tracked: false,
+            #[cfg(feature = "serde")]
+            json: None,
+        });
+    }
+
+    /// Adds the storage resource `Storage<C, D, I>` to the registry like `register`, under
+    /// `name`, so tools which only know layers by name, like scripts, can look it up.
+    ///
+    /// Registering a layer under the name it already has does nothing. Every layer has at
+    /// most one name, which can't be used by another layer; the registry is left unchanged
+    /// if `name` is taken or `C` has another name.
+    pub fn register_named<C, D>(&mut self, name: &str) -> Result<(), LayerNameError>
+    where
+        C: Send + Sync + 'static,
+        D: UnprotectedStorage<C> + Send + Sync + 'static,
+    {
+        if let Some(&index) = self.names.get(name) {
+            let entry = &self.entries[index];
+            if entry.component_type == TypeId::of::<C>() {
+                return Ok(());
+            }
+
+            return Err(LayerNameError::Taken {
+                name: name.to_owned(),
+                type_name: entry.type_name,
+            });
+        }
+        if let Some(existing) = self.name_of::<C>() {
+            return Err(LayerNameError::AlreadyNamed {
+                type_name: any::type_name::<C>(),
+                name: existing.to_owned(),
+            });
+        }
+
+        self.register::<C, D>();
+        let index = self
+            .entries
+            .iter()
+            .position(|entry| entry.component_type == TypeId::of::<C>())
+            .expect("registered above");
+        self.names.insert(name.to_owned(), index);
+
+        Ok(())
+    }
+
+    /// Adds the storage resource `Storage<C, C::Storage, I>` to the registry like `register`,
+    /// and records how to encode its components, so it takes part in the byte-based tools
+    /// like `replay`. If `C` is registered already, it has to be with `C::Storage` as the
+    /// backend, which is what `TileWorldExt::register_tile_comp` uses.
+    pub fn register_codec<C>(&mut self)
+    where
+        C: Component + TileCodec + Send + Sync,
+        C::Storage: Send + Sync + 'static,
+    {
+        self.register::<C, C::Storage>();
+
+        let entry = self
+            .entries
+            .iter_mut()
+            .find(|entry| entry.component_type == TypeId::of::<C>())
+            .expect("registered above");
+        entry.codec = Some(Codec::new::<C, C::Storage, I>());
+    }
+
+    /// Like `register_codec`, but also records how to compare components, so
+    /// `apply_delta_report` can report which tiles of the layer it changed.
+    pub fn register_codec_eq<C>(&mut self)
+    where
+        C: Component + PartialEq + TileCodec + Send + Sync,
+        C::Storage: Send + Sync + 'static,
+    {
+        self.register::<C, C::Storage>();
+
+        let entry = self
+            .entries
+            .iter_mut()
+            .find(|entry| entry.component_type == TypeId::of::<C>())
+            .expect("registered above");
+        entry.codec = Some(Codec::with_eq::<C, C::Storage, I>());
+    }
+
+    /// Adds the storage resource `Storage<C, C::Storage, I>` to the registry like `register`,
+    /// and records how to clone its components, so `TileWorldExt::copy_tile` copies them. If `C`
+    /// is registered already, it has to be with `C::Storage` as the backend.
+    pub fn register_clone<C>(&mut self)
+    where
+        C: Component + Clone + Send + Sync,
+        C::Storage: Send + Sync + 'static,
+    {
+        self.register::<C, C::Storage>();
+
+        let entry = self
+            .entries
+            .iter_mut()
+            .find(|entry| entry.component_type == TypeId::of::<C>())
+            .expect("registered above");
+        entry.clone_tile = Some(|storage, from, to| {
+            let storage = storage
+                .as_any_mut()
+                .downcast_mut::<Storage<C, C::Storage, I>>()
+                .expect("registered with `C::Storage`");
+            match storage.get(I::from_u32(from)).cloned() {
+                Some(comp) => {
+                    storage.insert(I::from_u32(to), comp);
+                    true
+                }
+                None => false,
+            }
+        });
+    }
+
+    /// Returns `true` if `C` has been added with `register_clone`, so `TileWorldExt::copy_tile`
+    /// copies its layer.
+    pub fn is_cloneable<C: 'static>(&self) -> bool {
+        self.entries
+            .iter()
+            .any(|entry| entry.component_type == TypeId::of::<C>() && entry.clone_tile.is_some())
+    }
+
+    /// Adds the storage resource `Storage<C, C::Storage, I>` to the registry like `register`,
+    /// and marks it as tracked in `TileWorldExt::tile_diagnostics`, which can't tell on its
+    /// own whether a backend emits `ComponentEvent`s. If `C` is registered already, it has to
+    /// be with `C::Storage` as the backend.
+    pub fn register_tracked<C>(&mut self)
+    where
+        C: Component + Send + Sync,
+        C::Storage: Tracked + Send + Sync + 'static,
+    {
+        self.register::<C, C::Storage>();
+
+        let entry = self
+            .entries
+            .iter_mut()
+            .find(|entry| entry.component_type == TypeId::of::<C>())
+            .expect("registered above");
+        entry.tracked = true;
+    }
+
+    /// Adds the storage resource `Storage<C, C::Storage, I>` to the registry like `register`,
+    /// and records how to hash its components, so `TileWorldExt::tile_space_hash` includes it.
+    /// If `C` is registered already, it has to be with `C::Storage` as the backend.
+    pub fn register_hash<C>(&mut self)
+    where
+        C: Component + Hash + Send + Sync,
+        C::Storage: Send + Sync + 'static,
+    {
+        self.register::<C, C::Storage>();
+
+        let entry = self
+            .entries
+            .iter_mut()
+            .find(|entry| entry.component_type == TypeId::of::<C>())
+            .expect("registered above");
+        entry.hash_tiles = Some(|storage, mut state| {
+            storage
+                .as_any()
+                .downcast_ref::<Storage<C, C::Storage, I>>()
+                .expect("registered with `C::Storage`")
+                .content_hash(&mut state);
+        });
+    }
+
+    /// Adds the storage resource `Storage<C, C::Storage, I>` to the registry like `register`,
+    /// and records how to convert its components from and to JSON, so `TileWorldExt::tile_json`
+    /// and `TileWorldExt::set_tile_json` can access the layer by its name.
+    /// If `C` is registered already, it has to be with `C::Storage` as the backend.
+    #[cfg(feature = "serde")]
+    pub fn register_json<C>(&mut self)
+    where
+        C: Component + DeserializeOwned + Serialize + Send + Sync,
+        C::Storage: Send + Sync + 'static,
+    {
+        self.register::<C, C::Storage>();
+
+        let entry = self
+            .entries
+            .iter_mut()
+            .find(|entry| entry.component_type == TypeId::of::<C>())
+            .expect("registered above");
+        entry.json = Some(JsonTile::new::<C, C::Storage, I>());
+    }
+
+    /// Returns `true` if `C` has been added with `register_codec_eq`, so conflicts in its
+    /// layer can be reported.
+    pub fn reports_conflicts<C: 'static>(&self) -> bool {
+        self.entries
+            .iter()
+            .find(|entry| entry.component_type == TypeId::of::<C>())
+            .and_then(|entry| entry.codec)
+            .is_some_and(|codec| codec.decode_replace.is_some())
+    }
+
+    /// Returns `true` if a storage for the component type `C` is registered.
+    pub fn contains<C: 'static>(&self) -> bool {
+        self.entries.iter().any(|entry| entry.component_type == TypeId::of::<C>())
+    }
+
+    /// Returns the name the layer of `C` is registered under, if any.
+    pub fn name_of<C: 'static>(&self) -> Option<&str> {
+        self.names
+            .iter()
+            .find(|&(_, &index)| self.entries[index].component_type == TypeId::of::<C>())
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Returns the name of the component type of the layer registered under `name`.
+    pub fn type_name_of(&self, name: &str) -> Option<&'static str> {
+        self.names.get(name).map(|&index| self.entries[index].type_name)
+    }
+
+    /// Returns the names of all registered component types, in registration order.
+    pub fn names<'a>(&'a self) -> impl Iterator<Item = &'static str> + 'a {
+        self.entries.iter().map(|entry| entry.type_name)
+    }
+
+    /// Returns the amount of registered storages.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if no storage is registered.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the entries together with the names they are registered under.
+    pub(crate) fn named_entries(&self) -> Vec<(Entry, Option<String>)> {
+        let mut names = vec![None; self.entries.len()];
+        for (name, &index) in &self.names {
+            names[index] = Some(name.clone());
+        }
+
+        self.entries.iter().cloned().zip(names).collect()
+    }
+
+    pub(crate) fn entries(&self) -> Vec<Entry> {
+        self.entries.clone()
+    }
+
+    #[cfg(feature = "serde")]
+    pub(crate) fn layer_names(&self) -> Vec<String> {
+        self.names.keys().cloned().collect()
+    }
+
+    pub(crate) fn entry_named(&self, name: &str) -> Option<Entry> {
+        self.names.get(name).map(|&index| self.entries[index])
+    }
+}
+
+/// The outcome of `TileWorldExt::copy_tile`, naming the layers by their component types.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CopyReport {
+    /// The layers with a component at the source tile, which has been cloned to the target.
+    pub copied: Vec<&'static str>,
+    /// The layers which haven't been added with `TileRegistry::register_clone`, so they
+    /// were left alone.
+    pub skipped: Vec<&'static str>,
+}
+
+/// The outcome of `TileWorldExt::remove_tile`, naming the layers by their component types.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RemovedSummary {
+    /// The layers which had a component at the tile, which has been dropped.
+    pub removed: Vec<&'static str>,
+    /// The layers which had no component at the tile.
+    pub vacant: Vec<&'static str>,
+}
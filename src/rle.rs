@@ -0,0 +1,375 @@
+//! A storage compressing runs of equal components.
+
+use std::cmp::Ordering;
+use std::marker::PhantomData;
+use std::mem;
+
+use hibitset::BitSetLike;
+use specs::Join;
+
+use bound;
+use mask::BITS;
+use {Id, Index, RangeMask};
+
+/// `len` consecutive ids starting at `start`, all with the same component.
+pub struct Run<C> {
+    start: Index,
+    len: Index,
+    value: C,
+}
+
+impl<C> Run<C> {
+    fn end(&self) -> Index {
+        self.start + self.len
+    }
+}
+
+/// Returns the index of the run containing `id`, or where a run starting at `id` would
+/// have to be inserted.
+fn search<C>(runs: &[Run<C>], id: Index) -> Result<usize, usize> {
+    runs.binary_search_by(|run| {
+        if run.end() <= id {
+            Ordering::Less
+        } else if run.start > id {
+            Ordering::Greater
+        } else {
+            Ordering::Equal
+        }
+    })
+}
+
+/// A storage for huge, read-mostly layers like background terrain, which keeps sorted runs
+/// of consecutive ids with equal components instead of one component per tile.
+///
+/// `get` does a binary search over the runs, and joins walk the runs lazily, so reading is
+/// cheap. Writing may have to split or merge runs, which moves the runs behind it, so it's
+/// linear in their amount. Neighboring runs are always merged, so the amount of runs is as
+/// small as the layer allows.
+///
+/// Because a component is shared by all tiles of its run, it can't be handed out mutably,
+/// so like `PackedEnumStorage`, this isn't a backend of `Storage` but offers the same
+/// methods itself. Its mask is computed from the runs, so there's no per-tile bitset.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::{Component, Join, VecStorage};
+/// use specs_static::{RleStorage, Storage};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// #[derive(Clone, Debug, PartialEq)]
+/// struct Depth(u32);
+///
+/// struct Ship;
+///
+/// impl Component for Ship {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// fn runs(depths: &RleStorage<Depth, TileId>) -> Vec<(u32, u32, u32)> {
+///     depths.runs().map(|(start, len, depth)| (start.0, len, depth.0)).collect()
+/// }
+///
+/// fn main() {
+///     let mut depths = RleStorage::<Depth, TileId>::new();
+///     for id in 0..1_000_000 {
+///         depths.insert(TileId(id), Depth(if id < 600_000 { 50 } else { 80 }));
+///     }
+///     assert_eq!(runs(&depths), vec![(0, 600_000, 50), (600_000, 400_000, 80)]);
+///     assert_eq!(depths.get(TileId(599_999)), Some(&Depth(50)));
+///     assert_eq!(depths.get(TileId(1_000_000)), None);
+///     assert_eq!(depths.remove(TileId(u32::MAX)), None);
+///
+///     // Into the middle of a run, which splits it.
+///     assert_eq!(depths.insert(TileId(100), Depth(7)), Some(Depth(50)));
+///     assert_eq!(
+///         runs(&depths),
+///         vec![(0, 100, 50), (100, 1, 7), (101, 599_899, 50), (600_000, 400_000, 80)]
+///     );
+///
+///     // At run boundaries.
+///     depths.insert(TileId(599_999), Depth(80));
+///     depths.insert(TileId(0), Depth(9));
+///     assert_eq!(
+///         runs(&depths),
+///         vec![(0, 1, 9), (1, 99, 50), (100, 1, 7), (101, 599_898, 50), (599_999, 400_001, 80)]
+///     );
+///
+///     // A value equal to both neighbors merges them.
+///     assert_eq!(depths.insert(TileId(100), Depth(50)), Some(Depth(7)));
+///     assert_eq!(depths.insert(TileId(0), Depth(50)), Some(Depth(9)));
+///     assert_eq!(runs(&depths), vec![(0, 599_999, 50), (599_999, 400_001, 80)]);
+///     assert_eq!(depths.insert(TileId(5), Depth(50)), Some(Depth(50)));
+///     assert_eq!(depths.run_count(), 2);
+///
+///     // Removal splits a run, and removing its ends shrinks it.
+///     assert_eq!(depths.remove(TileId(300)), Some(Depth(50)));
+///     assert_eq!(depths.remove(TileId(300)), None);
+///     depths.remove(TileId(0));
+///     depths.remove(TileId(999_999));
+///     assert_eq!(
+///         runs(&depths),
+///         vec![(1, 299, 50), (301, 599_698, 50), (599_999, 400_000, 80)]
+///     );
+///     // Filling the hole merges the runs again.
+///     depths.insert(TileId(300), Depth(50));
+///     assert_eq!(runs(&depths), vec![(1, 599_998, 50), (599_999, 400_000, 80)]);
+///     depths.insert(TileId(500_000), Depth(1));
+///     depths.remove(TileId(500_000));
+///     depths.insert(TileId(500_000), Depth(50));
+///     assert_eq!(depths.run_count(), 2);
+///
+///     let mut ships = Storage::<Ship, VecStorage<_>, TileId>::default();
+///     for &id in &[0, 1, 700_000, 999_999] {
+///         ships.insert(TileId(id), Ship);
+///     }
+///     let under_ships: Vec<u32> = (&depths, &ships).join().map(|(d, _)| d.0).collect();
+///     assert_eq!(under_ships, vec![50, 80]);
+///     assert_eq!((&depths).join().count(), 999_998);
+///     assert!(depths.memory_usage() < 1_000);
+/// }
+/// ```
+pub struct RleStorage<C, I> {
+    runs: Vec<Run<C>>,
+    phantom: PhantomData<I>,
+}
+
+impl<C, I> Default for RleStorage<C, I> {
+    fn default() -> Self {
+        RleStorage {
+            runs: Vec::new(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<C, I> RleStorage<C, I>
+where
+    C: Clone + PartialEq,
+    I: Id,
+{
+    /// Creates an empty storage.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Returns the component at `id`, if there is one.
+    pub fn get(&self, id: I) -> Option<&C> {
+        search(&self.runs, id.id())
+            .ok()
+            .map(|index| &self.runs[index].value)
+    }
+
+    /// Returns `true` if there's a component at `id`.
+    pub fn contains(&self, id: I) -> bool {
+        search(&self.runs, id.id()).is_ok()
+    }
+
+    /// Inserts `comp` at `id`. If there already was a value, it will be returned, even if it's
+    /// equal to `comp`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` exceeds `Storage::MAX_ID`.
+    ///
+    /// ```should_panic
+    /// # extern crate specs_static;
+    /// # use specs_static::RleStorage;
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// # fn main() {
+    /// let mut depths = RleStorage::<u32, TileId>::new();
+    /// // Panics: id 4294967295 exceeds the bound 16777215 of the storage.
+    /// depths.insert(TileId(u32::MAX), 50);
+    /// # }
+    /// ```
+    pub fn insert(&mut self, id: I, comp: C) -> Option<C> {
+        bound::expect_max_id(id.id());
+
+        let old = match search(&self.runs, id.id()) {
+            Ok(index) if self.runs[index].value == comp => {
+                return Some(self.runs[index].value.clone())
+            }
+            Ok(_) => self.remove(id),
+            Err(_) => None,
+        };
+
+        let id = id.id();
+        let index = search(&self.runs, id).expect_err("removed above");
+        let joins_left = index > 0 && {
+            let left = &self.runs[index - 1];
+            left.end() == id && left.value == comp
+        };
+        let joins_right = index < self.runs.len() && {
+            let right = &self.runs[index];
+            right.start == id + 1 && right.value == comp
+        };
+
+        match (joins_left, joins_right) {
+            (true, true) => {
+                let right = self.runs.remove(index);
+                self.runs[index - 1].len += 1 + right.len;
+            }
+            (true, false) => self.runs[index - 1].len += 1,
+            (false, true) => {
+                self.runs[index].start -= 1;
+                self.runs[index].len += 1;
+            }
+            (false, false) => self.runs.insert(
+                index,
+                Run {
+                    start: id,
+                    len: 1,
+                    value: comp,
+                },
+            ),
+        }
+
+        old
+    }
+
+    /// Removes the component at `id`.
+    pub fn remove(&mut self, id: I) -> Option<C> {
+        let id = id.id();
+        let index = search(&self.runs, id).ok()?;
+        let (start, end) = (self.runs[index].start, self.runs[index].end());
+
+        if end - start == 1 {
+            return Some(self.runs.remove(index).value);
+        }
+
+        let run = &mut self.runs[index];
+        let value = run.value.clone();
+        if id == start {
+            run.start += 1;
+            run.len -= 1;
+        } else if id == end - 1 {
+            run.len -= 1;
+        } else {
+            run.len = id - start;
+            let rest = Run {
+                start: id + 1,
+                len: end - id - 1,
+                value: value.clone(),
+            };
+            self.runs.insert(index + 1, rest);
+        }
+
+        Some(value)
+    }
+
+    /// Removes all components.
+    pub fn clear(&mut self) {
+        self.runs.clear();
+    }
+
+    /// Returns the mask of all occupied ids, which is computed from the runs.
+    pub fn mask<'a>(&'a self) -> RunMask<'a, C> {
+        RunMask { runs: &self.runs }
+    }
+
+    /// Returns the amount of runs.
+    pub fn run_count(&self) -> usize {
+        self.runs.len()
+    }
+
+    /// Yields the first id, the length and the component of every run, in id order.
+    pub fn runs<'a>(&'a self) -> impl Iterator<Item = (I, u32, &'a C)> + 'a {
+        self.runs
+            .iter()
+            .map(|run| (I::from_u32(run.start), run.len, &run.value))
+    }
+
+    /// Returns the heap memory used for the runs, in bytes. Memory owned by the components
+    /// themselves isn't included.
+    pub fn memory_usage(&self) -> usize {
+        self.runs.capacity() * mem::size_of::<Run<C>>()
+    }
+}
+
+/// The mask of a `RleStorage`, whose words are computed from the runs.
+pub struct RunMask<'a, C: 'a> {
+    runs: &'a [Run<C>],
+}
+
+impl<'a, C> RunMask<'a, C> {
+    fn word(&self, level: usize, i: usize) -> usize {
+        // The ids word `i` of `level` covers.
+        let shift = BITS * (level + 1);
+        let start = (i as u64) << shift;
+        let end = (i as u64 + 1) << shift;
+
+        let first = match search(self.runs, start as Index) {
+            Ok(index) | Err(index) => index,
+        };
+
+        self.runs[first..]
+            .iter()
+            .take_while(|run| u64::from(run.start) < end)
+            .fold(0, |word, run| {
+                word | RangeMask::from_range(run.start, run.end()).word(level, i)
+            })
+    }
+}
+
+impl<'a, C> BitSetLike for RunMask<'a, C> {
+    fn layer3(&self) -> usize {
+        self.word(3, 0)
+    }
+
+    fn layer2(&self, i: usize) -> usize {
+        self.word(2, i)
+    }
+
+    fn layer1(&self, i: usize) -> usize {
+        self.word(1, i)
+    }
+
+    fn layer0(&self, i: usize) -> usize {
+        self.word(0, i)
+    }
+
+    fn contains(&self, i: Index) -> bool {
+        search(self.runs, i).is_ok()
+    }
+}
+
+/// The runs of a joined `RleStorage`, remembering the run of the last id.
+pub struct RunCursor<'a, C: 'a> {
+    runs: &'a [Run<C>],
+    last: usize,
+}
+
+impl<'a, C, I> Join for &'a RleStorage<C, I> {
+    type Type = &'a C;
+    type Value = RunCursor<'a, C>;
+    type Mask = RunMask<'a, C>;
+
+    unsafe fn open(self) -> (Self::Mask, Self::Value) {
+        let cursor = RunCursor {
+            runs: &self.runs,
+            last: 0,
+        };
+
+        (RunMask { runs: &self.runs }, cursor)
+    }
+
+    unsafe fn get(cursor: &mut Self::Value, id: Index) -> &'a C {
+        // Joins visit the ids in ascending order, so the run is usually the last or the
+        // next one.
+        let runs = cursor.runs;
+        let hit = |index: usize| {
+            index < runs.len() && runs[index].start <= id && id < runs[index].end()
+        };
+        if !hit(cursor.last) {
+            cursor.last = match hit(cursor.last + 1) {
+                true => cursor.last + 1,
+                false => search(runs, id).expect("the mask says the id is occupied"),
+            };
+        }
+
+        &runs[cursor.last].value
+    }
+}
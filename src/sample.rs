@@ -0,0 +1,90 @@
+//! Picking random occupied ids.
+
+use rand::Rng;
+use specs::storage::UnprotectedStorage;
+
+use {Id, Storage};
+
+impl<C, D, I> Storage<C, D, I>
+where
+    D: UnprotectedStorage<C>,
+    I: Id,
+{
+    /// Returns `n` distinct occupied ids chosen uniformly at random, or all occupied ids if
+    /// there are at most `n`.
+    ///
+    /// The ids are chosen with reservoir sampling in one pass over the bitset, so no list of
+    /// all ids is built and only `n` ids are kept at a time. The storage doesn't know how
+    /// many ids are occupied without counting them, so this takes time linear in the amount
+    /// of occupied ids even for dense layers. The order of the returned ids is unspecified,
+    /// but the same for the same contents and the same random number generator state.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate rand;
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use rand::prng::XorShiftRng;
+    /// use rand::SeedableRng;
+    /// use specs::{Component, VecStorage};
+    /// use specs_static::Storage;
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// struct SpawnPoint;
+    ///
+    /// impl Component for SpawnPoint {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut spawns = Storage::<SpawnPoint, VecStorage<_>, TileId>::default();
+    ///     for id in 0..1000 {
+    ///         spawns.insert(TileId(id * 7), SpawnPoint);
+    ///     }
+    ///
+    ///     let mut rng = XorShiftRng::from_seed([7; 16]);
+    ///     let mut picked = spawns.sample_ids(&mut rng, 20);
+    ///     assert_eq!(picked.len(), 20);
+    ///     assert!(picked.iter().all(|&id| spawns.get(id).is_some()));
+    ///
+    ///     // The same seed picks the same ids.
+    ///     let again = spawns.sample_ids(&mut XorShiftRng::from_seed([7; 16]), 20);
+    ///     assert_eq!(again, picked);
+    ///
+    ///     picked.sort();
+    ///     picked.dedup();
+    ///     assert_eq!(picked.len(), 20);
+    ///
+    ///     // Asking for more ids than there are returns all of them.
+    ///     let mut all = spawns.sample_ids(&mut rng, 5000);
+    ///     all.sort();
+    ///     assert_eq!(all, (0..1000).map(|id| TileId(id * 7)).collect::<Vec<_>>());
+    ///     assert!(spawns.sample_ids(&mut rng, 0).is_empty());
+    /// }
+    /// ```
+    pub fn sample_ids<R>(&self, rng: &mut R, n: usize) -> Vec<I>
+    where
+        R: Rng + ?Sized,
+    {
+        let mut picked = Vec::with_capacity(n.min(1024));
+        if n == 0 {
+            return picked;
+        }
+
+        for (seen, id) in (&self.bitset).into_iter().enumerate() {
+            if seen < n {
+                picked.push(I::from_u32(id));
+            } else {
+                // Every id seen so far ends up picked with a probability of `n / (seen + 1)`.
+                let slot = rng.gen_range(0, seen + 1);
+                if slot < n {
+                    picked[slot] = I::from_u32(id);
+                }
+            }
+        }
+
+        picked
+    }
+}
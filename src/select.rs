@@ -0,0 +1,123 @@
+//! Selecting occupied ids by their components into masks.
+
+use hibitset::BitSet;
+use specs::storage::UnprotectedStorage;
+
+use {Id, Storage};
+
+impl<C, D, I> Storage<C, D, I>
+where
+    D: UnprotectedStorage<C>,
+    I: Id,
+{
+    /// Returns the mask of all occupied ids whose component matches `pred`.
+    ///
+    /// The mask can be joined with storages or be passed to mask-based methods. For
+    /// predicates over two layers, select on one of them and intersect the result with the
+    /// `mask` of the other one, like `BitSetAnd(&wet, warm.mask())`, or join both storages
+    /// with the selection.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate hibitset;
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use hibitset::BitSetAnd;
+    /// use specs::{Component, Join, NullStorage, VecStorage};
+    /// use specs_static::Storage;
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// struct Moisture(f32);
+    ///
+    /// impl Component for Moisture {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// #[derive(Default)]
+    /// struct Fertile;
+    ///
+    /// impl Component for Fertile {
+    ///     type Storage = NullStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut moisture = Storage::<Moisture, VecStorage<_>, TileId>::default();
+    ///     let mut fertile = Storage::<Fertile, NullStorage<_>, TileId>::default();
+    ///     for id in 0..10 {
+    ///         moisture.insert(TileId(id), Moisture(id as f32 / 10.0));
+    ///         if id % 2 == 0 {
+    ///             fertile.insert(TileId(id), Fertile);
+    ///         }
+    ///     }
+    ///
+    ///     let wet = moisture.select(|_, m| m.0 > 0.55);
+    ///     assert_eq!((&wet).join().collect::<Vec<_>>(), vec![6, 7, 8, 9]);
+    ///
+    ///     // Wet and fertile.
+    ///     let both = BitSetAnd(&wet, fertile.mask());
+    ///     assert_eq!(both.join().collect::<Vec<_>>(), vec![6, 8]);
+    ///
+    ///     assert_eq!((&moisture.select(|_, _| true)).join().count(), 10);
+    ///     assert_eq!((&moisture.select(|_, m| m.0 > 1.0)).join().count(), 0);
+    /// }
+    /// ```
+    pub fn select<F>(&self, pred: F) -> BitSet
+    where
+        F: FnMut(I, &C) -> bool,
+    {
+        let mut selected = BitSet::new();
+        self.select_into(&mut selected, pred);
+
+        selected
+    }
+
+    /// Like `select`, but writes the selection to `out`, reusing its allocation. `out` is
+    /// cleared first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate hibitset;
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use hibitset::BitSet;
+    /// use specs::{Component, Join, VecStorage};
+    /// use specs_static::Storage;
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// struct Fire(u8);
+    ///
+    /// impl Component for Fire {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut fire = Storage::<Fire, VecStorage<_>, TileId>::default();
+    ///     let mut burning = BitSet::new();
+    ///     burning.add(1000);
+    ///
+    ///     for frame in 0..3 {
+    ///         fire.insert(TileId(frame), Fire(frame as u8 * 2));
+    ///         fire.select_into(&mut burning, |id, fire| id.0 % 2 == 0 || fire.0 > 3);
+    ///     }
+    ///     assert_eq!((&burning).join().collect::<Vec<_>>(), vec![0, 2]);
+    ///
+    ///     fire.select_into(&mut burning, |_, _| false);
+    ///     assert_eq!((&burning).join().count(), 0);
+    /// }
+    /// ```
+    pub fn select_into<F>(&self, out: &mut BitSet, mut pred: F)
+    where
+        F: FnMut(I, &C) -> bool,
+    {
+        out.clear();
+        for id in &self.bitset {
+            if pred(I::from_u32(id), unsafe { self.data.get(id) }) {
+                out.add(id);
+            }
+        }
+    }
+}
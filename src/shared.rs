@@ -0,0 +1,241 @@
+//! Handles through which other threads read the latest published state of a layer.
+
+use std::mem;
+use std::sync::{Arc, PoisonError, RwLock};
+
+use shred::{ReadExpect, System};
+use specs::storage::UnprotectedStorage;
+use specs::Component;
+
+use {CowStorage, FrozenStorage, Id, ReadTiles, Storage};
+
+struct Published<C, I> {
+    frozen: FrozenStorage<C, I>,
+    generation: u64,
+}
+
+/// A cloneable handle to the latest published state of a layer, for threads which can't
+/// borrow the `World`, like an editor UI running next to the game loop.
+///
+/// `TileWorldExt::share_tile_comp` creates it and adds it to the world as a resource. The
+/// storage itself stays an ordinary resource, so systems keep fetching it with `ReadTiles`
+/// and `WriteTiles`; a `TileShareSystem` scheduled after the writers, or a call to `publish`,
+/// freezes it into the handle. `read` returns the last published `FrozenStorage`, which the
+/// other thread can keep for as long as it likes.
+///
+/// # Staleness
+///
+/// A read shows the layer as of the last publish, so it lags behind the live storage by up
+/// to a frame, and changes made after the `TileShareSystem` ran only show up in the next one.
+/// `generation` counts the publishes, which lets a reader tell whether anything new arrived.
+///
+/// # Contention
+///
+/// The handle's lock is only held to swap or clone the `Arc` of the snapshot, never while a
+/// storage is frozen or a snapshot is read, so neither side can block the other for long,
+/// and a reader can't deadlock with the dispatcher. Freezing happens on the dispatcher's
+/// thread instead: `freeze_cloned` takes time proportional to the amount of components,
+/// while storages using `CowStorage` can publish with the cheap `freeze`. Snapshots still
+/// held by a reader keep their memory alive.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use std::thread;
+///
+/// use specs::{Component, DispatcherBuilder, System, VecStorage, World, WorldExt};
+/// use specs_static::prelude::*;
+/// use specs_static::TileShareSystem;
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// #[derive(Clone, Debug, PartialEq)]
+/// struct Water(u32);
+///
+/// impl Component for Water {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// struct Flood;
+///
+/// impl<'a> System<'a> for Flood {
+///     type SystemData = WriteTiles<'a, Water, TileId>;
+///
+///     fn run(&mut self, mut water: Self::SystemData) {
+///         let level = water.get(TileId(0)).map_or(1, |water| water.0 + 1);
+///         for id in 0..64 {
+///             water.insert(TileId(id), Water(level));
+///         }
+///     }
+/// }
+///
+/// fn main() {
+///     let mut world = World::new();
+///     world.register_tile_comp::<Water, TileId>();
+///     let shared = world.share_tile_comp::<Water, TileId>();
+///     assert_eq!(shared.generation(), 1);
+///     assert_eq!(shared.read().iter().count(), 0);
+///
+///     let mut dispatcher = DispatcherBuilder::new()
+///         .with(Flood, "flood", &[])
+///         .with(TileShareSystem::<Water, TileId>::new(), "share", &["flood"])
+///         .build();
+///
+///     // The editor UI, which never touches the world.
+///     let ui = {
+///         let shared = shared.clone();
+///         thread::spawn(move || loop {
+///             let water = shared.read();
+///             // Every snapshot is a whole frame, never a half-flooded one.
+///             let level = water.get(TileId(0)).map_or(0, |water| water.0);
+///             assert!(water.iter().all(|(_, water)| water.0 == level));
+///             if level == 100 {
+///                 return water.iter().count();
+///             }
+///         })
+///     };
+///
+///     for _ in 0..100 {
+///         dispatcher.dispatch(&world);
+///     }
+///     assert_eq!(ui.join().unwrap(), 64);
+///     assert_eq!(shared.generation(), 101);
+///
+///     // Changes outside the dispatcher only show up once they're published.
+///     type Waters = Storage<Water, VecStorage<Water>, TileId>;
+///     world.write_resource::<Waters>().remove(TileId(3));
+///     assert!(shared.read().contains(TileId(3)));
+///     shared.publish(world.read_resource::<Waters>().freeze_cloned());
+///     assert!(!shared.read().contains(TileId(3)));
+/// }
+/// ```
+pub struct SharedTileStorage<C, I> {
+    inner: Arc<RwLock<Published<C, I>>>,
+}
+
+impl<C, I> Clone for SharedTileStorage<C, I> {
+    fn clone(&self) -> Self {
+        SharedTileStorage {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<C, I> SharedTileStorage<C, I>
+where
+    I: Id,
+{
+    /// Creates a handle whose first generation is `frozen`.
+    pub fn new(frozen: FrozenStorage<C, I>) -> Self {
+        SharedTileStorage {
+            inner: Arc::new(RwLock::new(Published {
+                frozen,
+                generation: 1,
+            })),
+        }
+    }
+
+    /// Returns the last published state of the layer.
+    pub fn read(&self) -> FrozenStorage<C, I> {
+        // Publishing can't panic while holding the lock, so poisoning can be ignored.
+        let published = self.inner.read().unwrap_or_else(PoisonError::into_inner);
+
+        published.frozen.clone()
+    }
+
+    /// Returns how many states have been published, including the initial one.
+    pub fn generation(&self) -> u64 {
+        self.inner
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .generation
+    }
+
+    /// Replaces the published state with `frozen`, from `Storage::freeze` or
+    /// `Storage::freeze_cloned`.
+    pub fn publish(&self, frozen: FrozenStorage<C, I>) {
+        let old = {
+            let mut published = self.inner.write().unwrap_or_else(PoisonError::into_inner);
+            published.generation += 1;
+
+            mem::replace(&mut published.frozen, frozen)
+        };
+        // Dropping the last handle to the old snapshot frees its components, which isn't
+        // worth blocking the readers for.
+        drop(old);
+    }
+}
+
+impl<C, I> SharedTileStorage<C, I>
+where
+    C: Clone,
+    I: Id,
+{
+    pub(crate) fn share<D>(storage: &Storage<C, D, I>) -> Self
+    where
+        D: UnprotectedStorage<C>,
+    {
+        SharedTileStorage::new(storage.freeze_cloned())
+    }
+}
+
+type Freeze<C, I> = fn(&Storage<C, <C as Component>::Storage, I>) -> FrozenStorage<C, I>;
+
+/// A system publishing the storage resource `Storage<C, C::Storage, I>` to the
+/// `SharedTileStorage<C, I>` resource added by `TileWorldExt::share_tile_comp`.
+///
+/// Schedule it after the systems writing the layer. `new` clones every component with
+/// `freeze_cloned`; for storages using `CowStorage`, `cow` freezes cheaply instead.
+pub struct TileShareSystem<C: Component, I> {
+    freeze: Freeze<C, I>,
+}
+
+impl<C, I> TileShareSystem<C, I>
+where
+    C: Component + Clone + Send + Sync,
+    I: Id,
+{
+    /// Creates a system which publishes by cloning every component.
+    pub fn new() -> Self {
+        TileShareSystem {
+            freeze: Storage::freeze_cloned,
+        }
+    }
+}
+
+impl<C, I> TileShareSystem<C, I>
+where
+    C: Component<Storage = CowStorage<C>> + Clone + Send + Sync,
+    I: Id,
+{
+    /// Creates a system which publishes with `Storage::freeze`, only sharing the chunks.
+    pub fn cow() -> Self {
+        TileShareSystem {
+            freeze: Storage::freeze,
+        }
+    }
+}
+
+impl<C, I> Default for TileShareSystem<C, I>
+where
+    C: Component + Clone + Send + Sync,
+    I: Id,
+{
+    fn default() -> Self {
+        TileShareSystem::new()
+    }
+}
+
+impl<'a, C, I> System<'a> for TileShareSystem<C, I>
+where
+    C: Component + Send + Sync,
+    I: Id,
+{
+    type SystemData = (ReadTiles<'a, C, I>, ReadExpect<'a, SharedTileStorage<C, I>>);
+
+    fn run(&mut self, (storage, shared): Self::SystemData) {
+        shared.publish((self.freeze)(&storage));
+    }
+}
@@ -0,0 +1,288 @@
+//! A `Vec`-based backend owned by this crate, so it can offer bulk operations on its slots.
+
+use std::mem::MaybeUninit;
+use std::ptr;
+
+use hibitset::{BitSet, BitSetLike};
+use specs::storage::{DistinctStorage, UnprotectedStorage};
+
+use fill::CopyFill;
+use mask::BITS;
+use {Grid2d, Id, Index, RangeMask, ReserveIds, Storage};
+
+/// Backends which store the slot of id `n` at index `n` of a contiguous slice.
+pub trait SliceAccess<C>: UnprotectedStorage<C> {
+    /// Returns all slots of this backend. Only the slots which are marked as occupied by
+    /// the storage's bitset are initialized.
+    fn slots(&self) -> &[MaybeUninit<C>];
+
+    /// Returns all slots of this backend mutably.
+    ///
+    /// # Safety
+    ///
+    /// The caller must not uninitialize occupied slots.
+    unsafe fn slots_mut(&mut self) -> &mut [MaybeUninit<C>];
+}
+
+/// Vector storage, just like `specs::VecStorage`, where slot `n` belongs to id `n`.
+///
+/// Because this crate owns its internals, it supports bulk operations that `VecStorage`
+/// can't offer, like `Storage::fill_range_copy`.
+#[derive(Derivative)]
+#[derivative(Default(bound = ""))]
+pub struct SliceStorage<C>(Vec<MaybeUninit<C>>);
+
+impl<C> SliceStorage<C> {
+    /// Returns the number of ids this backend has slots for without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.0.capacity()
+    }
+
+    /// Makes sure there are slots for every id below `len`.
+    pub(crate) fn grow(&mut self, len: usize) {
+        if self.0.len() < len {
+            self.0.resize_with(len, MaybeUninit::uninit);
+        }
+    }
+}
+
+impl<C> UnprotectedStorage<C> for SliceStorage<C> {
+    unsafe fn clean<B>(&mut self, has: B)
+    where
+        B: BitSetLike,
+    {
+        for (i, value) in self.0.iter_mut().enumerate() {
+            if has.contains(i as Index) {
+                ptr::drop_in_place(value.as_mut_ptr());
+            }
+        }
+        self.0.clear();
+    }
+
+    unsafe fn get(&self, id: Index) -> &C {
+        &*self.0.get_unchecked(id as usize).as_ptr()
+    }
+
+    unsafe fn get_mut(&mut self, id: Index) -> &mut C {
+        // Going through `Vec::as_mut_ptr` doesn't reborrow the whole buffer, so the
+        // references a mutable join has handed out for other ids stay valid.
+        &mut *(*self.0.as_mut_ptr().add(id as usize)).as_mut_ptr()
+    }
+
+    unsafe fn insert(&mut self, id: Index, value: C) {
+        self.grow(id as usize + 1);
+        ptr::write(self.0.get_unchecked_mut(id as usize).as_mut_ptr(), value);
+    }
+
+    unsafe fn remove(&mut self, id: Index) -> C {
+        ptr::read(self.get(id))
+    }
+}
+
+impl<C> ReserveIds for SliceStorage<C> {
+    fn reserve_ids(&mut self, len: u32) {
+        self.0.reserve((len as usize).saturating_sub(self.0.len()));
+    }
+}
+
+unsafe impl<C> DistinctStorage for SliceStorage<C> {}
+
+impl<C> SliceAccess<C> for SliceStorage<C> {
+    fn slots(&self) -> &[MaybeUninit<C>] {
+        &self.0
+    }
+
+    unsafe fn slots_mut(&mut self) -> &mut [MaybeUninit<C>] {
+        &mut self.0
+    }
+}
+
+impl<C> CopyFill<C> for SliceStorage<C>
+where
+    C: Copy,
+{
+    unsafe fn fill_copy(&mut self, start: Index, end: Index, value: C) {
+        self.grow(end as usize);
+        for slot in &mut self.0[start as usize..end as usize] {
+            *slot = MaybeUninit::new(value);
+        }
+    }
+}
+
+impl<C, D, I> Storage<C, D, I>
+where
+    D: SliceAccess<C>,
+    I: Id,
+{
+    /// Returns all components as one slice, where the component of id `n` is at index `n`.
+    ///
+    /// This only succeeds if every slot the backend has allocated is occupied, so the
+    /// occupied ids are exactly `0..n` with `n` being one past the highest id ever inserted.
+    /// Otherwise there would be vacant slots which could be read, and `None` is returned.
+    /// Checking this is done word-wise on the bitset.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, VecStorage};
+    /// use specs_static::{SliceStorage, Storage};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// #[derive(Clone, Copy, Debug, PartialEq)]
+    /// struct Elevation(u16);
+    ///
+    /// impl Component for Elevation {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut elevations = Storage::<Elevation, SliceStorage<_>, TileId>::default();
+    ///     elevations.fill_range_copy(TileId(0), TileId(300), Elevation(4));
+    ///     elevations.insert(TileId(299), Elevation(9));
+    ///
+    ///     // The renderer can upload the whole layer at once.
+    ///     let mut gpu_buffer = Vec::new();
+    ///     gpu_buffer.extend_from_slice(elevations.as_slice().unwrap());
+    ///     assert_eq!(gpu_buffer.len(), 300);
+    ///     assert_eq!(gpu_buffer[299], Elevation(9));
+    ///
+    ///     // A hole means there's no slice.
+    ///     elevations.remove(TileId(150));
+    ///     assert!(elevations.as_slice().is_none());
+    ///
+    ///     let (slots, mask) = elevations.as_slots();
+    ///     assert_eq!(slots.len(), 300);
+    ///     assert!(!mask.contains(150));
+    /// }
+    /// ```
+    pub fn as_slice(&self) -> Option<&[C]> {
+        let len = self.dense_len()?;
+        let slots = &self.data.slots()[..len];
+
+        Some(unsafe { &*(slots as *const [MaybeUninit<C>] as *const [C]) })
+    }
+
+    /// Returns all components as one mutable slice, with the same restrictions as `as_slice`.
+    pub fn as_mut_slice(&mut self) -> Option<&mut [C]> {
+        let len = self.dense_len()?;
+        let slots = unsafe { &mut self.data.slots_mut()[..len] };
+
+        Some(unsafe { &mut *(slots as *mut [MaybeUninit<C>] as *mut [C]) })
+    }
+
+    /// Returns the components of row `y` of `grid` as one slice, where the component at
+    /// `(x, y)` is at index `x`.
+    ///
+    /// This only succeeds if every tile of the row is occupied, and returns `None` otherwise
+    /// or if `y` is outside the grid, so callers can fall back to accessing tile by tile.
+    /// Like `as_slice`, this checks the bitset word-wise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, VecStorage};
+    /// use specs_static::{Grid2d, SliceStorage, Storage};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// #[derive(Clone, Copy, Debug, PartialEq)]
+    /// struct Elevation(u16);
+    ///
+    /// impl Component for Elevation {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let grid = Grid2d::new(100, 4);
+    ///     let mut elevations = Storage::<Elevation, SliceStorage<_>, TileId>::default();
+    ///     elevations.fill_range_copy(TileId(0), TileId(400), Elevation(4));
+    ///     elevations.remove(grid.id(70, 2).unwrap());
+    ///
+    ///     // Smooth every row which is complete.
+    ///     for y in 0..4 {
+    ///         if let Some(row) = elevations.row_slice_mut(&grid, y) {
+    ///             for x in 1..row.len() {
+    ///                 row[x].0 = (row[x - 1].0 + row[x].0 + 1) / 2;
+    ///             }
+    ///         }
+    ///     }
+    ///
+    ///     assert_eq!(elevations.row_slice(&grid, 1).map(|row| row.len()), Some(100));
+    ///     assert!(elevations.row_slice(&grid, 2).is_none());
+    ///     assert!(elevations.row_slice(&grid, 4).is_none());
+    ///     assert_eq!(elevations.get(grid.id(3, 3).unwrap()), Some(&Elevation(4)));
+    ///
+    ///     elevations.insert(grid.id(70, 2).unwrap(), Elevation(1));
+    ///     assert_eq!(elevations.row_slice(&grid, 2).unwrap()[70], Elevation(1));
+    /// }
+    /// ```
+    pub fn row_slice(&self, grid: &Grid2d, y: u32) -> Option<&[C]> {
+        let (start, end) = self.dense_row(grid, y)?;
+        let slots = &self.data.slots()[start..end];
+
+        Some(unsafe { &*(slots as *const [MaybeUninit<C>] as *const [C]) })
+    }
+
+    /// Returns the components of row `y` of `grid` as one mutable slice, with the same
+    /// restrictions as `row_slice`.
+    pub fn row_slice_mut(&mut self, grid: &Grid2d, y: u32) -> Option<&mut [C]> {
+        let (start, end) = self.dense_row(grid, y)?;
+        let slots = unsafe { &mut self.data.slots_mut()[start..end] };
+
+        Some(unsafe { &mut *(slots as *mut [MaybeUninit<C>] as *mut [C]) })
+    }
+
+    /// Returns all slots of the backend together with the mask telling which of them
+    /// are initialized.
+    pub fn as_slots(&self) -> (&[MaybeUninit<C>], &BitSet) {
+        (self.data.slots(), &self.bitset)
+    }
+
+    /// Returns the slot range of row `y` of `grid` if all of its ids are occupied.
+    fn dense_row(&self, grid: &Grid2d, y: u32) -> Option<(usize, usize)> {
+        if y >= grid.height() {
+            return None;
+        }
+        let start = y * grid.width();
+        let end = start + grid.width();
+        if start == end {
+            return Some((0, 0));
+        }
+
+        let row = RangeMask::from_range(start, end);
+        let words = (start as usize >> BITS)..=((end as usize - 1) >> BITS);
+        let occupied = words.into_iter().all(|i| {
+            let word = row.word(0, i);
+            self.bitset.layer0(i) & word == word
+        });
+
+        // Occupied ids always have a slot, so the range is in bounds.
+        match occupied {
+            true => Some((start as usize, end as usize)),
+            false => None,
+        }
+    }
+
+    /// Returns `Some(n)` if exactly the ids `0..n` are occupied.
+    fn dense_len(&self) -> Option<usize> {
+        // The backend has a slot for every id that has ever been inserted,
+        // so nothing beyond `len` can be occupied.
+        let len = self.data.slots().len();
+        let full_words = len >> BITS;
+        let rest = len & ((1 << BITS) - 1);
+
+        let full = (0..full_words).all(|i| self.bitset.layer0(i) == !0);
+        let partial = rest == 0 || self.bitset.layer0(full_words) == (1 << rest) - 1;
+
+        if full && partial {
+            Some(len)
+        } else {
+            None
+        }
+    }
+}
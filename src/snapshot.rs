@@ -0,0 +1,423 @@
+//! Saving the named JSON layers of a world together with its grid, and loading them back.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+
+use hibitset::BitSetLike;
+use serde::de::{self, Deserialize, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use serde_json::Value;
+use shred::World;
+
+use {registry_json_entry, Grid2d, Id, TileJsonError, TileRegistry};
+
+/// The width and the height, as a struct with the fields `width` and `height`.
+impl<S: ?Sized> Serialize for Grid2d<S> {
+    fn serialize<Z>(&self, serializer: Z) -> Result<Z::Ok, Z::Error>
+    where
+        Z: Serializer,
+    {
+        let mut grid = serializer.serialize_struct("Grid2d", 2)?;
+        grid.serialize_field("width", &self.width())?;
+        grid.serialize_field("height", &self.height())?;
+
+        grid.end()
+    }
+}
+
+/// Fails for grids which `Grid2d::try_new` rejects. Tagged grids are deserialized as plain
+/// ones and tagged afterwards.
+impl<'de> Deserialize<'de> for Grid2d {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct GridVisitor;
+
+        impl<'de> Visitor<'de> for GridVisitor {
+            type Value = Grid2d;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a grid with a width and a height")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Grid2d, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let width = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                let height = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+
+                Grid2d::try_new(width, height).map_err(de::Error::custom)
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Grid2d, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let (mut width, mut height) = (None, None);
+                while let Some(key) = map.next_key::<String>()? {
+                    let field = match key.as_str() {
+                        "width" => &mut width,
+                        "height" => &mut height,
+                        _ => {
+                            map.next_value::<IgnoredAny>()?;
+                            continue;
+                        }
+                    };
+                    if field.is_some() {
+                        return Err(de::Error::custom(format_args!("duplicate field `{}`", key)));
+                    }
+                    *field = Some(map.next_value()?);
+                }
+                let width = width.ok_or_else(|| de::Error::missing_field("width"))?;
+                let height = height.ok_or_else(|| de::Error::missing_field("height"))?;
+
+                Grid2d::try_new(width, height).map_err(de::Error::custom)
+            }
+        }
+
+        deserializer.deserialize_struct("Grid2d", &["width", "height"], GridVisitor)
+    }
+}
+
+/// The components of the named JSON layers of a world, together with the grid they were
+/// placed on.
+///
+/// `TileWorldExt::tile_snapshot` takes one of every layer which has a name and has been
+/// added with `TileRegistry::register_json`. A snapshot serializes as a struct with the grid
+/// and a map from the layer names to lists of `[id, component]` pairs, so it can be stored in
+/// any format serde supports.
+///
+/// Loading it with `TileWorldExt::load_tile_snapshot` checks that the ids still mean the same
+/// tiles: if the world has a `Grid2d` resource of other dimensions, loading fails with
+/// `SnapshotError::DimensionMismatch` instead of scrambling the coordinates, unless it's
+/// asked to remap the ids. A world without a grid gets the one of the snapshot.
+///
+/// # Examples
+///
+/// ```
+/// #[macro_use]
+/// extern crate serde_derive;
+/// # extern crate serde_crate as serde;
+/// extern crate serde_json;
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::{Component, VecStorage, World, WorldExt};
+/// use specs_static::{
+///     Grid2d, Id, SnapshotError, SnapshotLoad, Storage, TileRegistry, TileSnapshot, TileWorldExt,
+/// };
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+/// # #[serde(crate = "serde")]
+/// struct Tree(u8);
+///
+/// impl Component for Tree {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// type Trees = Storage<Tree, VecStorage<Tree>, TileId>;
+///
+/// fn world(grid: Option<Grid2d>) -> World {
+///     let mut world = World::new();
+///     if let Some(grid) = grid {
+///         world.insert(grid);
+///     }
+///     world.register_tile_comp_named::<Tree, TileId>("trees").unwrap();
+///     world.write_resource::<TileRegistry<TileId>>().register_json::<Tree>();
+///
+///     world
+/// }
+///
+/// fn main() {
+///     let grid = Grid2d::new(256, 64);
+///     let mut saved = world(Some(grid));
+///     let oak: TileId = grid.id(10, 20).unwrap();
+///     saved.write_resource::<Trees>().insert(oak, Tree(3));
+///
+///     let json = serde_json::to_string(&saved.tile_snapshot::<TileId>().unwrap()).unwrap();
+///     let snapshot: TileSnapshot = serde_json::from_str(&json).unwrap();
+///     assert_eq!(snapshot.grid(), grid);
+///
+///     // A world with the same grid, or without any, loads the layers as they are.
+///     for target in vec![Some(grid), None] {
+///         let mut loaded = world(target);
+///         loaded.load_tile_snapshot::<TileId>(&snapshot, SnapshotLoad::Exact).unwrap();
+///         assert_eq!(*loaded.read_resource::<Grid2d>(), grid);
+///         assert_eq!(loaded.read_resource::<Trees>().get(oak), Some(&Tree(3)));
+///     }
+///
+///     // A wider world would move every tile of the second row and below.
+///     let wide = Grid2d::new(512, 64);
+///     let mut loaded = world(Some(wide));
+///     match loaded.load_tile_snapshot::<TileId>(&snapshot, SnapshotLoad::Exact) {
+///         Err(SnapshotError::DimensionMismatch { expected, found }) => {
+///             assert_eq!((expected, found), (wide, grid));
+///         }
+///         _ => panic!("loaded a 256 wide map into a 512 wide world"),
+///     }
+///     assert_eq!(loaded.read_resource::<Trees>().get(oak), None);
+///
+///     // Unless the ids are remapped through the coordinates.
+///     loaded.load_tile_snapshot::<TileId>(&snapshot, SnapshotLoad::Remap).unwrap();
+///     let trees = loaded.read_resource::<Trees>();
+///     assert_eq!(trees.get(wide.id(10, 20).unwrap()), Some(&Tree(3)));
+///     assert_eq!(trees.get(oak), None);
+///     assert_eq!(*loaded.read_resource::<Grid2d>(), wide);
+///
+///     // Remapping can't squeeze tiles into a smaller world.
+///     let mut small = world(Some(Grid2d::new(8, 64)));
+///     match small.load_tile_snapshot::<TileId>(&snapshot, SnapshotLoad::Remap) {
+///         Err(SnapshotError::OutsideGrid { ref layer, id }) => {
+///             assert_eq!((layer.as_str(), id), ("trees", oak.0));
+///         }
+///         _ => panic!("the tree doesn't fit"),
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct TileSnapshot {
+    grid: Grid2d,
+    layers: BTreeMap<String, Vec<(u32, Value)>>,
+}
+
+impl TileSnapshot {
+    /// Returns the grid the layers were placed on.
+    pub fn grid(&self) -> Grid2d {
+        self.grid
+    }
+
+    /// Returns the names of the layers, in alphabetical order.
+    pub fn layer_names<'a>(&'a self) -> impl Iterator<Item = &'a str> + 'a {
+        self.layers.keys().map(String::as_str)
+    }
+
+    /// Returns the ids and the components of the layer `name`, in ascending order of the ids.
+    pub fn layer(&self, name: &str) -> Option<&[(u32, Value)]> {
+        self.layers.get(name).map(Vec::as_slice)
+    }
+}
+
+impl Serialize for TileSnapshot {
+    fn serialize<Z>(&self, serializer: Z) -> Result<Z::Ok, Z::Error>
+    where
+        Z: Serializer,
+    {
+        let mut snapshot = serializer.serialize_struct("TileSnapshot", 2)?;
+        snapshot.serialize_field("grid", &self.grid)?;
+        snapshot.serialize_field("layers", &self.layers)?;
+
+        snapshot.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for TileSnapshot {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct SnapshotVisitor;
+
+        impl<'de> Visitor<'de> for SnapshotVisitor {
+            type Value = TileSnapshot;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a tile snapshot with a grid and layers")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<TileSnapshot, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let (mut grid, mut layers) = (None, None);
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "grid" if grid.is_none() => grid = Some(map.next_value()?),
+                        "layers" if layers.is_none() => layers = Some(map.next_value()?),
+                        "grid" | "layers" => {
+                            let error = format!("duplicate field `{}`", key);
+                            return Err(de::Error::custom(error));
+                        }
+                        _ => {
+                            map.next_value::<IgnoredAny>()?;
+                        }
+                    }
+                }
+
+                Ok(TileSnapshot {
+                    grid: grid.ok_or_else(|| de::Error::missing_field("grid"))?,
+                    layers: layers.ok_or_else(|| de::Error::missing_field("layers"))?,
+                })
+            }
+        }
+
+        deserializer.deserialize_struct("TileSnapshot", &["grid", "layers"], SnapshotVisitor)
+    }
+}
+
+/// How `TileWorldExt::load_tile_snapshot` treats a snapshot whose grid differs from the
+/// `Grid2d` resource of the world.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum SnapshotLoad {
+    /// Fails with `SnapshotError::DimensionMismatch`.
+    Exact,
+    /// Moves every tile to the id of its coordinates in the grid of the world, failing with
+    /// `SnapshotError::OutsideGrid` if a tile doesn't fit into it.
+    Remap,
+}
+
+/// The error returned by `TileWorldExt::tile_snapshot` and `TileWorldExt::load_tile_snapshot`.
+#[derive(Debug)]
+pub enum SnapshotError {
+    /// The world has no `Grid2d` resource to save with the layers.
+    NoGrid,
+    /// The grid of the snapshot doesn't match the `Grid2d` resource of the world.
+    DimensionMismatch {
+        /// The grid of the world.
+        expected: Grid2d,
+        /// The grid of the snapshot.
+        found: Grid2d,
+    },
+    /// A tile of the snapshot doesn't fit into the grid of the world when remapping, or
+    /// wasn't inside of the grid of the snapshot in the first place.
+    OutsideGrid {
+        /// The name of the layer.
+        layer: String,
+        /// The id of the tile in the snapshot.
+        id: u32,
+    },
+    /// A layer of the snapshot couldn't be loaded.
+    Layer(TileJsonError),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SnapshotError::NoGrid => write!(f, "the world has no grid to save with the layers"),
+            SnapshotError::DimensionMismatch { expected, found } => write!(
+                f,
+                "the snapshot was taken on a {}x{} grid, but the world's grid is {}x{}",
+                found.width(),
+                found.height(),
+                expected.width(),
+                expected.height()
+            ),
+            SnapshotError::OutsideGrid { ref layer, id } => write!(
+                f,
+                "tile {} of the layer {:?} doesn't fit into the world's grid",
+                id, layer
+            ),
+            SnapshotError::Layer(ref error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl Error for SnapshotError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            SnapshotError::Layer(ref error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<TileJsonError> for SnapshotError {
+    fn from(error: TileJsonError) -> Self {
+        SnapshotError::Layer(error)
+    }
+}
+
+pub(crate) fn save<I>(world: &World) -> Result<TileSnapshot, SnapshotError>
+where
+    I: Id,
+{
+    let grid = *world.try_fetch::<Grid2d>().ok_or(SnapshotError::NoGrid)?;
+    let names = world
+        .try_fetch::<TileRegistry<I>>()
+        .map_or_else(Vec::new, |registry| registry.layer_names());
+
+    let mut layers = BTreeMap::new();
+    for name in names {
+        let (entry, json) = match registry_json_entry::<I>(world, &name) {
+            Ok(found) => found,
+            Err(_) => continue,
+        };
+        let storage = match (entry.fetch)(world) {
+            Some(storage) => storage,
+            None => continue,
+        };
+        let tiles = storage
+            .mask()
+            .iter()
+            .filter_map(|id| Some((id, (json.get)(&*storage, id)?)))
+            .collect();
+        layers.insert(name, tiles);
+    }
+
+    Ok(TileSnapshot { grid, layers })
+}
+
+pub(crate) fn load<I>(
+    world: &mut World,
+    snapshot: &TileSnapshot,
+    load: SnapshotLoad,
+) -> Result<(), SnapshotError>
+where
+    I: Id,
+{
+    let found = snapshot.grid;
+    let target = world.try_fetch::<Grid2d>().map(|grid| *grid);
+    let remap = match (target, load) {
+        (Some(expected), SnapshotLoad::Exact) if expected != found => {
+            return Err(SnapshotError::DimensionMismatch { expected, found });
+        }
+        (Some(expected), SnapshotLoad::Remap) if expected != found => Some(expected),
+        _ => None,
+    };
+
+    // Missing layers and ids which don't fit are checked before anything changes. Components
+    // which can't be deserialized or exceed a bound only fail in `set` below, leaving the
+    // layers before them replaced, as `load_tile_snapshot` documents.
+    let mut layers = Vec::with_capacity(snapshot.layers.len());
+    for (name, tiles) in &snapshot.layers {
+        let (entry, json) = registry_json_entry::<I>(world, name)?;
+        if (entry.fetch)(world).is_none() {
+            return Err(TileJsonError::UnknownLayer { name: name.clone() }.into());
+        }
+        let ids = tiles
+            .iter()
+            .map(|&(id, _)| match remap {
+                Some(grid) => found
+                    .coords(I::from_u32(id))
+                    .and_then(|(x, y)| grid.id::<I>(x, y))
+                    .map(|id| id.id())
+                    .ok_or_else(|| SnapshotError::OutsideGrid {
+                        layer: name.clone(),
+                        id,
+                    }),
+                None => Ok(id),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        layers.push((name, entry, json, ids, tiles));
+    }
+
+    if target.is_none() {
+        world.insert(found);
+    }
+    for (name, entry, json, ids, tiles) in layers {
+        let mut storage = (entry.fetch_mut)(world).expect("checked above");
+        storage.clear();
+        for (id, (_, value)) in ids.into_iter().zip(tiles) {
+            (json.set)(&mut *storage, name, id, value)?;
+        }
+    }
+
+    Ok(())
+}
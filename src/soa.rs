@@ -0,0 +1,275 @@
+//! Struct-of-arrays storages, which keep every field of a component in its own column.
+
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+
+use hibitset::{BitSet, BitSetLike};
+use specs::join::Join;
+use specs::world::Index;
+
+use Id;
+
+/// One field of the components of a `SoaStorage`, with a slot for every id up to the highest
+/// one inserted so far.
+///
+/// A column dereferences to a slice indexed by the ids. Vacant slots hold the default value of
+/// the field, so passes over the whole column work for dense layers; `SoaStorage::mask` tells
+/// the occupied ones apart. The columns of a storage always have the same length, which is
+/// why a column can't be resized on its own.
+pub struct SoaColumn<T> {
+    values: Vec<T>,
+}
+
+impl<T> SoaColumn<T> {
+    /// Grows the column to `len` slots. Only meant for `SoaComponent` impls.
+    #[doc(hidden)]
+    pub fn __grow(&mut self, len: usize)
+    where
+        T: Default,
+    {
+        if len > self.values.len() {
+            self.values.resize_with(len, T::default);
+        }
+    }
+}
+
+impl<T> Clone for SoaColumn<T>
+where
+    T: Clone,
+{
+    fn clone(&self) -> Self {
+        SoaColumn {
+            values: self.values.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for SoaColumn<T>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.values.fmt(f)
+    }
+}
+
+impl<T> Default for SoaColumn<T> {
+    fn default() -> Self {
+        SoaColumn { values: Vec::new() }
+    }
+}
+
+impl<T> Deref for SoaColumn<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        &self.values
+    }
+}
+
+impl<T> DerefMut for SoaColumn<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        &mut self.values
+    }
+}
+
+/// Components which can be split into columns for a `SoaStorage`.
+///
+/// `soa_tile_component!` implements this for a struct, with a struct of one `SoaColumn` per
+/// field as the columns. The methods are called with indices below the length the columns
+/// have been grown to.
+pub trait SoaComponent: Sized {
+    /// The columns of the fields.
+    type Columns: Default;
+
+    /// Grows every column to `len` slots, filling the new ones with defaults.
+    fn grow(columns: &mut Self::Columns, len: usize);
+
+    /// Reads the fields at `index` into a component.
+    fn read(columns: &Self::Columns, index: usize) -> Self;
+
+    /// Writes the fields of `comp` to `index`, returning the values they replace.
+    fn replace(columns: &mut Self::Columns, index: usize, comp: Self) -> Self;
+
+    /// Takes the fields at `index`, leaving defaults behind.
+    fn take(columns: &mut Self::Columns, index: usize) -> Self;
+}
+
+/// A storage keeping the components of a layer as a struct of arrays, for the components
+/// declared with `soa_tile_component!`.
+///
+/// Systems which only look at some fields of a component, like a temperature diffusion pass
+/// reading nothing but the temperatures, can walk the column of those fields with
+/// `columns` / `columns_mut`. Those are plain slices which the compiler can vectorize, and
+/// the other fields aren't dragged through the cache. Whole components are assembled by value
+/// from their fields, by `get` and by joining the storage.
+///
+/// The references `UnprotectedStorage` hands out can't be made up from columns, so this isn't
+/// a backend for `Storage`, but a storage of its own like `DenseStorage`.
+///
+/// # Examples
+///
+/// ```
+/// #[macro_use]
+/// extern crate specs_static;
+/// extern crate specs;
+///
+/// use specs::Join;
+/// use specs_static::SoaStorage;
+///
+/// # define_id!(pub struct TileId;);
+/// soa_tile_component! {
+///     #[derive(Clone, Copy, Debug, PartialEq)]
+///     pub struct Climate {
+///         pub temp: f32,
+///         pub humidity: f32,
+///         pub wind: u8,
+///     }
+///     columns = ClimateColumns;
+/// }
+///
+/// fn climate(id: u32) -> Climate {
+///     Climate { temp: id as f32, humidity: 1.0 / (id + 1) as f32, wind: (id % 7) as u8 }
+/// }
+///
+/// fn main() {
+///     let mut climate_layer = SoaStorage::<Climate, TileId>::new();
+///     for id in 0..100 {
+///         assert_eq!(climate_layer.insert(TileId(id), climate(id)), None);
+///     }
+///     assert_eq!(climate_layer.insert(TileId(7), climate(8)), Some(climate(7)));
+///     assert_eq!(climate_layer.remove(TileId(8)), Some(climate(8)));
+///     assert_eq!(climate_layer.remove(TileId(8)), None);
+///
+///     // Every field ends up where it belongs.
+///     assert_eq!(climate_layer.get(TileId(7)), Some(climate(8)));
+///     assert_eq!(climate_layer.get(TileId(8)), None);
+///     for id in (0..100).filter(|&id| id != 7 && id != 8) {
+///         assert_eq!(climate_layer.get(TileId(id)), Some(climate(id)));
+///     }
+///     assert_eq!(climate_layer.join().count(), 99);
+///
+///     // A pass over the temperatures only, which leaves the other columns alone, with the
+///     // vacant tile at its default.
+///     for temp in climate_layer.columns_mut().temp.iter_mut() {
+///         *temp -= 1.0;
+///     }
+///     let columns = climate_layer.columns();
+///     assert_eq!(columns.temp[..4], [-1.0, 0.0, 1.0, 2.0]);
+///     assert_eq!((columns.temp[8], columns.humidity[8], columns.wind[8]), (-1.0, 0.0, 0));
+///     assert_eq!(climate_layer.get(TileId(9)).map(|c| (c.temp, c.wind)), Some((8.0, 2)));
+/// }
+/// ```
+pub struct SoaStorage<C: SoaComponent, I> {
+    mask: BitSet,
+    len: usize,
+    columns: C::Columns,
+    phantom: PhantomData<I>,
+}
+
+impl<C, I> SoaStorage<C, I>
+where
+    C: SoaComponent,
+    I: Id,
+{
+    /// Creates an empty storage.
+    pub fn new() -> Self {
+        SoaStorage {
+            mask: BitSet::new(),
+            len: 0,
+            columns: Default::default(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns the mask of all occupied ids.
+    pub fn mask(&self) -> &BitSet {
+        &self.mask
+    }
+
+    /// Returns `true` if there's a component at `id`.
+    pub fn contains(&self, id: I) -> bool {
+        self.mask.contains(id.id())
+    }
+
+    /// Returns the columns, with a slot for every id up to the highest one inserted.
+    pub fn columns(&self) -> &C::Columns {
+        &self.columns
+    }
+
+    /// Returns the columns mutably, see `columns`.
+    ///
+    /// Writing to a vacant slot doesn't insert a component there, and inserting one
+    /// overwrites it.
+    pub fn columns_mut(&mut self) -> &mut C::Columns {
+        &mut self.columns
+    }
+
+    /// Returns the component at `id`, assembled from its fields.
+    pub fn get(&self, id: I) -> Option<C> {
+        match self.mask.contains(id.id()) {
+            true => Some(C::read(&self.columns, id.id() as usize)),
+            false => None,
+        }
+    }
+
+    /// Inserts `comp` at `id`, returning the component it replaces.
+    pub fn insert(&mut self, id: I, comp: C) -> Option<C> {
+        let index = id.id() as usize;
+        if index >= self.len {
+            self.len = index + 1;
+            C::grow(&mut self.columns, self.len);
+        }
+        let old = C::replace(&mut self.columns, index, comp);
+
+        match self.mask.add(id.id()) {
+            true => Some(old),
+            false => None,
+        }
+    }
+
+    /// Removes the component at `id`, returning it.
+    pub fn remove(&mut self, id: I) -> Option<C> {
+        match self.mask.remove(id.id()) {
+            true => Some(C::take(&mut self.columns, id.id() as usize)),
+            false => None,
+        }
+    }
+
+    /// Removes all components, keeping the columns allocated.
+    pub fn clear(&mut self) {
+        for id in (&self.mask).iter() {
+            C::take(&mut self.columns, id as usize);
+        }
+        self.mask.clear();
+    }
+}
+
+impl<C, I> Default for SoaStorage<C, I>
+where
+    C: SoaComponent,
+    I: Id,
+{
+    fn default() -> Self {
+        SoaStorage::new()
+    }
+}
+
+/// Yields the components by value, assembled from their fields.
+impl<'a, C, I> Join for &'a SoaStorage<C, I>
+where
+    C: SoaComponent,
+{
+    type Type = C;
+    type Value = &'a C::Columns;
+    type Mask = &'a BitSet;
+
+    unsafe fn open(self) -> (Self::Mask, Self::Value) {
+        (&self.mask, &self.columns)
+    }
+
+    unsafe fn get(value: &mut Self::Value, id: Index) -> C {
+        C::read(value, id as usize)
+    }
+}
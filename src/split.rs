@@ -0,0 +1,429 @@
+//! Splitting a storage into disjoint chunks of ids which can be mutated at the same time.
+
+use std::marker::PhantomData;
+use std::mem::{self, MaybeUninit};
+use std::ptr;
+
+use hibitset::{BitSet, BitSetLike, BitSetOr};
+#[cfg(feature = "parallel")]
+use rayon::iter::{IndexedParallelIterator, ParallelIterator};
+#[cfg(feature = "parallel")]
+use rayon::slice::ParallelSliceMut;
+
+use bound;
+use profile::Counters;
+use {Id, OutsideRegion, SliceAccess, SliceStorage, Storage};
+
+/// Chunk lengths have to be a multiple of this, so every chunk owns whole words of the
+/// occupancy bits.
+const WORD_BITS: usize = 64;
+
+impl<C, I> Storage<C, SliceStorage<C>, I>
+where
+    I: Id,
+{
+    /// Splits the ids `0..len` into chunks of `chunk_len` consecutive ids, which can be
+    /// mutated independently, for example by different threads.
+    ///
+    /// The chunks are taken from the returned `ChunksMut` with `iter_mut`, or `par_iter_mut`
+    /// with the default `parallel` feature. Every chunk gets the slots of its ids and the
+    /// words of occupancy bits for them, split off with `slice::chunks_mut`, so the chunks
+    /// are disjoint by construction and need no locks, and adding and removing components
+    /// inside of a chunk doesn't touch anything the others see. The last chunk may be
+    /// shorter. Ids from `len` on aren't part of any chunk and keep their components.
+    ///
+    /// The changes are merged into the storage when the `ChunksMut` is dropped, which
+    /// rebuilds its bitset. Since what the chunks did isn't tracked tile by tile, every id
+    /// occupied before or after the split is stamped as modified if the storage has
+    /// `enable_epochs` enabled.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_len` isn't a positive multiple of 64, or if the storage has been
+    /// created with `with_bound` and `len` exceeds it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::Component;
+    /// use specs_static::{SliceStorage, Storage};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// #[derive(Debug, PartialEq)]
+    /// struct Tree(u32);
+    ///
+    /// impl Component for Tree {
+    ///     type Storage = SliceStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut trees = Storage::<Tree, SliceStorage<_>, TileId>::default();
+    ///     trees.insert(TileId(10), Tree(0));
+    ///     trees.insert(TileId(500), Tree(0));
+    ///
+    ///     {
+    ///         let mut chunks = trees.chunks_mut(300, 128);
+    ///         assert_eq!(chunks.len(), 3);
+    ///         for mut chunk in chunks.iter_mut() {
+    ///             let (start, end) = chunk.range();
+    ///             assert_eq!(end.0 - start.0, if start.0 == 256 { 44 } else { 128 });
+    ///
+    ///             chunk.insert(start, Tree(start.0)).unwrap();
+    ///             for (_, tree) in chunk.iter_mut() {
+    ///                 tree.0 += 1;
+    ///             }
+    ///             // Other chunks' ids are off limits.
+    ///             assert!(chunk.insert(TileId(start.0 + 128), Tree(0)).is_err());
+    ///         }
+    ///     }
+    ///
+    ///     let trees: Vec<_> = (0..600)
+    ///         .filter_map(|id| Some((id, trees.get(TileId(id))?.0)))
+    ///         .collect();
+    ///     assert_eq!(trees, vec![(0, 1), (10, 1), (128, 129), (256, 257), (500, 0)]);
+    /// }
+    /// ```
+    pub fn chunks_mut(&mut self, len: u32, chunk_len: u32) -> ChunksMut<'_, C, I> {
+        assert!(
+            chunk_len > 0 && (chunk_len as usize).is_multiple_of(WORD_BITS),
+            "the chunk length has to be a positive multiple of {}, but it's {}",
+            WORD_BITS,
+            chunk_len
+        );
+        assert!(
+            self.observers.is_empty(),
+            "can't split a storage with observers into chunks"
+        );
+        bound::expect_in_bounds(self.check_range(0, len));
+        self.data.grow(len as usize);
+
+        // While the chunks are out, the storage looks empty, so forgetting the `ChunksMut`
+        // leaks the components instead of leaving stale bits behind.
+        let old = mem::replace(&mut self.bitset, BitSet::new());
+        let mut words = vec![0u64; (len as usize).div_ceil(WORD_BITS)];
+        let covered = words.len() * WORD_BITS;
+        for id in (&old).iter() {
+            if id as usize >= covered {
+                break;
+            }
+            words[id as usize / WORD_BITS] |= 1 << (id as usize % WORD_BITS);
+        }
+
+        ChunksMut {
+            storage: self,
+            old,
+            words,
+            len,
+            chunk_len,
+        }
+    }
+}
+
+/// A storage split into chunks of ids by `Storage::chunks_mut`, which merges the changes of
+/// the chunks back into the storage when this is dropped.
+pub struct ChunksMut<'a, C: 'a, I: 'a> {
+    storage: &'a mut Storage<C, SliceStorage<C>, I>,
+    old: BitSet,
+    words: Vec<u64>,
+    len: u32,
+    chunk_len: u32,
+}
+
+impl<'a, C, I> ChunksMut<'a, C, I>
+where
+    I: Id,
+{
+    /// Returns the amount of chunks.
+    pub fn len(&self) -> usize {
+        (self.len as usize).div_ceil(self.chunk_len as usize)
+    }
+
+    /// Returns `true` if there are no chunks, which is the case if the split covers no ids.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the amount of ids per chunk.
+    pub fn chunk_len(&self) -> u32 {
+        self.chunk_len
+    }
+
+    /// Returns an iterator over all chunks, in the order of their ids.
+    ///
+    /// The chunks can be moved to other threads, for example with rayon's `par_bridge`.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = StorageChunkMut<'_, C, I>> {
+        let chunk_len = self.chunk_len as usize;
+        let (slots, words, counters) = self.parts();
+
+        slots
+            .chunks_mut(chunk_len)
+            .zip(words.chunks_mut(chunk_len / WORD_BITS))
+            .enumerate()
+            .map(move |(index, (slots, words))| {
+                StorageChunkMut::new(index, chunk_len, slots, words, counters)
+            })
+    }
+
+    fn parts(&mut self) -> (&mut [MaybeUninit<C>], &mut [u64], &Counters) {
+        // The slots outside of `0..len` belong to no chunk.
+        let slots = unsafe { &mut self.storage.data.slots_mut()[..self.len as usize] };
+
+        (slots, &mut self.words, &self.storage.counters)
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl<'a, C, I> ChunksMut<'a, C, I>
+where
+    C: Send + Sync,
+    I: Id,
+{
+    /// Returns a parallel iterator over all chunks.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate rayon;
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use rayon::iter::ParallelIterator;
+    /// use specs::Component;
+    /// use specs_static::{Grid2d, SliceStorage, Storage};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// #[derive(Debug, PartialEq)]
+    /// enum Terrain {
+    ///     Water,
+    ///     Sand(u32),
+    ///     Rock,
+    /// }
+    ///
+    /// impl Component for Terrain {
+    ///     type Storage = SliceStorage<Self>;
+    /// }
+    ///
+    /// /// What worldgen puts at `id`, which depends on the chunk it's in.
+    /// fn generate(chunk: u32, id: u32) -> Option<Terrain> {
+    ///     match chunk % 3 {
+    ///         0 => Some(Terrain::Water),
+    ///         1 if id % 2 == 0 => Some(Terrain::Sand(id)),
+    ///         1 => None,
+    ///         _ => Some(Terrain::Rock),
+    ///     }
+    /// }
+    ///
+    /// fn main() {
+    ///     let grid = Grid2d::new(256, 200);
+    ///     let mut terrain = Storage::<Terrain, SliceStorage<_>, TileId>::default();
+    ///     // Leftovers of an earlier generation, which the chunks remove.
+    ///     for id in (0..grid.len()).step_by(3) {
+    ///         terrain.insert(TileId(id), Terrain::Rock);
+    ///     }
+    ///
+    ///     terrain.chunks_mut(grid.len(), 1024).par_iter_mut().for_each(|mut chunk| {
+    ///         let (start, end) = chunk.range();
+    ///         let index = start.0 / 1024;
+    ///         for id in start.0..end.0 {
+    ///             match generate(index, id) {
+    ///                 Some(tile) => chunk.insert(TileId(id), tile).unwrap(),
+    ///                 None => chunk.remove(TileId(id)).unwrap(),
+    ///             };
+    ///         }
+    ///     });
+    ///
+    ///     for id in 0..grid.len() {
+    ///         assert_eq!(terrain.get(TileId(id)), generate(id / 1024, id).as_ref());
+    ///     }
+    ///     assert_eq!(terrain.par_ids().count(), 256 * 200 - 17 * 512);
+    /// }
+    /// ```
+    pub fn par_iter_mut(
+        &mut self,
+    ) -> impl IndexedParallelIterator<Item = StorageChunkMut<'_, C, I>> {
+        let chunk_len = self.chunk_len as usize;
+        let (slots, words, counters) = self.parts();
+
+        slots
+            .par_chunks_mut(chunk_len)
+            .zip(words.par_chunks_mut(chunk_len / WORD_BITS))
+            .enumerate()
+            .map(move |(index, (slots, words))| {
+                StorageChunkMut::new(index, chunk_len, slots, words, counters)
+            })
+    }
+}
+
+impl<'a, C, I> Drop for ChunksMut<'a, C, I> {
+    fn drop(&mut self) {
+        let covered = (self.words.len() * WORD_BITS) as u32;
+        let mut bitset = BitSet::with_capacity(self.len);
+        for (index, &word) in self.words.iter().enumerate() {
+            let mut word = word;
+            while word != 0 {
+                bitset.add((index * WORD_BITS) as u32 + word.trailing_zeros());
+                word &= word - 1;
+            }
+        }
+        for id in (&self.old).iter().filter(|&id| id >= covered) {
+            bitset.add(id);
+        }
+
+        if !(&self.old).iter().eq((&bitset).iter()) {
+            self.storage.epoch.bump();
+        }
+        self.storage.stamps.stamp_all(BitSetOr(&self.old, &bitset));
+        self.storage.bitset = bitset;
+    }
+}
+
+/// Mutable access to the ids of one chunk of a `ChunksMut`.
+///
+/// All methods take ids of the whole storage and return `OutsideRegion`, with the index of
+/// the chunk as the region, for ids of other chunks.
+pub struct StorageChunkMut<'a, C: 'a, I> {
+    index: u32,
+    start: u32,
+    slots: &'a mut [MaybeUninit<C>],
+    words: &'a mut [u64],
+    counters: &'a Counters,
+    phantom: PhantomData<I>,
+}
+
+impl<'a, C, I> StorageChunkMut<'a, C, I>
+where
+    I: Id,
+{
+    fn new(
+        index: usize,
+        chunk_len: usize,
+        slots: &'a mut [MaybeUninit<C>],
+        words: &'a mut [u64],
+        counters: &'a Counters,
+    ) -> Self {
+        StorageChunkMut {
+            index: index as u32,
+            start: (index * chunk_len) as u32,
+            slots,
+            words,
+            counters,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns the index of the chunk.
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Returns the range `(start, end)` of the ids of the chunk.
+    pub fn range(&self) -> (I, I) {
+        let end = self.start + self.slots.len() as u32;
+
+        (I::from_u32(self.start), I::from_u32(end))
+    }
+
+    fn offset(&self, id: I) -> Result<usize, OutsideRegion> {
+        match id.id().checked_sub(self.start) {
+            Some(offset) if (offset as usize) < self.slots.len() => Ok(offset as usize),
+            _ => Err(OutsideRegion {
+                id: id.id(),
+                region: self.index,
+            }),
+        }
+    }
+
+    fn contains(&self, offset: usize) -> bool {
+        self.words[offset / WORD_BITS] & (1 << (offset % WORD_BITS)) != 0
+    }
+
+    /// Returns the component of `id`.
+    pub fn get(&self, id: I) -> Result<Option<&C>, OutsideRegion> {
+        let offset = self.offset(id)?;
+        self.counters.get();
+
+        match self.contains(offset) {
+            true => Ok(Some(unsafe { &*self.slots[offset].as_ptr() })),
+            false => Ok(None),
+        }
+    }
+
+    /// Returns the component of `id` mutably.
+    pub fn get_mut(&mut self, id: I) -> Result<Option<&mut C>, OutsideRegion> {
+        let offset = self.offset(id)?;
+        self.counters.get_mut();
+
+        match self.contains(offset) {
+            true => Ok(Some(unsafe { &mut *self.slots[offset].as_mut_ptr() })),
+            false => Ok(None),
+        }
+    }
+
+    /// Inserts `comp` at `id`, returning the component it replaced.
+    pub fn insert(&mut self, id: I, comp: C) -> Result<Option<C>, OutsideRegion> {
+        let offset = self.offset(id)?;
+        self.counters.insert();
+
+        let slot = self.slots[offset].as_mut_ptr();
+        if self.contains(offset) {
+            return Ok(Some(mem::replace(unsafe { &mut *slot }, comp)));
+        }
+        unsafe {
+            ptr::write(slot, comp);
+        }
+        self.words[offset / WORD_BITS] |= 1 << (offset % WORD_BITS);
+
+        Ok(None)
+    }
+
+    /// Removes the component of `id`.
+    pub fn remove(&mut self, id: I) -> Result<Option<C>, OutsideRegion> {
+        let offset = self.offset(id)?;
+        self.counters.remove();
+
+        if !self.contains(offset) {
+            return Ok(None);
+        }
+        self.words[offset / WORD_BITS] &= !(1 << (offset % WORD_BITS));
+
+        Ok(Some(unsafe { ptr::read(self.slots[offset].as_ptr()) }))
+    }
+
+    /// Returns an iterator over the occupied ids of the chunk and their components, in
+    /// ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = (I, &C)> {
+        let (start, words) = (self.start, &*self.words);
+
+        occupied(words).map(move |offset| {
+            let comp = unsafe { &*self.slots[offset].as_ptr() };
+
+            (I::from_u32(start + offset as u32), comp)
+        })
+    }
+
+    /// Returns an iterator over the occupied ids of the chunk and mutable references to
+    /// their components, in ascending order.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (I, &mut C)> {
+        let (start, words) = (self.start, &*self.words);
+
+        self.slots
+            .iter_mut()
+            .enumerate()
+            .filter(move |&(offset, _)| words[offset / WORD_BITS] >> (offset % WORD_BITS) & 1 != 0)
+            .map(move |(offset, slot)| {
+                (I::from_u32(start + offset as u32), unsafe { &mut *slot.as_mut_ptr() })
+            })
+    }
+}
+
+/// Returns the offsets of the set bits of `words`, in ascending order.
+fn occupied(words: &[u64]) -> impl Iterator<Item = usize> + '_ {
+    words.iter().enumerate().flat_map(|(index, &word)| {
+        (0..WORD_BITS)
+            .filter(move |bit| word & (1 << bit) != 0)
+            .map(move |bit| index * WORD_BITS + bit)
+    })
+}
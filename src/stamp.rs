@@ -0,0 +1,277 @@
+//! Copying a small map, like a prefab structure, into a bigger one.
+
+use std::marker::PhantomData;
+use std::ops::Range;
+
+use specs::storage::UnprotectedStorage;
+use specs::{Component, World};
+
+use {Grid2d, Id, ReserveIds, Storage, TileWorldExt};
+
+type Layer = Box<dyn Fn(&mut World, &Grid2d, i32, i32, StampMode) + Send + Sync>;
+
+/// What `stamp` does with the destination tiles under vacant source tiles.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum StampMode {
+    /// Leaves the destination tiles alone, so only the occupied tiles are copied.
+    Overlay,
+    /// Removes the destination components, so the stamped rectangle matches the source.
+    Replace,
+}
+
+/// Copies the tiles of `src` into `dst`, with the tile `(0, 0)` of `src_grid` ending up at
+/// `(dst_x, dst_y)` of `dst_grid`.
+///
+/// The offset may be negative, and the tiles which end up outside the destination grid
+/// are skipped. `mode` decides what happens to destination tiles under vacant source
+/// tiles.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::{Component, VecStorage};
+/// use specs_static::{stamp, Grid2d, StampMode, Storage};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// #[derive(Clone, Copy, Debug, PartialEq)]
+/// enum Tile {
+///     Grass,
+///     Wall,
+///     Door,
+/// }
+///
+/// impl Component for Tile {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// type Tiles = Storage<Tile, VecStorage<Tile>, TileId>;
+///
+/// fn parse(grid: &Grid2d, rows: &[&str]) -> Tiles {
+///     let mut tiles = Tiles::default();
+///     for (y, row) in rows.iter().enumerate() {
+///         for (x, tile) in row.chars().enumerate() {
+///             let id = grid.id(x as u32, y as u32).unwrap();
+///             match tile {
+///                 ',' => tiles.insert(id, Tile::Grass),
+///                 '#' => tiles.insert(id, Tile::Wall),
+///                 '+' => tiles.insert(id, Tile::Door),
+///                 _ => None,
+///             };
+///         }
+///     }
+///     tiles
+/// }
+///
+/// fn render(grid: &Grid2d, tiles: &Tiles) -> Vec<String> {
+///     (0..grid.height())
+///         .map(|y| {
+///             (0..grid.width())
+///                 .map(|x| match tiles.get(grid.id(x, y).unwrap()) {
+///                     Some(Tile::Grass) => ',',
+///                     Some(Tile::Wall) => '#',
+///                     Some(Tile::Door) => '+',
+///                     None => ' ',
+///                 })
+///                 .collect()
+///         })
+///         .collect()
+/// }
+///
+/// fn main() {
+///     let hut_grid = Grid2d::new(3, 3);
+///     // The middle of the hut is vacant.
+///     let hut = parse(&hut_grid, &["###", "# #", "#+#"]);
+///     let map_grid = Grid2d::new(5, 4);
+///     let stamped = |x, y, mode| {
+///         let mut map = parse(&map_grid, &[",,,,,"; 4]);
+///         stamp(&hut, &hut_grid, &mut map, &map_grid, x, y, mode);
+///         render(&map_grid, &map)
+///     };
+///
+///     assert_eq!(
+///         stamped(1, 0, StampMode::Overlay),
+///         vec![",###,", ",#,#,", ",#+#,", ",,,,,"]
+///     );
+///     assert_eq!(
+///         stamped(1, 0, StampMode::Replace),
+///         vec![",###,", ",# #,", ",#+#,", ",,,,,"]
+///     );
+///
+///     // Clipped at the bottom right and at the top left.
+///     assert_eq!(
+///         stamped(3, 2, StampMode::Replace),
+///         vec![",,,,,", ",,,,,", ",,,##", ",,,# "]
+///     );
+///     assert_eq!(
+///         stamped(-1, -2, StampMode::Overlay),
+///         vec!["+#,,,", ",,,,,", ",,,,,", ",,,,,"]
+///     );
+///     // Entirely outside.
+///     assert_eq!(stamped(5, 0, StampMode::Replace), vec![",,,,,"; 4]);
+/// }
+/// ```
+pub fn stamp<C, D, D2, I>(
+    src: &Storage<C, D, I>,
+    src_grid: &Grid2d,
+    dst: &mut Storage<C, D2, I>,
+    dst_grid: &Grid2d,
+    dst_x: i32,
+    dst_y: i32,
+    mode: StampMode,
+) where
+    C: Clone,
+    D: UnprotectedStorage<C>,
+    D2: UnprotectedStorage<C>,
+    I: Id,
+{
+    let columns = clip(dst_x, src_grid.width(), dst_grid.width());
+
+    for y in clip(dst_y, src_grid.height(), dst_grid.height()) {
+        let to_y = (i64::from(dst_y) + i64::from(y)) as u32;
+
+        for x in columns.clone() {
+            let to_x = (i64::from(dst_x) + i64::from(x)) as u32;
+            let from = src_grid.id(x, y).expect("clipped to the source grid");
+            let to = dst_grid.id(to_x, to_y).expect("clipped to the destination grid");
+
+            match src.get(from) {
+                Some(comp) => {
+                    dst.insert(to, comp.clone());
+                }
+                None if mode == StampMode::Replace => {
+                    dst.remove(to);
+                }
+                None => {}
+            }
+        }
+    }
+}
+
+/// Returns the source coordinates which end up inside `0..dst_len` when shifted by
+/// `offset`.
+fn clip(offset: i32, src_len: u32, dst_len: u32) -> Range<u32> {
+    let offset = i64::from(offset);
+    let start = (-offset).max(0).min(i64::from(src_len));
+    let end = (i64::from(dst_len) - offset).max(start).min(i64::from(src_len));
+
+    start as u32..end as u32
+}
+
+/// Several layers of a small map, which `stamp` into the tile storages of a `World` at once.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::{Component, VecStorage, World, WorldExt};
+/// use specs_static::{Grid2d, Prefab, StampMode, Storage, TileWorldExt};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// #[derive(Clone, Debug, PartialEq)]
+/// struct Wall;
+///
+/// impl Component for Wall {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// #[derive(Clone, Debug, PartialEq)]
+/// struct Loot(u32);
+///
+/// impl Component for Loot {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// fn main() {
+///     let room_grid = Grid2d::new(2, 2);
+///     let mut walls = Storage::<Wall, VecStorage<_>, TileId>::default();
+///     walls.insert(TileId(0), Wall);
+///     walls.insert(TileId(1), Wall);
+///     let mut loot = Storage::<Loot, VecStorage<_>, TileId>::default();
+///     loot.insert(TileId(3), Loot(50));
+///
+///     let room = Prefab::new(room_grid).with_layer(walls).with_layer(loot);
+///
+///     let map_grid = Grid2d::new(8, 8);
+///     let mut world = World::new();
+///     world.register_tile_comp::<Wall, TileId>();
+///     world
+///         .write_resource::<Storage<Wall, VecStorage<_>, TileId>>()
+///         .insert(map_grid.id(5, 5).unwrap(), Wall);
+///
+///     room.stamp(&mut world, &map_grid, 4, 4, StampMode::Replace);
+///     room.stamp(&mut world, &map_grid, 0, 6, StampMode::Overlay);
+///
+///     let walls = world.read_resource::<Storage<Wall, VecStorage<_>, TileId>>();
+///     let loot = world.read_resource::<Storage<Loot, VecStorage<_>, TileId>>();
+///     let id = |x, y| map_grid.id::<TileId>(x, y).unwrap();
+///     assert_eq!(walls.get(id(4, 4)), Some(&Wall));
+///     assert_eq!(walls.get(id(5, 4)), Some(&Wall));
+///     assert_eq!(walls.get(id(5, 5)), None);
+///     assert_eq!(walls.get(id(1, 6)), Some(&Wall));
+///     assert_eq!(loot.get(id(5, 5)), Some(&Loot(50)));
+///     assert_eq!(loot.get(id(1, 7)), Some(&Loot(50)));
+/// }
+/// ```
+pub struct Prefab<I> {
+    grid: Grid2d,
+    layers: Vec<Layer>,
+    phantom: PhantomData<I>,
+}
+
+impl<I> Prefab<I>
+where
+    I: Id,
+{
+    /// Creates a prefab without any layers, whose tiles are addressed with `grid`.
+    pub fn new(grid: Grid2d) -> Self {
+        Prefab {
+            grid,
+            layers: Vec::new(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns the grid of the prefab.
+    pub fn grid(&self) -> Grid2d {
+        self.grid
+    }
+
+    /// Adds a layer, which is stamped into the `Storage<C, C::Storage, I>` resource.
+    pub fn with_layer<C>(mut self, storage: Storage<C, C::Storage, I>) -> Self
+    where
+        C: Component + Clone + Send + Sync,
+        C::Storage: Default + ReserveIds + Send + Sync,
+    {
+        let grid = self.grid;
+        self.layers.push(Box::new(move |world, dst_grid, dst_x, dst_y, mode| {
+            if !world.has_value::<Storage<C, C::Storage, I>>() {
+                world.register_tile_comp::<C, I>();
+            }
+            let mut dst = world.fetch_mut::<Storage<C, C::Storage, I>>();
+
+            stamp(&storage, &grid, &mut dst, dst_grid, dst_x, dst_y, mode);
+        }));
+
+        self
+    }
+
+    /// Stamps every layer into the storages of `world`, registering the ones which don't
+    /// exist yet. See `stamp` for the details.
+    pub fn stamp(
+        &self,
+        world: &mut World,
+        dst_grid: &Grid2d,
+        dst_x: i32,
+        dst_y: i32,
+        mode: StampMode,
+    ) {
+        for layer in &self.layers {
+            layer(world, dst_grid, dst_x, dst_y, mode);
+        }
+    }
+}
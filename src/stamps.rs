@@ -0,0 +1,251 @@
+//! Per-id modification epochs, for caches asking whether a tile changed since they looked.
+
+#[cfg(not(feature = "specs"))]
+use liballoc::vec::Vec;
+
+use {BitSetLike, Id, Index, RawStorage, Storage};
+
+/// The modification epoch of every slot of a storage, empty unless enabled.
+///
+/// `0` marks slots which have never been stamped, so the epochs skip it when wrapping.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Stamps {
+    enabled: bool,
+    current: u32,
+    slots: Vec<u32>,
+}
+
+impl Stamps {
+    pub(crate) fn stamp(&mut self, id: Index) {
+        if !self.enabled {
+            return;
+        }
+
+        if self.slots.len() <= id as usize {
+            self.slots.resize(id as usize + 1, 0);
+        }
+        self.slots[id as usize] = self.current;
+    }
+
+    pub(crate) fn stamp_all<B: BitSetLike>(&mut self, ids: B) {
+        if self.enabled {
+            for id in ids.iter() {
+                self.stamp(id);
+            }
+        }
+    }
+}
+
+impl<C, D, I> Storage<C, D, I>
+where
+    D: RawStorage<C>,
+    I: Id,
+{
+    /// Starts recording the epoch every id has last been modified in, stamping all occupied
+    /// ids with the current epoch. Does nothing if the epochs are already enabled.
+    ///
+    /// While enabled, the storage keeps a `u32` per slot up to the highest id stamped so far;
+    /// while disabled, it keeps nothing. `insert`, `get_mut`, `ProofsMut::get_proven_mut`,
+    /// `remove`, `clear`, `remove_mask` and `insert_mask_with` stamp the ids they touch with
+    /// `current_epoch`, removals included, so caches notice tiles which vanished. Other
+    /// mutable access, like mutable joins, doesn't; call `set_modified` for the ids changed
+    /// that way.
+    ///
+    /// A cache which has looked at an id during epoch `e` is stale once `changed_since(id,
+    /// e)` returns `true`, which is the case for modifications in later epochs only. So
+    /// `advance_epoch` should be called between reading for the cache and the next
+    /// modifications, for example once per frame after the systems maintaining caches ran.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, Join, VecStorage};
+    /// use specs_static::Storage;
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// struct Opacity(f32);
+    ///
+    /// impl Component for Opacity {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut opacity = Storage::<Opacity, VecStorage<_>, TileId>::default();
+    ///     opacity.insert(TileId(1), Opacity(0.5));
+    ///     assert_eq!(opacity.epoch_of(TileId(1)), None);
+    ///
+    ///     opacity.enable_epochs();
+    ///     let start = opacity.current_epoch();
+    ///     assert_eq!(opacity.epoch_of(TileId(1)), Some(start));
+    ///
+    ///     // Every mutation path stamps the id with the current epoch.
+    ///     let seen = start;
+    ///     let epoch = opacity.advance_epoch();
+    ///     assert!(!opacity.changed_since(TileId(1), seen));
+    ///     opacity.insert(TileId(2), Opacity(1.0));
+    ///     assert_eq!(opacity.epoch_of(TileId(2)), Some(epoch));
+    ///     assert!(opacity.changed_since(TileId(2), seen));
+    ///
+    ///     let epoch = opacity.advance_epoch();
+    ///     opacity.get_mut(TileId(1)).unwrap().0 = 0.75;
+    ///     assert_eq!(opacity.epoch_of(TileId(1)), Some(epoch));
+    ///     let epoch = opacity.advance_epoch();
+    ///     opacity.proofs_mut(|mut opacity| {
+    ///         let token = opacity.check(TileId(2)).unwrap();
+    ///         opacity.get_proven_mut(token).0 = 0.25;
+    ///     });
+    ///     assert_eq!(opacity.epoch_of(TileId(2)), Some(epoch));
+    ///
+    ///     // Mutable joins don't stamp, so they have to be marked.
+    ///     let epoch = opacity.advance_epoch();
+    ///     for opacity in (&mut opacity).join() {
+    ///         opacity.0 *= 0.5;
+    ///     }
+    ///     assert!(!opacity.changed_since(TileId(2), epoch - 1));
+    ///     assert!(opacity.set_modified(TileId(2)));
+    ///     assert!(opacity.changed_since(TileId(2), epoch - 1));
+    ///     assert!(!opacity.set_modified(TileId(7)));
+    ///
+    ///     // Removals count as changes, but vacant ids have no epoch.
+    ///     let epoch = opacity.advance_epoch();
+    ///     opacity.remove(TileId(1));
+    ///     assert_eq!(opacity.epoch_of(TileId(1)), None);
+    ///     assert!(opacity.changed_since(TileId(1), epoch - 1));
+    ///     assert!(!opacity.changed_since(TileId(500), start));
+    ///
+    ///     let epoch = opacity.advance_epoch();
+    ///     opacity.clear();
+    ///     assert!(opacity.changed_since(TileId(2), epoch - 1));
+    ///
+    ///     opacity.disable_epochs();
+    ///     assert_eq!(opacity.epoch_of(TileId(2)), None);
+    /// }
+    /// ```
+    pub fn enable_epochs(&mut self) {
+        if self.stamps.enabled {
+            return;
+        }
+
+        self.stamps.enabled = true;
+        if self.stamps.current == 0 {
+            self.stamps.current = 1;
+        }
+        self.stamps.stamp_all(&self.bitset);
+    }
+
+    /// Stops recording modification epochs and frees their memory.
+    pub fn disable_epochs(&mut self) {
+        self.stamps.enabled = false;
+        self.stamps.slots = Vec::new();
+    }
+
+    /// Returns `true` if modification epochs are recorded.
+    pub fn epochs_enabled(&self) -> bool {
+        self.stamps.enabled
+    }
+
+    /// Returns the epoch modifications are currently stamped with.
+    pub fn current_epoch(&self) -> u32 {
+        self.stamps.current
+    }
+
+    /// Starts a new epoch and returns it.
+    ///
+    /// After `u32::MAX`, the epoch wraps around to 1. `changed_since` compares epochs with
+    /// wrapping arithmetic, so its answers stay correct as long as the compared epochs are
+    /// less than `2^31` epochs apart; at an epoch per frame, that's over a year at 60 frames
+    /// per second. Caches older than that should be rebuilt.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, VecStorage};
+    /// use specs_static::Storage;
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// struct Light(u8);
+    ///
+    /// impl Component for Light {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut lights = Storage::<Light, VecStorage<_>, TileId>::default();
+    ///     lights.enable_epochs();
+    ///     lights.set_epoch(u32::MAX - 1);
+    ///     lights.insert(TileId(0), Light(1));
+    ///     let seen = lights.current_epoch();
+    ///
+    ///     assert_eq!(lights.advance_epoch(), u32::MAX);
+    ///     lights.insert(TileId(1), Light(2));
+    ///     assert_eq!(lights.advance_epoch(), 1);
+    ///     lights.insert(TileId(2), Light(3));
+    ///
+    ///     assert!(!lights.changed_since(TileId(0), seen));
+    ///     assert!(lights.changed_since(TileId(1), seen));
+    ///     assert!(lights.changed_since(TileId(2), seen));
+    ///     assert!(lights.changed_since(TileId(2), u32::MAX));
+    ///     assert!(!lights.changed_since(TileId(1), 1));
+    /// }
+    /// ```
+    pub fn advance_epoch(&mut self) -> u32 {
+        self.stamps.current = match self.stamps.current.wrapping_add(1) {
+            0 => 1,
+            next => next,
+        };
+
+        self.stamps.current
+    }
+
+    /// Sets the current epoch, for example to continue the epochs of a saved game.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `epoch` is 0, which marks ids which have never been modified.
+    pub fn set_epoch(&mut self, epoch: u32) {
+        assert!(epoch != 0, "epoch 0 is reserved for ids which have never been modified");
+
+        self.stamps.current = epoch;
+    }
+
+    /// Stamps the component of `id` with the current epoch, for modifications which didn't
+    /// go through `get_mut`. Returns `false` without stamping if `id` is vacant.
+    pub fn set_modified(&mut self, id: I) -> bool {
+        let occupied = self.bitset.contains(id.id());
+        if occupied {
+            self.stamps.stamp(id.id());
+        }
+
+        occupied
+    }
+
+    /// Returns the epoch the component of `id` has last been modified in, or `None` if
+    /// `id` is vacant or epochs are disabled.
+    pub fn epoch_of(&self, id: I) -> Option<u32> {
+        match self.stamps.enabled && self.bitset.contains(id.id()) {
+            true => self.stamps.slots.get(id.id() as usize).cloned(),
+            false => None,
+        }
+    }
+
+    /// Returns `true` if `id` has been modified, inserted or removed in an epoch after
+    /// `epoch`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if epochs are disabled.
+    pub fn changed_since(&self, id: I, epoch: u32) -> bool {
+        assert!(self.stamps.enabled, "modification epochs are disabled");
+
+        match self.stamps.slots.get(id.id() as usize) {
+            Some(&0) | None => false,
+            Some(&stamp) => (stamp.wrapping_sub(epoch) as i32) > 0,
+        }
+    }
+}
@@ -0,0 +1,484 @@
+//! Unbounded worlds whose chunks are loaded and unloaded at runtime.
+
+use std::any::{self, Any, TypeId};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::marker::PhantomData;
+
+use shred::{World, SystemData};
+use specs::Component;
+
+use mask::MAX_LEN;
+use {registry_entries, Id, WriteTiles};
+
+/// The coordinates of a chunk in an unbounded world.
+pub type ChunkCoord = (i32, i32);
+
+/// Refers to one residency of a chunk: it stops being current when the chunk is unloaded,
+/// even if the same chunk is loaded into the same id block again later.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct ChunkHandle {
+    coord: ChunkCoord,
+    slot: u32,
+    generation: u32,
+}
+
+impl ChunkHandle {
+    /// Returns the coordinates of the chunk.
+    pub fn coord(&self) -> ChunkCoord {
+        self.coord
+    }
+}
+
+/// The error returned when loading a chunk while every id block is in use.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct ChunkSlotsExhausted {
+    /// The most chunks which can be loaded at once.
+    pub max_chunks: u32,
+}
+
+impl fmt::Display for ChunkSlotsExhausted {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "all {} chunk slots are in use", self.max_chunks)
+    }
+}
+
+impl Error for ChunkSlotsExhausted {}
+
+#[derive(Clone, Copy)]
+struct Slot {
+    coord: Option<ChunkCoord>,
+    generation: u32,
+}
+
+/// Keeps the chunks of an unbounded world which are currently needed in a fixed id space.
+///
+/// Every resident chunk of `chunk_size` x `chunk_size` tiles occupies a block of consecutive
+/// ids, row by row. `ensure_loaded` assigns a free block and lets a generator fill the
+/// layers of the chunk, and `unload` removes the components of the chunk from every layer
+/// registered for `I` and frees its block for reuse.
+///
+/// Ids are reused after unloading, so code which keeps ids of streamed chunks around should
+/// keep the `ChunkHandle` as well, and check it with `is_current`. With a chunk size of 32,
+/// the blocks line up with the chunks of `ChunkedVecStorage`, so unloading a chunk also
+/// frees its memory.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::{Component, World, WorldExt};
+/// use specs_static::{ChunkStreamer, ChunkedVecStorage, Storage, TileWorldExt};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// #[derive(Clone, Debug, PartialEq)]
+/// struct Height(i32);
+///
+/// impl Component for Height {
+///     type Storage = ChunkedVecStorage<Self>;
+/// }
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Tree;
+///
+/// impl Component for Tree {
+///     type Storage = ChunkedVecStorage<Self>;
+/// }
+///
+/// type Heights = Storage<Height, ChunkedVecStorage<Height>, TileId>;
+///
+/// fn main() {
+///     let mut world = World::new();
+///     world.register_tile_comp::<Height, TileId>();
+///     world.register_tile_comp::<Tree, TileId>();
+///     let mut streamer = ChunkStreamer::<TileId>::new(32);
+///
+///     let generate = |access: &mut specs_static::ChunkAccess<TileId>| {
+///         let (cx, cy) = access.coord();
+///         access.fill(Height(cx * 10 + cy));
+///         if cx < 0 {
+///             access.insert(3, 4, Tree);
+///         }
+///     };
+///     let home = streamer.ensure_loaded(&world, (0, 0), generate).unwrap();
+///     let west = streamer.ensure_loaded(&world, (-1, 0), generate).unwrap();
+///     // Already resident chunks aren't generated again.
+///     assert_eq!(streamer.ensure_loaded(&world, (0, 0), |_| panic!()), Ok(home));
+///     assert_eq!(streamer.resident().collect::<Vec<_>>(), vec![(0, 0), (-1, 0)]);
+///
+///     // World coordinates left of 0 are in the western chunk.
+///     let tree = streamer.id_at(-29, 4).unwrap();
+///     assert_eq!(streamer.handle_of(tree), Some(west));
+///     assert_eq!(streamer.coords_of(tree), Some(((-1, 0), 3, 4)));
+///     assert_eq!(world.read_resource::<Heights>().get(tree), Some(&Height(-10)));
+///
+///     // Unloading hands out the components, for example to save them.
+///     let mut saved = streamer.unload(&world, (-1, 0)).unwrap();
+///     assert_eq!(saved.take::<Tree>(), Some(vec![(3, 4, Tree)]));
+///     assert_eq!(saved.take::<Height>().map(|heights| heights.len()), Some(1024));
+///     assert!(!streamer.is_current(west));
+///     assert_eq!(streamer.id_at(-29, 4), None);
+///     assert_eq!(world.read_resource::<Heights>().get(tree), None);
+///
+///     // The freed block is reused, but the old handle stays stale.
+///     let east = streamer.ensure_loaded(&world, (1, 0), generate).unwrap();
+///     assert_eq!(streamer.tile(east, 3, 4), Some(tree));
+///     assert_eq!(streamer.tile(west, 3, 4), None);
+///     assert_eq!(world.read_resource::<Heights>().get(tree), Some(&Height(10)));
+/// }
+/// ```
+pub struct ChunkStreamer<I> {
+    chunk_size: u32,
+    slots: Vec<Slot>,
+    free: Vec<u32>,
+    resident: HashMap<ChunkCoord, u32>,
+    phantom: PhantomData<I>,
+}
+
+impl<I> ChunkStreamer<I>
+where
+    I: Id,
+{
+    /// Creates a streamer for chunks of `chunk_size` x `chunk_size` tiles, none of which are
+    /// loaded.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is `0`, or if one chunk has more tiles than a storage can
+    /// hold ids for.
+    pub fn new(chunk_size: u32) -> Self {
+        assert!(chunk_size > 0, "the chunk size has to be at least 1");
+        assert!(
+            u64::from(chunk_size) * u64::from(chunk_size) <= MAX_LEN,
+            "chunks of {0}x{0} tiles exceed the maximum of {1} ids",
+            chunk_size,
+            MAX_LEN
+        );
+
+        ChunkStreamer {
+            chunk_size,
+            slots: Vec::new(),
+            free: Vec::new(),
+            resident: HashMap::new(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns the amount of tiles per side of a chunk.
+    pub fn chunk_size(&self) -> u32 {
+        self.chunk_size
+    }
+
+    /// Returns the most chunks which can be resident at once.
+    pub fn max_chunks(&self) -> u32 {
+        (MAX_LEN / u64::from(self.block_len())) as u32
+    }
+
+    /// Returns the handle of the chunk at `coord`, loading it first if it isn't resident.
+    ///
+    /// A chunk which isn't resident gets a free id block and `generator` is called to fill
+    /// its layers, which have to be registered with `TileWorldExt::register_tile_comp`.
+    pub fn ensure_loaded<F>(
+        &mut self,
+        world: &World,
+        coord: ChunkCoord,
+        generator: F,
+    ) -> Result<ChunkHandle, ChunkSlotsExhausted>
+    where
+        F: FnOnce(&mut ChunkAccess<I>),
+    {
+        if let Some(handle) = self.handle(coord) {
+            return Ok(handle);
+        }
+
+        let slot = match self.free.pop() {
+            Some(slot) => slot,
+            None if (self.slots.len() as u32) < self.max_chunks() => {
+                self.slots.push(Slot {
+                    coord: None,
+                    generation: 0,
+                });
+                self.slots.len() as u32 - 1
+            }
+            None => {
+                return Err(ChunkSlotsExhausted {
+                    max_chunks: self.max_chunks(),
+                })
+            }
+        };
+        self.slots[slot as usize].coord = Some(coord);
+        self.resident.insert(coord, slot);
+        let handle = self.handle(coord).expect("just inserted");
+
+        generator(&mut ChunkAccess {
+            res: world,
+            handle,
+            size: self.chunk_size,
+            start: slot * self.block_len(),
+            phantom: PhantomData,
+        });
+
+        Ok(handle)
+    }
+
+    /// Unloads the chunk at `coord`, removing its components from every layer registered for
+    /// `I` and freeing its id block. Returns `None` if the chunk isn't resident.
+    ///
+    /// # Panics
+    ///
+    /// Panics if one of the layers is currently borrowed.
+    pub fn unload(&mut self, world: &World, coord: ChunkCoord) -> Option<UnloadedChunk> {
+        let slot = self.resident.remove(&coord)?;
+        let info = &mut self.slots[slot as usize];
+        info.coord = None;
+        info.generation = info.generation.wrapping_add(1);
+        self.free.push(slot);
+
+        let start = slot * self.block_len();
+        let end = start + self.block_len();
+        let layers = registry_entries::<I>(world)
+            .into_iter()
+            .filter_map(|entry| {
+                let mut storage = (entry.fetch_mut)(world)?;
+
+                Some(UnloadedLayer {
+                    component_type: entry.component_type,
+                    type_name: entry.type_name,
+                    components: (entry.take_range)(&mut *storage, start, end),
+                })
+            })
+            .collect();
+
+        Some(UnloadedChunk {
+            coord,
+            chunk_size: self.chunk_size,
+            layers,
+        })
+    }
+
+    /// Returns `true` if the chunk at `coord` is resident.
+    pub fn is_loaded(&self, coord: ChunkCoord) -> bool {
+        self.resident.contains_key(&coord)
+    }
+
+    /// Returns the amount of resident chunks.
+    pub fn len(&self) -> usize {
+        self.resident.len()
+    }
+
+    /// Returns `true` if no chunk is resident.
+    pub fn is_empty(&self) -> bool {
+        self.resident.is_empty()
+    }
+
+    /// Yields the coordinates of all resident chunks, ordered by their id blocks.
+    pub fn resident<'a>(&'a self) -> impl Iterator<Item = ChunkCoord> + 'a {
+        self.slots.iter().filter_map(|slot| slot.coord)
+    }
+
+    /// Returns the handle of the chunk at `coord` if it's resident.
+    pub fn handle(&self, coord: ChunkCoord) -> Option<ChunkHandle> {
+        let &slot = self.resident.get(&coord)?;
+
+        Some(ChunkHandle {
+            coord,
+            slot,
+            generation: self.slots[slot as usize].generation,
+        })
+    }
+
+    /// Returns `true` if the chunk of `handle` hasn't been unloaded since `handle` has been
+    /// created.
+    pub fn is_current(&self, handle: ChunkHandle) -> bool {
+        match self.slots.get(handle.slot as usize) {
+            Some(slot) => slot.generation == handle.generation && slot.coord.is_some(),
+            None => false,
+        }
+    }
+
+    /// Returns the handle of the chunk `id` currently belongs to.
+    pub fn handle_of(&self, id: I) -> Option<ChunkHandle> {
+        let slot = id.id() / self.block_len();
+        let coord = self.slots.get(slot as usize)?.coord?;
+
+        self.handle(coord)
+    }
+
+    /// Returns the id of the tile `(x, y)` inside the chunk of `handle`, or `None` if the
+    /// handle isn't current or the coordinates are outside the chunk.
+    pub fn tile(&self, handle: ChunkHandle, x: u32, y: u32) -> Option<I> {
+        if !self.is_current(handle) || x >= self.chunk_size || y >= self.chunk_size {
+            return None;
+        }
+
+        Some(I::from_u32(
+            handle.slot * self.block_len() + y * self.chunk_size + x,
+        ))
+    }
+
+    /// Returns the id of the tile at the world coordinates `(x, y)`, or `None` if its chunk
+    /// isn't resident.
+    pub fn id_at(&self, x: i64, y: i64) -> Option<I> {
+        let size = i64::from(self.chunk_size);
+        let coord = (x.div_euclid(size) as i32, y.div_euclid(size) as i32);
+
+        self.tile(
+            self.handle(coord)?,
+            x.rem_euclid(size) as u32,
+            y.rem_euclid(size) as u32,
+        )
+    }
+
+    /// Returns the chunk `id` belongs to and the coordinates of the tile inside of it.
+    pub fn coords_of(&self, id: I) -> Option<(ChunkCoord, u32, u32)> {
+        let handle = self.handle_of(id)?;
+        let offset = id.id() % self.block_len();
+
+        Some((handle.coord, offset % self.chunk_size, offset / self.chunk_size))
+    }
+
+    fn block_len(&self) -> u32 {
+        self.chunk_size * self.chunk_size
+    }
+}
+
+/// Access to the layers of a chunk while it's generated, handed out by
+/// `ChunkStreamer::ensure_loaded`.
+///
+/// Coordinates are relative to the chunk.
+pub struct ChunkAccess<'a, I> {
+    res: &'a World,
+    handle: ChunkHandle,
+    size: u32,
+    start: u32,
+    phantom: PhantomData<I>,
+}
+
+impl<'a, I> ChunkAccess<'a, I>
+where
+    I: Id,
+{
+    /// Returns the coordinates of the chunk.
+    pub fn coord(&self) -> ChunkCoord {
+        self.handle.coord
+    }
+
+    /// Returns the handle of the chunk.
+    pub fn handle(&self) -> ChunkHandle {
+        self.handle
+    }
+
+    /// Returns the amount of tiles per side of the chunk.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Returns the ids `start..end` of the chunk, for bulk operations like `fill_range`.
+    pub fn range(&self) -> (I, I) {
+        (
+            I::from_u32(self.start),
+            I::from_u32(self.start + self.size * self.size),
+        )
+    }
+
+    /// Returns the id of the tile `(x, y)` of the chunk.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the coordinates are outside the chunk.
+    pub fn id(&self, x: u32, y: u32) -> I {
+        assert!(
+            x < self.size && y < self.size,
+            "({}, {}) is outside of the {2}x{2} chunk",
+            x,
+            y,
+            self.size
+        );
+
+        I::from_u32(self.start + y * self.size + x)
+    }
+
+    /// Fetches the layer of the components `C`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the layer hasn't been registered, or is currently borrowed.
+    pub fn storage<C>(&self) -> WriteTiles<'a, C, I>
+    where
+        C: Component + Send + Sync,
+    {
+        WriteTiles::fetch(self.res)
+    }
+
+    /// Inserts a clone of `comp` at every tile of the chunk.
+    pub fn fill<C>(&mut self, comp: C)
+    where
+        C: Component + Clone + Send + Sync,
+    {
+        let (start, end) = self.range();
+        self.storage::<C>().fill_range(start, end, comp);
+    }
+
+    /// Inserts `comp` at the tile `(x, y)` of the chunk.
+    pub fn insert<C>(&mut self, x: u32, y: u32, comp: C) -> Option<C>
+    where
+        C: Component + Send + Sync,
+    {
+        let id = self.id(x, y);
+
+        self.storage::<C>().insert(id, comp)
+    }
+}
+
+struct UnloadedLayer {
+    component_type: TypeId,
+    type_name: &'static str,
+    components: Box<dyn Any + Send>,
+}
+
+/// The components of a chunk removed by `ChunkStreamer::unload`, by layer.
+pub struct UnloadedChunk {
+    coord: ChunkCoord,
+    chunk_size: u32,
+    layers: Vec<UnloadedLayer>,
+}
+
+impl UnloadedChunk {
+    /// Returns the coordinates of the chunk.
+    pub fn coord(&self) -> ChunkCoord {
+        self.coord
+    }
+
+    /// Returns the names of the component types of the layers which haven't been taken yet.
+    pub fn layer_names<'a>(&'a self) -> impl Iterator<Item = &'static str> + 'a {
+        self.layers.iter().map(|layer| layer.type_name)
+    }
+
+    /// Takes the components `C` with the coordinates of their tiles inside the chunk, row
+    /// by row. Returns `None` if there is no such layer, or if it has been taken already.
+    pub fn take<C>(&mut self) -> Option<Vec<(u32, u32, C)>>
+    where
+        C: 'static,
+    {
+        let index = self
+            .layers
+            .iter()
+            .position(|layer| layer.component_type == TypeId::of::<C>())?;
+        let layer = self.layers.remove(index);
+        let components = match layer.components.downcast::<Vec<(u32, C)>>() {
+            Ok(components) => *components,
+            Err(_) => panic!("the layer of `{}` has the wrong type", any::type_name::<C>()),
+        };
+        let size = self.chunk_size;
+
+        Some(
+            components
+                .into_iter()
+                .map(|(offset, comp)| (offset % size, offset / size, comp))
+                .collect(),
+        )
+    }
+}
@@ -0,0 +1,108 @@
+//! Cross-checking ids against the map dimensions in debug builds.
+
+#[cfg(feature = "strict-ids")]
+use std::any;
+
+use {Id, Index, RawStorage, Storage};
+
+impl<C, D, I> Storage<C, D, I>
+where
+    D: RawStorage<C>,
+    I: Id,
+{
+    /// Attaches a debug bound to the storage, or removes it.
+    ///
+    /// With the `strict-ids` feature and debug assertions enabled, every `get`, `get_mut`,
+    /// `insert` and `remove`, as well as the bulk operations, then asserts that the ids it
+    /// receives are at most `max_id`. The panic message names the id, the bound and the
+    /// component type; bulk operations also name the index of the offending element. This is
+    /// meant to catch stray ids at the call site during development, for example by passing
+    /// the highest id of your `Grid2d`.
+    ///
+    /// In contrast to `with_bound`, the bound isn't enforced in release builds, and without
+    /// the `strict-ids` feature this does nothing at all.
+    ///
+    /// # Examples
+    ///
+    #[cfg_attr(feature = "strict-ids", doc = "```should_panic")]
+    #[cfg_attr(not(feature = "strict-ids"), doc = "```ignore")]
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, VecStorage};
+    /// use specs_static::{Grid2d, Id, Storage};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// #[derive(Clone)]
+    /// struct Moisture(f32);
+    ///
+    /// impl Component for Moisture {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let grid = Grid2d::new(16, 16);
+    ///     let mut moisture = Storage::<Moisture, VecStorage<_>, TileId>::default();
+    ///     moisture.set_debug_bound(Some(grid.len() - 1));
+    ///
+    ///     moisture.fill_range(TileId(0), TileId(grid.len()), Moisture(0.5));
+    ///     assert!(moisture.get(TileId(255)).is_some());
+    ///
+    ///     // Panics: element 4 of the batch has the id 256, which exceeds the debug bound 255.
+    ///     moisture.fill_range(TileId(252), TileId(260), Moisture(1.0));
+    /// }
+    /// ```
+    #[allow(unused_variables)]
+    pub fn set_debug_bound(&mut self, max_id: Option<u32>) {
+        #[cfg(feature = "strict-ids")]
+        {
+            self.debug_bound = max_id;
+        }
+    }
+
+    /// Asserts that `id` doesn't exceed the debug bound. `element` is the index of the id
+    /// within a bulk operation.
+    #[inline]
+    #[allow(unused_variables)]
+    pub(crate) fn debug_check_id(&self, id: Index, element: Option<usize>) {
+        #[cfg(feature = "strict-ids")]
+        {
+            if let Some(bound) = self.debug_bound {
+                match element {
+                    Some(element) => debug_assert!(
+                        id <= bound,
+                        "element {} of the batch has the id {}, which exceeds the debug bound {} \
+                         of the storage of `{}`",
+                        element,
+                        id,
+                        bound,
+                        any::type_name::<C>()
+                    ),
+                    None => debug_assert!(
+                        id <= bound,
+                        "id {} exceeds the debug bound {} of the storage of `{}`",
+                        id,
+                        bound,
+                        any::type_name::<C>()
+                    ),
+                }
+            }
+        }
+    }
+
+    /// Asserts that no id of `start..end` exceeds the debug bound.
+    #[cfg(feature = "specs")]
+    #[inline]
+    #[allow(unused_variables)]
+    pub(crate) fn debug_check_range(&self, start: Index, end: Index) {
+        #[cfg(feature = "strict-ids")]
+        {
+            if let Some(bound) = self.debug_bound {
+                if start < end && end - 1 > bound {
+                    let id = start.max(bound + 1);
+                    self.debug_check_id(id, Some((id - start) as usize));
+                }
+            }
+        }
+    }
+}
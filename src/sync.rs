@@ -0,0 +1,697 @@
+//! Sending the changed tiles of tracked layers over the network.
+
+use std::any;
+use std::error::Error;
+use std::fmt;
+use std::marker::PhantomData;
+
+use hibitset::{BitSet, BitSetLike};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use shred::{Accessor, AccessorCow, DynamicSystemData, ResourceId, World, System};
+use shrev::{EventChannel, ReaderId};
+use specs::storage::ComponentEvent;
+use specs::{Component, Tracked};
+
+use {registry_entries, Id, Storage, TileCodec};
+
+/// The changes of one layer in a `TileDelta`.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Deserialize, Serialize),
+    serde(crate = "serde")
+)]
+pub struct LayerDelta {
+    /// The type name of the component, as listed by `TileRegistry::names`.
+    pub layer: String,
+    /// The ids whose components have been removed.
+    pub removed: Vec<u32>,
+    /// The ids whose components have been inserted or changed, with their encoded new values.
+    pub changed: Vec<(u32, Vec<u8>)>,
+}
+
+impl LayerDelta {
+    fn new(layer: &str) -> Self {
+        LayerDelta {
+            layer: layer.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    /// Returns `true` if the layer didn't change.
+    pub fn is_empty(&self) -> bool {
+        self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// A message listing the changed tiles of several layers, which `TileSyncSystem` collects on
+/// the server and `apply_delta` applies on a client.
+///
+/// It only holds plain data and the components encoded with `TileCodec`, so it can be sent
+/// with any serialization format. With the `serde` feature, it implements `Serialize` and
+/// `Deserialize`.
+///
+/// # Examples
+///
+#[cfg_attr(feature = "serde", doc = "```")]
+#[cfg_attr(not(feature = "serde"), doc = "```ignore")]
+/// extern crate serde_json;
+/// extern crate specs_static;
+///
+/// use specs_static::{LayerDelta, TileDelta};
+///
+/// fn main() {
+///     let delta = TileDelta {
+///         layers: vec![LayerDelta {
+///             layer: "game::Height".to_owned(),
+///             removed: vec![3, 4],
+///             changed: vec![(7, vec![2, 0, 0, 0])],
+///         }],
+///     };
+///
+///     let message = serde_json::to_vec(&delta).unwrap();
+///     let received: TileDelta = serde_json::from_slice(&message).unwrap();
+///     assert_eq!(received, delta);
+/// }
+/// ```
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Deserialize, Serialize),
+    serde(crate = "serde")
+)]
+pub struct TileDelta {
+    /// The changed layers. Layers without changes are left out.
+    pub layers: Vec<LayerDelta>,
+}
+
+impl TileDelta {
+    /// Returns `true` if nothing changed.
+    pub fn is_empty(&self) -> bool {
+        self.layers.iter().all(LayerDelta::is_empty)
+    }
+
+    /// Returns the size of the delta's payload in bytes: the layer names, four bytes per id
+    /// and per length of an encoded component, and the encoded components.
+    pub fn payload_len(&self) -> usize {
+        self.layers
+            .iter()
+            .map(|layer| {
+                let changed: usize = layer.changed.iter().map(|(_, bytes)| 8 + bytes.len()).sum();
+                layer.layer.len() + 4 * layer.removed.len() + changed
+            })
+            .sum()
+    }
+}
+
+/// The error returned when `apply_delta` can't apply a `TileDelta`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum ApplyDeltaError {
+    /// There is no layer with the name in the `TileRegistry`, or its storage resource is
+    /// missing.
+    UnknownLayer {
+        /// The name of the layer.
+        layer: String,
+    },
+    /// The layer hasn't been added with `TileRegistry::register_codec`.
+    NoCodec {
+        /// The name of the layer.
+        layer: String,
+    },
+    /// The bytes aren't a valid encoding of a component.
+    Decode {
+        /// The name of the layer.
+        layer: String,
+        /// The id of the tile.
+        id: u32,
+    },
+}
+
+impl fmt::Display for ApplyDeltaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ApplyDeltaError::UnknownLayer { ref layer } => {
+                write!(f, "the layer `{}` isn't registered", layer)
+            }
+            ApplyDeltaError::NoCodec { ref layer } => {
+                write!(f, "the layer `{}` has no codec registered", layer)
+            }
+            ApplyDeltaError::Decode { ref layer, id } => {
+                write!(f, "the component of layer `{}` at {} can't be decoded", layer, id)
+            }
+        }
+    }
+}
+
+impl Error for ApplyDeltaError {}
+
+/// A synced layer of a `TileSyncSystem`.
+struct SyncLayer {
+    resource: ResourceId,
+    register_reader: fn(&mut World) -> ReaderId<ComponentEvent>,
+    reader: Option<ReaderId<ComponentEvent>>,
+    /// Drains the events of the layer, and encodes the final state of every changed tile.
+    collect: fn(&World, &mut ReaderId<ComponentEvent>) -> LayerDelta,
+}
+
+/// A system collecting the changes of tracked tile storages into `TileDelta` messages, which
+/// it writes into the `EventChannel<TileDelta>` resource for the network code to send.
+///
+/// Every run drains the change events of the layers added with `with_layer`; a tile which
+/// changed several times is only sent once, with its final state. Messages are split so
+/// their `payload_len` stays below the limit given with `with_max_payload`, unless a single
+/// tile exceeds it. Like `TileEventBridge`, the readers are registered in `System::setup`.
+///
+/// # Examples
+///
+/// ```
+/// extern crate shrev;
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use shrev::EventChannel;
+/// use specs::prelude::*;
+/// use specs_static::{apply_delta, Id, Storage, TileCodec, TileDelta, TileRegistry};
+/// use specs_static::{TileSyncSystem, TileWorldExt};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// #[derive(Debug, PartialEq)]
+/// struct Height(u16);
+///
+/// impl Component for Height {
+///     type Storage = FlaggedStorage<Self, VecStorage<Self>>;
+/// }
+///
+/// impl TileCodec for Height {
+///     fn encode(&self, out: &mut Vec<u8>) {
+///         self.0.encode(out);
+///     }
+///
+///     fn decode(bytes: &[u8]) -> Option<Self> {
+///         u16::decode(bytes).map(Height)
+///     }
+/// }
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Owner(String);
+///
+/// impl Component for Owner {
+///     type Storage = FlaggedStorage<Self, VecStorage<Self>>;
+/// }
+///
+/// impl TileCodec for Owner {
+///     fn encode(&self, out: &mut Vec<u8>) {
+///         self.0.encode(out);
+///     }
+///
+///     fn decode(bytes: &[u8]) -> Option<Self> {
+///         String::decode(bytes).map(Owner)
+///     }
+/// }
+///
+/// type Heights = Storage<Height, FlaggedStorage<Height, VecStorage<Height>>, TileId>;
+/// type Owners = Storage<Owner, FlaggedStorage<Owner, VecStorage<Owner>>, TileId>;
+///
+/// fn world() -> World {
+///     let mut world = World::new();
+///     world.register_tile_comp::<Height, TileId>();
+///     world.register_tile_comp::<Owner, TileId>();
+///     let mut registry = world.write_resource::<TileRegistry<TileId>>();
+///     registry.register_codec::<Height>();
+///     registry.register_codec::<Owner>();
+///     drop(registry);
+///     world
+/// }
+///
+/// fn snapshot(world: &World) -> Vec<(u32, Option<u16>, Option<String>)> {
+///     let heights = world.read_resource::<Heights>();
+///     let owners = world.read_resource::<Owners>();
+///     (0..100)
+///         .map(|id| {
+///             let height = heights.get(TileId(id)).map(|h| h.0);
+///             (id, height, owners.get(TileId(id)).map(|o| o.0.clone()))
+///         })
+///         .collect()
+/// }
+///
+/// fn main() {
+///     let mut server = world();
+///     let mut client = world();
+///     let sync = TileSyncSystem::<TileId>::new()
+///         .with_layer::<Height>()
+///         .with_layer::<Owner>()
+///         .with_max_payload(64);
+///     let mut dispatcher = DispatcherBuilder::new().with(sync, "tile_sync", &[]).build();
+///     dispatcher.setup(&mut server);
+///     let mut outgoing = server.write_resource::<EventChannel<TileDelta>>().register_reader();
+///
+///     let mut network_tick = |server: &mut World, client: &mut World| -> usize {
+///         dispatcher.dispatch(&server);
+///         let channel = server.read_resource::<EventChannel<TileDelta>>();
+///         let messages: Vec<TileDelta> = channel.read(&mut outgoing).cloned().collect();
+///         for message in &messages {
+///             assert!(message.payload_len() <= 64);
+///             apply_delta::<TileId>(client, message).unwrap();
+///         }
+///         messages.len()
+///     };
+///
+///     {
+///         let mut heights = server.write_resource::<Heights>();
+///         for id in 0..40 {
+///             heights.insert(TileId(id), Height(id as u16 * 3));
+///         }
+///         let mut owners = server.write_resource::<Owners>();
+///         owners.insert(TileId(5), Owner("red".to_owned()));
+///         owners.insert(TileId(6), Owner("blue".to_owned()));
+///     }
+///     // 40 heights of 10 bytes each don't fit into one message.
+///     assert!(network_tick(&mut server, &mut client) > 1);
+///     assert_eq!(snapshot(&client), snapshot(&server));
+///
+///     {
+///         let mut heights = server.write_resource::<Heights>();
+///         heights.remove(TileId(3));
+///         heights.get_mut(TileId(4)).unwrap().0 = 1_000;
+///         heights.insert(TileId(50), Height(7));
+///         heights.remove(TileId(50));
+///         server.write_resource::<Owners>().remove(TileId(5));
+///     }
+///     assert_eq!(network_tick(&mut server, &mut client), 1);
+///     assert_eq!(snapshot(&client), snapshot(&server));
+///
+///     // Nothing changed, nothing is sent.
+///     assert_eq!(network_tick(&mut server, &mut client), 0);
+/// }
+/// ```
+pub struct TileSyncSystem<I> {
+    layers: Vec<SyncLayer>,
+    max_payload: usize,
+    phantom: PhantomData<I>,
+}
+
+impl<I> Default for TileSyncSystem<I> {
+    fn default() -> Self {
+        TileSyncSystem {
+            layers: Vec::new(),
+            max_payload: 16 * 1024,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<I> TileSyncSystem<I>
+where
+    I: Id,
+{
+    /// Creates a system without any layers, splitting messages above 16 KiB of payload.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds the tracked storage resource `Storage<C, C::Storage, I>`.
+    pub fn with_layer<C>(mut self) -> Self
+    where
+        C: Component + TileCodec + Send + Sync,
+        C::Storage: Default + Tracked,
+    {
+        self.layers.push(SyncLayer {
+            resource: ResourceId::new::<Storage<C, C::Storage, I>>(),
+            register_reader: |res| {
+                res.entry::<Storage<C, C::Storage, I>>()
+                    .or_insert_with(Default::default)
+                    .channel_mut()
+                    .register_reader()
+            },
+            reader: None,
+            collect: |res, reader| {
+                let storage = res.fetch::<Storage<C, C::Storage, I>>();
+                let mut changed = BitSet::new();
+                for event in storage.channel().read(reader) {
+                    changed.add(match *event {
+                        ComponentEvent::Inserted(id)
+                        | ComponentEvent::Modified(id)
+                        | ComponentEvent::Removed(id) => id,
+                    });
+                }
+
+                let mut delta = LayerDelta::new(any::type_name::<C>());
+                for id in changed.iter() {
+                    match storage.get(I::from_u32(id)) {
+                        Some(comp) => {
+                            let mut bytes = Vec::new();
+                            comp.encode(&mut bytes);
+                            delta.changed.push((id, bytes));
+                        }
+                        None => delta.removed.push(id),
+                    }
+                }
+
+                delta
+            },
+        });
+
+        self
+    }
+
+    /// Splits the messages so their `payload_len` stays below `max_payload` bytes.
+    pub fn with_max_payload(mut self, max_payload: usize) -> Self {
+        self.max_payload = max_payload;
+
+        self
+    }
+
+    /// Splits the changes into messages with at most `max_payload` bytes of payload.
+    fn split(&self, layers: Vec<LayerDelta>) -> Vec<TileDelta> {
+        let mut messages = Vec::new();
+        let mut message = TileDelta::default();
+        let mut len = 0;
+
+        for layer in layers {
+            let mut part = LayerDelta::new(&layer.layer);
+            let removed = layer.removed.into_iter().map(|id| (4, Err(id)));
+            let changed = layer
+                .changed
+                .into_iter()
+                .map(|(id, bytes)| (8 + bytes.len(), Ok((id, bytes))));
+
+            for (entry_len, entry) in removed.chain(changed) {
+                let name_len = if part.is_empty() { part.layer.len() } else { 0 };
+                if len > 0 && len + name_len + entry_len > self.max_payload {
+                    if !part.is_empty() {
+                        let rest = LayerDelta::new(&part.layer);
+                        message.layers.push(::std::mem::replace(&mut part, rest));
+                    }
+                    messages.push(::std::mem::take(&mut message));
+                    len = 0;
+                }
+
+                len += if part.is_empty() { part.layer.len() } else { 0 } + entry_len;
+                match entry {
+                    Ok(changed) => part.changed.push(changed),
+                    Err(removed) => part.removed.push(removed),
+                }
+            }
+
+            if !part.is_empty() {
+                message.layers.push(part);
+            }
+        }
+
+        if !message.is_empty() {
+            messages.push(message);
+        }
+
+        messages
+    }
+}
+
+/// The accessor of `TileSyncSystem`, reading the synced layers.
+pub struct TileSyncAccessor {
+    reads: Vec<ResourceId>,
+}
+
+impl Accessor for TileSyncAccessor {
+    fn try_new() -> Option<Self> {
+        None
+    }
+
+    fn reads(&self) -> Vec<ResourceId> {
+        self.reads.clone()
+    }
+
+    fn writes(&self) -> Vec<ResourceId> {
+        vec![ResourceId::new::<EventChannel<TileDelta>>()]
+    }
+}
+
+/// The resources of `TileSyncSystem`, which are only known at runtime.
+pub struct TileSyncData<'a> {
+    res: &'a World,
+}
+
+impl<'a> DynamicSystemData<'a> for TileSyncData<'a> {
+    type Accessor = TileSyncAccessor;
+
+    fn setup(_: &TileSyncAccessor, res: &mut World) {
+        res.entry::<EventChannel<TileDelta>>()
+            .or_insert_with(Default::default);
+    }
+
+    fn fetch(_: &TileSyncAccessor, res: &'a World) -> Self {
+        TileSyncData { res }
+    }
+}
+
+impl<'a, I> System<'a> for TileSyncSystem<I>
+where
+    I: Id,
+{
+    type SystemData = TileSyncData<'a>;
+
+    fn run(&mut self, data: TileSyncData<'a>) {
+        let mut layers = Vec::with_capacity(self.layers.len());
+        for layer in &mut self.layers {
+            let reader = layer
+                .reader
+                .as_mut()
+                .expect("`TileSyncSystem` has to be set up before it runs");
+            let delta = (layer.collect)(data.res, reader);
+            if !delta.is_empty() {
+                layers.push(delta);
+            }
+        }
+
+        let mut messages = self.split(layers);
+        data.res
+            .fetch_mut::<EventChannel<TileDelta>>()
+            .drain_vec_write(&mut messages);
+    }
+
+    fn accessor<'b>(&'b self) -> AccessorCow<'a, 'b, Self> {
+        AccessorCow::Owned(TileSyncAccessor {
+            reads: self.layers.iter().map(|layer| layer.resource.clone()).collect(),
+        })
+    }
+
+    fn setup(&mut self, res: &mut World) {
+        <TileSyncData as DynamicSystemData>::setup(&self.accessor(), res);
+        for layer in &mut self.layers {
+            layer.reader = Some((layer.register_reader)(res));
+        }
+    }
+}
+
+/// The tiles of one layer whose local values were overridden by `apply_delta_report`.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct LayerConflicts {
+    /// The type name of the component, as listed by `TileRegistry::names`.
+    pub layer: String,
+    /// The ids whose local component differed from the incoming one, including tiles that
+    /// were vacant locally or removed by the delta, in the order of the delta.
+    pub ids: Vec<u32>,
+}
+
+/// The tiles `apply_delta_report` changed, so predicted tiles which the server overrode
+/// can be corrected.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct ApplyReport {
+    /// The layers with overridden tiles. Layers without conflicts are left out.
+    pub conflicts: Vec<LayerConflicts>,
+    /// The layers of the delta which were applied without comparing, because they haven't
+    /// been added with `TileRegistry::register_codec_eq`.
+    pub unreported: Vec<String>,
+}
+
+impl ApplyReport {
+    /// Returns `true` if no local value was overridden in the reported layers.
+    pub fn is_empty(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+
+    /// Returns the overridden ids of `layer`.
+    pub fn conflicts(&self, layer: &str) -> &[u32] {
+        self.conflicts
+            .iter()
+            .find(|conflicts| conflicts.layer == layer)
+            .map_or(&[], |conflicts| &conflicts.ids)
+    }
+}
+
+/// Applies a `TileDelta` to the storages of `world`, which are looked up through its
+/// `TileRegistry<I>`. Every layer of the delta has to be added with
+/// `TileRegistry::register_codec`.
+///
+/// The layers are checked before anything is applied, so a delta naming an unknown layer
+/// doesn't change the world; a component which can't be decoded stops the application with
+/// the changes before it applied. See `TileSyncSystem` for an example.
+pub fn apply_delta<I>(world: &mut World, delta: &TileDelta) -> Result<(), ApplyDeltaError>
+where
+    I: Id,
+{
+    apply::<I>(world, delta, None)
+}
+
+/// Applies a `TileDelta` like `apply_delta`, and reports which tiles had a local value
+/// differing from the incoming one. The incoming values always win.
+///
+/// Only layers added with `TileRegistry::register_codec_eq` can be compared; the others are
+/// applied as well, but listed in `ApplyReport::unreported`. Comparing decodes every
+/// component once, like `apply_delta` does, and the report only allocates for conflicts.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use std::any;
+///
+/// use specs::{Component, VecStorage, World, WorldExt};
+/// use specs_static::{apply_delta_report, Id, LayerDelta, Storage, TileCodec, TileDelta};
+/// use specs_static::{TileRegistry, TileWorldExt};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// #[derive(Debug, PartialEq)]
+/// struct Wall(u8);
+///
+/// impl Component for Wall {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// impl TileCodec for Wall {
+///     fn encode(&self, out: &mut Vec<u8>) {
+///         self.0.encode(out);
+///     }
+///
+///     fn decode(bytes: &[u8]) -> Option<Self> {
+///         u8::decode(bytes).map(Wall)
+///     }
+/// }
+///
+/// type Walls = Storage<Wall, VecStorage<Wall>, TileId>;
+///
+/// fn main() {
+///     let mut client = World::new();
+///     client.register_tile_comp::<Wall, TileId>();
+///     client.write_resource::<TileRegistry<TileId>>().register_codec_eq::<Wall>();
+///     assert!(client.read_resource::<TileRegistry<TileId>>().reports_conflicts::<Wall>());
+///
+///     // The client predicted walls at 1, 2 and 3; the server agrees on 1 only.
+///     {
+///         let mut walls = client.write_resource::<Walls>();
+///         for id in 1..4 {
+///             walls.insert(TileId(id), Wall(id as u8));
+///         }
+///     }
+///
+///     let name = any::type_name::<Wall>();
+///     let delta = TileDelta {
+///         layers: vec![LayerDelta {
+///             layer: name.to_owned(),
+///             removed: vec![3, 5],
+///             changed: vec![(1, vec![1]), (2, vec![20]), (4, vec![4])],
+///         }],
+///     };
+///     let report = apply_delta_report::<TileId>(&mut client, &delta).unwrap();
+///     // 2 had another value, 3 was removed and 4 was missing; 5 was vacant already.
+///     assert_eq!(report.conflicts(name), &[2, 4, 3]);
+///     assert!(report.unreported.is_empty());
+///
+///     {
+///         let walls = client.read_resource::<Walls>();
+///         let values: Vec<_> = (0..6).map(|id| walls.get(TileId(id)).map(|w| w.0)).collect();
+///         assert_eq!(values, vec![None, Some(1), Some(20), None, Some(4), None]);
+///     }
+///
+///     // Applying it again doesn't conflict.
+///     assert!(apply_delta_report::<TileId>(&mut client, &delta).unwrap().is_empty());
+/// }
+/// ```
+pub fn apply_delta_report<I>(
+    world: &mut World,
+    delta: &TileDelta,
+) -> Result<ApplyReport, ApplyDeltaError>
+where
+    I: Id,
+{
+    let mut report = ApplyReport::default();
+    apply::<I>(world, delta, Some(&mut report))?;
+
+    Ok(report)
+}
+
+fn apply<I>(
+    world: &mut World,
+    delta: &TileDelta,
+    mut report: Option<&mut ApplyReport>,
+) -> Result<(), ApplyDeltaError>
+where
+    I: Id,
+{
+    let entries = registry_entries::<I>(world);
+    let mut layers = Vec::with_capacity(delta.layers.len());
+    for layer in &delta.layers {
+        let entry = entries
+            .iter()
+            .find(|entry| entry.type_name == layer.layer)
+            .ok_or_else(|| ApplyDeltaError::UnknownLayer {
+                layer: layer.layer.clone(),
+            })?;
+        let codec = entry.codec.ok_or_else(|| ApplyDeltaError::NoCodec {
+            layer: layer.layer.clone(),
+        })?;
+        layers.push((layer, entry.fetch_mut, codec));
+    }
+
+    for (layer, fetch_mut, codec) in layers {
+        let mut storage = fetch_mut(world).ok_or_else(|| ApplyDeltaError::UnknownLayer {
+            layer: layer.layer.clone(),
+        })?;
+        let decode_error = |id| ApplyDeltaError::Decode {
+            layer: layer.layer.clone(),
+            id,
+        };
+
+        let decode_replace = match (report.as_mut(), codec.decode_replace) {
+            (Some(_), Some(decode_replace)) => Some(decode_replace),
+            (Some(report), None) => {
+                report.unreported.push(layer.layer.clone());
+                None
+            }
+            (None, _) => None,
+        };
+        let decode_replace = match decode_replace {
+            Some(decode_replace) => decode_replace,
+            None => {
+                for &id in &layer.removed {
+                    storage.remove(id);
+                }
+                for &(id, ref bytes) in &layer.changed {
+                    if !(codec.decode_insert)(&mut *storage, id, bytes) {
+                        return Err(decode_error(id));
+                    }
+                }
+                continue;
+            }
+        };
+
+        let mut ids = Vec::new();
+        for &(id, ref bytes) in &layer.changed {
+            if decode_replace(&mut *storage, id, bytes).ok_or_else(|| decode_error(id))? {
+                ids.push(id);
+            }
+        }
+        ids.extend(layer.removed.iter().cloned().filter(|&id| storage.remove(id)));
+        if !ids.is_empty() {
+            let report = report.as_mut().expect("only compared with a report");
+            report.conflicts.push(LayerConflicts {
+                layer: layer.layer.clone(),
+                ids,
+            });
+        }
+    }
+
+    Ok(())
+}
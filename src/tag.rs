@@ -0,0 +1,315 @@
+//! Storages for marker components which only consist of the occupancy bitset.
+
+use std::marker::PhantomData;
+
+use hibitset::{AtomicBitSet, BitSet, BitSetAnd, BitSetLike};
+#[cfg(feature = "parallel")]
+use specs::ParJoin;
+use specs::Join;
+
+use bound;
+use {Id, Index};
+
+/// A storage for the zero-sized marker component `T`, which is nothing but a `BitSet`.
+///
+/// There's no backend to go through, so inserting and removing only flips a bit, and
+/// joining over it only intersects the mask. `T` is just used to tell apart the resources of
+/// different tags, so it doesn't have to be a `Component`.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::{Component, Join, VecStorage};
+/// use specs_static::{Storage, TagStorage};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// struct Explored;
+///
+/// struct Blocked;
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Treasure(u32);
+///
+/// impl Component for Treasure {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// fn main() {
+///     let mut explored = TagStorage::<Explored, TileId>::new();
+///     let mut blocked = TagStorage::<Blocked, TileId>::new();
+///     for id in 0..50 {
+///         explored.insert(TileId(id));
+///     }
+///     assert!(!explored.insert(TileId(10)));
+///     blocked.insert(TileId(20));
+///
+///     let mut treasures = Storage::<Treasure, VecStorage<_>, TileId>::default();
+///     treasures.insert(TileId(20), Treasure(1));
+///     treasures.insert(TileId(30), Treasure(2));
+///     treasures.insert(TileId(60), Treasure(3));
+///
+///     let visible: Vec<_> = (&explored, !blocked.mask(), &treasures)
+///         .join()
+///         .map(|(_, _, treasure)| treasure)
+///         .collect();
+///     assert_eq!(visible, vec![&Treasure(2)]);
+///
+///     explored.remove_mask(blocked.mask());
+///     assert!(!explored.contains(TileId(20)));
+///     assert_eq!(explored.len(), 49);
+/// }
+/// ```
+#[derive(Derivative)]
+#[derivative(Clone(bound = ""), Debug(bound = ""), Default(bound = ""))]
+pub struct TagStorage<T, I> {
+    bitset: BitSet,
+    #[derivative(Debug = "ignore")]
+    phantom: PhantomData<(T, I)>,
+}
+
+impl<T, I> TagStorage<T, I>
+where
+    I: Id,
+{
+    /// Creates an empty storage.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Creates a storage tagging all ids of `mask`.
+    pub fn from_mask(mask: BitSet) -> Self {
+        TagStorage {
+            bitset: mask,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Tags `id`, returning `true` if it wasn't tagged before.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` exceeds `Storage::MAX_ID`. The bitset would take `MAX_ID + 1`, but
+    /// couldn't iterate over it, so joins wouldn't see the tag.
+    ///
+    /// ```should_panic
+    /// # extern crate specs_static;
+    /// # use specs_static::TagStorage;
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// # struct Burning;
+    /// # fn main() {
+    /// let mut burning = TagStorage::<Burning, TileId>::new();
+    /// // Panics: id 16777216 exceeds the bound 16777215 of the storage.
+    /// burning.insert(TileId(1 << 24));
+    /// # }
+    /// ```
+    pub fn insert(&mut self, id: I) -> bool {
+        bound::expect_max_id(id.id());
+        !self.bitset.add(id.id())
+    }
+
+    /// Removes the tag of `id`, returning `true` if it was tagged.
+    pub fn remove(&mut self, id: I) -> bool {
+        self.bitset.remove(id.id())
+    }
+
+    /// Returns `true` if `id` is tagged.
+    pub fn contains(&self, id: I) -> bool {
+        self.bitset.contains(id.id())
+    }
+
+    /// Tags all ids of `mask`, word by word.
+    pub fn insert_mask<B>(&mut self, mask: B)
+    where
+        B: BitSetLike,
+    {
+        self.bitset |= &mask;
+    }
+
+    /// Removes the tags of all ids in `mask`, word by word.
+    pub fn remove_mask<B>(&mut self, mask: B)
+    where
+        B: BitSetLike,
+    {
+        let mut removed = BitSet::new();
+        removed |= &BitSetAnd(mask, &self.bitset);
+        self.bitset ^= &removed;
+    }
+
+    /// Removes all tags.
+    pub fn clear(&mut self) {
+        self.bitset.clear();
+    }
+
+    /// Returns the amount of tagged ids.
+    ///
+    /// This has to count them, so it takes time proportional to the amount of tagged ids.
+    pub fn len(&self) -> usize {
+        (&self.bitset).iter().count()
+    }
+
+    /// Returns `true` if no id is tagged.
+    pub fn is_empty(&self) -> bool {
+        self.bitset.is_empty()
+    }
+
+    /// Returns the mask of all tagged ids.
+    pub fn mask(&self) -> &BitSet {
+        &self.bitset
+    }
+
+    /// Returns the mask of all tagged ids mutably.
+    pub fn mask_mut(&mut self) -> &mut BitSet {
+        &mut self.bitset
+    }
+}
+
+impl<'a, T, I> Join for &'a TagStorage<T, I> {
+    type Type = ();
+    type Value = ();
+    type Mask = &'a BitSet;
+
+    unsafe fn open(self) -> (Self::Mask, Self::Value) {
+        (&self.bitset, ())
+    }
+
+    unsafe fn get(_: &mut Self::Value, _: Index) {}
+}
+
+#[cfg(feature = "parallel")]
+unsafe impl<T, I> ParJoin for &TagStorage<T, I> where T: Send + Sync, I: Send + Sync {}
+
+/// A storage for the marker `T` which can be tagged through a shared reference, backed by an
+/// `AtomicBitSet`.
+///
+/// Since `add` only needs `&self`, any number of systems fetching this resource with `Read`
+/// can flag tiles in parallel. Removing tags still requires `&mut self`.
+///
+/// Bits are set with relaxed atomic operations, so while a dispatch is running, other
+/// systems may or may not observe a flag which is being set concurrently. Once the dispatch
+/// is finished, all flags set during it are reliably visible.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::prelude::*;
+/// use specs_static::AtomicTagStorage;
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// struct Dirty;
+///
+/// struct Flag(u32, u32);
+///
+/// impl<'a> System<'a> for Flag {
+///     type SystemData = Read<'a, AtomicTagStorage<Dirty, TileId>>;
+///
+///     fn run(&mut self, dirty: Self::SystemData) {
+///         for id in self.0..self.1 {
+///             dirty.add(TileId(id));
+///         }
+///     }
+/// }
+///
+/// fn main() {
+///     let mut world = World::new();
+///     world.insert(AtomicTagStorage::<Dirty, TileId>::new());
+///
+///     let mut dispatcher = DispatcherBuilder::new()
+///         .with(Flag(0, 50_000), "a", &[])
+///         .with(Flag(50_000, 100_000), "b", &[])
+///         .with(Flag(25_000, 75_000), "c", &[])
+///         .with(Flag(99_000, 200_000), "d", &[])
+///         .build();
+///     dispatcher.dispatch(&world);
+///
+///     let mut dirty = world.write_resource::<AtomicTagStorage<Dirty, TileId>>();
+///     assert_eq!((&*dirty).join().count(), 200_000);
+///     assert!((0..200_000).all(|id| dirty.contains(TileId(id))));
+///     assert!(!dirty.contains(TileId(200_000)));
+///
+///     dirty.clear();
+///     assert!(dirty.is_empty());
+/// }
+/// ```
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""), Default(bound = ""))]
+pub struct AtomicTagStorage<T, I> {
+    bitset: AtomicBitSet,
+    #[derivative(Debug = "ignore")]
+    phantom: PhantomData<(T, I)>,
+}
+
+impl<T, I> AtomicTagStorage<T, I>
+where
+    I: Id,
+{
+    /// Creates an empty storage.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Tags `id`, returning `true` if it wasn't tagged before.
+    ///
+    /// If several threads tag the same id at once, more than one of them may be told it
+    /// wasn't tagged before.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` exceeds `Storage::MAX_ID`, like `TagStorage::insert`.
+    pub fn add(&self, id: I) -> bool {
+        bound::expect_max_id(id.id());
+        !self.bitset.add_atomic(id.id())
+    }
+
+    /// Removes the tag of `id`, returning `true` if it was tagged.
+    pub fn remove(&mut self, id: I) -> bool {
+        self.bitset.remove(id.id())
+    }
+
+    /// Returns `true` if `id` is tagged.
+    pub fn contains(&self, id: I) -> bool {
+        self.bitset.contains(id.id())
+    }
+
+    /// Removes all tags.
+    pub fn clear(&mut self) {
+        self.bitset.clear();
+    }
+
+    /// Returns the amount of tagged ids.
+    ///
+    /// This has to count them, so it takes time proportional to the amount of tagged ids.
+    pub fn len(&self) -> usize {
+        (&self.bitset).iter().count()
+    }
+
+    /// Returns `true` if no id is tagged.
+    pub fn is_empty(&self) -> bool {
+        self.bitset.is_empty()
+    }
+
+    /// Returns the mask of all tagged ids.
+    pub fn mask(&self) -> &AtomicBitSet {
+        &self.bitset
+    }
+}
+
+impl<'a, T, I> Join for &'a AtomicTagStorage<T, I> {
+    type Type = ();
+    type Value = ();
+    type Mask = &'a AtomicBitSet;
+
+    unsafe fn open(self) -> (Self::Mask, Self::Value) {
+        (&self.bitset, ())
+    }
+
+    unsafe fn get(_: &mut Self::Value, _: Index) {}
+}
+
+#[cfg(feature = "parallel")]
+unsafe impl<T, I> ParJoin for &AtomicTagStorage<T, I> where T: Send + Sync, I: Send + Sync {}
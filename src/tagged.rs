@@ -0,0 +1,181 @@
+//! Id types which only differ by a tag type, for keeping id spaces apart.
+
+use std::any;
+use std::fmt;
+use std::marker::PhantomData;
+
+#[cfg(feature = "serde")]
+use serde::de::{Deserialize, Deserializer};
+#[cfg(feature = "serde")]
+use serde::ser::{Serialize, Serializer};
+
+use Id;
+
+/// An id of the id space `Tag`, so several maps with the same layout can have distinct id
+/// types without writing an `Id` impl for each of them.
+///
+/// Storages keyed by `TaggedId<Surface>` only accept surface ids, and a `Grid2d<Surface>`
+/// (see `Grid2d::tagged`) only produces them, so passing the id of one map to the storage
+/// of another one doesn't compile.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::{Component, VecStorage};
+/// use specs_static::{Grid2d, Storage, TaggedId};
+///
+/// struct Surface;
+/// struct Caves;
+/// type SurfaceId = TaggedId<Surface>;
+/// type CaveId = TaggedId<Caves>;
+///
+/// struct Rock;
+///
+/// impl Component for Rock {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// fn main() {
+///     let surface = Grid2d::new(32, 32).tagged::<Surface>();
+///     let caves = Grid2d::new(32, 32).tagged::<Caves>();
+///
+///     let mut surface_rocks = Storage::<Rock, VecStorage<_>, SurfaceId>::default();
+///     let mut cave_rocks = Storage::<Rock, VecStorage<_>, CaveId>::default();
+///     surface_rocks.insert(surface.id(1, 2).unwrap(), Rock);
+///     cave_rocks.insert(caves.id(1, 2).unwrap(), Rock);
+///     cave_rocks.insert(TaggedId::new(5), Rock);
+///
+///     assert_eq!(surface.id(1, 2), Some(SurfaceId::new(65)));
+///     assert!(format!("{:?}", SurfaceId::new(65)).ends_with("Surface>(65)"));
+/// }
+/// ```
+///
+/// Mixing up the id spaces is a compile error:
+///
+/// ```compile_fail
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::{Component, VecStorage};
+/// use specs_static::{Grid2d, Storage, TaggedId};
+///
+/// struct Surface;
+/// struct Caves;
+///
+/// struct Rock;
+///
+/// impl Component for Rock {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// fn main() {
+///     let caves = Grid2d::new(32, 32).tagged::<Caves>();
+///     let mut surface_rocks = Storage::<Rock, VecStorage<_>, TaggedId<Surface>>::default();
+///     surface_rocks.insert(caves.id(1, 2).unwrap(), Rock);
+/// }
+/// ```
+///
+/// ```compile_fail
+/// use specs_static::{Grid2d, TaggedId};
+///
+/// struct Surface;
+/// struct Caves;
+///
+/// let caves = Grid2d::new(32, 32).tagged::<Caves>();
+/// let _: Option<TaggedId<Surface>> = caves.id(1, 2);
+/// ```
+///
+/// With the `serde` feature, a `TaggedId` is serialized as its bare `u32`, whatever the tag:
+///
+#[cfg_attr(feature = "serde", doc = "```")]
+#[cfg_attr(not(feature = "serde"), doc = "```ignore")]
+/// extern crate serde_json;
+/// extern crate specs_static;
+///
+/// use specs_static::TaggedId;
+///
+/// struct Surface;
+///
+/// fn main() {
+///     let ids = vec![TaggedId::<Surface>::new(65), TaggedId::new(7)];
+///     let json = serde_json::to_string(&ids).unwrap();
+///     assert_eq!(json, "[65,7]");
+///     assert_eq!(serde_json::from_str::<Vec<TaggedId<Surface>>>(&json).unwrap(), ids);
+/// }
+/// ```
+#[derive(Derivative)]
+#[derivative(
+    Clone(bound = ""),
+    Copy(bound = ""),
+    Eq(bound = ""),
+    Hash(bound = ""),
+    Ord(bound = ""),
+    PartialEq(bound = ""),
+    PartialOrd(bound = "")
+)]
+pub struct TaggedId<Tag>(u32, PhantomData<Tag>);
+
+impl<Tag> TaggedId<Tag> {
+    /// Creates the id with the value `id`.
+    pub fn new(id: u32) -> Self {
+        TaggedId(id, PhantomData)
+    }
+}
+
+impl<Tag> Id for TaggedId<Tag>
+where
+    Tag: 'static + Send + Sync,
+{
+    fn from_u32(value: u32) -> Self {
+        TaggedId::new(value)
+    }
+
+    fn id(&self) -> u32 {
+        self.0
+    }
+}
+
+impl<Tag> fmt::Debug for TaggedId<Tag> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TaggedId<{}>({})", any::type_name::<Tag>(), self.0)
+    }
+}
+
+/// The bare `u32`, so the tag doesn't have to be serializable.
+#[cfg(feature = "serde")]
+impl<Tag> Serialize for TaggedId<Tag> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.0.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, Tag> Deserialize<'de> for TaggedId<Tag> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        u32::deserialize(deserializer).map(TaggedId::new)
+    }
+}
+
+/// The id space of a plain `Grid2d`, which works with any id type.
+///
+/// It's unsized, so it can't be used as the tag of a `TaggedId`.
+pub struct Untagged {
+    _unsized: [()],
+}
+
+/// Id types of the id space `S`: every id type belongs to `Untagged`, and `TaggedId<Tag>`
+/// also belongs to `Tag`.
+pub trait IdSpace<S: ?Sized>: Id {}
+
+impl<I: Id> IdSpace<Untagged> for I {}
+
+impl<Tag> IdSpace<Tag> for TaggedId<Tag> where Tag: 'static + Send + Sync {}
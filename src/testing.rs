@@ -0,0 +1,401 @@
+//! Helpers for tests comparing storages and masks, with readable reports of the differences.
+//!
+//! A failing `assert_eq!` on two big layers prints both of them in full. The diffs of this
+//! module only list the ids which differ, with their coordinates if there's a grid, up to a
+//! limit, followed by a map of the differences for small grids. `assert_storage_eq!` and
+//! `assert_mask_eq!` panic with these diffs.
+
+use std::fmt::{self, Debug, Write};
+
+use hibitset::{BitSetLike, BitSetOr};
+use specs::storage::UnprotectedStorage;
+
+use {Grid2d, Id, Storage};
+
+/// The amount of ids the assertion macros list before cutting the diff short.
+pub const DIFF_LIMIT: usize = 16;
+
+/// The largest width and height of grids for which the diffs include a map.
+pub const MAP_LIMIT: u32 = 64;
+
+/// Describes how `actual` differs from `expected`, or returns `None` if they have equal
+/// components at the same ids.
+///
+/// Lists at most `limit` ids, as coordinates of `grid` if given. If the grid isn't wider and
+/// higher than `MAP_LIMIT`, the diff ends with a map of the grid marking the ids only
+/// `actual` has with `+`, the ones only `expected` has with `-`, the ones with different
+/// components with `~`, the equal ones with `#` and the vacant ones with `.`.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::{Component, VecStorage};
+/// use specs_static::testing::storage_diff;
+/// use specs_static::{Grid2d, Storage};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// #[derive(Debug, PartialEq)]
+/// struct Depth(u8);
+///
+/// impl Component for Depth {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// fn main() {
+///     let grid = Grid2d::new(4, 2);
+///     let mut actual = Storage::<Depth, VecStorage<_>, TileId>::default();
+///     let mut expected = Storage::<Depth, VecStorage<_>, TileId>::default();
+///     for id in 0..4 {
+///         actual.insert(TileId(id), Depth(1));
+///         expected.insert(TileId(id), Depth(1));
+///     }
+///     assert_eq!(storage_diff(&actual, &expected, Some(grid), 10), None);
+///
+///     actual.insert(TileId(1), Depth(2));
+///     actual.insert(TileId(6), Depth(1));
+///     expected.remove(TileId(3));
+///     let diff = storage_diff(&actual, &expected, Some(grid), 10).unwrap();
+///     assert_eq!(
+///         diff,
+///         "the storages differ at 3 ids: 2 only in actual, 0 only in expected, 1 different\n\
+///         \x20 (1, 0), id 1: Depth(2) in actual, Depth(1) in expected\n\
+///         \x20 (3, 0), id 3: only in actual: Depth(1)\n\
+///         \x20 (2, 1), id 6: only in actual: Depth(1)\n\
+///         map of the differences (`+` only in actual, `-` only in expected, `~` different):\n\
+///         \x20 #~#+\n\
+///         \x20 ..+.\n"
+///     );
+///
+///     // Without a grid, there are only ids.
+///     let diff = storage_diff(&actual, &expected, None, 1).unwrap();
+///     assert!(diff.contains("\n  id 1: Depth(2) in actual, Depth(1) in expected\n"));
+///     assert!(diff.ends_with("\n  ... 2 more\n"));
+/// }
+/// ```
+pub fn storage_diff<C, DA, DE, I>(
+    actual: &Storage<C, DA, I>,
+    expected: &Storage<C, DE, I>,
+    grid: Option<Grid2d>,
+    limit: usize,
+) -> Option<String>
+where
+    C: Debug + PartialEq,
+    DA: UnprotectedStorage<C>,
+    DE: UnprotectedStorage<C>,
+    I: Id,
+{
+    let mut diff = Diff::new("storages", grid, limit);
+    for id in BitSetOr(actual.mask(), expected.mask()).iter() {
+        match (actual.get(I::from_u32(id)), expected.get(I::from_u32(id))) {
+            (Some(a), Some(e)) if a == e => diff.equal(id),
+            (Some(a), Some(e)) => {
+                diff.different(id, format_args!("{:?} in actual, {:?} in expected", a, e))
+            }
+            (Some(a), None) => diff.only_actual(id, format_args!(": {:?}", a)),
+            (None, Some(e)) => diff.only_expected(id, format_args!(": {:?}", e)),
+            (None, None) => unreachable!(),
+        }
+    }
+
+    diff.finish()
+}
+
+/// Describes how the mask `actual` differs from `expected`, or returns `None` if they contain
+/// the same ids.
+///
+/// See `storage_diff` for the layout of the diff.
+///
+/// # Examples
+///
+/// ```
+/// extern crate hibitset;
+/// extern crate specs_static;
+///
+/// use hibitset::BitSet;
+/// use specs_static::testing::mask_diff;
+/// use specs_static::Grid2d;
+///
+/// fn main() {
+///     let actual: BitSet = [0, 1, 5].iter().cloned().collect();
+///     let expected: BitSet = [0, 1, 4].iter().cloned().collect();
+///     let diff = mask_diff(&actual, &expected, Some(Grid2d::new(3, 2)), 10).unwrap();
+///     assert_eq!(
+///         diff,
+///         "the masks differ at 2 ids: 1 only in actual, 1 only in expected\n\
+///         \x20 (1, 1), id 4: only in expected\n\
+///         \x20 (2, 1), id 5: only in actual\n\
+///         map of the differences (`+` only in actual, `-` only in expected):\n\
+///         \x20 ##.\n\
+///         \x20 .-+\n"
+///     );
+///     assert_eq!(mask_diff(&actual, &actual, None, 10), None);
+/// }
+/// ```
+pub fn mask_diff<A, E>(
+    actual: A,
+    expected: E,
+    grid: Option<Grid2d>,
+    limit: usize,
+) -> Option<String>
+where
+    A: BitSetLike,
+    E: BitSetLike,
+{
+    let mut diff = Diff::new("masks", grid, limit);
+    for id in BitSetOr(&actual, &expected).iter() {
+        match (actual.contains(id), expected.contains(id)) {
+            (true, true) => diff.equal(id),
+            (true, false) => diff.only_actual(id, format_args!("")),
+            (false, _) => diff.only_expected(id, format_args!("")),
+        }
+    }
+
+    diff.finish()
+}
+
+/// Asserts that two tile storages have equal components at the same ids, panicking with the
+/// diff of `testing::storage_diff` otherwise.
+///
+/// The storages are passed by reference. An optional third argument is the `Grid2d` the
+/// differing ids are shown on; tagged grids are passed as their untagged version.
+///
+/// # Examples
+///
+/// ```
+/// #[macro_use]
+/// extern crate specs_static;
+/// extern crate specs;
+///
+/// use std::panic::{self, AssertUnwindSafe};
+///
+/// use specs::{Component, VecStorage};
+/// use specs_static::{Grid2d, Storage};
+///
+/// # define_id!(pub struct TileId;);
+/// #[derive(Debug, PartialEq)]
+/// struct Ore(u32);
+///
+/// impl Component for Ore {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// fn main() {
+///     let grid = Grid2d::new(1000, 1000);
+///     let mut generated = Storage::<Ore, VecStorage<_>, TileId>::default();
+///     let mut expected = Storage::<Ore, VecStorage<_>, TileId>::default();
+///     generated.insert(grid.id(500, 20).unwrap(), Ore(3));
+///     expected.insert(grid.id(500, 20).unwrap(), Ore(3));
+///     assert_storage_eq!(&generated, &expected, grid);
+///
+///     generated.insert(grid.id(7, 9).unwrap(), Ore(1));
+///     let failure = panic::catch_unwind(AssertUnwindSafe(|| {
+///         assert_storage_eq!(&generated, &expected, grid);
+///     }));
+///     let message = failure.unwrap_err().downcast::<String>().unwrap();
+///     assert!(message.starts_with("assertion failed: `&generated == &expected`\n"));
+///     assert!(message.contains("\n  (7, 9), id 9007: only in actual: Ore(1)\n"));
+///     // The grid is too large for a map.
+///     assert!(!message.contains("map"));
+/// }
+/// ```
+#[macro_export]
+macro_rules! assert_storage_eq {
+    ($actual:expr, $expected:expr) => {
+        if let Some(diff) = $crate::testing::storage_diff(
+            $actual,
+            $expected,
+            None,
+            $crate::testing::DIFF_LIMIT,
+        ) {
+            panic!(
+                "assertion failed: `{} == {}`\n{}",
+                stringify!($actual),
+                stringify!($expected),
+                diff
+            );
+        }
+    };
+    ($actual:expr, $expected:expr, $grid:expr $(,)?) => {
+        if let Some(diff) = $crate::testing::storage_diff(
+            $actual,
+            $expected,
+            Some($grid),
+            $crate::testing::DIFF_LIMIT,
+        ) {
+            panic!(
+                "assertion failed: `{} == {}`\n{}",
+                stringify!($actual),
+                stringify!($expected),
+                diff
+            );
+        }
+    };
+}
+
+/// Asserts that two masks contain the same ids, panicking with the diff of
+/// `testing::mask_diff` otherwise.
+///
+/// The masks are anything implementing `BitSetLike`, usually references to `BitSet`s. Like
+/// for `assert_storage_eq!`, an optional third argument is a `Grid2d`.
+///
+/// # Examples
+///
+/// ```
+/// #[macro_use]
+/// extern crate specs_static;
+/// extern crate hibitset;
+///
+/// use hibitset::BitSet;
+/// use specs_static::{Grid2d, RangeMask};
+///
+/// fn main() {
+///     let grid = Grid2d::new(8, 8);
+///     let row: BitSet = (8..16).collect();
+///     assert_mask_eq!(&row, RangeMask::from_range(8, 16), grid);
+///     assert_mask_eq!(&row, &row);
+/// }
+/// ```
+#[macro_export]
+macro_rules! assert_mask_eq {
+    ($actual:expr, $expected:expr) => {
+        if let Some(diff) = $crate::testing::mask_diff(
+            $actual,
+            $expected,
+            None,
+            $crate::testing::DIFF_LIMIT,
+        ) {
+            panic!(
+                "assertion failed: `{} == {}`\n{}",
+                stringify!($actual),
+                stringify!($expected),
+                diff
+            );
+        }
+    };
+    ($actual:expr, $expected:expr, $grid:expr $(,)?) => {
+        if let Some(diff) = $crate::testing::mask_diff(
+            $actual,
+            $expected,
+            Some($grid),
+            $crate::testing::DIFF_LIMIT,
+        ) {
+            panic!(
+                "assertion failed: `{} == {}`\n{}",
+                stringify!($actual),
+                stringify!($expected),
+                diff
+            );
+        }
+    };
+}
+
+/// Collects the differences of two storages or masks.
+struct Diff {
+    what: &'static str,
+    grid: Option<Grid2d>,
+    limit: usize,
+    lines: String,
+    listed: usize,
+    only_actual: usize,
+    only_expected: usize,
+    different: usize,
+    map: Option<Vec<u8>>,
+}
+
+impl Diff {
+    fn new(what: &'static str, grid: Option<Grid2d>, limit: usize) -> Self {
+        let map = grid
+            .filter(|grid| !grid.is_empty())
+            .filter(|grid| grid.width() <= MAP_LIMIT && grid.height() <= MAP_LIMIT)
+            .map(|grid| vec![b'.'; grid.len() as usize]);
+
+        Diff {
+            what,
+            grid,
+            limit,
+            lines: String::new(),
+            listed: 0,
+            only_actual: 0,
+            only_expected: 0,
+            different: 0,
+            map,
+        }
+    }
+
+    fn equal(&mut self, id: u32) {
+        self.mark(id, b'#');
+    }
+
+    fn only_actual(&mut self, id: u32, value: fmt::Arguments) {
+        self.only_actual += 1;
+        self.mark(id, b'+');
+        self.list(id, format_args!("only in actual{}", value));
+    }
+
+    fn only_expected(&mut self, id: u32, value: fmt::Arguments) {
+        self.only_expected += 1;
+        self.mark(id, b'-');
+        self.list(id, format_args!("only in expected{}", value));
+    }
+
+    fn different(&mut self, id: u32, values: fmt::Arguments) {
+        self.different += 1;
+        self.mark(id, b'~');
+        self.list(id, values);
+    }
+
+    fn mark(&mut self, id: u32, mark: u8) {
+        if let Some(slot) = self.map.as_mut().and_then(|map| map.get_mut(id as usize)) {
+            *slot = mark;
+        }
+    }
+
+    fn list(&mut self, id: u32, text: fmt::Arguments) {
+        self.listed += 1;
+        if self.listed > self.limit {
+            return;
+        }
+
+        let coords = self.grid.map(|grid| grid.coords_of(id));
+        let _ = match coords {
+            Some(Some((x, y))) => writeln!(self.lines, "  ({}, {}), id {}: {}", x, y, id, text),
+            Some(None) => writeln!(self.lines, "  outside the grid, id {}: {}", id, text),
+            None => writeln!(self.lines, "  id {}: {}", id, text),
+        };
+    }
+
+    fn finish(self) -> Option<String> {
+        if self.listed == 0 {
+            return None;
+        }
+
+        let mut diff = format!(
+            "the {} differ at {} ids: {} only in actual, {} only in expected",
+            self.what, self.listed, self.only_actual, self.only_expected
+        );
+        let values = self.what == "storages";
+        if values {
+            let _ = write!(diff, ", {} different", self.different);
+        }
+        diff.push('\n');
+        diff.push_str(&self.lines);
+        if self.listed > self.limit {
+            let _ = writeln!(diff, "  ... {} more", self.listed - self.limit);
+        }
+
+        if let (Some(grid), Some(map)) = (self.grid, self.map) {
+            diff.push_str("map of the differences (`+` only in actual, `-` only in expected");
+            diff.push_str(if values { ", `~` different):\n" } else { "):\n" });
+            for row in map.chunks(grid.width() as usize) {
+                diff.push_str("  ");
+                diff.extend(row.iter().map(|&mark| mark as char));
+                diff.push('\n');
+            }
+        }
+
+        Some(diff)
+    }
+}
@@ -0,0 +1,188 @@
+//! Looking up one id in several storages at once.
+
+use specs::storage::UnprotectedStorage;
+
+use {Id, Storage};
+
+/// Storages, or tuples of storages, which can be queried for the components of a single id.
+///
+/// This is implemented for `&Storage` and `&mut Storage`, and for tuples of these of up to
+/// eight elements, which may freely mix shared and mutable references.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::{Component, VecStorage};
+/// use specs_static::{Storage, TileGet};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// #[derive(Debug, PartialEq)]
+/// struct Material(&'static str);
+///
+/// impl Component for Material {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Elevation(i32);
+///
+/// impl Component for Elevation {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Occupant(&'static str);
+///
+/// impl Component for Occupant {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// fn main() {
+///     let mut materials = Storage::<Material, VecStorage<_>, TileId>::default();
+///     let mut elevations = Storage::<Elevation, VecStorage<_>, TileId>::default();
+///     let mut occupants = Storage::<Occupant, VecStorage<_>, TileId>::default();
+///     materials.insert(TileId(1), Material("rock"));
+///     materials.insert(TileId(2), Material("sand"));
+///     elevations.insert(TileId(1), Elevation(4));
+///     elevations.insert(TileId(2), Elevation(0));
+///     occupants.insert(TileId(1), Occupant("goat"));
+///
+///     assert_eq!(
+///         (&materials, &elevations, &occupants).get_all(TileId(1)),
+///         Some((&Material("rock"), &Elevation(4), &Occupant("goat")))
+///     );
+///     assert_eq!((&materials, &elevations, &occupants).get_all(TileId(2)), None);
+///     assert_eq!(
+///         (&materials, &elevations, &occupants).get_any(TileId(2)),
+///         (Some(&Material("sand")), Some(&Elevation(0)), None)
+///     );
+///
+///     // Read two layers and write a third.
+///     if let Some((material, occupant, elevation)) =
+///         (&materials, &occupants, &mut elevations).get_all(TileId(1))
+///     {
+///         assert_eq!((material, occupant), (&Material("rock"), &Occupant("goat")));
+///         elevation.0 -= 1;
+///     }
+///     assert_eq!(elevations.get(TileId(1)), Some(&Elevation(3)));
+///
+///     // Nothing is handed out unless every storage has the id.
+///     assert_eq!((&mut materials, &occupants).get_all(TileId(2)), None);
+///     assert_eq!((&mut materials,).get_all(TileId(2)), Some((&mut Material("sand"),)));
+///
+///     let m = &materials;
+///     let e = &elevations;
+///     assert_eq!((m, e, m, e, m, e, m, e).get_all(TileId(3)), None);
+///     assert_eq!(
+///         (m, e, m, e, m, e, m, e).get_all(TileId(2)).map(|all| all.7),
+///         Some(&Elevation(0))
+///     );
+///     assert_eq!((m, e, m, e, m).get_any(TileId(0)), (None, None, None, None, None));
+/// }
+/// ```
+pub trait TileGet<I> {
+    /// The components of an id which all storages have.
+    type Item;
+    /// The components of an id, each of them only if the corresponding storage has it.
+    type Any;
+
+    /// Returns `true` if every storage has a component for `id`.
+    fn contains_all(&self, id: I) -> bool;
+
+    /// Returns the components of `id`, or `None` unless every storage has a component for it.
+    fn get_all(self, id: I) -> Option<Self::Item>;
+
+    /// Returns the components of `id` which exist.
+    fn get_any(self, id: I) -> Self::Any;
+}
+
+impl<'a, C, D, I> TileGet<I> for &'a Storage<C, D, I>
+where
+    D: UnprotectedStorage<C>,
+    I: Id,
+{
+    type Item = &'a C;
+    type Any = Option<&'a C>;
+
+    fn contains_all(&self, id: I) -> bool {
+        self.mask().contains(id.id())
+    }
+
+    fn get_all(self, id: I) -> Option<&'a C> {
+        self.get(id)
+    }
+
+    fn get_any(self, id: I) -> Option<&'a C> {
+        self.get(id)
+    }
+}
+
+impl<'a, C, D, I> TileGet<I> for &'a mut Storage<C, D, I>
+where
+    D: UnprotectedStorage<C>,
+    I: Id,
+{
+    type Item = &'a mut C;
+    type Any = Option<&'a mut C>;
+
+    fn contains_all(&self, id: I) -> bool {
+        self.mask().contains(id.id())
+    }
+
+    fn get_all(self, id: I) -> Option<&'a mut C> {
+        self.get_mut(id)
+    }
+
+    fn get_any(self, id: I) -> Option<&'a mut C> {
+        self.get_mut(id)
+    }
+}
+
+macro_rules! impl_tile_get {
+    ($($name:ident)*) => {
+        impl<I, $($name),*> TileGet<I> for ($($name,)*)
+        where
+            I: Id,
+            $($name: TileGet<I>,)*
+        {
+            type Item = ($($name::Item,)*);
+            type Any = ($($name::Any,)*);
+
+            #[allow(non_snake_case)]
+            fn contains_all(&self, id: I) -> bool {
+                let ($(ref $name,)*) = *self;
+
+                true $(&& $name.contains_all(id))*
+            }
+
+            #[allow(non_snake_case)]
+            fn get_all(self, id: I) -> Option<Self::Item> {
+                if !self.contains_all(id) {
+                    return None;
+                }
+                let ($($name,)*) = self;
+
+                Some(($($name.get_all(id)?,)*))
+            }
+
+            #[allow(non_snake_case)]
+            fn get_any(self, id: I) -> Self::Any {
+                let ($($name,)*) = self;
+
+                ($($name.get_any(id),)*)
+            }
+        }
+    };
+}
+
+impl_tile_get!(A);
+impl_tile_get!(A B);
+impl_tile_get!(A B C);
+impl_tile_get!(A B C D);
+impl_tile_get!(A B C D E);
+impl_tile_get!(A B C D E F);
+impl_tile_get!(A B C D E F G);
+impl_tile_get!(A B C D E F G H);
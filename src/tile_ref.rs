@@ -0,0 +1,145 @@
+//! Joining entities which stand on tiles with the components of those tiles.
+
+use specs::storage::UnprotectedStorage;
+use specs::{Component, DenseVecStorage, Join};
+
+use {Id, Index, Storage};
+
+/// A component for entities which refer to a tile, like units standing on it.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct TileRef<I>(pub I);
+
+impl<I> Component for TileRef<I>
+where
+    I: Id,
+{
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl<C, D, I> Storage<C, D, I>
+where
+    D: UnprotectedStorage<C>,
+    I: Id,
+{
+    /// Returns the component of the tile `tile_ref` refers to.
+    pub fn get_for(&self, tile_ref: &TileRef<I>) -> Option<&C> {
+        self.get(tile_ref.0)
+    }
+}
+
+/// Joins the `TileRef`s of entities with the components of the referred tiles.
+///
+/// `refs` can be anything joinable over entities which yields `&TileRef<I>`, usually a
+/// `&ReadStorage<TileRef<I>>`. The resulting join yields the tile reference together with
+/// the tile's component, if it has one, and can be combined with other entity storages in a
+/// single `.join()`.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::prelude::*;
+/// use specs_static::{tile_lookup, Storage, TileRef, TileWorldExt};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// #[derive(Debug, PartialEq)]
+/// enum Material {
+///     Mud,
+///     Stone,
+/// }
+///
+/// impl Component for Material {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// struct Speed(f32);
+///
+/// impl Component for Speed {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// struct SlowDown;
+///
+/// impl<'a> System<'a> for SlowDown {
+///     type SystemData = (
+///         Read<'a, Storage<Material, VecStorage<Material>, TileId>>,
+///         ReadStorage<'a, TileRef<TileId>>,
+///         WriteStorage<'a, Speed>,
+///     );
+///
+///     fn run(&mut self, (materials, refs, mut speeds): Self::SystemData) {
+///         for ((_, material), speed) in (tile_lookup(&*materials, &refs), &mut speeds).join() {
+///             if material == Some(&Material::Mud) {
+///                 speed.0 *= 0.5;
+///             }
+///         }
+///     }
+/// }
+///
+/// fn main() {
+///     let mut world = World::new();
+///     world.register::<TileRef<TileId>>();
+///     world.register::<Speed>();
+///     world.register_tile_comp::<Material, TileId>();
+///     {
+///         let mut materials = world.write_resource::<Storage<Material, VecStorage<_>, TileId>>();
+///         materials.insert(TileId(0), Material::Mud);
+///         materials.insert(TileId(1), Material::Stone);
+///     }
+///
+///     let stuck = world.create_entity().with(TileRef(TileId(0))).with(Speed(1.0)).build();
+///     let fine = world.create_entity().with(TileRef(TileId(1))).with(Speed(1.0)).build();
+///     let nowhere = world.create_entity().with(TileRef(TileId(2))).with(Speed(1.0)).build();
+///
+///     SlowDown.run_now(&world);
+///
+///     let speeds = world.read_storage::<Speed>();
+///     assert_eq!(speeds.get(stuck).unwrap().0, 0.5);
+///     assert_eq!(speeds.get(fine).unwrap().0, 1.0);
+///     assert_eq!(speeds.get(nowhere).unwrap().0, 1.0);
+/// }
+/// ```
+pub fn tile_lookup<'a, C, D, I, R>(
+    tiles: &'a Storage<C, D, I>,
+    refs: R,
+) -> TileLookup<'a, C, D, I, R>
+where
+    D: UnprotectedStorage<C>,
+    I: Id,
+    R: Join<Type = &'a TileRef<I>>,
+{
+    TileLookup { tiles, refs }
+}
+
+/// A join over entities yielding their `TileRef` and the component of the referred tile.
+///
+/// Created by `tile_lookup`.
+pub struct TileLookup<'a, C: 'a, D: 'a + UnprotectedStorage<C>, I: 'a, R> {
+    tiles: &'a Storage<C, D, I>,
+    refs: R,
+}
+
+impl<'a, C, D, I, R> Join for TileLookup<'a, C, D, I, R>
+where
+    D: UnprotectedStorage<C>,
+    I: Id,
+    R: Join<Type = &'a TileRef<I>>,
+{
+    type Type = (&'a TileRef<I>, Option<&'a C>);
+    type Value = (&'a Storage<C, D, I>, R::Value);
+    type Mask = R::Mask;
+
+    unsafe fn open(self) -> (Self::Mask, Self::Value) {
+        let (mask, value) = self.refs.open();
+
+        (mask, (self.tiles, value))
+    }
+
+    unsafe fn get(value: &mut Self::Value, id: Index) -> Self::Type {
+        let tile_ref = R::get(&mut value.1, id);
+
+        (tile_ref, value.0.get_for(tile_ref))
+    }
+}
@@ -0,0 +1,520 @@
+//! Importing the tile layers of maps made with the Tiled editor, from its JSON map format.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use serde_json::{self, Map, Value};
+use specs::storage::UnprotectedStorage;
+
+use {Grid2d, GridTooLarge, Id, Storage};
+
+const FLIPPED_HORIZONTALLY: u32 = 0x8000_0000;
+const FLIPPED_VERTICALLY: u32 = 0x4000_0000;
+const FLIPPED_DIAGONALLY: u32 = 0x2000_0000;
+/// All flag bits, including the one for rotated hexagonal tiles.
+const FLAGS: u32 = 0xf000_0000;
+
+/// The error returned when a Tiled map or one of its layers can't be imported.
+#[derive(Debug)]
+pub enum TiledError {
+    /// The map isn't valid JSON.
+    Json {
+        /// Why parsing failed.
+        error: serde_json::Error,
+    },
+    /// A field the importer needs is missing or has an unexpected type.
+    Malformed {
+        /// The name of the field.
+        field: &'static str,
+    },
+    /// The map is infinite, so its layers are stored in chunks, which aren't supported.
+    /// Resizing the map in Tiled with the "Infinite" option unchecked converts it.
+    Infinite,
+    /// The map has more tiles than a grid can have.
+    TooLarge {
+        /// The dimensions of the map.
+        error: GridTooLarge,
+    },
+    /// The map has no tile layer with the name.
+    UnknownLayer {
+        /// The requested name.
+        name: String,
+    },
+    /// The layer with the name is an object or image layer.
+    NotATileLayer {
+        /// The requested name.
+        name: String,
+    },
+    /// The size of a tile layer doesn't match the size of the map.
+    LayerSize {
+        /// The name of the layer.
+        name: String,
+        /// The amount of tiles of the map.
+        expected: u32,
+        /// The amount of tiles of the layer.
+        found: usize,
+    },
+    /// A tile layer is compressed, or uses an encoding other than CSV and Base64.
+    Encoding {
+        /// The name of the layer.
+        name: String,
+        /// The encoding, or the compression if there is one.
+        encoding: String,
+    },
+}
+
+impl fmt::Display for TiledError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TiledError::Json { ref error } => write!(f, "invalid Tiled map: {}", error),
+            TiledError::Malformed { field } => {
+                write!(f, "the Tiled map has a missing or malformed `{}` field", field)
+            }
+            TiledError::Infinite => write!(f, "infinite Tiled maps aren't supported"),
+            TiledError::TooLarge { ref error } => error.fmt(f),
+            TiledError::UnknownLayer { ref name } => {
+                write!(f, "the Tiled map has no tile layer named {:?}", name)
+            }
+            TiledError::NotATileLayer { ref name } => {
+                write!(f, "the Tiled layer {:?} isn't a tile layer", name)
+            }
+            TiledError::LayerSize {
+                ref name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "the Tiled layer {:?} has {} tiles instead of {}",
+                name, found, expected
+            ),
+            TiledError::Encoding {
+                ref name,
+                ref encoding,
+            } => write!(
+                f,
+                "the Tiled layer {:?} uses the unsupported encoding {:?}",
+                name, encoding
+            ),
+        }
+    }
+}
+
+impl Error for TiledError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            TiledError::Json { ref error } => Some(error),
+            TiledError::TooLarge { ref error } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// A cell of a Tiled tile layer, as passed to the closure of `TiledMap::import_layer`.
+#[derive(Clone, Copy, Debug)]
+pub struct TiledTile<'a> {
+    /// The global tile id, without the flip flags. Never 0, since empty cells are skipped.
+    pub gid: u32,
+    /// The tile is mirrored horizontally.
+    pub flipped_horizontally: bool,
+    /// The tile is mirrored vertically.
+    pub flipped_vertically: bool,
+    /// The tile is mirrored along its top-left to bottom-right diagonal, which combined
+    /// with the other flags rotates it.
+    pub flipped_diagonally: bool,
+    properties: Option<&'a Map<String, Value>>,
+}
+
+impl<'a> TiledTile<'a> {
+    /// Returns the value of the custom property `name` of the tile, as set in its tileset.
+    ///
+    /// Properties of tiles in external tilesets aren't available, since they're stored in
+    /// the tileset files rather than in the map; embed the tilesets to use them.
+    pub fn property(&self, name: &str) -> Option<&'a Value> {
+        self.properties.and_then(|properties| properties.get(name))
+    }
+}
+
+/// A map exported from the Tiled editor in its JSON format, ready to import tile layers
+/// from. TMX maps can be converted with "Export As" in Tiled, or with
+/// `tiled --export-map json map.tmx map.json`.
+///
+/// Only finite, orthogonal-style layouts are supported: every tile layer has to cover the
+/// whole map, as CSV or uncompressed Base64 data. Tile layers inside of group layers are
+/// found by their own names. Object and image layers are ignored.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::{Component, VecStorage};
+/// use specs_static::{Storage, TiledError, TiledMap};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// #[derive(Debug, PartialEq)]
+/// enum Floor {
+///     Stone,
+///     Water,
+///     Prop(u32),
+/// }
+///
+/// impl Component for Floor {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Wall {
+///     hp: u64,
+///     mirrored: bool,
+/// }
+///
+/// impl Component for Wall {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// fn main() {
+///     let json = include_str!(concat!(
+///         env!("CARGO_MANIFEST_DIR"),
+///         "/tests/fixtures/dungeon.json"
+///     ));
+///     let map = TiledMap::from_json_str(json).unwrap();
+///     let grid = map.grid();
+///     assert_eq!((grid.width(), grid.height()), (4, 3));
+///     assert_eq!(map.layer_names().collect::<Vec<_>>(), vec!["floor", "walls"]);
+///
+///     // Tile 1 of the embedded tileset is wet, gids from 10 on are props.
+///     let floor: Storage<Floor, VecStorage<_>, TileId> = map
+///         .import_layer("floor", |tile| match tile.gid {
+///             gid if gid >= 10 => Some(Floor::Prop(gid - 10)),
+///             _ if tile.property("wet").is_some() => Some(Floor::Water),
+///             _ => Some(Floor::Stone),
+///         })
+///         .unwrap();
+///     assert_eq!(floor.get(grid.id(0, 0).unwrap()), Some(&Floor::Stone));
+///     assert_eq!(floor.get(grid.id(2, 0).unwrap()), Some(&Floor::Water));
+///     assert_eq!(floor.get(grid.id(3, 1).unwrap()), Some(&Floor::Prop(1)));
+///     assert_eq!(floor.get(grid.id(3, 2).unwrap()), Some(&Floor::Prop(0)));
+///
+///     // The walls layer is nested in a group and Base64 encoded.
+///     let walls: Storage<Wall, VecStorage<_>, TileId> = map
+///         .import_layer("walls", |tile| match tile.property("solid") {
+///             Some(_) => Some(Wall {
+///                 hp: tile.property("hp").and_then(|hp| hp.as_u64()).unwrap_or(1),
+///                 mirrored: tile.flipped_horizontally,
+///             }),
+///             None => None,
+///         })
+///         .unwrap();
+///     let wall = |x, y| walls.get(grid.id(x, y).unwrap());
+///     assert_eq!(wall(0, 0), Some(&Wall { hp: 30, mirrored: false }));
+///     assert_eq!(wall(3, 1), Some(&Wall { hp: 30, mirrored: true }));
+///     assert_eq!(wall(1, 1), None);
+///     assert_eq!(wall(2, 2), None);
+///     assert_eq!(wall(3, 2), Some(&Wall { hp: 30, mirrored: false }));
+///
+///     let spawns: Result<Storage<Wall, VecStorage<_>, TileId>, _> =
+///         map.import_layer("spawns", |_| None);
+///     match spawns {
+///         Err(TiledError::NotATileLayer { ref name }) => assert_eq!(name, "spawns"),
+///         _ => panic!("spawns is an object layer"),
+///     }
+///     let roof: Result<Storage<Wall, VecStorage<_>, TileId>, _> =
+///         map.import_layer("roof", |_| None);
+///     match roof {
+///         Err(TiledError::UnknownLayer { .. }) => {}
+///         _ => panic!("there is no roof"),
+///     }
+/// }
+/// ```
+///
+/// Maps which can't be imported are rejected up front:
+///
+/// ```
+/// extern crate specs_static;
+///
+/// use specs_static::{TiledError, TiledMap};
+///
+/// fn main() {
+///     let infinite = r#"{ "width": 16, "height": 16, "infinite": true, "layers": [] }"#;
+///     match TiledMap::from_json_str(infinite) {
+///         Err(TiledError::Infinite) => {}
+///         _ => panic!("infinite maps are chunked"),
+///     }
+///
+///     let short = r#"{
+///         "width": 2, "height": 2, "infinite": false,
+///         "layers": [{ "type": "tilelayer", "name": "ground", "data": [1, 1, 1] }]
+///     }"#;
+///     match TiledMap::from_json_str(short) {
+///         Err(TiledError::LayerSize { expected: 4, found: 3, .. }) => {}
+///         _ => panic!("the layer is missing a tile"),
+///     }
+///
+///     let zipped = r#"{
+///         "width": 1, "height": 1, "infinite": false,
+///         "layers": [{
+///             "type": "tilelayer", "name": "ground",
+///             "encoding": "base64", "compression": "zlib", "data": "eJxjZGQAAAAHAAI="
+///         }]
+///     }"#;
+///     match TiledMap::from_json_str(zipped) {
+///         Err(TiledError::Encoding { ref encoding, .. }) => assert_eq!(encoding, "zlib"),
+///         _ => panic!("compressed layers aren't supported"),
+///     }
+///
+///     match TiledMap::from_json_str(r#"{ "width": 2 }"#) {
+///         Err(TiledError::Malformed { field: "height" }) => {}
+///         _ => panic!("the height is missing"),
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct TiledMap {
+    grid: Grid2d,
+    /// The name and global tile ids of every tile layer, in the order of the map.
+    tile_layers: Vec<(String, Vec<u32>)>,
+    /// The names of the object and image layers.
+    other_layers: Vec<String>,
+    /// The custom properties of the tiles of embedded tilesets, by global tile id.
+    properties: HashMap<u32, Map<String, Value>>,
+}
+
+impl TiledMap {
+    /// Parses a map from the text of a Tiled JSON map file.
+    pub fn from_json_str(json: &str) -> Result<Self, TiledError> {
+        let value: Value =
+            serde_json::from_str(json).map_err(|error| TiledError::Json { error })?;
+
+        Self::from_json(&value)
+    }
+
+    /// Reads a map from a parsed Tiled JSON map, validating its dimensions and decoding
+    /// all of its tile layers.
+    pub fn from_json(value: &Value) -> Result<Self, TiledError> {
+        let width = field_u32(value, "width")?;
+        let height = field_u32(value, "height")?;
+        if value.get("infinite").and_then(Value::as_bool) == Some(true) {
+            return Err(TiledError::Infinite);
+        }
+        let grid =
+            Grid2d::try_new(width, height).map_err(|error| TiledError::TooLarge { error })?;
+
+        let mut map = TiledMap {
+            grid,
+            tile_layers: Vec::new(),
+            other_layers: Vec::new(),
+            properties: HashMap::new(),
+        };
+        let layers = value
+            .get("layers")
+            .and_then(Value::as_array)
+            .ok_or(TiledError::Malformed { field: "layers" })?;
+        map.read_layers(layers)?;
+        if let Some(tilesets) = value.get("tilesets").and_then(Value::as_array) {
+            map.read_tilesets(tilesets)?;
+        }
+
+        Ok(map)
+    }
+
+    fn read_layers(&mut self, layers: &[Value]) -> Result<(), TiledError> {
+        for layer in layers {
+            let name = layer
+                .get("name")
+                .and_then(Value::as_str)
+                .ok_or(TiledError::Malformed { field: "name" })?
+                .to_owned();
+
+            match layer.get("type").and_then(Value::as_str) {
+                Some("tilelayer") => {
+                    let gids = self.read_data(&name, layer)?;
+                    self.tile_layers.push((name, gids));
+                }
+                Some("group") => {
+                    let layers = layer
+                        .get("layers")
+                        .and_then(Value::as_array)
+                        .ok_or(TiledError::Malformed { field: "layers" })?;
+                    self.read_layers(layers)?;
+                }
+                Some(_) => self.other_layers.push(name),
+                None => return Err(TiledError::Malformed { field: "type" }),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_data(&self, name: &str, layer: &Value) -> Result<Vec<u32>, TiledError> {
+        let encoding_error = |encoding: &str| TiledError::Encoding {
+            name: name.to_owned(),
+            encoding: encoding.to_owned(),
+        };
+        let compression = layer.get("compression").and_then(Value::as_str).unwrap_or("");
+        if !compression.is_empty() {
+            return Err(encoding_error(compression));
+        }
+
+        let data = layer.get("data").ok_or(TiledError::Malformed { field: "data" })?;
+        let gids = match layer.get("encoding").and_then(Value::as_str).unwrap_or("csv") {
+            "csv" => data
+                .as_array()
+                .ok_or(TiledError::Malformed { field: "data" })?
+                .iter()
+                .map(|gid| match gid.as_u64() {
+                    Some(gid) if gid <= u64::from(u32::MAX) => Ok(gid as u32),
+                    _ => Err(TiledError::Malformed { field: "data" }),
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            "base64" => {
+                let bytes = data
+                    .as_str()
+                    .and_then(decode_base64)
+                    .filter(|bytes| bytes.len() % 4 == 0)
+                    .ok_or(TiledError::Malformed { field: "data" })?;
+
+                bytes
+                    .chunks(4)
+                    .map(|gid| u32::from_le_bytes([gid[0], gid[1], gid[2], gid[3]]))
+                    .collect()
+            }
+            encoding => return Err(encoding_error(encoding)),
+        };
+
+        if gids.len() != self.grid.len() as usize {
+            return Err(TiledError::LayerSize {
+                name: name.to_owned(),
+                expected: self.grid.len(),
+                found: gids.len(),
+            });
+        }
+
+        Ok(gids)
+    }
+
+    fn read_tilesets(&mut self, tilesets: &[Value]) -> Result<(), TiledError> {
+        for tileset in tilesets {
+            let first_gid = field_u32(tileset, "firstgid")?;
+            // External tilesets only have a `source`, their tiles are in another file.
+            let tiles = match tileset.get("tiles").and_then(Value::as_array) {
+                Some(tiles) => tiles,
+                None => continue,
+            };
+
+            for tile in tiles {
+                let id = field_u32(tile, "id")?;
+                let properties = match tile.get("properties").and_then(Value::as_array) {
+                    Some(properties) => properties,
+                    None => continue,
+                };
+
+                let mut by_name = Map::new();
+                for property in properties {
+                    let name = property
+                        .get("name")
+                        .and_then(Value::as_str)
+                        .ok_or(TiledError::Malformed { field: "name" })?;
+                    let value = property
+                        .get("value")
+                        .ok_or(TiledError::Malformed { field: "value" })?;
+                    by_name.insert(name.to_owned(), value.clone());
+                }
+                self.properties.insert(first_gid + id, by_name);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the grid with the dimensions of the map.
+    pub fn grid(&self) -> Grid2d {
+        self.grid
+    }
+
+    /// Returns the names of all tile layers, including the ones inside of groups, in the
+    /// order of the map.
+    pub fn layer_names<'a>(&'a self) -> impl Iterator<Item = &'a str> + 'a {
+        self.tile_layers.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Creates a storage with the components `f(tile)` for the non-empty cells of the tile
+    /// layer `name`, skipping the cells for which `f` returns `None`.
+    ///
+    /// The ids are the ones of `grid`, so the cell in column `x` and row `y` of the map ends
+    /// up at `grid().id(x, y)`. If several tile layers have the name, the first one is used.
+    pub fn import_layer<C, D, I, F>(
+        &self,
+        name: &str,
+        mut f: F,
+    ) -> Result<Storage<C, D, I>, TiledError>
+    where
+        D: UnprotectedStorage<C> + Default,
+        I: Id,
+        F: FnMut(TiledTile) -> Option<C>,
+    {
+        let gids = match self.tile_layers.iter().find(|(layer, _)| layer == name) {
+            Some((_, gids)) => gids,
+            None if self.other_layers.iter().any(|layer| layer == name) => {
+                return Err(TiledError::NotATileLayer {
+                    name: name.to_owned(),
+                })
+            }
+            None => {
+                return Err(TiledError::UnknownLayer {
+                    name: name.to_owned(),
+                })
+            }
+        };
+
+        let width = self.grid.width();
+        Ok(Storage::from_fn(&self.grid, |x, y| {
+            let raw = gids[(y * width + x) as usize];
+            let gid = raw & !FLAGS;
+            if gid == 0 {
+                return None;
+            }
+
+            f(TiledTile {
+                gid,
+                flipped_horizontally: raw & FLIPPED_HORIZONTALLY != 0,
+                flipped_vertically: raw & FLIPPED_VERTICALLY != 0,
+                flipped_diagonally: raw & FLIPPED_DIAGONALLY != 0,
+                properties: self.properties.get(&gid),
+            })
+        }))
+    }
+}
+
+fn field_u32(value: &Value, field: &'static str) -> Result<u32, TiledError> {
+    match value.get(field).and_then(Value::as_u64) {
+        Some(n) if n <= u64::from(u32::MAX) => Ok(n as u32),
+        _ => Err(TiledError::Malformed { field }),
+    }
+}
+
+/// Decodes standard Base64 with optional padding, or returns `None` for invalid input.
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len() / 4 * 3);
+    let (mut buffer, mut bits) = (0u32, 0);
+    for c in text.bytes().take_while(|&c| c != b'=') {
+        let sextet = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b' ' | b'\n' | b'\r' | b'\t' => continue,
+            _ => return None,
+        };
+        buffer = buffer << 6 | u32::from(sextet);
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+
+    Some(bytes)
+}
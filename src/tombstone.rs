@@ -0,0 +1,250 @@
+//! Keeping removed components around, so removals can be undone.
+
+use std::collections::{HashMap, VecDeque};
+
+use specs::storage::UnprotectedStorage;
+
+use {Id, Index, Storage};
+
+/// A storage whose `remove` keeps the component as a tombstone instead of dropping it, so
+/// an editor can `restore` it later without routing every removal through its undo system.
+///
+/// Tombstones aren't part of the storage: the occupancy mask and joins over `storage()`
+/// only see the live components. A plain `Storage` doesn't pay anything for this, since
+/// the tombstones live in this wrapper.
+///
+/// The buffer is either unlimited, in which case `purge_tombstones` releases it, or capped
+/// with `with_limit`, dropping the oldest tombstones first. Removing an id which already
+/// has a tombstone replaces it.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::{Component, Join, VecStorage};
+/// use specs_static::TombstoneStorage;
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// #[derive(Debug, PartialEq)]
+/// struct Sign(&'static str);
+///
+/// impl Component for Sign {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// fn main() {
+///     let mut signs = TombstoneStorage::<Sign, VecStorage<_>, TileId>::default();
+///     signs.insert(TileId(1), Sign("north"));
+///     signs.insert(TileId(2), Sign("south"));
+///     signs.insert(TileId(3), Sign("east"));
+///
+///     // Remove, then restore.
+///     assert!(signs.remove(TileId(1)));
+///     assert!(!signs.remove(TileId(1)));
+///     assert_eq!(signs.storage().get(TileId(1)), None);
+///     assert!(signs.is_tombstoned(TileId(1)));
+///     assert_eq!(signs.storage().join().count(), 2);
+///     assert!(signs.restore(TileId(1)));
+///     assert_eq!(signs.storage().get(TileId(1)), Some(&Sign("north")));
+///     assert!(!signs.restore(TileId(1)));
+///
+///     // Something new took the slot, so the tombstone can't come back.
+///     signs.remove(TileId(2));
+///     signs.insert(TileId(2), Sign("west"));
+///     assert!(!signs.restore(TileId(2)));
+///     assert_eq!(signs.storage().get(TileId(2)), Some(&Sign("west")));
+///
+///     // Purging releases everything.
+///     signs.remove(TileId(3));
+///     assert_eq!(signs.tombstone_count(), 2);
+///     signs.purge_tombstones();
+///     assert_eq!(signs.tombstone_count(), 0);
+///     assert!(!signs.restore(TileId(3)));
+///
+///     // A capped buffer drops the oldest tombstones.
+///     let mut signs = TombstoneStorage::<Sign, VecStorage<_>, TileId>::with_limit(2);
+///     for &(id, text) in &[(1, "a"), (2, "b"), (3, "c")] {
+///         signs.insert(TileId(id), Sign(text));
+///         signs.remove(TileId(id));
+///     }
+///     assert!(!signs.restore(TileId(1)));
+///     assert!(signs.restore(TileId(2)));
+///     assert_eq!(signs.take_tombstone(TileId(3)), Some(Sign("c")));
+///
+///     // Replacing a tombstone makes it the newest one.
+///     signs.remove(TileId(2));
+///     signs.insert(TileId(4), Sign("d"));
+///     signs.remove(TileId(4));
+///     signs.insert(TileId(2), Sign("b2"));
+///     signs.remove(TileId(2));
+///     signs.insert(TileId(5), Sign("e"));
+///     signs.remove(TileId(5));
+///     assert!(!signs.is_tombstoned(TileId(4)));
+///     assert_eq!(signs.take_tombstone(TileId(2)), Some(Sign("b2")));
+///
+///     // Erasing a big area and restoring it takes time proportional to its size.
+///     let mut signs = TombstoneStorage::<Sign, VecStorage<_>, TileId>::default();
+///     for id in 0..100_000 {
+///         signs.insert(TileId(id), Sign("x"));
+///         signs.remove(TileId(id));
+///     }
+///     assert!((0..100_000).all(|id| signs.restore(TileId(id))));
+///     assert_eq!(signs.tombstone_count(), 0);
+/// }
+/// ```
+#[derive(Derivative)]
+#[derivative(Default(bound = "D: Default"))]
+pub struct TombstoneStorage<C, D: UnprotectedStorage<C>, I> {
+    storage: Storage<C, D, I>,
+    /// The tombstones with the sequence number of the removal which made them.
+    tombstones: HashMap<Index, (u64, C)>,
+    /// The ids and sequence numbers of the removals, oldest first. Entries whose tombstone
+    /// has been taken or replaced since are skipped and compacted away lazily.
+    order: VecDeque<(Index, u64)>,
+    next_seq: u64,
+    limit: Option<usize>,
+}
+
+impl<C, D, I> TombstoneStorage<C, D, I>
+where
+    D: UnprotectedStorage<C> + Default,
+    I: Id,
+{
+    /// Creates an empty storage keeping at most `limit` tombstones.
+    pub fn with_limit(limit: usize) -> Self {
+        TombstoneStorage::from_storage(Storage::default()).limited(limit)
+    }
+}
+
+impl<C, D, I> TombstoneStorage<C, D, I>
+where
+    D: UnprotectedStorage<C>,
+    I: Id,
+{
+    /// Wraps `storage`, keeping an unlimited amount of tombstones.
+    pub fn from_storage(storage: Storage<C, D, I>) -> Self {
+        TombstoneStorage {
+            storage,
+            tombstones: HashMap::new(),
+            order: VecDeque::new(),
+            next_seq: 0,
+            limit: None,
+        }
+    }
+
+    /// Keeps at most `limit` tombstones, dropping the oldest ones right away if there are
+    /// more.
+    pub fn limited(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self.enforce_limit();
+
+        self
+    }
+
+    /// Returns the live components.
+    pub fn storage(&self) -> &Storage<C, D, I> {
+        &self.storage
+    }
+
+    /// Returns the component at `id` mutably.
+    pub fn get_mut(&mut self, id: I) -> Option<&mut C> {
+        self.storage.get_mut(id)
+    }
+
+    /// Inserts `comp` at `id`, returning the component which was there. A tombstone of `id`
+    /// is kept, but can't be restored until the slot is vacant again.
+    pub fn insert(&mut self, id: I, comp: C) -> Option<C> {
+        self.storage.insert(id, comp)
+    }
+
+    /// Removes the component at `id`, keeping it as a tombstone. Returns `false` if `id`
+    /// was vacant.
+    pub fn remove(&mut self, id: I) -> bool {
+        let comp = match self.storage.remove(id) {
+            Some(comp) => comp,
+            None => return false,
+        };
+
+        let (id, seq) = (id.id(), self.next_seq);
+        self.next_seq += 1;
+        self.tombstones.insert(id, (seq, comp));
+        self.order.push_back((id, seq));
+        self.enforce_limit();
+        self.compact_order();
+
+        true
+    }
+
+    /// Puts the tombstone of `id` back into the storage if the slot is still vacant.
+    /// Returns `false` if there is no tombstone or the slot is occupied, in which case the
+    /// tombstone is kept.
+    pub fn restore(&mut self, id: I) -> bool {
+        if self.storage.get(id).is_some() || !self.tombstones.contains_key(&id.id()) {
+            return false;
+        }
+
+        let comp = self.take_tombstone(id).expect("checked to be there");
+        self.storage.insert(id, comp);
+
+        true
+    }
+
+    /// Removes the tombstone of `id` without restoring it.
+    pub fn take_tombstone(&mut self, id: I) -> Option<C> {
+        let (_, comp) = self.tombstones.remove(&id.id())?;
+        self.compact_order();
+
+        Some(comp)
+    }
+
+    /// Returns `true` if there is a tombstone for `id`.
+    pub fn is_tombstoned(&self, id: I) -> bool {
+        self.tombstones.contains_key(&id.id())
+    }
+
+    /// Returns the amount of tombstones.
+    pub fn tombstone_count(&self) -> usize {
+        self.tombstones.len()
+    }
+
+    /// Drops all tombstones.
+    pub fn purge_tombstones(&mut self) {
+        self.tombstones.clear();
+        self.order.clear();
+    }
+
+    /// Drops the tombstones and returns the live components.
+    pub fn into_storage(self) -> Storage<C, D, I> {
+        self.storage
+    }
+
+    fn enforce_limit(&mut self) {
+        let limit = match self.limit {
+            Some(limit) => limit,
+            None => return,
+        };
+
+        while self.tombstones.len() > limit {
+            let (oldest, seq) = self.order.pop_front().expect("every tombstone is ordered");
+            if is_current(&self.tombstones, oldest, seq) {
+                self.tombstones.remove(&oldest);
+            }
+        }
+    }
+
+    /// Drops the stale entries of `order` once they make up more than half of it, so removing
+    /// and taking tombstones stays amortized constant time.
+    fn compact_order(&mut self) {
+        if self.order.len() > 2 * self.tombstones.len() + 16 {
+            let tombstones = &self.tombstones;
+            self.order.retain(|&(id, seq)| is_current(tombstones, id, seq));
+        }
+    }
+}
+
+/// Returns `true` if the tombstone of `id` is the one the removal numbered `seq` made.
+fn is_current<C>(tombstones: &HashMap<Index, (u64, C)>, id: Index, seq: u64) -> bool {
+    tombstones.get(&id).is_some_and(|&(current, _)| current == seq)
+}
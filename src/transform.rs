@@ -0,0 +1,256 @@
+//! Rotating and mirroring rectangles of a storage in place.
+
+use std::error::Error;
+use std::fmt;
+
+use specs::storage::UnprotectedStorage;
+
+use {Grid2d, Id, Storage};
+
+/// A clockwise rotation of a region.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Rotation {
+    /// A quarter turn clockwise.
+    Cw90,
+    /// A half turn.
+    Cw180,
+    /// Three quarter turns clockwise, which is a quarter turn counterclockwise.
+    Cw270,
+}
+
+/// The axis a region is mirrored along.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Axis {
+    /// Swaps the left and the right side, so `x` is mirrored.
+    Horizontal,
+    /// Swaps the top and the bottom side, so `y` is mirrored.
+    Vertical,
+}
+
+/// The error returned when rotating a region which isn't square by a quarter turn.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct RegionNotSquare {
+    /// The width of the region.
+    pub width: u32,
+    /// The height of the region.
+    pub height: u32,
+}
+
+impl fmt::Display for RegionNotSquare {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "a {}x{} region can't be rotated by a quarter turn in place",
+            self.width, self.height
+        )
+    }
+}
+
+impl Error for RegionNotSquare {}
+
+/// Rotates the tiles with `x0 <= x < x1` and `y0 <= y < y1` of `grid` clockwise.
+///
+/// The components are moved, and vacant tiles are rotated along with the occupied ones.
+/// A quarter turn would change the shape of a region which isn't square, so it is rejected
+/// with `RegionNotSquare` without touching the storage; half turns work for any region.
+///
+/// # Panics
+///
+/// Panics if the rectangle doesn't fit into the grid.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::{Component, VecStorage};
+/// use specs_static::{mirror_region, rotate_region, Axis, Grid2d};
+/// use specs_static::{RegionNotSquare, Rotation, Storage};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// // Not `Clone`, so the components have to be moved.
+/// #[derive(Debug, PartialEq)]
+/// struct Glyph(char);
+///
+/// impl Component for Glyph {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// type Glyphs = Storage<Glyph, VecStorage<Glyph>, TileId>;
+///
+/// fn parse(grid: &Grid2d, rows: &[&str]) -> Glyphs {
+///     let mut glyphs = Glyphs::default();
+///     for (y, row) in rows.iter().enumerate() {
+///         for (x, c) in row.chars().enumerate().filter(|&(_, c)| c != ' ') {
+///             glyphs.insert(grid.id(x as u32, y as u32).unwrap(), Glyph(c));
+///         }
+///     }
+///     glyphs
+/// }
+///
+/// fn render(grid: &Grid2d, glyphs: &Glyphs) -> Vec<String> {
+///     (0..grid.height())
+///         .map(|y| {
+///             (0..grid.width())
+///                 .map(|x| glyphs.get(grid.id(x, y).unwrap()).map_or(' ', |g| g.0))
+///                 .collect()
+///         })
+///         .collect()
+/// }
+///
+/// fn main() {
+///     let grid = Grid2d::new(5, 4);
+///     let map = ["ab c.", "de f.", "ghij.", "....."];
+///
+///     let mut glyphs = parse(&grid, &map);
+///     rotate_region(&mut glyphs, &grid, 0, 0, 3, 3, Rotation::Cw90).unwrap();
+///     assert_eq!(render(&grid, &glyphs), vec!["gdac.", "hebf.", "i  j.", "....."]);
+///
+///     let mut glyphs = parse(&grid, &map);
+///     rotate_region(&mut glyphs, &grid, 0, 0, 4, 2, Rotation::Cw180).unwrap();
+///     assert_eq!(render(&grid, &glyphs), vec!["f ed.", "c ba.", "ghij.", "....."]);
+///
+///     let mut glyphs = parse(&grid, &map);
+///     mirror_region(&mut glyphs, &grid, 1, 0, 4, 3, Axis::Horizontal);
+///     assert_eq!(render(&grid, &glyphs), vec!["ac b.", "df e.", "gjih.", "....."]);
+///     mirror_region(&mut glyphs, &grid, 0, 1, 5, 4, Axis::Vertical);
+///     assert_eq!(render(&grid, &glyphs), vec!["ac b.", ".....", "gjih.", "df e."]);
+///
+///     let error = rotate_region(&mut glyphs, &grid, 0, 0, 4, 2, Rotation::Cw270);
+///     assert_eq!(error, Err(RegionNotSquare { width: 4, height: 2 }));
+///
+///     // Four quarter turns and two mirrorings restore the tiles.
+///     let mut seed = 7u32;
+///     let rows: Vec<String> = (0..4)
+///         .map(|_| {
+///             (0..5)
+///                 .map(|_| {
+///                     seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+///                     b" abcdefghijklmno"[(seed >> 16) as usize % 16] as char
+///                 })
+///                 .collect()
+///         })
+///         .collect();
+///     let rows: Vec<&str> = rows.iter().map(|row| row.as_str()).collect();
+///     let mut glyphs = parse(&grid, &rows);
+///     for &(x0, y0, size) in &[(0, 0, 4), (1, 0, 4), (2, 1, 3), (3, 3, 1), (1, 1, 0)] {
+///         for _ in 0..4 {
+///             rotate_region(&mut glyphs, &grid, x0, y0, x0 + size, y0 + size, Rotation::Cw90)
+///                 .unwrap();
+///         }
+///         rotate_region(&mut glyphs, &grid, x0, y0, 5, 4, Rotation::Cw180).unwrap();
+///         rotate_region(&mut glyphs, &grid, x0, y0, 5, 4, Rotation::Cw180).unwrap();
+///         for &axis in &[Axis::Horizontal, Axis::Vertical] {
+///             mirror_region(&mut glyphs, &grid, x0, y0, 5, 4, axis);
+///             mirror_region(&mut glyphs, &grid, x0, y0, 5, 4, axis);
+///         }
+///         assert_eq!(render(&grid, &glyphs), rows);
+///     }
+/// }
+/// ```
+pub fn rotate_region<C, D, I>(
+    storage: &mut Storage<C, D, I>,
+    grid: &Grid2d,
+    x0: u32,
+    y0: u32,
+    x1: u32,
+    y1: u32,
+    rotation: Rotation,
+) -> Result<(), RegionNotSquare>
+where
+    D: UnprotectedStorage<C>,
+    I: Id,
+{
+    check_rect(grid, x0, y0, x1, y1);
+    let (width, height) = (x1 - x0, y1 - y0);
+    if rotation != Rotation::Cw180 && width != height {
+        return Err(RegionNotSquare { width, height });
+    }
+
+    permute(storage, grid, x0, y0, x1, y1, |x, y| match rotation {
+        Rotation::Cw90 => (height - 1 - y, x),
+        Rotation::Cw180 => (width - 1 - x, height - 1 - y),
+        Rotation::Cw270 => (y, width - 1 - x),
+    });
+
+    Ok(())
+}
+
+/// Mirrors the tiles with `x0 <= x < x1` and `y0 <= y < y1` of `grid` along `axis`.
+///
+/// The components are moved, and vacant tiles are mirrored along with the occupied ones.
+/// See `rotate_region` for an example.
+///
+/// # Panics
+///
+/// Panics if the rectangle doesn't fit into the grid.
+pub fn mirror_region<C, D, I>(
+    storage: &mut Storage<C, D, I>,
+    grid: &Grid2d,
+    x0: u32,
+    y0: u32,
+    x1: u32,
+    y1: u32,
+    axis: Axis,
+) where
+    D: UnprotectedStorage<C>,
+    I: Id,
+{
+    check_rect(grid, x0, y0, x1, y1);
+    let (width, height) = (x1 - x0, y1 - y0);
+
+    permute(storage, grid, x0, y0, x1, y1, |x, y| match axis {
+        Axis::Horizontal => (width - 1 - x, y),
+        Axis::Vertical => (x, height - 1 - y),
+    });
+}
+
+fn check_rect(grid: &Grid2d, x0: u32, y0: u32, x1: u32, y1: u32) {
+    assert!(
+        x0 <= x1 && y0 <= y1 && x1 <= grid.width() && y1 <= grid.height(),
+        "the rectangle ({}, {})..({}, {}) doesn't fit into the {}x{} grid",
+        x0,
+        y0,
+        x1,
+        y1,
+        grid.width(),
+        grid.height()
+    );
+}
+
+/// Moves the tile at `(x0 + x, y0 + y)` to `(x0, y0) + to(x, y)` for every tile of the
+/// rectangle. `to` has to be a permutation of the rectangle.
+fn permute<C, D, I, F>(
+    storage: &mut Storage<C, D, I>,
+    grid: &Grid2d,
+    x0: u32,
+    y0: u32,
+    x1: u32,
+    y1: u32,
+    to: F,
+) where
+    D: UnprotectedStorage<C>,
+    I: Id,
+    F: Fn(u32, u32) -> (u32, u32),
+{
+    let id = |x, y| grid.id::<I>(x0 + x, y0 + y).expect("checked to fit into the grid");
+    let (width, height) = (x1 - x0, y1 - y0);
+
+    let mut moved = Vec::with_capacity(width as usize * height as usize);
+    for y in 0..height {
+        for x in 0..width {
+            moved.push(storage.remove(id(x, y)));
+        }
+    }
+
+    let mut moved = moved.into_iter();
+    for y in 0..height {
+        for x in 0..width {
+            if let Some(comp) = moved.next().and_then(|comp| comp) {
+                let (to_x, to_y) = to(x, y);
+                storage.insert(id(to_x, to_y), comp);
+            }
+        }
+    }
+}
@@ -0,0 +1,403 @@
+//! Undo and redo for edits of tile storages.
+
+use std::any::Any;
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+
+use specs::{Component, World};
+
+use fetch::check_registered;
+use {Id, Storage};
+
+/// The component a tile held, or `None` if it was vacant.
+type Value = Option<Box<dyn Any + Send + Sync>>;
+
+/// Puts a value into a tile of the layer, returning the value it held before.
+type Swap = fn(&World, u32, Value) -> Value;
+
+fn swap<C, I>(world: &World, id: u32, value: Value) -> Value
+where
+    C: Component + Send + Sync,
+    I: Id,
+{
+    let mut storage = world.fetch_mut::<Storage<C, C::Storage, I>>();
+    let id = I::from_u32(id);
+    let old = match value {
+        Some(value) => {
+            let comp = value.downcast::<C>().expect("undo value of the wrong type");
+            storage.insert(id, *comp)
+        }
+        None => storage.remove(id),
+    };
+
+    old.map(|old| Box::new(old) as Box<dyn Any + Send + Sync>)
+}
+
+/// One edited tile, together with the value it doesn't hold right now: before the edit while
+/// the step is on the undo side, the edited one while it's on the redo side.
+struct Edit {
+    swap: Swap,
+    id: u32,
+    value: Value,
+}
+
+/// The edits of one transaction, in the order they were applied.
+struct Step {
+    edits: Vec<Edit>,
+}
+
+impl Step {
+    fn undo(&mut self, world: &World) {
+        for edit in self.edits.iter_mut().rev() {
+            edit.value = (edit.swap)(world, edit.id, edit.value.take());
+        }
+    }
+
+    fn redo(&mut self, world: &World) {
+        for edit in self.edits.iter_mut() {
+            edit.value = (edit.swap)(world, edit.id, edit.value.take());
+        }
+    }
+}
+
+/// Undo and redo for edits of the tile storages keyed by `I`, like the history of an editor.
+///
+/// Edits go through an `EditTransaction` opened with `transaction`, which applies them
+/// right away and keeps whatever they replaced. Everything a transaction did, across any
+/// number of tiles and layers, is one step for `undo` and `redo`. Nothing is cloned for
+/// inserts and removals: undoing moves the old components back into the storages and the
+/// newer ones into the stack, which redoing reverses. Only `modify` clones, to keep the
+/// value from before the change.
+///
+/// At most `depth` steps are kept, dropping the oldest ones. Committing a new step discards
+/// the steps which have been undone, since they don't follow from the new state anymore.
+///
+/// The components are applied to the `Storage<C, C::Storage, I>` resources of the world
+/// given to `undo` and `redo`. Editing the layers in other ways between the steps doesn't
+/// break the stack, but undoing only restores the tiles the transactions touched.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::{Component, VecStorage, World, WorldExt};
+/// use specs_static::{Storage, TileWorldExt, UndoStack};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// #[derive(Clone, Debug, PartialEq)]
+/// struct Wall(u8);
+///
+/// impl Component for Wall {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// #[derive(Clone, Debug, PartialEq)]
+/// struct Door;
+///
+/// impl Component for Door {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// fn map(world: &World) -> (Vec<u32>, Vec<u32>) {
+///     let walls = world.read_resource::<Storage<Wall, VecStorage<Wall>, TileId>>();
+///     let doors = world.read_resource::<Storage<Door, VecStorage<Door>, TileId>>();
+///     (
+///         (0..10).filter(|&id| walls.get(TileId(id)).is_some()).collect(),
+///         (0..10).filter(|&id| doors.get(TileId(id)).is_some()).collect(),
+///     )
+/// }
+///
+/// fn main() {
+///     let mut world = World::new();
+///     world.register_tile_comp::<Wall, TileId>();
+///     world.register_tile_comp::<Door, TileId>();
+///     let mut history = UndoStack::<TileId>::new(100);
+///
+///     // Drawing a wall is one step, however many tiles it covers.
+///     {
+///         let mut tx = history.transaction(&world);
+///         for id in 0..5 {
+///             tx.insert(TileId(id), Wall(1));
+///         }
+///     }
+///     // Putting a door into it edits two layers in one step.
+///     {
+///         let mut tx = history.transaction(&world);
+///         tx.remove::<Wall>(TileId(2));
+///         tx.insert(TileId(2), Door);
+///         tx.modify(TileId(3), |wall: &mut Wall| wall.0 = 2);
+///     }
+///     let with_door = (vec![0, 1, 3, 4], vec![2]);
+///     assert_eq!(map(&world), with_door);
+///
+///     // Undo, redo, undo.
+///     assert!(history.undo(&world));
+///     assert_eq!(map(&world), (vec![0, 1, 2, 3, 4], vec![]));
+///     assert!(history.redo(&world));
+///     assert_eq!(map(&world), with_door);
+///     {
+///         let walls = world.read_resource::<Storage<Wall, VecStorage<Wall>, TileId>>();
+///         assert_eq!(walls.get(TileId(3)), Some(&Wall(2)));
+///     }
+///     assert!(history.undo(&world));
+///     assert!(history.undo(&world));
+///     assert_eq!(map(&world), (vec![], vec![]));
+///     assert!(!history.undo(&world));
+///
+///     // Editing after undoing drops the undone steps.
+///     assert!(history.redo(&world));
+///     assert_eq!(history.redo_len(), 1);
+///     history.transaction(&world).remove::<Wall>(TileId(0));
+///     assert_eq!(history.redo_len(), 0);
+///     assert!(!history.redo(&world));
+///     assert_eq!(map(&world), (vec![1, 2, 3, 4], vec![]));
+///
+///     assert!(history.undo(&world));
+///     assert!(history.undo(&world));
+///     assert_eq!(map(&world), (vec![], vec![]));
+/// }
+/// ```
+pub struct UndoStack<I> {
+    undo: VecDeque<Step>,
+    redo: Vec<Step>,
+    depth: usize,
+    phantom: PhantomData<I>,
+}
+
+impl<I> UndoStack<I>
+where
+    I: Id,
+{
+    /// Creates an empty stack which keeps the last `depth` steps.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, VecStorage, World, WorldExt};
+    /// use specs_static::{Storage, TileWorldExt, UndoStack};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// struct Crop(u8);
+    ///
+    /// impl Component for Crop {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut world = World::new();
+    ///     world.register_tile_comp::<Crop, TileId>();
+    ///     let mut history = UndoStack::<TileId>::new(2);
+    ///     for id in 0..3 {
+    ///         history.transaction(&world).insert(TileId(id), Crop(1));
+    ///     }
+    ///     assert_eq!(history.undo_len(), 2);
+    ///
+    ///     // The first step has been dropped, so its crop stays.
+    ///     while history.undo(&world) {}
+    ///     let crops = world.read_resource::<Storage<Crop, VecStorage<Crop>, TileId>>();
+    ///     assert!(crops.get(TileId(0)).is_some());
+    ///     assert!(crops.get(TileId(1)).is_none());
+    /// }
+    /// ```
+    pub fn new(depth: usize) -> Self {
+        UndoStack {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            depth,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns how many steps are kept at most.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Changes how many steps are kept at most, dropping the oldest ones beyond `depth`.
+    pub fn set_depth(&mut self, depth: usize) {
+        self.depth = depth;
+        self.trim();
+    }
+
+    /// Opens a transaction which applies edits to the storages of `world`. Its edits become
+    /// one step once it's committed or dropped.
+    pub fn transaction<'a>(&'a mut self, world: &'a World) -> EditTransaction<'a, I> {
+        EditTransaction {
+            stack: self,
+            world,
+            edits: Vec::new(),
+        }
+    }
+
+    /// Reverts the latest step, returning `false` if there is nothing to undo.
+    pub fn undo(&mut self, world: &World) -> bool {
+        match self.undo.pop_back() {
+            Some(mut step) => {
+                step.undo(world);
+                self.redo.push(step);
+
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Applies the latest undone step again, returning `false` if there is nothing to redo.
+    pub fn redo(&mut self, world: &World) -> bool {
+        match self.redo.pop() {
+            Some(mut step) => {
+                step.redo(world);
+                self.undo.push_back(step);
+
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns how many steps can be undone.
+    pub fn undo_len(&self) -> usize {
+        self.undo.len()
+    }
+
+    /// Returns how many steps can be redone.
+    pub fn redo_len(&self) -> usize {
+        self.redo.len()
+    }
+
+    /// Forgets all steps, without changing the storages.
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+impl<I> UndoStack<I> {
+    fn push(&mut self, step: Step) {
+        self.redo.clear();
+        self.undo.push_back(step);
+        self.trim();
+    }
+
+    fn trim(&mut self) {
+        while self.undo.len() > self.depth {
+            self.undo.pop_front();
+        }
+    }
+}
+
+/// Applies edits to tile storages and records them as one step of an `UndoStack`.
+///
+/// The step is pushed when the transaction is committed or dropped, unless it's empty; an
+/// empty transaction doesn't discard the steps which can be redone either. `rollback`
+/// reverts the edits instead. The storages are fetched for every edit, so none of them may
+/// be borrowed mutably while editing.
+///
+/// # Panics
+///
+/// The edit methods panic if the component hasn't been registered with
+/// `TileWorldExt::register_tile_comp`.
+pub struct EditTransaction<'a, I: 'a> {
+    stack: &'a mut UndoStack<I>,
+    world: &'a World,
+    edits: Vec<Edit>,
+}
+
+impl<'a, I> EditTransaction<'a, I>
+where
+    I: Id,
+{
+    /// Inserts `comp` at `id`.
+    pub fn insert<C>(&mut self, id: I, comp: C)
+    where
+        C: Component + Send + Sync,
+    {
+        self.apply::<C>(id, Some(Box::new(comp)));
+    }
+
+    /// Removes the component at `id`, returning `false` if there was none.
+    pub fn remove<C>(&mut self, id: I) -> bool
+    where
+        C: Component + Send + Sync,
+    {
+        check_registered::<C, I>(self.world);
+        if self.world.fetch::<Storage<C, C::Storage, I>>().get(id).is_none() {
+            return false;
+        }
+        self.apply::<C>(id, None);
+
+        true
+    }
+
+    /// Changes the component at `id` with `f`, keeping a clone of it for undoing. Returns
+    /// `false` without calling `f` if `id` is vacant.
+    pub fn modify<C, F>(&mut self, id: I, f: F) -> bool
+    where
+        C: Component + Clone + Send + Sync,
+        F: FnOnce(&mut C),
+    {
+        check_registered::<C, I>(self.world);
+        let mut storage = self.world.fetch_mut::<Storage<C, C::Storage, I>>();
+        let comp = match storage.get_mut(id) {
+            Some(comp) => comp,
+            None => return false,
+        };
+        let old = comp.clone();
+        f(comp);
+        self.edits.push(Edit {
+            swap: swap::<C, I>,
+            id: id.id(),
+            value: Some(Box::new(old)),
+        });
+
+        true
+    }
+
+    /// Returns how many edits the transaction has applied.
+    pub fn len(&self) -> usize {
+        self.edits.len()
+    }
+
+    /// Returns `true` if the transaction hasn't applied any edits.
+    pub fn is_empty(&self) -> bool {
+        self.edits.is_empty()
+    }
+
+    /// Pushes the edits as one step, the same as dropping the transaction.
+    pub fn commit(self) {}
+
+    /// Reverts the edits of the transaction, without pushing a step.
+    pub fn rollback(mut self) {
+        let mut step = Step {
+            edits: self.edits.drain(..).collect(),
+        };
+        step.undo(self.world);
+    }
+
+    fn apply<C>(&mut self, id: I, value: Value)
+    where
+        C: Component + Send + Sync,
+    {
+        check_registered::<C, I>(self.world);
+        let old = swap::<C, I>(self.world, id.id(), value);
+        self.edits.push(Edit {
+            swap: swap::<C, I>,
+            id: id.id(),
+            value: old,
+        });
+    }
+}
+
+impl<'a, I> Drop for EditTransaction<'a, I> {
+    fn drop(&mut self) {
+        if !self.edits.is_empty() {
+            let edits = self.edits.drain(..).collect();
+            self.stack.push(Step { edits });
+        }
+    }
+}
@@ -0,0 +1,142 @@
+//! Deferred modifications of tile storages, applied at a synchronization point.
+
+use std::marker::PhantomData;
+use std::mem;
+use std::sync::Mutex;
+
+use shred::FetchMut;
+use specs::{Component, World};
+
+use {Id, Storage};
+
+type Update = Box<dyn FnOnce(&mut World) + Send>;
+
+/// A queue of modifications to the tile storages of id type `I`, like specs' `LazyUpdate`.
+///
+/// Systems only need to `Read` this resource to queue updates, so any number of them can
+/// run in parallel even if they all modify the same layer. The updates are applied in the
+/// order they were queued by `TileWorldExt::maintain_tiles`.
+///
+/// Components are inserted into and removed from the `Storage<C, C::Storage, I>` resource,
+/// which is what `TileWorldExt::register_tile_comp` registers. If there is none yet, a default
+/// one is added.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::prelude::*;
+/// use specs_static::{Storage, TileUpdates, TileWorldExt};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// #[derive(Debug, PartialEq)]
+/// struct Fire(u32);
+///
+/// impl Component for Fire {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// struct Ignite(u32);
+///
+/// impl<'a> System<'a> for Ignite {
+///     type SystemData = Read<'a, TileUpdates<TileId>>;
+///
+///     fn run(&mut self, updates: Self::SystemData) {
+///         for i in 0..100 {
+///             updates.insert(TileId(self.0 + i * 2), Fire(self.0));
+///         }
+///         updates.remove::<Fire>(TileId(1000));
+///     }
+/// }
+///
+/// fn main() {
+///     let mut world = World::new();
+///     world.register_tile_comp::<Fire, TileId>();
+///     world
+///         .write_resource::<Storage<Fire, VecStorage<_>, TileId>>()
+///         .insert(TileId(1000), Fire(9));
+///
+///     let mut dispatcher = DispatcherBuilder::new()
+///         .with(Ignite(0), "ignite_even", &[])
+///         .with(Ignite(1), "ignite_odd", &[])
+///         .build();
+///     dispatcher.setup(&mut world);
+///     dispatcher.dispatch(&world);
+///
+///     // Nothing has been applied yet.
+///     {
+///         let fires = world.read_resource::<Storage<Fire, VecStorage<_>, TileId>>();
+///         assert_eq!(fires.get(TileId(0)), None);
+///     }
+///
+///     world.maintain_tiles::<TileId>();
+///
+///     let fires = world.read_resource::<Storage<Fire, VecStorage<_>, TileId>>();
+///     assert_eq!((&*fires).join().count(), 200);
+///     assert_eq!(fires.get(TileId(8)), Some(&Fire(0)));
+///     assert_eq!(fires.get(TileId(9)), Some(&Fire(1)));
+///     assert_eq!(fires.get(TileId(1000)), None);
+/// }
+/// ```
+#[derive(Derivative)]
+#[derivative(Default(bound = ""))]
+pub struct TileUpdates<I> {
+    queue: Mutex<Vec<Update>>,
+    phantom: PhantomData<I>,
+}
+
+impl<I> TileUpdates<I>
+where
+    I: Id,
+{
+    /// Queues inserting `comp` at `id`.
+    pub fn insert<C>(&self, id: I, comp: C)
+    where
+        C: Component + Send + Sync,
+        C::Storage: Default,
+    {
+        self.exec(move |world| {
+            storage::<C, I>(world).insert(id, comp);
+        });
+    }
+
+    /// Queues removing the component of type `C` at `id`.
+    pub fn remove<C>(&self, id: I)
+    where
+        C: Component + Send + Sync,
+        C::Storage: Default,
+    {
+        self.exec(move |world| {
+            storage::<C, I>(world).remove(id);
+        });
+    }
+
+    /// Queues an arbitrary modification of the world.
+    pub fn exec<F>(&self, f: F)
+    where
+        F: FnOnce(&mut World) + Send + 'static,
+    {
+        self.queue.lock().expect("tile update queue poisoned").push(Box::new(f));
+    }
+
+    /// Returns `true` if no updates are queued.
+    pub fn is_empty(&self) -> bool {
+        self.queue.lock().expect("tile update queue poisoned").is_empty()
+    }
+
+    /// Takes all queued updates out of the queue.
+    pub(crate) fn take(&self) -> Vec<Update> {
+        mem::take(&mut *self.queue.lock().expect("tile update queue poisoned"))
+    }
+}
+
+fn storage<'a, C, I>(world: &'a mut World) -> FetchMut<'a, Storage<C, C::Storage, I>>
+where
+    C: Component + Send + Sync,
+    C::Storage: Default,
+    I: Id,
+{
+    world.entry().or_insert_with(Default::default)
+}
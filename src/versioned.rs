@@ -0,0 +1,162 @@
+//! Generation-checked handles for storages whose tiles get recycled.
+
+use specs::storage::UnprotectedStorage;
+
+use {Id, Storage};
+
+/// A handle to the component at `id`, which is only valid as long as the component hasn't
+/// been removed, even if a new one has been inserted at the same id since.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct VersionedId<I> {
+    /// The id of the tile.
+    pub id: I,
+    /// The generation of the slot when the handle was issued.
+    pub gen: u32,
+}
+
+/// A storage which counts the removals of every slot, so the `VersionedId`s it hands out
+/// become stale once their component is removed.
+///
+/// This is opt-in: a plain `Storage` doesn't do any liveness checks, and isn't changed by
+/// this wrapper existing. `storage()` gives unversioned read access for joins.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::{Component, Join, VecStorage};
+/// use specs_static::VersionedStorage;
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// #[derive(Debug, PartialEq)]
+/// struct Building(&'static str);
+///
+/// impl Component for Building {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// fn main() {
+///     let mut buildings = VersionedStorage::<Building, VecStorage<_>, TileId>::default();
+///     let (mill, _) = buildings.insert(TileId(7), Building("mill"));
+///     assert_eq!(buildings.get(mill), Some(&Building("mill")));
+///
+///     // Demolish the mill and build a tower in its place.
+///     assert_eq!(buildings.remove(mill), Some(Building("mill")));
+///     let (tower, _) = buildings.insert(TileId(7), Building("tower"));
+///     assert_eq!(tower.gen, mill.gen + 1);
+///
+///     // The cached handle of the mill doesn't see the tower.
+///     assert_eq!(buildings.get(mill), None);
+///     assert!(!buildings.is_current(mill));
+///     assert_eq!(buildings.remove(mill), None);
+///     assert_eq!(buildings.get(tower), Some(&Building("tower")));
+///     assert_eq!(buildings.handle(TileId(7)), Some(tower));
+///
+///     // Replacing a component in place keeps the handle valid.
+///     let (same, old) = buildings.insert(TileId(7), Building("keep"));
+///     assert_eq!((same, old), (tower, Some(Building("tower"))));
+///     buildings.get_mut(tower).unwrap().0 = "castle";
+///
+///     let names: Vec<_> = buildings.storage().join().map(|b| b.0).collect();
+///     assert_eq!(names, vec!["castle"]);
+/// }
+/// ```
+#[derive(Derivative)]
+#[derivative(Default(bound = "D: Default"))]
+pub struct VersionedStorage<C, D: UnprotectedStorage<C>, I> {
+    storage: Storage<C, D, I>,
+    /// The generation of every slot, missing ones being 0.
+    gens: Vec<u32>,
+}
+
+impl<C, D, I> VersionedStorage<C, D, I>
+where
+    D: UnprotectedStorage<C>,
+    I: Id,
+{
+    /// Wraps `storage`, handing out generation 0 for all of its slots.
+    pub fn from_storage(storage: Storage<C, D, I>) -> Self {
+        VersionedStorage {
+            storage,
+            gens: Vec::new(),
+        }
+    }
+
+    /// Returns the components for unversioned access, like joins.
+    pub fn storage(&self) -> &Storage<C, D, I> {
+        &self.storage
+    }
+
+    /// Returns the current generation of the slot `id`.
+    pub fn generation(&self, id: I) -> u32 {
+        self.gens.get(id.id() as usize).cloned().unwrap_or(0)
+    }
+
+    /// Returns the handle of the component at `id`, or `None` if the slot is vacant.
+    pub fn handle(&self, id: I) -> Option<VersionedId<I>> {
+        self.storage.get(id)?;
+
+        Some(VersionedId {
+            id,
+            gen: self.generation(id),
+        })
+    }
+
+    /// Returns `true` if the component `handle` was issued for is still there.
+    pub fn is_current(&self, handle: VersionedId<I>) -> bool {
+        self.handle(handle.id) == Some(handle)
+    }
+
+    /// Returns the component of `handle`, or `None` if it has been removed.
+    pub fn get(&self, handle: VersionedId<I>) -> Option<&C> {
+        match self.is_current(handle) {
+            true => self.storage.get(handle.id),
+            false => None,
+        }
+    }
+
+    /// Returns the component of `handle` mutably, or `None` if it has been removed.
+    pub fn get_mut(&mut self, handle: VersionedId<I>) -> Option<&mut C> {
+        match self.is_current(handle) {
+            true => self.storage.get_mut(handle.id),
+            false => None,
+        }
+    }
+
+    /// Inserts `comp` at `id`, returning its handle and the component which was there.
+    ///
+    /// Replacing a component doesn't change the generation, so existing handles of `id`
+    /// stay valid and refer to the new component.
+    pub fn insert(&mut self, id: I, comp: C) -> (VersionedId<I>, Option<C>) {
+        let old = self.storage.insert(id, comp);
+        let handle = VersionedId {
+            id,
+            gen: self.generation(id),
+        };
+
+        (handle, old)
+    }
+
+    /// Removes the component of `handle`, which makes all handles of it stale. Returns
+    /// `None` without touching the storage if `handle` is stale already.
+    pub fn remove(&mut self, handle: VersionedId<I>) -> Option<C> {
+        if !self.is_current(handle) {
+            return None;
+        }
+
+        let index = handle.id.id() as usize;
+        if self.gens.len() <= index {
+            self.gens.resize(index + 1, 0);
+        }
+        self.gens[index] = self.gens[index].wrapping_add(1);
+
+        self.storage.remove(handle.id)
+    }
+
+    /// Returns the components, dropping the generations.
+    pub fn into_storage(self) -> Storage<C, D, I> {
+        self.storage
+    }
+}
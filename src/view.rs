@@ -0,0 +1,305 @@
+//! Views of a storage restricted to a rectangle of the grid.
+
+use std::marker::PhantomData;
+
+use hibitset::{BitIter, BitSet, BitSetAnd, BitSetLike};
+use specs::storage::UnprotectedStorage;
+
+use {Grid2d, Id, RangeMask, Storage};
+
+/// The rectangle `x0 <= x < x1`, `y0 <= y < y1` of a grid.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Rect {
+    grid: Grid2d,
+    x0: u32,
+    y0: u32,
+    x1: u32,
+    y1: u32,
+}
+
+impl Rect {
+    pub(crate) fn new(grid: &Grid2d, x0: u32, y0: u32, x1: u32, y1: u32) -> Self {
+        assert!(
+            x0 <= x1 && y0 <= y1 && x1 <= grid.width() && y1 <= grid.height(),
+            "the rectangle ({}, {})..({}, {}) doesn't fit into the {}x{} grid",
+            x0,
+            y0,
+            x1,
+            y1,
+            grid.width(),
+            grid.height()
+        );
+
+        Rect {
+            grid: *grid,
+            x0,
+            y0,
+            x1,
+            y1,
+        }
+    }
+
+    fn contains<I: Id>(&self, id: I) -> bool {
+        match self.grid.coords(id) {
+            Some((x, y)) => self.x0 <= x && x < self.x1 && self.y0 <= y && y < self.y1,
+            None => false,
+        }
+    }
+
+    fn row(&self, y: u32) -> RangeMask {
+        let start = y * self.grid.width();
+
+        RangeMask::from_range(start + self.x0, start + self.x1)
+    }
+
+    /// Returns the mask of all ids inside of the rectangle.
+    pub(crate) fn mask(&self) -> BitSet {
+        let mut mask = BitSet::new();
+        for y in self.y0..self.y1 {
+            for id in self.row(y).iter() {
+                mask.add(id);
+            }
+        }
+
+        mask
+    }
+
+    fn ids<'a, I>(&self, mask: &'a BitSet) -> ViewIds<'a, I> {
+        ViewIds {
+            rect: *self,
+            mask,
+            y: self.y0,
+            row: None,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<C, D, I> Storage<C, D, I>
+where
+    D: UnprotectedStorage<C>,
+    I: Id,
+{
+    /// Borrows the tiles with `x0 <= x < x1` and `y0 <= y < y1` of `grid`.
+    ///
+    /// See `view_mut` for details.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the rectangle doesn't fit into the grid.
+    pub fn view<'a>(
+        &'a self,
+        grid: &Grid2d,
+        x0: u32,
+        y0: u32,
+        x1: u32,
+        y1: u32,
+    ) -> StorageView<'a, C, D, I> {
+        StorageView {
+            storage: self,
+            rect: Rect::new(grid, x0, y0, x1, y1),
+        }
+    }
+
+    /// Borrows the tiles with `x0 <= x < x1` and `y0 <= y < y1` of `grid` mutably, so the
+    /// borrower can't change any tile outside of that rectangle.
+    ///
+    /// The view is addressed with the same ids as the storage; ids outside the rectangle are
+    /// treated as vacant by the getters and rejected by `insert`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the rectangle doesn't fit into the grid.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, VecStorage};
+    /// use specs_static::{Grid2d, Storage, StorageViewMut};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// #[derive(Debug, PartialEq)]
+    /// struct Room(u32);
+    ///
+    /// impl Component for Room {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// type Rooms = Storage<Room, VecStorage<Room>, TileId>;
+    ///
+    /// // A world generation stage which only knows about its own area.
+    /// fn carve(mut area: StorageViewMut<Room, VecStorage<Room>, TileId>, grid: &Grid2d) {
+    ///     for y in 0..grid.height() {
+    ///         for x in 0..grid.width() {
+    ///             // Ids outside the area are handed back.
+    ///             let _ = area.insert(grid.id(x, y).unwrap(), Room(1));
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// fn main() {
+    ///     let grid = Grid2d::new(8, 8);
+    ///     let id = |x, y| grid.id::<TileId>(x, y).unwrap();
+    ///     let mut rooms = Rooms::default();
+    ///     rooms.insert(id(0, 0), Room(0));
+    ///
+    ///     carve(rooms.view_mut(&grid, 2, 1, 5, 3), &grid);
+    ///     assert_eq!(rooms.view(&grid, 0, 0, 8, 8).ids().count(), 7);
+    ///     assert_eq!(rooms.get(id(2, 1)), Some(&Room(1)));
+    ///     assert_eq!(rooms.get(id(4, 2)), Some(&Room(1)));
+    ///     assert_eq!(rooms.get(id(5, 2)), None);
+    ///     assert_eq!(rooms.get(id(2, 3)), None);
+    ///
+    ///     let mut area = rooms.view_mut(&grid, 1, 0, 3, 2);
+    ///     assert_eq!(area.insert(id(0, 0), Room(2)), Err(Room(2)));
+    ///     assert_eq!(area.get(id(0, 0)), None);
+    ///     assert_eq!(area.remove(id(0, 0)), None);
+    ///     assert_eq!(area.ids().collect::<Vec<_>>(), vec![id(2, 1)]);
+    ///     assert_eq!(area.remove(id(2, 1)), Some(Room(1)));
+    ///     assert_eq!(area.ids().count(), 0);
+    ///
+    ///     let view = rooms.view(&grid, 0, 0, 8, 2);
+    ///     assert_eq!(view.ids().collect::<Vec<_>>(), vec![id(0, 0), id(3, 1), id(4, 1)]);
+    /// }
+    /// ```
+    pub fn view_mut<'a>(
+        &'a mut self,
+        grid: &Grid2d,
+        x0: u32,
+        y0: u32,
+        x1: u32,
+        y1: u32,
+    ) -> StorageViewMut<'a, C, D, I> {
+        StorageViewMut {
+            storage: self,
+            rect: Rect::new(grid, x0, y0, x1, y1),
+        }
+    }
+}
+
+/// A shared view of a rectangle of a storage, created with `Storage::view`.
+pub struct StorageView<'a, C: 'a, D: 'a + UnprotectedStorage<C>, I: 'a> {
+    storage: &'a Storage<C, D, I>,
+    rect: Rect,
+}
+
+impl<'a, C, D, I> StorageView<'a, C, D, I>
+where
+    D: UnprotectedStorage<C>,
+    I: Id,
+{
+    /// Returns `true` if `id` is inside the rectangle.
+    pub fn contains(&self, id: I) -> bool {
+        self.rect.contains(id)
+    }
+
+    /// Returns the component at `id`, or `None` if `id` is vacant or outside the rectangle.
+    pub fn get(&self, id: I) -> Option<&'a C> {
+        match self.rect.contains(id) {
+            true => self.storage.get(id),
+            false => None,
+        }
+    }
+
+    /// Yields the occupied ids inside the rectangle in ascending order.
+    pub fn ids(&self) -> ViewIds<'a, I> {
+        self.rect.ids(self.storage.mask())
+    }
+}
+
+/// A mutable view of a rectangle of a storage, created with `Storage::view_mut`.
+pub struct StorageViewMut<'a, C: 'a, D: 'a + UnprotectedStorage<C>, I: 'a> {
+    storage: &'a mut Storage<C, D, I>,
+    rect: Rect,
+}
+
+impl<'a, C, D, I> StorageViewMut<'a, C, D, I>
+where
+    D: UnprotectedStorage<C>,
+    I: Id,
+{
+    /// Returns `true` if `id` is inside the rectangle.
+    pub fn contains(&self, id: I) -> bool {
+        self.rect.contains(id)
+    }
+
+    /// Returns the component at `id`, or `None` if `id` is vacant or outside the rectangle.
+    pub fn get(&self, id: I) -> Option<&C> {
+        match self.rect.contains(id) {
+            true => self.storage.get(id),
+            false => None,
+        }
+    }
+
+    /// Returns the component at `id` mutably, or `None` if `id` is vacant or outside the
+    /// rectangle.
+    pub fn get_mut(&mut self, id: I) -> Option<&mut C> {
+        match self.rect.contains(id) {
+            true => self.storage.get_mut(id),
+            false => None,
+        }
+    }
+
+    /// Inserts `comp` at `id` and returns the previous component, or hands `comp` back if
+    /// `id` is outside the rectangle.
+    pub fn insert(&mut self, id: I, comp: C) -> Result<Option<C>, C> {
+        match self.rect.contains(id) {
+            true => Ok(self.storage.insert(id, comp)),
+            false => Err(comp),
+        }
+    }
+
+    /// Removes the component at `id`. Ids outside the rectangle are left alone.
+    pub fn remove(&mut self, id: I) -> Option<C> {
+        match self.rect.contains(id) {
+            true => self.storage.remove(id),
+            false => None,
+        }
+    }
+
+    /// Yields the occupied ids inside the rectangle in ascending order.
+    pub fn ids<'b>(&'b self) -> ViewIds<'b, I> {
+        self.rect.ids(self.storage.mask())
+    }
+
+    /// Reborrows this view as a shared one.
+    pub fn as_view<'b>(&'b self) -> StorageView<'b, C, D, I> {
+        StorageView {
+            storage: self.storage,
+            rect: self.rect,
+        }
+    }
+}
+
+/// The iterator returned by `StorageView::ids` and `StorageViewMut::ids`.
+pub struct ViewIds<'a, I> {
+    rect: Rect,
+    mask: &'a BitSet,
+    y: u32,
+    row: Option<BitIter<BitSetAnd<&'a BitSet, RangeMask>>>,
+    phantom: PhantomData<I>,
+}
+
+impl<'a, I> Iterator for ViewIds<'a, I>
+where
+    I: Id,
+{
+    type Item = I;
+
+    fn next(&mut self) -> Option<I> {
+        loop {
+            if let Some(id) = self.row.as_mut().and_then(|row| row.next()) {
+                return Some(I::from_u32(id));
+            }
+            if self.y >= self.rect.y1 || self.rect.x0 == self.rect.x1 {
+                return None;
+            }
+
+            self.row = Some(BitSetAnd(self.mask, self.rect.row(self.y)).iter());
+            self.y += 1;
+        }
+    }
+}
@@ -0,0 +1,170 @@
+//! Watching a rectangle of a grid for changes.
+
+use std::marker::PhantomData;
+
+use hibitset::{BitSet, BitSetAnd, BitSetLike};
+use specs::storage::ComponentEvent;
+
+use view::Rect;
+use {Grid2d, Id};
+
+/// Collects the changed ids inside of a rectangle of a grid, for example to refresh a panel
+/// showing the selected area only when something in it changes.
+///
+/// A watcher doesn't read any storage on its own; it's fed with the change events of a
+/// tracked storage (`observe`) or with a mask of changed ids (`observe_mask`), and keeps
+/// the changed ids which are inside of its rectangle until `clear` is called. Every watcher
+/// has its own state, so several watchers can observe the same events independently.
+///
+/// # Examples
+///
+/// ```
+/// extern crate hibitset;
+/// extern crate shrev;
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use hibitset::BitSet;
+/// use shrev::ReaderId;
+/// use specs::storage::ComponentEvent;
+/// use specs::{Component, FlaggedStorage, Tracked, VecStorage};
+/// use specs_static::{Grid2d, Id, RegionWatcher, Storage};
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// struct Crop(u8);
+///
+/// impl Component for Crop {
+///     type Storage = FlaggedStorage<Self, VecStorage<Self>>;
+/// }
+///
+/// type Crops = Storage<Crop, FlaggedStorage<Crop, VecStorage<Crop>>, TileId>;
+///
+/// /// Plants a crop at `id` and feeds the events of the frame to fresh watchers.
+/// fn frame(
+///     crops: &mut Crops,
+///     reader: &mut ReaderId<ComponentEvent>,
+///     id: TileId,
+///     watchers: &mut [&mut RegionWatcher<TileId>],
+/// ) {
+///     crops.insert(id, Crop(1));
+///     let events: Vec<_> = crops.channel().read(reader).collect();
+///     for watcher in watchers {
+///         watcher.clear();
+///         watcher.observe(events.iter().cloned());
+///     }
+/// }
+///
+/// fn main() {
+///     let grid = Grid2d::new(64, 64);
+///     let at = |x, y| grid.id(x, y).unwrap();
+///     let mut crops = Crops::default();
+///     let mut reader = crops.channel_mut().register_reader();
+///
+///     let mut selected = RegionWatcher::new(&grid, 10, 10, 20, 20);
+///     let mut corner = RegionWatcher::new(&grid, 0, 0, 4, 4);
+///
+///     // Inside of the selection.
+///     frame(&mut crops, &mut reader, at(12, 15), &mut [&mut selected, &mut corner]);
+///     assert!(selected.changed());
+///     assert_eq!(selected.changed_ids().collect::<Vec<_>>(), vec![at(12, 15)]);
+///     assert!(!corner.changed());
+///
+///     // Outside of the selection, but in the same rows.
+///     frame(&mut crops, &mut reader, at(25, 15), &mut [&mut selected, &mut corner]);
+///     assert!(!selected.changed());
+///
+///     // Moving the selection.
+///     selected.move_to(20, 10, 30, 20);
+///     frame(&mut crops, &mut reader, at(25, 15), &mut [&mut selected, &mut corner]);
+///     assert!(selected.changed());
+///     frame(&mut crops, &mut reader, at(12, 15), &mut [&mut selected, &mut corner]);
+///     assert!(!selected.changed());
+///
+///     frame(&mut crops, &mut reader, at(3, 0), &mut [&mut selected, &mut corner]);
+///     assert!(corner.changed());
+///     assert!(!selected.changed());
+///
+///     // Masks of changed ids, like the dirty ids of a frame, work as well.
+///     let mut dirty = BitSet::new();
+///     dirty.add(at(21, 11).id());
+///     dirty.add(at(40, 40).id());
+///     selected.observe_mask(&dirty);
+///     assert_eq!(selected.changed_ids().collect::<Vec<_>>(), vec![at(21, 11)]);
+/// }
+/// ```
+pub struct RegionWatcher<I> {
+    grid: Grid2d,
+    rect: BitSet,
+    changed: BitSet,
+    phantom: PhantomData<I>,
+}
+
+impl<I> RegionWatcher<I>
+where
+    I: Id,
+{
+    /// Creates a watcher of the tiles with `x0 <= x < x1` and `y0 <= y < y1` of `grid`,
+    /// without any changes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the rectangle doesn't fit into the grid.
+    pub fn new(grid: &Grid2d, x0: u32, y0: u32, x1: u32, y1: u32) -> Self {
+        RegionWatcher {
+            grid: *grid,
+            rect: Rect::new(grid, x0, y0, x1, y1).mask(),
+            changed: BitSet::new(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Watches the tiles with `x0 <= x < x1` and `y0 <= y < y1` instead, forgetting the
+    /// changes collected so far.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the rectangle doesn't fit into the grid.
+    pub fn move_to(&mut self, x0: u32, y0: u32, x1: u32, y1: u32) {
+        self.rect = Rect::new(&self.grid, x0, y0, x1, y1).mask();
+        self.changed.clear();
+    }
+
+    /// Collects the ids of `events` which are inside of the rectangle.
+    pub fn observe<'a, E>(&mut self, events: E)
+    where
+        E: IntoIterator<Item = &'a ComponentEvent>,
+    {
+        for event in events {
+            let id = match *event {
+                ComponentEvent::Inserted(id)
+                | ComponentEvent::Modified(id)
+                | ComponentEvent::Removed(id) => id,
+            };
+            if self.rect.contains(id) {
+                self.changed.add(id);
+            }
+        }
+    }
+
+    /// Collects the ids of `changed` which are inside of the rectangle.
+    pub fn observe_mask<B: BitSetLike>(&mut self, changed: B) {
+        for id in BitSetAnd(changed, &self.rect).iter() {
+            self.changed.add(id);
+        }
+    }
+
+    /// Returns `true` if any id inside of the rectangle changed since the last `clear`.
+    pub fn changed(&self) -> bool {
+        !self.changed.is_empty()
+    }
+
+    /// Returns the changed ids inside of the rectangle, in ascending order.
+    pub fn changed_ids<'a>(&'a self) -> impl Iterator<Item = I> + 'a {
+        (&self.changed).iter().map(I::from_u32)
+    }
+
+    /// Forgets the changes collected so far.
+    pub fn clear(&mut self) {
+        self.changed.clear();
+    }
+}
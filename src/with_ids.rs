@@ -0,0 +1,123 @@
+//! Joining components together with the ids they belong to.
+
+use std::marker::PhantomData;
+
+use specs::storage::UnprotectedStorage;
+#[cfg(feature = "parallel")]
+use specs::ParJoin;
+use specs::Join;
+
+use {Id, Index, Storage};
+
+/// A `Join` adapter yielding the id of every element in front of what `J` yields.
+///
+/// The id is reconstructed from the index with `Id::from_u32`, so it's correct even when
+/// the join is combined with other storages or masks that skip elements.
+///
+/// # Examples
+///
+/// ```
+/// extern crate specs;
+/// extern crate specs_static;
+///
+/// use specs::{Component, VecStorage};
+/// use specs_static::prelude::*;
+///
+/// # specs_static::define_id!(pub struct TileId;);
+/// #[derive(Debug, PartialEq)]
+/// struct Material(&'static str);
+///
+/// impl Component for Material {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Height(u32);
+///
+/// impl Component for Height {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// fn main() {
+///     let mut materials = Storage::<Material, VecStorage<_>, TileId>::default();
+///     let mut heights = Storage::<Height, VecStorage<_>, TileId>::default();
+///     for i in 0..10 {
+///         materials.insert(TileId(i), Material("dirt"));
+///         if i % 3 == 0 {
+///             heights.insert(TileId(i), Height(i * 10));
+///         }
+///     }
+///
+///     let joined: Vec<_> = (materials.with_ids(), &heights).join().collect();
+///     assert_eq!(joined.len(), 4);
+///     for ((id, material), height) in joined {
+///         assert_eq!(material, &Material("dirt"));
+///         assert_eq!(height.0, id.0 * 10);
+///     }
+///
+///     for (id, height) in heights.with_ids_mut().join() {
+///         height.0 += id.0;
+///     }
+///     assert_eq!(heights.get(TileId(9)), Some(&Height(99)));
+/// }
+/// ```
+pub struct WithIds<J, I> {
+    inner: J,
+    phantom: PhantomData<I>,
+}
+
+impl<J, I> WithIds<J, I>
+where
+    J: Join,
+    I: Id,
+{
+    /// Wraps any join over tile ids, for example a `&DenseStorage` or a `&TagStorage`.
+    pub fn new(inner: J) -> Self {
+        WithIds {
+            inner,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<J, I> Join for WithIds<J, I>
+where
+    J: Join,
+    I: Id,
+{
+    type Type = (I, J::Type);
+    type Value = J::Value;
+    type Mask = J::Mask;
+
+    unsafe fn open(self) -> (Self::Mask, Self::Value) {
+        self.inner.open()
+    }
+
+    unsafe fn get(value: &mut Self::Value, id: Index) -> Self::Type {
+        (I::from_u32(id), J::get(value, id))
+    }
+}
+
+#[cfg(feature = "parallel")]
+unsafe impl<J, I> ParJoin for WithIds<J, I>
+where
+    J: ParJoin,
+    I: Id + Send + Sync,
+{
+}
+
+impl<C, D, I> Storage<C, D, I>
+where
+    D: UnprotectedStorage<C>,
+    I: Id,
+{
+    /// Returns a join over `(id, &component)` pairs.
+    pub fn with_ids(&self) -> WithIds<&Self, I> {
+        WithIds::new(self)
+    }
+
+    /// Returns a join over `(id, &mut component)` pairs.
+    pub fn with_ids_mut(&mut self) -> WithIds<&mut Self, I> {
+        WithIds::new(self)
+    }
+}
@@ -0,0 +1,174 @@
+//! Iterating over the ids two or three storages have in common, without `Join`.
+
+use hibitset::{BitSetAnd, BitSetLike};
+use specs::storage::UnprotectedStorage;
+
+use join::JoinMut;
+use {Id, Storage};
+
+macro_rules! and_masks {
+    ($mask:expr) => {
+        $mask
+    };
+    ($mask:expr, $($rest:expr),+) => {
+        BitSetAnd($mask, and_masks!($($rest),+))
+    };
+}
+
+macro_rules! impl_zip {
+    (
+        $(#[$zip_attr:meta])*
+        fn $zip:ident;
+        $(#[$zip_mut_attr:meta])*
+        fn $zip_mut:ident;
+        $($name:ident $comp:ident $backend:ident),*;
+        mut $last:ident $last_comp:ident $last_backend:ident
+    ) => {
+        $(#[$zip_attr])*
+        pub fn $zip<'a, $($comp, $backend,)* $last_comp, $last_backend, I>(
+            $($name: &'a Storage<$comp, $backend, I>,)*
+            $last: &'a Storage<$last_comp, $last_backend, I>,
+        ) -> impl Iterator<Item = (I, $(&'a $comp,)* &'a $last_comp)> + 'a
+        where
+            $($backend: UnprotectedStorage<$comp>,)*
+            $last_backend: UnprotectedStorage<$last_comp>,
+            I: Id,
+        {
+            and_masks!($(&$name.bitset,)* &$last.bitset)
+                .iter()
+                .map(move |id| unsafe {
+                    (I::from_u32(id), $($name.data.get(id),)* $last.data.get(id))
+                })
+        }
+
+        $(#[$zip_mut_attr])*
+        pub fn $zip_mut<'a, $($comp, $backend,)* $last_comp, $last_backend, I>(
+            $($name: &'a Storage<$comp, $backend, I>,)*
+            $last: &'a mut Storage<$last_comp, $last_backend, I>,
+        ) -> impl Iterator<Item = (I, $(&'a $comp,)* &'a mut $last_comp)> + 'a
+        where
+            $($backend: UnprotectedStorage<$comp>,)*
+            $last_backend: UnprotectedStorage<$last_comp>,
+            I: Id,
+        {
+            // The mask yields every id once, so the mutable references never alias.
+            let data = JoinMut::new(&mut $last.data);
+
+            and_masks!($(&$name.bitset,)* &$last.bitset)
+                .iter()
+                .map(move |id| unsafe {
+                    (I::from_u32(id), $($name.data.get(id),)* data.get().get_mut(id))
+                })
+        }
+    };
+}
+
+impl_zip! {
+    /// Returns an iterator over the ids occupied in both `a` and `b`, in ascending order,
+    /// together with their components.
+    ///
+    /// This is the same as joining `(&a, &b)`, but yields the typed ids and doesn't need
+    /// the `Join` trait, which is handy in tools and tests. Only the branches of the
+    /// bitsets which are occupied in both are visited.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate specs;
+    /// extern crate specs_static;
+    ///
+    /// use specs::{Component, DenseVecStorage, HashMapStorage, Join, VecStorage};
+    /// use specs_static::{zip, zip3, zip3_mut, zip_mut, Storage};
+    ///
+    /// # specs_static::define_id!(pub struct TileId;);
+    /// #[derive(Debug, PartialEq)]
+    /// struct Soil(u32);
+    ///
+    /// impl Component for Soil {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// struct Rain(u32);
+    ///
+    /// impl Component for Rain {
+    ///     type Storage = DenseVecStorage<Self>;
+    /// }
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// struct Crop(u32);
+    ///
+    /// impl Component for Crop {
+    ///     type Storage = HashMapStorage<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut seed = 7u32;
+    ///     let mut random = move |len: u32| {
+    ///         seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+    ///         (seed >> 8) % len
+    ///     };
+    ///
+    ///     for round in 0..20 {
+    ///         // From sparse to dense occupancy, over several layers of the bitsets.
+    ///         let density = 1 + round % 5;
+    ///         let mut soil = Storage::<Soil, VecStorage<_>, TileId>::default();
+    ///         let mut rain = Storage::<Rain, DenseVecStorage<_>, TileId>::default();
+    ///         let mut crops = Storage::<Crop, HashMapStorage<_>, TileId>::default();
+    ///         for id in 0..20_000 {
+    ///             if random(6) < density {
+    ///                 soil.insert(TileId(id), Soil(id));
+    ///             }
+    ///             if random(6) < density {
+    ///                 rain.insert(TileId(id), Rain(id * 2));
+    ///             }
+    ///             if random(6) < density {
+    ///                 crops.insert(TileId(id), Crop(0));
+    ///             }
+    ///         }
+    ///
+    ///         let zipped: Vec<_> = zip(&soil, &rain).collect();
+    ///         let joined: Vec<_> = (soil.mask(), &soil, &rain)
+    ///             .join()
+    ///             .map(|(id, soil, rain)| (TileId(id), soil, rain))
+    ///             .collect();
+    ///         assert_eq!(zipped, joined);
+    ///         assert!(zipped.iter().all(|&(id, soil, rain)| soil.0 + rain.0 == id.0 * 3));
+    ///
+    ///         let zipped: Vec<_> = zip3(&soil, &rain, &crops).collect();
+    ///         let joined: Vec<_> = (soil.mask(), &soil, &rain, &crops)
+    ///             .join()
+    ///             .map(|(id, soil, rain, crop)| (TileId(id), soil, rain, crop))
+    ///             .collect();
+    ///         assert_eq!(zipped, joined);
+    ///
+    ///         for (_, soil, crop) in zip_mut(&soil, &mut crops) {
+    ///             crop.0 += soil.0;
+    ///         }
+    ///         for (_, _, rain, crop) in zip3_mut(&soil, &rain, &mut crops) {
+    ///             crop.0 += rain.0;
+    ///         }
+    ///         for (id, crop) in (soil.mask(), &crops).join() {
+    ///             let rain = rain.get(TileId(id)).map_or(0, |rain| rain.0);
+    ///             assert_eq!(crop.0, id + rain);
+    ///         }
+    ///         assert!((&crops, !soil.mask()).join().all(|(crop, _)| crop.0 == 0));
+    ///     }
+    /// }
+    /// ```
+    fn zip;
+    /// Returns an iterator over the ids occupied in both `a` and `b` like `zip`, with
+    /// mutable references to the components of `b`.
+    fn zip_mut;
+    a A DA; mut b B DB
+}
+
+impl_zip! {
+    /// Returns an iterator over the ids occupied in `a`, `b` and `c`, in ascending order,
+    /// together with their components, like `zip`.
+    fn zip3;
+    /// Returns an iterator over the ids occupied in `a`, `b` and `c` like `zip3`, with
+    /// mutable references to the components of `c`.
+    fn zip3_mut;
+    a A DA, b B DB; mut c C DC
+}